@@ -1,5 +1,5 @@
 use anyhow::{bail, Result};
-use std::fmt::{Debug, Formatter};
+use core::fmt::{Debug, Formatter};
 
 #[derive(Clone, PartialEq)]
 pub struct ClassInfoAccess {
@@ -43,10 +43,24 @@ impl ClassInfoAccess {
 			Ok(ClassInfoAccess { is_public, is_final, is_super, is_interface, is_abstract, is_synthetic, is_annotation, is_enum })
 		}
 	}
+
+	/// Returns the `access_flags` item these flags were parsed from, without the reserved bits.
+	pub fn to_flags(&self) -> u16 {
+		let mut access_flags = 0;
+		if self.is_public     { access_flags |= 0x0001; }
+		if self.is_final      { access_flags |= 0x0010; }
+		if self.is_super      { access_flags |= 0x0020; }
+		if self.is_interface  { access_flags |= 0x0200; }
+		if self.is_abstract   { access_flags |= 0x0400; }
+		if self.is_synthetic  { access_flags |= 0x1000; }
+		if self.is_annotation { access_flags |= 0x2000; }
+		if self.is_enum       { access_flags |= 0x4000; }
+		access_flags
+	}
 }
 
 impl Debug for ClassInfoAccess {
-	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
 		f.write_str("ClassInfoAccess { ")?;
 		if self.is_public     { f.write_str("public ")?; }
 		if self.is_final      { f.write_str("final ")?; }
@@ -94,18 +108,29 @@ impl FieldInfoAccess {
 			bail!("at most one of ACC_FINAL and ACC_VOLATILE may be set")
 		}
 
-		let is_interface_field = false;
-		if is_interface_field {
-			// must have: is_public, is_static, is_final
-			// must not have: is_private, is_protected, is_volatile, is_transient, is_synthetic, is_enum
-		}
+		// the rules for fields of interfaces depend on the class access flags, see crate::format::check_class
 
 		Ok(FieldInfoAccess { is_public, is_private, is_protected, is_static, is_final, is_volatile, is_transient, is_synthetic, is_enum })
 	}
+
+	/// Returns the `access_flags` item these flags were parsed from, without the reserved bits.
+	pub fn to_flags(&self) -> u16 {
+		let mut access_flags = 0;
+		if self.is_public    { access_flags |= 0x0001; }
+		if self.is_private   { access_flags |= 0x0002; }
+		if self.is_protected { access_flags |= 0x0004; }
+		if self.is_static    { access_flags |= 0x0008; }
+		if self.is_final     { access_flags |= 0x0010; }
+		if self.is_volatile  { access_flags |= 0x0040; }
+		if self.is_transient { access_flags |= 0x0080; }
+		if self.is_synthetic { access_flags |= 0x1000; }
+		if self.is_enum      { access_flags |= 0x4000; }
+		access_flags
+	}
 }
 
 impl Debug for FieldInfoAccess {
-	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
 		f.write_str("FieldInfoAccess { ")?;
 		if self.is_public    { f.write_str("public ")?; }
 		if self.is_private   { f.write_str("private ")?; }
@@ -158,19 +183,7 @@ impl MethodInfoAccess {
 			bail!("at most one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED may be set")
 		}
 
-		/// Methods of interfaces may have any of the flags in Table 4.6-A set except ACC_PROTECTED, ACC_FINAL, ACC_SYNCHRONIZED,
-		/// and ACC_NATIVE (JLS §9.4).
-
-		/// < 52.0   its ACC_PUBLIC and ACC_ABSTRACT flags set;
-		/// >= 52.0  exactly one of its ACC_PUBLIC and ACC_PRIVATE flags set.
-		let is_interface_method = false; // "methods of interfaces"
-		if is_interface_method {
-			if is_protected || is_final || is_synchronised || is_native {
-				bail!("methods of interfaces may not have ACC_PROTECTED, ACC_FINAL, ACC_SYNCHRONIZED and ACC_NATIVE")
-			}
-			// exactly one of is_public, is_private
-			// TODO: impl
-		}
+		// the rules for methods of interfaces depend on the class access flags, see crate::format::check_class
 
 		if is_abstract {
 			if is_private || is_static || is_final || is_synchronised || is_native || is_strict {
@@ -179,17 +192,7 @@ impl MethodInfoAccess {
 			}
 		}
 
-		/// Each instance initialization method (§2.9) may have at most one of its ACC_PUBLIC, ACC_PRIVATE, and ACC_PROTECTED flags set,
-		/// and may also have its ACC_VARARGS, ACC_STRICT, and ACC_SYNTHETIC flags set, but must not have any of the other flags in Table 4.6-A set.
-		let is_specific_instance_initialisation_method = false;
-		if is_specific_instance_initialisation_method {
-			// may have: is_varargs, is_strict, is_synthetic
-			// -> must not have: is_static, is_final, is_synchronised, is_native, is_bridge, is_abstract
-			if is_static || is_final || is_synchronised || is_native || is_bridge || is_abstract {
-				bail!("instance initialization methods may have at most one of ACC_PUBLIC, ACC_PRIVATE and ACC_PROTECTED, and may also have\
-				ACC_VARARGS, ACC_STRICT, ACC_SYNTHETIC, mut must not have any of the other flags set")
-			}
-		}
+		// the rules for instance initialization methods depend on the method name, see crate::format::check_class
 
 		/// Class and interface initialization methods are called implicitly by the Java Virtual Machine. The value of their access_flags
 		/// item is ignored except for the setting of the ACC_STRICT flag.
@@ -199,10 +202,28 @@ impl MethodInfoAccess {
 			is_public, is_private, is_protected, is_static, is_final, is_synchronised, is_bridge, is_varargs, is_native, is_abstract, is_strict, is_synthetic
 		})
 	}
+
+	/// Returns the `access_flags` item these flags were parsed from, without the reserved bits.
+	pub fn to_flags(&self) -> u16 {
+		let mut access_flags = 0;
+		if self.is_public       { access_flags |= 0x0001; }
+		if self.is_private      { access_flags |= 0x0002; }
+		if self.is_protected    { access_flags |= 0x0004; }
+		if self.is_static       { access_flags |= 0x0008; }
+		if self.is_final        { access_flags |= 0x0010; }
+		if self.is_synchronised { access_flags |= 0x0020; }
+		if self.is_bridge       { access_flags |= 0x0040; }
+		if self.is_varargs      { access_flags |= 0x0080; }
+		if self.is_native       { access_flags |= 0x0100; }
+		if self.is_abstract     { access_flags |= 0x0400; }
+		if self.is_strict       { access_flags |= 0x0800; }
+		if self.is_synthetic    { access_flags |= 0x1000; }
+		access_flags
+	}
 }
 
 impl Debug for MethodInfoAccess {
-	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
 		f.write_str("MethodInfoAccess { ")?;
 		if self.is_public       { f.write_str("public ")?; }
 		if self.is_private      { f.write_str("private ")?; }
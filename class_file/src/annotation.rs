@@ -0,0 +1,204 @@
+//! Querying the annotations of classes, fields and methods.
+//!
+//! Annotations are split into the `RuntimeVisible*` and `RuntimeInvisible*` attributes by their retention, the methods here look at both and report
+//! which one an annotation came from.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::{ClassFile, FieldInfo, MethodInfo};
+use crate::cp::attribute::{Annotation, AnnotationElementValue, AttributeInfo, ParameterAnnotationPair};
+
+/// Where an annotation is stored, see `java.lang.annotation.RetentionPolicy`. Annotations with `SOURCE` retention don't make it into the class file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetentionPolicy {
+	/// From a `RuntimeInvisible*Annotations` attribute.
+	Class,
+	/// From a `RuntimeVisible*Annotations` attribute.
+	Runtime,
+}
+
+/// Returns the annotations from all annotation attributes, the visible ones first.
+fn annotations(attributes: &[AttributeInfo]) -> impl Iterator<Item=(RetentionPolicy, &Annotation)> {
+	let visible = attributes.iter()
+		.filter_map(|attribute| match attribute {
+			AttributeInfo::RuntimeVisibleAnnotations(attribute) => Some(&attribute.annotations),
+			_ => None,
+		})
+		.flatten()
+		.map(|annotation| (RetentionPolicy::Runtime, annotation));
+	let invisible = attributes.iter()
+		.filter_map(|attribute| match attribute {
+			AttributeInfo::RuntimeInvisibleAnnotations(attribute) => Some(&attribute.annotations),
+			_ => None,
+		})
+		.flatten()
+		.map(|annotation| (RetentionPolicy::Class, annotation));
+	visible.chain(invisible)
+}
+
+fn annotation<'a>(attributes: &'a [AttributeInfo], descriptor: &str) -> Option<&'a Annotation> {
+	annotations(attributes)
+		.map(|(_, annotation)| annotation)
+		.find(|annotation| annotation.is(descriptor))
+}
+
+impl ClassFile {
+	/// Returns the annotations of the class, the ones visible at runtime first.
+	pub fn annotations(&self) -> impl Iterator<Item=(RetentionPolicy, &Annotation)> {
+		annotations(&self.attributes)
+	}
+
+	/// Returns the annotation of the class with the type given as field descriptor, like `Ljava/lang/FunctionalInterface;`.
+	pub fn annotation(&self, descriptor: &str) -> Option<&Annotation> {
+		annotation(&self.attributes, descriptor)
+	}
+}
+
+impl FieldInfo {
+	/// Returns the annotations of the field, the ones visible at runtime first.
+	pub fn annotations(&self) -> impl Iterator<Item=(RetentionPolicy, &Annotation)> {
+		annotations(&self.attributes)
+	}
+
+	/// Returns the annotation of the field with the type given as field descriptor.
+	pub fn annotation(&self, descriptor: &str) -> Option<&Annotation> {
+		annotation(&self.attributes, descriptor)
+	}
+}
+
+impl MethodInfo {
+	/// Returns the annotations of the method, the ones visible at runtime first.
+	pub fn annotations(&self) -> impl Iterator<Item=(RetentionPolicy, &Annotation)> {
+		annotations(&self.attributes)
+	}
+
+	/// Returns the annotation of the method with the type given as field descriptor.
+	pub fn annotation(&self, descriptor: &str) -> Option<&Annotation> {
+		annotation(&self.attributes, descriptor)
+	}
+
+	/// Returns the annotations of each parameter of the method descriptor.
+	///
+	/// The parameter annotation attributes may cover fewer parameters than the descriptor has: javac leaves out the parameters it adds itself, like the
+	/// outer instance of inner class constructors or the name and ordinal of enum constructors. As these come first, the annotations are aligned with the
+	/// last parameters then.
+	pub fn parameter_annotations(&self) -> Vec<Vec<(RetentionPolicy, &Annotation)>> {
+		let count = self.descriptor.parameters.len();
+		let mut parameters = vec![Vec::new(); count];
+		for attribute in &self.attributes {
+			let (retention, pairs): (_, &[ParameterAnnotationPair]) = match attribute {
+				AttributeInfo::RuntimeVisibleParameterAnnotations(attribute) => (RetentionPolicy::Runtime, &attribute.parameter_annotations),
+				AttributeInfo::RuntimeInvisibleParameterAnnotations(attribute) => (RetentionPolicy::Class, &attribute.parameter_annotations),
+				_ => continue,
+			};
+			// an attribute with too many parameters is invalid, its extra entries are dropped
+			let skipped = count.saturating_sub(pairs.len());
+			for (parameter, pair) in parameters[skipped..].iter_mut().zip(pairs) {
+				parameter.extend(pair.annotations.iter().map(|annotation| (retention, annotation)));
+			}
+		}
+		for parameter in &mut parameters {
+			parameter.sort_by_key(|(retention, _)| *retention != RetentionPolicy::Runtime);
+		}
+		parameters
+	}
+
+	/// Returns the default value of an element of an annotation interface, if this method is one with a default.
+	pub fn annotation_default(&self) -> Option<&AnnotationElementValue> {
+		self.attributes.iter().find_map(|attribute| match attribute {
+			AttributeInfo::AnnotationDefault(attribute) => Some(&attribute.default_value),
+			_ => None,
+		})
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::MethodInfo;
+	use crate::access::MethodInfoAccess;
+	use crate::annotation::RetentionPolicy;
+	use crate::cp::attribute::{AnnotationElementValue, AttributeInfo};
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+
+	fn attribute(pool: &mut PoolBuilder, name: &str, body: &[u8]) -> Vec<u8> {
+		let mut bytes = (pool.utf8(name.as_bytes()).unwrap() as u16).to_be_bytes().to_vec();
+		bytes.extend_from_slice(&(body.len() as u32).to_be_bytes());
+		bytes.extend_from_slice(body);
+		bytes
+	}
+
+	#[test]
+	fn method_annotations() {
+		let mut pool = PoolBuilder::new();
+		let index = |index: usize| (index as u16).to_be_bytes();
+		let mapping = index(pool.utf8(b"Lcom/example/Mapping;").unwrap());
+		let nullable = index(pool.utf8(b"Lcom/example/Nullable;").unwrap());
+
+		// @Mapping(value = {"/a", "/b"}, timeout = 30L, method = Method.GET, body = String.class, retry = @Retry(3))
+		let mut body = vec![0, 1];
+		body.extend_from_slice(&mapping);
+		body.extend_from_slice(&[0, 5]);
+		body.extend_from_slice(&index(pool.utf8(b"value").unwrap()));
+		body.extend_from_slice(&[b'[', 0, 2, b's']);
+		body.extend_from_slice(&index(pool.utf8(b"/a").unwrap()));
+		body.push(b's');
+		body.extend_from_slice(&index(pool.utf8(b"/b").unwrap()));
+		body.extend_from_slice(&index(pool.utf8(b"timeout").unwrap()));
+		body.push(b'J');
+		body.extend_from_slice(&index(pool.long(30).unwrap()));
+		body.extend_from_slice(&index(pool.utf8(b"method").unwrap()));
+		body.push(b'e');
+		body.extend_from_slice(&index(pool.utf8(b"Lcom/example/Method;").unwrap()));
+		body.extend_from_slice(&index(pool.utf8(b"GET").unwrap()));
+		body.extend_from_slice(&index(pool.utf8(b"body").unwrap()));
+		body.push(b'c');
+		body.extend_from_slice(&index(pool.utf8(b"Ljava/lang/String;").unwrap()));
+		body.extend_from_slice(&index(pool.utf8(b"retry").unwrap()));
+		body.push(b'@');
+		body.extend_from_slice(&index(pool.utf8(b"Lcom/example/Retry;").unwrap()));
+		body.extend_from_slice(&[0, 1]);
+		body.extend_from_slice(&index(pool.utf8(b"value").unwrap()));
+		body.push(b'I');
+		body.extend_from_slice(&index(pool.integer(3).unwrap()));
+		let visible = attribute(&mut pool, "RuntimeVisibleAnnotations", &body);
+
+		// @Nullable on the last parameter only
+		let mut body = vec![1, 0, 1];
+		body.extend_from_slice(&nullable);
+		body.extend_from_slice(&[0, 0]);
+		let parameters = attribute(&mut pool, "RuntimeInvisibleParameterAnnotations", &body);
+
+		let pool = pool.build();
+		let attributes = vec![
+			AttributeInfo::parse(&mut &visible[..], &pool).unwrap(),
+			AttributeInfo::parse(&mut &parameters[..], &pool).unwrap(),
+		];
+		let int = FieldDescriptor { array_dimension: 0, base_type: BaseOrObjectType::I };
+		let method = MethodInfo {
+			access_flags: MethodInfoAccess::parse(0x0401).unwrap(),
+			name: (&b"handle"[..]).into(),
+			descriptor: MethodDescriptor { parameters: vec![int, int], return_type: None },
+			attributes,
+			code: None,
+		};
+
+		let mapping = method.annotation("Lcom/example/Mapping;").unwrap();
+		assert_eq!(method.annotations().next().unwrap().0, RetentionPolicy::Runtime);
+		let paths: Vec<_> = mapping.get("value").unwrap().as_array().unwrap().iter().map(|path| path.as_string().unwrap()).collect();
+		assert_eq!(paths, vec![&b"/a"[..], &b"/b"[..]]);
+		assert_eq!(mapping.get("timeout").and_then(AnnotationElementValue::as_long), Some(30));
+		assert_eq!(mapping.get("method").and_then(AnnotationElementValue::as_enum), Some((&b"Lcom/example/Method;"[..], &b"GET"[..])));
+		assert_eq!(mapping.get("body").and_then(AnnotationElementValue::as_class), Some(&b"Ljava/lang/String;"[..]));
+		let retry = mapping.get("retry").and_then(AnnotationElementValue::as_annotation).unwrap();
+		assert_eq!(retry.get("value").and_then(AnnotationElementValue::as_int), Some(3));
+		assert!(mapping.get("missing").is_none());
+
+		let parameters = method.parameter_annotations();
+		assert_eq!(parameters.len(), 2);
+		assert!(parameters[0].is_empty());
+		assert_eq!(parameters[1].len(), 1);
+		assert_eq!(parameters[1][0].0, RetentionPolicy::Class);
+		assert!(parameters[1][0].1.is("Lcom/example/Nullable;"));
+	}
+}
@@ -0,0 +1,583 @@
+//! Call graphs of the methods reachable from some entry points, built from the invoke instructions of their code with Class Hierarchy Analysis or
+//! Rapid Type Analysis.
+//!
+//! Both analyses take `invokestatic`, `invokespecial` and the implementations of lambdas and method references as they are resolved. They differ in
+//! the targets of `invokevirtual` and `invokeinterface`: CHA takes the method [selected](ClassHierarchy::select_method) for every class in the
+//! [ClassHierarchy] that could be the receiver, RTA only for the classes created with `new` in the methods reached so far. Methods without code in the
+//! classes given, like the ones of the JDK, are in the graph as reachable, but without calls of their own.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter, Write};
+use crate::{json, ClassFile, MethodInfo};
+use crate::call_site::CallSiteKind;
+use crate::cp::MethodHandleInfo;
+use crate::descriptor::MethodDescriptor;
+use crate::hierarchy::{ClassHierarchy, MethodEntry};
+use crate::instruction::opcode::Opcode;
+use crate::name::{ClassName, MethodName};
+
+const CLINIT: &[u8] = b"<clinit>";
+
+/// A method, given by the class declaring it, its name and its descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MethodId {
+	pub class: ClassName,
+	pub name: MethodName,
+	pub descriptor: MethodDescriptor,
+}
+
+impl MethodId {
+	pub fn new(class: ClassName, name: MethodName, descriptor: MethodDescriptor) -> MethodId {
+		MethodId { class, name, descriptor }
+	}
+
+	fn of(class: ClassName, method: &MethodEntry) -> MethodId {
+		MethodId::new(class, method.name, method.descriptor.clone())
+	}
+}
+
+/// Formats the method like `java/lang/Object.equals(Ljava/lang/Object;)Z`.
+impl Display for MethodId {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}.{}{}",
+			String::from_utf8_lossy(self.class.as_bytes()),
+			String::from_utf8_lossy(self.name.as_bytes()),
+			String::from_utf8_lossy(&self.descriptor.to_bytes()))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Analysis {
+	/// Class Hierarchy Analysis: a virtual call may go to the method selected for any class extending or implementing the class it is made on.
+	ClassHierarchy,
+	/// Rapid Type Analysis: like CHA, but only for the classes instantiated in the reachable methods, which leaves out calls on classes that are never
+	/// created. Classes whose code isn't given to the builder, like the ones of the JDK, count as instantiated, as their own code may create them.
+	RapidType,
+}
+
+impl Analysis {
+	pub fn name(self) -> &'static str {
+		match self {
+			Analysis::ClassHierarchy => "cha",
+			Analysis::RapidType => "rta",
+		}
+	}
+}
+
+/// How a method is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CallKind {
+	Static,
+	Special,
+	Virtual,
+	Interface,
+	/// The implementation of a lambda or method reference, or the bootstrap method of any other `invokedynamic`.
+	Dynamic,
+	/// A static initializer, run by the JVM when the instruction first creates an instance of its class or accesses its static members.
+	Initialization,
+}
+
+impl CallKind {
+	pub fn name(self) -> &'static str {
+		match self {
+			CallKind::Static => "static",
+			CallKind::Special => "special",
+			CallKind::Virtual => "virtual",
+			CallKind::Interface => "interface",
+			CallKind::Dynamic => "dynamic",
+			CallKind::Initialization => "initialization",
+		}
+	}
+}
+
+/// A call of `callee` by the instruction at `offset` in the code of `caller`. A virtual call has an edge for each method it may go to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CallEdge {
+	pub caller: MethodId,
+	pub offset: usize,
+	pub kind: CallKind,
+	pub callee: MethodId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraph {
+	pub analysis: Analysis,
+	pub entry_points: BTreeSet<MethodId>,
+	/// The methods reachable from the entry points, including them.
+	pub methods: BTreeSet<MethodId>,
+	pub edges: BTreeSet<CallEdge>,
+	/// The classes created by `new` or a constructor reference in the reachable methods, and the ones given to
+	/// [CallGraphBuilder::instantiated].
+	pub instantiated: BTreeSet<ClassName>,
+}
+
+impl CallGraph {
+	pub fn is_reachable(&self, method: &MethodId) -> bool {
+		self.methods.contains(method)
+	}
+
+	pub fn callees<'a>(&'a self, caller: &'a MethodId) -> impl Iterator<Item=&'a CallEdge> {
+		self.edges.iter().filter(move |edge| edge.caller == *caller)
+	}
+
+	pub fn callers<'a>(&'a self, callee: &'a MethodId) -> impl Iterator<Item=&'a CallEdge> {
+		self.edges.iter().filter(move |edge| edge.callee == *callee)
+	}
+
+	/// Writes the graph in the DOT language of Graphviz. Entry points are drawn as boxes, and the calls between two methods are merged into one edge
+	/// per kind.
+	pub fn to_dot(&self) -> String {
+		fn quoted(out: &mut String, method: &MethodId) {
+			out.push('"');
+			for char in method.to_string().chars() {
+				if char == '"' || char == '\\' {
+					out.push('\\');
+				}
+				out.push(char);
+			}
+			out.push('"');
+		}
+
+		let mut out = String::from("digraph calls {\n");
+		for method in &self.methods {
+			out.push('\t');
+			quoted(&mut out, method);
+			if self.entry_points.contains(method) {
+				out.push_str(" [shape=box]");
+			}
+			out.push_str(";\n");
+		}
+		let edges: BTreeSet<_> = self.edges.iter().map(|edge| (&edge.caller, &edge.callee, edge.kind)).collect();
+		for (caller, callee, kind) in edges {
+			out.push('\t');
+			quoted(&mut out, caller);
+			out.push_str(" -> ");
+			quoted(&mut out, callee);
+			let _ = writeln!(out, " [label=\"{}\"];", kind.name());
+		}
+		out.push_str("}\n");
+		out
+	}
+
+	/// Writes the graph as JSON object, with methods written like `java/lang/Object.equals(Ljava/lang/Object;)Z`:
+	///
+	/// ```json
+	/// {"analysis": "rta", "entry_points": [...], "methods": [...], "instantiated": [...],
+	///  "edges": [{"caller": "...", "offset": 4, "kind": "virtual", "callee": "..."}, ...]}
+	/// ```
+	pub fn to_json(&self) -> String {
+		let method = |out: &mut String, method: &MethodId| json::string(out, method.to_string().as_bytes());
+		let mut out = String::new();
+		let _ = write!(out, "{{\"analysis\":\"{}\",\"entry_points\":", self.analysis.name());
+		json::array(&mut out, &self.entry_points, method);
+		out.push_str(",\"methods\":");
+		json::array(&mut out, &self.methods, method);
+		out.push_str(",\"instantiated\":");
+		json::array(&mut out, &self.instantiated, |out, class| json::string(out, class.as_bytes()));
+		out.push_str(",\"edges\":");
+		json::array(&mut out, &self.edges, |out, edge| {
+			out.push_str("{\"caller\":");
+			method(out, &edge.caller);
+			let _ = write!(out, ",\"offset\":{},\"kind\":\"{}\",\"callee\":", edge.offset, edge.kind.name());
+			method(out, &edge.callee);
+			out.push('}');
+		});
+		out.push('}');
+		out
+	}
+}
+
+/// Collects the classes whose code is analyzed and the entry points to start from, see [CallGraphBuilder::build].
+#[derive(Debug, Clone)]
+pub struct CallGraphBuilder<'a> {
+	hierarchy: &'a ClassHierarchy,
+	analysis: Analysis,
+	classes: BTreeMap<ClassName, &'a ClassFile>,
+	entry_points: BTreeSet<MethodId>,
+	instantiated: BTreeSet<ClassName>,
+}
+
+impl<'a> CallGraphBuilder<'a> {
+	/// Creates a builder resolving calls with `hierarchy`, which should contain at least the classes added with [CallGraphBuilder::add_class].
+	pub fn new(hierarchy: &'a ClassHierarchy, analysis: Analysis) -> CallGraphBuilder<'a> {
+		CallGraphBuilder {
+			hierarchy,
+			analysis,
+			classes: BTreeMap::new(),
+			entry_points: BTreeSet::new(),
+			instantiated: BTreeSet::new(),
+		}
+	}
+
+	/// Adds a class whose methods are followed into when reached. Like on a class path, the first class added with a name wins.
+	pub fn add_class(&mut self, class: &'a ClassFile) -> &mut Self {
+		self.classes.entry(class.this_class).or_insert(class);
+		self
+	}
+
+	pub fn entry_point(&mut self, method: MethodId) -> &mut Self {
+		self.entry_points.insert(method);
+		self
+	}
+
+	/// Adds the `public static void main(String[])` methods of the classes added so far as entry points.
+	pub fn main_methods(&mut self) -> &mut Self {
+		let main = MethodName::from(b"main");
+		for class in self.classes.values() {
+			for method in &class.methods {
+				if method.name == main && method.descriptor.to_bytes() == b"([Ljava/lang/String;)V" && method.access_flags.is_public && method.access_flags.is_static {
+					self.entry_points.insert(MethodId::new(class.this_class, method.name, method.descriptor.clone()));
+				}
+			}
+		}
+		self
+	}
+
+	/// Adds the public and protected methods of the public classes added so far as entry points, for a library that can be called from anywhere.
+	/// For RTA, the classes its callers create need to be given with [CallGraphBuilder::instantiated] too.
+	pub fn accessible_methods(&mut self) -> &mut Self {
+		for class in self.classes.values().filter(|class| class.access_flags.is_public) {
+			for method in &class.methods {
+				if (method.access_flags.is_public || method.access_flags.is_protected) && !method.access_flags.is_abstract {
+					self.entry_points.insert(MethodId::new(class.this_class, method.name, method.descriptor.clone()));
+				}
+			}
+		}
+		self
+	}
+
+	/// Marks a class as instantiated even if no reachable method creates it, like the classes created through reflection.
+	pub fn instantiated(&mut self, class: ClassName) -> &mut Self {
+		self.instantiated.insert(class);
+		self
+	}
+
+	fn method(&self, method: &MethodId) -> Option<(&'a ClassFile, &'a MethodInfo)> {
+		let class = *self.classes.get(&method.class)?;
+		let info = class.methods.iter().find(|info| info.name == method.name && info.descriptor == method.descriptor)?;
+		Some((class, info))
+	}
+
+	/// Builds the call graph from the entry points, running the static initializers of their classes first.
+	pub fn build(&self) -> CallGraph {
+		let mut state = State {
+			builder: self,
+			graph: CallGraph {
+				analysis: self.analysis,
+				entry_points: self.entry_points.clone(),
+				methods: BTreeSet::new(),
+				edges: BTreeSet::new(),
+				instantiated: BTreeSet::new(),
+			},
+			worklist: Vec::new(),
+			initialized: BTreeSet::new(),
+			sites: Vec::new(),
+			cones: BTreeMap::new(),
+		};
+		for &class in &self.instantiated {
+			state.instantiate(class);
+		}
+		for entry_point in &self.entry_points {
+			state.initialize(None, entry_point.class);
+			state.reach(entry_point.clone());
+		}
+		while let Some(method) = state.worklist.pop() {
+			state.scan(&method);
+		}
+		state.graph
+	}
+}
+
+/// A virtual call, kept by RTA to find more targets when more classes are instantiated.
+#[derive(Debug, Clone)]
+struct VirtualSite<'a> {
+	caller: MethodId,
+	offset: usize,
+	kind: CallKind,
+	/// The class in the method reference, the receiver is an instance of it or of one of its subtypes.
+	class: ClassName,
+	name: MethodName,
+	descriptor: MethodDescriptor,
+	/// The method the reference resolves to, with its class. `None` if the class isn't in the hierarchy.
+	resolved: Option<(ClassName, &'a MethodEntry)>,
+}
+
+struct State<'b, 'a> {
+	builder: &'b CallGraphBuilder<'a>,
+	graph: CallGraph,
+	/// The reached methods not scanned yet.
+	worklist: Vec<MethodId>,
+	initialized: BTreeSet<ClassName>,
+	sites: Vec<VirtualSite<'a>>,
+	/// The classes extending or implementing a class, and the class itself.
+	cones: BTreeMap<ClassName, BTreeSet<ClassName>>,
+}
+
+impl<'a> State<'_, 'a> {
+	fn reach(&mut self, method: MethodId) {
+		if self.graph.methods.insert(method.clone()) {
+			self.worklist.push(method);
+		}
+	}
+
+	fn call(&mut self, caller: &MethodId, offset: usize, kind: CallKind, callee: MethodId) {
+		self.graph.edges.insert(CallEdge { caller: caller.clone(), offset, kind, callee: callee.clone() });
+		self.reach(callee);
+	}
+
+	/// Reaches the static initializers of `class` and its superclasses. Each instruction that may run them gets an edge, but they are only scanned once.
+	fn initialize(&mut self, site: Option<(&MethodId, usize)>, class: ClassName) {
+		let hierarchy = self.builder.hierarchy;
+		if !self.initialized.insert(class) && site.is_none() {
+			return;
+		}
+		for class in core::iter::once(class).chain(hierarchy.superclasses(&class)) {
+			let Some(clinit) = hierarchy.get(&class).and_then(|entry| entry.methods.iter().find(|method| method.name == CLINIT)) else {
+				continue;
+			};
+			match site {
+				Some((caller, offset)) => self.call(caller, offset, CallKind::Initialization, MethodId::of(class, clinit)),
+				None => self.reach(MethodId::of(class, clinit)),
+			}
+		}
+	}
+
+	fn resolve(&self, class: ClassName, name: MethodName, descriptor: &MethodDescriptor) -> MethodId {
+		match self.builder.hierarchy.resolve_method(&class, name, descriptor) {
+			Some((class, method)) => MethodId::of(class, method),
+			None => MethodId::new(class, name, descriptor.clone()),
+		}
+	}
+
+	fn scan(&mut self, method: &MethodId) {
+		let Some((class, info)) = self.builder.method(method) else {
+			return;
+		};
+		let Some(code) = &info.code else {
+			return;
+		};
+		for instruction in &code.code {
+			let offset = instruction.offset();
+			match instruction.opcode() {
+				Opcode::InvokeStatic(reference) => {
+					let callee = self.resolve(reference.class, reference.name, &reference.descriptor);
+					self.initialize(Some((method, offset)), callee.class);
+					self.call(method, offset, CallKind::Static, callee);
+				},
+				Opcode::InvokeSpecial(reference) => {
+					let callee = self.resolve(reference.class, reference.name, &reference.descriptor);
+					self.call(method, offset, CallKind::Special, callee);
+				},
+				Opcode::InvokeVirtual(reference) => {
+					self.virtual_call(method, offset, CallKind::Virtual, reference.class, reference.name, &reference.descriptor);
+				},
+				Opcode::InvokeInterface { method_ref: reference, .. } => {
+					self.virtual_call(method, offset, CallKind::Interface, reference.class, reference.name, &reference.descriptor);
+				},
+				Opcode::InvokeDynamic { call_site, .. } => {
+					// a malformed call site fails when linked, and calls nothing
+					if let Ok(call_site) = class.call_site(call_site) {
+						let handle = match call_site.kind {
+							CallSiteKind::Lambda { implementation, .. } => implementation,
+							_ => &call_site.bootstrap.bootstrap_method,
+						};
+						self.method_handle(method, offset, handle);
+					}
+				},
+				Opcode::New(class) => {
+					self.initialize(Some((method, offset)), *class);
+					self.instantiate(*class);
+				},
+				Opcode::GetStatic(field) | Opcode::PutStatic(field) => {
+					let class = self.builder.hierarchy.resolve_field(&field.class, field.name, &field.descriptor).map_or(field.class, |(class, _)| class);
+					self.initialize(Some((method, offset)), class);
+				},
+				_ => {},
+			}
+		}
+	}
+
+	fn method_handle(&mut self, caller: &MethodId, offset: usize, handle: &MethodHandleInfo) {
+		match handle {
+			MethodHandleInfo::InvokeStatic(reference) | MethodHandleInfo::InvokeSpecial(reference) => {
+				let callee = self.resolve(reference.class, reference.name, &reference.descriptor);
+				self.call(caller, offset, CallKind::Dynamic, callee);
+			},
+			MethodHandleInfo::InvokeStaticInterface(reference) | MethodHandleInfo::InvokeSpecialInterface(reference) => {
+				let callee = self.resolve(reference.class, reference.name, &reference.descriptor);
+				self.call(caller, offset, CallKind::Dynamic, callee);
+			},
+			MethodHandleInfo::NewInvokeSpecial(reference) => {
+				self.instantiate(reference.class);
+				let callee = self.resolve(reference.class, reference.name, &reference.descriptor);
+				self.call(caller, offset, CallKind::Dynamic, callee);
+			},
+			MethodHandleInfo::InvokeVirtual(reference) => {
+				self.virtual_call(caller, offset, CallKind::Dynamic, reference.class, reference.name, &reference.descriptor);
+			},
+			MethodHandleInfo::InvokeInterface(reference) => {
+				self.virtual_call(caller, offset, CallKind::Dynamic, reference.class, reference.name, &reference.descriptor);
+			},
+			MethodHandleInfo::GetField(_) | MethodHandleInfo::GetStatic(_) | MethodHandleInfo::PutField(_) | MethodHandleInfo::PutStatic(_) => {},
+		}
+	}
+
+	fn virtual_call(&mut self, caller: &MethodId, offset: usize, kind: CallKind, class: ClassName, name: MethodName, descriptor: &MethodDescriptor) {
+		let resolved = self.builder.hierarchy.resolve_method(&class, name, descriptor);
+		let site = VirtualSite { caller: caller.clone(), offset, kind, class, name, descriptor: descriptor.clone(), resolved };
+		if resolved.is_none() {
+			self.call(caller, offset, kind, MethodId::new(class, name, descriptor.clone()));
+		}
+		self.compute_cone(class);
+		let receivers: Vec<_> = self.cones[&class].iter().copied().filter(|receiver| self.is_receiver(receiver)).collect();
+		for receiver in receivers {
+			self.dispatch(&site, receiver);
+		}
+		if self.builder.analysis == Analysis::RapidType {
+			self.sites.push(site);
+		}
+	}
+
+	fn compute_cone(&mut self, class: ClassName) {
+		let hierarchy = self.builder.hierarchy;
+		self.cones.entry(class).or_insert_with(|| {
+			let mut cone = hierarchy.subtypes(&class);
+			cone.insert(class);
+			cone
+		});
+	}
+
+	/// Whether an instance of `class` may be the receiver of a virtual call.
+	fn is_receiver(&self, class: &ClassName) -> bool {
+		let Some(entry) = self.builder.hierarchy.get(class) else {
+			return false;
+		};
+		if entry.is_interface() || entry.access_flags.is_abstract {
+			return false;
+		}
+		match self.builder.analysis {
+			Analysis::ClassHierarchy => true,
+			Analysis::RapidType => self.graph.instantiated.contains(class) || !self.builder.classes.contains_key(class),
+		}
+	}
+
+	fn dispatch(&mut self, site: &VirtualSite<'a>, receiver: ClassName) {
+		let hierarchy = self.builder.hierarchy;
+		let callee = match site.resolved {
+			Some((declaring, method)) => hierarchy.select_method(&receiver, &declaring, method).map(|(class, method)| MethodId::of(class, method)),
+			// without the resolved method, only the overriding methods of the classes in the hierarchy can be found
+			None => core::iter::once(receiver).chain(hierarchy.superclasses(&receiver))
+				.take_while(|class| *class != site.class)
+				.find_map(|class| {
+					let method = hierarchy.get(&class)?.method(site.name, &site.descriptor)?;
+					let flags = &method.access_flags;
+					(!flags.is_private && !flags.is_static && !flags.is_abstract).then(|| MethodId::of(class, method))
+				}),
+		};
+		if let Some(callee) = callee {
+			self.call(&site.caller, site.offset, site.kind, callee);
+		}
+	}
+
+	fn instantiate(&mut self, class: ClassName) {
+		if !self.graph.instantiated.insert(class) || self.builder.analysis != Analysis::RapidType || !self.is_receiver(&class) {
+			return;
+		}
+		for index in 0..self.sites.len() {
+			let site_class = self.sites[index].class;
+			self.compute_cone(site_class);
+			if self.cones[&site_class].contains(&class) {
+				let site = self.sites[index].clone();
+				self.dispatch(&site, class);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::ClassFile;
+	use crate::call_graph::{Analysis, CallGraphBuilder, CallKind, MethodId};
+	use crate::cp::{InterfaceMethodRefInfo, MethodRefInfo};
+	use crate::descriptor::MethodDescriptor;
+	use crate::fixture::{class, method, method_with_code};
+	use crate::hierarchy::ClassHierarchy;
+	use crate::instruction::opcode::Opcode;
+	use crate::name::{ClassName, MethodName};
+
+	fn descriptor(descriptor: &[u8]) -> MethodDescriptor {
+		MethodDescriptor::try_from(descriptor).unwrap()
+	}
+
+	fn shape(name: &[u8]) -> ClassFile {
+		class(0x0021, name, b"java/lang/Object", &[b"Shape"], Vec::new(), vec![
+			method_with_code(0x0001, b"<init>", b"()V", |code| {
+				code.aload(0)
+					.invokespecial(MethodRefInfo { class: ClassName::from(b"java/lang/Object"), name: MethodName::from(b"<init>"), descriptor: descriptor(b"()V") })
+					.emit(Opcode::Return);
+			}),
+			method_with_code(0x0001, b"area", b"()D", |code| { code.dconst(0.0).emit(Opcode::DReturn); }),
+		])
+	}
+
+	#[test]
+	fn cha_and_rta() {
+		// static void main(String[] args) { Shape shape = new Circle(); shape.area(); }
+		let main = method_with_code(0x0009, b"main", b"([Ljava/lang/String;)V", |code| {
+			code.new_(ClassName::from(b"Circle")).emit(Opcode::Dup)
+				.invokespecial(MethodRefInfo { class: ClassName::from(b"Circle"), name: MethodName::from(b"<init>"), descriptor: descriptor(b"()V") })
+				.invokeinterface(InterfaceMethodRefInfo { class: ClassName::from(b"Shape"), name: MethodName::from(b"area"), descriptor: descriptor(b"()D") })
+				.emit(Opcode::Pop2).emit(Opcode::Return);
+		});
+		let clinit = method_with_code(0x0008, b"<clinit>", b"()V", |code| { code.emit(Opcode::Return); });
+		let classes = [
+			class(0x0601, b"Shape", b"java/lang/Object", &[], Vec::new(), vec![method(0x0401, b"area", b"()D")]),
+			shape(b"Circle"),
+			shape(b"Square"),
+			class(0x0021, b"Main", b"java/lang/Object", &[], Vec::new(), vec![main, clinit]),
+		];
+		let mut hierarchy = ClassHierarchy::new();
+		for class in &classes {
+			hierarchy.add_class_file(class);
+		}
+
+		let id = |class: &[u8], name: &[u8], descriptor: &[u8]| MethodId::new(ClassName::from(class), MethodName::from(name), self::descriptor(descriptor));
+		let build = |analysis| {
+			let mut builder = CallGraphBuilder::new(&hierarchy, analysis);
+			for class in &classes {
+				builder.add_class(class);
+			}
+			builder.main_methods().build()
+		};
+
+		let cha = build(Analysis::ClassHierarchy);
+		let main = id(b"Main", b"main", b"([Ljava/lang/String;)V");
+		assert_eq!(cha.entry_points.iter().collect::<Vec<_>>(), [&main]);
+		assert!(cha.is_reachable(&id(b"Main", b"<clinit>", b"()V")));
+		assert!(cha.is_reachable(&id(b"Circle", b"area", b"()D")));
+		assert!(cha.is_reachable(&id(b"Square", b"area", b"()D")));
+		assert!(!cha.is_reachable(&id(b"Square", b"<init>", b"()V")));
+		// methods without code are reached, but not followed
+		assert!(cha.is_reachable(&id(b"java/lang/Object", b"<init>", b"()V")));
+		assert_eq!(cha.callers(&id(b"java/lang/Object", b"<init>", b"()V")).map(|edge| &edge.caller).collect::<Vec<_>>(), [&id(b"Circle", b"<init>", b"()V")]);
+
+		let rta = build(Analysis::RapidType);
+		assert!(rta.is_reachable(&id(b"Circle", b"area", b"()D")));
+		assert!(!rta.is_reachable(&id(b"Square", b"area", b"()D")));
+		assert_eq!(rta.instantiated.iter().collect::<Vec<_>>(), [&ClassName::from(b"Circle")]);
+		let kinds: Vec<_> = rta.callees(&main).map(|edge| (edge.kind, edge.callee.to_string())).collect();
+		assert_eq!(kinds, [
+			(CallKind::Special, "Circle.<init>()V".into()),
+			(CallKind::Interface, "Circle.area()D".into()),
+		]);
+
+		let dot = rta.to_dot();
+		assert!(dot.contains("\t\"Main.main([Ljava/lang/String;)V\" [shape=box];\n"));
+		assert!(dot.contains("\"Main.main([Ljava/lang/String;)V\" -> \"Circle.area()D\" [label=\"interface\"];"));
+		let json = rta.to_json();
+		assert!(json.starts_with("{\"analysis\":\"rta\",\"entry_points\":[\"Main.main([Ljava/lang/String;)V\"],"));
+		assert!(json.contains("{\"caller\":\"Main.main([Ljava/lang/String;)V\",\"offset\":7,\"kind\":\"interface\",\"callee\":\"Circle.area()D\"}"));
+	}
+}
@@ -0,0 +1,197 @@
+//! Linking `invokedynamic` instructions to their bootstrap methods.
+//!
+//! The constant pool entry of an `invokedynamic` only holds an index into the `BootstrapMethods` attribute of the class. [ClassFile::call_site] resolves
+//! it, and recognizes the call sites javac emits for lambdas, method references and string concatenation.
+
+use alloc::vec::Vec;
+use anyhow::{anyhow, Result};
+use crate::{find_attribute, ClassFile, MethodInfo};
+use crate::cp::{InvokeDynamicInfo, MethodHandleInfo, MethodRefInfo};
+use crate::cp::attribute::{AttributeInfo, BootstrapMethodArgument, BootstrapMethodsAttributeEntry};
+use crate::descriptor::{BaseOrObjectType, MethodDescriptor};
+use crate::instruction::opcode::Opcode;
+use crate::name::ClassName;
+
+const LAMBDA_METAFACTORY: &[u8] = b"java/lang/invoke/LambdaMetafactory";
+const STRING_CONCAT_FACTORY: &[u8] = b"java/lang/invoke/StringConcatFactory";
+
+/// An `invokedynamic` joined with its bootstrap method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallSite<'a> {
+	pub info: &'a InvokeDynamicInfo,
+	pub bootstrap: &'a BootstrapMethodsAttributeEntry,
+	pub kind: CallSiteKind<'a>,
+}
+
+/// What a call site does, as far as can be told from its bootstrap method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallSiteKind<'a> {
+	/// A lambda or method reference, bootstrapped by `LambdaMetafactory.metafactory` or `altMetafactory`. The functional interface is the return type
+	/// of the call site descriptor, the name of the call site is the name of the method it implements.
+	Lambda {
+		/// The erased descriptor of the method implemented.
+		interface_method_type: &'a MethodDescriptor,
+		/// The method the lambda body was compiled to, or the method referenced.
+		implementation: &'a MethodHandleInfo,
+		/// The descriptor of the method implemented, after specializing its type parameters.
+		instantiated_method_type: &'a MethodDescriptor,
+		/// Whether `altMetafactory` is used, which javac does for serializable lambdas and marker interfaces.
+		alternate: bool,
+	},
+	/// A string concatenation, bootstrapped by `StringConcatFactory.makeConcatWithConstants` or `makeConcat`.
+	StringConcat {
+		/// The recipe, in which `\1` stands for an argument of the call site and `\2` for the next of the `constants`. There is no recipe for
+		/// `makeConcat`, which concatenates the arguments.
+		recipe: Option<&'a [u8]>,
+		constants: &'a [BootstrapMethodArgument],
+	},
+	/// Any other bootstrap method, or one of the above with arguments not matching it.
+	Other,
+}
+
+impl<'a> CallSiteKind<'a> {
+	fn of(bootstrap: &'a BootstrapMethodsAttributeEntry) -> CallSiteKind<'a> {
+		let MethodHandleInfo::InvokeStatic(MethodRefInfo { class, name, .. }) = &bootstrap.bootstrap_method else {
+			return CallSiteKind::Other;
+		};
+		let arguments = &bootstrap.bootstrap_arguments[..];
+		match (class.as_bytes(), name.as_bytes()) {
+			(LAMBDA_METAFACTORY, b"metafactory" | b"altMetafactory") => match arguments {
+				[
+					BootstrapMethodArgument::MethodType(interface_method_type),
+					BootstrapMethodArgument::MethodHandle(implementation),
+					BootstrapMethodArgument::MethodType(instantiated_method_type),
+					..
+				] => CallSiteKind::Lambda {
+					interface_method_type, implementation, instantiated_method_type,
+					alternate: name.as_bytes() == b"altMetafactory",
+				},
+				_ => CallSiteKind::Other,
+			},
+			(STRING_CONCAT_FACTORY, b"makeConcatWithConstants") => match arguments {
+				[BootstrapMethodArgument::String(recipe), constants @ ..] => CallSiteKind::StringConcat { recipe: Some(recipe), constants },
+				_ => CallSiteKind::Other,
+			},
+			(STRING_CONCAT_FACTORY, b"makeConcat") if arguments.is_empty() => CallSiteKind::StringConcat { recipe: None, constants: arguments },
+			_ => CallSiteKind::Other,
+		}
+	}
+}
+
+impl CallSite<'_> {
+	/// Returns the functional interface implemented by a lambda call site.
+	pub fn functional_interface(&self) -> Option<&ClassName> {
+		match self.kind {
+			CallSiteKind::Lambda { .. } => self.info.descriptor.return_type.as_ref().and_then(|return_type| match &return_type.base_type {
+				BaseOrObjectType::Object(class) if return_type.array_dimension == 0 => Some(class),
+				_ => None,
+			}),
+			_ => None,
+		}
+	}
+}
+
+impl ClassFile {
+	/// Returns the bootstrap methods of the class, from its `BootstrapMethods` attribute.
+	pub fn bootstrap_methods(&self) -> &[BootstrapMethodsAttributeEntry] {
+		find_attribute(&self.attributes, |attribute| match attribute {
+			AttributeInfo::BootstrapMethods(attribute) => Some(&attribute.bootstrap_methods[..]),
+			_ => None,
+		}).unwrap_or(&[])
+	}
+
+	/// Resolves the bootstrap method of an `invokedynamic` of this class.
+	pub fn call_site<'a>(&'a self, info: &'a InvokeDynamicInfo) -> Result<CallSite<'a>> {
+		let index = info.bootstrap_method_attribute_index as usize;
+		let bootstrap = self.bootstrap_methods().get(index)
+			.ok_or_else(|| anyhow!("bootstrap method {index} of call site {:?} doesn't exist in class {:?}", info.name, self.this_class))?;
+		Ok(CallSite { info, bootstrap, kind: CallSiteKind::of(bootstrap) })
+	}
+
+	/// Returns the call sites of all `invokedynamic` instructions of `method`, with the index of the instruction.
+	pub fn call_sites<'a>(&'a self, method: &'a MethodInfo) -> Result<Vec<(usize, CallSite<'a>)>> {
+		let Some(code) = &method.code else {
+			return Ok(Vec::new());
+		};
+		code.code.iter()
+			.enumerate()
+			.filter_map(|(index, instruction)| match instruction.opcode() {
+				Opcode::InvokeDynamic { call_site, .. } => Some(self.call_site(call_site).map(|call_site| (index, call_site))),
+				_ => None,
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::ClassFile;
+	use crate::access::ClassInfoAccess;
+	use crate::call_site::CallSiteKind;
+	use crate::cp::{InvokeDynamicInfo, MethodHandleInfo, MethodRefInfo};
+	use crate::cp::attribute::{AttributeInfo, BootstrapMethodArgument};
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+	use crate::name::ClassName;
+
+	#[test]
+	fn lambda_and_string_concat() {
+		let object = |name: &str| FieldDescriptor { array_dimension: 0, base_type: BaseOrObjectType::Object(ClassName::from(name.as_bytes())) };
+		let method = |class: &str, name: &str, descriptor| MethodRefInfo { class: ClassName::from(class.as_bytes()), name: name.as_bytes().into(), descriptor };
+		let run = MethodDescriptor { parameters: Vec::new(), return_type: None };
+		let lookup = vec![object("java/lang/invoke/MethodHandles$Lookup"), object("java/lang/String"), object("java/lang/invoke/MethodType")];
+		let call_site = |return_type| MethodDescriptor { parameters: lookup.clone(), return_type: Some(object(return_type)) };
+
+		let mut pool = PoolBuilder::new();
+		let metafactory = pool.method_handle(&MethodHandleInfo::InvokeStatic(method("java/lang/invoke/LambdaMetafactory", "metafactory", {
+			let mut descriptor = call_site("java/lang/invoke/CallSite");
+			descriptor.parameters.extend([object("java/lang/invoke/MethodType"), object("java/lang/invoke/MethodHandle"), object("java/lang/invoke/MethodType")]);
+			descriptor
+		}))).unwrap();
+		let run_type = pool.method_type(&run).unwrap();
+		let implementation = MethodHandleInfo::InvokeStatic(method("Main", "lambda$main$0", run.clone()));
+		let body = pool.method_handle(&implementation).unwrap();
+		let concat = pool.method_handle(&MethodHandleInfo::InvokeStatic(method("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants", {
+			let mut descriptor = call_site("java/lang/invoke/CallSite");
+			descriptor.parameters.extend([object("java/lang/String"), FieldDescriptor { array_dimension: 1, ..object("java/lang/Object") }]);
+			descriptor
+		}))).unwrap();
+		let recipe = pool.string(b"x = \x01").unwrap();
+
+		let mut bytes = (pool.utf8(b"BootstrapMethods").unwrap() as u16).to_be_bytes().to_vec();
+		let entries = [metafactory, 3, run_type, body, run_type, concat, 1, recipe];
+		bytes.extend_from_slice(&(2 + 2 * entries.len() as u32).to_be_bytes());
+		bytes.extend_from_slice(&2u16.to_be_bytes());
+		for entry in entries {
+			bytes.extend_from_slice(&(entry as u16).to_be_bytes());
+		}
+		let attribute = AttributeInfo::parse(&mut &bytes[..], &pool.build()).unwrap();
+
+		let class = ClassFile {
+			minor_version: 0, major_version: 61,
+			access_flags: ClassInfoAccess::parse(0x0021).unwrap(),
+			this_class: ClassName::from(&b"Main"[..]),
+			super_class: Some(ClassName::from(&b"java/lang/Object"[..])),
+			interfaces: Vec::new(), fields: Vec::new(), methods: Vec::new(),
+			attributes: vec![attribute],
+		};
+
+		let lambda = InvokeDynamicInfo {
+			bootstrap_method_attribute_index: 0,
+			name: (&b"run"[..]).into(),
+			descriptor: MethodDescriptor { parameters: Vec::new(), return_type: Some(object("java/lang/Runnable")) },
+		};
+		let call_site = class.call_site(&lambda).unwrap();
+		assert_eq!(call_site.kind, CallSiteKind::Lambda {
+			interface_method_type: &run, implementation: &implementation, instantiated_method_type: &run, alternate: false,
+		});
+		assert_eq!(call_site.functional_interface(), Some(&ClassName::from(&b"java/lang/Runnable"[..])));
+
+		let concatenation = InvokeDynamicInfo { bootstrap_method_attribute_index: 1, ..lambda.clone() };
+		let call_site = class.call_site(&concatenation).unwrap();
+		assert_eq!(call_site.kind, CallSiteKind::StringConcat { recipe: Some(&b"x = \x01"[..]), constants: &[] });
+		assert!(matches!(call_site.bootstrap.bootstrap_arguments[..], [BootstrapMethodArgument::String(_)]));
+
+		assert!(class.call_site(&InvokeDynamicInfo { bootstrap_method_attribute_index: 2, ..lambda }).is_err());
+	}
+}
@@ -0,0 +1,391 @@
+//! Control flow graphs over the [Instructions] of a method.
+//!
+//! The code is split into basic blocks, that is maximal sequences of instructions that are only entered at the first instruction and only left after the
+//! last instruction. Blocks are connected by fall through, branch and exception edges, the latter coming from the exception table. On top of that, the
+//! dominator tree and the natural loops of the graph can be computed.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use crate::cp::attribute::{CodeAttribute, ExceptionTableEntry};
+use crate::instruction::Instructions;
+
+/// Identifies a [BasicBlock] in a [ControlFlowGraph]. The blocks are numbered in the order of their first instruction, so the entry block is always `0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+	/// Execution continues with the instruction following the last one of the block.
+	FallThrough,
+	/// The last instruction of the block branches to the target block.
+	Branch,
+	/// An exception thrown in the block is caught by the handler of the exception table entry with this index.
+	Exception(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+	pub from: BlockId,
+	pub to: BlockId,
+	pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+	/// The index (not offset) of the first instruction of this block.
+	pub start: usize,
+	/// The index after the last instruction of this block.
+	pub end: usize,
+	pub successors: Vec<Edge>,
+	pub predecessors: Vec<Edge>,
+}
+
+impl BasicBlock {
+	/// The indices of the instructions in this block.
+	pub fn instructions(&self) -> core::ops::Range<usize> {
+		self.start..self.end
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+	blocks: Vec<BasicBlock>,
+	/// Maps the index of each instruction to the block containing it.
+	block_of_instruction: Vec<BlockId>,
+	/// The offset of the first instruction of each block, for looking up blocks by offset.
+	block_offsets: Vec<usize>,
+}
+
+impl ControlFlowGraph {
+	pub fn new(code: &CodeAttribute) -> Result<ControlFlowGraph> {
+		Self::from_instructions(&code.code, &code.exception_table)
+	}
+
+	pub fn from_instructions(instructions: &Instructions, exception_table: &[ExceptionTableEntry]) -> Result<ControlFlowGraph> {
+		let index_of = |offset: usize| instructions.index_of(offset)
+			.ok_or_else(|| anyhow!("offset {offset} is not the start of an instruction"));
+
+		// an instruction starts a new block if it is the first one, is the target of a jump, follows a jump, or starts or ends a region covered by an
+		// exception handler, or is a handler itself
+		let mut is_leader = vec![false; instructions.len() + 1];
+		is_leader[0] = true;
+		for (index, instruction) in instructions.iter().enumerate() {
+			let opcode = instruction.opcode();
+			for target in opcode.branch_targets() {
+				is_leader[index_of(target.0)?] = true;
+			}
+			if !opcode.branch_targets().is_empty() || !opcode.falls_through() {
+				is_leader[index + 1] = true;
+			}
+		}
+		for entry in exception_table {
+			let start = index_of(entry.start_pc)?;
+			let end = instructions.index_of_or_end(entry.end_pc)
+				.ok_or_else(|| anyhow!("end_pc {} is neither the start of an instruction nor the end of the code", entry.end_pc))?;
+			if start >= end {
+				bail!("exception table entry covers no instructions: {entry:?}");
+			}
+			is_leader[start] = true;
+			is_leader[end] = true;
+			is_leader[index_of(entry.handler_pc)?] = true;
+		}
+
+		let mut blocks = Vec::new();
+		let mut block_of_instruction = Vec::with_capacity(instructions.len());
+		for (index, &leader) in is_leader[..instructions.len()].iter().enumerate() {
+			if leader {
+				blocks.push(BasicBlock { start: index, end: index, successors: Vec::new(), predecessors: Vec::new() });
+			}
+			blocks.last_mut().expect("the first instruction is always a leader").end = index + 1;
+			block_of_instruction.push(BlockId(blocks.len() - 1));
+		}
+
+		let mut edges = Vec::new();
+		for (id, block) in blocks.iter().enumerate() {
+			let from = BlockId(id);
+			let opcode = instructions.get(block.end - 1).expect("blocks are never empty").opcode();
+
+			if opcode.falls_through() {
+				if block.end == instructions.len() {
+					bail!("execution falls off the end of the code");
+				}
+				edges.push(Edge { from, to: block_of_instruction[block.end], kind: EdgeKind::FallThrough });
+			}
+			for target in opcode.branch_targets() {
+				let to = block_of_instruction[index_of(target.0)?];
+				// switches may list the same target more than once
+				if !edges.iter().any(|edge: &Edge| edge.from == from && edge.to == to && edge.kind == EdgeKind::Branch) {
+					edges.push(Edge { from, to, kind: EdgeKind::Branch });
+				}
+			}
+			for (entry_index, entry) in exception_table.iter().enumerate() {
+				let start = index_of(entry.start_pc)?;
+				// since the start and the end of each covered region start blocks, a block is either completely covered, or not at all
+				if start <= block.start && instructions.index_of_or_end(entry.end_pc).is_some_and(|end| block.end <= end) {
+					edges.push(Edge { from, to: block_of_instruction[index_of(entry.handler_pc)?], kind: EdgeKind::Exception(entry_index) });
+				}
+			}
+		}
+		for edge in edges {
+			blocks[edge.from.0].successors.push(edge);
+			blocks[edge.to.0].predecessors.push(edge);
+		}
+
+		let block_offsets = blocks.iter()
+			.map(|block| instructions.get(block.start).expect("blocks are never empty").offset())
+			.collect();
+
+		Ok(ControlFlowGraph { blocks, block_of_instruction, block_offsets })
+	}
+
+	pub fn blocks(&self) -> &[BasicBlock] {
+		&self.blocks
+	}
+
+	pub fn block(&self, id: BlockId) -> &BasicBlock {
+		&self.blocks[id.0]
+	}
+
+	/// Returns the block containing the instruction with the index `instruction_index`.
+	pub fn block_of(&self, instruction_index: usize) -> BlockId {
+		self.block_of_instruction[instruction_index]
+	}
+
+	/// Returns the block that starts at the byte offset `offset`, if there is one.
+	pub fn block_at_offset(&self, offset: usize) -> Option<BlockId> {
+		self.block_offsets.binary_search(&offset).ok().map(BlockId)
+	}
+
+	pub fn edges(&self) -> impl Iterator<Item=&Edge> {
+		self.blocks.iter().flat_map(|block| &block.successors)
+	}
+
+	/// Returns the blocks reachable from the entry block in reverse postorder. In this order, every block comes before its successors, except for the
+	/// targets of back edges.
+	pub fn reverse_postorder(&self) -> Vec<BlockId> {
+		let mut order = Vec::with_capacity(self.blocks.len());
+		if self.blocks.is_empty() {
+			return order;
+		}
+
+		let mut visited = vec![false; self.blocks.len()];
+		// the stack holds a block together with the index of its next successor to visit
+		let mut stack = vec![(BlockId(0), 0)];
+		visited[0] = true;
+		while let Some((id, next)) = stack.last_mut() {
+			let id = *id;
+			if let Some(edge) = self.blocks[id.0].successors.get(*next) {
+				*next += 1;
+				if !visited[edge.to.0] {
+					visited[edge.to.0] = true;
+					stack.push((edge.to, 0));
+				}
+			} else {
+				order.push(id);
+				stack.pop();
+			}
+		}
+		order.reverse();
+		order
+	}
+
+	/// Computes the dominator tree, using the algorithm of Cooper, Harvey and Kennedy ("A Simple, Fast Dominance Algorithm").
+	pub fn dominators(&self) -> Dominators {
+		let order = self.reverse_postorder();
+		let mut order_index = vec![usize::MAX; self.blocks.len()];
+		for (i, id) in order.iter().enumerate() {
+			order_index[id.0] = i;
+		}
+
+		let mut idom: Vec<Option<BlockId>> = vec![None; self.blocks.len()];
+		if let Some(&entry) = order.first() {
+			idom[entry.0] = Some(entry);
+		}
+
+		let intersect = |idom: &Vec<Option<BlockId>>, mut a: BlockId, mut b: BlockId| {
+			while a != b {
+				while order_index[a.0] > order_index[b.0] {
+					a = idom[a.0].expect("processed blocks have a dominator");
+				}
+				while order_index[b.0] > order_index[a.0] {
+					b = idom[b.0].expect("processed blocks have a dominator");
+				}
+			}
+			a
+		};
+
+		let mut changed = true;
+		while changed {
+			changed = false;
+			for &id in order.iter().skip(1) {
+				let new_idom = self.blocks[id.0].predecessors.iter()
+					.map(|edge| edge.from)
+					.filter(|pred| idom[pred.0].is_some())
+					.reduce(|a, b| intersect(&idom, a, b));
+				if new_idom.is_some() && idom[id.0] != new_idom {
+					idom[id.0] = new_idom;
+					changed = true;
+				}
+			}
+		}
+
+		// the entry block has no immediate dominator
+		if let Some(&entry) = order.first() {
+			idom[entry.0] = None;
+		}
+		Dominators { idom, reachable: order_index.iter().map(|&i| i != usize::MAX).collect() }
+	}
+
+	/// Finds the natural loops of the graph. A loop is identified by its header, which dominates all blocks of the loop, and the back edges to it. Loops
+	/// sharing a header are merged into one.
+	///
+	/// The loops are ordered by their header.
+	pub fn loops(&self) -> Vec<Loop> {
+		let dominators = self.dominators();
+
+		let mut loops: Vec<Loop> = Vec::new();
+		for edge in self.edges() {
+			if !dominators.dominates(edge.to, edge.from) {
+				continue;
+			}
+
+			let index = match loops.iter().position(|l| l.header == edge.to) {
+				Some(index) => index,
+				None => {
+					loops.push(Loop { header: edge.to, latches: Vec::new(), body: vec![edge.to] });
+					loops.len() - 1
+				},
+			};
+			let l = &mut loops[index];
+			if !l.latches.contains(&edge.from) {
+				l.latches.push(edge.from);
+			}
+
+			// all blocks that reach the latch without going through the header belong to the loop
+			let mut stack = vec![edge.from];
+			while let Some(id) = stack.pop() {
+				if l.body.contains(&id) {
+					continue;
+				}
+				l.body.push(id);
+				stack.extend(self.blocks[id.0].predecessors.iter().map(|edge| edge.from).filter(|&pred| dominators.is_reachable(pred)));
+			}
+		}
+
+		for l in &mut loops {
+			l.latches.sort();
+			l.body.sort();
+		}
+		loops.sort_by_key(|l| l.header);
+		loops
+	}
+}
+
+/// The dominator tree of a [ControlFlowGraph]. A block `a` dominates a block `b` if every path from the entry block to `b` goes through `a`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dominators {
+	idom: Vec<Option<BlockId>>,
+	reachable: Vec<bool>,
+}
+
+impl Dominators {
+	/// Returns the immediate dominator of a block. This is `None` for the entry block and for unreachable blocks.
+	pub fn immediate_dominator(&self, id: BlockId) -> Option<BlockId> {
+		self.idom[id.0]
+	}
+
+	pub fn is_reachable(&self, id: BlockId) -> bool {
+		self.reachable[id.0]
+	}
+
+	/// Returns whether `a` dominates `b`. Every reachable block dominates itself, unreachable blocks neither dominate nor are dominated.
+	pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+		if !self.is_reachable(a) || !self.is_reachable(b) {
+			return false;
+		}
+		let mut current = Some(b);
+		while let Some(id) = current {
+			if id == a {
+				return true;
+			}
+			current = self.idom[id.0];
+		}
+		false
+	}
+}
+
+/// A natural loop in a [ControlFlowGraph].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+	/// The single entry of the loop, which dominates all blocks of it.
+	pub header: BlockId,
+	/// The blocks with a back edge to the header.
+	pub latches: Vec<BlockId>,
+	/// All blocks of the loop, including the header, in ascending order.
+	pub body: Vec<BlockId>,
+}
+
+impl Loop {
+	pub fn contains(&self, id: BlockId) -> bool {
+		self.body.binary_search(&id).is_ok()
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::cp::attribute::ExceptionTableEntry;
+	use crate::cp::Pool;
+	use crate::instruction::Instructions;
+	use super::{BlockId, ControlFlowGraph, EdgeKind, Loop};
+
+	#[test]
+	fn loop_with_exception_handler() {
+		let code = [
+			0x03,             //  0: iconst_0
+			0x3c,             //  1: istore_1
+			0x1b,             //  2: iload_1
+			0x10, 0x0a,       //  3: bipush 10
+			0xa2, 0x00, 0x0b, //  5: if_icmpge 16
+			0x84, 0x01, 0x01, //  8: iinc 1 1
+			0xa7, 0xff, 0xf7, // 11: goto 2
+			0x4d,             // 14: astore_2
+			0x00,             // 15: nop
+			0xb1,             // 16: return
+		];
+		let pool = Pool::parse(&mut &[0u8, 1][..]).unwrap();
+		let instructions = Instructions::parse(&code, &pool).unwrap();
+		let exception_table = [ExceptionTableEntry { start_pc: 8, end_pc: 11, handler_pc: 14, catch_type: None }];
+
+		let cfg = ControlFlowGraph::from_instructions(&instructions, &exception_table).unwrap();
+
+		let ranges: Vec<_> = cfg.blocks().iter().map(|block| block.instructions()).collect();
+		assert_eq!(ranges, vec![0..2, 2..5, 5..6, 6..7, 7..9, 9..10]);
+		assert_eq!(cfg.block_at_offset(14), Some(BlockId(4)));
+		assert_eq!(cfg.block_at_offset(15), None);
+
+		let successors: Vec<Vec<_>> = cfg.blocks().iter()
+			.map(|block| block.successors.iter().map(|edge| (edge.to.0, edge.kind)).collect())
+			.collect();
+		assert_eq!(successors, vec![
+			vec![(1, EdgeKind::FallThrough)],
+			vec![(2, EdgeKind::FallThrough), (5, EdgeKind::Branch)],
+			vec![(3, EdgeKind::FallThrough), (4, EdgeKind::Exception(0))],
+			vec![(1, EdgeKind::Branch)],
+			vec![(5, EdgeKind::FallThrough)],
+			vec![],
+		]);
+
+		let dominators = cfg.dominators();
+		let idoms: Vec<_> = (0..6).map(|id| dominators.immediate_dominator(BlockId(id)).map(|id| id.0)).collect();
+		assert_eq!(idoms, vec![None, Some(0), Some(1), Some(2), Some(2), Some(1)]);
+		assert!(dominators.dominates(BlockId(1), BlockId(4)));
+		assert!(!dominators.dominates(BlockId(4), BlockId(5)));
+
+		assert_eq!(cfg.loops(), vec![Loop {
+			header: BlockId(1),
+			latches: vec![BlockId(3)],
+			body: vec![BlockId(1), BlockId(2), BlockId(3)],
+		}]);
+	}
+}
@@ -0,0 +1,216 @@
+//! Reading class files from the places a class path can point to: jars, directories and single class files.
+
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, Read as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use anyhow::{Context, Result};
+use zip::ZipArchive;
+use crate::name::ClassName;
+
+/// Whether the file is a class, leaving out module and package descriptors, and the versioned classes of multi-release jars.
+fn is_class_file(name: &str) -> bool {
+	name.ends_with(".class") && !name.ends_with("module-info.class") && !name.ends_with("package-info.class") && !name.starts_with("META-INF/")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ClassPathEntry {
+	/// A `.jar` or `.zip` file, containing class files under names like `foo/bar/Baz.class`.
+	Jar(PathBuf, OpenJar),
+	/// A directory containing class files in subdirectories for their packages, like `foo/bar/Baz.class`.
+	Directory(PathBuf),
+	/// A single class file.
+	Class(PathBuf),
+}
+
+impl ClassPathEntry {
+	/// Picks the kind of entry by looking at the path: directories are taken as such, files ending in `.class` as class files, and any other file as jar.
+	pub fn new(path: impl Into<PathBuf>) -> ClassPathEntry {
+		let path = path.into();
+		if path.is_dir() {
+			ClassPathEntry::Directory(path)
+		} else if path.extension().is_some_and(|extension| extension == "class") {
+			ClassPathEntry::Class(path)
+		} else {
+			ClassPathEntry::Jar(path, OpenJar::default())
+		}
+	}
+
+	/// Splits a class path like `lib/a.jar:classes` at the separator of the platform.
+	pub fn split(class_path: &str) -> Vec<ClassPathEntry> {
+		std::env::split_paths(class_path)
+			.filter(|path| !path.as_os_str().is_empty())
+			.map(ClassPathEntry::new)
+			.collect()
+	}
+
+	pub fn path(&self) -> &Path {
+		match self {
+			ClassPathEntry::Jar(path, _) | ClassPathEntry::Directory(path) | ClassPathEntry::Class(path) => path,
+		}
+	}
+
+	/// Reads all class files of the entry, with their name relative to the entry, like `foo/Bar.class`. The class files of a jar are in the order of
+	/// the jar, the ones of a directory are sorted by name.
+	pub fn class_files(&self) -> Result<Vec<(String, Vec<u8>)>> {
+		match self {
+			ClassPathEntry::Class(path) => {
+				let bytes = std::fs::read(path).with_context(|| format!("while reading {}", path.display()))?;
+				let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+				Ok(vec![(name, bytes)])
+			},
+			_ => self.files(is_class_file),
+		}
+	}
+
+	/// Reads the files of the entry [ClassPathEntry::class_files] leaves out, like `META-INF/MANIFEST.MF` or `module-info.class`, in the same order. A
+	/// single class file has none.
+	pub fn resources(&self) -> Result<Vec<(String, Vec<u8>)>> {
+		match self {
+			ClassPathEntry::Class(_) => Ok(Vec::new()),
+			_ => self.files(|name| !is_class_file(name)),
+		}
+	}
+
+	/// Reads the files of a jar or a directory whose name is `wanted`.
+	fn files(&self, wanted: fn(&str) -> bool) -> Result<Vec<(String, Vec<u8>)>> {
+		let mut files = Vec::new();
+		match self {
+			ClassPathEntry::Jar(path, jar) => jar.with(path, |jar| {
+				for index in 0..jar.len() {
+					let mut file = jar.by_index(index)?;
+					if file.is_file() && wanted(file.name()) {
+						let mut bytes = Vec::with_capacity(file.size() as usize);
+						file.read_to_end(&mut bytes).with_context(|| format!("while reading {} from {}", file.name(), path.display()))?;
+						files.push((file.name().to_owned(), bytes));
+					}
+				}
+				Ok(())
+			})?,
+			ClassPathEntry::Directory(path) => read_directory(path, "", wanted, &mut files)?,
+			ClassPathEntry::Class(_) => {},
+		}
+		Ok(files)
+	}
+
+	/// Reads the class file for the class `name`, if the entry contains one, going by the file name. A single class file is only returned if its file
+	/// name matches the simple name of the class.
+	pub fn find(&self, name: &ClassName) -> Result<Option<Vec<u8>>> {
+		let file_name = format!("{}.class", String::from_utf8_lossy(name.as_bytes()));
+		match self {
+			ClassPathEntry::Jar(path, jar) => jar.with(path, |jar| {
+				let result = jar.by_name(&file_name);
+				match result {
+					Ok(mut file) => {
+						let mut bytes = Vec::with_capacity(file.size() as usize);
+						file.read_to_end(&mut bytes).with_context(|| format!("while reading {file_name} from {}", path.display()))?;
+						Ok(Some(bytes))
+					},
+					Err(zip::result::ZipError::FileNotFound) => Ok(None),
+					Err(error) => Err(error).with_context(|| format!("while reading {file_name} from {}", path.display())),
+				}
+			}),
+			ClassPathEntry::Directory(path) => {
+				let path = path.join(&file_name);
+				if path.is_file() {
+					Ok(Some(std::fs::read(&path).with_context(|| format!("while reading {}", path.display()))?))
+				} else {
+					Ok(None)
+				}
+			},
+			ClassPathEntry::Class(path) => {
+				let simple_name = file_name.rsplit('/').next().unwrap_or(&file_name);
+				if path.file_name().is_some_and(|name| name == simple_name) {
+					Ok(Some(std::fs::read(path).with_context(|| format!("while reading {}", path.display()))?))
+				} else {
+					Ok(None)
+				}
+			},
+		}
+	}
+}
+
+/// The archive of a [ClassPathEntry::Jar], opened on first use and kept open, so that looking up classes one by one reads the central directory of
+/// the jar and builds the index of its file names only once. Clones share the open archive. It takes no part in comparing and hashing entries,
+/// which only go by the path.
+#[derive(Clone, Default)]
+pub struct OpenJar(Arc<Mutex<Option<ZipArchive<BufReader<File>>>>>);
+
+impl OpenJar {
+	fn with<T>(&self, path: &Path, f: impl FnOnce(&mut ZipArchive<BufReader<File>>) -> Result<T>) -> Result<T> {
+		let mut jar = self.0.lock().unwrap();
+		if jar.is_none() {
+			*jar = Some(open_jar(path)?);
+		}
+		f(jar.as_mut().unwrap())
+	}
+}
+
+impl Debug for OpenJar {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str("OpenJar")
+	}
+}
+
+impl PartialEq for OpenJar {
+	fn eq(&self, _: &OpenJar) -> bool {
+		true
+	}
+}
+
+impl Eq for OpenJar {}
+
+impl Hash for OpenJar {
+	fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+fn open_jar(path: &Path) -> Result<ZipArchive<BufReader<File>>> {
+	let file = File::open(path).with_context(|| format!("while opening {}", path.display()))?;
+	ZipArchive::new(BufReader::new(file)).with_context(|| format!("while reading {} as jar", path.display()))
+}
+
+fn read_directory(directory: &Path, prefix: &str, wanted: fn(&str) -> bool, files: &mut Vec<(String, Vec<u8>)>) -> Result<()> {
+	let mut entries = std::fs::read_dir(directory)
+		.with_context(|| format!("while reading directory {}", directory.display()))?
+		.collect::<std::io::Result<Vec<_>>>()?;
+	entries.sort_by_key(|entry| entry.file_name());
+	for entry in entries {
+		let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+		if entry.file_type()?.is_dir() {
+			read_directory(&entry.path(), &format!("{name}/"), wanted, files)?;
+		} else if wanted(&name) {
+			let bytes = std::fs::read(entry.path()).with_context(|| format!("while reading {}", entry.path().display()))?;
+			files.push((name, bytes));
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+	use std::io::Write as _;
+	use zip::write::FileOptions;
+	use zip::ZipWriter;
+	use crate::classpath::ClassPathEntry;
+	use crate::name::ClassName;
+
+	#[test]
+	fn jar_stays_open_between_lookups() {
+		let path = std::env::temp_dir().join(format!("classpath-{}.jar", std::process::id()));
+		let mut jar = ZipWriter::new(std::fs::File::create(&path).unwrap());
+		jar.start_file("foo/Bar.class", FileOptions::default()).unwrap();
+		jar.write_all(b"\xca\xfe\xba\xbe").unwrap();
+		jar.finish().unwrap();
+
+		let entry = ClassPathEntry::new(&path);
+		assert_eq!(entry.find(&ClassName::from(b"foo/Bar")).unwrap(), Some(b"\xca\xfe\xba\xbe".to_vec()));
+		std::fs::remove_file(&path).unwrap();
+		// the clone shares the archive opened by the first lookup, so it doesn't need the file anymore
+		let clone = entry.clone();
+		assert_eq!(clone, ClassPathEntry::new(&path));
+		assert_eq!(clone.find(&ClassName::from(b"foo/Baz")).unwrap(), None);
+		assert_eq!(clone.find(&ClassName::from(b"foo/Bar")).unwrap(), Some(b"\xca\xfe\xba\xbe".to_vec()));
+	}
+}
@@ -0,0 +1,406 @@
+//! Checking two versions of a library for changes to its API that break the code using it, following chapter 13 of the JLS, like `japicmp`.
+//!
+//! A change is binary-incompatible if classes compiled against the old version may fail to link or run with the new one, and source-incompatible if
+//! they still link, but may no longer compile against it. The API of a library is made of its public classes and interfaces with their public
+//! members, and the protected members of the ones that can be extended.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use crate::{ClassFile, FieldInfo, MethodInfo};
+use crate::descriptor::{FieldDescriptor, MethodDescriptor};
+use crate::hierarchy::{ClassEntry, ClassHierarchy};
+use crate::name::{ClassName, FieldName, MethodName};
+
+/// Who a change breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Incompatibility {
+	/// Classes compiled against the old version may fail to link or run with the new one. This mostly breaks compiling against it too.
+	Binary,
+	/// Classes compiled against the old version still link with the new one, but may no longer compile against it, or behave differently once
+	/// recompiled.
+	Source,
+}
+
+impl Incompatibility {
+	pub fn name(self) -> &'static str {
+		match self {
+			Incompatibility::Binary => "binary",
+			Incompatibility::Source => "source",
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Member {
+	Field(FieldName, FieldDescriptor),
+	Method(MethodName, MethodDescriptor),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChangeKind {
+	ClassRemoved,
+	/// The class is no longer public.
+	ClassLessAccessible,
+	ClassMadeAbstract,
+	ClassMadeFinal,
+	ClassMadeInterface,
+	InterfaceMadeClass,
+	/// A superclass or superinterface, direct or not, was removed.
+	SupertypeRemoved(ClassName),
+	/// The type parameters, or the type arguments of the supertypes, changed.
+	ClassSignatureChanged,
+
+	/// The field was removed, and isn't inherited from a supertype either.
+	FieldRemoved,
+	FieldLessAccessible,
+	/// The field has the new type.
+	FieldTypeChanged(FieldDescriptor),
+	FieldMadeFinal,
+	FieldMadeStatic,
+	FieldMadeNonStatic,
+	FieldSignatureChanged,
+	/// The value of a constant changed, which the classes compiled against the old version have inlined.
+	ConstantValueChanged,
+
+	/// The method was removed, and isn't inherited from a superclass either.
+	MethodRemoved,
+	/// The only method with the name was replaced by one with the new descriptor.
+	MethodDescriptorChanged(MethodDescriptor),
+	MethodLessAccessible,
+	MethodMadeAbstract,
+	MethodMadeFinal,
+	MethodMadeStatic,
+	MethodMadeNonStatic,
+	MethodSignatureChanged,
+	/// The method throws the new exception class.
+	ExceptionAdded(ClassName),
+	/// An abstract method was added to an interface or an abstract class, which the classes implementing or extending it don't implement.
+	AbstractMethodAdded,
+}
+
+impl ChangeKind {
+	pub fn incompatibility(&self) -> Incompatibility {
+		match self {
+			// 13.4.9: the classes compiled against the old version keep the old value
+			ChangeKind::ConstantValueChanged |
+			// 13.4.5, 13.4.13 and 13.4.15: signatures are erased in the descriptors the classes link against
+			ChangeKind::ClassSignatureChanged | ChangeKind::FieldSignatureChanged | ChangeKind::MethodSignatureChanged |
+			// 13.4.21: the JVM doesn't check the exceptions thrown
+			ChangeKind::ExceptionAdded(_) |
+			// 13.4.12 and 13.5.3: only invoking the method on an old implementation fails, with an `AbstractMethodError`
+			ChangeKind::AbstractMethodAdded => Incompatibility::Source,
+			_ => Incompatibility::Binary,
+		}
+	}
+}
+
+impl Display for ChangeKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		let name = |class: &ClassName| String::from_utf8_lossy(class.as_bytes()).into_owned();
+		match self {
+			ChangeKind::ClassRemoved => write!(f, "class removed"),
+			ChangeKind::ClassLessAccessible => write!(f, "class no longer public"),
+			ChangeKind::ClassMadeAbstract => write!(f, "class made abstract"),
+			ChangeKind::ClassMadeFinal => write!(f, "class made final"),
+			ChangeKind::ClassMadeInterface => write!(f, "class changed to an interface"),
+			ChangeKind::InterfaceMadeClass => write!(f, "interface changed to a class"),
+			ChangeKind::SupertypeRemoved(class) => write!(f, "supertype {} removed", name(class)),
+			ChangeKind::ClassSignatureChanged => write!(f, "generic signature changed"),
+			ChangeKind::FieldRemoved => write!(f, "field removed"),
+			ChangeKind::FieldLessAccessible => write!(f, "field made less accessible"),
+			ChangeKind::FieldTypeChanged(descriptor) => write!(f, "field type changed to {}", String::from_utf8_lossy(&descriptor.to_bytes())),
+			ChangeKind::FieldMadeFinal => write!(f, "field made final"),
+			ChangeKind::FieldMadeStatic => write!(f, "field made static"),
+			ChangeKind::FieldMadeNonStatic => write!(f, "field no longer static"),
+			ChangeKind::FieldSignatureChanged => write!(f, "generic signature changed"),
+			ChangeKind::ConstantValueChanged => write!(f, "constant value changed"),
+			ChangeKind::MethodRemoved => write!(f, "method removed"),
+			ChangeKind::MethodDescriptorChanged(descriptor) => write!(f, "descriptor changed to {}", String::from_utf8_lossy(&descriptor.to_bytes())),
+			ChangeKind::MethodLessAccessible => write!(f, "method made less accessible"),
+			ChangeKind::MethodMadeAbstract => write!(f, "method made abstract"),
+			ChangeKind::MethodMadeFinal => write!(f, "method made final"),
+			ChangeKind::MethodMadeStatic => write!(f, "method made static"),
+			ChangeKind::MethodMadeNonStatic => write!(f, "method no longer static"),
+			ChangeKind::MethodSignatureChanged => write!(f, "generic signature changed"),
+			ChangeKind::ExceptionAdded(class) => write!(f, "throws {}", name(class)),
+			ChangeKind::AbstractMethodAdded => write!(f, "abstract method added"),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Change {
+	pub class: ClassName,
+	/// The member changed, `None` for changes to the class itself.
+	pub member: Option<Member>,
+	pub kind: ChangeKind,
+}
+
+/// Formats the change like `binary: java/lang/String.trim()Ljava/lang/String;: method removed`.
+impl Display for Change {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}: {}", self.kind.incompatibility().name(), String::from_utf8_lossy(self.class.as_bytes()))?;
+		match &self.member {
+			Some(Member::Field(name, descriptor)) => write!(f, ".{}:{}", String::from_utf8_lossy(name.as_bytes()), String::from_utf8_lossy(&descriptor.to_bytes()))?,
+			Some(Member::Method(name, descriptor)) => write!(f, ".{}{}", String::from_utf8_lossy(name.as_bytes()), String::from_utf8_lossy(&descriptor.to_bytes()))?,
+			None => {},
+		}
+		write!(f, ": {}", self.kind)
+	}
+}
+
+/// One version of a library: its classes, and the hierarchy of these and the libraries they use, to find the members they inherit.
+#[derive(Debug, Clone, Default)]
+pub struct Api {
+	hierarchy: ClassHierarchy,
+	classes: BTreeMap<ClassName, ClassFile>,
+}
+
+impl Api {
+	pub fn new() -> Api {
+		Api::default()
+	}
+
+	/// Adds a class of the library. Like on a class path, the first class added with a name wins, so these are to be added before the libraries.
+	pub fn add_class(&mut self, class: &ClassFile) -> bool {
+		if !self.hierarchy.add_class_file(class) {
+			return false;
+		}
+		// only the declarations are compared
+		let mut class = class.clone();
+		for method in &mut class.methods {
+			method.code = None;
+		}
+		self.classes.insert(class.this_class, class);
+		true
+	}
+
+	/// Adds a class of a library the library uses, which is only looked at for the members the classes of the library inherit.
+	pub fn add_library(&mut self, class: ClassEntry) -> bool {
+		self.hierarchy.add(class)
+	}
+}
+
+/// The ranks of the access levels, from private to public.
+fn access(is_public: bool, is_protected: bool, is_private: bool) -> u8 {
+	match (is_public, is_protected, is_private) {
+		(true, _, _) => 3,
+		(_, true, _) => 2,
+		(_, _, true) => 0,
+		_ => 1,
+	}
+}
+
+fn field_access(field: &FieldInfo) -> u8 {
+	access(field.access_flags.is_public, field.access_flags.is_protected, field.access_flags.is_private)
+}
+
+fn method_access(method: &MethodInfo) -> u8 {
+	access(method.access_flags.is_public, method.access_flags.is_protected, method.access_flags.is_private)
+}
+
+fn is_api_class(class: &ClassFile) -> bool {
+	class.access_flags.is_public && !class.access_flags.is_synthetic
+}
+
+/// Whether a member with the access rank is part of the API of `class`. Protected members are if the class can be extended.
+fn is_api_member(class: &ClassFile, access: u8) -> bool {
+	access == 3 || (access == 2 && !class.access_flags.is_final)
+}
+
+/// Compares the API of two versions of a library, returning the incompatible changes by class.
+pub fn compare(old: &Api, new: &Api) -> Vec<Change> {
+	let mut changes = Vec::new();
+	for (&name, old_class) in old.classes.iter().filter(|(_, class)| is_api_class(class)) {
+		let mut change = |member: Option<Member>, kind: ChangeKind| changes.push(Change { class: name, member, kind });
+		let Some(new_class) = new.classes.get(&name) else {
+			change(None, ChangeKind::ClassRemoved);
+			continue;
+		};
+		if !is_api_class(new_class) {
+			change(None, ChangeKind::ClassLessAccessible);
+			continue;
+		}
+
+		// 13.4.1 to 13.4.5
+		let (old_flags, new_flags) = (&old_class.access_flags, &new_class.access_flags);
+		match (old_flags.is_interface, new_flags.is_interface) {
+			(false, true) => change(None, ChangeKind::ClassMadeInterface),
+			(true, false) => change(None, ChangeKind::InterfaceMadeClass),
+			(false, false) if !old_flags.is_abstract && new_flags.is_abstract => change(None, ChangeKind::ClassMadeAbstract),
+			_ => {},
+		}
+		if !old_flags.is_final && new_flags.is_final {
+			change(None, ChangeKind::ClassMadeFinal);
+		}
+		let new_supertypes = new.hierarchy.supertypes(&name);
+		for supertype in old.hierarchy.supertypes(&name).difference(&new_supertypes) {
+			change(None, ChangeKind::SupertypeRemoved(*supertype));
+		}
+		if old_class.signature() != new_class.signature() {
+			change(None, ChangeKind::ClassSignatureChanged);
+		}
+
+		// 13.4.6 to 13.4.10
+		for old_field in old_class.fields.iter().filter(|field| !field.access_flags.is_synthetic && is_api_member(old_class, field_access(field))) {
+			let member = || Some(Member::Field(old_field.name, old_field.descriptor));
+			let Some(new_field) = new_class.fields.iter().find(|field| field.name == old_field.name && field.descriptor == old_field.descriptor) else {
+				if let Some(new_field) = new_class.fields.iter().find(|field| field.name == old_field.name) {
+					change(member(), ChangeKind::FieldTypeChanged(new_field.descriptor));
+				} else {
+					let inherited = new.hierarchy.resolve_field(&name, old_field.name, &old_field.descriptor)
+						.is_some_and(|(_, field)| {
+							let flags = &field.access_flags;
+							access(flags.is_public, flags.is_protected, flags.is_private) >= field_access(old_field) && flags.is_static == old_field.access_flags.is_static
+						});
+					if !inherited {
+						change(member(), ChangeKind::FieldRemoved);
+					}
+				}
+				continue;
+			};
+			if field_access(new_field) < field_access(old_field) {
+				change(member(), ChangeKind::FieldLessAccessible);
+			}
+			if !old_field.access_flags.is_final && new_field.access_flags.is_final {
+				change(member(), ChangeKind::FieldMadeFinal);
+			}
+			match (old_field.access_flags.is_static, new_field.access_flags.is_static) {
+				(false, true) => change(member(), ChangeKind::FieldMadeStatic),
+				(true, false) => change(member(), ChangeKind::FieldMadeNonStatic),
+				_ => {},
+			}
+			if old_field.signature() != new_field.signature() {
+				change(member(), ChangeKind::FieldSignatureChanged);
+			}
+			if old_field.constant_value.is_some() && old_field.constant_value != new_field.constant_value {
+				change(member(), ChangeKind::ConstantValueChanged);
+			}
+		}
+
+		// 13.4.12 to 13.4.23, and 13.5.3 to 13.5.6 for interfaces
+		let is_api_method = |class: &ClassFile, method: &MethodInfo| {
+			method.name.as_bytes() != b"<clinit>" && (!method.access_flags.is_synthetic || method.access_flags.is_bridge) && is_api_member(class, method_access(method))
+		};
+		let declares = |class: &ClassFile, method: &MethodInfo| class.methods.iter().any(|other| other.name == method.name && other.descriptor == method.descriptor);
+		for old_method in old_class.methods.iter().filter(|method| is_api_method(old_class, method)) {
+			let member = || Some(Member::Method(old_method.name, old_method.descriptor.clone()));
+			let Some(new_method) = new_class.methods.iter().find(|method| method.name == old_method.name && method.descriptor == old_method.descriptor) else {
+				let inherited = !old_method.name.as_bytes().starts_with(b"<") && new.hierarchy.resolve_method(&name, old_method.name, &old_method.descriptor)
+					.is_some_and(|(_, method)| {
+						let flags = &method.access_flags;
+						access(flags.is_public, flags.is_protected, flags.is_private) >= method_access(old_method) && flags.is_static == old_method.access_flags.is_static
+					});
+				if inherited {
+					continue;
+				}
+				// a method whose parameters or return type changed, as long as it isn't overloaded
+				let removed = old_class.methods.iter().filter(|method| method.name == old_method.name && !declares(new_class, method)).count();
+				let mut added = new_class.methods.iter().filter(|method| method.name == old_method.name && !declares(old_class, method));
+				match (removed, added.next(), added.next()) {
+					(1, Some(new_method), None) => change(member(), ChangeKind::MethodDescriptorChanged(new_method.descriptor.clone())),
+					_ => change(member(), ChangeKind::MethodRemoved),
+				}
+				continue;
+			};
+			let (old_flags, new_flags) = (&old_method.access_flags, &new_method.access_flags);
+			if method_access(new_method) < method_access(old_method) {
+				change(member(), ChangeKind::MethodLessAccessible);
+			}
+			if !old_flags.is_abstract && new_flags.is_abstract {
+				change(member(), ChangeKind::MethodMadeAbstract);
+			}
+			// a static method can't be overridden, only hidden
+			if !old_flags.is_final && new_flags.is_final && !new_flags.is_static && !old_class.access_flags.is_final {
+				change(member(), ChangeKind::MethodMadeFinal);
+			}
+			match (old_flags.is_static, new_flags.is_static) {
+				(false, true) => change(member(), ChangeKind::MethodMadeStatic),
+				(true, false) => change(member(), ChangeKind::MethodMadeNonStatic),
+				_ => {},
+			}
+			if old_method.signature() != new_method.signature() {
+				change(member(), ChangeKind::MethodSignatureChanged);
+			}
+			let old_exceptions: BTreeSet<_> = old_method.exceptions().iter().collect();
+			for exception in new_method.exceptions().iter().filter(|exception| !old_exceptions.contains(exception)) {
+				change(member(), ChangeKind::ExceptionAdded(*exception));
+			}
+		}
+
+		// only classes that can be extended or implemented outside the library have to implement new abstract methods
+		if !new_class.access_flags.is_final && (new_class.access_flags.is_interface || new_class.access_flags.is_abstract) {
+			for new_method in new_class.methods.iter().filter(|method| method.access_flags.is_abstract && is_api_method(new_class, method)) {
+				let declared_before = old.hierarchy.resolve_method(&name, new_method.name, &new_method.descriptor)
+					.is_some_and(|(_, method)| !method.access_flags.is_private);
+				if !declared_before {
+					change(Some(Member::Method(new_method.name, new_method.descriptor.clone())), ChangeKind::AbstractMethodAdded);
+				}
+			}
+		}
+	}
+	changes
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::{ClassFile, MethodInfo};
+	use crate::compat::{compare, Api, Incompatibility};
+	use crate::cp::attribute::{AttributeInfo, ExceptionsAttribute};
+	use crate::fixture::{class, field, method};
+	use crate::name::ClassName;
+
+	fn api(classes: &[ClassFile]) -> Api {
+		let mut api = Api::new();
+		for class in classes {
+			api.add_class(class);
+		}
+		api
+	}
+
+	#[test]
+	fn changes() {
+		let old = api(&[
+			class(0x0021, b"Base", b"java/lang/Object", &[], Vec::new(), vec![method(0x0001, b"<init>", b"()V"), method(0x0001, b"moved", b"()V")]),
+			class(0x0021, b"A", b"Base", &[], vec![field(0x0001, b"count", b"I"), field(0x0004, b"limit", b"I")], vec![
+				method(0x0001, b"<init>", b"()V"),
+				method(0x0001, b"moved", b"()V"),
+				method(0x0001, b"run", b"()V"),
+				method(0x0001, b"size", b"()I"),
+				method(0x0002, b"internal", b"()V"),
+				method(0x0009, b"create", b"()LA;"),
+			]),
+			class(0x0601, b"I", b"java/lang/Object", &[], Vec::new(), vec![method(0x0401, b"get", b"()I")]),
+			class(0x0021, b"Removed", b"java/lang/Object", &[], Vec::new(), Vec::new()),
+			class(0x0020, b"Internal", b"java/lang/Object", &[], Vec::new(), Vec::new()),
+		]);
+		let new = api(&[
+			class(0x0021, b"Base", b"java/lang/Object", &[], Vec::new(), vec![method(0x0001, b"<init>", b"()V"), method(0x0001, b"moved", b"()V")]),
+			class(0x0031, b"A", b"java/lang/Object", &[], vec![field(0x0001, b"count", b"J"), field(0x0004, b"limit", b"I")], vec![
+				method(0x0001, b"<init>", b"()V"),
+				MethodInfo {
+					attributes: vec![AttributeInfo::Exceptions(ExceptionsAttribute { exception_table: vec![ClassName::from(b"java/io/IOException")] })],
+					..method(0x0001, b"run", b"()V")
+				},
+				method(0x0001, b"size", b"()J"),
+				method(0x0009, b"create", b"()LA;"),
+			]),
+			class(0x0601, b"I", b"java/lang/Object", &[], Vec::new(), vec![method(0x0401, b"get", b"()I"), method(0x0401, b"put", b"(I)V")]),
+		]);
+		let changes: Vec<_> = compare(&old, &new).iter().map(|change| change.to_string()).collect();
+		assert_eq!(changes, [
+			"binary: A: class made final",
+			"binary: A: supertype Base removed",
+			"binary: A.count:I: field type changed to J",
+			"binary: A.moved()V: method removed",
+			"source: A.run()V: throws java/io/IOException",
+			"binary: A.size()I: descriptor changed to ()J",
+			"source: I.put(I)V: abstract method added",
+			"binary: Removed: class removed",
+		]);
+		let binary = compare(&old, &new).iter().filter(|change| change.kind.incompatibility() == Incompatibility::Binary).count();
+		assert_eq!(binary, 6);
+	}
+}
@@ -1,19 +1,14 @@
+use alloc::vec::Vec;
 use anyhow::Result;
-use std::io::Read;
+use crate::io::Read;
 use anyhow::bail;
 use itertools::{Either, Itertools};
-use crate::cp::{MethodHandleInfo, NameAndType, Pool, PoolEntry, Utf8Info};
-use crate::descriptor::MethodDescriptor;
+use crate::cp::{MethodHandleInfo, NameAndType, Pool, PoolEntry};
+use crate::descriptor::{FieldDescriptor, MethodDescriptor};
 use crate::MyRead;
 use crate::instruction::Instructions;
-use crate::name::{ClassName, MethodName};
-
-// TODO: remove
-type LongInfo = u32;
-type FloatInfo = u32;
-type DoubleInfo = u32;
-type IntegerInfo = u32;
-type StringInfo = u32;
+use crate::name::{ClassName, FieldName, MethodName};
+use crate::smap::Smap;
 
 fn check_attribute_length<R: Read>(reader: &mut R, length: u32) -> Result<()> {
 	let len = reader.read_u32()?;
@@ -24,13 +19,16 @@ fn check_attribute_length<R: Read>(reader: &mut R, length: u32) -> Result<()> {
 	}
 }
 
+/// The value of a constant field. Floating point values are kept as their bits, like in
+/// [Opcode::LdcFloat](crate::instruction::opcode::Opcode::LdcFloat).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ConstantValueAttribute { // 4.7.2
-	Long(LongInfo),
-	Float(FloatInfo),
-	Double(DoubleInfo),
-	Integer(IntegerInfo),
-	String(StringInfo),
+	Long(i64),
+	Float(u32),
+	Double(u64),
+	Integer(i32),
+	/// A string in modified UTF-8.
+	String(Vec<u8>),
 }
 impl ConstantValueAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<ConstantValueAttribute> {
@@ -39,12 +37,12 @@ impl ConstantValueAttribute {
 		let index = reader.read_u16_as_usize()?;
 
 		match pool.get::<&PoolEntry>(index)? {
-			PoolEntry::Long { high, low } => Ok(ConstantValueAttribute::Long(high.clone())),
-			PoolEntry::Float(float) => Ok(ConstantValueAttribute::Float(float.clone())),
-			PoolEntry::Double { high, low } => Ok(ConstantValueAttribute::Double(high.clone())),
-			PoolEntry::Integer(integer) => Ok(ConstantValueAttribute::Integer(integer.clone())),
-			PoolEntry::String(string) => Ok(ConstantValueAttribute::String(0)), // TODO: implement
-			tag => bail!("expected Long/Float/Double/Integer, but got {tag:?}"),
+			PoolEntry::Long { high, low } => Ok(ConstantValueAttribute::Long(((*high as u64) << 32 | *low as u64) as i64)),
+			PoolEntry::Float(bits) => Ok(ConstantValueAttribute::Float(*bits)),
+			PoolEntry::Double { high, low } => Ok(ConstantValueAttribute::Double((*high as u64) << 32 | *low as u64)),
+			PoolEntry::Integer(value) => Ok(ConstantValueAttribute::Integer(*value as i32)),
+			PoolEntry::String(string) => Ok(ConstantValueAttribute::String(pool.get::<&Vec<u8>>(*string)?.clone())),
+			tag => bail!("expected Long/Float/Double/Integer/String, but got {tag:?}"),
 		}
 	}
 }
@@ -107,7 +105,7 @@ impl CodeAttribute {
 			1 => {
 				stack_map_tables.into_iter().next().unwrap()
 			},
-			_ => todo!("there may at max be one stack map frame!"),
+			_ => bail!("found multiple StackMapTable attributes"),
 		};
 
 		Ok(CodeAttribute {
@@ -120,6 +118,75 @@ impl CodeAttribute {
 			stack_map_table,
 		})
 	}
+
+	/// Returns the source line of the instruction at `offset`, the one of the closest entry of the line number table starting at or before it.
+	pub fn line_for_offset(&self, offset: usize) -> Option<u16> {
+		// the table may be in any order, and javac repeats the same start_pc at times, in which case the later entry wins
+		self.line_number_table.iter()
+			.filter(|entry| entry.start_pc <= offset)
+			.max_by_key(|entry| entry.start_pc)
+			.map(|entry| entry.line_number)
+	}
+
+	/// Returns the offsets at which code for the source line `line` starts, in ascending order. There's one offset per entry of the line number table, a
+	/// line split by a loop for example has several.
+	pub fn offsets_for_line(&self, line: u16) -> Vec<usize> {
+		let mut offsets: Vec<usize> = self.line_number_table.iter()
+			.filter(|entry| entry.line_number == line)
+			.map(|entry| entry.start_pc)
+			.collect();
+		offsets.sort_unstable();
+		offsets.dedup();
+		offsets
+	}
+
+	/// Returns the local variables from the `LocalVariableTable` attributes whose scope covers `offset`, sorted by their index. Their generic signature is
+	/// taken from the matching entry of a `LocalVariableTypeTable` attribute.
+	pub fn locals_at(&self, offset: usize) -> Vec<LocalVariable<'_>> {
+		let covers = |start_pc: usize, end_pc: usize| start_pc <= offset && offset < end_pc;
+		let signatures: Vec<&LocalVariableTypeTableEntry> = self.attributes.iter()
+			.filter_map(|attribute| match attribute {
+				AttributeInfo::LocalVariableTypeTable(table) => Some(&table.local_variable_type_table),
+				_ => None,
+			})
+			.flatten()
+			.filter(|entry| covers(entry.start_pc, entry.end_pc))
+			.collect();
+
+		let mut locals: Vec<LocalVariable> = self.attributes.iter()
+			.filter_map(|attribute| match attribute {
+				AttributeInfo::LocalVariableTable(table) => Some(&table.local_variable_table),
+				_ => None,
+			})
+			.flatten()
+			.filter(|entry| covers(entry.start_pc, entry.end_pc))
+			.map(|entry| LocalVariable {
+				index: entry.lv_index,
+				name: &entry.name,
+				descriptor: &entry.descriptor,
+				signature: signatures.iter()
+					.find(|signature| signature.lv_index == entry.lv_index && signature.start_pc == entry.start_pc && signature.name == entry.name)
+					.map(|signature| &signature.signature[..]),
+				start_pc: entry.start_pc,
+				end_pc: entry.end_pc,
+			})
+			.collect();
+		locals.sort_by_key(|local| local.index);
+		locals
+	}
+}
+
+/// A local variable in scope at some offset, see [CodeAttribute::locals_at].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalVariable<'a> {
+	pub index: u16,
+	pub name: &'a FieldName,
+	pub descriptor: &'a FieldDescriptor,
+	/// The generic signature, if the variable has a type using type variables or parameterized types.
+	pub signature: Option<&'a [u8]>,
+	/// The range of offsets the variable is in scope.
+	pub start_pc: usize,
+	pub end_pc: usize,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -433,7 +500,7 @@ impl StackMapFrame {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExceptionsAttribute { // 4.7.5
-	exception_table: Vec<ClassName>,
+	pub exception_table: Vec<ClassName>,
 }
 impl ExceptionsAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<ExceptionsAttribute> {
@@ -450,7 +517,7 @@ impl ExceptionsAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InnerClassesAttribute { // 4.7.6
-	classes: Vec<InnerClassesAttributeClassesElement>,
+	pub classes: Vec<InnerClassesAttributeClassesElement>,
 }
 impl InnerClassesAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<InnerClassesAttribute> {
@@ -466,38 +533,53 @@ impl InnerClassesAttribute {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct InnerClassesAttributeClassesElement { // 4.7.6, classes
-	inner_class: ClassName,
-	outer_class: Option<ClassName>,
-	inner_name: Option<ClassName>,
-	inner_class_access_flags: u16,
+pub struct InnerClassesAttributeClassesElement { // 4.7.6, classes
+	pub inner_class: ClassName,
+	/// The class this one is a member of, `None` for local and anonymous classes.
+	pub outer_class: Option<ClassName>,
+	/// The simple name as given in the source, `None` for anonymous classes.
+	pub inner_name: Option<Vec<u8>>,
+	/// The flags as given in the source, with ACC_PRIVATE, ACC_PROTECTED and ACC_STATIC that can't be expressed in the flags of the class itself.
+	pub inner_class_access_flags: u16,
 }
 impl InnerClassesAttributeClassesElement {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<InnerClassesAttributeClassesElement> {
 		Ok(InnerClassesAttributeClassesElement {
 			inner_class: pool.get(reader.read_u16_as_usize()?)?,
 			outer_class: pool.get(reader.read_u16_as_usize()?)?,
-			inner_name: pool.get(reader.read_u16_as_usize()?)?,
+			inner_name: pool.get::<Option<&Vec<u8>>>(reader.read_u16_as_usize()?)?.cloned(),
 			inner_class_access_flags: reader.read_u16()?,
 		})
 	}
+
+	pub fn is_anonymous(&self) -> bool {
+		self.inner_name.is_none()
+	}
+
+	/// Returns whether the class is declared in a block, like a method body. Anonymous classes are not counted as local.
+	pub fn is_local(&self) -> bool {
+		self.outer_class.is_none() && self.inner_name.is_some()
+	}
+
+	pub fn is_member(&self) -> bool {
+		self.outer_class.is_some()
+	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EnclosingMethodAttribute { // 4.7.7
-	class: ClassName,
-	name: MethodName,
-	descriptor: MethodDescriptor
+	pub class: ClassName,
+	/// The name and descriptor of the method the class is declared in, `None` if it's declared in an initializer of a field.
+	pub method: Option<(MethodName, MethodDescriptor)>,
 }
 impl EnclosingMethodAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<EnclosingMethodAttribute> {
 		check_attribute_length(reader, 4)?;
 		let class = pool.get(reader.read_u16_as_usize()?)?;
-		let name_and_type: NameAndType<_, _> = pool.get(reader.read_u16_as_usize()?)?;
+		let name_and_type: Option<NameAndType<_, _>> = pool.get(reader.read_u16_as_usize()?)?;
 		Ok(EnclosingMethodAttribute {
 			class,
-			name: name_and_type.name,
-			descriptor: name_and_type.descriptor,
+			method: name_and_type.map(|name_and_type| (name_and_type.name, name_and_type.descriptor)),
 		})
 	}
 }
@@ -513,33 +595,35 @@ impl SyntheticAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SignatureAttribute { // 4.7.9
-	signature: Utf8Info,
+	/// The class, method or field signature, in modified UTF-8.
+	pub signature: Vec<u8>,
 }
 impl SignatureAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<SignatureAttribute> {
 		check_attribute_length(reader, 2)?;
 		Ok(SignatureAttribute {
-			signature: pool.get(reader.read_u16_as_usize()?)?,
+			signature: pool.get::<&Vec<u8>>(reader.read_u16_as_usize()?)?.clone(),
 		})
 	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SourceFileAttribute { // 4.7.10
-	sourcefile: Utf8Info,
+	/// The name of the source file without any directories, in modified UTF-8.
+	pub sourcefile: Vec<u8>,
 }
 impl SourceFileAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<SourceFileAttribute> {
 		check_attribute_length(reader, 2)?;
 		Ok(SourceFileAttribute {
-			sourcefile: pool.get(reader.read_u16_as_usize()?)?,
+			sourcefile: pool.get::<&Vec<u8>>(reader.read_u16_as_usize()?)?.clone(),
 		})
 	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SourceDebugExtensionAttribute { // 4.7.11
-	debug_extension: Vec<u8>,
+	pub debug_extension: Vec<u8>,
 }
 impl SourceDebugExtensionAttribute {
 	fn parse<R: Read>(reader: &mut R) -> Result<SourceDebugExtensionAttribute> {
@@ -550,11 +634,16 @@ impl SourceDebugExtensionAttribute {
 			)?,
 		})
 	}
+
+	/// Parses the debug extension as a JSR-45 source map, which is what compilers put there in practice.
+	pub fn smap(&self) -> Result<Smap> {
+		Smap::parse(&self.debug_extension)
+	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LineNumberTableAttribute { // 4.7.12
-	line_number_table: Vec<LineNumberTableEntry>,
+	pub line_number_table: Vec<LineNumberTableEntry>,
 }
 impl LineNumberTableAttribute {
 	fn parse<R: Read>(reader: &mut R) -> Result<LineNumberTableAttribute> {
@@ -570,8 +659,8 @@ impl LineNumberTableAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LineNumberTableEntry { // 4.7.12, line_number_table
-	start_pc: usize,
-	line_number: u16,
+	pub start_pc: usize,
+	pub line_number: u16,
 }
 impl LineNumberTableEntry {
 	fn parse<R: Read>(reader: &mut R) -> Result<LineNumberTableEntry> {
@@ -584,7 +673,7 @@ impl LineNumberTableEntry {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LocalVariableTableAttribute { // 4.7.13
-	local_variable_table: Vec<LocalVariableTableEntry>,
+	pub local_variable_table: Vec<LocalVariableTableEntry>,
 }
 impl LocalVariableTableAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<LocalVariableTableAttribute> {
@@ -600,11 +689,11 @@ impl LocalVariableTableAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LocalVariableTableEntry { // 4.7.13, local_variable_table
-	start_pc: usize,
-	end_pc: usize,
-	name: Utf8Info,
-	descriptor: Utf8Info,
-	lv_index: u16,
+	pub start_pc: usize,
+	pub end_pc: usize,
+	pub name: FieldName,
+	pub descriptor: FieldDescriptor,
+	pub lv_index: u16,
 }
 impl LocalVariableTableEntry {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<LocalVariableTableEntry> {
@@ -621,7 +710,7 @@ impl LocalVariableTableEntry {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LocalVariableTypeTableAttribute { // 4.7.14
-	local_variable_type_table: Vec<LocalVariableTypeTableEntry>,
+	pub local_variable_type_table: Vec<LocalVariableTypeTableEntry>,
 }
 impl LocalVariableTypeTableAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<LocalVariableTypeTableAttribute> {
@@ -637,11 +726,12 @@ impl LocalVariableTypeTableAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LocalVariableTypeTableEntry { // 4.7.14, local_variable_type_table
-	start_pc: usize,
-	end_pc: usize,
-	name: Utf8Info,
-	signature: Utf8Info,
-	lv_index: u16,
+	pub start_pc: usize,
+	pub end_pc: usize,
+	pub name: FieldName,
+	/// The field signature of the variable, in modified UTF-8.
+	pub signature: Vec<u8>,
+	pub lv_index: u16,
 }
 impl LocalVariableTypeTableEntry {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<LocalVariableTypeTableEntry> {
@@ -650,7 +740,7 @@ impl LocalVariableTypeTableEntry {
 		Ok(LocalVariableTypeTableEntry {
 			start_pc, end_pc,
 			name: pool.get(reader.read_u16_as_usize()?)?,
-			signature: pool.get(reader.read_u16_as_usize()?)?,
+			signature: pool.get::<&Vec<u8>>(reader.read_u16_as_usize()?)?.clone(),
 			lv_index: reader.read_u16()?,
 		})
 	}
@@ -667,7 +757,7 @@ impl DeprecatedAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RuntimeVisibleAnnotationsAttribute { // 4.7.16
-	annotations: Vec<Annotation>,
+	pub annotations: Vec<Annotation>,
 }
 impl RuntimeVisibleAnnotationsAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<RuntimeVisibleAnnotationsAttribute> {
@@ -683,89 +773,198 @@ impl RuntimeVisibleAnnotationsAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Annotation { // 4.7.16, annotations
-	annotation_type: Utf8Info,
-	element_value_pairs: Vec<AnnotationElementValuePair>,
+	/// The field descriptor of the annotation interface, like `Ljava/lang/Deprecated;`.
+	pub annotation_type: Vec<u8>,
+	pub element_value_pairs: Vec<AnnotationElementValuePair>,
 }
 impl Annotation {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<Annotation> {
 		Ok(Annotation {
-			annotation_type: pool.get(reader.read_u16_as_usize()?)?,
+			annotation_type: pool.get::<&Vec<u8>>(reader.read_u16_as_usize()?)?.clone(),
 			element_value_pairs: reader.read_vec(
 				|r| r.read_u16_as_usize(),
 				|r| AnnotationElementValuePair::parse(r, pool)
 			)?,
 		})
 	}
+
+	/// Returns whether the annotation has the type with the field descriptor `descriptor`, like `Ljava/lang/Deprecated;`.
+	pub fn is(&self, descriptor: &str) -> bool {
+		self.annotation_type == descriptor.as_bytes()
+	}
+
+	/// Returns the value of the element `name`. Elements left at their default value are not stored in the class file, see
+	/// [AnnotationDefaultAttribute] for these.
+	pub fn get(&self, name: &str) -> Option<&AnnotationElementValue> {
+		self.element_value_pairs.iter()
+			.find(|pair| pair.element_name == name.as_bytes())
+			.map(|pair| &pair.value)
+	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct AnnotationElementValuePair { // 4.7.16, element_value_pairs
-	element_name: Utf8Info,
-	value: AnnotationElementValue,
+pub struct AnnotationElementValuePair { // 4.7.16, element_value_pairs
+	pub element_name: Vec<u8>,
+	pub value: AnnotationElementValue,
 }
 impl AnnotationElementValuePair {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<AnnotationElementValuePair> {
 		Ok(AnnotationElementValuePair {
-			element_name: pool.get(reader.read_u16_as_usize()?)?,
+			element_name: pool.get::<&Vec<u8>>(reader.read_u16_as_usize()?)?.clone(),
 			value: AnnotationElementValue::parse(reader, pool)?,
 		})
 	}
 }
 
+/// The value of an annotation element, with the constants resolved from the constant pool. Floating point values are kept as their bits, like in
+/// [Opcode::LdcFloat](crate::instruction::opcode::Opcode::LdcFloat).
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AnnotationElementValue { // 4.7.16.1, value
-	// TODO: this should also be split up, and use the constant_pool::* structures instead of index
-	ConstantValueIndex { // B, C, D, F, I, J, S, Z, s
-		const_value_index: u16,
-	},
-	EnumConstValue { // e
-		type_name_index: u16,
-		const_name_index: u16,
+	Byte(i8), // B
+	Char(u16), // C
+	Double(u64), // D
+	Float(u32), // F
+	Int(i32), // I
+	Long(i64), // J
+	Short(i16), // S
+	Boolean(bool), // Z
+	/// A string in modified UTF-8.
+	String(Vec<u8>), // s
+	Enum { // e
+		/// The field descriptor of the enum class, like `Ljava/lang/annotation/RetentionPolicy;`.
+		type_name: Vec<u8>,
+		const_name: Vec<u8>,
 	},
-	ClassInfoIndex { // c
-		class_info_index: u16,
-	},
-	AnnotationValue { // @
-		annotation_value: Annotation,
-	},
-	ArrayValue { // [
-		values: Vec<AnnotationElementValue>,
-	}
+	/// A class literal, given as return descriptor, like `Ljava/lang/String;`, `[I` or `V` for `void.class`.
+	Class(Vec<u8>), // c
+	Annotation(Annotation), // @
+	Array(Vec<AnnotationElementValue>), // [
 }
 impl AnnotationElementValue {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<Self> {
 		let tag = reader.read_u8()?;
 
+		let int = |index: usize| -> Result<i32> {
+			match pool.get::<&PoolEntry>(index)? {
+				PoolEntry::Integer(value) => Ok(*value as i32),
+				entry => bail!("expected an integer constant for an annotation element of type {:?}, got {entry:?}", tag as char),
+			}
+		};
+		let utf8 = |index: usize| -> Result<Vec<u8>> {
+			Ok(pool.get::<&Vec<u8>>(index)?.clone())
+		};
+
 		Ok(match tag {
-			b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => Self::ConstantValueIndex {
-				const_value_index: reader.read_u16()?,
-			},
-			b'e' => Self::EnumConstValue {
-				type_name_index: reader.read_u16()?,
-				const_name_index: reader.read_u16()?,
-			},
-			b'c' => Self::ClassInfoIndex {
-				class_info_index: reader.read_u16()?,
+			b'B' => Self::Byte(int(reader.read_u16_as_usize()?)? as i8),
+			b'C' => Self::Char(int(reader.read_u16_as_usize()?)? as u16),
+			b'S' => Self::Short(int(reader.read_u16_as_usize()?)? as i16),
+			b'Z' => Self::Boolean(int(reader.read_u16_as_usize()?)? != 0),
+			b'I' => Self::Int(int(reader.read_u16_as_usize()?)?),
+			b'D' | b'F' | b'J' => match (tag, pool.get::<&PoolEntry>(reader.read_u16_as_usize()?)?) {
+				(b'D', PoolEntry::Double { high, low }) => Self::Double((*high as u64) << 32 | *low as u64),
+				(b'F', PoolEntry::Float(bits)) => Self::Float(*bits),
+				(b'J', PoolEntry::Long { high, low }) => Self::Long(((*high as u64) << 32 | *low as u64) as i64),
+				(_, entry) => bail!("constant {entry:?} doesn't match the annotation element type {:?}", tag as char),
 			},
-			b'@' => Self::AnnotationValue {
-				annotation_value: Annotation::parse(reader, pool)?,
+			b's' => Self::String(utf8(reader.read_u16_as_usize()?)?),
+			b'e' => Self::Enum {
+				type_name: utf8(reader.read_u16_as_usize()?)?,
+				const_name: utf8(reader.read_u16_as_usize()?)?,
 			},
+			b'c' => Self::Class(utf8(reader.read_u16_as_usize()?)?),
+			b'@' => Self::Annotation(Annotation::parse(reader, pool)?),
 			b'[' => {
-				Self::ArrayValue {
-					values: reader.read_vec(
-						|r| r.read_u16_as_usize(),
-						|r| AnnotationElementValue::parse(r, pool)
-					)?,
-				}
+				Self::Array(reader.read_vec(
+					|r| r.read_u16_as_usize(),
+					|r| AnnotationElementValue::parse(r, pool)
+				)?)
 			},
 			tag => bail!("unknown annotation element value tag: {tag}"),
 		})
 	}
+
+	/// Returns the value of an `int`, `short`, `char` or `byte` element.
+	pub fn as_int(&self) -> Option<i32> {
+		match *self {
+			Self::Int(value) => Some(value),
+			Self::Short(value) => Some(value as i32),
+			Self::Char(value) => Some(value as i32),
+			Self::Byte(value) => Some(value as i32),
+			_ => None,
+		}
+	}
+
+	pub fn as_long(&self) -> Option<i64> {
+		match *self {
+			Self::Long(value) => Some(value),
+			_ => self.as_int().map(i64::from),
+		}
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match *self {
+			Self::Boolean(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	pub fn as_float(&self) -> Option<f32> {
+		match *self {
+			Self::Float(bits) => Some(f32::from_bits(bits)),
+			_ => None,
+		}
+	}
+
+	pub fn as_double(&self) -> Option<f64> {
+		match *self {
+			Self::Double(bits) => Some(f64::from_bits(bits)),
+			Self::Float(bits) => Some(f32::from_bits(bits) as f64),
+			_ => None,
+		}
+	}
+
+	/// Returns the value of a `String` element, in modified UTF-8.
+	pub fn as_string(&self) -> Option<&[u8]> {
+		match self {
+			Self::String(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	/// Returns the type descriptor and the name of the constant of an enum element.
+	pub fn as_enum(&self) -> Option<(&[u8], &[u8])> {
+		match self {
+			Self::Enum { type_name, const_name } => Some((type_name, const_name)),
+			_ => None,
+		}
+	}
+
+	/// Returns the return descriptor of a class literal.
+	pub fn as_class(&self) -> Option<&[u8]> {
+		match self {
+			Self::Class(descriptor) => Some(descriptor),
+			_ => None,
+		}
+	}
+
+	pub fn as_annotation(&self) -> Option<&Annotation> {
+		match self {
+			Self::Annotation(annotation) => Some(annotation),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&[AnnotationElementValue]> {
+		match self {
+			Self::Array(values) => Some(values),
+			_ => None,
+		}
+	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RuntimeInvisibleAnnotationsAttribute { // 4.7.17
-	annotations: Vec<Annotation>,
+	pub annotations: Vec<Annotation>,
 }
 impl RuntimeInvisibleAnnotationsAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<RuntimeInvisibleAnnotationsAttribute> {
@@ -781,14 +980,14 @@ impl RuntimeInvisibleAnnotationsAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RuntimeVisibleParameterAnnotationsAttribute { // 4.7.18
-	parameter_annotations: Vec<ParameterAnnotationPair>,
+	pub parameter_annotations: Vec<ParameterAnnotationPair>,
 }
 impl RuntimeVisibleParameterAnnotationsAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<RuntimeVisibleParameterAnnotationsAttribute> {
 		let _attribute_length = reader.read_u32()?;
 		Ok(RuntimeVisibleParameterAnnotationsAttribute {
 			parameter_annotations: reader.read_vec(
-				|r| r.read_u16_as_usize(),
+				|r| r.read_u8_as_usize(),
 				|r| ParameterAnnotationPair::parse(r, pool)
 			)?,
 		})
@@ -797,7 +996,7 @@ impl RuntimeVisibleParameterAnnotationsAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParameterAnnotationPair { // 4.7.18, parameter_annotations
-	annotations: Vec<Annotation>,
+	pub annotations: Vec<Annotation>,
 }
 impl ParameterAnnotationPair {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<ParameterAnnotationPair> {
@@ -812,7 +1011,7 @@ impl ParameterAnnotationPair {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RuntimeInvisibleParameterAnnotationsAttribute { // 4.7.19
-	parameter_annotations: Vec<ParameterAnnotationPair>,
+	pub parameter_annotations: Vec<ParameterAnnotationPair>,
 }
 impl RuntimeInvisibleParameterAnnotationsAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<RuntimeInvisibleParameterAnnotationsAttribute> {
@@ -828,7 +1027,7 @@ impl RuntimeInvisibleParameterAnnotationsAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AnnotationDefaultAttribute { // 4.7.20
-	default_value: AnnotationElementValue,
+	pub default_value: AnnotationElementValue,
 }
 impl AnnotationDefaultAttribute {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<AnnotationDefaultAttribute> {
@@ -841,9 +1040,12 @@ impl AnnotationDefaultAttribute {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BootstrapMethodsAttribute { // 4.7.21
-	bootstrap_methods: Vec<BootstrapMethodsAttributeEntry>,
+	pub bootstrap_methods: Vec<BootstrapMethodsAttributeEntry>,
 }
 impl BootstrapMethodsAttribute {
+	pub(crate) fn count(&self) -> usize {
+		self.bootstrap_methods.len()
+	}
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<BootstrapMethodsAttribute> {
 		let _attribute_length = reader.read_u32()?;
 		Ok(BootstrapMethodsAttribute {
@@ -856,14 +1058,15 @@ impl BootstrapMethodsAttribute {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct BootstrapMethodsAttributeEntry { // 4.7.21, bootstrap_methods
-	boostrap_method: MethodHandleInfo,
-	bootstrap_arguments: Vec<BootstrapMethodArgument>,
+pub struct BootstrapMethodsAttributeEntry { // 4.7.21, bootstrap_methods
+	pub bootstrap_method: MethodHandleInfo,
+	/// The static arguments, passed after the lookup, name and type of the call site.
+	pub bootstrap_arguments: Vec<BootstrapMethodArgument>,
 }
 impl BootstrapMethodsAttributeEntry {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<BootstrapMethodsAttributeEntry> {
 		Ok(BootstrapMethodsAttributeEntry {
-			boostrap_method: pool.get(reader.read_u16_as_usize()?)?,
+			bootstrap_method: pool.get(reader.read_u16_as_usize()?)?,
 			bootstrap_arguments: reader.read_vec(
 				|r| r.read_u16_as_usize(),
 				|r| BootstrapMethodArgument::parse(r, pool)
@@ -872,14 +1075,16 @@ impl BootstrapMethodsAttributeEntry {
 	}
 }
 
+/// A static argument of a bootstrap method. Floating point values are kept as their bits.
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum BootstrapMethodArgument {
-	String(StringInfo),
+pub enum BootstrapMethodArgument {
+	/// A string in modified UTF-8.
+	String(Vec<u8>),
 	Class(ClassName),
-	Integer(IntegerInfo),
-	Long(LongInfo),
-	Float(FloatInfo),
-	Double(DoubleInfo),
+	Integer(i32),
+	Long(i64),
+	Float(u32),
+	Double(u64),
 	MethodHandle(MethodHandleInfo),
 	MethodType(MethodDescriptor),
 }
@@ -888,22 +1093,22 @@ impl BootstrapMethodArgument {
 		let index = reader.read_u16_as_usize()?;
 
 		match pool.get::<&PoolEntry>(index)? {
-			PoolEntry::String(_)          => Ok(Self::String(0)), // TODO: impl
+			PoolEntry::String(string)     => Ok(Self::String(pool.get::<&Vec<u8>>(*string)?.clone())),
 			PoolEntry::ClassName(_)       => Ok(Self::Class       (pool.get(index)?)),
 			PoolEntry::MethodHandle(_, _) => Ok(Self::MethodHandle(pool.get(index)?)),
-			PoolEntry::MethodType(_)      => Ok(Self::MethodType  (pool.get(index)?)),
-			PoolEntry::Integer(..)        => Ok(Self::Integer(0)),
-			PoolEntry::Long { .. }        => Ok(Self::Long(0)),
-			PoolEntry::Float(..)          => Ok(Self::Float(0)),
-			PoolEntry::Double { .. }      => Ok(Self::Double(0)),
-			tag => bail!("expected Long/Float/Double/Integer, but got {tag:?}"), // TODO: message is wrong???
+			PoolEntry::MethodType(descriptor) => Ok(Self::MethodType(pool.get(*descriptor)?)),
+			PoolEntry::Integer(value)     => Ok(Self::Integer(*value as i32)),
+			PoolEntry::Long { high, low } => Ok(Self::Long(((*high as u64) << 32 | *low as u64) as i64)),
+			PoolEntry::Float(bits)        => Ok(Self::Float(*bits)),
+			PoolEntry::Double { high, low } => Ok(Self::Double((*high as u64) << 32 | *low as u64)),
+			tag => bail!("expected a loadable constant as bootstrap method argument, but got {tag:?}"),
 		}
 	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct MethodParametersAttribute {
-	parameters: Vec<MethodParameterEntry>,
+pub struct MethodParametersAttribute { // 4.7.24
+	pub parameters: Vec<MethodParameterEntry>,
 }
 
 impl MethodParametersAttribute {
@@ -919,15 +1124,16 @@ impl MethodParametersAttribute {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct MethodParameterEntry {
-	name: Option<Utf8Info>,
-	access_flags: MethodParameterAccessFlags,
+pub struct MethodParameterEntry { // 4.7.24, parameters
+	/// The name of the parameter in modified UTF-8, `None` for a formal parameter without a name.
+	pub name: Option<Vec<u8>>,
+	pub access_flags: MethodParameterAccessFlags,
 }
 
 impl MethodParameterEntry {
 	fn parse<R: Read>(reader: &mut R, pool: &Pool) -> Result<MethodParameterEntry> {
 		Ok(MethodParameterEntry {
-			name: pool.get(reader.read_u16_as_usize()?)?,
+			name: pool.get::<Option<&Vec<u8>>>(reader.read_u16_as_usize()?)?.cloned(),
 			access_flags: MethodParameterAccessFlags::parse(reader.read_u16()?)?,
 		})
 	}
@@ -951,6 +1157,14 @@ impl MethodParameterAccessFlags {
 			is_final, is_synthetic, is_mandated,
 		})
 	}
+
+	pub fn to_flags(&self) -> u16 {
+		let mut access_flags = 0;
+		if self.is_final     { access_flags |= 0x0010; }
+		if self.is_synthetic { access_flags |= 0x1000; }
+		if self.is_mandated  { access_flags |= 0x8000; }
+		access_flags
+	}
 }
 
 macro_rules! try_from_enum_impl {
@@ -1019,6 +1233,34 @@ pub enum AttributeInfo { // 4.7
 }
 
 impl AttributeInfo {
+	/// Returns the name the attribute has in the class file, like `SourceFile`.
+	pub fn name(&self) -> &[u8] {
+		match self {
+			Self::ConstantValue(_) => b"ConstantValue",
+			Self::Code(_) => b"Code",
+			Self::StackMapTable(_) => b"StackMapTable",
+			Self::Exceptions(_) => b"Exceptions",
+			Self::InnerClasses(_) => b"InnerClasses",
+			Self::EnclosingMethod(_) => b"EnclosingMethod",
+			Self::Synthetic(_) => b"Synthetic",
+			Self::Signature(_) => b"Signature",
+			Self::SourceFile(_) => b"SourceFile",
+			Self::SourceDebugExtension(_) => b"SourceDebugExtension",
+			Self::LineNumberTable(_) => b"LineNumberTable",
+			Self::LocalVariableTable(_) => b"LocalVariableTable",
+			Self::LocalVariableTypeTable(_) => b"LocalVariableTypeTable",
+			Self::Deprecated(_) => b"Deprecated",
+			Self::RuntimeVisibleAnnotations(_) => b"RuntimeVisibleAnnotations",
+			Self::RuntimeInvisibleAnnotations(_) => b"RuntimeInvisibleAnnotations",
+			Self::RuntimeVisibleParameterAnnotations(_) => b"RuntimeVisibleParameterAnnotations",
+			Self::RuntimeInvisibleParameterAnnotations(_) => b"RuntimeInvisibleParameterAnnotations",
+			Self::AnnotationDefault(_) => b"AnnotationDefault",
+			Self::BootstrapMethods(_) => b"BootstrapMethods",
+			Self::MethodParameters(_) => b"MethodParameters",
+			Self::Unknown { name, .. } => name,
+		}
+	}
+
 	pub fn parse<'a, R: Read>(reader: &mut R, pool: &'a Pool) -> Result<Self> {
 		let name: &'a Vec<u8> = pool.get(reader.read_u16_as_usize()?)?;
 		Ok(match name.as_slice() {
@@ -1048,9 +1290,69 @@ impl AttributeInfo {
 					|r| r.read_u32_as_usize(),
 					|r| r.read_u8()
 				)?;
+				#[cfg(feature = "std")]
 				eprintln!("WARN: unknown attr: {name:?}: {info:?}"); // TODO: print?
 				Self::Unknown { name: name.clone(), info }
 			},
 		})
 	}
 }
+
+#[cfg(test)]
+mod testing {
+	use crate::cp::attribute::{AttributeInfo, LineNumberTableEntry, LocalVariableTypeTableAttribute, LocalVariableTypeTableEntry};
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+	use crate::instruction::builder::CodeBuilder;
+	use crate::instruction::opcode::Opcode;
+	use crate::name::{ClassName, FieldName};
+
+	#[test]
+	fn lines_and_locals() {
+		let list = FieldDescriptor { array_dimension: 0, base_type: BaseOrObjectType::Object(ClassName::from(&b"java/util/List"[..])) };
+		let int = FieldDescriptor { array_dimension: 0, base_type: BaseOrObjectType::I };
+		let mut code = CodeBuilder::new(MethodDescriptor { parameters: vec![list], return_type: None }, true);
+		let start = code.here();
+		code.line_number(10).iconst(1).istore(1);
+		let i = code.here();
+		code.line_number(11).iinc(1, 1).line_number(10).iinc(1, 2);
+		let end = code.here();
+		code.line_number(12).emit(Opcode::Return);
+		code.local_variable(FieldName::from(&b"list"[..]), list, 0, start, end);
+		code.local_variable(FieldName::from(&b"i"[..]), int, 1, i, end);
+		let mut code = code.build(&mut PoolBuilder::new()).unwrap();
+
+		let offsets: Vec<_> = code.code.iter().map(|instruction| instruction.offset()).collect();
+		assert_eq!(code.line_for_offset(offsets[1]), Some(10));
+		assert_eq!(code.line_for_offset(offsets[2]), Some(11));
+		assert_eq!(code.line_for_offset(offsets[4]), Some(12));
+		assert_eq!(code.offsets_for_line(10), vec![0, offsets[3]]);
+		assert!(code.offsets_for_line(13).is_empty());
+
+		code.attributes.push(AttributeInfo::LocalVariableTypeTable(LocalVariableTypeTableAttribute {
+			local_variable_type_table: vec![LocalVariableTypeTableEntry {
+				start_pc: 0, end_pc: offsets[4], name: FieldName::from(&b"list"[..]), signature: b"Ljava/util/List<Ljava/lang/String;>;".to_vec(), lv_index: 0,
+			}],
+		}));
+		let locals = code.locals_at(offsets[1]);
+		assert_eq!(locals.len(), 1);
+		assert_eq!(locals[0].signature, Some(&b"Ljava/util/List<Ljava/lang/String;>;"[..]));
+		let locals = code.locals_at(offsets[2]);
+		assert_eq!(locals.iter().map(|local| local.name.as_bytes()).collect::<Vec<_>>(), vec![&b"list"[..], &b"i"[..]]);
+		assert_eq!((locals[1].descriptor, locals[1].signature), (&int, None));
+		assert!(code.locals_at(offsets[4]).is_empty());
+	}
+
+	#[test]
+	fn repeated_line_start() {
+		let mut code = CodeBuilder::new(MethodDescriptor { parameters: Vec::new(), return_type: None }, true);
+		code.line_number(10).emit(Opcode::Nop).line_number(11).emit(Opcode::Return);
+		let mut code = code.build(&mut PoolBuilder::new()).unwrap();
+		code.line_number_table.push(LineNumberTableEntry { start_pc: 1, line_number: 12 });
+		code.line_number_table.push(LineNumberTableEntry { start_pc: 0, line_number: 13 });
+
+		// of the entries with the same start_pc, the later one wins, whatever the order of the table
+		assert_eq!(code.line_for_offset(0), Some(13));
+		assert_eq!(code.line_for_offset(1), Some(12));
+	}
+}
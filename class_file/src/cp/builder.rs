@@ -0,0 +1,277 @@
+//! Building constant pools for writing class files.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use anyhow::{bail, Result};
+use crate::cp::{FieldRefInfo, InterfaceMethodRefInfo, InvokeDynamicInfo, MethodHandleInfo, MethodRefInfo, Pool, PoolEntry};
+use crate::descriptor::MethodDescriptor;
+use crate::name::ClassName;
+
+/// Collects the entries of a constant pool. Adding an entry returns its index, equal entries are only added once.
+#[derive(Debug, Clone)]
+pub struct PoolBuilder {
+	pool: Pool,
+	indices: BTreeMap<PoolEntry, usize>,
+}
+
+impl Default for PoolBuilder {
+	fn default() -> Self {
+		PoolBuilder::new()
+	}
+}
+
+impl PoolBuilder {
+	pub fn new() -> PoolBuilder {
+		PoolBuilder { pool: Pool(vec![PoolEntry::None]), indices: BTreeMap::new() }
+	}
+
+	/// Starts with all entries of `pool`, so that its indices stay valid.
+	pub fn from_pool(pool: &Pool) -> PoolBuilder {
+		let mut indices = BTreeMap::new();
+		for (index, entry) in pool.iter() {
+			if !matches!(entry, PoolEntry::Unusable) {
+				indices.entry(entry.clone()).or_insert(index);
+			}
+		}
+		PoolBuilder { pool: Pool(pool.0.clone()), indices }
+	}
+
+	/// Returns the `constant_pool_count`, which is one larger than the largest index used so far.
+	pub fn len(&self) -> usize {
+		self.pool.len()
+	}
+
+	/// Returns whether no entries were added yet.
+	pub fn is_empty(&self) -> bool {
+		self.pool.len() == 1
+	}
+
+	/// The pool with the entries added so far.
+	pub fn pool(&self) -> &Pool {
+		&self.pool
+	}
+
+	pub fn build(self) -> Pool {
+		self.pool
+	}
+
+	fn add(&mut self, entry: PoolEntry) -> Result<usize> {
+		if let Some(&index) = self.indices.get(&entry) {
+			return Ok(index);
+		}
+
+		let takes_two_entries = matches!(entry, PoolEntry::Long { .. } | PoolEntry::Double { .. });
+		let index = self.pool.0.len();
+		if index + takes_two_entries as usize > u16::MAX as usize - 1 {
+			bail!("constant pool is full");
+		}
+		self.pool.0.push(entry.clone());
+		if takes_two_entries {
+			self.pool.0.push(PoolEntry::Unusable);
+		}
+		self.indices.insert(entry, index);
+		Ok(index)
+	}
+
+	/// Adds a `CONSTANT_Utf8_info`, with `bytes` given in modified UTF-8.
+	pub fn utf8(&mut self, bytes: &[u8]) -> Result<usize> {
+		if bytes.len() > u16::MAX as usize {
+			bail!("string of length {} is too long for the constant pool", bytes.len());
+		}
+		self.add(PoolEntry::Utf8(bytes.to_vec()))
+	}
+
+	pub fn integer(&mut self, value: i32) -> Result<usize> {
+		self.add(PoolEntry::Integer(value as u32))
+	}
+
+	/// Adds a `CONSTANT_Float_info` with the bits of the value, see [f32::to_bits].
+	pub fn float(&mut self, bits: u32) -> Result<usize> {
+		self.add(PoolEntry::Float(bits))
+	}
+
+	pub fn long(&mut self, value: i64) -> Result<usize> {
+		self.add(PoolEntry::Long { high: (value as u64 >> 32) as u32, low: value as u32 })
+	}
+
+	/// Adds a `CONSTANT_Double_info` with the bits of the value, see [f64::to_bits].
+	pub fn double(&mut self, bits: u64) -> Result<usize> {
+		self.add(PoolEntry::Double { high: (bits >> 32) as u32, low: bits as u32 })
+	}
+
+	pub fn class(&mut self, class: &ClassName) -> Result<usize> {
+		let name = self.utf8(class.as_bytes())?;
+		self.add(PoolEntry::ClassName(name))
+	}
+
+	/// Adds a `CONSTANT_String_info`, with `bytes` given in modified UTF-8.
+	pub fn string(&mut self, bytes: &[u8]) -> Result<usize> {
+		let string = self.utf8(bytes)?;
+		self.add(PoolEntry::String(string))
+	}
+
+	pub fn name_and_type(&mut self, name: &[u8], descriptor: &[u8]) -> Result<usize> {
+		let name_index = self.utf8(name)?;
+		let descriptor_index = self.utf8(descriptor)?;
+		self.add(PoolEntry::NameAndType { name_index, descriptor_index })
+	}
+
+	pub fn field_ref(&mut self, field: &FieldRefInfo) -> Result<usize> {
+		let class_index = self.class(&field.class)?;
+		let name_and_type_index = self.name_and_type(field.name.as_bytes(), &field.descriptor.to_bytes())?;
+		self.add(PoolEntry::FieldRef { class_index, name_and_type_index })
+	}
+
+	pub fn method_ref(&mut self, method: &MethodRefInfo) -> Result<usize> {
+		let class_index = self.class(&method.class)?;
+		let name_and_type_index = self.name_and_type(method.name.as_bytes(), &method.descriptor.to_bytes())?;
+		self.add(PoolEntry::MethodRef { class_index, name_and_type_index })
+	}
+
+	pub fn interface_method_ref(&mut self, method: &InterfaceMethodRefInfo) -> Result<usize> {
+		let class_index = self.class(&method.class)?;
+		let name_and_type_index = self.name_and_type(method.name.as_bytes(), &method.descriptor.to_bytes())?;
+		self.add(PoolEntry::InterfaceMethodRef { class_index, name_and_type_index })
+	}
+
+	pub fn method_handle(&mut self, method_handle: &MethodHandleInfo) -> Result<usize> {
+		let (kind, reference) = match method_handle {
+			MethodHandleInfo::GetField(field) => (1, self.field_ref(field)?),
+			MethodHandleInfo::GetStatic(field) => (2, self.field_ref(field)?),
+			MethodHandleInfo::PutField(field) => (3, self.field_ref(field)?),
+			MethodHandleInfo::PutStatic(field) => (4, self.field_ref(field)?),
+			MethodHandleInfo::InvokeVirtual(method) => (5, self.method_ref(method)?),
+			MethodHandleInfo::InvokeStatic(method) => (6, self.method_ref(method)?),
+			MethodHandleInfo::InvokeSpecial(method) => (7, self.method_ref(method)?),
+			MethodHandleInfo::NewInvokeSpecial(method) => (8, self.method_ref(method)?),
+			MethodHandleInfo::InvokeInterface(method) => (9, self.interface_method_ref(method)?),
+			MethodHandleInfo::InvokeStaticInterface(method) => (6, self.interface_method_ref(method)?),
+			MethodHandleInfo::InvokeSpecialInterface(method) => (7, self.interface_method_ref(method)?),
+		};
+		self.add(PoolEntry::MethodHandle(kind, reference))
+	}
+
+	pub fn method_type(&mut self, descriptor: &MethodDescriptor) -> Result<usize> {
+		let descriptor = self.utf8(&descriptor.to_bytes())?;
+		self.add(PoolEntry::MethodType(descriptor))
+	}
+
+	pub fn invoke_dynamic(&mut self, call_site: &InvokeDynamicInfo) -> Result<usize> {
+		let name_and_type_index = self.name_and_type(call_site.name.as_bytes(), &call_site.descriptor.to_bytes())?;
+		self.add(PoolEntry::InvokeDynamic { bootstrap_method_attribute_index: call_site.bootstrap_method_attribute_index, name_and_type_index })
+	}
+
+	/// Adds the entry at `index` of another pool, together with the entries it refers to, and returns its index in this pool. This moves references
+	/// of attributes the parser doesn't know to a new pool.
+	pub fn copy(&mut self, pool: &Pool, index: usize) -> Result<usize> {
+		let entry = match pool.0.get(index) {
+			None | Some(PoolEntry::None | PoolEntry::Unusable) => bail!("invalid constant pool index {index}"),
+			Some(entry @ (PoolEntry::Utf8(_) | PoolEntry::Integer(_) | PoolEntry::Float(_) | PoolEntry::Long { .. } | PoolEntry::Double { .. })) => entry.clone(),
+			Some(PoolEntry::ClassName(index)) => PoolEntry::ClassName(self.copy(pool, *index)?),
+			Some(PoolEntry::String(index)) => PoolEntry::String(self.copy(pool, *index)?),
+			Some(PoolEntry::MethodType(index)) => PoolEntry::MethodType(self.copy(pool, *index)?),
+			Some(PoolEntry::Module(index)) => PoolEntry::Module(self.copy(pool, *index)?),
+			Some(PoolEntry::Package(index)) => PoolEntry::Package(self.copy(pool, *index)?),
+			Some(PoolEntry::MethodHandle(kind, index)) => PoolEntry::MethodHandle(*kind, self.copy(pool, *index)?),
+			Some(PoolEntry::FieldRef { class_index, name_and_type_index }) => PoolEntry::FieldRef {
+				class_index: self.copy(pool, *class_index)?,
+				name_and_type_index: self.copy(pool, *name_and_type_index)?,
+			},
+			Some(PoolEntry::MethodRef { class_index, name_and_type_index }) => PoolEntry::MethodRef {
+				class_index: self.copy(pool, *class_index)?,
+				name_and_type_index: self.copy(pool, *name_and_type_index)?,
+			},
+			Some(PoolEntry::InterfaceMethodRef { class_index, name_and_type_index }) => PoolEntry::InterfaceMethodRef {
+				class_index: self.copy(pool, *class_index)?,
+				name_and_type_index: self.copy(pool, *name_and_type_index)?,
+			},
+			Some(PoolEntry::NameAndType { name_index, descriptor_index }) => PoolEntry::NameAndType {
+				name_index: self.copy(pool, *name_index)?,
+				descriptor_index: self.copy(pool, *descriptor_index)?,
+			},
+			Some(PoolEntry::InvokeDynamic { bootstrap_method_attribute_index, name_and_type_index }) => PoolEntry::InvokeDynamic {
+				bootstrap_method_attribute_index: *bootstrap_method_attribute_index,
+				name_and_type_index: self.copy(pool, *name_and_type_index)?,
+			},
+		};
+		self.add(entry)
+	}
+
+	/// Writes the `constant_pool_count` followed by the entries, as they appear in a class file.
+	pub fn write(&self, bytes: &mut Vec<u8>) {
+		bytes.extend_from_slice(&(self.pool.len() as u16).to_be_bytes());
+		for entry in &self.pool.0 {
+			entry.write(bytes);
+		}
+	}
+}
+
+impl PoolEntry {
+	fn write(&self, bytes: &mut Vec<u8>) {
+		let u16 = |index: usize| (index as u16).to_be_bytes();
+		match self {
+			PoolEntry::None | PoolEntry::Unusable => {},
+			PoolEntry::Utf8(vec) => {
+				bytes.push(1);
+				bytes.extend_from_slice(&u16(vec.len()));
+				bytes.extend_from_slice(vec);
+			},
+			PoolEntry::Integer(value) => {
+				bytes.push(3);
+				bytes.extend_from_slice(&value.to_be_bytes());
+			},
+			PoolEntry::Float(bits) => {
+				bytes.push(4);
+				bytes.extend_from_slice(&bits.to_be_bytes());
+			},
+			PoolEntry::Long { high, low } | PoolEntry::Double { high, low } => {
+				bytes.push(if matches!(self, PoolEntry::Long { .. }) { 5 } else { 6 });
+				bytes.extend_from_slice(&high.to_be_bytes());
+				bytes.extend_from_slice(&low.to_be_bytes());
+			},
+			PoolEntry::ClassName(index) => {
+				bytes.push(7);
+				bytes.extend_from_slice(&u16(*index));
+			},
+			PoolEntry::String(index) => {
+				bytes.push(8);
+				bytes.extend_from_slice(&u16(*index));
+			},
+			PoolEntry::FieldRef { class_index, name_and_type_index } |
+			PoolEntry::MethodRef { class_index, name_and_type_index } |
+			PoolEntry::InterfaceMethodRef { class_index, name_and_type_index } => {
+				bytes.push(match self {
+					PoolEntry::FieldRef { .. } => 9,
+					PoolEntry::MethodRef { .. } => 10,
+					_ => 11,
+				});
+				bytes.extend_from_slice(&u16(*class_index));
+				bytes.extend_from_slice(&u16(*name_and_type_index));
+			},
+			PoolEntry::NameAndType { name_index, descriptor_index } => {
+				bytes.push(12);
+				bytes.extend_from_slice(&u16(*name_index));
+				bytes.extend_from_slice(&u16(*descriptor_index));
+			},
+			PoolEntry::MethodHandle(kind, index) => {
+				bytes.push(15);
+				bytes.push(*kind);
+				bytes.extend_from_slice(&u16(*index));
+			},
+			PoolEntry::MethodType(index) => {
+				bytes.push(16);
+				bytes.extend_from_slice(&u16(*index));
+			},
+			PoolEntry::Module(index) | PoolEntry::Package(index) => {
+				bytes.push(if matches!(self, PoolEntry::Module(_)) { 19 } else { 20 });
+				bytes.extend_from_slice(&u16(*index));
+			},
+			PoolEntry::InvokeDynamic { bootstrap_method_attribute_index, name_and_type_index } => {
+				bytes.push(18);
+				bytes.extend_from_slice(&bootstrap_method_attribute_index.to_be_bytes());
+				bytes.extend_from_slice(&u16(*name_and_type_index));
+			},
+		}
+	}
+}
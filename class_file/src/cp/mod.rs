@@ -1,13 +1,14 @@
+use alloc::vec::Vec;
 use anyhow::{anyhow, bail, Context, Result};
 use crate::descriptor::{FieldDescriptor, MethodDescriptor};
 use crate::MyRead;
 use crate::name::{ClassName, FieldName, MethodName};
-use crate::verifier::VerificationType;
 
 pub mod attribute;
+pub mod builder;
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pool(Vec<PoolEntry>);
 impl Pool {
 	pub fn parse<R: MyRead>(reader: &mut R) -> Result<Pool> {
@@ -16,14 +17,31 @@ impl Pool {
 
 		vec.push(PoolEntry::None); // constant pool indices are based on 0
 
-		for _ in 0..count {
+		// the count is one larger than the number of entries, and Long and Double take up two entries each (4.4.5)
+		while vec.len() < count {
 			let entry = PoolEntry::parse(reader)
 				.with_context(|| "while parsing the constant pool")?;
 
+			let takes_two_entries = matches!(entry, PoolEntry::Long { .. } | PoolEntry::Double { .. });
 			vec.push(entry);
+			if takes_two_entries {
+				vec.push(PoolEntry::Unusable);
+			}
 		}
 		Ok(Pool(vec))
 	}
+	/// Returns the `constant_pool_count`, which is one larger than the largest valid index.
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+	/// Returns whether the pool has no entries, that is a `constant_pool_count` of `1`.
+	pub fn is_empty(&self) -> bool {
+		self.0.len() <= 1
+	}
+	/// Iterates over all entries together with their index, skipping the entry at index `0`.
+	pub fn iter(&self) -> impl Iterator<Item=(usize, &PoolEntry)> {
+		self.0.iter().enumerate().skip(1)
+	}
 	pub fn get<'a, T>(&'a self, index: usize) -> Result<T>
 	where
 		T: FromPoolEntry<'a>
@@ -48,19 +66,6 @@ pub trait FromPoolEntry<'a> {
 
 
 
-impl FromPoolEntry<'_> for VerificationType {
-	// TODO: remove this
-	fn from_pool_entry(_: &Pool, _: &PoolEntry) -> Result<Self> {
-		todo!()
-	}
-}
-impl FromPoolEntry<'_> for i32 {
-	// TODO: remove this
-	fn from_pool_entry(_: &Pool, _: &PoolEntry) -> Result<Self> {
-		todo!()
-	}
-}
-
 impl<'a> FromPoolEntry<'a> for &'a PoolEntry {
 	fn from_pool_entry(_: &Pool, entry: &'a PoolEntry) -> Result<Self> {
 		Ok(entry)
@@ -86,17 +91,6 @@ impl<'a> FromPoolEntry<'a> for &'a Vec<u8> {
 	}
 }
 
-impl FromPoolEntry<'_> for Utf8Info {
-	fn from_pool_entry(_: &Pool, entry: &PoolEntry) -> Result<Self> {
-		match entry {
-			PoolEntry::Utf8(vec) => Ok(Utf8Info {
-				inner: vec.clone(),
-			}),
-			_ => create_err!(entry, PoolEntry::Utf8),
-		}
-	}
-}
-
 impl FromPoolEntry<'_> for ClassName {
 	fn from_pool_entry(pool: &Pool, entry: &PoolEntry) -> Result<Self> {
 		if let PoolEntry::ClassName(index) = entry {
@@ -210,16 +204,20 @@ impl FromPoolEntry<'_> for InterfaceMethodRefInfo {
 impl FromPoolEntry<'_> for MethodHandleInfo {
 	fn from_pool_entry(pool: &Pool, entry: &PoolEntry) -> Result<Self> {
 		if let PoolEntry::MethodHandle(kind, index) = entry {
+			// since version 52.0 kinds 6 and 7 may also reference interface methods, the methods each kind may reference are checked by `format::check_pool`
+			let is_interface = matches!(pool.get::<&PoolEntry>(*index)?, PoolEntry::InterfaceMethodRef { .. });
 			match *kind {
 				1 => Ok(MethodHandleInfo::GetField        (pool.get(*index)?)),
 				2 => Ok(MethodHandleInfo::GetStatic       (pool.get(*index)?)),
 				3 => Ok(MethodHandleInfo::PutField        (pool.get(*index)?)),
 				4 => Ok(MethodHandleInfo::PutStatic       (pool.get(*index)?)),
-				5 => Ok(MethodHandleInfo::InvokeVirtual   (pool.get(*index)?)), // TODO: must not be <init> and not <clinit>
-				6 => Ok(MethodHandleInfo::InvokeStatic    (pool.get(*index)?)), // TODO: must not be <init> and not <clinit>
-				7 => Ok(MethodHandleInfo::InvokeSpecial   (pool.get(*index)?)), // TODO: must not be <init> and not <clinit>
-				8 => Ok(MethodHandleInfo::NewInvokeSpecial(pool.get(*index)?)), // TODO: must be <init>
-				9 => Ok(MethodHandleInfo::InvokeInterface (pool.get(*index)?)), // TODO: must not be <init> and not <clinit>
+				5 => Ok(MethodHandleInfo::InvokeVirtual   (pool.get(*index)?)),
+				6 if is_interface => Ok(MethodHandleInfo::InvokeStaticInterface(pool.get(*index)?)),
+				7 if is_interface => Ok(MethodHandleInfo::InvokeSpecialInterface(pool.get(*index)?)),
+				6 => Ok(MethodHandleInfo::InvokeStatic    (pool.get(*index)?)),
+				7 => Ok(MethodHandleInfo::InvokeSpecial   (pool.get(*index)?)),
+				8 => Ok(MethodHandleInfo::NewInvokeSpecial(pool.get(*index)?)),
+				9 => Ok(MethodHandleInfo::InvokeInterface (pool.get(*index)?)),
 				kind => bail!("unknown method handle info kind: {kind}"),
 			}
 		} else {
@@ -256,9 +254,10 @@ impl FromPoolEntry<'_> for InvokeDynamicInfo {
 ///              |
 ///         MethodHandle
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum PoolEntry { // TODO: should also not be public
 	None, // used for index = 0
+	Unusable, // used for the index after a Long or Double
 	Utf8(Vec<u8>),
 	Integer(u32), // TODO: figure out what to do with the simple data types!
 	Float(u32),
@@ -288,6 +287,8 @@ pub enum PoolEntry { // TODO: should also not be public
 		bootstrap_method_attribute_index: u16,
 		name_and_type_index: usize,
 	},
+	Module(usize), // Utf8
+	Package(usize), // Utf8
 }
 impl PoolEntry {
 	fn parse<R: MyRead>(reader: &mut R) -> Result<PoolEntry> {
@@ -330,16 +331,13 @@ impl PoolEntry {
 				bootstrap_method_attribute_index: reader.read_u16()?,
 				name_and_type_index: reader.read_u16_as_usize()?,
 			}),
+			19 => Ok(Self::Module(reader.read_u16_as_usize()?)),
+			20 => Ok(Self::Package(reader.read_u16_as_usize()?)),
 			tag => bail!("unknown constant pool tag {tag}"),
 		}
 	}
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Utf8Info {
-	inner: Vec<u8>,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FieldRefInfo {
 	pub class: ClassName,
@@ -372,11 +370,17 @@ pub enum MethodHandleInfo {
 	InvokeSpecial(MethodRefInfo),
 	NewInvokeSpecial(MethodRefInfo),
 	InvokeInterface(InterfaceMethodRefInfo),
+	/// Kind 6 referencing an interface method, a static method of an interface.
+	InvokeStaticInterface(InterfaceMethodRefInfo),
+	/// Kind 7 referencing an interface method, a private or super method of an interface.
+	InvokeSpecialInterface(InterfaceMethodRefInfo),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct InvokeDynamicInfo {
-	bootstrap_method_attribute_index: u16,
-	name: MethodName,
-	descriptor: MethodDescriptor,
+	/// The index into the `bootstrap_methods` of the `BootstrapMethods` attribute of the class, see
+	/// [ClassFile::call_site](crate::ClassFile::call_site) for resolving it.
+	pub bootstrap_method_attribute_index: u16,
+	pub name: MethodName,
+	pub descriptor: MethodDescriptor,
 }
@@ -0,0 +1,381 @@
+//! Abstract interpretation of the locals and the operand stack.
+//!
+//! A [Frame] holds an abstract value for every local variable and every operand stack entry, similar to the frames of the verifier. How values are
+//! created and combined is up to an [Interpreter], while [Frame::execute] takes care of moving them between the locals and the operand stack. A
+//! [FrameAnalysis] turns an interpreter into a forward [Analysis].
+//!
+//! Unlike in the verifier, `long` and `double` values take a single entry on the operand stack. In the locals they take two entries, the second one is
+//! [unknown](Interpreter::unknown) with no kind.
+
+use alloc::{format, vec};
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use crate::cp::attribute::ExceptionTableEntry;
+use crate::dataflow::{Analysis, Direction};
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+use crate::instruction::Instruction;
+use crate::instruction::opcode::Opcode;
+use crate::MethodInfo;
+
+/// The computational kind of a value, see JVMS 2.11.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+	Int,
+	Float,
+	Long,
+	Double,
+	Reference,
+}
+
+impl ValueKind {
+	/// The number of local variables a value of this kind takes.
+	pub fn size(self) -> usize {
+		match self {
+			ValueKind::Long | ValueKind::Double => 2,
+			_ => 1,
+		}
+	}
+
+	pub fn of(descriptor: &FieldDescriptor) -> ValueKind {
+		if descriptor.array_dimension > 0 {
+			return ValueKind::Reference;
+		}
+		match descriptor.base_type {
+			BaseOrObjectType::B | BaseOrObjectType::C | BaseOrObjectType::I | BaseOrObjectType::S | BaseOrObjectType::Z => ValueKind::Int,
+			BaseOrObjectType::F => ValueKind::Float,
+			BaseOrObjectType::J => ValueKind::Long,
+			BaseOrObjectType::D => ValueKind::Double,
+			BaseOrObjectType::Object(_) => ValueKind::Reference,
+		}
+	}
+}
+
+/// The semantics of the values of a [Frame].
+///
+/// Each method gets the opcode that creates the value together with the kind of the result, instructions that only consume values don't call the
+/// interpreter.
+pub trait Interpreter {
+	type Value: Clone + PartialEq;
+
+	/// A value nothing is known about. Without a kind, this is a value that can't be used, like an unassigned local variable.
+	fn unknown(&self, kind: Option<ValueKind>) -> Self::Value;
+
+	/// The number of local variables `value` takes, `2` for `long` and `double` values and `1` for everything else.
+	fn size(&self, value: &Self::Value) -> usize;
+
+	/// The value of a parameter at the entry of the method, `is_this` is set for the receiver of instance methods.
+	fn parameter(&self, kind: ValueKind, is_this: bool) -> Self::Value;
+
+	/// The exception pushed onto the operand stack when entering the handler of `entry`.
+	fn caught_exception(&self, entry: &ExceptionTableEntry) -> Self::Value;
+
+	/// A value created without operands, by the constant instructions, `ldc`, `new` and `getstatic`.
+	fn constant(&self, opcode: &Opcode, kind: ValueKind) -> Self::Value;
+
+	/// A value computed from one operand. For `iinc`, `value` is the local variable incremented.
+	fn unary(&self, opcode: &Opcode, value: &Self::Value, kind: ValueKind) -> Self::Value;
+
+	/// A value computed from two operands, `value1` being the deeper one on the operand stack.
+	fn binary(&self, opcode: &Opcode, value1: &Self::Value, value2: &Self::Value, kind: ValueKind) -> Self::Value;
+
+	/// A value computed from any number of operands, by the invoke instructions and `multianewarray`. The receiver of a method comes first.
+	fn nary(&self, opcode: &Opcode, values: &[Self::Value], kind: ValueKind) -> Self::Value;
+
+	/// Combines two values reaching the same instruction.
+	fn merge(&self, value1: &Self::Value, value2: &Self::Value) -> Self::Value;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame<V> {
+	pub locals: Vec<V>,
+	pub stack: Vec<V>,
+}
+
+impl<V: Clone + PartialEq> Frame<V> {
+	/// Creates the frame at the entry of `method`, holding the parameters in the first locals.
+	pub fn entry<I: Interpreter<Value=V>>(interpreter: &I, method: &MethodInfo) -> Result<Frame<V>> {
+		let max_locals = method.code.as_ref().map_or(0, |code| code.max_locals as usize);
+		Self::entry_from_descriptor(interpreter, &method.descriptor, method.access_flags.is_static, max_locals)
+	}
+
+	pub fn entry_from_descriptor<I: Interpreter<Value=V>>(
+		interpreter: &I, descriptor: &MethodDescriptor, is_static: bool, max_locals: usize,
+	) -> Result<Frame<V>> {
+		let mut locals = Vec::with_capacity(max_locals);
+		if !is_static {
+			locals.push(interpreter.parameter(ValueKind::Reference, true));
+		}
+		for parameter in &descriptor.parameters {
+			let kind = ValueKind::of(parameter);
+			locals.push(interpreter.parameter(kind, false));
+			if kind.size() == 2 {
+				locals.push(interpreter.unknown(None));
+			}
+		}
+		if locals.len() > max_locals {
+			bail!("parameters take {} locals, but max_locals is {max_locals}", locals.len());
+		}
+		locals.resize(max_locals, interpreter.unknown(None));
+
+		Ok(Frame { locals, stack: Vec::new() })
+	}
+
+	/// Merges `other` into this frame, returning whether this frame changed.
+	pub fn merge<I: Interpreter<Value=V>>(&mut self, interpreter: &I, other: &Frame<V>) -> Result<bool> {
+		if self.locals.len() != other.locals.len() {
+			bail!("frames with {} and {} locals can't be merged", self.locals.len(), other.locals.len());
+		}
+		if self.stack.len() != other.stack.len() {
+			bail!("operand stacks of depth {} and {} can't be merged", self.stack.len(), other.stack.len());
+		}
+
+		let mut changed = false;
+		for (value, other) in self.locals.iter_mut().chain(self.stack.iter_mut()).zip(other.locals.iter().chain(&other.stack)) {
+			let merged = interpreter.merge(value, other);
+			if merged != *value {
+				*value = merged;
+				changed = true;
+			}
+		}
+		Ok(changed)
+	}
+
+	fn pop(&mut self) -> Result<V> {
+		self.stack.pop().ok_or_else(|| anyhow!("operand stack underflow"))
+	}
+
+	fn pop_n(&mut self, n: usize) -> Result<Vec<V>> {
+		if self.stack.len() < n {
+			bail!("operand stack underflow");
+		}
+		Ok(self.stack.split_off(self.stack.len() - n))
+	}
+
+	/// Pops values taking `slots` stack slots in total, as the category-independent stack instructions like `dup2` do.
+	fn pop_slots<I: Interpreter<Value=V>>(&mut self, interpreter: &I, slots: usize) -> Result<Vec<V>> {
+		let mut values = Vec::new();
+		let mut taken = 0;
+		while taken < slots {
+			let value = self.pop()?;
+			taken += interpreter.size(&value);
+			values.push(value);
+		}
+		if taken != slots {
+			bail!("operand stack entry of category 2 split by a stack instruction");
+		}
+		values.reverse();
+		Ok(values)
+	}
+
+	fn local(&self, index: usize) -> Result<&V> {
+		self.locals.get(index).ok_or_else(|| anyhow!("local variable {index} out of bounds"))
+	}
+
+	fn store<I: Interpreter<Value=V>>(&mut self, interpreter: &I, index: usize, value: V) -> Result<()> {
+		let size = interpreter.size(&value);
+		if index + size > self.locals.len() {
+			bail!("local variable {index} out of bounds");
+		}
+		// overwriting the second half of a long or double destroys the whole value
+		if index > 0 && interpreter.size(&self.locals[index - 1]) == 2 {
+			self.locals[index - 1] = interpreter.unknown(None);
+		}
+		self.locals[index] = value;
+		if size == 2 {
+			self.locals[index + 1] = interpreter.unknown(None);
+		}
+		Ok(())
+	}
+
+	fn invoke<I: Interpreter<Value=V>>(&mut self, interpreter: &I, opcode: &Opcode, descriptor: &MethodDescriptor, has_receiver: bool) -> Result<()> {
+		let count = descriptor.parameters.len() + has_receiver as usize;
+		let values = self.pop_n(count)?;
+		if let Some(return_type) = &descriptor.return_type {
+			self.stack.push(interpreter.nary(opcode, &values, ValueKind::of(return_type)));
+		}
+		Ok(())
+	}
+
+	/// Applies the effect of `opcode` to this frame. Jumps and exceptions are not handled here, this is left to the [Analysis].
+	pub fn execute<I: Interpreter<Value=V>>(&mut self, interpreter: &I, opcode: &Opcode) -> Result<()> {
+		use Opcode::*;
+		use ValueKind::*;
+
+		match opcode {
+			Nop | Breakpoint | ImpDep1 | ImpDep2 | Goto(_) | Return => {},
+			Jsr(_) | Ret(_) => bail!("subroutines are not supported, they must be inlined first"),
+
+			AConstNull | LdcReferenceString(_) | LdcReferenceClass(_) | LdcReferenceMethodType(_) | LdcReferenceMethodHandle(_) | New(_) =>
+				self.stack.push(interpreter.constant(opcode, Reference)),
+			IConstM1 | IConst0 | IConst1 | IConst2 | IConst3 | IConst4 | IConst5 | BIPush(_) | SIPush(_) | LdcInt(_) =>
+				self.stack.push(interpreter.constant(opcode, Int)),
+			LConst0 | LConst1 | Ldc2WLong(_) => self.stack.push(interpreter.constant(opcode, Long)),
+			FConst0 | FConst1 | FConst2 | LdcFloat(_) => self.stack.push(interpreter.constant(opcode, Float)),
+			DConst0 | DConst1 | Ldc2WDouble(_) => self.stack.push(interpreter.constant(opcode, Double)),
+			GetStatic(field) => self.stack.push(interpreter.constant(opcode, ValueKind::of(&field.descriptor))),
+
+			ILoad(index) | LLoad(index) | FLoad(index) | DLoad(index) | ALoad(index) => {
+				let value = self.local(index.0)?.clone();
+				self.stack.push(value);
+			},
+			IStore(index) | LStore(index) | FStore(index) | DStore(index) | AStore(index) => {
+				let value = self.pop()?;
+				self.store(interpreter, index.0, value)?;
+			},
+			IInc { lv_index, .. } => {
+				let value = interpreter.unary(opcode, self.local(lv_index.0)?, Int);
+				self.store(interpreter, lv_index.0, value)?;
+			},
+
+			IALoad | BALoad | CALoad | SALoad | LALoad | FALoad | DALoad | AALoad => {
+				let kind = match opcode {
+					LALoad => Long,
+					FALoad => Float,
+					DALoad => Double,
+					AALoad => Reference,
+					_ => Int,
+				};
+				let index = self.pop()?;
+				let array = self.pop()?;
+				self.stack.push(interpreter.binary(opcode, &array, &index, kind));
+			},
+			IAStore | BAStore | CAStore | SAStore | LAStore | FAStore | DAStore | AAStore => {
+				self.pop_n(3)?;
+			},
+
+			Pop => { self.pop_slots(interpreter, 1)?; },
+			Pop2 => { self.pop_slots(interpreter, 2)?; },
+			Dup | DupX1 | DupX2 | Dup2 | Dup2X1 | Dup2X2 => {
+				let (copied, skipped) = match opcode {
+					Dup => (1, 0),
+					DupX1 => (1, 1),
+					DupX2 => (1, 2),
+					Dup2 => (2, 0),
+					Dup2X1 => (2, 1),
+					_ => (2, 2),
+				};
+				let top = self.pop_slots(interpreter, copied)?;
+				let below = self.pop_slots(interpreter, skipped)?;
+				self.stack.extend(top.iter().cloned());
+				self.stack.extend(below);
+				self.stack.extend(top);
+			},
+			Swap => {
+				let top = self.pop_slots(interpreter, 1)?;
+				let below = self.pop_slots(interpreter, 1)?;
+				self.stack.extend(top);
+				self.stack.extend(below);
+			},
+
+			IAdd | ISub | IMul | IDiv | IRem | IShl | IShr | IUShr | IAnd | IOr | IXor | LCmp | FCmpL | FCmpG | DCmpL | DCmpG |
+			LAdd | LSub | LMul | LDiv | LRem | LShl | LShr | LUShr | LAnd | LOr | LXor |
+			FAdd | FSub | FMul | FDiv | FRem |
+			DAdd | DSub | DMul | DDiv | DRem => {
+				let kind = match opcode {
+					LAdd | LSub | LMul | LDiv | LRem | LShl | LShr | LUShr | LAnd | LOr | LXor => Long,
+					FAdd | FSub | FMul | FDiv | FRem => Float,
+					DAdd | DSub | DMul | DDiv | DRem => Double,
+					_ => Int,
+				};
+				let value2 = self.pop()?;
+				let value1 = self.pop()?;
+				self.stack.push(interpreter.binary(opcode, &value1, &value2, kind));
+			},
+			INeg | LNeg | FNeg | DNeg | I2l | I2f | I2d | L2i | L2f | L2d | F2i | F2l | F2d | D2i | D2l | D2f | I2b | I2c | I2s |
+			ArrayLength | InstanceOf(_) | CheckCast(_) | NewArray { .. } | ANewArray(_) | GetField(_) => {
+				let kind = match opcode {
+					LNeg | I2l | F2l | D2l => Long,
+					FNeg | I2f | L2f | D2f => Float,
+					DNeg | I2d | L2d | F2d => Double,
+					CheckCast(_) | NewArray { .. } | ANewArray(_) => Reference,
+					GetField(field) => ValueKind::of(&field.descriptor),
+					_ => Int,
+				};
+				let value = self.pop()?;
+				self.stack.push(interpreter.unary(opcode, &value, kind));
+			},
+
+			IfEq(_) | IfNe(_) | IfLt(_) | IfGe(_) | IfGt(_) | IfLe(_) | IfNull(_) | IfNonNull(_) |
+			TableSwitch { .. } | LookupSwitch { .. } |
+			IReturn | LReturn | FReturn | DReturn | AReturn | AThrow |
+			PutStatic(_) | MonitorEnter | MonitorExit => {
+				self.pop()?;
+			},
+			IfICmpEq(_) | IfICmpNe(_) | IfICmpLt(_) | IfICmpGe(_) | IfICmpGt(_) | IfICmpLe(_) | IfACmpEq(_) | IfACmpNe(_) | PutField(_) => {
+				self.pop_n(2)?;
+			},
+
+			InvokeVirtual(method) | InvokeSpecial(method) => self.invoke(interpreter, opcode, &method.descriptor, true)?,
+			InvokeStatic(method) => self.invoke(interpreter, opcode, &method.descriptor, false)?,
+			InvokeInterface { method_ref, .. } => self.invoke(interpreter, opcode, &method_ref.descriptor, true)?,
+			InvokeDynamic { call_site, .. } => self.invoke(interpreter, opcode, &call_site.descriptor, false)?,
+			MultiANewArray(_, dimensions) => {
+				let values = self.pop_n(*dimensions)?;
+				self.stack.push(interpreter.nary(opcode, &values, Reference));
+			},
+		}
+		Ok(())
+	}
+}
+
+/// A forward [Analysis] computing the [Frame] before and after every instruction, with `None` for instructions that are not reached.
+pub struct FrameAnalysis<I: Interpreter> {
+	interpreter: I,
+	entry: Frame<I::Value>,
+}
+
+impl<I: Interpreter> FrameAnalysis<I> {
+	pub fn new(interpreter: I, method: &MethodInfo) -> Result<FrameAnalysis<I>> {
+		let entry = Frame::entry(&interpreter, method)?;
+		Ok(FrameAnalysis { interpreter, entry })
+	}
+
+	/// Creates the analysis with a given frame at the entry of the method.
+	pub fn with_entry(interpreter: I, entry: Frame<I::Value>) -> FrameAnalysis<I> {
+		FrameAnalysis { interpreter, entry }
+	}
+
+	pub fn interpreter(&self) -> &I {
+		&self.interpreter
+	}
+}
+
+impl<I: Interpreter> Analysis for FrameAnalysis<I> {
+	type Fact = Option<Frame<I::Value>>;
+
+	const DIRECTION: Direction = Direction::Forward;
+
+	fn bottom(&self) -> Self::Fact {
+		None
+	}
+
+	fn boundary(&self) -> Self::Fact {
+		Some(self.entry.clone())
+	}
+
+	fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) -> Result<bool> {
+		match (fact.as_mut(), other) {
+			(_, None) => Ok(false),
+			(None, Some(other)) => {
+				*fact = Some(other.clone());
+				Ok(true)
+			},
+			(Some(frame), Some(other)) => frame.merge(&self.interpreter, other),
+		}
+	}
+
+	fn transfer(&self, _index: usize, instruction: &Instruction, fact: &mut Self::Fact) -> Result<()> {
+		match fact {
+			Some(frame) => frame.execute(&self.interpreter, instruction.opcode())
+				.map_err(|e| e.context(format!("at offset {}", instruction.offset()))),
+			None => Ok(()),
+		}
+	}
+
+	fn exception_edge(&self, entry: &ExceptionTableEntry, fact: &Self::Fact) -> Self::Fact {
+		fact.as_ref().map(|frame| Frame {
+			locals: frame.locals.clone(),
+			stack: vec![self.interpreter.caught_exception(entry)],
+		})
+	}
+}
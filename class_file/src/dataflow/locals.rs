@@ -0,0 +1,130 @@
+//! Analyses of the local variables: liveness and reaching definitions.
+
+use alloc::collections::BTreeSet;
+use anyhow::Result;
+use crate::dataflow::{Analysis, Direction};
+use crate::instruction::Instruction;
+use crate::instruction::opcode::Opcode;
+
+/// How an instruction accesses a local variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalAccess {
+	/// The local is loaded.
+	Use(usize),
+	/// The local is stored to, `wide` is set if it holds a `long` or `double` afterwards and so also overwrites the next local.
+	Def { index: usize, wide: bool },
+	/// The local is loaded and stored to, by `iinc`.
+	UseDef(usize),
+}
+
+impl LocalAccess {
+	pub fn of(opcode: &Opcode) -> Option<LocalAccess> {
+		match opcode {
+			Opcode::ILoad(index) | Opcode::LLoad(index) | Opcode::FLoad(index) | Opcode::DLoad(index) | Opcode::ALoad(index) =>
+				Some(LocalAccess::Use(index.0)),
+			Opcode::IStore(index) | Opcode::FStore(index) | Opcode::AStore(index) =>
+				Some(LocalAccess::Def { index: index.0, wide: false }),
+			Opcode::LStore(index) | Opcode::DStore(index) =>
+				Some(LocalAccess::Def { index: index.0, wide: true }),
+			Opcode::IInc { lv_index, .. } => Some(LocalAccess::UseDef(lv_index.0)),
+			_ => None,
+		}
+	}
+}
+
+/// Computes the local variables that are live, that is whose value may still be loaded later on. The facts are the indices of the live locals.
+///
+/// For `long` and `double` values only the index of the first local is considered live.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Liveness;
+
+impl Analysis for Liveness {
+	type Fact = BTreeSet<usize>;
+
+	const DIRECTION: Direction = Direction::Backward;
+
+	fn bottom(&self) -> Self::Fact {
+		BTreeSet::new()
+	}
+
+	fn boundary(&self) -> Self::Fact {
+		BTreeSet::new()
+	}
+
+	fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) -> Result<bool> {
+		let len = fact.len();
+		fact.extend(other);
+		Ok(fact.len() != len)
+	}
+
+	fn transfer(&self, _index: usize, instruction: &Instruction, fact: &mut Self::Fact) -> Result<()> {
+		match LocalAccess::of(instruction.opcode()) {
+			Some(LocalAccess::Use(index)) | Some(LocalAccess::UseDef(index)) => {
+				fact.insert(index);
+			},
+			Some(LocalAccess::Def { index, .. }) => {
+				fact.remove(&index);
+			},
+			None => {},
+		}
+		Ok(())
+	}
+}
+
+/// Where the value of a local variable was assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DefinitionSite {
+	/// The value the local had at the entry of the method, a parameter or nothing at all.
+	Entry,
+	/// The instruction with this index stored the value.
+	Instruction(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Definition {
+	pub local: usize,
+	pub site: DefinitionSite,
+}
+
+/// Computes the assignments to local variables that may reach an instruction without being overwritten.
+#[derive(Debug, Clone, Copy)]
+pub struct ReachingDefinitions {
+	max_locals: usize,
+}
+
+impl ReachingDefinitions {
+	pub fn new(max_locals: usize) -> ReachingDefinitions {
+		ReachingDefinitions { max_locals }
+	}
+}
+
+impl Analysis for ReachingDefinitions {
+	type Fact = BTreeSet<Definition>;
+
+	const DIRECTION: Direction = Direction::Forward;
+
+	fn bottom(&self) -> Self::Fact {
+		BTreeSet::new()
+	}
+
+	fn boundary(&self) -> Self::Fact {
+		(0..self.max_locals).map(|local| Definition { local, site: DefinitionSite::Entry }).collect()
+	}
+
+	fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) -> Result<bool> {
+		let len = fact.len();
+		fact.extend(other);
+		Ok(fact.len() != len)
+	}
+
+	fn transfer(&self, index: usize, instruction: &Instruction, fact: &mut Self::Fact) -> Result<()> {
+		let (local, wide) = match LocalAccess::of(instruction.opcode()) {
+			Some(LocalAccess::Def { index, wide }) => (index, wide),
+			Some(LocalAccess::UseDef(index)) => (index, false),
+			_ => return Ok(()),
+		};
+		fact.retain(|definition| definition.local != local && !(wide && definition.local == local + 1));
+		fact.insert(Definition { local, site: DefinitionSite::Instruction(index) });
+		Ok(())
+	}
+}
@@ -0,0 +1,308 @@
+//! Dataflow analysis on the [Instructions] of a method.
+//!
+//! An [Analysis] describes a lattice of facts and how each instruction transforms them. [solve] computes the fixpoint over the blocks of a
+//! [ControlFlowGraph] with a worklist, in the direction of the analysis, and records the facts before and after every instruction.
+//!
+//! Analyses that need the state of the locals and the operand stack can be written as an [Interpreter](frame::Interpreter) over [Frame](frame::Frame)s
+//! instead, see the [frame] module. Liveness, reaching definitions, constant propagation and nullness are built in.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use alloc::collections::VecDeque;
+use anyhow::{bail, Result};
+use crate::cfg::{ControlFlowGraph, EdgeKind};
+use crate::cp::attribute::{CodeAttribute, ExceptionTableEntry};
+use crate::instruction::{Instruction, Instructions};
+
+pub mod frame;
+pub mod locals;
+pub mod values;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+	/// Facts flow from the entry of the method along the edges of the control flow graph.
+	Forward,
+	/// Facts flow from the exits of the method against the edges of the control flow graph.
+	Backward,
+}
+
+/// A dataflow problem over a lattice of [Fact](Analysis::Fact)s.
+pub trait Analysis {
+	type Fact: Clone + PartialEq;
+
+	const DIRECTION: Direction;
+
+	/// The least element of the lattice, the fact of code no information has flowed to (yet).
+	fn bottom(&self) -> Self::Fact;
+
+	/// The fact at the entry of the method for forward analyses, and at the exits of the method for backward ones.
+	fn boundary(&self) -> Self::Fact;
+
+	/// Joins `other` into `fact`, returning whether `fact` changed.
+	fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) -> Result<bool>;
+
+	/// Applies the effect of the instruction with the index `index` to `fact`. For forward analyses, `fact` holds before the instruction and is turned
+	/// into the fact after it, for backward analyses the other way round.
+	fn transfer(&self, index: usize, instruction: &Instruction, fact: &mut Self::Fact) -> Result<()>;
+
+	/// Computes the fact passed along the edge to the handler of `entry`, given the fact where the exception was thrown.
+	fn exception_edge(&self, _entry: &ExceptionTableEntry, fact: &Self::Fact) -> Self::Fact {
+		fact.clone()
+	}
+}
+
+/// The facts at the fixpoint of an [Analysis], before and after each instruction. Instructions that are not reachable have the fact
+/// [bottom](Analysis::bottom).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataflowResults<F> {
+	before: Vec<F>,
+	after: Vec<F>,
+}
+
+impl<F> DataflowResults<F> {
+	/// The fact that holds right before the instruction with the index `index` is executed.
+	pub fn before(&self, index: usize) -> &F {
+		&self.before[index]
+	}
+
+	/// The fact that holds right after the instruction with the index `index` is executed.
+	pub fn after(&self, index: usize) -> &F {
+		&self.after[index]
+	}
+}
+
+/// Solves `analysis` for the code of a method.
+pub fn solve<A: Analysis>(analysis: &A, code: &CodeAttribute) -> Result<DataflowResults<A::Fact>> {
+	let cfg = ControlFlowGraph::new(code)?;
+	solve_with_cfg(analysis, &code.code, &code.exception_table, &cfg)
+}
+
+/// Solves `analysis` using an already computed control flow graph of `instructions`.
+pub fn solve_with_cfg<A: Analysis>(
+	analysis: &A, instructions: &Instructions, exception_table: &[ExceptionTableEntry], cfg: &ControlFlowGraph,
+) -> Result<DataflowResults<A::Fact>> {
+	let blocks = cfg.blocks();
+	let mut before = vec![analysis.bottom(); instructions.len()];
+	let mut after = vec![analysis.bottom(); instructions.len()];
+	if blocks.is_empty() {
+		return Ok(DataflowResults { before, after });
+	}
+
+	// visiting the blocks in reverse postorder (or its reverse for backward analyses) first keeps the number of iterations low
+	let mut order = cfg.reverse_postorder();
+	if A::DIRECTION == Direction::Backward {
+		order.reverse();
+	}
+	let mut worklist: VecDeque<_> = order.into_iter().collect();
+	let mut queued = vec![false; blocks.len()];
+	for id in &worklist {
+		queued[id.0] = true;
+	}
+	let reachable = queued.clone();
+
+	// the fact flowing into each block, at its start for forward analyses and at its end for backward ones
+	let mut input = vec![analysis.bottom(); blocks.len()];
+	match A::DIRECTION {
+		Direction::Forward => input[0] = analysis.boundary(),
+		Direction::Backward => for (id, block) in blocks.iter().enumerate() {
+			if block.successors.iter().all(|edge| matches!(edge.kind, EdgeKind::Exception(_))) {
+				input[id] = analysis.boundary();
+			}
+		},
+	}
+
+	while let Some(id) = worklist.pop_front() {
+		queued[id.0] = false;
+		let block = cfg.block(id);
+
+		match A::DIRECTION {
+			Direction::Forward => {
+				let mut fact = input[id.0].clone();
+				// an exception may be thrown by any instruction of the block, so the handlers see the facts before each of them
+				let mut thrown = vec![analysis.bottom(); block.successors.len()];
+				for index in block.instructions() {
+					for (edge, thrown) in block.successors.iter().zip(&mut thrown) {
+						if let EdgeKind::Exception(entry) = edge.kind {
+							analysis.join(thrown, &analysis.exception_edge(&exception_table[entry], &fact))?;
+						}
+					}
+					analysis.transfer(index, instruction(instructions, index)?, &mut fact)?;
+				}
+
+				for (edge, thrown) in block.successors.iter().zip(&thrown) {
+					let out = match edge.kind {
+						EdgeKind::Exception(_) => thrown,
+						_ => &fact,
+					};
+					if analysis.join(&mut input[edge.to.0], out)? && !queued[edge.to.0] {
+						queued[edge.to.0] = true;
+						worklist.push_back(edge.to);
+					}
+				}
+			},
+			Direction::Backward => {
+				let mut fact = input[id.0].clone();
+				let mut thrown = analysis.bottom();
+				for edge in &block.successors {
+					if let EdgeKind::Exception(entry) = edge.kind {
+						let handler = &before[blocks[edge.to.0].start];
+						analysis.join(&mut thrown, &analysis.exception_edge(&exception_table[entry], handler))?;
+					}
+				}
+				for index in block.instructions().rev() {
+					analysis.transfer(index, instruction(instructions, index)?, &mut fact)?;
+					analysis.join(&mut fact, &thrown)?;
+				}
+
+				if fact != before[block.start] {
+					before[block.start] = fact.clone();
+					// the handlers changed, so the blocks covered by them have to be recomputed, too
+					for edge in &block.predecessors {
+						if !queued[edge.from.0] {
+							queued[edge.from.0] = true;
+							worklist.push_back(edge.from);
+						}
+					}
+				}
+				for edge in &block.predecessors {
+					if !matches!(edge.kind, EdgeKind::Exception(_)) {
+						analysis.join(&mut input[edge.from.0], &fact)?;
+					}
+				}
+			},
+		}
+	}
+
+	// with the fixpoint reached, replay every block once to record the facts at each instruction
+	for (id, block) in blocks.iter().enumerate() {
+		if !reachable[id] {
+			continue;
+		}
+		match A::DIRECTION {
+			Direction::Forward => {
+				let mut fact = input[id].clone();
+				for index in block.instructions() {
+					before[index] = fact.clone();
+					analysis.transfer(index, instruction(instructions, index)?, &mut fact)?;
+					after[index] = fact.clone();
+				}
+			},
+			Direction::Backward => {
+				let mut thrown = analysis.bottom();
+				for edge in &block.successors {
+					if let EdgeKind::Exception(entry) = edge.kind {
+						let handler = &before[blocks[edge.to.0].start];
+						analysis.join(&mut thrown, &analysis.exception_edge(&exception_table[entry], handler))?;
+					}
+				}
+				let mut fact = input[id].clone();
+				for index in block.instructions().rev() {
+					after[index] = fact.clone();
+					analysis.transfer(index, instruction(instructions, index)?, &mut fact)?;
+					analysis.join(&mut fact, &thrown)?;
+					before[index] = fact.clone();
+				}
+			},
+		}
+	}
+
+	Ok(DataflowResults { before, after })
+}
+
+fn instruction(instructions: &Instructions, index: usize) -> Result<&Instruction> {
+	match instructions.get(index) {
+		Some(instruction) => Ok(instruction),
+		None => bail!("no instruction with index {index}"),
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use alloc::collections::BTreeSet;
+	use crate::cfg::ControlFlowGraph;
+	use crate::cp::attribute::ExceptionTableEntry;
+	use crate::cp::Pool;
+	use crate::dataflow::frame::{Frame, FrameAnalysis, ValueKind};
+	use crate::dataflow::locals::{Definition, DefinitionSite, Liveness, ReachingDefinitions};
+	use crate::dataflow::solve_with_cfg;
+	use crate::dataflow::values::{Constant, ConstantInterpreter, Nullness, NullnessInterpreter};
+	use crate::descriptor::MethodDescriptor;
+	use crate::instruction::Instructions;
+
+	#[test]
+	fn locals_in_loop_with_exception_handler() {
+		let code = [
+			0x03,             //  0: iconst_0
+			0x3c,             //  1: istore_1
+			0x1b,             //  2: iload_1
+			0x10, 0x0a,       //  3: bipush 10
+			0xa2, 0x00, 0x0b, //  5: if_icmpge 16
+			0x84, 0x01, 0x01, //  8: iinc 1 1
+			0xa7, 0xff, 0xf7, // 11: goto 2
+			0x4d,             // 14: astore_2
+			0x1b,             // 15: iload_1
+			0xac,             // 16: ireturn
+		];
+		let pool = Pool::parse(&mut &[0u8, 1][..]).unwrap();
+		let instructions = Instructions::parse(&code, &pool).unwrap();
+		let exception_table = [ExceptionTableEntry { start_pc: 8, end_pc: 11, handler_pc: 14, catch_type: None }];
+		let cfg = ControlFlowGraph::from_instructions(&instructions, &exception_table).unwrap();
+
+		let liveness = solve_with_cfg(&Liveness, &instructions, &exception_table, &cfg).unwrap();
+		assert_eq!(liveness.before(0), &BTreeSet::new());
+		assert_eq!(liveness.after(1), &BTreeSet::from([1]));
+		assert_eq!(liveness.before(5), &BTreeSet::from([1]));
+		// the handler uses the local, so it stays live in the covered iinc
+		assert_eq!(liveness.after(5), &BTreeSet::from([1]));
+		assert_eq!(liveness.before(9), &BTreeSet::new());
+
+		let definitions = solve_with_cfg(&ReachingDefinitions::new(3), &instructions, &exception_table, &cfg).unwrap();
+		let reaching = |index: usize| -> Vec<_> {
+			definitions.before(index).iter().filter(|d| d.local == 1).map(|d| d.site).collect()
+		};
+		assert_eq!(reaching(2), vec![DefinitionSite::Instruction(1), DefinitionSite::Instruction(5)]);
+		assert_eq!(reaching(6), vec![DefinitionSite::Instruction(5)]);
+		// the handler sees the value from before the iinc
+		assert_eq!(reaching(7), vec![DefinitionSite::Instruction(1), DefinitionSite::Instruction(5)]);
+		assert!(definitions.after(7).contains(&Definition { local: 2, site: DefinitionSite::Instruction(7) }));
+	}
+
+	#[test]
+	fn constants_and_nullness() {
+		let code = [
+			0x05,             //  0: iconst_2
+			0x10, 0xfd,       //  1: bipush -3
+			0x68,             //  3: imul
+			0x3c,             //  4: istore_1
+			0x01,             //  5: aconst_null
+			0x4d,             //  6: astore_2
+			0x1b,             //  7: iload_1
+			0x99, 0x00, 0x05, //  8: ifeq 13
+			0x2a,             // 11: aload_0
+			0x4d,             // 12: astore_2
+			0x1b,             // 13: iload_1
+			0x85,             // 14: i2l
+			0x5c,             // 15: dup2
+			0x61,             // 16: ladd
+			0xad,             // 17: lreturn
+		];
+		let pool = Pool::parse(&mut &[0u8, 1][..]).unwrap();
+		let instructions = Instructions::parse(&code, &pool).unwrap();
+		let cfg = ControlFlowGraph::from_instructions(&instructions, &[]).unwrap();
+		let descriptor = MethodDescriptor::try_from(&b"()J"[..]).unwrap();
+
+		let entry = Frame::entry_from_descriptor(&ConstantInterpreter, &descriptor, false, 3).unwrap();
+		let constants = solve_with_cfg(&FrameAnalysis::with_entry(ConstantInterpreter, entry), &instructions, &[], &cfg).unwrap();
+		let frame = constants.before(8).as_ref().unwrap();
+		assert_eq!(frame.locals, vec![Constant::Unknown(ValueKind::Reference), Constant::Int(-6), Constant::Null]);
+		let frame = constants.before(14).as_ref().unwrap();
+		assert_eq!(frame.locals[2], Constant::Unknown(ValueKind::Reference));
+		assert_eq!(frame.stack, vec![Constant::Long(-12)]);
+
+		let entry = Frame::entry_from_descriptor(&NullnessInterpreter, &descriptor, false, 3).unwrap();
+		let nullness = solve_with_cfg(&FrameAnalysis::with_entry(NullnessInterpreter, entry), &instructions, &[], &cfg).unwrap();
+		assert_eq!(nullness.before(8).as_ref().unwrap().locals[2], Nullness::Null);
+		assert_eq!(nullness.after(9).as_ref().unwrap().locals[2], Nullness::NonNull);
+		assert_eq!(nullness.before(10).as_ref().unwrap().locals[2], Nullness::MaybeNull);
+	}
+}
@@ -0,0 +1,286 @@
+//! [Interpreter]s for constant propagation and nullness, to be used with a [FrameAnalysis](crate::dataflow::frame::FrameAnalysis).
+
+use crate::cp::attribute::ExceptionTableEntry;
+use crate::dataflow::frame::{Interpreter, ValueKind};
+use crate::instruction::opcode::Opcode;
+
+/// A value that may be known at compile time. Floating point values are stored as their bits, so that `NaN`s compare equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Constant {
+	/// An unusable value, like an unassigned local variable.
+	Top,
+	Int(i32),
+	Long(i64),
+	Float(u32),
+	Double(u64),
+	Null,
+	/// A value of the given kind that is not known.
+	Unknown(ValueKind),
+}
+
+impl Constant {
+	pub fn kind(&self) -> Option<ValueKind> {
+		match self {
+			Constant::Top => None,
+			Constant::Int(_) => Some(ValueKind::Int),
+			Constant::Long(_) => Some(ValueKind::Long),
+			Constant::Float(_) => Some(ValueKind::Float),
+			Constant::Double(_) => Some(ValueKind::Double),
+			Constant::Null => Some(ValueKind::Reference),
+			Constant::Unknown(kind) => Some(*kind),
+		}
+	}
+
+	fn float(value: f32) -> Constant {
+		Constant::Float(value.to_bits())
+	}
+
+	fn double(value: f64) -> Constant {
+		Constant::Double(value.to_bits())
+	}
+}
+
+/// Tracks the values of `int`, `long`, `float` and `double` computations on constants, and `null`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConstantInterpreter;
+
+impl Interpreter for ConstantInterpreter {
+	type Value = Constant;
+
+	fn unknown(&self, kind: Option<ValueKind>) -> Constant {
+		kind.map_or(Constant::Top, Constant::Unknown)
+	}
+
+	fn size(&self, value: &Constant) -> usize {
+		value.kind().map_or(1, ValueKind::size)
+	}
+
+	fn parameter(&self, kind: ValueKind, _is_this: bool) -> Constant {
+		Constant::Unknown(kind)
+	}
+
+	fn caught_exception(&self, _entry: &ExceptionTableEntry) -> Constant {
+		Constant::Unknown(ValueKind::Reference)
+	}
+
+	fn constant(&self, opcode: &Opcode, kind: ValueKind) -> Constant {
+		match opcode {
+			Opcode::AConstNull => Constant::Null,
+			Opcode::IConstM1 => Constant::Int(-1),
+			Opcode::IConst0 => Constant::Int(0),
+			Opcode::IConst1 => Constant::Int(1),
+			Opcode::IConst2 => Constant::Int(2),
+			Opcode::IConst3 => Constant::Int(3),
+			Opcode::IConst4 => Constant::Int(4),
+			Opcode::IConst5 => Constant::Int(5),
+			Opcode::BIPush(value) => Constant::Int(*value as i8 as i32),
+			Opcode::SIPush(value) => Constant::Int(*value as i32),
+			Opcode::LdcInt(value) => Constant::Int(*value),
+			Opcode::LdcFloat(bits) => Constant::Float(*bits),
+			Opcode::Ldc2WLong(value) => Constant::Long(*value),
+			Opcode::Ldc2WDouble(bits) => Constant::Double(*bits),
+			Opcode::LConst0 => Constant::Long(0),
+			Opcode::LConst1 => Constant::Long(1),
+			Opcode::FConst0 => Constant::float(0.0),
+			Opcode::FConst1 => Constant::float(1.0),
+			Opcode::FConst2 => Constant::float(2.0),
+			Opcode::DConst0 => Constant::double(0.0),
+			Opcode::DConst1 => Constant::double(1.0),
+			_ => Constant::Unknown(kind),
+		}
+	}
+
+	fn unary(&self, opcode: &Opcode, value: &Constant, kind: ValueKind) -> Constant {
+		let result = match (opcode, *value) {
+			(Opcode::IInc { const_, .. }, Constant::Int(v)) => Some(Constant::Int(v.wrapping_add(*const_))),
+			(Opcode::INeg, Constant::Int(v)) => Some(Constant::Int(v.wrapping_neg())),
+			(Opcode::I2l, Constant::Int(v)) => Some(Constant::Long(v as i64)),
+			(Opcode::I2f, Constant::Int(v)) => Some(Constant::float(v as f32)),
+			(Opcode::I2d, Constant::Int(v)) => Some(Constant::double(v as f64)),
+			(Opcode::I2b, Constant::Int(v)) => Some(Constant::Int(v as i8 as i32)),
+			(Opcode::I2c, Constant::Int(v)) => Some(Constant::Int(v as u16 as i32)),
+			(Opcode::I2s, Constant::Int(v)) => Some(Constant::Int(v as i16 as i32)),
+			(Opcode::LNeg, Constant::Long(v)) => Some(Constant::Long(v.wrapping_neg())),
+			(Opcode::L2i, Constant::Long(v)) => Some(Constant::Int(v as i32)),
+			(Opcode::L2f, Constant::Long(v)) => Some(Constant::float(v as f32)),
+			(Opcode::L2d, Constant::Long(v)) => Some(Constant::double(v as f64)),
+			// the saturating casts of Rust match the semantics of the JVM, including NaN becoming 0
+			(Opcode::FNeg, Constant::Float(v)) => Some(Constant::float(-f32::from_bits(v))),
+			(Opcode::F2i, Constant::Float(v)) => Some(Constant::Int(f32::from_bits(v) as i32)),
+			(Opcode::F2l, Constant::Float(v)) => Some(Constant::Long(f32::from_bits(v) as i64)),
+			(Opcode::F2d, Constant::Float(v)) => Some(Constant::double(f32::from_bits(v) as f64)),
+			(Opcode::DNeg, Constant::Double(v)) => Some(Constant::double(-f64::from_bits(v))),
+			(Opcode::D2i, Constant::Double(v)) => Some(Constant::Int(f64::from_bits(v) as i32)),
+			(Opcode::D2l, Constant::Double(v)) => Some(Constant::Long(f64::from_bits(v) as i64)),
+			(Opcode::D2f, Constant::Double(v)) => Some(Constant::float(f64::from_bits(v) as f32)),
+			(Opcode::CheckCast(_), Constant::Null) => Some(Constant::Null),
+			(Opcode::InstanceOf(_), Constant::Null) => Some(Constant::Int(0)),
+			_ => None,
+		};
+		result.unwrap_or(Constant::Unknown(kind))
+	}
+
+	fn binary(&self, opcode: &Opcode, value1: &Constant, value2: &Constant, kind: ValueKind) -> Constant {
+		let result = match (*value1, *value2) {
+			(Constant::Int(a), Constant::Int(b)) => match opcode {
+				Opcode::IAdd => Some(a.wrapping_add(b)),
+				Opcode::ISub => Some(a.wrapping_sub(b)),
+				Opcode::IMul => Some(a.wrapping_mul(b)),
+				// division by zero throws, so there is no value
+				Opcode::IDiv if b != 0 => Some(a.wrapping_div(b)),
+				Opcode::IRem if b != 0 => Some(a.wrapping_rem(b)),
+				Opcode::IAnd => Some(a & b),
+				Opcode::IOr => Some(a | b),
+				Opcode::IXor => Some(a ^ b),
+				Opcode::IShl => Some(a.wrapping_shl(b as u32)),
+				Opcode::IShr => Some(a.wrapping_shr(b as u32)),
+				Opcode::IUShr => Some((a as u32).wrapping_shr(b as u32) as i32),
+				_ => None,
+			}.map(Constant::Int),
+			(Constant::Long(a), Constant::Long(b)) => match opcode {
+				Opcode::LAdd => Some(Constant::Long(a.wrapping_add(b))),
+				Opcode::LSub => Some(Constant::Long(a.wrapping_sub(b))),
+				Opcode::LMul => Some(Constant::Long(a.wrapping_mul(b))),
+				Opcode::LDiv if b != 0 => Some(Constant::Long(a.wrapping_div(b))),
+				Opcode::LRem if b != 0 => Some(Constant::Long(a.wrapping_rem(b))),
+				Opcode::LAnd => Some(Constant::Long(a & b)),
+				Opcode::LOr => Some(Constant::Long(a | b)),
+				Opcode::LXor => Some(Constant::Long(a ^ b)),
+				Opcode::LCmp => Some(Constant::Int(a.cmp(&b) as i32)),
+				_ => None,
+			},
+			(Constant::Long(a), Constant::Int(b)) => match opcode {
+				Opcode::LShl => Some(a.wrapping_shl(b as u32)),
+				Opcode::LShr => Some(a.wrapping_shr(b as u32)),
+				Opcode::LUShr => Some((a as u64).wrapping_shr(b as u32) as i64),
+				_ => None,
+			}.map(Constant::Long),
+			(Constant::Float(a), Constant::Float(b)) => {
+				let (a, b) = (f32::from_bits(a), f32::from_bits(b));
+				match opcode {
+					Opcode::FAdd => Some(Constant::float(a + b)),
+					Opcode::FSub => Some(Constant::float(a - b)),
+					Opcode::FMul => Some(Constant::float(a * b)),
+					Opcode::FDiv => Some(Constant::float(a / b)),
+					Opcode::FRem => Some(Constant::float(a % b)),
+					Opcode::FCmpL => Some(Constant::Int(a.partial_cmp(&b).map_or(-1, |o| o as i32))),
+					Opcode::FCmpG => Some(Constant::Int(a.partial_cmp(&b).map_or(1, |o| o as i32))),
+					_ => None,
+				}
+			},
+			(Constant::Double(a), Constant::Double(b)) => {
+				let (a, b) = (f64::from_bits(a), f64::from_bits(b));
+				match opcode {
+					Opcode::DAdd => Some(Constant::double(a + b)),
+					Opcode::DSub => Some(Constant::double(a - b)),
+					Opcode::DMul => Some(Constant::double(a * b)),
+					Opcode::DDiv => Some(Constant::double(a / b)),
+					Opcode::DRem => Some(Constant::double(a % b)),
+					Opcode::DCmpL => Some(Constant::Int(a.partial_cmp(&b).map_or(-1, |o| o as i32))),
+					Opcode::DCmpG => Some(Constant::Int(a.partial_cmp(&b).map_or(1, |o| o as i32))),
+					_ => None,
+				}
+			},
+			_ => None,
+		};
+		result.unwrap_or(Constant::Unknown(kind))
+	}
+
+	fn nary(&self, _opcode: &Opcode, _values: &[Constant], kind: ValueKind) -> Constant {
+		Constant::Unknown(kind)
+	}
+
+	fn merge(&self, value1: &Constant, value2: &Constant) -> Constant {
+		if value1 == value2 {
+			*value1
+		} else if value1.kind() == value2.kind() {
+			value1.kind().map_or(Constant::Top, Constant::Unknown)
+		} else {
+			Constant::Top
+		}
+	}
+}
+
+/// Whether a value may be `null`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Nullness {
+	/// An unusable value, like an unassigned local variable.
+	Top,
+	/// A value that is not a reference.
+	Primitive(ValueKind),
+	Null,
+	NonNull,
+	MaybeNull,
+}
+
+/// Tracks which references are known to be `null` or not. References created by `new`, `ldc` and the array creation instructions, `this` and caught
+/// exceptions are never `null`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullnessInterpreter;
+
+impl NullnessInterpreter {
+	fn value(kind: ValueKind, nullness: Nullness) -> Nullness {
+		match kind {
+			ValueKind::Reference => nullness,
+			kind => Nullness::Primitive(kind),
+		}
+	}
+}
+
+impl Interpreter for NullnessInterpreter {
+	type Value = Nullness;
+
+	fn unknown(&self, kind: Option<ValueKind>) -> Nullness {
+		kind.map_or(Nullness::Top, |kind| Self::value(kind, Nullness::MaybeNull))
+	}
+
+	fn size(&self, value: &Nullness) -> usize {
+		match value {
+			Nullness::Primitive(kind) => kind.size(),
+			_ => 1,
+		}
+	}
+
+	fn parameter(&self, kind: ValueKind, is_this: bool) -> Nullness {
+		Self::value(kind, if is_this { Nullness::NonNull } else { Nullness::MaybeNull })
+	}
+
+	fn caught_exception(&self, _entry: &ExceptionTableEntry) -> Nullness {
+		Nullness::NonNull
+	}
+
+	fn constant(&self, opcode: &Opcode, kind: ValueKind) -> Nullness {
+		Self::value(kind, match opcode {
+			Opcode::AConstNull => Nullness::Null,
+			Opcode::GetStatic(_) => Nullness::MaybeNull,
+			_ => Nullness::NonNull,
+		})
+	}
+
+	fn unary(&self, opcode: &Opcode, value: &Nullness, kind: ValueKind) -> Nullness {
+		Self::value(kind, match opcode {
+			Opcode::CheckCast(_) => *value,
+			Opcode::NewArray { .. } | Opcode::ANewArray(_) => Nullness::NonNull,
+			_ => Nullness::MaybeNull,
+		})
+	}
+
+	fn binary(&self, _opcode: &Opcode, _value1: &Nullness, _value2: &Nullness, kind: ValueKind) -> Nullness {
+		Self::value(kind, Nullness::MaybeNull)
+	}
+
+	fn nary(&self, opcode: &Opcode, _values: &[Nullness], kind: ValueKind) -> Nullness {
+		Self::value(kind, match opcode {
+			Opcode::MultiANewArray(..) => Nullness::NonNull,
+			_ => Nullness::MaybeNull,
+		})
+	}
+
+	fn merge(&self, value1: &Nullness, value2: &Nullness) -> Nullness {
+		match (*value1, *value2) {
+			(a, b) if a == b => a,
+			(Nullness::Null | Nullness::NonNull | Nullness::MaybeNull, Nullness::Null | Nullness::NonNull | Nullness::MaybeNull) => Nullness::MaybeNull,
+			_ => Nullness::Top,
+		}
+	}
+}
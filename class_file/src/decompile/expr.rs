@@ -0,0 +1,869 @@
+//! Expressions rebuilt from the operand stack, and how they are written.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+use crate::cp::{FieldRefInfo, MethodHandleInfo};
+use crate::decompile::types::{text, Names};
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+use crate::name::{ClassName, MethodName};
+use crate::signature::TypeSignature;
+
+/// The type of an expression, as far as the instructions tell. `boolean`, `byte`, `char` and `short` values are only told apart from `int`s by
+/// descriptors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Type {
+	Boolean,
+	Byte,
+	Char,
+	Short,
+	Int,
+	Long,
+	Float,
+	Double,
+	/// A class or array type.
+	Reference(FieldDescriptor),
+	Null,
+	Void,
+}
+
+impl Type {
+	pub(crate) fn of(descriptor: &FieldDescriptor) -> Type {
+		if descriptor.array_dimension > 0 {
+			return Type::Reference(*descriptor);
+		}
+		match descriptor.base_type {
+			BaseOrObjectType::B => Type::Byte,
+			BaseOrObjectType::C => Type::Char,
+			BaseOrObjectType::D => Type::Double,
+			BaseOrObjectType::F => Type::Float,
+			BaseOrObjectType::I => Type::Int,
+			BaseOrObjectType::J => Type::Long,
+			BaseOrObjectType::S => Type::Short,
+			BaseOrObjectType::Z => Type::Boolean,
+			BaseOrObjectType::Object(_) => Type::Reference(*descriptor),
+		}
+	}
+
+	pub(crate) fn class(class: ClassName) -> Type {
+		Type::Reference(FieldDescriptor { array_dimension: 0, base_type: BaseOrObjectType::Object(class) })
+	}
+
+	pub(crate) fn object() -> Type {
+		Type::class(ClassName::from(b"java/lang/Object"))
+	}
+
+	pub(crate) fn string() -> Type {
+		Type::class(ClassName::from(b"java/lang/String"))
+	}
+
+	/// The type of the class literal or `ldc` of a class, which may name an array class.
+	pub(crate) fn of_class(class: ClassName) -> Type {
+		match FieldDescriptor::try_from(class.as_bytes()) {
+			Ok(descriptor) if descriptor.array_dimension > 0 => Type::Reference(descriptor),
+			_ => Type::class(class),
+		}
+	}
+
+	/// Whether the value takes two slots on the stack and in the locals.
+	pub(crate) fn is_wide(&self) -> bool {
+		matches!(self, Type::Long | Type::Double)
+	}
+
+	pub(crate) fn is_floating(&self) -> bool {
+		matches!(self, Type::Float | Type::Double)
+	}
+
+	/// The type of the components, for an array type.
+	pub(crate) fn component(&self) -> Option<Type> {
+		match self {
+			Type::Reference(descriptor) if descriptor.array_dimension > 0 => Some(Type::of(&FieldDescriptor {
+				array_dimension: descriptor.array_dimension - 1,
+				base_type: descriptor.base_type,
+			})),
+			_ => None,
+		}
+	}
+
+	/// The descriptor of the type, with `Object` for `null` and `void`.
+	pub(crate) fn descriptor(&self) -> FieldDescriptor {
+		let base_type = match self {
+			Type::Reference(descriptor) => return *descriptor,
+			Type::Boolean => BaseOrObjectType::Z,
+			Type::Byte => BaseOrObjectType::B,
+			Type::Char => BaseOrObjectType::C,
+			Type::Short => BaseOrObjectType::S,
+			Type::Int => BaseOrObjectType::I,
+			Type::Long => BaseOrObjectType::J,
+			Type::Float => BaseOrObjectType::F,
+			Type::Double => BaseOrObjectType::D,
+			Type::Null | Type::Void => BaseOrObjectType::Object(ClassName::from(b"java/lang/Object")),
+		};
+		FieldDescriptor { array_dimension: 0, base_type }
+	}
+
+	pub(crate) fn write(&self, names: &Names) -> String {
+		String::from(match self {
+			Type::Boolean => "boolean",
+			Type::Byte => "byte",
+			Type::Char => "char",
+			Type::Short => "short",
+			Type::Int => "int",
+			Type::Long => "long",
+			Type::Float => "float",
+			Type::Double => "double",
+			Type::Reference(descriptor) => return names.descriptor(descriptor),
+			Type::Null => return names.class(&ClassName::from(b"java/lang/Object")),
+			Type::Void => "void",
+		})
+	}
+}
+
+/// A local variable of a method, a parameter or a variable introduced for a value on the stack.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Variable {
+	pub(crate) name: String,
+	pub(crate) ty: Type,
+	/// The generic type, from the `LocalVariableTypeTable` or the signature of the method.
+	pub(crate) signature: Option<TypeSignature>,
+}
+
+impl Variable {
+	pub(crate) fn write_type(&self, names: &Names) -> String {
+		match &self.signature {
+			Some(signature) => names.signature(signature),
+			None => self.ty.write(names),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnaryOp {
+	Neg,
+	Not,
+	BitNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BinaryOp {
+	Add, Sub, Mul, Div, Rem,
+	Shl, Shr, UShr,
+	And, Or, Xor,
+	Eq, Ne, Lt, Ge, Gt, Le,
+	AndAnd, OrOr,
+}
+
+impl BinaryOp {
+	fn symbol(self) -> &'static str {
+		match self {
+			BinaryOp::Add => "+",
+			BinaryOp::Sub => "-",
+			BinaryOp::Mul => "*",
+			BinaryOp::Div => "/",
+			BinaryOp::Rem => "%",
+			BinaryOp::Shl => "<<",
+			BinaryOp::Shr => ">>",
+			BinaryOp::UShr => ">>>",
+			BinaryOp::And => "&",
+			BinaryOp::Or => "|",
+			BinaryOp::Xor => "^",
+			BinaryOp::Eq => "==",
+			BinaryOp::Ne => "!=",
+			BinaryOp::Lt => "<",
+			BinaryOp::Ge => ">=",
+			BinaryOp::Gt => ">",
+			BinaryOp::Le => "<=",
+			BinaryOp::AndAnd => "&&",
+			BinaryOp::OrOr => "||",
+		}
+	}
+
+	fn precedence(self) -> u8 {
+		match self {
+			BinaryOp::OrOr => 3,
+			BinaryOp::AndAnd => 4,
+			BinaryOp::Or => 5,
+			BinaryOp::Xor => 6,
+			BinaryOp::And => 7,
+			BinaryOp::Eq | BinaryOp::Ne => 8,
+			BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Gt | BinaryOp::Le => 9,
+			BinaryOp::Shl | BinaryOp::Shr | BinaryOp::UShr => 10,
+			BinaryOp::Add | BinaryOp::Sub => 11,
+			BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 12,
+		}
+	}
+
+	pub(crate) fn is_comparison(self) -> bool {
+		matches!(self, BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Gt | BinaryOp::Le)
+	}
+
+	pub(crate) fn inverted(self) -> Option<BinaryOp> {
+		match self {
+			BinaryOp::Eq => Some(BinaryOp::Ne),
+			BinaryOp::Ne => Some(BinaryOp::Eq),
+			BinaryOp::Lt => Some(BinaryOp::Ge),
+			BinaryOp::Ge => Some(BinaryOp::Lt),
+			BinaryOp::Gt => Some(BinaryOp::Le),
+			BinaryOp::Le => Some(BinaryOp::Gt),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InvokeKind {
+	Static,
+	/// `invokevirtual` and `invokeinterface`.
+	Virtual,
+	/// Constructors, private methods and methods of the super class.
+	Special,
+}
+
+const ASSIGNMENT: u8 = 1;
+const CONDITIONAL: u8 = 2;
+const UNARY: u8 = 13;
+const POSTFIX: u8 = 14;
+const PRIMARY: u8 = 15;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+	Int(i32),
+	Long(i64),
+	Float(u32),
+	Double(u64),
+	Boolean(bool),
+	Char(u16),
+	/// A string literal in modified UTF-8.
+	String(Vec<u8>),
+	Null,
+	/// A class literal like `String.class`.
+	Class(Type),
+	/// A constant without a literal, like a method handle, written as a comment.
+	Constant(String, Type),
+	This(ClassName),
+	Local(usize),
+	/// The exception caught, at the start of a handler.
+	Caught(Type),
+	/// A value on the stack at the start of a block that is reached from blocks leaving different values there, until it's replaced.
+	Stack { node: usize, slot: usize, ty: Type },
+	Field { target: Option<Box<Expr>>, field: FieldRefInfo },
+	ArrayElement { array: Box<Expr>, index: Box<Expr>, ty: Type },
+	ArrayLength(Box<Expr>),
+	Invoke { kind: InvokeKind, target: Option<Box<Expr>>, class: ClassName, name: MethodName, descriptor: MethodDescriptor, arguments: Vec<Expr> },
+	/// The result of a `new` before its constructor is called, told apart by the offset of the `new`.
+	Uninitialized { class: ClassName, offset: usize },
+	New { class: ClassName, descriptor: MethodDescriptor, arguments: Vec<Expr> },
+	/// An array of type `ty`, created with the lengths of its first dimensions, or with its elements.
+	NewArray { ty: FieldDescriptor, dimensions: Vec<Expr>, elements: Option<Vec<Expr>> },
+	Unary(UnaryOp, Box<Expr>),
+	/// The type is the one of the operands.
+	Binary { op: BinaryOp, ty: Type, left: Box<Expr>, right: Box<Expr> },
+	/// An `lcmp`, `fcmpl`, `fcmpg`, `dcmpl` or `dcmpg`, which the branch using it turns into a comparison. The `g` variants compare NaN as greater.
+	Compare { ty: Type, left: Box<Expr>, right: Box<Expr>, nan_greater: bool },
+	Cast(Type, Box<Expr>),
+	InstanceOf(Box<Expr>, Type),
+	Conditional { condition: Box<Expr>, then: Box<Expr>, otherwise: Box<Expr> },
+	Assign(Box<Expr>, Box<Expr>),
+	/// An `iinc`, as `i++` if it's not `prefix`, and as `++i` or `i += by` otherwise.
+	Increment { target: Box<Expr>, by: i32, prefix: bool },
+	Concat(Vec<Expr>),
+	/// A lambda or method reference, with the number of parameters of the method implemented.
+	Lambda { ty: Type, parameters: usize, implementation: MethodHandleInfo, captured: Vec<Expr> },
+	/// Any other `invokedynamic`.
+	Dynamic { name: MethodName, descriptor: MethodDescriptor, arguments: Vec<Expr> },
+}
+
+impl Expr {
+	pub(crate) fn ty(&self, variables: &[Variable]) -> Type {
+		match self {
+			Expr::Int(_) => Type::Int,
+			Expr::Long(_) => Type::Long,
+			Expr::Float(_) => Type::Float,
+			Expr::Double(_) => Type::Double,
+			Expr::Boolean(_) => Type::Boolean,
+			Expr::Char(_) => Type::Char,
+			Expr::String(_) | Expr::Concat(_) => Type::string(),
+			Expr::Null => Type::Null,
+			Expr::Class(_) => Type::class(ClassName::from(b"java/lang/Class")),
+			Expr::Constant(_, ty) | Expr::Caught(ty) | Expr::Stack { ty, .. } | Expr::ArrayElement { ty, .. } | Expr::Cast(ty, _) | Expr::Lambda { ty, .. } => *ty,
+			Expr::This(class) | Expr::Uninitialized { class, .. } | Expr::New { class, .. } => Type::class(*class),
+			Expr::Local(variable) => variables[*variable].ty,
+			Expr::Field { field, .. } => Type::of(&field.descriptor),
+			Expr::ArrayLength(_) | Expr::Compare { .. } => Type::Int,
+			Expr::Invoke { descriptor, .. } | Expr::Dynamic { descriptor, .. } => descriptor.return_type.as_ref().map_or(Type::Void, Type::of),
+			Expr::NewArray { ty, .. } => Type::Reference(*ty),
+			Expr::Unary(UnaryOp::Not, _) | Expr::InstanceOf(..) => Type::Boolean,
+			Expr::Unary(_, operand) => operand.ty(variables),
+			Expr::Binary { op, ty, .. } => if op.is_comparison() || matches!(op, BinaryOp::AndAnd | BinaryOp::OrOr) { Type::Boolean } else { *ty },
+			Expr::Conditional { then, otherwise, .. } => match then.ty(variables) {
+				Type::Null => otherwise.ty(variables),
+				ty => ty,
+			},
+			Expr::Assign(target, _) | Expr::Increment { target, .. } => target.ty(variables),
+		}
+	}
+
+	/// Calls `f` with the expressions this one is made of.
+	pub(crate) fn for_each_child(&self, f: &mut dyn FnMut(&Expr)) {
+		match self {
+			Expr::Field { target, .. } | Expr::Invoke { target, .. } => {
+				if let Some(target) = target {
+					f(target);
+				}
+				if let Expr::Invoke { arguments, .. } = self {
+					arguments.iter().for_each(f);
+				}
+			},
+			Expr::ArrayElement { array, index, .. } => {
+				f(array);
+				f(index);
+			},
+			Expr::ArrayLength(operand) | Expr::Unary(_, operand) | Expr::Cast(_, operand) | Expr::InstanceOf(operand, _) => f(operand),
+			Expr::Increment { target, .. } => f(target),
+			Expr::New { arguments, .. } | Expr::Concat(arguments) | Expr::Dynamic { arguments, .. } | Expr::Lambda { captured: arguments, .. } => arguments.iter().for_each(f),
+			Expr::NewArray { dimensions, elements, .. } => {
+				dimensions.iter().for_each(&mut *f);
+				elements.iter().flatten().for_each(f);
+			},
+			Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Assign(left, right) => {
+				f(left);
+				f(right);
+			},
+			Expr::Conditional { condition, then, otherwise } => {
+				f(condition);
+				f(then);
+				f(otherwise);
+			},
+			_ => {},
+		}
+	}
+
+	pub(crate) fn for_each_child_mut(&mut self, f: &mut dyn FnMut(&mut Expr)) {
+		match self {
+			Expr::Field { target: Some(target), .. } => f(target),
+			Expr::Invoke { target, arguments, .. } => {
+				if let Some(target) = target {
+					f(target);
+				}
+				arguments.iter_mut().for_each(f);
+			},
+			Expr::ArrayElement { array, index, .. } => {
+				f(array);
+				f(index);
+			},
+			Expr::ArrayLength(operand) | Expr::Unary(_, operand) | Expr::Cast(_, operand) | Expr::InstanceOf(operand, _) => f(operand),
+			Expr::Increment { target, .. } => f(target),
+			Expr::New { arguments, .. } | Expr::Concat(arguments) | Expr::Dynamic { arguments, .. } | Expr::Lambda { captured: arguments, .. } => arguments.iter_mut().for_each(f),
+			Expr::NewArray { dimensions, elements, .. } => {
+				dimensions.iter_mut().for_each(&mut *f);
+				elements.iter_mut().flatten().for_each(f);
+			},
+			Expr::Binary { left, right, .. } | Expr::Compare { left, right, .. } | Expr::Assign(left, right) => {
+				f(left);
+				f(right);
+			},
+			Expr::Conditional { condition, then, otherwise } => {
+				f(condition);
+				f(then);
+				f(otherwise);
+			},
+			_ => {},
+		}
+	}
+
+	/// Calls `f` with this expression and all expressions it's made of.
+	pub(crate) fn visit(&self, f: &mut dyn FnMut(&Expr)) {
+		f(self);
+		self.for_each_child(&mut |child| child.visit(f));
+	}
+
+	/// Returns whether `f` holds for this expression or any expression it's made of.
+	pub(crate) fn any(&self, f: &dyn Fn(&Expr) -> bool) -> bool {
+		if f(self) {
+			return true;
+		}
+		let mut found = false;
+		self.for_each_child(&mut |child| found = found || child.any(f));
+		found
+	}
+
+	/// Replaces this expression and all it's made of, bottom up, with what `f` returns for them.
+	pub(crate) fn replace(&mut self, f: &mut dyn FnMut(&Expr) -> Option<Expr>) {
+		self.for_each_child_mut(&mut |child| child.replace(f));
+		if let Some(replacement) = f(self) {
+			*self = replacement;
+		}
+	}
+
+	/// Whether the expression can be written twice, as in `x = x + y`, without evaluating something different: a variable, or a field or an
+	/// array element of one.
+	fn is_simple_target(&self) -> bool {
+		match self {
+			Expr::Local(_) | Expr::This(_) | Expr::Int(_) => true,
+			Expr::Field { target, .. } => target.as_deref().is_none_or(Expr::is_simple_target),
+			Expr::ArrayElement { array, index, .. } => array.is_simple_target() && index.is_simple_target(),
+			_ => false,
+		}
+	}
+
+	/// Whether evaluating the expression later than the instructions did, or not at all, gives the same value and has the same effects: it has no
+	/// side effects, doesn't read fields or array elements, can't throw and doesn't call code of the program, like the `toString` of a concatenation.
+	pub(crate) fn is_pure(&self) -> bool {
+		!self.any(&|expr| match expr {
+			Expr::Field { .. } | Expr::ArrayElement { .. } | Expr::Invoke { .. } | Expr::New { .. } | Expr::NewArray { .. } | Expr::Assign(..) |
+			Expr::Increment { .. } | Expr::Dynamic { .. } | Expr::ArrayLength(_) | Expr::Concat(_) | Expr::Cast(Type::Reference(_), _) => true,
+			// integer division by zero throws
+			Expr::Binary { op: BinaryOp::Div | BinaryOp::Rem, ty, right, .. } => {
+				!ty.is_floating() && !matches!(**right, Expr::Int(1..) | Expr::Int(..=-1) | Expr::Long(1..) | Expr::Long(..=-1))
+			},
+			_ => false,
+		})
+	}
+
+	pub(crate) fn reads(&self, variable: usize) -> bool {
+		self.any(&|expr| *expr == Expr::Local(variable))
+	}
+
+	/// Returns the expression with the opposite truth value.
+	pub(crate) fn negate(self) -> Expr {
+		match self {
+			Expr::Unary(UnaryOp::Not, operand) => *operand,
+			Expr::Boolean(value) => Expr::Boolean(!value),
+			// with a NaN operand, all comparisons but != are false, so only those two are each other's opposites
+			Expr::Binary { op, ty, left, right } if op.is_comparison() && (!ty.is_floating() || matches!(op, BinaryOp::Eq | BinaryOp::Ne)) => {
+				Expr::Binary { op: op.inverted().unwrap_or(op), ty, left, right }
+			},
+			Expr::Binary { op: BinaryOp::AndAnd, ty, left, right } => {
+				Expr::Binary { op: BinaryOp::OrOr, ty, left: Box::new(left.negate()), right: Box::new(right.negate()) }
+			},
+			Expr::Binary { op: BinaryOp::OrOr, ty, left, right } => {
+				Expr::Binary { op: BinaryOp::AndAnd, ty, left: Box::new(left.negate()), right: Box::new(right.negate()) }
+			},
+			operand => Expr::Unary(UnaryOp::Not, Box::new(operand)),
+		}
+	}
+
+	/// Turns an `int` into a value of type `ty`, where the instructions don't tell them apart: `0` and `1` into `false` and `true`, and numbers into
+	/// characters.
+	pub(crate) fn coerce(self, ty: Type) -> Expr {
+		match (ty, self) {
+			(Type::Boolean, Expr::Int(value @ (0 | 1))) => Expr::Boolean(value == 1),
+			(Type::Boolean, Expr::Conditional { condition, then, otherwise }) => {
+				match (then.coerce(ty), otherwise.coerce(ty)) {
+					(Expr::Boolean(true), Expr::Boolean(false)) => *condition,
+					(Expr::Boolean(false), Expr::Boolean(true)) => condition.negate(),
+					(Expr::Boolean(true), otherwise) => Expr::Binary { op: BinaryOp::OrOr, ty, left: condition, right: Box::new(otherwise) },
+					(then, Expr::Boolean(false)) => Expr::Binary { op: BinaryOp::AndAnd, ty, left: condition, right: Box::new(then) },
+					(then, otherwise) => Expr::Conditional { condition, then: Box::new(then), otherwise: Box::new(otherwise) },
+				}
+			},
+			(Type::Char, Expr::Int(value)) if (0..=0xffff).contains(&value) => Expr::Char(value as u16),
+			(Type::Char, Expr::Conditional { condition, then, otherwise }) => Expr::Conditional {
+				condition, then: Box::new(then.coerce(ty)), otherwise: Box::new(otherwise.coerce(ty)),
+			},
+			(_, expr) => expr,
+		}
+	}
+
+	fn precedence(&self) -> u8 {
+		match self {
+			Expr::Int(value) if *value < 0 => UNARY,
+			Expr::Long(value) if *value < 0 => UNARY,
+			Expr::Float(bits) if f32::from_bits(*bits).is_sign_negative() => UNARY,
+			Expr::Double(bits) if f64::from_bits(*bits).is_sign_negative() => UNARY,
+			Expr::Assign(..) => ASSIGNMENT,
+			Expr::Increment { prefix: true, by, .. } if by.abs() != 1 => ASSIGNMENT,
+			Expr::Lambda { implementation, .. } if is_lambda_body(implementation) => ASSIGNMENT,
+			Expr::Conditional { .. } => CONDITIONAL,
+			Expr::Binary { op, .. } => op.precedence(),
+			Expr::Concat(_) => BinaryOp::Add.precedence(),
+			Expr::InstanceOf(..) => BinaryOp::Lt.precedence(),
+			Expr::Unary(..) | Expr::Cast(..) | Expr::Increment { prefix: true, .. } | Expr::NewArray { .. } => UNARY,
+			Expr::Increment { .. } => POSTFIX,
+			_ => PRIMARY,
+		}
+	}
+
+	/// Writes the expression, in parentheses if it binds less tightly than `precedence`.
+	pub(crate) fn write(&self, out: &mut String, printer: &Printer, precedence: u8) {
+		let parenthesized = self.precedence() < precedence;
+		if parenthesized {
+			out.push('(');
+		}
+		self.write_unparenthesized(out, printer);
+		if parenthesized {
+			out.push(')');
+		}
+	}
+
+	pub(crate) fn to_source(&self, printer: &Printer) -> String {
+		let mut out = String::new();
+		self.write(&mut out, printer, 0);
+		out
+	}
+
+	fn write_unparenthesized(&self, out: &mut String, printer: &Printer) {
+		let names = printer.names;
+		match self {
+			Expr::Int(value) => { let _ = write!(out, "{value}"); },
+			Expr::Long(value) => { let _ = write!(out, "{value}L"); },
+			Expr::Float(bits) => write_floating(out, f32::from_bits(*bits) as f64, "Float", "F"),
+			Expr::Double(bits) => write_floating(out, f64::from_bits(*bits), "Double", ""),
+			Expr::Boolean(value) => { let _ = write!(out, "{value}"); },
+			Expr::Char(value) => {
+				out.push('\'');
+				write_escaped(out, &[*value], '\'');
+				out.push('\'');
+			},
+			Expr::String(bytes) => {
+				out.push('"');
+				write_escaped(out, &utf16(bytes), '"');
+				out.push('"');
+			},
+			Expr::Null => out.push_str("null"),
+			Expr::Class(ty) => {
+				out.push_str(&ty.write(names));
+				out.push_str(".class");
+			},
+			Expr::Constant(text, _) => out.push_str(text),
+			Expr::This(_) => out.push_str("this"),
+			Expr::Local(variable) => out.push_str(&printer.variables[*variable].name),
+			Expr::Caught(_) => out.push_str(CAUGHT),
+			Expr::Stack { node, slot, .. } => { let _ = write!(out, "stack{node}_{slot}"); },
+			Expr::Field { target, field } => {
+				match target {
+					Some(target) => {
+						target.write(out, printer, POSTFIX);
+						out.push('.');
+					},
+					None if field.class == printer.class => {},
+					None => {
+						out.push_str(&names.class(&field.class));
+						out.push('.');
+					},
+				}
+				out.push_str(&text(field.name.as_bytes()));
+			},
+			Expr::ArrayElement { array, index, .. } => {
+				array.write(out, printer, POSTFIX);
+				out.push('[');
+				index.write(out, printer, 0);
+				out.push(']');
+			},
+			Expr::ArrayLength(array) => {
+				array.write(out, printer, POSTFIX);
+				out.push_str(".length");
+			},
+			Expr::Invoke { kind, target, class, name, arguments, .. } => {
+				let name = text(name.as_bytes());
+				match (kind, target.as_deref()) {
+					(InvokeKind::Special, Some(Expr::This(_))) if name == "<init>" => {
+						out.push_str(if *class == printer.class { "this" } else { "super" });
+					},
+					(InvokeKind::Special, Some(Expr::This(_))) if *class != printer.class => {
+						out.push_str("super.");
+						out.push_str(&name);
+					},
+					(_, Some(Expr::This(_))) => out.push_str(&name),
+					(_, Some(target)) => {
+						target.write(out, printer, POSTFIX);
+						out.push('.');
+						out.push_str(&name);
+					},
+					(_, None) if *class == printer.class => out.push_str(&name),
+					(_, None) => {
+						out.push_str(&names.class(class));
+						out.push('.');
+						out.push_str(&name);
+					},
+				}
+				write_arguments(out, printer, arguments);
+			},
+			Expr::Uninitialized { class, .. } => {
+				out.push_str("/* uninitialized */ new ");
+				out.push_str(&names.class(class));
+			},
+			Expr::New { class, arguments, .. } => {
+				out.push_str("new ");
+				out.push_str(&names.class(class));
+				write_arguments(out, printer, arguments);
+			},
+			Expr::NewArray { ty, dimensions, elements } => {
+				out.push_str("new ");
+				out.push_str(&names.descriptor(&FieldDescriptor { array_dimension: 0, base_type: ty.base_type }));
+				match elements {
+					Some(elements) => {
+						for _ in 0..ty.array_dimension {
+							out.push_str("[]");
+						}
+						out.push('{');
+						for (index, element) in elements.iter().enumerate() {
+							if index > 0 {
+								out.push_str(", ");
+							}
+							element.write(out, printer, ASSIGNMENT);
+						}
+						out.push('}');
+					},
+					None => {
+						for dimension in dimensions {
+							out.push('[');
+							dimension.write(out, printer, 0);
+							out.push(']');
+						}
+						for _ in dimensions.len()..ty.array_dimension {
+							out.push_str("[]");
+						}
+					},
+				}
+			},
+			Expr::Unary(op, operand) => {
+				out.push_str(match op {
+					UnaryOp::Neg => "-",
+					UnaryOp::Not => "!",
+					UnaryOp::BitNot => "~",
+				});
+				// `- -x` must not become `--x`
+				let precedence = if *op == UnaryOp::Neg && operand.precedence() == UNARY { PRIMARY } else { UNARY };
+				operand.write(out, printer, precedence);
+			},
+			Expr::Binary { op, left, right, .. } => {
+				left.write(out, printer, op.precedence());
+				out.push(' ');
+				out.push_str(op.symbol());
+				out.push(' ');
+				right.write(out, printer, op.precedence() + 1);
+			},
+			Expr::Compare { ty, left, right, .. } => {
+				out.push_str(match ty {
+					Type::Long => "Long.compare(",
+					Type::Float => "Float.compare(",
+					_ => "Double.compare(",
+				});
+				left.write(out, printer, ASSIGNMENT);
+				out.push_str(", ");
+				right.write(out, printer, ASSIGNMENT);
+				out.push(')');
+			},
+			Expr::Cast(ty, operand) => {
+				out.push('(');
+				out.push_str(&ty.write(names));
+				out.push_str(") ");
+				operand.write(out, printer, UNARY);
+			},
+			Expr::InstanceOf(operand, ty) => {
+				operand.write(out, printer, BinaryOp::Lt.precedence());
+				out.push_str(" instanceof ");
+				out.push_str(&ty.write(names));
+			},
+			Expr::Conditional { condition, then, otherwise } => {
+				condition.write(out, printer, BinaryOp::OrOr.precedence());
+				out.push_str(" ? ");
+				then.write(out, printer, ASSIGNMENT);
+				out.push_str(" : ");
+				otherwise.write(out, printer, CONDITIONAL);
+			},
+			Expr::Assign(target, value) => {
+				target.write(out, printer, POSTFIX);
+				match &**value {
+					// `x = x + y` as `x += y`
+					Expr::Binary { op, left, right, .. } if left == target && target.is_simple_target() && !op.is_comparison() && !matches!(op, BinaryOp::AndAnd | BinaryOp::OrOr) => {
+						let _ = write!(out, " {}= ", op.symbol());
+						right.write(out, printer, ASSIGNMENT);
+					},
+					value => {
+						out.push_str(" = ");
+						value.write(out, printer, ASSIGNMENT);
+					},
+				}
+			},
+			Expr::Increment { target, by, prefix } => match (prefix, by) {
+				(false, 1) | (false, -1) => {
+					target.write(out, printer, POSTFIX);
+					out.push_str(if *by == 1 { "++" } else { "--" });
+				},
+				(true, 1) | (true, -1) => {
+					out.push_str(if *by == 1 { "++" } else { "--" });
+					target.write(out, printer, UNARY);
+				},
+				_ => {
+					target.write(out, printer, POSTFIX);
+					let _ = write!(out, " {}= {}", if *by < 0 { '-' } else { '+' }, by.unsigned_abs());
+				},
+			},
+			Expr::Concat(parts) => {
+				// `+` only concatenates if one of its first two operands is a string
+				if !parts.iter().take(2).any(|part| part.ty(printer.variables) == Type::string()) {
+					out.push_str("\"\" + ");
+				}
+				for (index, part) in parts.iter().enumerate() {
+					if index > 0 {
+						out.push_str(" + ");
+					}
+					part.write(out, printer, BinaryOp::Add.precedence() + 1);
+				}
+			},
+			Expr::Lambda { parameters, implementation, captured, .. } => write_lambda(out, printer, *parameters, implementation, captured),
+			Expr::Dynamic { name, arguments, .. } => {
+				out.push_str("/* invokedynamic */ ");
+				out.push_str(&text(name.as_bytes()));
+				write_arguments(out, printer, arguments);
+			},
+		}
+	}
+}
+
+/// The name the exception caught is written with, if the handler doesn't store it in a variable.
+pub(crate) const CAUGHT: &str = "ex";
+
+/// What expressions need to be written: how to write class names, and the class and variables of the method they are from.
+pub(crate) struct Printer<'a> {
+	pub(crate) names: &'a Names,
+	pub(crate) class: ClassName,
+	pub(crate) variables: &'a [Variable],
+}
+
+fn write_arguments(out: &mut String, printer: &Printer, arguments: &[Expr]) {
+	out.push('(');
+	for (index, argument) in arguments.iter().enumerate() {
+		if index > 0 {
+			out.push_str(", ");
+		}
+		argument.write(out, printer, ASSIGNMENT);
+	}
+	out.push(')');
+}
+
+fn is_lambda_body(implementation: &MethodHandleInfo) -> bool {
+	matches!(implementation,
+		MethodHandleInfo::InvokeStatic(method) | MethodHandleInfo::InvokeSpecial(method) | MethodHandleInfo::InvokeVirtual(method)
+			if method.name.as_bytes().starts_with(b"lambda$")
+	)
+}
+
+/// Writes lambdas javac compiled to a method of the class as a call of that method, and method references as they are written in the source.
+fn write_lambda(out: &mut String, printer: &Printer, parameters: usize, implementation: &MethodHandleInfo, captured: &[Expr]) {
+	let names = printer.names;
+	let parameter_names: Vec<String> = (0..parameters).map(|index| format!("p{index}")).collect();
+	let (class, name, receiver) = match implementation {
+		MethodHandleInfo::InvokeStatic(method) => (method.class, method.name, None),
+		MethodHandleInfo::InvokeSpecial(method) | MethodHandleInfo::InvokeVirtual(method) => (method.class, method.name, captured.first()),
+		MethodHandleInfo::InvokeInterface(method) | MethodHandleInfo::InvokeStaticInterface(method) | MethodHandleInfo::InvokeSpecialInterface(method) => {
+			let receiver = if matches!(implementation, MethodHandleInfo::InvokeStaticInterface(_)) { None } else { captured.first() };
+			(method.class, method.name, receiver)
+		},
+		MethodHandleInfo::NewInvokeSpecial(method) => {
+			out.push_str(&names.class(&method.class));
+			out.push_str("::new");
+			return;
+		},
+		MethodHandleInfo::GetField(field) | MethodHandleInfo::GetStatic(field) | MethodHandleInfo::PutField(field) | MethodHandleInfo::PutStatic(field) => {
+			let _ = write!(out, "/* field handle {}.{} */ null", names.class(&field.class), text(field.name.as_bytes()));
+			return;
+		},
+	};
+	if is_lambda_body(implementation) {
+		if let [parameter] = &parameter_names[..] {
+			out.push_str(parameter);
+		} else {
+			let _ = write!(out, "({})", parameter_names.join(", "));
+		}
+		out.push_str(" -> ");
+		let arguments: Vec<Expr> = captured.iter().skip(usize::from(receiver.is_some())).cloned().collect();
+		let mut call = String::new();
+		if class != printer.class {
+			call.push_str(&names.class(&class));
+			call.push('.');
+		}
+		call.push_str(&text(name.as_bytes()));
+		out.push_str(&call);
+		out.push('(');
+		let mut first = true;
+		for argument in &arguments {
+			if !first {
+				out.push_str(", ");
+			}
+			first = false;
+			argument.write(out, printer, ASSIGNMENT);
+		}
+		for parameter in &parameter_names {
+			if !first {
+				out.push_str(", ");
+			}
+			first = false;
+			out.push_str(parameter);
+		}
+		out.push(')');
+		return;
+	}
+	match receiver {
+		Some(Expr::This(_)) if matches!(implementation, MethodHandleInfo::InvokeSpecial(_)) && class != printer.class => out.push_str("super"),
+		Some(receiver) => receiver.write(out, printer, POSTFIX),
+		None => out.push_str(&names.class(&class)),
+	}
+	out.push_str("::");
+	out.push_str(&text(name.as_bytes()));
+}
+
+fn write_floating(out: &mut String, value: f64, class: &str, suffix: &str) {
+	if value.is_nan() {
+		let _ = write!(out, "{class}.NaN");
+	} else if value.is_infinite() {
+		let _ = write!(out, "{class}.{}_INFINITY", if value > 0.0 { "POSITIVE" } else { "NEGATIVE" });
+	} else if suffix.is_empty() {
+		let _ = write!(out, "{value:?}");
+	} else {
+		// written with the precision of a float, so that the literal is the same float again
+		let _ = write!(out, "{:?}{suffix}", value as f32);
+	}
+}
+
+/// Decodes modified UTF-8 into UTF-16 code units, which Java strings are made of.
+fn utf16(bytes: &[u8]) -> Vec<u16> {
+	let mut units = Vec::with_capacity(bytes.len());
+	let mut index = 0;
+	while index < bytes.len() {
+		let byte = bytes[index] as u16;
+		let continuation = |offset: usize| bytes.get(index + offset).map(|&byte| byte as u16 & 0x3f);
+		match (byte, continuation(1), continuation(2)) {
+			(0..=0x7f, _, _) => {
+				units.push(byte);
+				index += 1;
+			},
+			(0xc0..=0xdf, Some(second), _) => {
+				units.push((byte & 0x1f) << 6 | second);
+				index += 2;
+			},
+			(0xe0..=0xef, Some(second), Some(third)) => {
+				units.push((byte & 0x0f) << 12 | second << 6 | third);
+				index += 3;
+			},
+			_ => {
+				units.push(0xfffd);
+				index += 1;
+			},
+		}
+	}
+	units
+}
+
+fn write_escaped(out: &mut String, units: &[u16], quote: char) {
+	for decoded in char::decode_utf16(units.iter().copied()) {
+		match decoded {
+			Ok(c) if c == quote || c == '\\' => {
+				out.push('\\');
+				out.push(c);
+			},
+			Ok('\n') => out.push_str("\\n"),
+			Ok('\t') => out.push_str("\\t"),
+			Ok('\r') => out.push_str("\\r"),
+			Ok('\u{8}') => out.push_str("\\b"),
+			Ok('\u{c}') => out.push_str("\\f"),
+			Ok(c) if !c.is_control() => out.push(c),
+			Ok(c) => { let _ = write!(out, "\\u{:04x}", c as u32); },
+			Err(error) => { let _ = write!(out, "\\u{:04x}", error.unpaired_surrogate()); },
+		}
+	}
+}
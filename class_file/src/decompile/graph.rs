@@ -0,0 +1,1303 @@
+//! The blocks of a method as statements, with the values they leave on the stack, and the reductions joining the blocks of conditions and
+//! conditional expressions.
+//!
+//! Each basic block becomes a [Node]: the instructions are run on a stack of expressions, instructions with effects become statements, and the
+//! branch at the end becomes its [Terminator]. Values left on the stack for a block reached from several blocks are [Expr::Stack] placeholders,
+//! until the reductions join the blocks or variables are introduced for them.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use crate::{ClassFile, MethodInfo};
+use crate::call_site::CallSiteKind;
+use crate::cfg::{BlockId, ControlFlowGraph, Dominators, EdgeKind};
+use crate::cp::attribute::{AttributeInfo, BootstrapMethodArgument, CodeAttribute, LocalVariableTableEntry};
+use crate::decompile::expr::{BinaryOp, Expr, InvokeKind, Type, UnaryOp, Variable};
+use crate::decompile::stmt::Stmt;
+use crate::decompile::types::text;
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+use crate::instruction::{BranchTarget, LvIndex};
+use crate::instruction::opcode::{ArrayType, Opcode};
+use crate::name::{ClassName, MethodName};
+use crate::signature::{MethodSignature, TypeSignature};
+
+/// How control leaves a [Node].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Terminator {
+	Jump(usize),
+	/// Continues with `then` if the condition holds, and with `otherwise` if not.
+	If { condition: Expr, then: usize, otherwise: usize },
+	Switch { value: Expr, cases: Vec<(i32, usize)>, default: usize },
+	Return(Option<Expr>),
+	Throw(Expr),
+}
+
+impl Terminator {
+	pub(crate) fn successors(&self) -> Vec<usize> {
+		match self {
+			Terminator::Jump(to) => vec![*to],
+			Terminator::If { then, otherwise, .. } => vec![*then, *otherwise],
+			Terminator::Switch { cases, default, .. } => cases.iter().map(|&(_, to)| to).chain([*default]).collect(),
+			Terminator::Return(_) | Terminator::Throw(_) => Vec::new(),
+		}
+	}
+
+	fn for_each_expr_mut(&mut self, f: &mut dyn FnMut(&mut Expr)) {
+		match self {
+			Terminator::If { condition: expr, .. } | Terminator::Switch { value: expr, .. } | Terminator::Return(Some(expr)) | Terminator::Throw(expr) => f(expr),
+			Terminator::Jump(_) | Terminator::Return(None) => {},
+		}
+	}
+}
+
+/// A basic block, or several joined by the reductions. Nodes are identified by the [BlockId] of their first block, so they are in the order of the
+/// code.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Node {
+	pub(crate) offset: usize,
+	pub(crate) statements: Vec<Stmt>,
+	pub(crate) terminator: Terminator,
+	/// The values on the stack when the node is entered and when it's left.
+	entry: Vec<Expr>,
+	exit: Vec<Expr>,
+	/// The indices of the entries of the exception table covering the node.
+	pub(crate) protection: Vec<usize>,
+	/// Whether the node is the handler of an entry of the exception table.
+	pub(crate) handler: bool,
+	/// Whether the node was joined into another one, or is unreachable.
+	pub(crate) removed: bool,
+}
+
+/// An entry of the exception table, with nodes instead of offsets.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Handler {
+	pub(crate) start: usize,
+	/// The node after the last one covered, or the number of nodes.
+	pub(crate) end: usize,
+	pub(crate) handler: usize,
+	pub(crate) catch_type: Option<ClassName>,
+}
+
+pub(crate) struct Graph {
+	pub(crate) nodes: Vec<Node>,
+	pub(crate) variables: Vec<Variable>,
+	/// The variables of the parameters, without `this`.
+	pub(crate) parameters: Vec<usize>,
+	pub(crate) handlers: Vec<Handler>,
+	/// The natural loops, as their first node, their header and their last node, in the order of the code.
+	pub(crate) loops: Vec<(usize, usize, usize)>,
+	pub(crate) dominators: Dominators,
+	return_type: Option<FieldDescriptor>,
+}
+
+impl Graph {
+	pub(crate) fn new(class: &ClassFile, method: &MethodInfo) -> Result<Graph> {
+		let code = method.code.as_ref().ok_or_else(|| anyhow!("method has no code"))?;
+		let cfg = ControlFlowGraph::new(code)?;
+		let mut builder = Builder::new(class, method, code);
+
+		let mut nodes: Vec<Option<Node>> = cfg.blocks().iter().map(|_| None).collect();
+		for id in cfg.reverse_postorder() {
+			let block = cfg.block(id);
+			let catch_types: Vec<Option<ClassName>> = code.exception_table.iter()
+				.filter(|entry| cfg.block_at_offset(entry.handler_pc) == Some(id))
+				.map(|entry| entry.catch_type)
+				.collect();
+			let handler = !catch_types.is_empty();
+			let entry = if handler {
+				let catch_type = match &catch_types[..] {
+					[Some(class), rest @ ..] if rest.iter().all(|other| *other == Some(*class)) => *class,
+					_ => ClassName::from(b"java/lang/Throwable"),
+				};
+				vec![Expr::Caught(Type::class(catch_type))]
+			} else {
+				let mut predecessors: Vec<usize> = block.predecessors.iter()
+					.filter(|edge| matches!(edge.kind, EdgeKind::FallThrough | EdgeKind::Branch))
+					.map(|edge| edge.from.0)
+					.collect();
+				predecessors.dedup();
+				let exits: Vec<&Vec<Expr>> = predecessors.iter().filter_map(|&predecessor| nodes[predecessor].as_ref().map(|node| &node.exit)).collect();
+				match (&predecessors[..], exits.first()) {
+					([_], Some(exit)) => (*exit).clone(),
+					(_, Some(exit)) => (0..exit.len())
+						.map(|slot| {
+							// values all predecessors leave, which can't change in between, like the result of a `new` waiting for its constructor
+							let value = &exit[slot];
+							let stable = matches!(value, Expr::Uninitialized { .. } | Expr::This(_) | Expr::Int(_) | Expr::Long(_) | Expr::Null | Expr::String(_));
+							if stable && exits.len() == predecessors.len() && exits.iter().all(|exit| exit.get(slot) == Some(value)) {
+								return value.clone();
+							}
+							let types = exits.iter().filter_map(|exit| exit.get(slot)).map(|value| value.ty(&builder.variables));
+							Expr::Stack { node: id.0, slot, ty: join_types(types) }
+						})
+						.collect(),
+					(_, None) => Vec::new(),
+				}
+			};
+			let (statements, terminator, exit) = builder.simulate(&cfg, id, entry.clone())?;
+			let mut protection: Vec<usize> = block.successors.iter()
+				.filter_map(|edge| match edge.kind {
+					EdgeKind::Exception(index) => Some(index),
+					_ => None,
+				})
+				.collect();
+			protection.sort();
+			let offset = code.code.get(block.start).map_or(0, |instruction| instruction.offset());
+			nodes[id.0] = Some(Node { offset, statements, terminator, entry, exit, protection, handler, removed: false });
+		}
+		let nodes = nodes.into_iter()
+			.enumerate()
+			.map(|(id, node)| node.unwrap_or_else(|| {
+				// unreachable blocks are left out
+				let offset = code.code.get(cfg.block(BlockId(id)).start).map_or(0, |instruction| instruction.offset());
+				Node {
+					offset, statements: Vec::new(), terminator: Terminator::Return(None), entry: Vec::new(), exit: Vec::new(), protection: Vec::new(),
+					handler: false, removed: true,
+				}
+			}))
+			.collect();
+
+		let node_at = |offset: usize| cfg.block_at_offset(offset).map(|id| id.0).ok_or_else(|| anyhow!("no block starts at offset {offset}"));
+		let handlers = code.exception_table.iter()
+			.map(|entry| Ok(Handler {
+				start: node_at(entry.start_pc)?,
+				end: if entry.end_pc == code.code.code_length() { cfg.blocks().len() } else { node_at(entry.end_pc)? },
+				handler: node_at(entry.handler_pc)?,
+				catch_type: entry.catch_type,
+			}))
+			.collect::<Result<_>>()?;
+		// a handler covering itself, like the one of `synchronized`, doesn't make a loop
+		let loops = cfg.loops().iter()
+			.filter(|l| l.latches.iter().any(|latch| cfg.block(*latch).successors.iter().any(|edge| edge.to == l.header && !matches!(edge.kind, EdgeKind::Exception(_)))))
+			.map(|l| (l.body.first().map_or(l.header.0, |first| first.0), l.header.0, l.body.last().map_or(l.header.0, |last| last.0)))
+			.collect();
+
+		Ok(Graph {
+			nodes,
+			variables: builder.variables,
+			parameters: builder.parameters,
+			handlers,
+			loops,
+			dominators: cfg.dominators(),
+			return_type: method.descriptor.return_type,
+		})
+	}
+
+	/// The distinct nodes each node is reached from, not counting exceptions.
+	pub(crate) fn predecessors(&self) -> Vec<Vec<usize>> {
+		let mut predecessors = vec![Vec::new(); self.nodes.len()];
+		for (id, node) in self.nodes.iter().enumerate().filter(|(_, node)| !node.removed) {
+			for successor in node.terminator.successors() {
+				if !predecessors[successor].contains(&id) {
+					predecessors[successor].push(id);
+				}
+			}
+		}
+		predecessors
+	}
+
+	/// Joins conditions spread over several nodes with `&&` and `||`, builds conditional expressions from the nodes pushing their alternatives, and
+	/// joins nodes only reached from the one before them.
+	pub(crate) fn reduce(&mut self) {
+		let mut predecessors = self.predecessors();
+		let mut changed = true;
+		while changed {
+			changed = false;
+			for id in 0..self.nodes.len() {
+				while !self.nodes[id].removed && self.reduce_at(id, &predecessors) {
+					predecessors = self.predecessors();
+					changed = true;
+				}
+			}
+		}
+	}
+
+	fn reduce_at(&mut self, id: usize, predecessors: &[Vec<usize>]) -> bool {
+		match self.nodes[id].terminator {
+			Terminator::If { then, otherwise, .. } if then != otherwise => {
+				self.join_condition(id, then, otherwise, predecessors) || self.join_conditional(id, then, otherwise, predecessors)
+			},
+			Terminator::Jump(next) => self.join_next(id, next, predecessors),
+			_ => false,
+		}
+	}
+
+	/// Whether the node `id` is only reached from `from`, and can be joined into it.
+	fn joinable(&self, id: usize, from: usize, predecessors: &[Vec<usize>]) -> bool {
+		let node = &self.nodes[id];
+		id != from && !node.removed && !node.handler && predecessors[id][..] == [from] && node.protection == self.nodes[from].protection
+	}
+
+	/// Joins a node only testing a condition into the node branching to it, if they share a target.
+	fn join_condition(&mut self, id: usize, then: usize, otherwise: usize, predecessors: &[Vec<usize>]) -> bool {
+		for (inner, on_true) in [(then, true), (otherwise, false)] {
+			if !self.joinable(inner, id, predecessors) {
+				continue;
+			}
+			let node = &self.nodes[inner];
+			let Terminator::If { condition, then: inner_then, otherwise: inner_otherwise } = &node.terminator else {
+				continue;
+			};
+			let (inner_then, inner_otherwise) = (*inner_then, *inner_otherwise);
+			if !node.statements.is_empty() || node.entry != node.exit || inner_then == inner || inner_otherwise == inner {
+				continue;
+			}
+			let shared = if on_true { otherwise } else { then };
+			let (op, negated, new_then, new_otherwise) = match (on_true, inner_then == shared, inner_otherwise == shared) {
+				(true, _, true) => (BinaryOp::AndAnd, false, inner_then, shared),
+				(true, true, _) => (BinaryOp::AndAnd, true, inner_otherwise, shared),
+				(false, true, _) => (BinaryOp::OrOr, false, shared, inner_otherwise),
+				(false, _, true) => (BinaryOp::OrOr, true, shared, inner_then),
+				_ => continue,
+			};
+			let inner_condition = if negated { condition.clone().negate() } else { condition.clone() };
+			self.nodes[inner].removed = true;
+			let Terminator::If { condition, .. } = core::mem::replace(&mut self.nodes[id].terminator, Terminator::Return(None)) else {
+				unreachable!("the terminator was matched before")
+			};
+			let condition = Expr::Binary { op, ty: Type::Boolean, left: Box::new(condition), right: Box::new(inner_condition) };
+			self.nodes[id].terminator = Terminator::If { condition, then: new_then, otherwise: new_otherwise };
+			return true;
+		}
+		false
+	}
+
+	/// Turns two nodes only pushing a value, each reached from one side of a condition, into a conditional expression.
+	fn join_conditional(&mut self, id: usize, then: usize, otherwise: usize, predecessors: &[Vec<usize>]) -> bool {
+		let value = |arm: usize| {
+			if !self.joinable(arm, id, predecessors) {
+				return None;
+			}
+			let node = &self.nodes[arm];
+			let Terminator::Jump(join) = node.terminator else {
+				return None;
+			};
+			let pushes_one = node.exit.len() == node.entry.len() + 1 && node.exit[..node.entry.len()] == node.entry[..];
+			(node.statements.is_empty() && pushes_one).then(|| (join, node.exit[node.entry.len()].clone()))
+		};
+		let (Some((join, then_value)), Some((other_join, otherwise_value))) = (value(then), value(otherwise)) else {
+			return false;
+		};
+		if join != other_join || join == then || join == otherwise {
+			return false;
+		}
+		let Terminator::If { condition, .. } = core::mem::replace(&mut self.nodes[id].terminator, Terminator::Jump(join)) else {
+			unreachable!("the terminator was matched before")
+		};
+		// branches test the opposite of the condition in the source, which is written without negating it
+		let conditional = match condition {
+			Expr::Unary(UnaryOp::Not, condition) => Expr::Conditional { condition, then: Box::new(otherwise_value), otherwise: Box::new(then_value) },
+			condition => Expr::Conditional { condition: Box::new(condition), then: Box::new(then_value), otherwise: Box::new(otherwise_value) },
+		};
+		self.nodes[id].exit.push(conditional);
+		self.nodes[then].removed = true;
+		self.nodes[otherwise].removed = true;
+		true
+	}
+
+	/// Joins the node following `id` into it, if it's only reached from it.
+	fn join_next(&mut self, id: usize, next: usize, predecessors: &[Vec<usize>]) -> bool {
+		let following = (id + 1..self.nodes.len()).find(|&other| !self.nodes[other].removed);
+		if following != Some(next) || !self.joinable(next, id, predecessors) {
+			return false;
+		}
+		let values = self.nodes[id].exit.clone();
+		substitute(&mut self.nodes, next, &values);
+		let node = &mut self.nodes[next];
+		node.removed = true;
+		let mut statements = core::mem::take(&mut node.statements);
+		let terminator = core::mem::replace(&mut node.terminator, Terminator::Return(None));
+		let exit = core::mem::take(&mut node.exit);
+		let joined = &mut self.nodes[id];
+		joined.statements.append(&mut statements);
+		joined.terminator = terminator;
+		joined.exit = exit;
+		true
+	}
+
+	/// Replaces the values left on the stack for nodes reached from several nodes: by the value all of them leave, or by a variable each of them
+	/// assigns.
+	pub(crate) fn resolve_stack(&mut self) {
+		let predecessors = self.predecessors();
+		for (id, predecessors) in predecessors.iter().enumerate() {
+			let node = &self.nodes[id];
+			if node.removed || !node.entry.iter().any(|value| matches!(value, Expr::Stack { node, .. } if *node == id)) {
+				continue;
+			}
+			let mut values = Vec::new();
+			for slot in 0..self.nodes[id].entry.len() {
+				let exits: Vec<Expr> = predecessors.iter().filter_map(|&predecessor| self.nodes[predecessor].exit.get(slot).cloned()).collect();
+				let value = match exits.first() {
+					Some(first) if exits.iter().all(|exit| exit == first) => first.clone(),
+					_ => {
+						let ty = self.nodes[id].entry[slot].ty(&self.variables);
+						let variable = self.variables.len();
+						self.variables.push(Variable { name: format!("stack{id}_{slot}"), ty: if ty == Type::Null { Type::object() } else { ty }, signature: None });
+						for &predecessor in predecessors {
+							if let Some(value) = self.nodes[predecessor].exit.get(slot).cloned() {
+								self.nodes[predecessor].statements.push(Stmt::Expr(Expr::Assign(Box::new(Expr::Local(variable)), Box::new(value))));
+							}
+						}
+						Expr::Local(variable)
+					},
+				};
+				values.push(value);
+			}
+			substitute(&mut self.nodes, id, &values);
+		}
+	}
+
+	/// Turns the values assigned, passed and returned into the `boolean`s and `char`s they are, now that the values of the conditional expressions
+	/// are known.
+	pub(crate) fn coerce(&mut self) {
+		let return_type = self.return_type.as_ref().map(Type::of);
+		let variables = &self.variables;
+		for node in self.nodes.iter_mut().filter(|node| !node.removed) {
+			for statement in &mut node.statements {
+				statement.for_each_expr_mut(&mut |expr| coerce_values(expr, variables));
+			}
+			node.terminator.for_each_expr_mut(&mut |expr| coerce_values(expr, variables));
+			if let (Terminator::Return(Some(value)), Some(ty)) = (&mut node.terminator, return_type) {
+				coerce_in_place(value, ty);
+			}
+		}
+	}
+}
+
+/// The type of a value left on the stack by several blocks.
+fn join_types(types: impl Iterator<Item=Type>) -> Type {
+	let mut joined = Type::Null;
+	for ty in types {
+		joined = match (joined, ty) {
+			(Type::Null, ty) => ty,
+			(joined, Type::Null) => joined,
+			(joined, ty) if joined == ty => joined,
+			(Type::Reference(a), Type::Reference(b)) => join_references(a, b),
+			_ => Type::Int,
+		};
+	}
+	joined
+}
+
+/// The closest type both class or array types are known to be assignable to, without knowing the class hierarchy: arrays of references of the same
+/// dimension are `Object` arrays, and anything else is an `Object`.
+fn join_references(a: FieldDescriptor, b: FieldDescriptor) -> Type {
+	let is_reference_array = |descriptor: &FieldDescriptor| {
+		descriptor.array_dimension > 0 && (descriptor.array_dimension > 1 || matches!(descriptor.base_type, BaseOrObjectType::Object(_)))
+	};
+	if a.array_dimension == b.array_dimension && is_reference_array(&a) && is_reference_array(&b) {
+		// `int[][]` and `long[][]` are both `Object[]`, while `String[][]` and `Integer[][]` are both `Object[][]`
+		let classes = matches!(a.base_type, BaseOrObjectType::Object(_)) && matches!(b.base_type, BaseOrObjectType::Object(_));
+		let mut object = Type::object().descriptor();
+		object.array_dimension = if classes { a.array_dimension } else { a.array_dimension - 1 };
+		Type::Reference(object)
+	} else {
+		Type::object()
+	}
+}
+
+/// Finds the entries of the `LocalVariableTable` that are the same variable as an earlier entry, with the start of the first one: javac splits the
+/// scope of a variable assigned in each branch of an `if` or a `switch` into one starting after the assignment in each branch but the last, and
+/// one starting where the branches join. The scope of a branch has the jump to where the last one starts. Similarly, ecj splits the scope of a
+/// variable assigned in the test at the end of a loop into the one of the test, which jumps back to the one of the body.
+fn continued_locals(code: &CodeAttribute) -> BTreeMap<(usize, usize), usize> {
+	let jumps = |from: &LocalVariableTableEntry, to: &LocalVariableTableEntry| code.code.iter()
+		.filter(|instruction| (from.start_pc..from.end_pc).contains(&instruction.offset()))
+		.any(|instruction| instruction.opcode().branch_targets().into_iter().any(|target| (to.start_pc..to.end_pc).contains(&target.0)));
+	let mut entries: Vec<&LocalVariableTableEntry> = code.attributes.iter()
+		.filter_map(|attribute| match attribute {
+			AttributeInfo::LocalVariableTable(table) => Some(&table.local_variable_table),
+			_ => None,
+		})
+		.flatten()
+		.collect();
+	entries.sort_by_key(|entry| entry.start_pc);
+	let first = |continued: &BTreeMap<(usize, usize), usize>, slot: usize, mut start: usize| {
+		while let Some(&earlier) = continued.get(&(slot, start)) {
+			start = earlier;
+		}
+		start
+	};
+	let mut continued = BTreeMap::new();
+	for (index, later) in entries.iter().enumerate() {
+		let slot = usize::from(later.lv_index);
+		for earlier in &entries[..index] {
+			if earlier.lv_index == later.lv_index && earlier.name == later.name && earlier.descriptor == later.descriptor &&
+				(jumps(earlier, later) || jumps(later, earlier)) {
+				let (a, b) = (first(&continued, slot, earlier.start_pc), first(&continued, slot, later.start_pc));
+				if a != b {
+					continued.insert((slot, a.max(b)), a.min(b));
+				}
+			}
+		}
+	}
+	let chains = continued.clone();
+	for (&(slot, _), start) in &mut continued {
+		*start = first(&chains, slot, *start);
+	}
+	continued
+}
+
+/// Replaces the values left on the stack for node `id` with `values`.
+fn substitute(nodes: &mut [Node], id: usize, values: &[Expr]) {
+	let mut f = |expr: &mut Expr| expr.replace(&mut |expr| match expr {
+		Expr::Stack { node, slot, .. } if *node == id => values.get(*slot).cloned(),
+		_ => None,
+	});
+	// values passed through a node without being used are still placeholders in the nodes after it
+	for node in nodes.iter_mut().filter(|node| !node.removed) {
+		for statement in &mut node.statements {
+			statement.for_each_expr_mut(&mut f);
+		}
+		node.terminator.for_each_expr_mut(&mut f);
+		node.entry.iter_mut().for_each(&mut f);
+		node.exit.iter_mut().for_each(&mut f);
+	}
+}
+
+fn coerce_in_place(expr: &mut Expr, ty: Type) {
+	let value = core::mem::replace(expr, Expr::Null);
+	*expr = value.coerce(ty);
+}
+
+fn coerce_values(expr: &mut Expr, variables: &[Variable]) {
+	expr.for_each_child_mut(&mut |child| coerce_values(child, variables));
+	match expr {
+		Expr::Assign(target, value) => coerce_in_place(value, target.ty(variables)),
+		Expr::Invoke { descriptor, arguments, .. } | Expr::New { descriptor, arguments, .. } => {
+			for (argument, parameter) in arguments.iter_mut().zip(&descriptor.parameters) {
+				coerce_in_place(argument, Type::of(parameter));
+			}
+		},
+		// a conditional of ones and zeros compared with zero is a condition
+		Expr::Binary { op: op @ (BinaryOp::Eq | BinaryOp::Ne), left, right, .. } if **right == Expr::Int(0) && is_boolean_conditional(left) => {
+			let negated = *op == BinaryOp::Eq;
+			let condition = core::mem::replace(&mut **left, Expr::Null).coerce(Type::Boolean);
+			*expr = if negated { condition.negate() } else { condition };
+		},
+		_ => {},
+	}
+}
+
+fn is_boolean_conditional(expr: &Expr) -> bool {
+	match expr {
+		Expr::Conditional { then, otherwise, .. } => {
+			[then, otherwise].iter().all(|value| matches!(***value, Expr::Int(0 | 1)) || is_boolean_conditional(value))
+		},
+		_ => false,
+	}
+}
+
+/// The kinds of values the load and store instructions tell apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+	Int,
+	Long,
+	Float,
+	Double,
+	Reference,
+}
+
+impl Kind {
+	fn of(descriptor: &FieldDescriptor) -> Kind {
+		match (descriptor.array_dimension, &descriptor.base_type) {
+			(0, BaseOrObjectType::J) => Kind::Long,
+			(0, BaseOrObjectType::F) => Kind::Float,
+			(0, BaseOrObjectType::D) => Kind::Double,
+			(0, BaseOrObjectType::Object(_)) | (1.., _) => Kind::Reference,
+			_ => Kind::Int,
+		}
+	}
+
+	fn ty(self) -> Type {
+		match self {
+			Kind::Int => Type::Int,
+			Kind::Long => Type::Long,
+			Kind::Float => Type::Float,
+			Kind::Double => Type::Double,
+			Kind::Reference => Type::object(),
+		}
+	}
+}
+
+/// Identifies a variable: by the entry of the `LocalVariableTable` if there is one, and by the slot and the kind of value otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+	Table { slot: usize, start_pc: usize },
+	Slot(usize, Kind),
+}
+
+struct Builder<'a> {
+	class: &'a ClassFile,
+	code: &'a CodeAttribute,
+	variables: Vec<Variable>,
+	parameters: Vec<usize>,
+	keys: BTreeMap<Key, usize>,
+	/// The `LocalVariableTable` entries that continue an earlier one, by their slot and start, with the start of the earlier one.
+	continued: BTreeMap<(usize, usize), usize>,
+	/// Whether slot 0 always holds `this`.
+	this: bool,
+	temporaries: usize,
+}
+
+/// The stack and the statements while simulating a block.
+struct State {
+	stack: Vec<Expr>,
+	statements: Vec<Stmt>,
+}
+
+impl State {
+	fn pop(&mut self) -> Result<Expr> {
+		self.stack.pop().ok_or_else(|| anyhow!("stack underflow"))
+	}
+}
+
+impl<'a> Builder<'a> {
+	fn new(class: &'a ClassFile, method: &'a MethodInfo, code: &'a CodeAttribute) -> Builder<'a> {
+		let is_static = method.access_flags.is_static;
+		let this = !is_static && !code.code.iter().any(|instruction| matches!(instruction.opcode(), Opcode::AStore(LvIndex(0))));
+		let continued = continued_locals(code);
+		let mut builder = Builder { class, code, variables: Vec::new(), parameters: Vec::new(), keys: BTreeMap::new(), continued, this, temporaries: 0 };
+
+		let parameters = &method.descriptor.parameters;
+		let signature = method.signature()
+			.and_then(|signature| MethodSignature::parse(signature).ok())
+			.filter(|signature| signature.parameters.len() == parameters.len());
+		let names = method.attributes.iter()
+			.find_map(|attribute| match attribute {
+				AttributeInfo::MethodParameters(attribute) => Some(&attribute.parameters),
+				_ => None,
+			})
+			.filter(|names| names.len() == parameters.len());
+		let locals = code.locals_at(0);
+		let mut slot = usize::from(!is_static);
+		for (index, parameter) in parameters.iter().enumerate() {
+			let kind = Kind::of(parameter);
+			let local = locals.iter().find(|local| local.index as usize == slot && Kind::of(local.descriptor) == kind);
+			let name = local.map(|local| text(local.name.as_bytes()))
+				.or_else(|| names.and_then(|names| names[index].name.as_deref()).map(text))
+				.unwrap_or_else(|| format!("arg{index}"));
+			let key = local.map_or(Key::Slot(slot, kind), |local| Key::Table { slot, start_pc: local.start_pc });
+			let signature = signature.as_ref().map(|signature| signature.parameters[index].clone());
+			builder.variables.push(Variable { name, ty: Type::of(parameter), signature });
+			builder.keys.insert(key, builder.variables.len() - 1);
+			builder.parameters.push(builder.variables.len() - 1);
+			slot += if kind == Kind::Long || kind == Kind::Double { 2 } else { 1 };
+		}
+		builder
+	}
+
+	/// Returns the variable in `slot` at `offset`, for loads the offset of the instruction, for stores the one of the next instruction, where the
+	/// `LocalVariableTable` has the variable start.
+	fn variable(&mut self, slot: usize, kind: Kind, offset: usize, value: Option<Type>) -> usize {
+		let local = self.code.locals_at(offset).into_iter().find(|local| local.index as usize == slot && Kind::of(local.descriptor) == kind);
+		let key = local.as_ref().map_or(Key::Slot(slot, kind), |local| Key::Table {
+			slot,
+			start_pc: self.continued.get(&(slot, local.start_pc)).copied().unwrap_or(local.start_pc),
+		});
+		if let Some(&variable) = self.keys.get(&key) {
+			// without a type from the table, a variable has a type all values stored in it are assignable to
+			if let (Key::Slot(..), Type::Reference(a), Some(Type::Reference(b))) = (key, self.variables[variable].ty, value) {
+				if a != b && !self.parameters.contains(&variable) {
+					self.variables[variable].ty = join_references(a, b);
+				}
+			}
+			return variable;
+		}
+		let variable = match local {
+			Some(local) => Variable {
+				name: text(local.name.as_bytes()),
+				ty: Type::of(local.descriptor),
+				signature: local.signature.and_then(|signature| TypeSignature::parse(signature).ok()),
+			},
+			None => {
+				let ty = match (kind, value) {
+					(Kind::Int, Some(Type::Boolean)) => Type::Boolean,
+					(Kind::Reference, Some(ty @ Type::Reference(_))) => ty,
+					_ => kind.ty(),
+				};
+				let mut name = format!("var{slot}");
+				if self.variables.iter().any(|variable| variable.name == name) {
+					name = format!("var{slot}_{}", self.variables.len());
+				}
+				Variable { name, ty, signature: None }
+			},
+		};
+		self.variables.push(variable);
+		self.keys.insert(key, self.variables.len() - 1);
+		self.variables.len() - 1
+	}
+
+	fn temporary(&mut self, ty: Type) -> usize {
+		self.temporaries += 1;
+		let ty = match ty {
+			Type::Null | Type::Void => Type::object(),
+			ty => ty,
+		};
+		self.variables.push(Variable { name: format!("tmp{}", self.temporaries), ty, signature: None });
+		self.variables.len() - 1
+	}
+
+	fn load(&mut self, slot: usize, kind: Kind, offset: usize) -> Expr {
+		if slot == 0 && self.this {
+			Expr::This(self.class.this_class)
+		} else {
+			Expr::Local(self.variable(slot, kind, offset, None))
+		}
+	}
+
+	fn is_wide(&self, value: &Expr) -> bool {
+		value.ty(&self.variables).is_wide()
+	}
+
+	/// Moves the values on the stack that the statement could change, or whose effects must happen before it, into variables.
+	fn spill(&mut self, state: &mut State, written: Option<usize>) {
+		for index in 0..state.stack.len() {
+			let value = &state.stack[index];
+			if !value.is_pure() || written.is_some_and(|variable| value.reads(variable)) {
+				let temporary = self.temporary(value.ty(&self.variables));
+				let value = core::mem::replace(&mut state.stack[index], Expr::Local(temporary));
+				state.statements.push(Stmt::Expr(Expr::Assign(Box::new(Expr::Local(temporary)), Box::new(value))));
+			}
+		}
+	}
+
+	fn emit(&mut self, state: &mut State, statement: Stmt) {
+		let written = match &statement {
+			Stmt::Expr(Expr::Assign(target, _) | Expr::Increment { target, .. }) => match **target {
+				Expr::Local(variable) => Some(variable),
+				_ => None,
+			},
+			_ => None,
+		};
+		self.spill(state, written);
+		state.statements.push(statement);
+	}
+
+	/// Assigns `value` to `target`, as an expression left on the stack if the value was duplicated for it.
+	fn store(&mut self, state: &mut State, target: Expr, value: Expr, duplicated: bool) {
+		let ty = target.ty(&self.variables);
+		// a variable with a generic type is declared with it, so the cast to its erasure would make the assignment invalid, like
+		// `T item = (Comparable) iterator.next();`
+		let value = match (&target, value) {
+			(Expr::Local(variable), Expr::Cast(cast, value)) if self.variables[*variable].signature.is_some() && cast == ty => *value,
+			(_, value) => value,
+		};
+		if duplicated && state.stack.last() == Some(&value) {
+			let last = state.stack.last_mut().expect("the stack was checked to have a last value");
+			*last = Expr::Assign(Box::new(target), Box::new(value.coerce(ty)));
+		} else {
+			self.emit(state, Stmt::Expr(Expr::Assign(Box::new(target), Box::new(value.coerce(ty)))));
+		}
+	}
+
+	/// Evaluates a value whose result isn't used.
+	fn discard(&mut self, state: &mut State, value: Expr) {
+		if !value.is_pure() {
+			self.emit(state, Stmt::Expr(value));
+		}
+	}
+
+	fn arguments(&mut self, state: &mut State, descriptor: &MethodDescriptor) -> Result<Vec<Expr>> {
+		let mut arguments = Vec::with_capacity(descriptor.parameters.len());
+		for parameter in descriptor.parameters.iter().rev() {
+			arguments.push(state.pop()?.coerce(Type::of(parameter)));
+		}
+		arguments.reverse();
+		Ok(arguments)
+	}
+
+	/// Leaves the result of a call on the stack, or makes it a statement if there is none.
+	fn result(&mut self, state: &mut State, value: Expr, returns: bool) {
+		if returns {
+			state.stack.push(value);
+		} else {
+			self.emit(state, Stmt::Expr(value));
+		}
+	}
+
+	fn invoke(&mut self, state: &mut State, kind: InvokeKind, class: ClassName, name: MethodName, descriptor: &MethodDescriptor) -> Result<()> {
+		let arguments = self.arguments(state, descriptor)?;
+		let target = if kind == InvokeKind::Static { None } else { Some(state.pop()?) };
+		if name.as_bytes() == b"<init>" {
+			if let Some(uninitialized @ Expr::Uninitialized { class, .. }) = &target {
+				let created = Expr::New { class: *class, descriptor: descriptor.clone(), arguments };
+				let mut found = false;
+				for value in state.stack.iter_mut().filter(|value| *value == uninitialized) {
+					*value = created.clone();
+					found = true;
+				}
+				if !found {
+					self.emit(state, Stmt::Expr(created));
+				}
+				return Ok(());
+			}
+		}
+		if name.as_bytes() == b"toString" && descriptor.parameters.is_empty() {
+			if let Some(parts) = target.as_ref().and_then(|target| self.concatenation(target)) {
+				state.stack.push(Expr::Concat(parts));
+				return Ok(());
+			}
+		}
+		let call = Expr::Invoke { kind, target: target.map(Box::new), class, name, descriptor: descriptor.clone(), arguments };
+		self.result(state, call, descriptor.return_type.is_some());
+		Ok(())
+	}
+
+	/// Returns the parts appended, if `value` is a `StringBuilder` or `StringBuffer` created and appended to in one expression, as javac compiles
+	/// string concatenation before Java 9.
+	fn concatenation(&self, value: &Expr) -> Option<Vec<Expr>> {
+		let is_builder = |class: &ClassName| class.as_bytes() == b"java/lang/StringBuilder" || class.as_bytes() == b"java/lang/StringBuffer";
+		match value {
+			Expr::Invoke { kind: InvokeKind::Virtual, target: Some(target), class, name, arguments, .. } if is_builder(class) && name.as_bytes() == b"append" => {
+				let [argument] = &arguments[..] else {
+					return None;
+				};
+				let mut parts = self.concatenation(target)?;
+				parts.push(argument.clone());
+				Some(parts)
+			},
+			Expr::New { class, arguments, .. } if is_builder(class) => match &arguments[..] {
+				[] => Some(Vec::new()),
+				[Expr::Invoke { class, name, descriptor, arguments, .. }]
+					if class.as_bytes() == b"java/lang/String" && name.as_bytes() == b"valueOf" && descriptor.parameters[..] == [Type::object().descriptor()] =>
+				{
+					Some(arguments.clone())
+				},
+				[argument] if argument.ty(&self.variables) == Type::string() => Some(vec![argument.clone()]),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
+	/// Duplicates the values on top of the stack, moving them into variables if they have effects and aren't just assigned twice.
+	fn duplicate(&mut self, state: &mut State, opcode: &Opcode, stored: bool) -> Result<()> {
+		let top_is_wide = state.stack.last().is_some_and(|value| self.is_wide(value));
+		let count = match opcode {
+			Opcode::Dup | Opcode::DupX1 | Opcode::DupX2 => 1,
+			_ if top_is_wide => 1,
+			_ => 2,
+		};
+		// an array duplicated right after being created is the one of an array initializer, which the elements are stored into
+		let initializer = matches!((opcode, state.stack.last()), (Opcode::Dup, Some(Expr::NewArray { .. })));
+		let top = &state.stack[state.stack.len().saturating_sub(count)..];
+		if !stored && !initializer && top.iter().any(|value| !value.is_pure()) {
+			self.spill(state, None);
+		}
+		let v1 = state.pop()?;
+		match opcode {
+			Opcode::Dup => state.stack.extend([v1.clone(), v1]),
+			Opcode::DupX1 => {
+				let v2 = state.pop()?;
+				state.stack.extend([v1.clone(), v2, v1]);
+			},
+			Opcode::DupX2 => {
+				let v2 = state.pop()?;
+				if self.is_wide(&v2) {
+					state.stack.extend([v1.clone(), v2, v1]);
+				} else {
+					let v3 = state.pop()?;
+					state.stack.extend([v1.clone(), v3, v2, v1]);
+				}
+			},
+			Opcode::Dup2 if top_is_wide => state.stack.extend([v1.clone(), v1]),
+			Opcode::Dup2 => {
+				let v2 = state.pop()?;
+				state.stack.extend([v2.clone(), v1.clone(), v2, v1]);
+			},
+			Opcode::Dup2X1 if top_is_wide => {
+				let v2 = state.pop()?;
+				state.stack.extend([v1.clone(), v2, v1]);
+			},
+			Opcode::Dup2X1 => {
+				let v2 = state.pop()?;
+				let v3 = state.pop()?;
+				state.stack.extend([v2.clone(), v1.clone(), v3, v2, v1]);
+			},
+			Opcode::Dup2X2 if top_is_wide => {
+				let v2 = state.pop()?;
+				if self.is_wide(&v2) {
+					state.stack.extend([v1.clone(), v2, v1]);
+				} else {
+					let v3 = state.pop()?;
+					state.stack.extend([v1.clone(), v3, v2, v1]);
+				}
+			},
+			Opcode::Dup2X2 => {
+				let v2 = state.pop()?;
+				let v3 = state.pop()?;
+				if self.is_wide(&v3) {
+					state.stack.extend([v2.clone(), v1.clone(), v3, v2, v1]);
+				} else {
+					let v4 = state.pop()?;
+					state.stack.extend([v2.clone(), v1.clone(), v4, v3, v2, v1]);
+				}
+			},
+			_ => bail!("{opcode:?} doesn't duplicate values"),
+		}
+		Ok(())
+	}
+
+	/// Builds the condition of a branch comparing `value` with zero.
+	fn compare_with_zero(&self, op: BinaryOp, value: Expr) -> Expr {
+		match value {
+			Expr::Compare { ty, left, right, nan_greater } => {
+				// with a NaN operand, `fcmpg` pushes 1 and `fcmpl` -1, so the branch is taken where the comparison of the operands is false
+				let nan_branches = ty.is_floating() && match op {
+					BinaryOp::Gt | BinaryOp::Ge => nan_greater,
+					BinaryOp::Lt | BinaryOp::Le => !nan_greater,
+					_ => false,
+				};
+				if nan_branches {
+					let op = op.inverted().unwrap_or(op);
+					Expr::Unary(UnaryOp::Not, Box::new(Expr::Binary { op, ty, left, right }))
+				} else {
+					Expr::Binary { op, ty, left, right }
+				}
+			},
+			value if value.ty(&self.variables) == Type::Boolean && matches!(op, BinaryOp::Eq | BinaryOp::Ne) => {
+				if op == BinaryOp::Eq { value.negate() } else { value }
+			},
+			value => self.comparison(op, Type::Int, value, Expr::Int(0)),
+		}
+	}
+
+	fn comparison(&self, op: BinaryOp, ty: Type, left: Expr, right: Expr) -> Expr {
+		// booleans and characters are compared with literals of their type
+		let left_ty = left.ty(&self.variables);
+		let right_ty = right.ty(&self.variables);
+		Expr::Binary { op, ty, left: Box::new(left.coerce(right_ty)), right: Box::new(right.coerce(left_ty)) }
+	}
+
+	fn binary(&self, state: &mut State, op: BinaryOp, ty: Type) -> Result<()> {
+		let right = state.pop()?;
+		let left = state.pop()?;
+		// `&`, `|` and `^` also work on booleans
+		let ty = if left.ty(&self.variables) == Type::Boolean && right.ty(&self.variables) == Type::Boolean { Type::Boolean } else { ty };
+		// javac compiles `~x` to `x ^ -1`
+		if op == BinaryOp::Xor && matches!(right, Expr::Int(-1) | Expr::Long(-1)) {
+			state.stack.push(Expr::Unary(UnaryOp::BitNot, Box::new(left)));
+			return Ok(());
+		}
+		state.stack.push(Expr::Binary { op, ty, left: Box::new(left), right: Box::new(right) });
+		Ok(())
+	}
+
+	fn simulate(&mut self, cfg: &ControlFlowGraph, id: BlockId, entry: Vec<Expr>) -> Result<(Vec<Stmt>, Terminator, Vec<Expr>)> {
+		let block = cfg.block(id);
+		let code = self.code;
+		let instructions = &code.code;
+		let node = |target: &BranchTarget| cfg.block_at_offset(target.0).map(|id| id.0).ok_or_else(|| anyhow!("no block starts at offset {}", target.0));
+		let mut state = State { stack: entry, statements: Vec::new() };
+		let mut index = block.start;
+		while index < block.end {
+			let instruction = instructions.get(index).ok_or_else(|| anyhow!("no instruction {index}"))?;
+			let offset = instruction.offset();
+			let next_offset = instructions.get(index + 1).map_or(instructions.code_length(), |next| next.offset());
+			let following = (index + 1 < block.end).then(|| instructions.get(index + 1).map(|next| next.opcode())).flatten();
+			let duplicated = index > block.start && instructions.get(index - 1).is_some_and(|previous| matches!(previous.opcode(),
+				Opcode::Dup | Opcode::DupX1 | Opcode::DupX2 | Opcode::Dup2 | Opcode::Dup2X1 | Opcode::Dup2X2
+			));
+			index += 1;
+
+			let opcode = instruction.opcode();
+			match opcode {
+				Opcode::AConstNull => state.stack.push(Expr::Null),
+				Opcode::IConstM1 => state.stack.push(Expr::Int(-1)),
+				Opcode::IConst0 => state.stack.push(Expr::Int(0)),
+				Opcode::IConst1 => state.stack.push(Expr::Int(1)),
+				Opcode::IConst2 => state.stack.push(Expr::Int(2)),
+				Opcode::IConst3 => state.stack.push(Expr::Int(3)),
+				Opcode::IConst4 => state.stack.push(Expr::Int(4)),
+				Opcode::IConst5 => state.stack.push(Expr::Int(5)),
+				Opcode::LConst0 => state.stack.push(Expr::Long(0)),
+				Opcode::LConst1 => state.stack.push(Expr::Long(1)),
+				Opcode::FConst0 => state.stack.push(Expr::Float(0f32.to_bits())),
+				Opcode::FConst1 => state.stack.push(Expr::Float(1f32.to_bits())),
+				Opcode::FConst2 => state.stack.push(Expr::Float(2f32.to_bits())),
+				Opcode::DConst0 => state.stack.push(Expr::Double(0f64.to_bits())),
+				Opcode::DConst1 => state.stack.push(Expr::Double(1f64.to_bits())),
+				Opcode::BIPush(value) => state.stack.push(Expr::Int(*value as i8 as i32)),
+				Opcode::SIPush(value) => state.stack.push(Expr::Int(*value as i32)),
+				Opcode::LdcInt(value) => state.stack.push(Expr::Int(*value)),
+				Opcode::LdcFloat(bits) => state.stack.push(Expr::Float(*bits)),
+				Opcode::Ldc2WLong(value) => state.stack.push(Expr::Long(*value)),
+				Opcode::Ldc2WDouble(bits) => state.stack.push(Expr::Double(*bits)),
+				Opcode::LdcReferenceString(string) => state.stack.push(Expr::String(string.clone())),
+				Opcode::LdcReferenceClass(class) => state.stack.push(Expr::Class(Type::of_class(*class))),
+				Opcode::LdcReferenceMethodType(descriptor) => state.stack.push(Expr::Constant(
+					format!("/* method type {} */ null", text(&descriptor.to_bytes())),
+					Type::class(ClassName::from(b"java/lang/invoke/MethodType")),
+				)),
+				Opcode::LdcReferenceMethodHandle(_) => state.stack.push(Expr::Constant(
+					String::from("/* method handle */ null"),
+					Type::class(ClassName::from(b"java/lang/invoke/MethodHandle")),
+				)),
+
+				Opcode::ILoad(LvIndex(slot)) => {
+					// `i++` is loading `i` and then incrementing it
+					if let Some(Opcode::IInc { lv_index: LvIndex(incremented), const_ }) = following {
+						if incremented == slot && !(*slot == 0 && self.this) {
+							let target = self.load(*slot, Kind::Int, offset);
+							state.stack.push(Expr::Increment { target: Box::new(target), by: *const_, prefix: false });
+							index += 1;
+							continue;
+						}
+					}
+					let value = self.load(*slot, Kind::Int, offset);
+					state.stack.push(value);
+				},
+				Opcode::LLoad(LvIndex(slot)) => { let value = self.load(*slot, Kind::Long, offset); state.stack.push(value); },
+				Opcode::FLoad(LvIndex(slot)) => { let value = self.load(*slot, Kind::Float, offset); state.stack.push(value); },
+				Opcode::DLoad(LvIndex(slot)) => { let value = self.load(*slot, Kind::Double, offset); state.stack.push(value); },
+				Opcode::ALoad(LvIndex(slot)) => { let value = self.load(*slot, Kind::Reference, offset); state.stack.push(value); },
+
+				Opcode::IStore(LvIndex(slot)) | Opcode::LStore(LvIndex(slot)) | Opcode::FStore(LvIndex(slot)) | Opcode::DStore(LvIndex(slot)) |
+				Opcode::AStore(LvIndex(slot)) => {
+					let kind = match opcode {
+						Opcode::IStore(_) => Kind::Int,
+						Opcode::LStore(_) => Kind::Long,
+						Opcode::FStore(_) => Kind::Float,
+						Opcode::DStore(_) => Kind::Double,
+						_ => Kind::Reference,
+					};
+					let value = state.pop()?;
+					let variable = self.variable(*slot, kind, next_offset, Some(value.ty(&self.variables)));
+					self.store(&mut state, Expr::Local(variable), value, duplicated);
+				},
+				Opcode::IInc { lv_index: LvIndex(slot), const_ } => {
+					let variable = self.variable(*slot, Kind::Int, offset, None);
+					let target = Box::new(Expr::Local(variable));
+					// `++i` in an expression is incrementing `i` and then loading it
+					match following {
+						Some(Opcode::ILoad(LvIndex(loaded))) if loaded == slot && !state.stack.is_empty() => {
+							state.stack.push(Expr::Increment { target, by: *const_, prefix: true });
+							index += 1;
+						},
+						_ => self.emit(&mut state, Stmt::Expr(Expr::Increment { target, by: *const_, prefix: true })),
+					}
+				},
+
+				Opcode::IALoad | Opcode::LALoad | Opcode::FALoad | Opcode::DALoad | Opcode::AALoad | Opcode::BALoad | Opcode::CALoad | Opcode::SALoad => {
+					let index = state.pop()?;
+					let array = state.pop()?;
+					let ty = array.ty(&self.variables).component().unwrap_or(match opcode {
+						Opcode::IALoad => Type::Int,
+						Opcode::LALoad => Type::Long,
+						Opcode::FALoad => Type::Float,
+						Opcode::DALoad => Type::Double,
+						Opcode::BALoad => Type::Byte,
+						Opcode::CALoad => Type::Char,
+						Opcode::SALoad => Type::Short,
+						_ => Type::object(),
+					});
+					state.stack.push(Expr::ArrayElement { array: Box::new(array), index: Box::new(index), ty });
+				},
+				Opcode::IAStore | Opcode::LAStore | Opcode::FAStore | Opcode::DAStore | Opcode::AAStore | Opcode::BAStore | Opcode::CAStore | Opcode::SAStore => {
+					let value = state.pop()?;
+					let element = state.pop()?;
+					let array = state.pop()?;
+					// the elements of an array initializer are stored into the array left on the stack
+					if !duplicated && state.stack.last() == Some(&array) {
+						if let Some(Expr::NewArray { ty, dimensions, elements }) = state.stack.last_mut() {
+							let stored = elements.as_ref().map_or(0, Vec::len);
+							if let ([Expr::Int(length)], Expr::Int(position)) = (&dimensions[..], &element) {
+								if *position as usize == stored && stored < *length as usize {
+									let component = Type::of(&FieldDescriptor { array_dimension: ty.array_dimension - 1, base_type: ty.base_type });
+									elements.get_or_insert_with(Vec::new).push(value.coerce(component));
+									continue;
+								}
+							}
+						}
+					}
+					let ty = array.ty(&self.variables).component().unwrap_or(Type::Int);
+					let target = Expr::ArrayElement { array: Box::new(array), index: Box::new(element), ty };
+					self.store(&mut state, target, value, duplicated);
+				},
+				Opcode::ArrayLength => {
+					let array = state.pop()?;
+					state.stack.push(Expr::ArrayLength(Box::new(array)));
+				},
+
+				Opcode::Pop => {
+					let value = state.pop()?;
+					self.discard(&mut state, value);
+				},
+				Opcode::Pop2 => {
+					let value = state.pop()?;
+					if !self.is_wide(&value) {
+						let below = state.pop()?;
+						self.discard(&mut state, below);
+					}
+					self.discard(&mut state, value);
+				},
+				Opcode::Dup | Opcode::DupX1 | Opcode::DupX2 | Opcode::Dup2 | Opcode::Dup2X1 | Opcode::Dup2X2 => {
+					let stored = matches!(following,
+						Some(Opcode::IStore(_) | Opcode::LStore(_) | Opcode::FStore(_) | Opcode::DStore(_) | Opcode::AStore(_) | Opcode::PutField(_) |
+							Opcode::PutStatic(_) | Opcode::IAStore | Opcode::LAStore | Opcode::FAStore | Opcode::DAStore | Opcode::AAStore |
+							Opcode::BAStore | Opcode::CAStore | Opcode::SAStore)
+					);
+					self.duplicate(&mut state, opcode, stored)?;
+				},
+				Opcode::Swap => {
+					let v1 = state.pop()?;
+					let v2 = state.pop()?;
+					state.stack.extend([v1, v2]);
+				},
+
+				Opcode::IAdd => self.binary(&mut state, BinaryOp::Add, Type::Int)?,
+				Opcode::LAdd => self.binary(&mut state, BinaryOp::Add, Type::Long)?,
+				Opcode::FAdd => self.binary(&mut state, BinaryOp::Add, Type::Float)?,
+				Opcode::DAdd => self.binary(&mut state, BinaryOp::Add, Type::Double)?,
+				Opcode::ISub => self.binary(&mut state, BinaryOp::Sub, Type::Int)?,
+				Opcode::LSub => self.binary(&mut state, BinaryOp::Sub, Type::Long)?,
+				Opcode::FSub => self.binary(&mut state, BinaryOp::Sub, Type::Float)?,
+				Opcode::DSub => self.binary(&mut state, BinaryOp::Sub, Type::Double)?,
+				Opcode::IMul => self.binary(&mut state, BinaryOp::Mul, Type::Int)?,
+				Opcode::LMul => self.binary(&mut state, BinaryOp::Mul, Type::Long)?,
+				Opcode::FMul => self.binary(&mut state, BinaryOp::Mul, Type::Float)?,
+				Opcode::DMul => self.binary(&mut state, BinaryOp::Mul, Type::Double)?,
+				Opcode::IDiv => self.binary(&mut state, BinaryOp::Div, Type::Int)?,
+				Opcode::LDiv => self.binary(&mut state, BinaryOp::Div, Type::Long)?,
+				Opcode::FDiv => self.binary(&mut state, BinaryOp::Div, Type::Float)?,
+				Opcode::DDiv => self.binary(&mut state, BinaryOp::Div, Type::Double)?,
+				Opcode::IRem => self.binary(&mut state, BinaryOp::Rem, Type::Int)?,
+				Opcode::LRem => self.binary(&mut state, BinaryOp::Rem, Type::Long)?,
+				Opcode::FRem => self.binary(&mut state, BinaryOp::Rem, Type::Float)?,
+				Opcode::DRem => self.binary(&mut state, BinaryOp::Rem, Type::Double)?,
+				Opcode::IShl => self.binary(&mut state, BinaryOp::Shl, Type::Int)?,
+				Opcode::LShl => self.binary(&mut state, BinaryOp::Shl, Type::Long)?,
+				Opcode::IShr => self.binary(&mut state, BinaryOp::Shr, Type::Int)?,
+				Opcode::LShr => self.binary(&mut state, BinaryOp::Shr, Type::Long)?,
+				Opcode::IUShr => self.binary(&mut state, BinaryOp::UShr, Type::Int)?,
+				Opcode::LUShr => self.binary(&mut state, BinaryOp::UShr, Type::Long)?,
+				Opcode::IAnd => self.binary(&mut state, BinaryOp::And, Type::Int)?,
+				Opcode::LAnd => self.binary(&mut state, BinaryOp::And, Type::Long)?,
+				Opcode::IOr => self.binary(&mut state, BinaryOp::Or, Type::Int)?,
+				Opcode::LOr => self.binary(&mut state, BinaryOp::Or, Type::Long)?,
+				Opcode::IXor => self.binary(&mut state, BinaryOp::Xor, Type::Int)?,
+				Opcode::LXor => self.binary(&mut state, BinaryOp::Xor, Type::Long)?,
+				Opcode::INeg | Opcode::LNeg | Opcode::FNeg | Opcode::DNeg => {
+					let value = state.pop()?;
+					state.stack.push(Expr::Unary(UnaryOp::Neg, Box::new(value)));
+				},
+				Opcode::I2l | Opcode::F2l | Opcode::D2l => { let value = state.pop()?; state.stack.push(Expr::Cast(Type::Long, Box::new(value))); },
+				Opcode::I2f | Opcode::L2f | Opcode::D2f => { let value = state.pop()?; state.stack.push(Expr::Cast(Type::Float, Box::new(value))); },
+				Opcode::I2d | Opcode::L2d | Opcode::F2d => { let value = state.pop()?; state.stack.push(Expr::Cast(Type::Double, Box::new(value))); },
+				Opcode::L2i | Opcode::F2i | Opcode::D2i => { let value = state.pop()?; state.stack.push(Expr::Cast(Type::Int, Box::new(value))); },
+				Opcode::I2b => { let value = state.pop()?; state.stack.push(Expr::Cast(Type::Byte, Box::new(value))); },
+				Opcode::I2c => { let value = state.pop()?; state.stack.push(Expr::Cast(Type::Char, Box::new(value))); },
+				Opcode::I2s => { let value = state.pop()?; state.stack.push(Expr::Cast(Type::Short, Box::new(value))); },
+				Opcode::LCmp | Opcode::FCmpL | Opcode::FCmpG | Opcode::DCmpL | Opcode::DCmpG => {
+					let right = state.pop()?;
+					let left = state.pop()?;
+					let ty = match opcode {
+						Opcode::LCmp => Type::Long,
+						Opcode::FCmpL | Opcode::FCmpG => Type::Float,
+						_ => Type::Double,
+					};
+					let nan_greater = matches!(opcode, Opcode::FCmpG | Opcode::DCmpG);
+					state.stack.push(Expr::Compare { ty, left: Box::new(left), right: Box::new(right), nan_greater });
+				},
+
+				Opcode::IfEq(target) | Opcode::IfNe(target) | Opcode::IfLt(target) | Opcode::IfGe(target) | Opcode::IfGt(target) |
+				Opcode::IfLe(target) => {
+					let op = match opcode {
+						Opcode::IfEq(_) => BinaryOp::Eq,
+						Opcode::IfNe(_) => BinaryOp::Ne,
+						Opcode::IfLt(_) => BinaryOp::Lt,
+						Opcode::IfGe(_) => BinaryOp::Ge,
+						Opcode::IfGt(_) => BinaryOp::Gt,
+						_ => BinaryOp::Le,
+					};
+					let value = state.pop()?;
+					let condition = self.compare_with_zero(op, value);
+					self.spill(&mut state, None);
+					return Ok((state.statements, Terminator::If { condition, then: node(target)?, otherwise: id.0 + 1 }, state.stack));
+				},
+				Opcode::IfICmpEq(target) | Opcode::IfICmpNe(target) | Opcode::IfICmpLt(target) | Opcode::IfICmpGe(target) |
+				Opcode::IfICmpGt(target) | Opcode::IfICmpLe(target) | Opcode::IfACmpEq(target) | Opcode::IfACmpNe(target) => {
+					let (op, ty) = match opcode {
+						Opcode::IfICmpEq(_) => (BinaryOp::Eq, Type::Int),
+						Opcode::IfICmpNe(_) => (BinaryOp::Ne, Type::Int),
+						Opcode::IfICmpLt(_) => (BinaryOp::Lt, Type::Int),
+						Opcode::IfICmpGe(_) => (BinaryOp::Ge, Type::Int),
+						Opcode::IfICmpGt(_) => (BinaryOp::Gt, Type::Int),
+						Opcode::IfICmpLe(_) => (BinaryOp::Le, Type::Int),
+						Opcode::IfACmpEq(_) => (BinaryOp::Eq, Type::object()),
+						_ => (BinaryOp::Ne, Type::object()),
+					};
+					let right = state.pop()?;
+					let left = state.pop()?;
+					let condition = self.comparison(op, ty, left, right);
+					self.spill(&mut state, None);
+					return Ok((state.statements, Terminator::If { condition, then: node(target)?, otherwise: id.0 + 1 }, state.stack));
+				},
+				Opcode::IfNull(target) | Opcode::IfNonNull(target) => {
+					let op = if matches!(opcode, Opcode::IfNull(_)) { BinaryOp::Eq } else { BinaryOp::Ne };
+					let value = state.pop()?;
+					let condition = Expr::Binary { op, ty: Type::object(), left: Box::new(value), right: Box::new(Expr::Null) };
+					self.spill(&mut state, None);
+					return Ok((state.statements, Terminator::If { condition, then: node(target)?, otherwise: id.0 + 1 }, state.stack));
+				},
+				Opcode::Goto(target) => return Ok((state.statements, Terminator::Jump(node(target)?), state.stack)),
+				Opcode::TableSwitch { default_target, low, targets, .. } => {
+					let value = state.pop()?;
+					let cases = targets.iter()
+						.enumerate()
+						.map(|(index, target)| Ok((low.wrapping_add(index as i32), node(target)?)))
+						.collect::<Result<_>>()?;
+					self.spill(&mut state, None);
+					return Ok((state.statements, Terminator::Switch { value, cases, default: node(default_target)? }, state.stack));
+				},
+				Opcode::LookupSwitch { default_target, targets, .. } => {
+					let value = state.pop()?;
+					let cases = targets.iter()
+						.map(|(key, target)| Ok((*key, node(target)?)))
+						.collect::<Result<_>>()?;
+					self.spill(&mut state, None);
+					return Ok((state.statements, Terminator::Switch { value, cases, default: node(default_target)? }, state.stack));
+				},
+				Opcode::IReturn | Opcode::LReturn | Opcode::FReturn | Opcode::DReturn | Opcode::AReturn => {
+					let value = state.pop()?;
+					return Ok((state.statements, Terminator::Return(Some(value)), Vec::new()));
+				},
+				Opcode::Return => return Ok((state.statements, Terminator::Return(None), Vec::new())),
+				Opcode::AThrow => {
+					let value = state.pop()?;
+					return Ok((state.statements, Terminator::Throw(value), Vec::new()));
+				},
+
+				Opcode::GetStatic(field) => state.stack.push(Expr::Field { target: None, field: field.clone() }),
+				Opcode::GetField(field) => {
+					let target = state.pop()?;
+					state.stack.push(Expr::Field { target: Some(Box::new(target)), field: field.clone() });
+				},
+				Opcode::PutStatic(field) => {
+					let value = state.pop()?;
+					self.store(&mut state, Expr::Field { target: None, field: field.clone() }, value, duplicated);
+				},
+				Opcode::PutField(field) => {
+					let value = state.pop()?;
+					let target = state.pop()?;
+					self.store(&mut state, Expr::Field { target: Some(Box::new(target)), field: field.clone() }, value, duplicated);
+				},
+				Opcode::InvokeVirtual(method) => self.invoke(&mut state, InvokeKind::Virtual, method.class, method.name, &method.descriptor)?,
+				Opcode::InvokeSpecial(method) => self.invoke(&mut state, InvokeKind::Special, method.class, method.name, &method.descriptor)?,
+				Opcode::InvokeStatic(method) => self.invoke(&mut state, InvokeKind::Static, method.class, method.name, &method.descriptor)?,
+				Opcode::InvokeInterface { method_ref, .. } => {
+					self.invoke(&mut state, InvokeKind::Virtual, method_ref.class, method_ref.name, &method_ref.descriptor)?;
+				},
+				Opcode::InvokeDynamic { call_site, .. } => {
+					let arguments = self.arguments(&mut state, &call_site.descriptor)?;
+					let returns = call_site.descriptor.return_type.is_some();
+					let value = match self.class.call_site(call_site).map(|site| site.kind) {
+						Ok(CallSiteKind::StringConcat { recipe, constants }) => Expr::Concat(concatenation(recipe, constants, arguments)),
+						Ok(CallSiteKind::Lambda { interface_method_type, implementation, .. }) => Expr::Lambda {
+							ty: call_site.descriptor.return_type.as_ref().map_or_else(Type::object, Type::of),
+							parameters: interface_method_type.parameters.len(),
+							implementation: implementation.clone(),
+							captured: arguments,
+						},
+						_ => Expr::Dynamic { name: call_site.name, descriptor: call_site.descriptor.clone(), arguments },
+					};
+					self.result(&mut state, value, returns);
+				},
+
+				Opcode::New(class) => state.stack.push(Expr::Uninitialized { class: *class, offset }),
+				Opcode::NewArray { a_type } => {
+					let length = state.pop()?;
+					let base_type = match a_type {
+						ArrayType::Boolean => BaseOrObjectType::Z,
+						ArrayType::Char => BaseOrObjectType::C,
+						ArrayType::Float => BaseOrObjectType::F,
+						ArrayType::Double => BaseOrObjectType::D,
+						ArrayType::Byte => BaseOrObjectType::B,
+						ArrayType::Short => BaseOrObjectType::S,
+						ArrayType::Int => BaseOrObjectType::I,
+						ArrayType::Long => BaseOrObjectType::J,
+					};
+					let ty = FieldDescriptor { array_dimension: 1, base_type };
+					state.stack.push(Expr::NewArray { ty, dimensions: vec![length], elements: None });
+				},
+				Opcode::ANewArray(class) => {
+					let length = state.pop()?;
+					let component = Type::of_class(*class).descriptor();
+					let ty = FieldDescriptor { array_dimension: component.array_dimension + 1, base_type: component.base_type };
+					state.stack.push(Expr::NewArray { ty, dimensions: vec![length], elements: None });
+				},
+				Opcode::MultiANewArray(class, dimensions) => {
+					let mut lengths = Vec::new();
+					for _ in 0..*dimensions {
+						lengths.push(state.pop()?);
+					}
+					lengths.reverse();
+					let ty = Type::of_class(*class).descriptor();
+					state.stack.push(Expr::NewArray { ty, dimensions: lengths, elements: None });
+				},
+				Opcode::CheckCast(class) => {
+					let value = state.pop()?;
+					state.stack.push(Expr::Cast(Type::of_class(*class), Box::new(value)));
+				},
+				Opcode::InstanceOf(class) => {
+					let value = state.pop()?;
+					state.stack.push(Expr::InstanceOf(Box::new(value), Type::of_class(*class)));
+				},
+				Opcode::MonitorEnter => {
+					let value = state.pop()?;
+					self.emit(&mut state, Stmt::MonitorEnter(value));
+				},
+				Opcode::MonitorExit => {
+					let value = state.pop()?;
+					self.emit(&mut state, Stmt::MonitorExit(value));
+				},
+				Opcode::Nop | Opcode::Breakpoint | Opcode::ImpDep1 | Opcode::ImpDep2 => {},
+				Opcode::Jsr(_) | Opcode::Ret(_) => bail!("subroutines are inlined before decompiling"),
+			}
+		}
+		Ok((state.statements, Terminator::Jump(id.0 + 1), state.stack))
+	}
+}
+
+/// The parts of a string concatenation compiled to `invokedynamic`, from the recipe and the arguments of the call site.
+fn concatenation(recipe: Option<&[u8]>, constants: &[BootstrapMethodArgument], arguments: Vec<Expr>) -> Vec<Expr> {
+	let Some(recipe) = recipe else {
+		return arguments;
+	};
+	let mut arguments = arguments.into_iter();
+	let mut constants = constants.iter();
+	let mut parts = Vec::new();
+	let mut literal = Vec::new();
+	for &byte in recipe {
+		if byte == 1 || byte == 2 {
+			if !literal.is_empty() {
+				parts.push(Expr::String(core::mem::take(&mut literal)));
+			}
+			let part = if byte == 1 { arguments.next() } else { constants.next().map(constant) };
+			parts.extend(part);
+		} else {
+			literal.push(byte);
+		}
+	}
+	if !literal.is_empty() || parts.is_empty() {
+		parts.push(Expr::String(literal));
+	}
+	parts
+}
+
+fn constant(argument: &BootstrapMethodArgument) -> Expr {
+	match argument {
+		BootstrapMethodArgument::String(string) => Expr::String(string.clone()),
+		BootstrapMethodArgument::Class(class) => Expr::Class(Type::of_class(*class)),
+		BootstrapMethodArgument::Integer(value) => Expr::Int(*value),
+		BootstrapMethodArgument::Long(value) => Expr::Long(*value),
+		BootstrapMethodArgument::Float(bits) => Expr::Float(*bits),
+		BootstrapMethodArgument::Double(bits) => Expr::Double(*bits),
+		BootstrapMethodArgument::MethodHandle(_) | BootstrapMethodArgument::MethodType(_) => Expr::Constant(String::from("/* constant */ null"), Type::object()),
+	}
+}
@@ -0,0 +1,648 @@
+//! Reconstructing Java source from class files, for reading code whose source isn't at hand.
+//!
+//! The code of each method goes through these steps:
+//! - [graph]: the instructions of each basic block are simulated on a stack of expressions, giving statements for the values stored or discarded,
+//!   and a terminator for how the block ends. Conditions spread over several blocks are joined with `&&` and `||`, and the alternatives of
+//!   conditional expressions are joined into one. Local variables get their names and generic types from the `LocalVariableTable` and
+//!   `LocalVariableTypeTable` attributes, if they're there.
+//! - [structure]: the blocks are turned into loops, `try` statements, `if`s and `switch`es, with `break`, `continue` and, where the code doesn't fit
+//!   these, labels and `goto`s.
+//! - [stmt]: loops get their conditions, counting loops become `for` loops, and variables are declared in the innermost block using them.
+//!
+//! The output is meant to be read, not compiled: it's Java where the code allows, but `goto`s, values left on the stack across blocks, and
+//! constructs javac generates, like the `switch` on the hash code of a string, show up as they are in the code. A method that can't be decompiled
+//! is written with the error and a listing of its code.
+
+mod expr;
+mod graph;
+mod stmt;
+mod structure;
+mod types;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::Result;
+use core::fmt::Write;
+use crate::{ClassFile, FieldInfo, MethodInfo};
+use crate::cp::attribute::{CodeAttribute, ConstantValueAttribute};
+use crate::decompile::expr::{Expr, InvokeKind, Printer, Type};
+use crate::decompile::graph::Graph;
+use crate::decompile::stmt::Stmt;
+use crate::decompile::types::{text, Names};
+use crate::deps::package;
+use crate::diff::listing;
+use crate::name::ClassName;
+use crate::signature::{ClassSignature, MethodSignature, TypeSignature};
+
+/// Decompiles classes, writing the member classes of a class inside it if they were added too.
+#[derive(Debug, Default)]
+pub struct Decompiler<'a> {
+	classes: BTreeMap<ClassName, &'a ClassFile>,
+}
+
+/// Decompiles a single class, without its member classes.
+pub fn decompile(class: &ClassFile) -> String {
+	let mut decompiler = Decompiler::new();
+	decompiler.add_class(class);
+	decompiler.decompile(class)
+}
+
+impl<'a> Decompiler<'a> {
+	pub fn new() -> Decompiler<'a> {
+		Decompiler::default()
+	}
+
+	/// Adds a class, so that it's written inside the class it's a member of.
+	pub fn add_class(&mut self, class: &'a ClassFile) -> &mut Self {
+		self.classes.insert(class.this_class, class);
+		self
+	}
+
+	/// Returns whether the class is a member of a class that was added, and so is written inside that one.
+	pub fn is_nested(&self, class: &ClassFile) -> bool {
+		class.inner_class_entry()
+			.and_then(|entry| entry.outer_class)
+			.is_some_and(|outer| self.classes.contains_key(&outer))
+	}
+
+	/// Returns the source of a class, with its package and imports, and the member classes that were added.
+	pub fn decompile(&self, class: &ClassFile) -> String {
+		let mut names = Names::new(&class.this_class, self.classes.values().copied().chain([class]));
+		// the imports are chosen from the classes used in a first pass
+		self.write_class(&mut String::new(), &names, class, 0);
+		let imports = names.import_used();
+		let mut body = String::new();
+		self.write_class(&mut body, &names, class, 0);
+
+		let mut out = String::new();
+		let package = package(&class.this_class);
+		if !package.is_empty() {
+			let _ = writeln!(out, "package {};\n", java_name(package));
+		}
+		for import in &imports {
+			let _ = writeln!(out, "import {};", java_name(import.as_bytes()));
+		}
+		if !imports.is_empty() {
+			out.push('\n');
+		}
+		out.push_str(&body);
+		out
+	}
+
+	fn write_class(&self, out: &mut String, names: &Names, class: &ClassFile, indent: usize) {
+		let access = &class.access_flags;
+		let flags = class.inner_class_entry().map_or(0, |entry| entry.inner_class_access_flags);
+		let nested = class.inner_class_entry().is_some();
+		if class.is_deprecated() {
+			write_indent(out, indent);
+			out.push_str("@Deprecated\n");
+		}
+		write_indent(out, indent);
+		let mut modifiers = Vec::new();
+		if nested {
+			modifiers.extend([(0x0001, "public"), (0x0004, "protected"), (0x0002, "private")].iter().filter(|(flag, _)| flags & flag != 0).map(|&(_, name)| name));
+			if flags & 0x0008 != 0 && !access.is_interface && !access.is_enum {
+				modifiers.push("static");
+			}
+		} else if access.is_public {
+			modifiers.push("public");
+		}
+		if access.is_abstract && !access.is_interface && !access.is_enum {
+			modifiers.push("abstract");
+		}
+		if access.is_final && !access.is_enum {
+			modifiers.push("final");
+		}
+		modifiers.push(if access.is_annotation {
+			"@interface"
+		} else if access.is_interface {
+			"interface"
+		} else if access.is_enum {
+			"enum"
+		} else {
+			"class"
+		});
+		out.push_str(&modifiers.join(" "));
+		out.push(' ');
+		let simple_name = class.simple_name();
+		out.push_str(&text(if simple_name.is_empty() { class_simple_name(&class.this_class) } else { simple_name }));
+
+		let signature = class.signature().and_then(|signature| ClassSignature::parse(signature).ok());
+		let (super_class, interfaces): (Option<String>, Vec<String>) = match &signature {
+			Some(signature) => {
+				out.push_str(&names.type_parameters(&signature.type_parameters));
+				let super_class = Some(&signature.super_class).filter(|super_class| is_written_super_class(class, &super_class.name));
+				let interfaces = signature.interfaces.iter()
+					.filter(|interface| !access.is_annotation || interface.name.as_bytes() != b"java/lang/annotation/Annotation")
+					.map(|interface| names.class_signature(interface))
+					.collect();
+				(super_class.map(|super_class| names.class_signature(super_class)), interfaces)
+			},
+			None => {
+				let super_class = class.super_class.filter(|super_class| is_written_super_class(class, super_class));
+				let interfaces = class.interfaces.iter()
+					.filter(|interface| !access.is_annotation || interface.as_bytes() != b"java/lang/annotation/Annotation")
+					.map(|interface| names.class(interface))
+					.collect();
+				(super_class.map(|super_class| names.class(&super_class)), interfaces)
+			},
+		};
+		if let Some(super_class) = super_class {
+			let _ = write!(out, " extends {super_class}");
+		}
+		if !interfaces.is_empty() {
+			let _ = write!(out, " {} {}", if access.is_interface { "extends" } else { "implements" }, interfaces.join(", "));
+		}
+		out.push_str(" {\n");
+
+		let mut sections = Vec::new();
+		let constants: Vec<String> = class.fields.iter()
+			.filter(|field| field.access_flags.is_enum)
+			.map(|field| text(field.name.as_bytes()))
+			.collect();
+		if !constants.is_empty() {
+			let mut section = String::new();
+			write_indent(&mut section, indent + 1);
+			let _ = writeln!(section, "{};", constants.join(", "));
+			sections.push(section);
+		}
+		let mut section = String::new();
+		for field in class.fields.iter().filter(|field| !field.access_flags.is_synthetic && !field.access_flags.is_enum) {
+			write_field(&mut section, names, class, field, indent + 1);
+		}
+		if !section.is_empty() {
+			sections.push(section);
+		}
+		let constructors = class.methods.iter().filter(|method| method.name.as_bytes() == b"<init>").count();
+		for method in class.methods.iter().filter(|method| is_written(class, method)) {
+			let mut section = String::new();
+			write_method(&mut section, names, class, method, constructors, indent + 1);
+			if !section.is_empty() {
+				sections.push(section);
+			}
+		}
+		for entry in class.inner_classes().iter().filter(|entry| entry.outer_class == Some(class.this_class)) {
+			if let Some(member) = self.classes.get(&entry.inner_class) {
+				let mut section = String::new();
+				self.write_class(&mut section, names, member, indent + 1);
+				sections.push(section);
+			}
+		}
+		out.push_str(&sections.join("\n"));
+		write_indent(out, indent);
+		out.push_str("}\n");
+	}
+}
+
+fn java_name(name: &[u8]) -> String {
+	text(name).replace('/', ".")
+}
+
+fn class_simple_name(class: &ClassName) -> &'static [u8] {
+	let name = class.as_bytes();
+	name.rsplit(|&byte| byte == b'/' || byte == b'$').next().unwrap_or(name)
+}
+
+fn write_indent(out: &mut String, indent: usize) {
+	for _ in 0..indent {
+		out.push('\t');
+	}
+}
+
+/// Whether the super class is written: it's left out if it's `Object`, the `Enum` of an enum, or the super class of an interface.
+fn is_written_super_class(class: &ClassFile, super_class: &ClassName) -> bool {
+	!class.access_flags.is_interface && super_class.as_bytes() != b"java/lang/Object" && !(class.access_flags.is_enum && super_class.as_bytes() == b"java/lang/Enum")
+}
+
+/// Whether a method is written: bridge methods, the methods javac generates for enums, and synthetic methods other than the bodies of lambdas
+/// are left out.
+fn is_written(class: &ClassFile, method: &MethodInfo) -> bool {
+	let name = method.name.as_bytes();
+	if method.access_flags.is_bridge || method.access_flags.is_synthetic && !name.starts_with(b"lambda$") {
+		return false;
+	}
+	!(class.access_flags.is_enum && method.access_flags.is_static && matches!(name, b"values" | b"valueOf" | b"$values"))
+}
+
+fn write_field(out: &mut String, names: &Names, class: &ClassFile, field: &FieldInfo, indent: usize) {
+	if field.is_deprecated() {
+		write_indent(out, indent);
+		out.push_str("@Deprecated\n");
+	}
+	write_indent(out, indent);
+	let access = &field.access_flags;
+	for (set, modifier) in [
+		(access.is_public, "public "), (access.is_protected, "protected "), (access.is_private, "private "), (access.is_static, "static "),
+		(access.is_final, "final "), (access.is_transient, "transient "), (access.is_volatile, "volatile "),
+	] {
+		if set && !class.access_flags.is_interface {
+			out.push_str(modifier);
+		}
+	}
+	match field.signature().and_then(|signature| TypeSignature::parse(signature).ok()) {
+		Some(signature) => out.push_str(&names.signature(&signature)),
+		None => out.push_str(&names.descriptor(&field.descriptor)),
+	}
+	out.push(' ');
+	out.push_str(&text(field.name.as_bytes()));
+	if let Some(constant) = &field.constant_value {
+		let value = match constant {
+			ConstantValueAttribute::Integer(value) => Expr::Int(*value).coerce(Type::of(&field.descriptor)),
+			ConstantValueAttribute::Long(value) => Expr::Long(*value),
+			ConstantValueAttribute::Float(bits) => Expr::Float(*bits),
+			ConstantValueAttribute::Double(bits) => Expr::Double(*bits),
+			ConstantValueAttribute::String(value) => Expr::String(value.clone()),
+		};
+		let printer = Printer { names, class: class.this_class, variables: &[] };
+		out.push_str(" = ");
+		out.push_str(&value.to_source(&printer));
+	}
+	out.push_str(";\n");
+}
+
+/// Writes a method, or nothing for a constructor that is the only one and does nothing, like the one javac adds to classes without any.
+fn write_method(out: &mut String, names: &Names, class: &ClassFile, method: &MethodInfo, constructors: usize, indent: usize) {
+	let body = method.code.as_ref().map(|_| decompile_method(class, method));
+	let is_constructor = method.name.as_bytes() == b"<init>";
+	if is_constructor && constructors == 1 && method.descriptor.parameters.is_empty() && matches!(&body, Some(Ok((_, statements))) if statements.is_empty()) {
+		return;
+	}
+
+	if method.is_deprecated() {
+		write_indent(out, indent);
+		out.push_str("@Deprecated\n");
+	}
+	write_indent(out, indent);
+	if method.name.as_bytes() == b"<clinit>" {
+		out.push_str("static");
+	} else {
+		write_method_header(out, names, class, method, body.as_ref().and_then(|body| body.as_ref().ok()).map(|(graph, _)| graph));
+	}
+	match body {
+		None => out.push_str(";\n"),
+		Some(Ok((graph, statements))) => {
+			out.push_str(" {\n");
+			let printer = Printer { names, class: class.this_class, variables: &graph.variables };
+			stmt::write_block(out, &printer, &statements, indent + 1);
+			write_indent(out, indent);
+			out.push_str("}\n");
+		},
+		Some(Err(error)) => {
+			out.push_str(" {\n");
+			write_indent(out, indent + 1);
+			let _ = writeln!(out, "// could not decompile: {error:#}");
+			for line in method.code.as_ref().map(listing).unwrap_or_default() {
+				write_indent(out, indent + 1);
+				let _ = writeln!(out, "// {line}");
+			}
+			write_indent(out, indent);
+			out.push_str("}\n");
+		},
+	}
+}
+
+fn write_method_header(out: &mut String, names: &Names, class: &ClassFile, method: &MethodInfo, graph: Option<&Graph>) {
+	let access = &method.access_flags;
+	let interface = class.access_flags.is_interface;
+	let mut modifiers = Vec::new();
+	for (set, modifier) in [
+		(access.is_public && !interface, "public"), (access.is_protected, "protected"), (access.is_private, "private"),
+		(access.is_abstract && !interface, "abstract"), (access.is_static, "static"), (access.is_final, "final"),
+		(access.is_synchronised, "synchronized"), (access.is_native, "native"), (access.is_strict, "strictfp"),
+		(interface && !access.is_abstract && !access.is_static && !access.is_private, "default"),
+	] {
+		if set {
+			modifiers.push(modifier);
+		}
+	}
+	for modifier in modifiers {
+		out.push_str(modifier);
+		out.push(' ');
+	}
+
+	let signature = method.signature().and_then(|signature| MethodSignature::parse(signature).ok())
+		// the signatures of the constructors of inner classes and enums leave out the parameters javac adds
+		.filter(|signature| signature.parameters.len() == method.descriptor.parameters.len());
+	if let Some(signature) = &signature {
+		let type_parameters = names.type_parameters(&signature.type_parameters);
+		if !type_parameters.is_empty() {
+			out.push_str(&type_parameters);
+			out.push(' ');
+		}
+	}
+	if method.name.as_bytes() == b"<init>" {
+		let simple_name = class.simple_name();
+		out.push_str(&text(if simple_name.is_empty() { class_simple_name(&class.this_class) } else { simple_name }));
+	} else {
+		let return_type = match (&signature, &method.descriptor.return_type) {
+			(Some(MethodSignature { return_type: Some(return_type), .. }), _) => names.signature(return_type),
+			(_, Some(return_type)) => names.descriptor(return_type),
+			(_, None) => String::from("void"),
+		};
+		let _ = write!(out, "{return_type} {}", text(method.name.as_bytes()));
+	}
+
+	let parameters: Vec<String> = method.descriptor.parameters.iter()
+		.enumerate()
+		.map(|(index, descriptor)| {
+			let mut ty = match &signature {
+				Some(signature) => names.signature(&signature.parameters[index]),
+				None => names.descriptor(descriptor),
+			};
+			if access.is_varargs && index + 1 == method.descriptor.parameters.len() && ty.ends_with("[]") {
+				ty.truncate(ty.len() - 2);
+				ty.push_str("...");
+			}
+			let name = graph.and_then(|graph| graph.parameters.get(index).map(|&variable| graph.variables[variable].name.clone()))
+				.unwrap_or_else(|| format!("arg{index}"));
+			format!("{ty} {name}")
+		})
+		.collect();
+	let _ = write!(out, "({})", parameters.join(", "));
+
+	let throws: Vec<String> = match &signature {
+		Some(signature) if !signature.throws.is_empty() => signature.throws.iter().map(|throws| names.signature(throws)).collect(),
+		_ => method.exceptions().iter().map(|exception| names.class(exception)).collect(),
+	};
+	if !throws.is_empty() {
+		let _ = write!(out, " throws {}", throws.join(", "));
+	}
+}
+
+/// Decompiles the code of a method into statements, with the graph they were built from for the variables they use.
+fn decompile_method(class: &ClassFile, method: &MethodInfo) -> Result<(Graph, Vec<Stmt>)> {
+	// javac compiled `finally` to subroutines before 1.4.2, their copies read like the code compiled since then
+	let mut inlined = None;
+	if method.code.as_ref().is_some_and(CodeAttribute::has_subroutines) {
+		let mut copy = method.clone();
+		if let Some(code) = &mut copy.code {
+			code.inline_subroutines()?;
+		}
+		inlined = Some(copy);
+	}
+	let mut graph = Graph::new(class, inlined.as_ref().unwrap_or(method))?;
+	graph.reduce();
+	graph.resolve_stack();
+	graph.coerce();
+	let mut statements = structure::structure(&graph);
+	stmt::remove_unused_labels(&mut statements);
+	stmt::simplify(&mut statements);
+	if method.descriptor.return_type.is_none() && statements.last() == Some(&Stmt::Return(None)) {
+		statements.pop();
+	}
+	if method.name.as_bytes() == b"<init>" {
+		// the call of a constructor of the super class without arguments, and the one of `Enum` javac adds to enum constructors, aren't written;
+		// javac stores the outer instance of inner classes before it
+		let call = statements.iter().position(|statement| matches!(statement,
+			Stmt::Expr(Expr::Invoke { kind: InvokeKind::Special, target: Some(target), name, .. }) if matches!(**target, Expr::This(_)) && name.as_bytes() == b"<init>"
+		));
+		if let Some(Stmt::Expr(Expr::Invoke { class: called, arguments, .. })) = call.map(|call| &statements[call]) {
+			let implicit = arguments.is_empty() || class.access_flags.is_enum && called.as_bytes() == b"java/lang/Enum";
+			if *called != class.this_class && implicit {
+				statements.remove(call.unwrap_or_default());
+			}
+		}
+	}
+	let declared: BTreeSet<usize> = graph.parameters.iter().copied().collect();
+	stmt::declare(&mut statements, &declared);
+	Ok((graph, statements))
+}
+
+
+#[cfg(test)]
+mod testing {
+	use alloc::string::String;
+	use alloc::vec::Vec;
+	use crate::ClassFile;
+	use crate::cp::MethodRefInfo;
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::MethodDescriptor;
+	use crate::instruction::builder::CodeBuilder;
+	use crate::instruction::opcode::Opcode;
+	use crate::name::{ClassName, MethodName};
+	use super::decompile;
+
+	fn test2() -> ClassFile {
+		let bytes = include_bytes!("../../../../java_example_classfiles/Test2.class");
+		ClassFile::parse(&mut &bytes[..]).unwrap()
+	}
+
+	/// The class of `Decompile.java`, compiled with `javac --release 8 -g`.
+	fn decompile_class() -> ClassFile {
+		let bytes = include_bytes!("../../../../java_example_classfiles/Decompile.class");
+		ClassFile::parse(&mut &bytes[..]).unwrap()
+	}
+
+	fn decompile_lines(class: &ClassFile) -> Vec<String> {
+		decompile(class).lines().map(|line| line.trim().into()).collect()
+	}
+
+	fn assert_contains(lines: &[String], expected: &[&str]) {
+		assert!(lines.windows(expected.len()).any(|window| window == expected), "{expected:#?} not in {lines:#?}");
+	}
+
+	#[test]
+	fn class() {
+		let lines = decompile_lines(&test2());
+		assert_contains(&lines, &["import java.util.Objects;", "", "public class Test2 {"]);
+		// the constant is written where it's declared, the other values where they're assigned
+		assert_contains(&lines, &["public static final int FOO;", "public static final byte BAR = 0;", "public boolean baz;"]);
+		assert_contains(&lines, &["public Test2() {", "this.baz = true;", "this.foo = 0L;"]);
+		assert_contains(&lines, &["static {", "FOO = getFoo();", "}"]);
+		assert_contains(&lines, &[
+			"public static void main(String[] arg0) {",
+			"System.out.println(FOO);",
+			"for (int var1 = 1; var1 < FOO; var1 *= 2) {",
+			"System.out.println(var1);",
+			"}",
+			"String var1_2 = \"1\" + FOO + \"23\";",
+			"System.out.println(var1_2.hashCode() + \" \" + Objects.hashCode(var1_2));",
+		]);
+		assert_contains(&lines, &["String var3 = new String(new char[]{'1', '1', '0', '2', '3'});"]);
+	}
+
+	#[test]
+	fn branches_and_loops() {
+		let mut class = test2();
+		let descriptor = MethodDescriptor::try_from(&b"(I)I"[..]).unwrap();
+		let mut code = CodeBuilder::new(descriptor.clone(), true);
+		let (head, end, negative) = (code.new_label(), code.new_label(), code.new_label());
+		code.iconst(0).istore(1)
+			.place(head).iload(0).ifle(end)
+			.iload(1).iload(0).emit(Opcode::IAdd).istore(1)
+			.iinc(0, -1)
+			.goto(head)
+			.place(end).iload(1).iflt(negative)
+			.iload(1).emit(Opcode::IReturn)
+			.place(negative).iconst(0).emit(Opcode::IReturn);
+		let method = class.methods.iter_mut().find(|method| method.name.as_bytes() == b"getFoo").unwrap();
+		method.descriptor = descriptor;
+		method.code = Some(code.build(&mut PoolBuilder::new()).unwrap());
+		assert_contains(&decompile_lines(&class), &[
+			"public static int getFoo(int arg0) {",
+			"int var1 = 0;",
+			"while (arg0 > 0) {",
+			"var1 += arg0;",
+			"arg0--;",
+			"}",
+			"if (var1 >= 0) {",
+			"return var1;",
+			"}",
+			"return 0;",
+			"}",
+		]);
+	}
+
+	#[test]
+	fn loops_tested_at_the_end() {
+		let mut class = test2();
+		let descriptor = MethodDescriptor::try_from(&b"(I)I"[..]).unwrap();
+		let mut code = CodeBuilder::new(descriptor.clone(), true);
+		let (body, test) = (code.new_label(), code.new_label());
+		// like ecj compiles `for (int j = 0; j < i; j++) sum += j;`
+		code.iconst(0).istore(1)
+			.iconst(0).istore(2)
+			.goto(test)
+			.place(body).iload(1).iload(2).emit(Opcode::IAdd).istore(1)
+			.iinc(2, 1)
+			.place(test).iload(2).iload(0).if_icmplt(body)
+			.iload(1).emit(Opcode::IReturn);
+		let method = class.methods.iter_mut().find(|method| method.name.as_bytes() == b"getFoo").unwrap();
+		method.descriptor = descriptor;
+		method.code = Some(code.build(&mut PoolBuilder::new()).unwrap());
+		assert_contains(&decompile_lines(&class), &[
+			"public static int getFoo(int arg0) {",
+			"int var1 = 0;",
+			"for (int var2 = 0; var2 < arg0; var2++) {",
+			"var1 += var2;",
+			"}",
+			"return var1;",
+			"}",
+		]);
+	}
+
+	#[test]
+	fn discarded_values_that_can_throw() {
+		let mut class = test2();
+		let descriptor = MethodDescriptor::try_from(&b"(Ljava/lang/Object;[II)V"[..]).unwrap();
+		let mut code = CodeBuilder::new(descriptor.clone(), true);
+		let string_builder = ClassName::from(b"java/lang/StringBuilder");
+		let method = |class: ClassName, name: &[u8], descriptor: &[u8]| MethodRefInfo {
+			class, name: MethodName::from(name), descriptor: MethodDescriptor::try_from(descriptor).unwrap(),
+		};
+		code.aload(1).emit(Opcode::ArrayLength).emit(Opcode::Pop)
+			.aload(0).checkcast(ClassName::from(b"java/lang/String")).emit(Opcode::Pop)
+			.iload(2).iload(2).emit(Opcode::IDiv).emit(Opcode::Pop)
+			.iload(2).iload(2).emit(Opcode::IRem).emit(Opcode::Pop)
+			.new_(string_builder).emit(Opcode::Dup).invokespecial(method(string_builder, b"<init>", b"()V"))
+			.aload(0).invokevirtual(method(string_builder, b"append", b"(Ljava/lang/Object;)Ljava/lang/StringBuilder;"))
+			.invokevirtual(method(string_builder, b"toString", b"()Ljava/lang/String;")).emit(Opcode::Pop)
+			// these can't throw, and are left out
+			.iload(2).iconst(2).emit(Opcode::IDiv).emit(Opcode::Pop)
+			.aload(0).emit(Opcode::Pop)
+			.emit(Opcode::Return);
+		let getter = class.methods.iter_mut().find(|method| method.name.as_bytes() == b"getFoo").unwrap();
+		getter.descriptor = descriptor;
+		getter.code = Some(code.build(&mut PoolBuilder::new()).unwrap());
+		assert_contains(&decompile_lines(&class), &[
+			"public static void getFoo(Object arg0, int[] arg1, int arg2) {",
+			"arg1.length;",
+			"(String) arg0;",
+			"arg2 / arg2;",
+			"arg2 % arg2;",
+			"\"\" + arg0;",
+			"}",
+		]);
+	}
+
+	#[test]
+	fn names_from_local_variable_table() {
+		let lines = decompile_lines(&decompile_class());
+		assert_contains(&lines, &["public static int count(int[][] rows, int wanted) {", "int count = 0;"]);
+		assert_contains(&lines, &["int[] row = var3[var5];"]);
+		assert_contains(&lines, &["int cell = var7[var9];"]);
+	}
+
+	#[test]
+	fn generics_from_signature() {
+		let lines = decompile_lines(&decompile_class());
+		assert_contains(&lines, &["public class Decompile<T extends Comparable<T>> {", "private final List<T> items;"]);
+		// the cast to the erasure of `T` is left out
+		assert_contains(&lines, &[
+			"public T max() {",
+			"T best = null;",
+			"Iterator var2 = this.items.iterator();",
+			"while (var2.hasNext()) {",
+			"T item = var2.next();",
+		]);
+	}
+
+	#[test]
+	fn try_catch_finally() {
+		// the `finally` block is written where javac copied it to, with a handler of any exception for the rest
+		assert_contains(&decompile_lines(&decompile_class()), &[
+			"public static int parse(String text) {",
+			"try {",
+			"try {",
+			"int var1 = Integer.parseInt(text);",
+			"System.out.println(text);",
+			"return var1;",
+			"} catch (NumberFormatException e) {",
+			"int var2 = -1;",
+			"System.out.println(text);",
+			"return var2;",
+			"}",
+			"} catch (Throwable var3) {",
+			"System.out.println(text);",
+			"throw var3;",
+			"}",
+			"}",
+		]);
+	}
+
+	#[test]
+	fn switch() {
+		// the variable assigned in every case is declared once
+		assert_contains(&decompile_lines(&decompile_class()), &[
+			"public static String name(int day) {",
+			"String name;",
+			"switch (day) {",
+			"case 0:",
+			"name = \"sunday\";",
+			"break;",
+			"case 6:",
+			"name = \"saturday\";",
+			"break;",
+			"default:",
+			"name = \"weekday\";",
+			"}",
+			"return name;",
+		]);
+	}
+
+	#[test]
+	fn labeled_break_and_continue() {
+		// `continue` of a `for` loop goes on with its update
+		assert_contains(&decompile_lines(&decompile_class()), &[
+			"label11: for (int var5 = 0; var5 < var4; var5++) {",
+			"int[] row = var3[var5];",
+			"int[] var7 = row;",
+			"int var8 = var7.length;",
+			"for (int var9 = 0; var9 < var8; var9++) {",
+			"int cell = var7[var9];",
+			"if (cell < 0) {",
+			"continue label11;",
+			"}",
+			"if (cell == wanted) {",
+			"break label11;",
+			"}",
+			"count++;",
+			"}",
+			"count *= 2;",
+			"}",
+			"return count;",
+		]);
+	}
+}
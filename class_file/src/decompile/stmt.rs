@@ -0,0 +1,592 @@
+//! Statements, the passes tidying them up once the control flow is structured, and how they are written.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write as _;
+use crate::decompile::expr::{Expr, Printer, Type, CAUGHT};
+use crate::name::ClassName;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Stmt {
+	Expr(Expr),
+	/// The declaration of a variable, with its first value.
+	Declare(usize, Option<Expr>),
+	Return(Option<Expr>),
+	Throw(Expr),
+	If { condition: Expr, then: Vec<Stmt>, otherwise: Vec<Stmt> },
+	/// A loop, with the offset of its first instruction as label if some `break` or `continue` needs one.
+	Loop { kind: LoopKind, body: Vec<Stmt>, label: Option<usize> },
+	Switch { value: Expr, cases: Vec<Case>, label: Option<usize> },
+	Try { body: Vec<Stmt>, catches: Vec<Catch> },
+	Synchronized { lock: Expr, body: Vec<Stmt> },
+	Break(Option<usize>),
+	Continue(Option<usize>),
+	/// A jump that doesn't fit into the structure, to the [Stmt::Label] with the same offset.
+	Goto(usize),
+	Label(usize),
+	MonitorEnter(Expr),
+	MonitorExit(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LoopKind {
+	Infinite,
+	While(Expr),
+	DoWhile(Expr),
+	For { init: Vec<Stmt>, condition: Expr, update: Vec<Expr> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Case {
+	pub(crate) values: Vec<i32>,
+	pub(crate) default: bool,
+	pub(crate) body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Catch {
+	/// The classes caught, none for a handler of any exception, like the ones of `finally` and `synchronized`.
+	pub(crate) types: Vec<ClassName>,
+	pub(crate) variable: Option<usize>,
+	pub(crate) body: Vec<Stmt>,
+}
+
+impl Stmt {
+	/// Calls `f` with the expressions of this statement, but not of the statements nested in it.
+	pub(crate) fn for_each_expr(&self, f: &mut dyn FnMut(&Expr)) {
+		match self {
+			Stmt::Expr(expr) | Stmt::Throw(expr) | Stmt::MonitorEnter(expr) | Stmt::MonitorExit(expr) | Stmt::Declare(_, Some(expr)) |
+			Stmt::Return(Some(expr)) | Stmt::If { condition: expr, .. } | Stmt::Switch { value: expr, .. } | Stmt::Synchronized { lock: expr, .. } => f(expr),
+			Stmt::Loop { kind: LoopKind::While(condition) | LoopKind::DoWhile(condition), .. } => f(condition),
+			Stmt::Loop { kind: LoopKind::For { condition, update, .. }, .. } => {
+				f(condition);
+				update.iter().for_each(f);
+			},
+			_ => {},
+		}
+	}
+
+	pub(crate) fn for_each_expr_mut(&mut self, f: &mut dyn FnMut(&mut Expr)) {
+		match self {
+			Stmt::Expr(expr) | Stmt::Throw(expr) | Stmt::MonitorEnter(expr) | Stmt::MonitorExit(expr) | Stmt::Declare(_, Some(expr)) |
+			Stmt::Return(Some(expr)) | Stmt::If { condition: expr, .. } | Stmt::Switch { value: expr, .. } | Stmt::Synchronized { lock: expr, .. } => f(expr),
+			Stmt::Loop { kind: LoopKind::While(condition) | LoopKind::DoWhile(condition), .. } => f(condition),
+			Stmt::Loop { kind: LoopKind::For { condition, update, .. }, .. } => {
+				f(condition);
+				update.iter_mut().for_each(f);
+			},
+			_ => {},
+		}
+	}
+
+	/// Calls `f` with the blocks of statements nested in this one.
+	pub(crate) fn for_each_block_mut(&mut self, f: &mut dyn FnMut(&mut Vec<Stmt>)) {
+		match self {
+			Stmt::If { then, otherwise, .. } => {
+				f(then);
+				f(otherwise);
+			},
+			Stmt::Loop { kind, body, .. } => {
+				if let LoopKind::For { init, .. } = kind {
+					f(init);
+				}
+				f(body);
+			},
+			Stmt::Switch { cases, .. } => cases.iter_mut().for_each(|case| f(&mut case.body)),
+			Stmt::Try { body, catches } => {
+				f(body);
+				catches.iter_mut().for_each(|catch| f(&mut catch.body));
+			},
+			Stmt::Synchronized { body, .. } => f(body),
+			_ => {},
+		}
+	}
+
+	fn for_each_block(&self, f: &mut dyn FnMut(&[Stmt])) {
+		match self {
+			Stmt::If { then, otherwise, .. } => {
+				f(then);
+				f(otherwise);
+			},
+			Stmt::Loop { kind, body, .. } => {
+				if let LoopKind::For { init, .. } = kind {
+					f(init);
+				}
+				f(body);
+			},
+			Stmt::Switch { cases, .. } => cases.iter().for_each(|case| f(&case.body)),
+			Stmt::Try { body, catches } => {
+				f(body);
+				catches.iter().for_each(|catch| f(&catch.body));
+			},
+			Stmt::Synchronized { body, .. } => f(body),
+			_ => {},
+		}
+	}
+
+	/// Returns whether `f` holds for an expression of this statement or of the statements nested in it.
+	fn any_expr(&self, f: &dyn Fn(&Expr) -> bool) -> bool {
+		let mut found = false;
+		self.for_each_expr(&mut |expr| found = found || expr.any(f));
+		if let Stmt::Declare(variable, _) = self {
+			found = found || f(&Expr::Local(*variable));
+		}
+		if let Stmt::Try { catches, .. } = self {
+			found = found || catches.iter().any(|catch| catch.variable.is_some_and(|variable| f(&Expr::Local(variable))));
+		}
+		self.for_each_block(&mut |block| found = found || block.iter().any(|statement| statement.any_expr(f)));
+		found
+	}
+
+	/// Whether control never reaches the statement after this one.
+	fn ends_abruptly(&self) -> bool {
+		match self {
+			Stmt::Return(_) | Stmt::Throw(_) | Stmt::Break(_) | Stmt::Continue(_) | Stmt::Goto(_) => true,
+			Stmt::If { then, otherwise, .. } => ends_abruptly(then) && ends_abruptly(otherwise),
+			_ => false,
+		}
+	}
+}
+
+pub(crate) fn ends_abruptly(statements: &[Stmt]) -> bool {
+	statements.last().is_some_and(Stmt::ends_abruptly)
+}
+
+/// Removes the labels no `goto` jumps to.
+pub(crate) fn remove_unused_labels(statements: &mut Vec<Stmt>) {
+	fn targets(statements: &[Stmt], found: &mut BTreeSet<usize>) {
+		for statement in statements {
+			if let Stmt::Goto(offset) = statement {
+				found.insert(*offset);
+			}
+			statement.for_each_block(&mut |block| targets(block, found));
+		}
+	}
+	fn remove(statements: &mut Vec<Stmt>, targets: &BTreeSet<usize>) {
+		statements.retain(|statement| !matches!(statement, Stmt::Label(offset) if !targets.contains(offset)));
+		for statement in statements {
+			statement.for_each_block_mut(&mut |block| remove(block, targets));
+		}
+	}
+	let mut found = BTreeSet::new();
+	targets(statements, &mut found);
+	remove(statements, &found);
+}
+
+/// Whether a `continue` in the statements continues the loop with the label, or the innermost one if `label` is `None`.
+fn continues(statements: &[Stmt], label: Option<usize>) -> bool {
+	statements.iter().any(|statement| match statement {
+		Stmt::Continue(None) => label.is_none(),
+		Stmt::Continue(Some(target)) => label == Some(*target),
+		// an unlabeled continue in a nested loop continues that loop
+		Stmt::Loop { body, .. } => label.is_some() && continues(body, label),
+		_ => {
+			let mut found = false;
+			statement.for_each_block(&mut |block| found = found || continues(block, label));
+			found
+		},
+	})
+}
+
+fn continues_loop(body: &[Stmt], label: Option<usize>) -> bool {
+	continues(body, None) || label.is_some() && continues(body, label)
+}
+
+/// Whether each `continue` of the loop with the label, or the innermost one if `label` is `None`, follows `update`, which is removed before them if
+/// `remove` is set.
+fn continues_after(statements: &mut Vec<Stmt>, label: Option<usize>, update: &Stmt, remove: bool) -> bool {
+	let mut all = true;
+	let mut index = 0;
+	while index < statements.len() {
+		match &mut statements[index] {
+			Stmt::Continue(target) if *target == label => {
+				if index > 0 && statements[index - 1] == *update {
+					if remove {
+						statements.remove(index - 1);
+						index -= 1;
+					}
+				} else {
+					all = false;
+				}
+			},
+			// an unlabeled continue in a nested loop continues that loop
+			Stmt::Loop { body, .. } => all &= label.is_none() || continues_after(body, label, update, remove),
+			statement => statement.for_each_block_mut(&mut |block| all &= continues_after(block, label, update, remove)),
+		}
+		index += 1;
+	}
+	all
+}
+
+/// Tidies up the statements: loops get their conditions, `if`s without a `then` are turned around, and counting `while` loops become `for` loops.
+pub(crate) fn simplify(statements: &mut Vec<Stmt>) {
+	for statement in statements.iter_mut() {
+		statement.for_each_block_mut(&mut simplify);
+		match statement {
+			Stmt::Loop { kind: kind @ LoopKind::Infinite, body, label } => {
+				if let Some(Stmt::Continue(None)) = body.last() {
+					body.pop();
+				}
+				if let Some(Stmt::If { then, otherwise, .. }) = body.first() {
+					if then[..] == [Stmt::Break(None)] && otherwise.is_empty() {
+						let Stmt::If { condition, .. } = body.remove(0) else { unreachable!() };
+						*kind = LoopKind::While(condition.negate());
+						continue;
+					}
+				}
+				if let Some(Stmt::If { then, otherwise, .. }) = body.last() {
+					if then[..] == [Stmt::Break(None)] && otherwise.is_empty() && !continues_loop(&body[..body.len() - 1], *label) {
+						let Some(Stmt::If { condition, .. }) = body.pop() else { unreachable!() };
+						*kind = LoopKind::DoWhile(condition.negate());
+					}
+				}
+			},
+			Stmt::If { condition, then, otherwise } if then.is_empty() && !otherwise.is_empty() => {
+				*condition = core::mem::replace(condition, Expr::Null).negate();
+				core::mem::swap(then, otherwise);
+			},
+			_ => {},
+		}
+	}
+	// an `if` with an empty `then` and `else` is left from a condition that jumps to where it would have fallen through
+	statements.retain(|statement| !matches!(statement, Stmt::If { condition, then, otherwise } if then.is_empty() && otherwise.is_empty() && condition.is_pure()));
+	for statement in statements.iter_mut() {
+		if let Stmt::If { condition, then, otherwise } = statement {
+			if then.is_empty() && otherwise.is_empty() {
+				*statement = Stmt::Expr(core::mem::replace(condition, Expr::Null));
+			}
+		}
+	}
+	synchronize(statements);
+	// the `else` of an `if` whose `then` doesn't complete normally follows it
+	let mut index = 0;
+	while index < statements.len() {
+		if let Stmt::If { then, otherwise, .. } = &mut statements[index] {
+			if ends_abruptly(then) && !otherwise.is_empty() && !matches!(otherwise[..], [Stmt::If { .. }]) {
+				let otherwise = core::mem::take(otherwise);
+				statements.splice(index + 1..index + 1, otherwise);
+			}
+		}
+		index += 1;
+	}
+	make_for_loops(statements);
+}
+
+/// Turns entering a monitor, followed by a `try` leaving it at its end and in a handler of any exception, into a `synchronized` statement.
+fn synchronize(statements: &mut Vec<Stmt>) {
+	fn remove_exits(statements: &mut Vec<Stmt>, monitor: &Expr) {
+		statements.retain(|statement| !matches!(statement, Stmt::MonitorExit(value) if value == monitor));
+		for statement in statements {
+			statement.for_each_block_mut(&mut |block| remove_exits(block, monitor));
+		}
+	}
+
+	let mut index = 0;
+	while index + 1 < statements.len() {
+		if let [Stmt::MonitorEnter(lock), Stmt::Try { body, catches }] = &statements[index..index + 2] {
+			// javac keeps the monitor in a variable, to leave it after the code in between
+			let (lock, monitor) = match lock {
+				Expr::Assign(target, value) => ((**value).clone(), (**target).clone()),
+				lock => (lock.clone(), lock.clone()),
+			};
+			let leaves = match &catches[..] {
+				[Catch { types, variable, body: handler }] if types.is_empty() => match &handler[..] {
+					[Stmt::MonitorExit(value), Stmt::Throw(thrown)] => {
+						*value == monitor && (variable.map(Expr::Local).as_ref() == Some(thrown) || matches!(thrown, Expr::Caught(_)))
+					},
+					_ => false,
+				},
+				_ => false,
+			};
+			if leaves {
+				let mut body = body.clone();
+				remove_exits(&mut body, &monitor);
+				statements.splice(index..index + 2, [Stmt::Synchronized { lock, body }]);
+			}
+		}
+		index += 1;
+	}
+}
+
+fn make_for_loops(statements: &mut Vec<Stmt>) {
+	let mut index = 1;
+	while index < statements.len() {
+		let (before, rest) = statements.split_at_mut(index);
+		if let (Stmt::Expr(Expr::Assign(target, _)), Stmt::Loop { kind: LoopKind::While(condition), body, label }) = (&before[index - 1], &mut rest[0]) {
+			let updates = match body.last() {
+				Some(Stmt::Expr(Expr::Increment { target: updated, .. } | Expr::Assign(updated, _))) => updated == target,
+				_ => false,
+			};
+			if let Expr::Local(variable) = **target {
+				// a `continue` goes on with the update of a `for` loop, so the update before each `continue` is left out
+				let labels = [None, *label];
+				let update = body.last().filter(|_| updates && condition.reads(variable)).cloned();
+				if let Some(update) = update.filter(|update| labels.iter().all(|&label| continues_after(body, label, update, false))) {
+					for label in labels {
+						continues_after(body, label, &update, true);
+					}
+					let Some(Stmt::Expr(update)) = body.pop() else { unreachable!() };
+					let condition = core::mem::replace(condition, Expr::Null);
+					let init = statements.remove(index - 1);
+					let Stmt::Loop { kind, .. } = &mut statements[index - 1] else { unreachable!() };
+					*kind = LoopKind::For { init: vec![init], condition, update: vec![update] };
+					continue;
+				}
+			}
+		}
+		index += 1;
+	}
+}
+
+/// Declares the variables in the innermost block all their uses are in, with their first value if they get it there. The variables of `catch`
+/// clauses and those `declared` already are left alone.
+pub(crate) fn declare(statements: &mut Vec<Stmt>, declared: &BTreeSet<usize>) {
+	// the blocks are numbered in the order they're visited, and each use is recorded with the path of blocks leading to it
+	fn uses(statements: &[Stmt], path: &mut Vec<usize>, next_block: &mut usize, found: &mut BTreeMap<usize, Vec<usize>>) {
+		let block = *next_block;
+		*next_block += 1;
+		path.push(block);
+		for statement in statements {
+			let record = |path: &Vec<usize>, found: &mut BTreeMap<usize, Vec<usize>>, expr: &Expr| {
+				let mut variables = Vec::new();
+				expr.visit(&mut |expr| {
+					if let Expr::Local(variable) = expr {
+						variables.push(*variable);
+					}
+				});
+				for variable in variables {
+					let common = found.entry(variable).or_insert_with(|| path.clone());
+					let length = common.iter().zip(path.iter()).take_while(|(a, b)| a == b).count();
+					common.truncate(length);
+				}
+			};
+			match statement {
+				// the variables of the condition and the update of a `for` loop are in the scope of its initialization
+				Stmt::Loop { kind: LoopKind::For { init, condition, update }, body, .. } => {
+					let init_block = *next_block;
+					uses(init, path, next_block, found);
+					path.push(init_block);
+					for expr in core::iter::once(condition).chain(update) {
+						record(path, found, expr);
+					}
+					uses(body, path, next_block, found);
+					path.pop();
+				},
+				_ => {
+					statement.for_each_expr(&mut |expr| record(path, found, expr));
+					statement.for_each_block(&mut |block| uses(block, path, next_block, found));
+				},
+			}
+		}
+		path.pop();
+	}
+
+	fn insert(statements: &mut Vec<Stmt>, next_block: &mut usize, scopes: &BTreeMap<usize, Vec<usize>>) {
+		let block = *next_block;
+		*next_block += 1;
+		for statement in statements.iter_mut() {
+			statement.for_each_block_mut(&mut |block| insert(block, next_block, scopes));
+		}
+		let Some(variables) = scopes.get(&block) else {
+			return;
+		};
+		for &variable in variables {
+			let Some(first) = statements.iter().position(|statement| statement.any_expr(&|expr| *expr == Expr::Local(variable))) else {
+				continue;
+			};
+			match &mut statements[first] {
+				Stmt::Expr(Expr::Assign(target, value)) if **target == Expr::Local(variable) => {
+					let value = core::mem::replace(&mut **value, Expr::Null);
+					statements[first] = Stmt::Declare(variable, Some(value));
+				},
+				_ => statements.insert(first, Stmt::Declare(variable, None)),
+			}
+		}
+	}
+
+	// the variables of `catch` clauses are declared by them
+	fn caught(statements: &[Stmt], found: &mut BTreeSet<usize>) {
+		for statement in statements {
+			if let Stmt::Try { catches, .. } = statement {
+				found.extend(catches.iter().filter_map(|catch| catch.variable));
+			}
+			statement.for_each_block(&mut |block| caught(block, found));
+		}
+	}
+
+	let mut found = BTreeMap::new();
+	uses(statements, &mut Vec::new(), &mut 0, &mut found);
+	let mut declared = declared.clone();
+	caught(statements, &mut declared);
+	let mut scopes: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+	for (variable, path) in found {
+		if !declared.contains(&variable) {
+			if let Some(&block) = path.last() {
+				scopes.entry(block).or_default().push(variable);
+			}
+		}
+	}
+	insert(statements, &mut 0, &scopes);
+}
+
+/// Writes statements, one per line, indented with `indent` tabs.
+pub(crate) fn write_block(out: &mut String, printer: &Printer, statements: &[Stmt], indent: usize) {
+	for statement in statements {
+		statement.write(out, printer, indent);
+	}
+}
+
+fn write_indent(out: &mut String, indent: usize) {
+	for _ in 0..indent {
+		out.push('\t');
+	}
+}
+
+fn label(label: &Option<usize>) -> String {
+	label.map(|offset| format!(" label{offset}")).unwrap_or_default()
+}
+
+fn write_simple(out: &mut String, printer: &Printer, statement: &Stmt) {
+	match statement {
+		Stmt::Expr(Expr::Increment { target, by: by @ (1 | -1), .. }) => {
+			out.push_str(&target.to_source(printer));
+			out.push_str(if *by == 1 { "++" } else { "--" });
+		},
+		Stmt::Expr(expr) => out.push_str(&expr.to_source(printer)),
+		Stmt::Declare(variable, value) => {
+			let variable_info = &printer.variables[*variable];
+			let _ = write!(out, "{} {}", variable_info.write_type(printer.names), variable_info.name);
+			if let Some(value) = value {
+				out.push_str(" = ");
+				out.push_str(&value.to_source(printer));
+			}
+		},
+		_ => {},
+	}
+}
+
+impl Stmt {
+	fn write(&self, out: &mut String, printer: &Printer, indent: usize) {
+		write_indent(out, indent);
+		match self {
+			Stmt::Expr(_) | Stmt::Declare(..) => {
+				write_simple(out, printer, self);
+				out.push_str(";\n");
+			},
+			Stmt::Return(None) => out.push_str("return;\n"),
+			Stmt::Return(Some(value)) => { let _ = writeln!(out, "return {};", value.to_source(printer)); },
+			Stmt::Throw(value) => { let _ = writeln!(out, "throw {};", value.to_source(printer)); },
+			Stmt::If { .. } => {
+				let mut statement = self;
+				loop {
+					let Stmt::If { condition, then, otherwise } = statement else { unreachable!() };
+					let _ = writeln!(out, "if ({}) {{", condition.to_source(printer));
+					write_block(out, printer, then, indent + 1);
+					write_indent(out, indent);
+					match &otherwise[..] {
+						[] => {
+							out.push_str("}\n");
+							break;
+						},
+						[nested @ Stmt::If { .. }] => {
+							out.push_str("} else ");
+							statement = nested;
+						},
+						_ => {
+							out.push_str("} else {\n");
+							write_block(out, printer, otherwise, indent + 1);
+							write_indent(out, indent);
+							out.push_str("}\n");
+							break;
+						},
+					}
+				}
+			},
+			Stmt::Loop { kind, body, label } => {
+				if let Some(offset) = label {
+					let _ = write!(out, "label{offset}: ");
+				}
+				match kind {
+					LoopKind::Infinite => out.push_str("while (true) {\n"),
+					LoopKind::While(condition) => { let _ = writeln!(out, "while ({}) {{", condition.to_source(printer)); },
+					LoopKind::DoWhile(_) => out.push_str("do {\n"),
+					LoopKind::For { init, condition, update } => {
+						let mut parts = Vec::new();
+						for part in init {
+							let mut text = String::new();
+							write_simple(&mut text, printer, part);
+							parts.push(text);
+						}
+						let update: Vec<String> = update.iter()
+							.map(|update| {
+								let mut text = String::new();
+								write_simple(&mut text, printer, &Stmt::Expr(update.clone()));
+								text
+							})
+							.collect();
+						let _ = writeln!(out, "for ({}; {}; {}) {{", parts.join(", "), condition.to_source(printer), update.join(", "));
+					},
+				}
+				write_block(out, printer, body, indent + 1);
+				write_indent(out, indent);
+				match kind {
+					LoopKind::DoWhile(condition) => { let _ = writeln!(out, "}} while ({});", condition.to_source(printer)); },
+					_ => out.push_str("}\n"),
+				}
+			},
+			Stmt::Switch { value, cases, label } => {
+				if let Some(offset) = label {
+					let _ = write!(out, "label{offset}: ");
+				}
+				let _ = writeln!(out, "switch ({}) {{", value.to_source(printer));
+				let ty = value.ty(printer.variables);
+				for case in cases {
+					for value in &case.values {
+						write_indent(out, indent + 1);
+						let value = if ty == Type::Char { Expr::Int(*value).coerce(ty) } else { Expr::Int(*value) };
+						let _ = writeln!(out, "case {}:", value.to_source(printer));
+					}
+					if case.default {
+						write_indent(out, indent + 1);
+						out.push_str("default:\n");
+					}
+					write_block(out, printer, &case.body, indent + 2);
+				}
+				write_indent(out, indent);
+				out.push_str("}\n");
+			},
+			Stmt::Try { body, catches } => {
+				out.push_str("try {\n");
+				write_block(out, printer, body, indent + 1);
+				for catch in catches {
+					write_indent(out, indent);
+					let types: Vec<String> = if catch.types.is_empty() {
+						vec![printer.names.class(&ClassName::from(b"java/lang/Throwable"))]
+					} else {
+						catch.types.iter().map(|class| printer.names.class(class)).collect()
+					};
+					let name = catch.variable.map_or(CAUGHT, |variable| &printer.variables[variable].name);
+					let _ = writeln!(out, "}} catch ({} {name}) {{", types.join(" | "));
+					write_block(out, printer, &catch.body, indent + 1);
+				}
+				write_indent(out, indent);
+				out.push_str("}\n");
+			},
+			Stmt::Synchronized { lock, body } => {
+				let _ = writeln!(out, "synchronized ({}) {{", lock.to_source(printer));
+				write_block(out, printer, body, indent + 1);
+				write_indent(out, indent);
+				out.push_str("}\n");
+			},
+			Stmt::Break(target) => { let _ = writeln!(out, "break{};", label(target)); },
+			Stmt::Continue(target) => { let _ = writeln!(out, "continue{};", label(target)); },
+			Stmt::Goto(offset) => { let _ = writeln!(out, "goto L{offset};"); },
+			Stmt::Label(offset) => { let _ = writeln!(out, "L{offset}:"); },
+			Stmt::MonitorEnter(value) => { let _ = writeln!(out, "monitorenter({});", value.to_source(printer)); },
+			Stmt::MonitorExit(value) => { let _ = writeln!(out, "monitorexit({});", value.to_source(printer)); },
+		}
+	}
+}
@@ -0,0 +1,477 @@
+//! Structured statements from the [Graph] of a method: loops from its natural loops, `try` statements from its exception table, and `if`s and
+//! `switch`es from the branches in between, with `goto`s where the code doesn't fit these.
+//!
+//! The nodes are walked in the order of the code. A construct covers a range of nodes, which is walked with the node control continues at after the
+//! range, so that jumps there are left out.
+
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::cfg::BlockId;
+use crate::decompile::expr::Expr;
+use crate::decompile::graph::{Graph, Terminator};
+use crate::decompile::stmt::{Case, Catch, LoopKind, Stmt};
+use crate::name::ClassName;
+
+pub(crate) fn structure(graph: &Graph) -> Vec<Stmt> {
+	let mut structurer = Structurer { graph, predecessors: graph.predecessors(), loops: Vec::new(), tries: Vec::new(), open: Vec::new(), scopes: Vec::new() };
+	structurer.tries = structurer.tries();
+	structurer.loops = graph.loops.iter().map(|&(first, header, last)| structurer.walked(first, header, last)).collect();
+	let end = graph.nodes.len();
+	structurer.block(0, end, end)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Construct {
+	Loop(usize),
+	Try(usize),
+}
+
+/// The first nodes of exception handlers, with the classes they catch, none for any.
+type Handlers = Vec<(usize, Vec<ClassName>)>;
+
+/// Exception handlers covering the same nodes.
+struct Try {
+	start: usize,
+	/// The handlers in the order of the code, with the classes they catch, none for any.
+	handlers: Handlers,
+}
+
+/// A loop, walked from `start` up to `end`.
+#[derive(Debug, Clone, Copy)]
+struct Loop {
+	/// The header, or the first node of the loop if it's tested at its end.
+	start: usize,
+	header: usize,
+	/// The node after the last one.
+	end: usize,
+	/// The node updating the loop before it's continued, like the `i++` of a `for` loop.
+	update: Option<usize>,
+}
+
+/// A statement `break` can leave.
+struct Scope {
+	/// The header, for loops.
+	header: Option<usize>,
+	/// The node updating a loop before it's continued.
+	update: Option<usize>,
+	/// Where control continues after the statement.
+	exit: usize,
+	/// The offset the statement is labeled with, if a `break` or `continue` needs it.
+	label: usize,
+	labeled: bool,
+}
+
+/// How a jump is written.
+enum Jump {
+	/// Not at all, as the node jumped to is the next one.
+	Next,
+	/// Not at all, as the node jumped to is where control continues after the range walked.
+	Silent,
+	/// With a `break`, a `continue` or a `goto`.
+	Stmts(Vec<Stmt>),
+	/// To a later node of the range walked, the first being where it continues in the range, the second the node jumped to.
+	Forward(usize, usize),
+}
+
+struct Structurer<'a> {
+	graph: &'a Graph,
+	predecessors: Vec<Vec<usize>>,
+	loops: Vec<Loop>,
+	tries: Vec<Try>,
+	/// The constructs currently walked.
+	open: Vec<Construct>,
+	scopes: Vec<Scope>,
+}
+
+/// Where control continues after a construct ending at `end`, inside a range ending at `to` and continuing at `follow`.
+fn follow_of(end: usize, to: usize, follow: usize) -> usize {
+	if end >= to { follow } else { end }
+}
+
+impl Structurer<'_> {
+	/// The first node at or after `id` that wasn't removed.
+	fn at(&self, id: usize) -> usize {
+		(id..self.graph.nodes.len()).find(|&id| !self.graph.nodes[id].removed).unwrap_or(self.graph.nodes.len())
+	}
+
+	fn offset(&self, id: usize) -> usize {
+		self.graph.nodes[id].offset
+	}
+
+	fn tries(&self) -> Vec<Try> {
+		// the nodes covered for each handler, with the classes caught, none meaning any
+		let mut spans: BTreeMap<usize, (usize, usize, Option<Vec<ClassName>>)> = BTreeMap::new();
+		for entry in &self.graph.handlers {
+			// the handler of a `synchronized` statement also covers its own start, to release the monitor once more if that fails
+			if entry.start <= entry.handler && entry.handler < entry.end {
+				continue;
+			}
+			let span = spans.entry(entry.handler).or_insert((entry.start, entry.end, Some(Vec::new())));
+			span.0 = span.0.min(entry.start);
+			span.1 = span.1.max(entry.end);
+			match (&mut span.2, entry.catch_type) {
+				(Some(types), Some(class)) if !types.contains(&class) => types.push(class),
+				(types, None) => *types = None,
+				_ => {},
+			}
+		}
+		// handlers covering the same nodes share a `try`, which is only written if they all come after the nodes covered
+		let mut tries: BTreeMap<(usize, usize), Handlers> = BTreeMap::new();
+		for (handler, (start, end, types)) in spans {
+			if handler >= end {
+				tries.entry((self.at(start), end)).or_default().push((handler, types.unwrap_or_default()));
+			}
+		}
+		tries.into_iter().map(|((start, _), handlers)| Try { start, handlers }).collect()
+	}
+
+	fn try_end(&self, t: &Try) -> usize {
+		self.handler_end(t.handlers.last().map_or(t.start, |handler| handler.0), self.graph.nodes.len())
+	}
+
+	/// How a loop is walked. A loop whose header is its last node, with a condition going on at its first node, is tested at its end, like the loops
+	/// ecj and kotlinc compile: it's rotated, walking it from its first node, with the test written at the start of its body. The nodes of a
+	/// condition joined into the header may come after it, and so may the nodes the test leads to that end the method.
+	fn walked(&self, first: usize, header: usize, last: usize) -> Loop {
+		if first < header {
+			let end = self.loop_end(first, last);
+			let after = self.at(header + 1);
+			if let Terminator::If { then, otherwise, .. } = self.graph.nodes[header].terminator {
+				let exit = if then == first { otherwise } else { then };
+				if (then == first) != (otherwise == first) && !(first..=header).contains(&exit) && (end == after || exit == after) {
+					return Loop { start: first, header, end, update: self.update(first, header, end) };
+				}
+			}
+		}
+		let end = self.loop_end(header, last);
+		Loop { start: header, header, end, update: self.update(header, header, end) }
+	}
+
+	/// The node updating a loop before it's continued: the last one jumping to the header before it, or in the loop if it's the first, with only an
+	/// assignment.
+	fn update(&self, start: usize, header: usize, end: usize) -> Option<usize> {
+		let range = if start == header { header + 1..end } else { start..header };
+		let id = range.rev().find(|&id| {
+			let node = &self.graph.nodes[id];
+			!node.removed && matches!(node.terminator, Terminator::Jump(target) if target == header)
+		})?;
+		matches!(self.graph.nodes[id].statements[..], [Stmt::Expr(Expr::Assign(..) | Expr::Increment { .. })]).then_some(id)
+	}
+
+	/// The node after a loop walked from `start`: usually the one after its last node, but a loop also contains the `try` statements it's continued
+	/// from a handler of, like when retrying, and the nodes only it leads to that end with a `return` or `throw` before where it exits to.
+	fn loop_end(&self, start: usize, last: usize) -> usize {
+		let mut end = self.at(last + 1);
+		for t in &self.tries {
+			if start <= t.start && t.start <= last && t.handlers.first().is_some_and(|&(handler, _)| handler <= last) {
+				end = end.max(self.try_end(t));
+			}
+		}
+		let exit = (start..end)
+			.filter(|&id| !self.graph.nodes[id].removed)
+			.flat_map(|id| self.graph.nodes[id].terminator.successors())
+			.max()
+			.unwrap_or(end);
+		while end < exit {
+			let node = &self.graph.nodes[end];
+			let predecessors = &self.predecessors[end];
+			let inside = !predecessors.is_empty() && predecessors.iter().all(|&predecessor| start <= predecessor && predecessor < end);
+			if node.handler || !inside || !node.terminator.successors().is_empty() {
+				break;
+			}
+			end = self.at(end + 1);
+		}
+		end
+	}
+
+	/// The end of the nodes of a handler that comes last: the nodes it dominates following it.
+	fn handler_end(&self, handler: usize, to: usize) -> usize {
+		let mut end = handler + 1;
+		while end < to && (self.graph.nodes[end].removed || self.graph.dominators.dominates(BlockId(handler), BlockId(end))) {
+			end += 1;
+		}
+		end
+	}
+
+	/// The outermost construct starting at `id` that isn't walked yet and ends before `to`, with the node after it.
+	fn construct_at(&self, id: usize, to: usize) -> Option<(Construct, usize)> {
+		let loops = self.loops.iter()
+			.enumerate()
+			.filter(|(_, l)| l.start == id)
+			.map(|(index, l)| (Construct::Loop(index), l.end));
+		let tries = self.tries.iter()
+			.enumerate()
+			.filter(|(_, t)| t.start == id)
+			.map(|(index, t)| (Construct::Try(index), self.try_end(t)));
+		loops.chain(tries)
+			.filter(|(construct, end)| *end <= to && !self.open.contains(construct))
+			// a loop and a `try` ending together: the loop is outside
+			.max_by_key(|(construct, end)| (*end, matches!(construct, Construct::Loop(_))))
+	}
+
+	/// Walks the nodes from `from` up to `to`, after which control continues at `follow`.
+	fn block(&mut self, from: usize, to: usize, follow: usize) -> Vec<Stmt> {
+		let mut statements = Vec::new();
+		let mut id = self.at(from);
+		while id < to {
+			let next = match self.construct_at(id, to) {
+				Some((construct @ Construct::Loop(index), end)) => {
+					self.open.push(construct);
+					let l = self.loops[index];
+					let Loop { start, header, update, .. } = l;
+					self.scopes.push(Scope { header: Some(header), update, exit: follow_of(end, to, follow), label: self.offset(start), labeled: false });
+					let body = if start == header {
+						self.block(header, end, header)
+					} else {
+						let mut body = self.test(l);
+						body.extend(self.block(start, header, header));
+						body
+					};
+					let scope = self.scopes.pop().expect("the scope was pushed before");
+					self.open.pop();
+					statements.push(Stmt::Loop { kind: LoopKind::Infinite, body, label: scope.labeled.then_some(scope.label) });
+					end
+				},
+				Some((construct @ Construct::Try(index), end)) => {
+					self.open.push(construct);
+					let handlers = self.tries[index].handlers.clone();
+					let continuation = follow_of(end, to, follow);
+					let body = self.block(id, handlers[0].0, continuation);
+					let mut catches = Vec::new();
+					for (position, (handler, types)) in handlers.iter().enumerate() {
+						let handler_end = handlers.get(position + 1).map_or(end, |next| next.0);
+						let mut body = self.block(*handler, handler_end, continuation);
+						let variable = catch_variable(&mut body);
+						catches.push(Catch { types: types.clone(), variable, body });
+					}
+					self.open.pop();
+					statements.push(Stmt::Try { body, catches });
+					end
+				},
+				None => self.node(id, to, follow, &mut statements),
+			};
+			id = self.at(next);
+		}
+		statements
+	}
+
+	/// Writes a node, and returns the node to continue with.
+	fn node(&mut self, id: usize, to: usize, follow: usize, out: &mut Vec<Stmt>) -> usize {
+		let node = &self.graph.nodes[id];
+		out.push(Stmt::Label(node.offset));
+		out.extend(node.statements.iter().cloned());
+		match &node.terminator {
+			Terminator::Return(value) => out.push(Stmt::Return(value.clone())),
+			Terminator::Throw(value) => out.push(Stmt::Throw(value.clone())),
+			Terminator::Jump(target) => {
+				let jump = self.jump(id, *target, to, follow);
+				out.extend(self.simple(jump));
+			},
+			Terminator::If { condition, then, otherwise } => return self.conditional(id, condition.clone(), *then, *otherwise, to, follow, out),
+			Terminator::Switch { value, cases, default } => return self.switch(id, value.clone(), cases, *default, to, follow, out),
+		}
+		id + 1
+	}
+
+	/// Decides how a jump from `from` to `target` is written, in the range ending at `to` with control continuing at `follow`.
+	fn jump(&mut self, from: usize, target: usize, to: usize, follow: usize) -> Jump {
+		let next = self.at(from + 1);
+		if target == next && next < to {
+			return Jump::Next;
+		}
+		if next >= to && target == follow {
+			return Jump::Silent;
+		}
+		// entering a rotated loop, whose test comes first
+		if next < to && self.loops.iter().any(|l| l.start == next && l.header == target && l.end <= to) {
+			return Jump::Next;
+		}
+		let mut innermost_break = true;
+		let mut innermost_continue = true;
+		for index in (0..self.scopes.len()).rev() {
+			let scope = &mut self.scopes[index];
+			if scope.exit == target {
+				scope.labeled |= !innermost_break;
+				return Jump::Stmts(vec![Stmt::Break((!innermost_break).then_some(scope.label))]);
+			}
+			if let Some(header) = scope.header {
+				if header == target {
+					scope.labeled |= !innermost_continue;
+					return Jump::Stmts(vec![Stmt::Continue((!innermost_continue).then_some(scope.label))]);
+				}
+				innermost_continue = false;
+			}
+			innermost_break = false;
+		}
+		if from < target && target < to {
+			Jump::Forward(target, target)
+		} else if target == follow {
+			Jump::Forward(to, target)
+		} else {
+			Jump::Stmts(self.continue_at_update(target).unwrap_or_else(|| vec![Stmt::Goto(self.offset(target))]))
+		}
+	}
+
+	/// The statements for a jump to the node updating a loop: the update, and a `continue` of the loop.
+	fn continue_at_update(&mut self, target: usize) -> Option<Vec<Stmt>> {
+		let mut innermost = true;
+		for scope in self.scopes.iter_mut().rev().filter(|scope| scope.header.is_some()) {
+			if scope.update == Some(target) {
+				scope.labeled |= !innermost;
+				let mut statements = self.graph.nodes[target].statements.clone();
+				statements.push(Stmt::Continue((!innermost).then_some(scope.label)));
+				return Some(statements);
+			}
+			innermost = false;
+		}
+		None
+	}
+
+	/// The statements of the header of a rotated loop, which tests whether to leave it.
+	fn test(&mut self, Loop { start, header, end, .. }: Loop) -> Vec<Stmt> {
+		let graph = self.graph;
+		let node = &graph.nodes[header];
+		let mut out = vec![Stmt::Label(node.offset)];
+		out.extend(node.statements.iter().cloned());
+		let Terminator::If { condition, then, otherwise } = &node.terminator else {
+			unreachable!("only loops tested by their header are rotated");
+		};
+		let (condition, exit) = if *then == start { (condition.clone().negate(), *otherwise) } else { (condition.clone(), *then) };
+		let after = self.at(header + 1);
+		let then = if exit == after && after < end {
+			self.block(after, end, header)
+		} else {
+			let jump = self.jump(header, exit, start, header);
+			self.simple(jump)
+		};
+		out.push(Stmt::If { condition, then, otherwise: Vec::new() });
+		out
+	}
+
+	/// The statements for a jump that doesn't start a range of its own.
+	fn simple(&self, jump: Jump) -> Vec<Stmt> {
+		match jump {
+			Jump::Next | Jump::Silent => Vec::new(),
+			Jump::Stmts(statements) => statements,
+			Jump::Forward(_, target) => vec![Stmt::Goto(self.offset(target))],
+		}
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	fn conditional(&mut self, id: usize, condition: Expr, then: usize, otherwise: usize, to: usize, follow: usize, out: &mut Vec<Stmt>) -> usize {
+		if then == otherwise {
+			out.push(Stmt::If { condition, then: Vec::new(), otherwise: Vec::new() });
+			let jump = self.jump(id, then, to, follow);
+			out.extend(self.simple(jump));
+			return id + 1;
+		}
+		let then_jump = self.jump(id, then, to, follow);
+		let otherwise_jump = self.jump(id, otherwise, to, follow);
+		match (then_jump, otherwise_jump) {
+			(Jump::Forward(end, _), Jump::Next) => self.if_range(id, condition.negate(), end, to, follow, out),
+			(Jump::Next, Jump::Forward(end, _)) => self.if_range(id, condition, end, to, follow, out),
+			(then_jump, otherwise_jump) => {
+				out.push(Stmt::If { condition, then: self.simple(then_jump), otherwise: self.simple(otherwise_jump) });
+				id + 1
+			},
+		}
+	}
+
+	/// Writes an `if` whose `then` part are the nodes following `id` up to `end`, and returns the node to continue with.
+	fn if_range(&mut self, id: usize, condition: Expr, end: usize, to: usize, follow: usize, out: &mut Vec<Stmt>) -> usize {
+		let next = self.at(id + 1);
+		// if only the condition leads to the nodes following the `then` part, and the `then` part jumps past them, these are the `else` part
+		let targets: Vec<usize> = (next..end)
+			.filter(|&id| !self.graph.nodes[id].removed)
+			.flat_map(|id| self.graph.nodes[id].terminator.successors())
+			.collect();
+		let otherwise = if end >= to || self.predecessors[end][..] != [id] {
+			None
+		} else if let Some(&past) = targets.iter().filter(|&&target| target > end && target < to).min() {
+			Some((past, past))
+		} else if targets.contains(&follow) {
+			Some((to, follow))
+		} else {
+			None
+		};
+		match otherwise {
+			Some((otherwise_end, continuation)) => {
+				let then = self.block(next, end, continuation);
+				let otherwise = self.block(end, otherwise_end, continuation);
+				out.push(Stmt::If { condition, then, otherwise });
+				otherwise_end
+			},
+			None => {
+				let then = self.block(next, end, follow_of(end, to, follow));
+				out.push(Stmt::If { condition, then, otherwise: Vec::new() });
+				end
+			},
+		}
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	fn switch(&mut self, id: usize, value: Expr, cases: &[(i32, usize)], default: usize, to: usize, follow: usize, out: &mut Vec<Stmt>) -> usize {
+		let mut targets: Vec<usize> = cases.iter().map(|&(_, target)| target).chain([default]).filter(|&target| id < target && target < to).collect();
+		targets.sort();
+		targets.dedup();
+		// the switch ends where its clauses jump to past the last one
+		let last = targets.last().copied().unwrap_or(id + 1);
+		let mut end = to;
+		for node in (targets.first().copied().unwrap_or(to)..to).filter(|&node| !self.graph.nodes[node].removed) {
+			let next = self.at(node + 1);
+			for target in self.graph.nodes[node].terminator.successors() {
+				if target > node && target >= last && target < end && target != next {
+					end = target;
+				}
+			}
+		}
+		let exit = follow_of(end, to, follow);
+		self.scopes.push(Scope { header: None, update: None, exit, label: self.offset(id), labeled: false });
+
+		let values = |target: usize| cases.iter().filter(|&&(_, case)| case == target).map(|&(value, _)| value).collect::<Vec<_>>();
+		let mut clauses = Vec::new();
+		// the clauses jumping out of the switch come first, as nothing falls through to them
+		let mut outside: Vec<usize> = cases.iter().map(|&(_, target)| target).chain([default]).filter(|target| !targets[..].contains(target) || *target >= end).collect();
+		outside.sort();
+		outside.dedup();
+		for target in outside {
+			let values = values(target);
+			let is_default = target == default && target != exit;
+			if values.is_empty() && !is_default {
+				continue;
+			}
+			let body = match self.jump(id, target, to, follow) {
+				Jump::Next | Jump::Silent => vec![Stmt::Break(None)],
+				Jump::Stmts(statements) => statements,
+				Jump::Forward(_, target) => vec![Stmt::Goto(self.offset(target))],
+			};
+			clauses.push(Case { values, default: is_default, body });
+		}
+		let inside: Vec<usize> = targets.iter().copied().filter(|&target| target < end).collect();
+		for (index, &target) in inside.iter().enumerate() {
+			let clause_end = inside.get(index + 1).copied().unwrap_or(end);
+			let body = self.block(target, clause_end, follow_of(clause_end, end, exit));
+			clauses.push(Case { values: values(target), default: target == default, body });
+		}
+
+		let scope = self.scopes.pop().expect("the scope was pushed before");
+		out.push(Stmt::Switch { value, cases: clauses, label: scope.labeled.then_some(scope.label) });
+		end
+	}
+}
+
+/// Removes the assignment of the exception caught to a variable at the start of a handler, and returns the variable.
+fn catch_variable(body: &mut Vec<Stmt>) -> Option<usize> {
+	let position = body.iter().position(|statement| !matches!(statement, Stmt::Label(_)))?;
+	match &body[position] {
+		Stmt::Expr(Expr::Assign(target, value)) if matches!(**value, Expr::Caught(_)) => {
+			let Expr::Local(variable) = **target else {
+				return None;
+			};
+			body.remove(position);
+			Some(variable)
+		},
+		_ => None,
+	}
+}
@@ -0,0 +1,183 @@
+//! Writing class names and types the way the source would.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use crate::ClassFile;
+use crate::deps::package;
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor};
+use crate::name::ClassName;
+use crate::signature::{ClassTypeSignature, TypeArgument, TypeParameter, TypeSignature};
+
+pub(crate) fn text(bytes: &[u8]) -> String {
+	String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Decides how class names are written: nested classes as `Outer.Inner`, and classes of `java.lang`, of the package of the class and of the
+/// imports by their simple name. The names used are recorded, the imports are chosen from them with [Names::import_used].
+pub(crate) struct Names {
+	package: &'static [u8],
+	/// The class a member class is declared in, and its simple name, from the `InnerClasses` attributes.
+	members: BTreeMap<ClassName, (ClassName, Vec<u8>)>,
+	/// The classes written by their simple name, once [Names::import_used] decided them.
+	imports: Option<BTreeMap<&'static [u8], ClassName>>,
+	used: RefCell<BTreeSet<ClassName>>,
+}
+
+impl Names {
+	pub(crate) fn new<'a>(class: &ClassName, classes: impl IntoIterator<Item=&'a ClassFile>) -> Names {
+		let mut members = BTreeMap::new();
+		for class in classes {
+			for entry in class.inner_classes() {
+				if let (Some(outer), Some(name)) = (entry.outer_class, &entry.inner_name) {
+					members.insert(entry.inner_class, (outer, name.clone()));
+				}
+			}
+		}
+		Names { package: package(class), members, imports: None, used: RefCell::new(BTreeSet::new()) }
+	}
+
+	/// Imports the classes used so far that can be written by their simple name without clashing with another class used, and returns them sorted.
+	pub(crate) fn import_used(&mut self) -> Vec<ClassName> {
+		let used = self.used.take();
+		let mut by_simple_name: BTreeMap<&[u8], Vec<ClassName>> = BTreeMap::new();
+		for class in &used {
+			by_simple_name.entry(simple_name(class)).or_default().push(*class);
+		}
+		let mut simple_names = BTreeMap::new();
+		let mut imports = Vec::new();
+		for (simple_name, classes) in by_simple_name {
+			// a class of the package wins over an import, an import over java.lang
+			let chosen = classes.iter().find(|class| package(class) == self.package)
+				.or_else(|| match &classes[..] {
+					[class] => Some(class),
+					_ => None,
+				});
+			if let Some(&class) = chosen {
+				simple_names.insert(simple_name, class);
+				if package(&class) != self.package && package(&class) != b"java/lang" {
+					imports.push(class);
+				}
+			}
+		}
+		self.imports = Some(simple_names);
+		imports.sort_by_key(|class| class.as_bytes());
+		imports
+	}
+
+	/// Writes the name of a class, recording it as used.
+	pub(crate) fn class(&self, class: &ClassName) -> String {
+		if let Some((outer, name)) = self.members.get(class) {
+			return format!("{}.{}", self.class(outer), text(name));
+		}
+		self.used.borrow_mut().insert(*class);
+		let simple_name = simple_name(class);
+		let short = match &self.imports {
+			Some(imports) => imports.get(simple_name) == Some(class),
+			None => package(class) == self.package || package(class) == b"java/lang",
+		};
+		if short {
+			text(simple_name)
+		} else {
+			text(class.as_bytes()).replace('/', ".")
+		}
+	}
+
+	pub(crate) fn descriptor(&self, descriptor: &FieldDescriptor) -> String {
+		let mut out = match &descriptor.base_type {
+			BaseOrObjectType::Object(class) => self.class(class),
+			base_type => String::from(base_type_name(base_type)),
+		};
+		for _ in 0..descriptor.array_dimension {
+			out.push_str("[]");
+		}
+		out
+	}
+
+	pub(crate) fn signature(&self, signature: &TypeSignature) -> String {
+		match signature {
+			TypeSignature::Base(base_type) => String::from(match base_type {
+				b'B' => "byte",
+				b'C' => "char",
+				b'D' => "double",
+				b'F' => "float",
+				b'I' => "int",
+				b'J' => "long",
+				b'S' => "short",
+				_ => "boolean",
+			}),
+			TypeSignature::Class(class) => self.class_signature(class),
+			TypeSignature::TypeVariable(name) => text(name),
+			TypeSignature::Array(component) => format!("{}[]", self.signature(component)),
+		}
+	}
+
+	pub(crate) fn class_signature(&self, signature: &ClassTypeSignature) -> String {
+		let mut out = match &signature.outer {
+			Some(outer) => {
+				let outer_name = outer.name.as_bytes();
+				let name = signature.name.as_bytes();
+				let inner = name.strip_prefix(outer_name).and_then(|name| name.strip_prefix(b"$")).unwrap_or(name);
+				format!("{}.{}", self.class_signature(outer), text(inner))
+			},
+			None => self.class(&signature.name),
+		};
+		if !signature.type_arguments.is_empty() {
+			let arguments: Vec<String> = signature.type_arguments.iter()
+				.map(|argument| match argument {
+					TypeArgument::Any => String::from("?"),
+					TypeArgument::Extends(bound) => format!("? extends {}", self.signature(bound)),
+					TypeArgument::Super(bound) => format!("? super {}", self.signature(bound)),
+					TypeArgument::Exact(argument) => self.signature(argument),
+				})
+				.collect();
+			out.push('<');
+			out.push_str(&arguments.join(", "));
+			out.push('>');
+		}
+		out
+	}
+
+	/// Writes type parameters like `<K extends Comparable<K>, V>`, or nothing if there are none.
+	pub(crate) fn type_parameters(&self, parameters: &[TypeParameter]) -> String {
+		if parameters.is_empty() {
+			return String::new();
+		}
+		let parameters: Vec<String> = parameters.iter()
+			.map(|parameter| {
+				let bounds: Vec<String> = parameter.class_bound.iter()
+					.filter(|bound| !matches!(bound, TypeSignature::Class(class) if class.name.as_bytes() == b"java/lang/Object" && class.type_arguments.is_empty()))
+					.chain(&parameter.interface_bounds)
+					.map(|bound| self.signature(bound))
+					.collect();
+				if bounds.is_empty() {
+					text(&parameter.name)
+				} else {
+					format!("{} extends {}", text(&parameter.name), bounds.join(" & "))
+				}
+			})
+			.collect();
+		format!("<{}>", parameters.join(", "))
+	}
+}
+
+fn simple_name(class: &ClassName) -> &'static [u8] {
+	let name = class.as_bytes();
+	name.rsplit(|&byte| byte == b'/').next().unwrap_or(name)
+}
+
+pub(crate) fn base_type_name(base_type: &BaseOrObjectType) -> &'static str {
+	match base_type {
+		BaseOrObjectType::B => "byte",
+		BaseOrObjectType::C => "char",
+		BaseOrObjectType::D => "double",
+		BaseOrObjectType::F => "float",
+		BaseOrObjectType::I => "int",
+		BaseOrObjectType::J => "long",
+		BaseOrObjectType::S => "short",
+		BaseOrObjectType::Z => "boolean",
+		BaseOrObjectType::Object(_) => "Object",
+	}
+}
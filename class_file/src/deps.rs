@@ -0,0 +1,430 @@
+//! Finding the classes a class depends on, and summarizing the dependencies of many classes by package and by where they come from, like `jdeps`.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::{ClassFile, FieldInfo, MethodInfo};
+use crate::cp::{Pool, PoolEntry};
+use crate::cp::attribute::{Annotation, AnnotationElementValue, AttributeInfo};
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+use crate::name::ClassName;
+use crate::signature::{ClassSignature, MethodSignature, TypeSignature};
+
+/// How a class refers to another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+	/// The superclass or a direct superinterface.
+	Supertype,
+	/// A class entry of the constant pool, or a class in a descriptor in the constant pool. These are used by instructions and call sites.
+	ConstantPool,
+	/// A class in the descriptor of a field, a method or a local variable.
+	Descriptor,
+	/// A class in a generic signature.
+	Signature,
+	/// An annotation type, or a class or enum used in the elements of an annotation.
+	Annotation,
+	/// A class thrown by a method, from its `Exceptions` attribute.
+	Throws,
+	/// An inner or enclosing class, from the `InnerClasses` and `EnclosingMethod` attributes.
+	Nesting,
+}
+
+/// The classes a class depends on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassDependencies {
+	pub class: ClassName,
+	/// The classes referenced, with all the ways they are. Array classes are replaced by their element class, and the class itself is left out.
+	pub dependencies: BTreeMap<ClassName, BTreeSet<DependencyKind>>,
+}
+
+/// Calls `f` with every class named in a field or method descriptor. Malformed descriptors are read as far as they go.
+pub(crate) fn visit_descriptor_classes(descriptor: &[u8], f: &mut impl FnMut(ClassName)) {
+	let mut rest = descriptor;
+	while let Some((&first, tail)) = rest.split_first() {
+		rest = tail;
+		if first == b'L' {
+			let end = rest.iter().position(|&byte| byte == b';').unwrap_or(rest.len());
+			f(ClassName::from(&rest[..end]));
+			rest = rest.get(end + 1..).unwrap_or(&[]);
+		}
+	}
+}
+
+impl ClassDependencies {
+	/// Collects the dependencies of `class`, from the constant pool it was parsed with, its descriptors, signatures, annotations and nesting
+	/// attributes. Signatures that don't parse are skipped.
+	pub fn of(class: &ClassFile, pool: &Pool) -> ClassDependencies {
+		let mut dependencies = ClassDependencies { class: class.this_class, dependencies: BTreeMap::new() };
+
+		for super_class in class.super_class.iter().chain(&class.interfaces) {
+			dependencies.add(*super_class, DependencyKind::Supertype);
+		}
+		for (_, entry) in pool.iter() {
+			let descriptor = match entry {
+				PoolEntry::ClassName(index) => match pool.get::<&PoolEntry>(*index) {
+					// array classes are named by their descriptor
+					Ok(PoolEntry::Utf8(name)) if name.starts_with(b"[") => name,
+					Ok(PoolEntry::Utf8(name)) => {
+						dependencies.add(ClassName::from(&name[..]), DependencyKind::ConstantPool);
+						continue;
+					},
+					_ => continue,
+				},
+				PoolEntry::NameAndType { descriptor_index: index, .. } | PoolEntry::MethodType(index) => match pool.get::<&PoolEntry>(*index) {
+					Ok(PoolEntry::Utf8(descriptor)) => descriptor,
+					_ => continue,
+				},
+				_ => continue,
+			};
+			visit_descriptor_classes(descriptor, &mut |class| dependencies.add(class, DependencyKind::ConstantPool));
+		}
+
+		dependencies.add_attributes(&class.attributes, class_signature_classes);
+		for field in &class.fields {
+			dependencies.add_field(field);
+		}
+		for method in &class.methods {
+			dependencies.add_method(method);
+		}
+		dependencies
+	}
+
+	fn add(&mut self, class: ClassName, kind: DependencyKind) {
+		let class = if class.as_bytes().starts_with(b"[") {
+			match FieldDescriptor::try_from(class.as_bytes()) {
+				Ok(FieldDescriptor { base_type: BaseOrObjectType::Object(class), .. }) => class,
+				_ => return,
+			}
+		} else {
+			class
+		};
+		if class != self.class {
+			self.dependencies.entry(class).or_default().insert(kind);
+		}
+	}
+
+	fn add_field_descriptor(&mut self, descriptor: &FieldDescriptor) {
+		if let BaseOrObjectType::Object(class) = descriptor.base_type {
+			self.add(class, DependencyKind::Descriptor);
+		}
+	}
+
+	fn add_method_descriptor(&mut self, descriptor: &MethodDescriptor) {
+		for descriptor in descriptor.parameters.iter().chain(&descriptor.return_type) {
+			self.add_field_descriptor(descriptor);
+		}
+	}
+
+	fn add_field(&mut self, field: &FieldInfo) {
+		self.add_field_descriptor(&field.descriptor);
+		self.add_attributes(&field.attributes, type_signature_classes);
+	}
+
+	fn add_method(&mut self, method: &MethodInfo) {
+		self.add_method_descriptor(&method.descriptor);
+		self.add_attributes(&method.attributes, method_signature_classes);
+		if let Some(code) = &method.code {
+			// the catch types are in the constant pool already
+			self.add_attributes(&code.attributes, type_signature_classes);
+		}
+	}
+
+	/// Adds the dependencies found in `attributes`, using `signature` to get the classes named by a `Signature` attribute.
+	fn add_attributes(&mut self, attributes: &[AttributeInfo], signature: fn(&[u8]) -> Option<Vec<ClassName>>) {
+		for attribute in attributes {
+			match attribute {
+				AttributeInfo::Signature(attribute) => {
+					for class in signature(&attribute.signature).unwrap_or_default() {
+						self.add(class, DependencyKind::Signature);
+					}
+				},
+				AttributeInfo::Exceptions(attribute) => {
+					for class in &attribute.exception_table {
+						self.add(*class, DependencyKind::Throws);
+					}
+				},
+				AttributeInfo::InnerClasses(attribute) => {
+					for element in &attribute.classes {
+						self.add(element.inner_class, DependencyKind::Nesting);
+						if let Some(outer_class) = element.outer_class {
+							self.add(outer_class, DependencyKind::Nesting);
+						}
+					}
+				},
+				AttributeInfo::EnclosingMethod(attribute) => {
+					self.add(attribute.class, DependencyKind::Nesting);
+					if let Some((_, descriptor)) = &attribute.method {
+						self.add_method_descriptor(descriptor);
+					}
+				},
+				AttributeInfo::RuntimeVisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+				AttributeInfo::RuntimeInvisibleAnnotations(attribute) => self.add_annotations(&attribute.annotations),
+				AttributeInfo::RuntimeVisibleParameterAnnotations(attribute) => {
+					for parameter in &attribute.parameter_annotations {
+						self.add_annotations(&parameter.annotations);
+					}
+				},
+				AttributeInfo::RuntimeInvisibleParameterAnnotations(attribute) => {
+					for parameter in &attribute.parameter_annotations {
+						self.add_annotations(&parameter.annotations);
+					}
+				},
+				AttributeInfo::AnnotationDefault(attribute) => self.add_element_value(&attribute.default_value),
+				AttributeInfo::LocalVariableTable(attribute) => {
+					for entry in &attribute.local_variable_table {
+						self.add_field_descriptor(&entry.descriptor);
+					}
+				},
+				AttributeInfo::LocalVariableTypeTable(attribute) => {
+					for entry in &attribute.local_variable_type_table {
+						for class in type_signature_classes(&entry.signature).unwrap_or_default() {
+							self.add(class, DependencyKind::Signature);
+						}
+					}
+				},
+				_ => {},
+			}
+		}
+	}
+
+	fn add_annotations(&mut self, annotations: &[Annotation]) {
+		for annotation in annotations {
+			self.add_annotation(annotation);
+		}
+	}
+
+	fn add_annotation(&mut self, annotation: &Annotation) {
+		visit_descriptor_classes(&annotation.annotation_type, &mut |class| self.add(class, DependencyKind::Annotation));
+		for pair in &annotation.element_value_pairs {
+			self.add_element_value(&pair.value);
+		}
+	}
+
+	fn add_element_value(&mut self, value: &AnnotationElementValue) {
+		match value {
+			AnnotationElementValue::Enum { type_name: descriptor, .. } | AnnotationElementValue::Class(descriptor) => {
+				visit_descriptor_classes(descriptor, &mut |class| self.add(class, DependencyKind::Annotation));
+			},
+			AnnotationElementValue::Annotation(annotation) => self.add_annotation(annotation),
+			AnnotationElementValue::Array(values) => {
+				for value in values {
+					self.add_element_value(value);
+				}
+			},
+			_ => {},
+		}
+	}
+}
+
+fn class_signature_classes(bytes: &[u8]) -> Option<Vec<ClassName>> {
+	let mut classes = Vec::new();
+	ClassSignature::parse(bytes).ok()?.visit_classes(&mut |class| classes.push(class));
+	Some(classes)
+}
+
+fn method_signature_classes(bytes: &[u8]) -> Option<Vec<ClassName>> {
+	let mut classes = Vec::new();
+	MethodSignature::parse(bytes).ok()?.visit_classes(&mut |class| classes.push(class));
+	Some(classes)
+}
+
+fn type_signature_classes(bytes: &[u8]) -> Option<Vec<ClassName>> {
+	let mut classes = Vec::new();
+	TypeSignature::parse(bytes).ok()?.visit_classes(&mut |class| classes.push(class));
+	Some(classes)
+}
+
+/// Returns the package of a class, like `java/lang` for `java/lang/Object`. Classes in the unnamed package have the empty package.
+pub fn package(class: &ClassName) -> &'static [u8] {
+	let name = class.as_bytes();
+	let end = name.iter().rposition(|&byte| byte == b'/').unwrap_or(0);
+	&name[..end]
+}
+
+/// The dependencies of many classes, each from some origin like a jar, together with the classes available from other origins, like the rest of the
+/// class path.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyAnalysis {
+	origins: Vec<String>,
+	/// The classes analyzed, with the index of their origin.
+	analyzed: BTreeMap<ClassName, (usize, ClassDependencies)>,
+	/// All known classes, including the analyzed ones, with the index of their origin. The first origin to add a class wins, like on a class path.
+	available: BTreeMap<ClassName, usize>,
+}
+
+impl DependencyAnalysis {
+	pub fn new() -> DependencyAnalysis {
+		DependencyAnalysis::default()
+	}
+
+	fn origin(&mut self, origin: &str) -> usize {
+		match self.origins.iter().position(|known| known == origin) {
+			Some(index) => index,
+			None => {
+				self.origins.push(origin.into());
+				self.origins.len() - 1
+			},
+		}
+	}
+
+	/// Analyzes `class` from `origin`, which is usually the path of the jar or directory it is in.
+	pub fn add(&mut self, origin: &str, class: &ClassFile, pool: &Pool) {
+		let origin = self.origin(origin);
+		self.available.entry(class.this_class).or_insert(origin);
+		self.analyzed.entry(class.this_class).or_insert_with(|| (origin, ClassDependencies::of(class, pool)));
+	}
+
+	/// Records that `class` can be found in `origin`, without analyzing it.
+	pub fn add_available(&mut self, origin: &str, class: ClassName) {
+		let origin = self.origin(origin);
+		self.available.entry(class).or_insert(origin);
+	}
+
+	/// Returns the analyzed classes, with their origin.
+	pub fn classes(&self) -> impl Iterator<Item=(&str, &ClassDependencies)> {
+		self.analyzed.values().map(|(origin, dependencies)| (self.origins[*origin].as_str(), dependencies))
+	}
+
+	/// Returns the origin a class is available from.
+	pub fn origin_of(&self, class: &ClassName) -> Option<&str> {
+		self.available.get(class).map(|&origin| self.origins[origin].as_str())
+	}
+
+	/// Returns the packages of the analyzed classes, with the other packages their classes depend on.
+	pub fn package_dependencies(&self) -> BTreeMap<&'static [u8], BTreeSet<&'static [u8]>> {
+		let mut packages: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+		for (_, dependencies) in self.analyzed.values() {
+			let from = package(&dependencies.class);
+			let targets = packages.entry(from).or_default();
+			targets.extend(dependencies.dependencies.keys().map(package).filter(|&to| to != from));
+		}
+		packages
+	}
+
+	/// Returns the origins of the analyzed classes, with the other origins their dependencies are available from. Dependencies that aren't available
+	/// are left out, see [DependencyAnalysis::missing].
+	pub fn origin_dependencies(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+		let mut origins: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+		for (origin, dependencies) in self.analyzed.values() {
+			let targets = origins.entry(self.origins[*origin].as_str()).or_default();
+			for class in dependencies.dependencies.keys() {
+				match self.available.get(class) {
+					Some(target) if target != origin => {
+						targets.insert(self.origins[*target].as_str());
+					},
+					_ => {},
+				}
+			}
+		}
+		origins
+	}
+
+	/// Returns the cycles between the packages of the analyzed classes, that is, the strongly connected components of more than one package. The
+	/// packages of each cycle are sorted, and so are the cycles.
+	pub fn package_cycles(&self) -> Vec<Vec<&'static [u8]>> {
+		let graph = self.package_dependencies();
+		let mut tarjan = Tarjan { graph: &graph, index: BTreeMap::new(), low: BTreeMap::new(), stack: Vec::new(), components: Vec::new() };
+		for &package in graph.keys() {
+			if !tarjan.index.contains_key(package) {
+				tarjan.visit(package);
+			}
+		}
+		let mut cycles: Vec<_> = tarjan.components.into_iter()
+			.filter(|component| component.len() > 1)
+			.map(|mut component| {
+				component.sort();
+				component
+			})
+			.collect();
+		cycles.sort();
+		cycles
+	}
+
+	/// Returns the classes referenced by analyzed classes that aren't available, with the classes referencing them. Classes for which `provided`
+	/// returns `true` are assumed to be available, like the ones of the platform.
+	pub fn missing(&self, provided: impl Fn(&ClassName) -> bool) -> BTreeMap<ClassName, BTreeSet<ClassName>> {
+		let mut missing: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+		for (_, dependencies) in self.analyzed.values() {
+			for class in dependencies.dependencies.keys() {
+				if !self.available.contains_key(class) && !provided(class) {
+					missing.entry(*class).or_default().insert(dependencies.class);
+				}
+			}
+		}
+		missing
+	}
+}
+
+/// Tarjan's algorithm for strongly connected components.
+struct Tarjan<'a> {
+	graph: &'a BTreeMap<&'static [u8], BTreeSet<&'static [u8]>>,
+	index: BTreeMap<&'static [u8], usize>,
+	low: BTreeMap<&'static [u8], usize>,
+	stack: Vec<&'static [u8]>,
+	components: Vec<Vec<&'static [u8]>>,
+}
+
+impl Tarjan<'_> {
+	fn visit(&mut self, node: &'static [u8]) {
+		let index = self.index.len();
+		self.index.insert(node, index);
+		self.low.insert(node, index);
+		self.stack.push(node);
+
+		for &next in self.graph.get(node).into_iter().flatten() {
+			if !self.index.contains_key(next) {
+				self.visit(next);
+				let low = self.low[node].min(self.low[next]);
+				self.low.insert(node, low);
+			} else if self.stack.contains(&next) {
+				let low = self.low[node].min(self.index[next]);
+				self.low.insert(node, low);
+			}
+		}
+
+		if self.low[node] == index {
+			let start = self.stack.iter().rposition(|&other| other == node).unwrap_or(0);
+			self.components.push(self.stack.split_off(start));
+		}
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::ClassFile;
+	use crate::deps::{ClassDependencies, DependencyAnalysis, DependencyKind};
+	use crate::name::ClassName;
+
+	#[test]
+	fn test3_dependencies() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test3.class");
+		let (class, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+		let dependencies = ClassDependencies::of(&class, &pool);
+		let kinds = |name: &[u8]| dependencies.dependencies.get(&ClassName::from(name)).cloned().unwrap_or_default();
+		assert!(kinds(b"java/lang/Object").contains(&DependencyKind::Supertype));
+		assert!(kinds(b"java/lang/String").contains(&DependencyKind::Descriptor));
+		assert!(kinds(b"java/io/PrintStream").contains(&DependencyKind::ConstantPool));
+		assert!(kinds(b"java/lang/NullPointerException").contains(&DependencyKind::ConstantPool));
+		assert!(kinds(b"Test3").is_empty());
+
+		let mut analysis = DependencyAnalysis::new();
+		analysis.add("test.jar", &class, &pool);
+		analysis.add_available("rt.jar", ClassName::from(b"java/lang/Object"));
+		let missing = analysis.missing(|class| class.as_bytes().starts_with(b"java/io/"));
+		assert!(missing.contains_key(&ClassName::from(b"java/lang/String")));
+		assert!(!missing.contains_key(&ClassName::from(b"java/lang/Object")));
+		assert!(!missing.contains_key(&ClassName::from(b"java/io/PrintStream")));
+		assert_eq!(analysis.origin_dependencies()["test.jar"].iter().copied().collect::<Vec<_>>(), ["rt.jar"]);
+		assert!(analysis.package_dependencies()[&b""[..]].contains(&b"java/lang"[..]));
+		assert!(analysis.package_cycles().is_empty());
+
+		// a -> b -> c -> a, and c -> d -> d/e -> d
+		for (from, to) in [("a/A", "b/B"), ("b/B", "c/C"), ("c/C", "a/A"), ("c/D", "d/D"), ("d/D", "d/e/E"), ("d/e/E", "d/D")] {
+			let class = ClassName::from(from.as_bytes());
+			let (_, dependencies) = analysis.analyzed.entry(class)
+				.or_insert_with(|| (0, ClassDependencies { class, dependencies: Default::default() }));
+			dependencies.dependencies.entry(ClassName::from(to.as_bytes())).or_default().insert(DependencyKind::ConstantPool);
+		}
+		assert_eq!(analysis.package_cycles(), [vec![&b"a"[..], b"b", b"c"], vec![&b"d"[..], b"d/e"]]);
+	}
+}
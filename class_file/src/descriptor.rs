@@ -1,9 +1,13 @@
+use alloc::format;
+use alloc::vec;
+use alloc::string::String;
+use alloc::vec::Vec;
 use anyhow::{bail, Result};
-use std::iter::Peekable;
-use crate::name::ClassName;
+use core::iter::Peekable;
+use crate::name::{ClassName, MethodName};
 
 // TODO: this is not good...
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum BaseOrObjectType {
 	B, C, D, F, I, J, S, Z,
 	Object(ClassName),
@@ -26,7 +30,7 @@ pub enum BaseOrObjectType {
 ///   Z
 /// ```
 /// A field descriptor representing an array type is valid only if it represents a type with 255 or fewer dimensions.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FieldDescriptor {
 	pub array_dimension: usize,
 	pub base_type: BaseOrObjectType, // TODO: this needs change!
@@ -76,6 +80,32 @@ impl FieldDescriptor {
 			base_type,
 		})
 	}
+
+	/// Returns the descriptor as it appears in a class file, like `[Ljava/lang/String;`.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		self.write(&mut bytes);
+		bytes
+	}
+
+	fn write(&self, bytes: &mut Vec<u8>) {
+		bytes.resize(bytes.len() + self.array_dimension, b'[');
+		match &self.base_type {
+			BaseOrObjectType::B => bytes.push(b'B'),
+			BaseOrObjectType::C => bytes.push(b'C'),
+			BaseOrObjectType::D => bytes.push(b'D'),
+			BaseOrObjectType::F => bytes.push(b'F'),
+			BaseOrObjectType::I => bytes.push(b'I'),
+			BaseOrObjectType::J => bytes.push(b'J'),
+			BaseOrObjectType::S => bytes.push(b'S'),
+			BaseOrObjectType::Z => bytes.push(b'Z'),
+			BaseOrObjectType::Object(class_name) => {
+				bytes.push(b'L');
+				bytes.extend_from_slice(class_name.as_bytes());
+				bytes.push(b';');
+			},
+		}
+	}
 }
 
 impl TryFrom<&[u8]> for FieldDescriptor {
@@ -102,13 +132,34 @@ impl TryFrom<&[u8]> for FieldDescriptor {
 ///   VoidDescriptor:
 ///     V
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MethodDescriptor {
 	pub parameters: Vec<FieldDescriptor>,
 	/// A value of `None` indicates the type `void`.
 	pub return_type: Option<FieldDescriptor>,
 }
 
+impl MethodDescriptor {
+	/// Returns the descriptor as it appears in a class file, like `(I[J)V`.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut bytes = vec![b'('];
+		for parameter in &self.parameters {
+			parameter.write(&mut bytes);
+		}
+		bytes.push(b')');
+		match &self.return_type {
+			Some(return_type) => return_type.write(&mut bytes),
+			None => bytes.push(b'V'),
+		}
+		bytes
+	}
+}
+
+/// The method `name` with its descriptor, like `run(I)V`, as it's written in messages.
+pub(crate) fn method_string(name: MethodName, descriptor: &MethodDescriptor) -> String {
+	format!("{}{}", String::from_utf8_lossy(name.as_bytes()), String::from_utf8_lossy(&descriptor.to_bytes()))
+}
+
 impl TryFrom<&[u8]> for MethodDescriptor {
 	type Error = anyhow::Error;
 
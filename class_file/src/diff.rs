@@ -0,0 +1,630 @@
+//! Comparing two versions of a class file by what they declare and what their code does, rather than by their bytes.
+//!
+//! The code of methods is compared as listings, where branch targets, exception handlers and local variable scopes are written as symbolic labels
+//! instead of offsets, so that code moving around doesn't show up as a change.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use crate::{ClassFile, FieldInfo, MethodInfo, json};
+use crate::access::{ClassInfoAccess, FieldInfoAccess, MethodInfoAccess};
+use crate::cp::MethodHandleInfo;
+use crate::cp::attribute::{AttributeInfo, CodeAttribute, ConstantValueAttribute};
+use crate::cp::builder::PoolBuilder;
+use crate::instruction::BranchTarget;
+use crate::instruction::opcode::Opcode;
+
+/// Listings with more lines than this squared are compared by their common start and end only.
+const MAX_DIFF_CELLS: usize = 1 << 22;
+
+/// A line of a listing, with whether it is in both versions or only in one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+	Same(String),
+	Removed(String),
+	Added(String),
+}
+
+impl Edit {
+	fn name(&self) -> &'static str {
+		match self {
+			Edit::Same(_) => "same",
+			Edit::Removed(_) => "removed",
+			Edit::Added(_) => "added",
+		}
+	}
+
+	fn line(&self) -> &str {
+		match self {
+			Edit::Same(line) | Edit::Removed(line) | Edit::Added(line) => line,
+		}
+	}
+}
+
+/// A property of a class or member that changed, like its access flags or an attribute. The values are rendered as text, `None` if the property isn't
+/// there in a version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyChange {
+	pub property: String,
+	pub old: Option<String>,
+	pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+	Field,
+	Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberStatus {
+	Added,
+	Removed,
+	Changed,
+}
+
+/// A field or method that was added, removed or changed. Members are matched by their name and descriptor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberDiff {
+	pub kind: MemberKind,
+	pub name: String,
+	pub descriptor: String,
+	pub status: MemberStatus,
+	pub changes: Vec<PropertyChange>,
+	/// The listing of the code of a changed method, with all lines. Empty if the code didn't change.
+	pub code: Vec<Edit>,
+}
+
+/// The differences between two versions of a class file, see [ClassDiff::new].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassDiff {
+	/// The name of the class in the new version.
+	pub class: String,
+	pub changes: Vec<PropertyChange>,
+	pub members: Vec<MemberDiff>,
+}
+
+impl ClassDiff {
+	pub fn new(old: &ClassFile, new: &ClassFile) -> ClassDiff {
+		let mut changes = Vec::new();
+		let mut property = |property: &str, old: Option<String>, new: Option<String>| if old != new {
+			changes.push(PropertyChange { property: property.to_string(), old, new });
+		};
+		property("name", Some(name(old.this_class.as_bytes())), Some(name(new.this_class.as_bytes())));
+		property("version", Some(format!("{}.{}", old.major_version, old.minor_version)), Some(format!("{}.{}", new.major_version, new.minor_version)));
+		property("access flags", Some(class_flags(&old.access_flags)), Some(class_flags(&new.access_flags)));
+		property("super class", old.super_class.map(|class| name(class.as_bytes())), new.super_class.map(|class| name(class.as_bytes())));
+		let interfaces = |class: &ClassFile| class.interfaces.iter().map(|interface| name(interface.as_bytes())).collect::<Vec<_>>().join(", ");
+		property("interfaces", Some(interfaces(old)), Some(interfaces(new)));
+		attribute_changes(&mut changes, &old.attributes, &new.attributes);
+
+		let mut members = Vec::new();
+		let field_key = |field: &FieldInfo| (name(field.name.as_bytes()), name(&field.descriptor.to_bytes()));
+		members_diff(&mut members, MemberKind::Field, &old.fields, &new.fields, field_key, |old, new| {
+			let mut changes = Vec::new();
+			if old.access_flags != new.access_flags {
+				changes.push(PropertyChange {
+					property: "access flags".to_string(), old: Some(field_flags(&old.access_flags)), new: Some(field_flags(&new.access_flags)),
+				});
+			}
+			if old.constant_value != new.constant_value {
+				changes.push(PropertyChange {
+					property: "constant value".to_string(), old: old.constant_value.as_ref().map(constant), new: new.constant_value.as_ref().map(constant),
+				});
+			}
+			attribute_changes(&mut changes, &old.attributes, &new.attributes);
+			(changes, Vec::new())
+		});
+		let method_key = |method: &MethodInfo| (name(method.name.as_bytes()), name(&method.descriptor.to_bytes()));
+		members_diff(&mut members, MemberKind::Method, &old.methods, &new.methods, method_key, |old, new| {
+			let mut changes = Vec::new();
+			if old.access_flags != new.access_flags {
+				changes.push(PropertyChange {
+					property: "access flags".to_string(), old: Some(method_flags(&old.access_flags)), new: Some(method_flags(&new.access_flags)),
+				});
+			}
+			attribute_changes(&mut changes, &old.attributes, &new.attributes);
+			let mut code = Vec::new();
+			if old.code != new.code {
+				let (old_code, new_code) = (old.code.as_ref(), new.code.as_ref());
+				let mut property = |property: &str, old: Option<String>, new: Option<String>| if old != new {
+					changes.push(PropertyChange { property: property.to_string(), old, new });
+				};
+				property("max stack", old_code.map(|code| code.max_stack.to_string()), new_code.map(|code| code.max_stack.to_string()));
+				property("max locals", old_code.map(|code| code.max_locals.to_string()), new_code.map(|code| code.max_locals.to_string()));
+				let old_listing = old_code.map(listing).unwrap_or_default();
+				let new_listing = new_code.map(listing).unwrap_or_default();
+				if old_listing != new_listing {
+					code = diff_lines(&old_listing, &new_listing);
+				}
+			}
+			(changes, code)
+		});
+		ClassDiff { class: name(new.this_class.as_bytes()), changes, members }
+	}
+
+	/// Whether the versions don't differ in anything compared.
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty() && self.members.is_empty()
+	}
+
+	/// Formats the differences for reading, showing the changed lines of code with `context` unchanged lines around them:
+	///
+	/// ```txt
+	/// class com/example/Foo
+	///   version: 52.0 -> 61.0
+	/// + field count:I
+	/// ~ method run()V
+	///     max stack: 2 -> 3
+	///       L0:
+	///   -     iload 1
+	///   +     iload 2
+	/// ```
+	pub fn to_text(&self, context: usize) -> String {
+		let mut out = String::new();
+		let _ = writeln!(out, "class {}", self.class);
+		for change in &self.changes {
+			text_change(&mut out, "  ", change);
+		}
+		for member in &self.members {
+			let sign = match member.status {
+				MemberStatus::Added => '+',
+				MemberStatus::Removed => '-',
+				MemberStatus::Changed => '~',
+			};
+			let (kind, separator) = match member.kind {
+				MemberKind::Field => ("field", ":"),
+				MemberKind::Method => ("method", ""),
+			};
+			let _ = writeln!(out, "{sign} {kind} {}{separator}{}", member.name, member.descriptor);
+			for change in &member.changes {
+				text_change(&mut out, "    ", change);
+			}
+			// only the changed lines and their context
+			let mut last = None;
+			for (index, edit) in member.code.iter().enumerate() {
+				let start = index.saturating_sub(context);
+				let near = member.code[start..(index + context + 1).min(member.code.len())].iter().any(|edit| !matches!(edit, Edit::Same(_)));
+				if !near {
+					continue;
+				}
+				if last.is_some_and(|last| last + 1 != index) {
+					out.push_str("      ...\n");
+				}
+				last = Some(index);
+				let sign = match edit {
+					Edit::Same(_) => ' ',
+					Edit::Removed(_) => '-',
+					Edit::Added(_) => '+',
+				};
+				let _ = writeln!(out, "  {sign}   {}", edit.line());
+			}
+		}
+		out
+	}
+
+	/// Formats the differences as JSON, with all lines of the listings of changed code:
+	///
+	/// ```json
+	/// {"class": "...", "changes": [{"property": "version", "old": "52.0", "new": "61.0"}, ...],
+	///  "members": [{"kind": "method", "name": "...", "descriptor": "...", "status": "changed", "changes": [...],
+	///               "code": [{"edit": "removed", "line": "iload 1"}, ...]}, ...]}
+	/// ```
+	pub fn to_json(&self) -> String {
+		let mut out = String::new();
+		self.write_json(&mut out);
+		out
+	}
+
+	/// Formats the differences between two sets of class files as one JSON document, with the names of the files only in the old or only in the new
+	/// set, and the differences of the classes in both as [ClassDiff::to_json] formats them:
+	///
+	/// ```json
+	/// {"added": ["..."], "removed": ["..."], "changed": [{"class": "...", ...}, ...]}
+	/// ```
+	pub fn to_json_document(added: &[&str], removed: &[&str], diffs: &[ClassDiff]) -> String {
+		let mut out = String::new();
+		out.push_str("{\"added\":");
+		json::array(&mut out, added, |out, name| json::string(out, name.as_bytes()));
+		out.push_str(",\"removed\":");
+		json::array(&mut out, removed, |out, name| json::string(out, name.as_bytes()));
+		out.push_str(",\"changed\":");
+		json::array(&mut out, diffs, |out, diff| diff.write_json(out));
+		out.push('}');
+		out
+	}
+
+	fn write_json(&self, out: &mut String) {
+		out.push_str("{\"class\":");
+		json::string(out, self.class.as_bytes());
+		out.push_str(",\"changes\":");
+		json::array(out, &self.changes, json_change);
+		out.push_str(",\"members\":");
+		json::array(out, &self.members, |out, member| {
+			let kind = match member.kind {
+				MemberKind::Field => "field",
+				MemberKind::Method => "method",
+			};
+			let status = match member.status {
+				MemberStatus::Added => "added",
+				MemberStatus::Removed => "removed",
+				MemberStatus::Changed => "changed",
+			};
+			let _ = write!(out, "{{\"kind\":\"{kind}\",\"name\":");
+			json::string(out, member.name.as_bytes());
+			out.push_str(",\"descriptor\":");
+			json::string(out, member.descriptor.as_bytes());
+			let _ = write!(out, ",\"status\":\"{status}\",\"changes\":");
+			json::array(out, &member.changes, json_change);
+			out.push_str(",\"code\":");
+			json::array(out, &member.code, |out, edit| {
+				let _ = write!(out, "{{\"edit\":\"{}\",\"line\":", edit.name());
+				json::string(out, edit.line().as_bytes());
+				out.push('}');
+			});
+			out.push('}');
+		});
+		out.push('}');
+	}
+}
+
+fn text_change(out: &mut String, indent: &str, change: &PropertyChange) {
+	let value = |value: &Option<String>| value.clone().unwrap_or_else(|| "(none)".to_string());
+	let _ = writeln!(out, "{indent}{}: {} -> {}", change.property, value(&change.old), value(&change.new));
+}
+
+fn json_change(out: &mut String, change: &PropertyChange) {
+	let value = |out: &mut String, value: &Option<String>| match value {
+		Some(value) => json::string(out, value.as_bytes()),
+		None => out.push_str("null"),
+	};
+	out.push_str("{\"property\":");
+	json::string(out, change.property.as_bytes());
+	out.push_str(",\"old\":");
+	value(out, &change.old);
+	out.push_str(",\"new\":");
+	value(out, &change.new);
+	out.push('}');
+}
+
+fn name(bytes: &[u8]) -> String {
+	String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Collects the members of one kind that were added, removed or changed, in the order of the new version, with the removed ones at the end.
+fn members_diff<T>(
+	members: &mut Vec<MemberDiff>, kind: MemberKind, old: &[T], new: &[T],
+	key: impl Fn(&T) -> (String, String),
+	compare: impl Fn(&T, &T) -> (Vec<PropertyChange>, Vec<Edit>),
+) {
+	let old_members: BTreeMap<_, _> = old.iter().map(|member| (key(member), member)).collect();
+	let new_keys: Vec<_> = new.iter().map(&key).collect();
+	for (member, (name, descriptor)) in new.iter().zip(&new_keys) {
+		let (status, changes, code) = match old_members.get(&(name.clone(), descriptor.clone())) {
+			Some(old) => {
+				let (changes, code) = compare(old, member);
+				if changes.is_empty() && code.is_empty() {
+					continue;
+				}
+				(MemberStatus::Changed, changes, code)
+			},
+			None => (MemberStatus::Added, Vec::new(), Vec::new()),
+		};
+		members.push(MemberDiff { kind, name: name.clone(), descriptor: descriptor.clone(), status, changes, code });
+	}
+	for (name, descriptor) in old.iter().map(&key).filter(|key| !new_keys.contains(key)) {
+		members.push(MemberDiff { kind, name, descriptor, status: MemberStatus::Removed, changes: Vec::new(), code: Vec::new() });
+	}
+}
+
+/// Compares the attributes by name, as a class or member has most attributes at most once.
+fn attribute_changes(changes: &mut Vec<PropertyChange>, old: &[AttributeInfo], new: &[AttributeInfo]) {
+	let mut names: Vec<&[u8]> = Vec::new();
+	for attribute in old.iter().chain(new) {
+		if !names.contains(&attribute.name()) {
+			names.push(attribute.name());
+		}
+	}
+	for attribute_name in names {
+		let render = |attributes: &[AttributeInfo]| {
+			let values: Vec<String> = attributes.iter().filter(|attribute| attribute.name() == attribute_name).map(attribute).collect();
+			(!values.is_empty()).then(|| values.join("; "))
+		};
+		let (old, new) = (render(old), render(new));
+		if old != new {
+			changes.push(PropertyChange { property: format!("attribute {}", name(attribute_name)), old, new });
+		}
+	}
+}
+
+fn attribute(attribute: &AttributeInfo) -> String {
+	match attribute {
+		AttributeInfo::Signature(signature) => name(&signature.signature),
+		AttributeInfo::SourceFile(source_file) => name(&source_file.sourcefile),
+		AttributeInfo::Exceptions(exceptions) => exceptions.exception_table.iter().map(|class| name(class.as_bytes())).collect::<Vec<_>>().join(", "),
+		AttributeInfo::Deprecated(_) | AttributeInfo::Synthetic(_) => String::new(),
+		AttributeInfo::Unknown { info, .. } => digest(info),
+		attribute => {
+			// written with a pool of its own, the bytes only depend on what the attribute contains, not on where its constants are in the class file
+			let mut pool = PoolBuilder::new();
+			let mut bytes = Vec::new();
+			match attribute.write(&mut bytes, &mut pool, &Ok) {
+				Ok(()) => {
+					let mut contents = Vec::new();
+					pool.write(&mut contents);
+					contents.extend_from_slice(&bytes[6..]);
+					format!("{} bytes, digest {:016x}", bytes.len() - 6, fnv1a(&contents))
+				},
+				Err(_) => name(attribute.name()),
+			}
+		},
+	}
+}
+
+/// Renders the `info` of an attribute by its length and a digest of it.
+fn digest(info: &[u8]) -> String {
+	format!("{} bytes, digest {:016x}", info.len(), fnv1a(info))
+}
+
+/// The 64 bit FNV-1a hash, which is enough for telling apart attributes that differ.
+fn fnv1a(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+fn constant(value: &ConstantValueAttribute) -> String {
+	match value {
+		ConstantValueAttribute::Long(value) => format!("{value}L"),
+		ConstantValueAttribute::Float(bits) => format!("{:?}f", f32::from_bits(*bits)),
+		ConstantValueAttribute::Double(bits) => format!("{:?}", f64::from_bits(*bits)),
+		ConstantValueAttribute::Integer(value) => value.to_string(),
+		ConstantValueAttribute::String(value) => format!("{:?}", name(value)),
+	}
+}
+
+fn flags(flags: &[(bool, &str)]) -> String {
+	flags.iter().filter(|(set, _)| *set).map(|(_, name)| *name).collect::<Vec<_>>().join(" ")
+}
+
+fn class_flags(access: &ClassInfoAccess) -> String {
+	flags(&[
+		(access.is_public, "public"), (access.is_final, "final"), (access.is_super, "super"), (access.is_interface, "interface"),
+		(access.is_abstract, "abstract"), (access.is_synthetic, "synthetic"), (access.is_annotation, "annotation"), (access.is_enum, "enum"),
+	])
+}
+
+fn field_flags(access: &FieldInfoAccess) -> String {
+	flags(&[
+		(access.is_public, "public"), (access.is_private, "private"), (access.is_protected, "protected"), (access.is_static, "static"),
+		(access.is_final, "final"), (access.is_volatile, "volatile"), (access.is_transient, "transient"), (access.is_synthetic, "synthetic"),
+		(access.is_enum, "enum"),
+	])
+}
+
+fn method_flags(access: &MethodInfoAccess) -> String {
+	flags(&[
+		(access.is_public, "public"), (access.is_private, "private"), (access.is_protected, "protected"), (access.is_static, "static"),
+		(access.is_final, "final"), (access.is_synchronised, "synchronized"), (access.is_bridge, "bridge"), (access.is_varargs, "varargs"),
+		(access.is_native, "native"), (access.is_abstract, "abstract"), (access.is_strict, "strict"), (access.is_synthetic, "synthetic"),
+	])
+}
+
+/// Lists the code with labels instead of offsets: the instructions, the source lines they start, and then the exception handlers and local variables.
+pub fn listing(code: &CodeAttribute) -> Vec<String> {
+	let mut offsets: Vec<usize> = code.code.iter().flat_map(|instruction| instruction.opcode().branch_targets()).map(|target| target.0).collect();
+	for entry in &code.exception_table {
+		offsets.extend([entry.start_pc, entry.end_pc, entry.handler_pc]);
+	}
+	let locals = code.attributes.iter()
+		.filter_map(|attribute| match attribute {
+			AttributeInfo::LocalVariableTable(table) => Some(&table.local_variable_table),
+			_ => None,
+		})
+		.flatten();
+	for local in locals.clone() {
+		offsets.extend([local.start_pc, local.end_pc]);
+	}
+	offsets.sort_unstable();
+	offsets.dedup();
+	let label = |offset: usize| match offsets.binary_search(&offset) {
+		Ok(index) => format!("L{index}"),
+		Err(_) => format!("@{offset}"),
+	};
+
+	let mut lines = Vec::new();
+	for instruction in &code.code {
+		let offset = instruction.offset();
+		if offsets.binary_search(&offset).is_ok() {
+			lines.push(format!("{}:", label(offset)));
+		}
+		for entry in code.line_number_table.iter().filter(|entry| entry.start_pc == offset) {
+			lines.push(format!("line {}", entry.line_number));
+		}
+		lines.push(format!("  {}", self::instruction(instruction.opcode(), &|target| label(target.0))));
+	}
+	if offsets.binary_search(&code.code.code_length()).is_ok() {
+		lines.push(format!("{}:", label(code.code.code_length())));
+	}
+	for entry in &code.exception_table {
+		let catch_type = entry.catch_type.map_or_else(|| "any".to_string(), |class| name(class.as_bytes()));
+		lines.push(format!("try {} {} catch {catch_type} {}", label(entry.start_pc), label(entry.end_pc), label(entry.handler_pc)));
+	}
+	for local in locals {
+		lines.push(format!("local {} {}:{} {} {}", local.lv_index, name(local.name.as_bytes()), name(&local.descriptor.to_bytes()), label(local.start_pc), label(local.end_pc)));
+	}
+	lines
+}
+
+/// Formats an instruction like `javap` does, with references written out instead of pool indices, and branch targets written with `label`.
+pub fn instruction(opcode: &Opcode, label: &dyn Fn(&BranchTarget) -> String) -> String {
+	let mnemonic = opcode.mnemonic();
+	let method = |class: &crate::name::ClassName, method: &crate::name::MethodName, descriptor: &crate::descriptor::MethodDescriptor| {
+		format!("{}.{}{}", name(class.as_bytes()), name(method.as_bytes()), name(&descriptor.to_bytes()))
+	};
+	let field = |field: &crate::cp::FieldRefInfo| {
+		format!("{}.{}:{}", name(field.class.as_bytes()), name(field.name.as_bytes()), name(&field.descriptor.to_bytes()))
+	};
+	let handle = |handle: &MethodHandleInfo| match handle {
+		MethodHandleInfo::GetField(reference) => format!("getfield {}", field(reference)),
+		MethodHandleInfo::GetStatic(reference) => format!("getstatic {}", field(reference)),
+		MethodHandleInfo::PutField(reference) => format!("putfield {}", field(reference)),
+		MethodHandleInfo::PutStatic(reference) => format!("putstatic {}", field(reference)),
+		MethodHandleInfo::InvokeVirtual(reference) => format!("invokevirtual {}", method(&reference.class, &reference.name, &reference.descriptor)),
+		MethodHandleInfo::InvokeStatic(reference) => format!("invokestatic {}", method(&reference.class, &reference.name, &reference.descriptor)),
+		MethodHandleInfo::InvokeSpecial(reference) => format!("invokespecial {}", method(&reference.class, &reference.name, &reference.descriptor)),
+		MethodHandleInfo::NewInvokeSpecial(reference) => format!("newinvokespecial {}", method(&reference.class, &reference.name, &reference.descriptor)),
+		MethodHandleInfo::InvokeInterface(reference) => format!("invokeinterface {}", method(&reference.class, &reference.name, &reference.descriptor)),
+		MethodHandleInfo::InvokeStaticInterface(reference) => format!("invokestatic {}", method(&reference.class, &reference.name, &reference.descriptor)),
+		MethodHandleInfo::InvokeSpecialInterface(reference) => format!("invokespecial {}", method(&reference.class, &reference.name, &reference.descriptor)),
+	};
+	let operands = match opcode {
+		Opcode::ALoad(index) | Opcode::AStore(index) | Opcode::DLoad(index) | Opcode::DStore(index) | Opcode::FLoad(index) | Opcode::FStore(index) |
+		Opcode::ILoad(index) | Opcode::IStore(index) | Opcode::LLoad(index) | Opcode::LStore(index) => index.0.to_string(),
+		Opcode::IInc { lv_index, const_ } => format!("{} {const_}", lv_index.0),
+		Opcode::BIPush(value) => (*value as i8).to_string(),
+		Opcode::SIPush(value) => value.to_string(),
+		Opcode::LdcInt(value) => value.to_string(),
+		Opcode::LdcFloat(bits) => format!("{:?}f", f32::from_bits(*bits)),
+		Opcode::Ldc2WLong(value) => format!("{value}L"),
+		Opcode::Ldc2WDouble(bits) => format!("{:?}", f64::from_bits(*bits)),
+		Opcode::LdcReferenceString(value) => format!("{:?}", name(value)),
+		Opcode::LdcReferenceClass(class) | Opcode::ANewArray(class) | Opcode::CheckCast(class) | Opcode::InstanceOf(class) | Opcode::New(class) => {
+			name(class.as_bytes())
+		},
+		Opcode::LdcReferenceMethodType(descriptor) => name(&descriptor.to_bytes()),
+		Opcode::LdcReferenceMethodHandle(reference) => handle(reference),
+		Opcode::MultiANewArray(class, dimensions) => format!("{} {dimensions}", name(class.as_bytes())),
+		Opcode::NewArray { a_type } => format!("{a_type:?}").to_lowercase(),
+		Opcode::GetField(reference) | Opcode::GetStatic(reference) | Opcode::PutField(reference) | Opcode::PutStatic(reference) => field(reference),
+		Opcode::InvokeSpecial(reference) | Opcode::InvokeStatic(reference) | Opcode::InvokeVirtual(reference) => {
+			method(&reference.class, &reference.name, &reference.descriptor)
+		},
+		Opcode::InvokeInterface { method_ref, .. } => method(&method_ref.class, &method_ref.name, &method_ref.descriptor),
+		Opcode::InvokeDynamic { call_site, .. } => {
+			format!("{}{} bootstrap {}", name(call_site.name.as_bytes()), name(&call_site.descriptor.to_bytes()), call_site.bootstrap_method_attribute_index)
+		},
+		Opcode::LookupSwitch { default_target, targets, .. } => {
+			let cases: Vec<String> = targets.iter().map(|(key, target)| format!("{key}: {}", label(target))).collect();
+			format!("{{ {}, default: {} }}", cases.join(", "), label(default_target))
+		},
+		Opcode::TableSwitch { default_target, low, targets, .. } => {
+			let cases: Vec<String> = targets.iter().zip(*low..).map(|(target, key)| format!("{key}: {}", label(target))).collect();
+			format!("{{ {}, default: {} }}", cases.join(", "), label(default_target))
+		},
+		opcode => match opcode.branch_targets().first() {
+			Some(target) => label(target),
+			None => return mnemonic.to_string(),
+		},
+	};
+	format!("{mnemonic} {operands}")
+}
+
+/// Compares two listings line by line, with the longest common subsequence of lines kept.
+fn diff_lines(old: &[String], new: &[String]) -> Vec<Edit> {
+	let prefix = old.iter().zip(new).take_while(|(old, new)| old == new).count();
+	let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(old, new)| old == new).count();
+	let (old_middle, new_middle) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+	let mut edits: Vec<Edit> = old[..prefix].iter().cloned().map(Edit::Same).collect();
+	let (n, m) = (old_middle.len(), new_middle.len());
+	if (n + 1) * (m + 1) > MAX_DIFF_CELLS {
+		edits.extend(old_middle.iter().cloned().map(Edit::Removed));
+		edits.extend(new_middle.iter().cloned().map(Edit::Added));
+	} else {
+		// lengths[i][j] is the length of the longest common subsequence of old_middle[i..] and new_middle[j..]
+		let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+		for i in (0..n).rev() {
+			for j in (0..m).rev() {
+				lengths[i * (m + 1) + j] = if old_middle[i] == new_middle[j] {
+					lengths[(i + 1) * (m + 1) + j + 1] + 1
+				} else {
+					lengths[(i + 1) * (m + 1) + j].max(lengths[i * (m + 1) + j + 1])
+				};
+			}
+		}
+		let (mut i, mut j) = (0, 0);
+		while i < n || j < m {
+			if i < n && j < m && old_middle[i] == new_middle[j] {
+				edits.push(Edit::Same(old_middle[i].clone()));
+				i += 1;
+				j += 1;
+			} else if j == m || (i < n && lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1]) {
+				edits.push(Edit::Removed(old_middle[i].clone()));
+				i += 1;
+			} else {
+				edits.push(Edit::Added(new_middle[j].clone()));
+				j += 1;
+			}
+		}
+	}
+	edits.extend(old[old.len() - suffix..].iter().cloned().map(Edit::Same));
+	edits
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::ClassFile;
+	use crate::cp::attribute::AttributeInfo;
+	use crate::diff::{ClassDiff, Edit, MemberStatus};
+	use crate::fixture;
+	use crate::instruction::opcode::Opcode;
+
+	/// `static int sign(boolean b) { return b ? one : 0; }`, with `nops` in front.
+	fn class(nops: usize, one: i32, major_version: u16) -> ClassFile {
+		let sign = fixture::method_with_code(0x0009, b"sign", b"(Z)I", |code| {
+			for _ in 0..nops {
+				code.emit(Opcode::Nop);
+			}
+			let zero = code.new_label();
+			code.line_number(3).iload(0).ifeq(zero).iconst(one).emit(Opcode::IReturn)
+				.place(zero).line_number(4).iconst(0).emit(Opcode::IReturn);
+		});
+		ClassFile { major_version, ..fixture::class(0x0021, b"Sign", b"java/lang/Object", &[], Vec::new(), vec![sign]) }
+	}
+
+	#[test]
+	fn changed_method() {
+		let old = class(0, 1, 52);
+		assert!(ClassDiff::new(&old, &old).is_empty());
+		// the nops move all offsets, which doesn't show as the branch refers to a label
+		let diff = ClassDiff::new(&old, &class(2, 2, 61));
+		assert_eq!(diff.changes.len(), 1);
+		assert_eq!((diff.changes[0].old.as_deref(), diff.changes[0].new.as_deref()), (Some("52.0"), Some("61.0")));
+		assert_eq!(diff.members.len(), 1);
+		assert_eq!(diff.members[0].status, MemberStatus::Changed);
+		let edits: Vec<_> = diff.members[0].code.iter().filter(|edit| !matches!(edit, Edit::Same(_))).collect();
+		assert_eq!(edits, [
+			&Edit::Added("  nop".to_string()),
+			&Edit::Added("  nop".to_string()),
+			&Edit::Removed("  iconst_1".to_string()),
+			&Edit::Added("  iconst_2".to_string()),
+		]);
+		assert_eq!(diff.to_text(0), "\
+class Sign
+  version: 52.0 -> 61.0
+~ method sign(Z)I
+  +     nop
+  +     nop
+      ...
+  -     iconst_1
+  +     iconst_2
+");
+		assert!(diff.to_json().starts_with("{\"class\":\"Sign\",\"changes\":[{\"property\":\"version\",\"old\":\"52.0\",\"new\":\"61.0\"}],"));
+		let document = ClassDiff::to_json_document(&["a/\"New\"\n.class"], &[], core::slice::from_ref(&diff));
+		assert!(document.starts_with("{\"added\":[\"a/\\\"New\\\"\\n.class\"],\"removed\":[],\"changed\":[{\"class\":\"Sign\","));
+		assert!(document.ends_with(&(diff.to_json() + "]}")));
+	}
+
+	#[test]
+	fn unknown_attribute() {
+		let old = class(0, 1, 52);
+		let mut new = old.clone();
+		new.attributes.push(AttributeInfo::Unknown { name: b"Custom".to_vec(), info: vec![1, 2, 3] });
+		let diff = ClassDiff::new(&old, &new);
+		assert_eq!(diff.changes.len(), 1);
+		assert_eq!(diff.changes[0].property, "attribute Custom");
+		assert_eq!(diff.changes[0].new.as_deref(), Some("3 bytes, digest d0aa6218672cf5ab"));
+	}
+}
@@ -0,0 +1,43 @@
+//! Builders for the classes, fields and methods the tests of the analyses are run on.
+
+use crate::{ClassFile, FieldInfo, MethodInfo};
+use crate::access::{ClassInfoAccess, FieldInfoAccess, MethodInfoAccess};
+use crate::cp::builder::PoolBuilder;
+use crate::descriptor::{FieldDescriptor, MethodDescriptor};
+use crate::instruction::builder::CodeBuilder;
+use crate::name::{ClassName, FieldName, MethodName};
+
+/// A class of version 61 without attributes.
+pub(crate) fn class(access_flags: u16, name: &[u8], super_class: &[u8], interfaces: &[&[u8]], fields: Vec<FieldInfo>, methods: Vec<MethodInfo>) -> ClassFile {
+	ClassFile {
+		minor_version: 0, major_version: 61,
+		access_flags: ClassInfoAccess::parse(access_flags).unwrap(),
+		this_class: ClassName::from(name),
+		super_class: Some(ClassName::from(super_class)),
+		interfaces: interfaces.iter().map(|&interface| ClassName::from(interface)).collect(),
+		fields, methods, attributes: Vec::new(),
+	}
+}
+
+pub(crate) fn field(access_flags: u16, name: &[u8], descriptor: &[u8]) -> FieldInfo {
+	FieldInfo {
+		access_flags: FieldInfoAccess::parse(access_flags).unwrap(), name: FieldName::from(name),
+		descriptor: FieldDescriptor::try_from(descriptor).unwrap(), attributes: Vec::new(), constant_value: None,
+	}
+}
+
+/// A method without code, like an abstract one.
+pub(crate) fn method(access_flags: u16, name: &[u8], descriptor: &[u8]) -> MethodInfo {
+	MethodInfo {
+		access_flags: MethodInfoAccess::parse(access_flags).unwrap(), name: MethodName::from(name),
+		descriptor: MethodDescriptor::try_from(descriptor).unwrap(), attributes: Vec::new(), code: None,
+	}
+}
+
+/// A method with the code `code` writes.
+pub(crate) fn method_with_code(access_flags: u16, name: &[u8], descriptor: &[u8], code: impl FnOnce(&mut CodeBuilder)) -> MethodInfo {
+	let method = method(access_flags, name, descriptor);
+	let mut builder = CodeBuilder::new(method.descriptor.clone(), method.access_flags.is_static);
+	code(&mut builder);
+	MethodInfo { code: Some(builder.build(&mut PoolBuilder::new()).unwrap()), ..method }
+}
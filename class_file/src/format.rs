@@ -0,0 +1,685 @@
+//! Format checking of class files, as described in 4.8.
+//!
+//! This is separate from bytecode verification (4.10): it only checks that the class file is structurally well-formed, that is, that constant pool
+//! entries reference entries of the right kind, and that the flags of the class and its members make sense together. Instead of stopping at the first
+//! problem, every violation found is reported together with the section of the JVMS that describes the violated rule.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::Result;
+use core::fmt::{Display, Formatter};
+use crate::io::Read;
+use crate::ClassFile;
+use crate::cp::attribute::{AttributeInfo, ConstantValueAttribute};
+use crate::cp::{Pool, PoolEntry};
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+use crate::name::{check_binary_class_name, check_method_name, check_module_name, check_package_name, check_unqualified_name, ClassName};
+
+const JAVA_LANG_OBJECT: &[u8] = b"java/lang/Object";
+const INIT: &[u8] = b"<init>";
+const CLINIT: &[u8] = b"<clinit>";
+
+/// A single violation of a rule of the class file format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatViolation {
+	/// The section of the JVMS that defines the violated rule, like `"4.4.2"`.
+	pub section: &'static str,
+	pub message: String,
+}
+
+impl Display for FormatViolation {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		write!(f, "JVMS §{}: {}", self.section, self.message)
+	}
+}
+
+/// Selects which of the format checks are run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatCheckOptions {
+	/// Whether class, field and method names, as well as the class names in descriptors, are checked against the rules of 4.2.
+	///
+	/// Obfuscators often produce names that don't follow these rules, so this can be turned off to still check jars produced by them.
+	pub check_names: bool,
+}
+
+impl Default for FormatCheckOptions {
+	fn default() -> Self {
+		FormatCheckOptions { check_names: true }
+	}
+}
+
+#[derive(Debug, Default)]
+struct Violations {
+	inner: Vec<FormatViolation>,
+}
+
+impl Violations {
+	fn report(&mut self, section: &'static str, message: impl Into<String>) {
+		self.inner.push(FormatViolation { section, message: message.into() });
+	}
+}
+
+/// Parses a class file and runs all format checks on it.
+///
+/// Returns an error if the class file can't be parsed at all, otherwise the parsed class file together with all found violations. The class file is only
+/// well-formed if no violations are returned.
+pub fn check_class_file<R: Read>(reader: &mut R, options: &FormatCheckOptions) -> Result<(ClassFile, Vec<FormatViolation>)> {
+	let (class_file, pool) = ClassFile::parse_with_pool(reader)?;
+
+	let mut violations = check_pool(&pool, &class_file, options);
+	violations.extend(check_class(&class_file, options));
+
+	Ok((class_file, violations))
+}
+
+// 4.4
+
+/// Checks that every entry of the constant pool only references entries of the kind required by 4.4.
+///
+/// The class file is needed for the version number and the number of bootstrap methods.
+pub fn check_pool(pool: &Pool, class_file: &ClassFile, options: &FormatCheckOptions) -> Vec<FormatViolation> {
+	let mut v = Violations::default();
+
+	let bootstrap_methods_count = class_file.attributes.iter()
+		.find_map(|attribute| match attribute {
+			AttributeInfo::BootstrapMethods(bootstrap_methods) => Some(bootstrap_methods.count()),
+			_ => None,
+		});
+
+	for (index, entry) in pool.iter() {
+		check_pool_entry(&mut v, pool, class_file.major_version, bootstrap_methods_count, options, index, entry);
+	}
+
+	v.inner
+}
+
+/// Returns the entry at `index`, or reports that the index isn't valid.
+fn get_entry<'a>(v: &mut Violations, pool: &'a Pool, section: &'static str, index: usize, from: usize) -> Option<&'a PoolEntry> {
+	match pool.get::<&PoolEntry>(index) {
+		Ok(PoolEntry::None) | Ok(PoolEntry::Unusable) | Err(_) => {
+			v.report(section, format!("constant pool entry {from} references the invalid index {index}"));
+			None
+		},
+		Ok(entry) => Some(entry),
+	}
+}
+
+fn get_utf8<'a>(v: &mut Violations, pool: &'a Pool, section: &'static str, index: usize, from: usize) -> Option<&'a [u8]> {
+	match get_entry(v, pool, section, index, from)? {
+		PoolEntry::Utf8(vec) => Some(vec),
+		entry => {
+			v.report(section, format!("constant pool entry {from} must reference a Utf8 entry at {index}, got {entry:?}"));
+			None
+		},
+	}
+}
+
+/// Returns the name and descriptor of a `NameAndType` entry at `index`.
+fn get_name_and_type<'a>(v: &mut Violations, pool: &'a Pool, section: &'static str, index: usize, from: usize) -> Option<(&'a [u8], &'a [u8])> {
+	match get_entry(v, pool, section, index, from)? {
+		PoolEntry::NameAndType { name_index, descriptor_index } => {
+			let name = get_utf8(v, pool, "4.4.6", *name_index, index);
+			let descriptor = get_utf8(v, pool, "4.4.6", *descriptor_index, index);
+			Some((name?, descriptor?))
+		},
+		entry => {
+			v.report(section, format!("constant pool entry {from} must reference a NameAndType entry at {index}, got {entry:?}"));
+			None
+		},
+	}
+}
+
+fn check_class_index(v: &mut Violations, pool: &Pool, section: &'static str, index: usize, from: usize) {
+	match get_entry(v, pool, section, index, from) {
+		Some(PoolEntry::ClassName(_)) | None => {},
+		Some(entry) => v.report(section, format!("constant pool entry {from} must reference a Class entry at {index}, got {entry:?}")),
+	}
+}
+
+/// The bytes must be in modified UTF-8: no byte may be `0` or lie in the range `0xf0..=0xff`, and multibyte sequences must be complete.
+fn is_modified_utf8(bytes: &[u8]) -> bool {
+	let mut iter = bytes.iter();
+	while let Some(&byte) = iter.next() {
+		let continuation_bytes = match byte {
+			0x01..=0x7f => 0,
+			0xc0..=0xdf => 1,
+			0xe0..=0xef => 2,
+			_ => return false,
+		};
+		for _ in 0..continuation_bytes {
+			match iter.next() {
+				Some(&byte) if byte & 0xc0 == 0x80 => {},
+				_ => return false,
+			}
+		}
+	}
+	true
+}
+
+/// Reports the class names in a descriptor that aren't binary class names.
+fn check_descriptor_names<'a>(v: &mut Violations, section: &'static str, descriptors: impl IntoIterator<Item=&'a FieldDescriptor>) {
+	for descriptor in descriptors {
+		if let BaseOrObjectType::Object(class_name) = &descriptor.base_type {
+			if let Err(e) = check_binary_class_name(class_name.as_bytes()) {
+				v.report(section, format!("invalid class name in descriptor: {e}"));
+			}
+		}
+	}
+}
+
+fn check_method_descriptor_names(v: &mut Violations, descriptor: &MethodDescriptor) {
+	check_descriptor_names(v, "4.3.3", descriptor.parameters.iter().chain(&descriptor.return_type));
+}
+
+fn check_pool_entry(
+	v: &mut Violations, pool: &Pool, major_version: u16, bootstrap_methods_count: Option<usize>, options: &FormatCheckOptions,
+	index: usize, entry: &PoolEntry
+) {
+	match entry {
+		PoolEntry::None | PoolEntry::Unusable => {},
+		PoolEntry::Utf8(vec) => {
+			if !is_modified_utf8(vec) {
+				v.report("4.4.7", format!("constant pool entry {index} is not valid modified UTF-8: {}", String::from_utf8_lossy(vec)));
+			}
+		},
+		PoolEntry::Integer(_) | PoolEntry::Float(_) | PoolEntry::Long { .. } | PoolEntry::Double { .. } => {},
+		PoolEntry::ClassName(name_index) => {
+			if let Some(name) = get_utf8(v, pool, "4.4.1", *name_index, index) {
+				if options.check_names {
+					if let Err(e) = ClassName::from(name).check() {
+						v.report("4.4.1", format!("constant pool entry {index} has an invalid class name: {e}"));
+					}
+				}
+			}
+		},
+		PoolEntry::String(string_index) => {
+			get_utf8(v, pool, "4.4.3", *string_index, index);
+		},
+		PoolEntry::FieldRef { class_index, name_and_type_index } => {
+			check_class_index(v, pool, "4.4.2", *class_index, index);
+			if let Some((name, descriptor)) = get_name_and_type(v, pool, "4.4.2", *name_and_type_index, index) {
+				if options.check_names {
+					if let Err(e) = check_unqualified_name(name) {
+						v.report("4.4.2", format!("constant pool entry {index} has an invalid field name: {e}"));
+					}
+				}
+				match FieldDescriptor::try_from(descriptor) {
+					Ok(descriptor) if options.check_names => check_descriptor_names(v, "4.3.2", [&descriptor]),
+					Ok(_) => {},
+					Err(e) => v.report("4.4.2", format!("constant pool entry {index} must have a field descriptor: {e}")),
+				}
+			}
+		},
+		PoolEntry::MethodRef { class_index, name_and_type_index } | PoolEntry::InterfaceMethodRef { class_index, name_and_type_index } => {
+			check_class_index(v, pool, "4.4.2", *class_index, index);
+			if let Some((name, descriptor)) = get_name_and_type(v, pool, "4.4.2", *name_and_type_index, index) {
+				match MethodDescriptor::try_from(descriptor) {
+					Ok(descriptor) => {
+						// if the name of the method starts with a '<', then the name must be the special name <init>, representing an instance
+						// initialization method, and the return type of the method must be void
+						if name.starts_with(b"<") && (name != INIT || descriptor.return_type.is_some()) {
+							v.report("4.4.2", format!(
+								"constant pool entry {index} may only reference the special method name <init> returning void, got {}",
+								String::from_utf8_lossy(name)
+							));
+						}
+						if options.check_names {
+							if let Err(e) = check_method_name(name) {
+								v.report("4.4.2", format!("constant pool entry {index} has an invalid method name: {e}"));
+							}
+							check_method_descriptor_names(v, &descriptor);
+						}
+					},
+					Err(e) => v.report("4.4.2", format!("constant pool entry {index} must have a method descriptor: {e}")),
+				}
+			}
+		},
+		PoolEntry::NameAndType { name_index, descriptor_index } => {
+			if let Some(name) = get_utf8(v, pool, "4.4.6", *name_index, index) {
+				// either an unqualified name of a field or method, or the special method name <init>
+				if options.check_names && name != INIT {
+					if let Err(e) = check_unqualified_name(name) {
+						v.report("4.4.6", format!("constant pool entry {index} has an invalid name: {e}"));
+					}
+				}
+			}
+			if let Some(descriptor) = get_utf8(v, pool, "4.4.6", *descriptor_index, index) {
+				if FieldDescriptor::try_from(descriptor).is_err() && MethodDescriptor::try_from(descriptor).is_err() {
+					v.report("4.4.6", format!(
+						"constant pool entry {index} must have a field or method descriptor, got {}", String::from_utf8_lossy(descriptor)
+					));
+				}
+			}
+		},
+		PoolEntry::MethodHandle(kind, reference_index) => check_method_handle(v, pool, major_version, index, *kind, *reference_index),
+		PoolEntry::MethodType(descriptor_index) => {
+			if let Some(descriptor) = get_utf8(v, pool, "4.4.9", *descriptor_index, index) {
+				match MethodDescriptor::try_from(descriptor) {
+					Ok(descriptor) if options.check_names => check_method_descriptor_names(v, &descriptor),
+					Ok(_) => {},
+					Err(e) => v.report("4.4.9", format!("constant pool entry {index} must have a method descriptor: {e}")),
+				}
+			}
+		},
+		PoolEntry::Module(name_index) | PoolEntry::Package(name_index) => {
+			let is_module = matches!(entry, PoolEntry::Module(_));
+			let (section, kind) = if is_module { ("4.4.11", "module") } else { ("4.4.12", "package") };
+			if major_version < 53 {
+				v.report(section, format!("constant pool entry {index} is a {kind} entry, which needs version 53.0, but the version is {major_version}"));
+			}
+			if let Some(name) = get_utf8(v, pool, section, *name_index, index) {
+				if options.check_names {
+					let checked = if is_module { check_module_name(name) } else { check_package_name(name) };
+					if let Err(e) = checked {
+						v.report(section, format!("constant pool entry {index} has an invalid {kind} name: {e}"));
+					}
+				}
+			}
+		},
+		PoolEntry::InvokeDynamic { bootstrap_method_attribute_index, name_and_type_index } => {
+			match bootstrap_methods_count {
+				Some(count) if (*bootstrap_method_attribute_index as usize) < count => {},
+				Some(count) => v.report("4.4.10", format!(
+					"constant pool entry {index} references bootstrap method {bootstrap_method_attribute_index}, but there are only {count}"
+				)),
+				None => v.report("4.7.23", format!(
+					"constant pool entry {index} is an InvokeDynamic entry, but the class file has no BootstrapMethods attribute"
+				)),
+			}
+			if let Some((_, descriptor)) = get_name_and_type(v, pool, "4.4.10", *name_and_type_index, index) {
+				if let Err(e) = MethodDescriptor::try_from(descriptor) {
+					v.report("4.4.10", format!("constant pool entry {index} must have a method descriptor: {e}"));
+				}
+			}
+		},
+	}
+}
+
+fn check_method_handle(v: &mut Violations, pool: &Pool, major_version: u16, index: usize, kind: u8, reference_index: usize) {
+	let Some(entry) = get_entry(v, pool, "4.4.8", reference_index, index) else { return };
+
+	let (kind_is_valid, name_and_type_index) = match (kind, entry) {
+		// REF_getField, REF_getStatic, REF_putField, REF_putStatic
+		(1..=4, PoolEntry::FieldRef { name_and_type_index, .. }) => (true, *name_and_type_index),
+		// REF_invokeVirtual, REF_newInvokeSpecial
+		(5 | 8, PoolEntry::MethodRef { name_and_type_index, .. }) => (true, *name_and_type_index),
+		// REF_invokeStatic, REF_invokeSpecial: from 52.0 on, these may also reference interface methods
+		(6 | 7, PoolEntry::MethodRef { name_and_type_index, .. }) => (true, *name_and_type_index),
+		(6 | 7, PoolEntry::InterfaceMethodRef { name_and_type_index, .. }) => (major_version >= 52, *name_and_type_index),
+		// REF_invokeInterface
+		(9, PoolEntry::InterfaceMethodRef { name_and_type_index, .. }) => (true, *name_and_type_index),
+		(1..=9, entry) => {
+			v.report("4.4.8", format!("constant pool entry {index} with reference kind {kind} can't reference {entry:?}"));
+			return;
+		},
+		_ => {
+			v.report("4.4.8", format!("constant pool entry {index} has the unknown reference kind {kind}"));
+			return;
+		},
+	};
+	if !kind_is_valid {
+		v.report("4.4.8", format!(
+			"constant pool entry {index} with reference kind {kind} may only reference an InterfaceMethodRef from class file version 52.0 on"
+		));
+	}
+
+	if let Some((name, _)) = get_name_and_type(v, pool, "4.4.8", name_and_type_index, reference_index) {
+		match kind {
+			5 | 6 | 7 | 9 if name == INIT || name == CLINIT => v.report("4.4.8", format!(
+				"constant pool entry {index} with reference kind {kind} must not reference <init> or <clinit>"
+			)),
+			8 if name != INIT => v.report("4.4.8", format!(
+				"constant pool entry {index} with reference kind 8 must reference <init>, got {}", String::from_utf8_lossy(name)
+			)),
+			_ => {},
+		}
+	}
+}
+
+// 4.1, 4.5, 4.6, 4.7
+
+/// Checks the flags and the members of a class against the rules of 4.1, 4.5, 4.6 and 4.7.
+pub fn check_class(class_file: &ClassFile, options: &FormatCheckOptions) -> Vec<FormatViolation> {
+	let mut v = Violations::default();
+
+	check_super_class(&mut v, class_file);
+
+	if options.check_names {
+		let class_names = core::iter::once(&class_file.this_class)
+			.chain(&class_file.super_class)
+			.chain(&class_file.interfaces);
+		for class_name in class_names {
+			if let Err(e) = check_binary_class_name(class_name.as_bytes()) {
+				v.report("4.1", format!("invalid class name: {e}"));
+			}
+		}
+	}
+
+	for (i, field) in class_file.fields.iter().enumerate() {
+		let name = String::from_utf8_lossy(field.name.as_bytes());
+
+		if class_file.fields[..i].iter().any(|other| other.name == field.name && other.descriptor == field.descriptor) {
+			v.report("4.5", format!("field {name} is declared twice with the same descriptor"));
+		}
+
+		if options.check_names {
+			if let Err(e) = field.name.check() {
+				v.report("4.5", format!("invalid field name: {e}"));
+			}
+			check_descriptor_names(&mut v, "4.3.2", [&field.descriptor]);
+		}
+
+		let flags = &field.access_flags;
+		if class_file.access_flags.is_interface {
+			if !(flags.is_public && flags.is_static && flags.is_final) {
+				v.report("4.5", format!("field {name} of an interface must be ACC_PUBLIC, ACC_STATIC and ACC_FINAL"));
+			}
+			if flags.is_private || flags.is_protected || flags.is_volatile || flags.is_transient || flags.is_enum {
+				v.report("4.5", format!("field {name} of an interface may only have ACC_SYNTHETIC besides ACC_PUBLIC, ACC_STATIC and ACC_FINAL"));
+			}
+		}
+
+		if let Some(constant_value) = &field.constant_value {
+			if !constant_value_matches(constant_value, &field.descriptor) {
+				v.report("4.7.2", format!("field {name} has a ConstantValue of {constant_value:?}, which doesn't match its type"));
+			}
+		}
+
+		check_attribute_locations(&mut v, "field", &name, &field.attributes);
+		check_attribute_duplicates(&mut v, "field", &name, &field.attributes);
+	}
+
+	for (i, method) in class_file.methods.iter().enumerate() {
+		let name = String::from_utf8_lossy(method.name.as_bytes());
+
+		if class_file.methods[..i].iter().any(|other| other.name == method.name && other.descriptor == method.descriptor) {
+			v.report("4.6", format!("method {name} is declared twice with the same descriptor"));
+		}
+
+		if options.check_names {
+			if let Err(e) = method.name.check() {
+				v.report("4.6", format!("invalid method name: {e}"));
+			}
+			check_method_descriptor_names(&mut v, &method.descriptor);
+		}
+
+		let flags = &method.access_flags;
+		let is_init = method.name == INIT;
+		let is_clinit = method.name == CLINIT;
+
+		if class_file.access_flags.is_interface && !is_clinit {
+			if flags.is_protected || flags.is_final || flags.is_synchronised || flags.is_native {
+				v.report("4.6", format!(
+					"method {name} of an interface must not have ACC_PROTECTED, ACC_FINAL, ACC_SYNCHRONIZED or ACC_NATIVE"
+				));
+			}
+			if class_file.major_version < 52 {
+				if !(flags.is_public && flags.is_abstract) {
+					v.report("4.6", format!("method {name} of an interface must have ACC_PUBLIC and ACC_ABSTRACT before version 52.0"));
+				}
+			} else if flags.is_public == flags.is_private {
+				v.report("4.6", format!("method {name} of an interface must have exactly one of ACC_PUBLIC and ACC_PRIVATE"));
+			}
+		}
+
+		if is_init {
+			if class_file.access_flags.is_interface {
+				v.report("2.9.1", "interfaces must not have an instance initialization method");
+			}
+			if flags.is_static || flags.is_final || flags.is_synchronised || flags.is_native || flags.is_bridge || flags.is_abstract {
+				v.report("4.6", format!(
+					"instance initialization method {name} may only have ACC_VARARGS, ACC_STRICT and ACC_SYNTHETIC besides one of ACC_PUBLIC, \
+					ACC_PRIVATE and ACC_PROTECTED"
+				));
+			}
+			if method.descriptor.return_type.is_some() {
+				v.report("2.9.1", "instance initialization methods must return void");
+			}
+		}
+
+		let parameter_slots: usize = method.descriptor.parameters.iter()
+			.map(|parameter| match parameter {
+				FieldDescriptor { array_dimension: 0, base_type: BaseOrObjectType::D | BaseOrObjectType::J } => 2,
+				_ => 1,
+			})
+			.sum::<usize>() + if flags.is_static { 0 } else { 1 };
+		if parameter_slots > 255 {
+			v.report("4.3.3", format!("method {name} has parameters with a total length of {parameter_slots}, but at most 255 are allowed"));
+		}
+
+		check_attribute_locations(&mut v, "method", &name, &method.attributes);
+		check_attribute_duplicates(&mut v, "method", &name, &method.attributes);
+		if let Some(code) = &method.code {
+			check_attribute_locations(&mut v, "code of method", &name, &code.attributes);
+		}
+	}
+
+	let name = String::from_utf8_lossy(class_file.this_class.as_bytes());
+	check_attribute_locations(&mut v, "class", &name, &class_file.attributes);
+	check_attribute_duplicates(&mut v, "class", &name, &class_file.attributes);
+
+	v.inner
+}
+
+fn check_super_class(v: &mut Violations, class_file: &ClassFile) {
+	match &class_file.super_class {
+		None if class_file.this_class != JAVA_LANG_OBJECT => {
+			v.report("4.1", "only java/lang/Object may have no super class");
+		},
+		Some(super_class) if class_file.access_flags.is_interface && super_class != JAVA_LANG_OBJECT => {
+			v.report("4.1", format!("the super class of an interface must be java/lang/Object, got {}", String::from_utf8_lossy(super_class.as_bytes())));
+		},
+		_ => {},
+	}
+}
+
+fn constant_value_matches(constant_value: &ConstantValueAttribute, descriptor: &FieldDescriptor) -> bool {
+	use BaseOrObjectType::*;
+	match (constant_value, descriptor) {
+		(ConstantValueAttribute::Long(_), FieldDescriptor { array_dimension: 0, base_type: J }) => true,
+		(ConstantValueAttribute::Float(_), FieldDescriptor { array_dimension: 0, base_type: F }) => true,
+		(ConstantValueAttribute::Double(_), FieldDescriptor { array_dimension: 0, base_type: D }) => true,
+		(ConstantValueAttribute::Integer(_), FieldDescriptor { array_dimension: 0, base_type: I | S | C | B | Z }) => true,
+		(ConstantValueAttribute::String(_), FieldDescriptor { array_dimension: 0, base_type: Object(name) }) => name == &b"java/lang/String"[..],
+		_ => false,
+	}
+}
+
+/// Checks that every predefined attribute only appears at the locations given in table 4.7-C. Unknown attributes may appear everywhere.
+fn check_attribute_locations(v: &mut Violations, location: &str, name: &str, attributes: &[AttributeInfo]) {
+	for attribute in attributes {
+		use AttributeInfo::*;
+		let allowed = matches!((location, attribute),
+			(_, Unknown { .. })
+			| ("class" | "field" | "method", Synthetic(_) | Deprecated(_) | Signature(_) | RuntimeVisibleAnnotations(_) | RuntimeInvisibleAnnotations(_))
+			| ("class", SourceFile(_) | InnerClasses(_) | EnclosingMethod(_) | SourceDebugExtension(_) | BootstrapMethods(_))
+			| ("field", ConstantValue(_))
+			| ("method", Code(_) | Exceptions(_) | RuntimeVisibleParameterAnnotations(_) | RuntimeInvisibleParameterAnnotations(_)
+				| AnnotationDefault(_) | MethodParameters(_))
+			| ("code of method", LineNumberTable(_) | LocalVariableTable(_) | LocalVariableTypeTable(_) | StackMapTable(_))
+		);
+
+		if !allowed {
+			let attribute_name = format!("{attribute:?}");
+			let attribute_name = attribute_name.split(['(', ' ']).next().unwrap_or_default();
+			v.report("4.7", format!("the {location} {name} must not have a {attribute_name} attribute"));
+		}
+	}
+}
+
+/// Checks that the attributes of which there may be at most one in an `attributes` table appear only once.
+fn check_attribute_duplicates(v: &mut Violations, location: &str, name: &str, attributes: &[AttributeInfo]) {
+	use AttributeInfo::*;
+	let unique = |attribute: &AttributeInfo| -> Option<(&'static str, &'static str)> {
+		Some(match attribute {
+			Exceptions(_) => ("Exceptions", "4.7.5"),
+			InnerClasses(_) => ("InnerClasses", "4.7.6"),
+			EnclosingMethod(_) => ("EnclosingMethod", "4.7.7"),
+			Signature(_) => ("Signature", "4.7.9"),
+			SourceFile(_) => ("SourceFile", "4.7.10"),
+			SourceDebugExtension(_) => ("SourceDebugExtension", "4.7.11"),
+			RuntimeVisibleAnnotations(_) => ("RuntimeVisibleAnnotations", "4.7.16"),
+			RuntimeInvisibleAnnotations(_) => ("RuntimeInvisibleAnnotations", "4.7.17"),
+			RuntimeVisibleParameterAnnotations(_) => ("RuntimeVisibleParameterAnnotations", "4.7.18"),
+			RuntimeInvisibleParameterAnnotations(_) => ("RuntimeInvisibleParameterAnnotations", "4.7.19"),
+			AnnotationDefault(_) => ("AnnotationDefault", "4.7.20"),
+			BootstrapMethods(_) => ("BootstrapMethods", "4.7.21"),
+			MethodParameters(_) => ("MethodParameters", "4.7.22"),
+			_ => return None,
+		})
+	};
+
+	for (i, attribute) in attributes.iter().enumerate() {
+		let Some((attribute_name, section)) = unique(attribute) else { continue };
+		if attributes[..i].iter().any(|other| unique(other).is_some_and(|(other, _)| other == attribute_name)) {
+			v.report(section, format!("the {location} {name} must not have more than one {attribute_name} attribute"));
+		}
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::{ClassFile, FieldInfo};
+	use crate::access::{ClassInfoAccess, FieldInfoAccess};
+	use crate::cp::Pool;
+	use crate::cp::attribute::{AttributeInfo, SignatureAttribute, SourceFileAttribute};
+	use crate::descriptor::FieldDescriptor;
+	use crate::name::{ClassName, FieldName};
+	use super::{check_class, check_pool, is_modified_utf8, FormatCheckOptions};
+
+	fn utf8(s: &[u8]) -> Vec<u8> {
+		let mut vec = vec![1, 0, s.len() as u8];
+		vec.extend_from_slice(s);
+		vec
+	}
+
+	fn class_file() -> ClassFile {
+		ClassFile {
+			minor_version: 0,
+			major_version: 52,
+			access_flags: ClassInfoAccess::parse(0x0021).unwrap(),
+			this_class: ClassName::from(&b"Foo"[..]),
+			super_class: Some(ClassName::from(&b"java/lang/Object"[..])),
+			interfaces: Vec::new(),
+			fields: Vec::new(),
+			methods: Vec::new(),
+			attributes: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn modified_utf8() {
+		assert!(is_modified_utf8(b"java/lang/Object"));
+		assert!(is_modified_utf8(&[0xc0, 0x80])); // the encoding of '\0'
+		assert!(!is_modified_utf8(&[0x00]));
+		assert!(!is_modified_utf8(&[0xf0, 0x90, 0x80, 0x80]));
+		assert!(!is_modified_utf8(&[0xe0, 0x80]));
+	}
+
+	#[test]
+	fn pool_cross_references() {
+		let mut bytes = vec![0, 9];
+		bytes.extend(utf8(b"Foo")); // 1
+		bytes.extend(utf8(b"<init>")); // 2
+		bytes.extend(utf8(b"()V")); // 3
+		bytes.extend([12, 0, 2, 0, 3]); // 4: NameAndType <init> ()V
+		bytes.extend([9, 0, 1, 0, 4]); // 5: FieldRef with a class index pointing to a Utf8, and a method descriptor
+		bytes.extend([15, 5, 0, 7]); // 6: MethodHandle REF_invokeVirtual of <init>
+		bytes.extend([10, 0, 8, 0, 4]); // 7: MethodRef Foo.<init>()V
+		bytes.extend([7, 0, 1]); // 8: Class Foo
+		let pool = Pool::parse(&mut &bytes[..]).unwrap();
+
+		let violations = check_pool(&pool, &class_file(), &FormatCheckOptions::default());
+		let sections: Vec<_> = violations.iter().map(|violation| violation.section).collect();
+		assert_eq!(sections, vec!["4.4.2", "4.4.2", "4.4.8"], "{violations:#?}");
+	}
+
+	#[test]
+	fn pool_long_takes_two_entries() {
+		let mut bytes = vec![0, 4];
+		bytes.extend([5, 0, 0, 0, 0, 0, 0, 0, 1]); // 1, 2: Long
+		bytes.extend([8, 0, 2]); // 3: String referencing the unusable entry
+		let pool = Pool::parse(&mut &bytes[..]).unwrap();
+
+		assert_eq!(pool.len(), 4);
+		let violations = check_pool(&pool, &class_file(), &FormatCheckOptions::default());
+		assert_eq!(violations.len(), 1, "{violations:#?}");
+		assert_eq!(violations[0].section, "4.4.3");
+	}
+
+	#[test]
+	fn pool_modules_and_packages() {
+		let mut bytes = vec![0, 9];
+		bytes.extend(utf8(b"java.base")); // 1
+		bytes.extend(utf8(b"a:b")); // 2
+		bytes.extend(utf8(b"java/lang")); // 3
+		bytes.extend(utf8(b"java//lang")); // 4
+		bytes.extend([19, 0, 1]); // 5: Module java.base
+		bytes.extend([19, 0, 2]); // 6: Module with an unescaped ':'
+		bytes.extend([20, 0, 3]); // 7: Package java/lang
+		bytes.extend([20, 0, 4]); // 8: Package with an empty identifier
+		let pool = Pool::parse(&mut &bytes[..]).unwrap();
+
+		let mut class_file = class_file();
+		class_file.major_version = 53;
+		let violations = check_pool(&pool, &class_file, &FormatCheckOptions::default());
+		let sections: Vec<_> = violations.iter().map(|violation| violation.section).collect();
+		assert_eq!(sections, vec!["4.4.11", "4.4.12"], "{violations:#?}");
+		assert!(check_pool(&pool, &class_file, &FormatCheckOptions { check_names: false }).is_empty());
+
+		// module and package entries need version 53.0
+		class_file.major_version = 52;
+		assert_eq!(check_pool(&pool, &class_file, &FormatCheckOptions { check_names: false }).len(), 4);
+	}
+
+	#[test]
+	fn interface_fields() {
+		let mut class_file = class_file();
+		class_file.access_flags = ClassInfoAccess::parse(0x0601).unwrap();
+		class_file.fields.push(FieldInfo {
+			access_flags: FieldInfoAccess::parse(0x0001).unwrap(),
+			name: FieldName::from(&b"x"[..]),
+			descriptor: FieldDescriptor::try_from(&b"I"[..]).unwrap(),
+			attributes: Vec::new(),
+			constant_value: None,
+		});
+		class_file.fields.push(class_file.fields[0].clone());
+
+		let violations = check_class(&class_file, &FormatCheckOptions::default());
+		let sections: Vec<_> = violations.iter().map(|violation| violation.section).collect();
+		assert_eq!(sections, vec!["4.5", "4.5", "4.5"], "{violations:#?}");
+	}
+
+	#[test]
+	fn names() {
+		let mut class_file = class_file();
+		class_file.fields.push(FieldInfo {
+			access_flags: FieldInfoAccess::parse(0x0001).unwrap(),
+			name: FieldName::from(&b"a.b"[..]),
+			descriptor: FieldDescriptor::try_from(&b"[Lfoo.Bar;"[..]).unwrap(),
+			attributes: Vec::new(),
+			constant_value: None,
+		});
+
+		let violations = check_class(&class_file, &FormatCheckOptions::default());
+		let sections: Vec<_> = violations.iter().map(|violation| violation.section).collect();
+		assert_eq!(sections, vec!["4.5", "4.3.2"], "{violations:#?}");
+
+		let violations = check_class(&class_file, &FormatCheckOptions { check_names: false });
+		assert_eq!(violations, Vec::new());
+	}
+
+	#[test]
+	fn duplicate_attributes() {
+		let source_file = AttributeInfo::SourceFile(SourceFileAttribute { sourcefile: b"Foo.java".to_vec() });
+		let signature = AttributeInfo::Signature(SignatureAttribute { signature: b"<T:Ljava/lang/Object;>Ljava/lang/Object;".to_vec() });
+		let mut class_file = class_file();
+		class_file.attributes = vec![source_file.clone(), signature, source_file.clone(), source_file];
+
+		let violations = check_class(&class_file, &FormatCheckOptions::default());
+		let sections: Vec<_> = violations.iter().map(|violation| violation.section).collect();
+		assert_eq!(sections, vec!["4.7.10", "4.7.10"], "{violations:#?}");
+		assert_eq!(class_file.source_file(), Some(&b"Foo.java"[..]));
+	}
+}
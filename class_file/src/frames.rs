@@ -0,0 +1,483 @@
+//! Computing the `StackMapTable` of a method, which the type checking verifier needs for class files of version 50.0 and later.
+//!
+//! The verification types of the locals and the operand stack are inferred by a forward dataflow analysis over [Frame]s of [VerificationTypeInfo]s.
+//! Where control flow merges, two class types become their common superclass, looked up in a [ClassHierarchy]. Like the verifier, interfaces are
+//! treated as `java/lang/Object`, and so are classes missing from the hierarchy: it should hold the whole class path of the class, including the
+//! JDK classes it uses.
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use core::iter::once;
+use crate::MethodInfo;
+use crate::cfg::ControlFlowGraph;
+use crate::cp::attribute::{CodeAttribute, ExceptionTableEntry, StackMapFrame, VerificationTypeInfo};
+use crate::dataflow::{solve_with_cfg, Analysis, DataflowResults, Direction};
+use crate::dataflow::frame::{Frame, Interpreter, ValueKind};
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+use crate::hierarchy::ClassHierarchy;
+use crate::instruction::{Instruction, Instructions};
+use crate::instruction::edit::CodeEdit;
+use crate::instruction::opcode::{ArrayType, Opcode};
+use crate::name::{ClassName, MethodName};
+
+const OBJECT: &[u8] = b"java/lang/Object";
+
+type Types = Option<Frame<VerificationTypeInfo>>;
+
+/// Computes the stack map frames of `method`, a method of `class`, replacing the ones it has. `max_stack` is computed as well.
+///
+/// Code that can't be reached is removed first, as there is no frame it could be verified with. Methods without code are left alone.
+pub fn compute_frames(hierarchy: &ClassHierarchy, class: &ClassName, method: &mut MethodInfo) -> Result<()> {
+	let Some(code) = &mut method.code else {
+		return Ok(());
+	};
+	let interpreter = TypeInterpreter { hierarchy };
+	let entry = entry_frame(class, method.name, &method.descriptor, method.access_flags.is_static, code.max_locals as usize)?;
+	code.stack_map_table.entries.clear();
+
+	let mut frames = solve(&interpreter, class, &entry, code)?;
+	let unreachable: Vec<usize> = (0..code.code.len()).filter(|&index| frames.before(index).is_none()).collect();
+	if !unreachable.is_empty() {
+		let mut edit = CodeEdit::new();
+		for index in unreachable {
+			edit.remove(index);
+		}
+		edit.apply(code)?;
+		frames = solve(&interpreter, class, &entry, code)?;
+	}
+
+	let depth = |frame: &Types| frame.as_ref().map_or(0, |frame| frame.stack.iter().map(|value| interpreter.size(value)).sum::<usize>());
+	let max_stack = (0..code.code.len())
+		.map(|index| depth(frames.before(index)).max(depth(frames.after(index))))
+		.max()
+		.unwrap_or(0);
+	code.max_stack = u16::try_from(max_stack).map_err(|_| anyhow!("operand stack of depth {max_stack} is too deep"))?;
+	code.stack_map_table.entries = stack_map(code, &frames, &entry);
+	Ok(())
+}
+
+fn solve(interpreter: &TypeInterpreter, class: &ClassName, entry: &Frame<VerificationTypeInfo>, code: &CodeAttribute) -> Result<DataflowResults<Types>> {
+	let cfg = ControlFlowGraph::from_instructions(&code.code, &code.exception_table)?;
+	let analysis = TypeAnalysis { interpreter, instructions: &code.code, class, entry };
+	solve_with_cfg(&analysis, &code.code, &code.exception_table, &cfg)
+}
+
+fn entry_frame(class: &ClassName, name: MethodName, descriptor: &MethodDescriptor, is_static: bool, max_locals: usize) -> Result<Frame<VerificationTypeInfo>> {
+	let mut locals = Vec::with_capacity(max_locals);
+	if !is_static {
+		// the receiver of a constructor is initialized by calling the constructor of the superclass, except in java/lang/Object itself
+		if name.as_bytes() == b"<init>" && class.as_bytes() != OBJECT {
+			locals.push(VerificationTypeInfo::UninitializedThis);
+		} else {
+			locals.push(VerificationTypeInfo::Object(*class));
+		}
+	}
+	for parameter in &descriptor.parameters {
+		locals.push(of_descriptor(parameter));
+		if ValueKind::of(parameter).size() == 2 {
+			locals.push(VerificationTypeInfo::Top);
+		}
+	}
+	if locals.len() > max_locals {
+		bail!("parameters take {} locals, but max_locals is {max_locals}", locals.len());
+	}
+	locals.resize(max_locals, VerificationTypeInfo::Top);
+	Ok(Frame { locals, stack: Vec::new() })
+}
+
+/// Places a frame at every branch target, every exception handler and every instruction following one that doesn't fall through, each encoded
+/// relative to the one before.
+fn stack_map(code: &CodeAttribute, frames: &DataflowResults<Types>, entry: &Frame<VerificationTypeInfo>) -> Vec<StackMapFrame> {
+	let instructions = &code.code;
+	let positions = frame_positions(code);
+	let mut previous = compact_locals(&entry.locals);
+	let mut entries = Vec::with_capacity(positions.len());
+	for index in positions {
+		let (Some(frame), Some(instruction)) = (frames.before(index), instructions.get(index)) else {
+			continue;
+		};
+		let locals = compact_locals(&frame.locals);
+		entries.push(encode(&previous, instruction.offset(), &locals, &frame.stack));
+		previous = locals;
+	}
+	entries
+}
+
+/// The indices of the instructions that need a stack map frame: the branch targets, the exception handlers and the instructions following one that
+/// doesn't fall through.
+pub(crate) fn frame_positions(code: &CodeAttribute) -> BTreeSet<usize> {
+	let instructions = &code.code;
+	let mut positions = BTreeSet::new();
+	for (index, instruction) in instructions.iter().enumerate() {
+		positions.extend(instruction.opcode().branch_targets().into_iter().filter_map(|target| instructions.index_of(target.0)));
+		if !instruction.opcode().falls_through() && index + 1 < instructions.len() {
+			positions.insert(index + 1);
+		}
+	}
+	positions.extend(code.exception_table.iter().filter_map(|entry| instructions.index_of(entry.handler_pc)));
+	positions
+}
+
+/// Encodes the frame with `locals`, as returned by [compact_locals], and `stack` relative to the locals of the frame before it.
+fn encode(previous: &[VerificationTypeInfo], bytecode_offset: usize, locals: &[VerificationTypeInfo], stack: &[VerificationTypeInfo]) -> StackMapFrame {
+	match stack {
+		[] if locals == previous => StackMapFrame::Same { bytecode_offset },
+		[stack] if locals == previous => StackMapFrame::SameLocals1StackItem { bytecode_offset, stack: stack.clone() },
+		[] if locals.len() < previous.len() && previous.len() - locals.len() <= 3 && previous.starts_with(locals) => {
+			StackMapFrame::Chop { bytecode_offset, k: (previous.len() - locals.len()) as u8 }
+		},
+		[] if locals.len() > previous.len() && locals.len() - previous.len() <= 3 && locals.starts_with(previous) => {
+			StackMapFrame::Append { bytecode_offset, locals: locals[previous.len()..].to_vec() }
+		},
+		_ => StackMapFrame::Full { bytecode_offset, locals: locals.to_vec(), stack: stack.to_vec() },
+	}
+}
+
+/// The locals of the implicit first frame of `method`, a method of `class`, as a stack map frame lists them.
+pub(crate) fn entry_locals(class: &ClassName, method: &MethodInfo) -> Result<Vec<VerificationTypeInfo>> {
+	let max_locals = method.code.as_ref().map_or(0, |code| code.max_locals as usize);
+	let entry = entry_frame(class, method.name, &method.descriptor, method.access_flags.is_static, max_locals)?;
+	Ok(compact_locals(&entry.locals))
+}
+
+/// Turns all `frames` into [StackMapFrame::Full]s, which don't depend on the frame before them, so that they can be moved, removed and inserted.
+/// `entry` are the locals of the implicit first frame, see [entry_locals].
+pub(crate) fn expand_frames(entry: &[VerificationTypeInfo], frames: &[StackMapFrame]) -> Result<Vec<StackMapFrame>> {
+	let mut locals = entry.to_vec();
+	let mut expanded = Vec::with_capacity(frames.len());
+	for frame in frames {
+		let bytecode_offset = frame.get_bytecode_offset();
+		let stack = match frame {
+			StackMapFrame::Same { .. } => Vec::new(),
+			StackMapFrame::SameLocals1StackItem { stack, .. } => alloc::vec![stack.clone()],
+			StackMapFrame::Chop { k, .. } => {
+				let len = locals.len().checked_sub(*k as usize)
+					.ok_or_else(|| anyhow!("frame at offset {bytecode_offset} chops {k} locals, but there are only {}", locals.len()))?;
+				locals.truncate(len);
+				Vec::new()
+			},
+			StackMapFrame::Append { locals: appended, .. } => {
+				locals.extend(appended.iter().cloned());
+				Vec::new()
+			},
+			StackMapFrame::Full { locals: full, stack, .. } => {
+				locals.clone_from(full);
+				stack.clone()
+			},
+		};
+		expanded.push(StackMapFrame::Full { bytecode_offset, locals: locals.clone(), stack });
+	}
+	Ok(expanded)
+}
+
+/// Encodes `frames`, all [StackMapFrame::Full]s as returned by [expand_frames], each relative to the one before again.
+pub(crate) fn compress_frames(entry: &[VerificationTypeInfo], frames: &[StackMapFrame]) -> Vec<StackMapFrame> {
+	let mut previous = entry.to_vec();
+	let mut compressed = Vec::with_capacity(frames.len());
+	for frame in frames {
+		let StackMapFrame::Full { bytecode_offset, locals, stack } = frame else {
+			unreachable!("frames are expanded");
+		};
+		compressed.push(encode(&previous, *bytecode_offset, locals, stack));
+		previous.clone_from(locals);
+	}
+	compressed
+}
+
+/// Returns the locals as a stack map frame lists them: `long` and `double` values stand for the `top` following them, and the `top`s at the end are
+/// left out.
+fn compact_locals(locals: &[VerificationTypeInfo]) -> Vec<VerificationTypeInfo> {
+	let mut compact = Vec::with_capacity(locals.len());
+	let mut index = 0;
+	while let Some(local) = locals.get(index) {
+		compact.push(local.clone());
+		index += match local {
+			VerificationTypeInfo::Long | VerificationTypeInfo::Double => 2,
+			_ => 1,
+		};
+	}
+	while compact.last() == Some(&VerificationTypeInfo::Top) {
+		compact.pop();
+	}
+	compact
+}
+
+fn object(class: &[u8]) -> VerificationTypeInfo {
+	VerificationTypeInfo::Object(ClassName::from(class))
+}
+
+fn of_kind(kind: ValueKind) -> VerificationTypeInfo {
+	match kind {
+		ValueKind::Int => VerificationTypeInfo::Integer,
+		ValueKind::Float => VerificationTypeInfo::Float,
+		ValueKind::Long => VerificationTypeInfo::Long,
+		ValueKind::Double => VerificationTypeInfo::Double,
+		ValueKind::Reference => object(OBJECT),
+	}
+}
+
+fn of_descriptor(descriptor: &FieldDescriptor) -> VerificationTypeInfo {
+	match &descriptor.base_type {
+		_ if descriptor.array_dimension > 0 => object(&descriptor.to_bytes()),
+		BaseOrObjectType::Object(class) => VerificationTypeInfo::Object(*class),
+		_ => of_kind(ValueKind::of(descriptor)),
+	}
+}
+
+fn of_return_type(descriptor: &MethodDescriptor, kind: ValueKind) -> VerificationTypeInfo {
+	descriptor.return_type.as_ref().map_or_else(|| of_kind(kind), of_descriptor)
+}
+
+/// The name of the array class with the elements of class `class`.
+fn array_of(class: &ClassName) -> ClassName {
+	let class = class.as_bytes();
+	if class.starts_with(b"[") {
+		ClassName::from(&[b"[", class].concat()[..])
+	} else {
+		ClassName::from(&[b"[L", class, b";"].concat()[..])
+	}
+}
+
+/// The class of the elements of the array class `class`, or `None` if it isn't an array of references.
+fn reference_component(class: &ClassName) -> Option<ClassName> {
+	match class.as_bytes().strip_prefix(b"[")? {
+		component @ [b'[', ..] => Some(ClassName::from(component)),
+		[b'L', component @ .., b';'] => Some(ClassName::from(component)),
+		_ => None,
+	}
+}
+
+/// An [Interpreter] tracking the verification type of values. `new` and the initialization of objects are left to [TypeAnalysis], as they depend on
+/// the offset of instructions.
+struct TypeInterpreter<'a> {
+	hierarchy: &'a ClassHierarchy,
+}
+
+impl TypeInterpreter<'_> {
+	fn common_superclass(&self, a: &ClassName, b: &ClassName) -> ClassName {
+		if a == b {
+			return *a;
+		}
+		let is_array = |class: &ClassName| class.as_bytes().starts_with(b"[");
+		let is_interface = |class: &ClassName| self.hierarchy.get(class).is_some_and(|class| class.is_interface());
+		match (is_array(a), is_array(b)) {
+			(true, true) => match (reference_component(a), reference_component(b)) {
+				(Some(a), Some(b)) => array_of(&self.common_superclass(&a, &b)),
+				_ => ClassName::from(OBJECT),
+			},
+			(false, false) if !is_interface(a) && !is_interface(b) => {
+				let superclasses: Vec<ClassName> = once(*a).chain(self.hierarchy.superclasses(a)).collect();
+				once(*b).chain(self.hierarchy.superclasses(b))
+					.find(|class| superclasses.contains(class))
+					.unwrap_or_else(|| ClassName::from(OBJECT))
+			},
+			_ => ClassName::from(OBJECT),
+		}
+	}
+}
+
+impl Interpreter for TypeInterpreter<'_> {
+	type Value = VerificationTypeInfo;
+
+	fn unknown(&self, kind: Option<ValueKind>) -> VerificationTypeInfo {
+		kind.map_or(VerificationTypeInfo::Top, of_kind)
+	}
+
+	fn size(&self, value: &VerificationTypeInfo) -> usize {
+		match value {
+			VerificationTypeInfo::Long | VerificationTypeInfo::Double => 2,
+			_ => 1,
+		}
+	}
+
+	fn parameter(&self, kind: ValueKind, _is_this: bool) -> VerificationTypeInfo {
+		of_kind(kind)
+	}
+
+	fn caught_exception(&self, entry: &ExceptionTableEntry) -> VerificationTypeInfo {
+		entry.catch_type.map_or_else(|| object(b"java/lang/Throwable"), VerificationTypeInfo::Object)
+	}
+
+	fn constant(&self, opcode: &Opcode, kind: ValueKind) -> VerificationTypeInfo {
+		match opcode {
+			Opcode::AConstNull => VerificationTypeInfo::Null,
+			Opcode::LdcReferenceString(_) => object(b"java/lang/String"),
+			Opcode::LdcReferenceClass(_) => object(b"java/lang/Class"),
+			Opcode::LdcReferenceMethodType(_) => object(b"java/lang/invoke/MethodType"),
+			Opcode::LdcReferenceMethodHandle(_) => object(b"java/lang/invoke/MethodHandle"),
+			Opcode::GetStatic(field) => of_descriptor(&field.descriptor),
+			_ => of_kind(kind),
+		}
+	}
+
+	fn unary(&self, opcode: &Opcode, _value: &VerificationTypeInfo, kind: ValueKind) -> VerificationTypeInfo {
+		match opcode {
+			Opcode::CheckCast(class) => VerificationTypeInfo::Object(*class),
+			Opcode::ANewArray(class) => VerificationTypeInfo::Object(array_of(class)),
+			Opcode::NewArray { a_type } => object(match a_type {
+				ArrayType::Boolean => b"[Z",
+				ArrayType::Char => b"[C",
+				ArrayType::Float => b"[F",
+				ArrayType::Double => b"[D",
+				ArrayType::Byte => b"[B",
+				ArrayType::Short => b"[S",
+				ArrayType::Int => b"[I",
+				ArrayType::Long => b"[J",
+			}),
+			Opcode::GetField(field) => of_descriptor(&field.descriptor),
+			_ => of_kind(kind),
+		}
+	}
+
+	fn binary(&self, opcode: &Opcode, value1: &VerificationTypeInfo, _value2: &VerificationTypeInfo, kind: ValueKind) -> VerificationTypeInfo {
+		match (opcode, value1) {
+			(Opcode::AALoad, VerificationTypeInfo::Null) => VerificationTypeInfo::Null,
+			(Opcode::AALoad, VerificationTypeInfo::Object(array)) => reference_component(array).map_or_else(|| of_kind(kind), VerificationTypeInfo::Object),
+			_ => of_kind(kind),
+		}
+	}
+
+	fn nary(&self, opcode: &Opcode, _values: &[VerificationTypeInfo], kind: ValueKind) -> VerificationTypeInfo {
+		match opcode {
+			Opcode::InvokeVirtual(method) | Opcode::InvokeSpecial(method) | Opcode::InvokeStatic(method) => of_return_type(&method.descriptor, kind),
+			Opcode::InvokeInterface { method_ref, .. } => of_return_type(&method_ref.descriptor, kind),
+			Opcode::InvokeDynamic { call_site, .. } => of_return_type(&call_site.descriptor, kind),
+			Opcode::MultiANewArray(class, _) => VerificationTypeInfo::Object(*class),
+			_ => of_kind(kind),
+		}
+	}
+
+	fn merge(&self, value1: &VerificationTypeInfo, value2: &VerificationTypeInfo) -> VerificationTypeInfo {
+		match (value1, value2) {
+			(value1, value2) if value1 == value2 => value1.clone(),
+			(VerificationTypeInfo::Null, VerificationTypeInfo::Object(class)) | (VerificationTypeInfo::Object(class), VerificationTypeInfo::Null) => {
+				VerificationTypeInfo::Object(*class)
+			},
+			(VerificationTypeInfo::Object(class1), VerificationTypeInfo::Object(class2)) => {
+				VerificationTypeInfo::Object(self.common_superclass(class1, class2))
+			},
+			_ => VerificationTypeInfo::Top,
+		}
+	}
+}
+
+/// The forward analysis of the types, tracking objects created by `new` until their constructor is called.
+struct TypeAnalysis<'a> {
+	interpreter: &'a TypeInterpreter<'a>,
+	instructions: &'a Instructions,
+	class: &'a ClassName,
+	entry: &'a Frame<VerificationTypeInfo>,
+}
+
+impl TypeAnalysis<'_> {
+	/// Replaces the uninitialized receiver of a constructor call, taking `parameters` arguments, by the class it is now an instance of.
+	fn initialize(&self, frame: &mut Frame<VerificationTypeInfo>, parameters: usize) -> Result<()> {
+		let receiver = frame.stack.len().checked_sub(parameters + 1)
+			.and_then(|index| frame.stack.get(index))
+			.ok_or_else(|| anyhow!("operand stack underflow"))?
+			.clone();
+		let initialized = match receiver {
+			VerificationTypeInfo::UninitializedThis => VerificationTypeInfo::Object(*self.class),
+			VerificationTypeInfo::Uninitialized { bytecode_offset } => {
+				match self.instructions.index_of(bytecode_offset).and_then(|index| self.instructions.get(index)).map(Instruction::opcode) {
+					Some(Opcode::New(class)) => VerificationTypeInfo::Object(*class),
+					_ => bail!("uninitialized object of offset {bytecode_offset} doesn't come from a new instruction"),
+				}
+			},
+			_ => return Ok(()),
+		};
+		for value in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+			if *value == receiver {
+				*value = initialized.clone();
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Analysis for TypeAnalysis<'_> {
+	type Fact = Types;
+
+	const DIRECTION: Direction = Direction::Forward;
+
+	fn bottom(&self) -> Types {
+		None
+	}
+
+	fn boundary(&self) -> Types {
+		Some(self.entry.clone())
+	}
+
+	fn join(&self, fact: &mut Types, other: &Types) -> Result<bool> {
+		match (fact.as_mut(), other) {
+			(_, None) => Ok(false),
+			(None, Some(other)) => {
+				*fact = Some(other.clone());
+				Ok(true)
+			},
+			(Some(frame), Some(other)) => frame.merge(self.interpreter, other),
+		}
+	}
+
+	fn transfer(&self, _index: usize, instruction: &Instruction, fact: &mut Types) -> Result<()> {
+		let Some(frame) = fact else {
+			return Ok(());
+		};
+		let result = match instruction.opcode() {
+			Opcode::New(_) => {
+				frame.stack.push(VerificationTypeInfo::Uninitialized { bytecode_offset: instruction.offset() });
+				Ok(())
+			},
+			Opcode::InvokeSpecial(method) if method.name.as_bytes() == b"<init>" => self.initialize(frame, method.descriptor.parameters.len())
+				.and_then(|()| frame.execute(self.interpreter, instruction.opcode())),
+			opcode => frame.execute(self.interpreter, opcode),
+		};
+		result.map_err(|e| e.context(format!("at offset {}", instruction.offset())))
+	}
+
+	fn exception_edge(&self, entry: &ExceptionTableEntry, fact: &Types) -> Types {
+		fact.as_ref().map(|frame| Frame {
+			locals: frame.locals.clone(),
+			stack: alloc::vec![self.interpreter.caught_exception(entry)],
+		})
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use alloc::vec::Vec;
+	use crate::{ClassFile, MethodInfo};
+	use crate::cp::attribute::{StackMapFrame, VerificationTypeInfo};
+	use crate::hierarchy::ClassHierarchy;
+	use super::compute_frames;
+
+	#[test]
+	fn like_javac() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test2.class");
+		let class = ClassFile::parse(&mut &bytes[..]).unwrap();
+		let mut hierarchy = ClassHierarchy::new();
+		hierarchy.add_class_file(&class);
+		let offsets = |method: &MethodInfo| method.code.as_ref().map(|code| {
+			(code.max_stack, code.stack_map_table.entries.iter().map(StackMapFrame::get_bytecode_offset).collect::<Vec<_>>())
+		});
+		for method in &class.methods {
+			let mut computed = method.clone();
+			if let Some(code) = &mut computed.code {
+				code.stack_map_table.entries.clear();
+				code.max_stack = 0;
+			}
+			compute_frames(&hierarchy, &class.this_class, &mut computed).unwrap();
+			assert_eq!(offsets(&computed), offsets(method));
+
+			if method.name.as_bytes() == b"main" {
+				// javac chops the loop variable at the end of the loop, while it is still there for the analysis
+				assert_eq!(computed.code.unwrap().stack_map_table.entries, [
+					StackMapFrame::Append { bytecode_offset: 11, locals: alloc::vec![VerificationTypeInfo::Integer] },
+					StackMapFrame::Same { bytecode_offset: 32 },
+				]);
+			}
+		}
+	}
+}
@@ -0,0 +1,624 @@
+//! An index of the classes and interfaces of a class path, answering questions about their hierarchy without keeping the class files around: the
+//! supertypes and subtypes of a class, which method a method reference resolves to, which methods override each other and where fields are declared.
+
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::format;
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+use crate::{ClassFile, MyRead};
+use crate::access::{ClassInfoAccess, FieldInfoAccess, MethodInfoAccess};
+use crate::deps::package;
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+use crate::lazy::ClassFileRef;
+use crate::name::{ClassName, FieldName, MethodName};
+
+const OBJECT: &[u8] = b"java/lang/Object";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldEntry {
+	pub access_flags: FieldInfoAccess,
+	pub name: FieldName,
+	pub descriptor: FieldDescriptor,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodEntry {
+	pub access_flags: MethodInfoAccess,
+	pub name: MethodName,
+	pub descriptor: MethodDescriptor,
+}
+
+impl MethodEntry {
+	/// Whether the method is an instance or class initialization method, which never override anything.
+	pub fn is_initializer(&self) -> bool {
+		self.name.as_bytes().starts_with(b"<")
+	}
+}
+
+/// What the index knows about a class: the parts of the class file that make up its place in the hierarchy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassEntry {
+	pub access_flags: ClassInfoAccess,
+	pub name: ClassName,
+	pub super_class: Option<ClassName>,
+	pub interfaces: Vec<ClassName>,
+	pub fields: Vec<FieldEntry>,
+	pub methods: Vec<MethodEntry>,
+}
+
+impl ClassEntry {
+	pub fn from_class_file(class: &ClassFile) -> ClassEntry {
+		ClassEntry {
+			access_flags: class.access_flags.clone(),
+			name: class.this_class,
+			super_class: class.super_class,
+			interfaces: class.interfaces.clone(),
+			fields: class.fields.iter()
+				.map(|field| FieldEntry { access_flags: field.access_flags.clone(), name: field.name, descriptor: field.descriptor })
+				.collect(),
+			methods: class.methods.iter()
+				.map(|method| MethodEntry { access_flags: method.access_flags.clone(), name: method.name, descriptor: method.descriptor.clone() })
+				.collect(),
+		}
+	}
+
+	/// Reads the entry from the bytes of a class file, decoding only the parts needed.
+	pub fn from_bytes(bytes: &[u8]) -> Result<ClassEntry> {
+		let class = ClassFileRef::parse(bytes)?;
+		let fields = class.fields()
+			.map(|field| Ok(FieldEntry {
+				access_flags: FieldInfoAccess::parse(field.access_flags())?,
+				name: FieldName::from(field.name()?),
+				descriptor: FieldDescriptor::try_from(field.descriptor()?)?,
+			}))
+			.collect::<Result<_>>()?;
+		let methods = class.methods()
+			.map(|method| Ok(MethodEntry {
+				access_flags: MethodInfoAccess::parse(method.access_flags())?,
+				name: MethodName::from(method.name()?),
+				descriptor: MethodDescriptor::try_from(method.descriptor()?)?,
+			}))
+			.collect::<Result<_>>()?;
+		Ok(ClassEntry {
+			access_flags: class.access_flags()?,
+			name: ClassName::from(class.this_class()?),
+			super_class: class.super_class()?.map(ClassName::from),
+			interfaces: class.interfaces().map(|interface| interface.map(ClassName::from)).collect::<Result<_>>()?,
+			fields,
+			methods,
+		})
+	}
+
+	pub fn is_interface(&self) -> bool {
+		self.access_flags.is_interface
+	}
+
+	pub fn field(&self, name: FieldName, descriptor: &FieldDescriptor) -> Option<&FieldEntry> {
+		self.fields.iter().find(|field| field.name == name && field.descriptor == *descriptor)
+	}
+
+	pub fn method(&self, name: MethodName, descriptor: &MethodDescriptor) -> Option<&MethodEntry> {
+		self.methods.iter().find(|method| method.name == name && method.descriptor == *descriptor)
+	}
+}
+
+/// The hierarchy of many classes. Classes that are referenced but not in the index, like the ones of the JDK when only a jar is indexed, end the
+/// walks through the hierarchy: they show up as supertypes, but nothing is known about their own supertypes or members.
+#[derive(Debug, Clone, Default)]
+pub struct ClassHierarchy {
+	classes: BTreeMap<ClassName, ClassEntry>,
+	/// The classes directly extending a class, and the classes and interfaces directly implementing or extending an interface.
+	direct_subtypes: BTreeMap<ClassName, BTreeSet<ClassName>>,
+}
+
+impl ClassHierarchy {
+	pub fn new() -> ClassHierarchy {
+		ClassHierarchy::default()
+	}
+
+	/// Adds a class to the index. Like on a class path, the first class added with a name wins: returns `false` if a class with the same name was
+	/// added before, leaving that one in place.
+	pub fn add(&mut self, class: ClassEntry) -> bool {
+		if self.classes.contains_key(&class.name) {
+			return false;
+		}
+		for super_type in class.super_class.iter().chain(&class.interfaces) {
+			self.direct_subtypes.entry(*super_type).or_default().insert(class.name);
+		}
+		self.classes.insert(class.name, class);
+		true
+	}
+
+	pub fn add_class_file(&mut self, class: &ClassFile) -> bool {
+		self.add(ClassEntry::from_class_file(class))
+	}
+
+	pub fn add_bytes(&mut self, bytes: &[u8]) -> Result<bool> {
+		Ok(self.add(ClassEntry::from_bytes(bytes)?))
+	}
+
+	/// Adds all classes of a class path entry. Class files that can't be read are skipped, and returned with their name and the reason.
+	#[cfg(feature = "std")]
+	pub fn add_class_path_entry(&mut self, entry: &crate::classpath::ClassPathEntry) -> Result<Vec<(String, anyhow::Error)>> {
+		let mut skipped = Vec::new();
+		for (file_name, bytes) in entry.class_files()? {
+			if let Err(error) = self.add_bytes(&bytes) {
+				skipped.push((file_name, error));
+			}
+		}
+		Ok(skipped)
+	}
+
+	pub fn len(&self) -> usize {
+		self.classes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.classes.is_empty()
+	}
+
+	pub fn get(&self, name: &ClassName) -> Option<&ClassEntry> {
+		self.classes.get(name)
+	}
+
+	pub fn contains(&self, name: &ClassName) -> bool {
+		self.classes.contains_key(name)
+	}
+
+	/// Returns all classes of the index, sorted by name.
+	pub fn classes(&self) -> impl Iterator<Item=&ClassEntry> {
+		self.classes.values()
+	}
+
+	/// Returns the superclass chain of a class, starting with its direct superclass. The chain ends with `java/lang/Object`, or with the first class
+	/// that isn't in the index.
+	pub fn superclasses(&self, name: &ClassName) -> Vec<ClassName> {
+		let mut superclasses = Vec::new();
+		let mut current = self.get(name).and_then(|class| class.super_class);
+		while let Some(class) = current {
+			// guard against malformed, circular hierarchies
+			if superclasses.contains(&class) || class == *name {
+				break;
+			}
+			superclasses.push(class);
+			current = self.get(&class).and_then(|class| class.super_class);
+		}
+		superclasses
+	}
+
+	/// Returns all superclasses and superinterfaces of a class, direct or not.
+	pub fn supertypes(&self, name: &ClassName) -> BTreeSet<ClassName> {
+		self.walk(name, |class| self.get(class).into_iter().flat_map(|class| class.super_class.iter().chain(&class.interfaces)).copied().collect())
+	}
+
+	/// Returns the interfaces a class implements, directly, through its superclasses or through other interfaces.
+	pub fn superinterfaces(&self, name: &ClassName) -> BTreeSet<ClassName> {
+		let mut interfaces = self.supertypes(name);
+		for superclass in self.superclasses(name) {
+			interfaces.remove(&superclass);
+		}
+		interfaces.remove(&ClassName::from(OBJECT));
+		interfaces
+	}
+
+	pub fn direct_subtypes(&self, name: &ClassName) -> impl Iterator<Item=&ClassName> {
+		self.direct_subtypes.get(name).into_iter().flatten()
+	}
+
+	/// Returns all classes and interfaces extending or implementing a class or interface, direct or not.
+	pub fn subtypes(&self, name: &ClassName) -> BTreeSet<ClassName> {
+		self.walk(name, |class| self.direct_subtypes(class).copied().collect())
+	}
+
+	/// Returns all classes extending a class, direct or not.
+	pub fn subclasses(&self, name: &ClassName) -> BTreeSet<ClassName> {
+		self.walk(name, |class| self.direct_subtypes(class)
+			.filter(|subclass| self.get(subclass).is_some_and(|subclass| subclass.super_class == Some(*class)))
+			.copied()
+			.collect())
+	}
+
+	/// Returns all classes implementing an interface, either directly, through a subinterface or by extending a class implementing it. Interfaces are
+	/// left out.
+	pub fn implementors(&self, interface: &ClassName) -> BTreeSet<ClassName> {
+		let mut implementors = self.subtypes(interface);
+		implementors.retain(|class| self.get(class).is_some_and(|class| !class.is_interface()));
+		implementors
+	}
+
+	/// Collects the classes reachable from `start` over `next`, leaving out `start`.
+	fn walk(&self, start: &ClassName, next: impl Fn(&ClassName) -> Vec<ClassName>) -> BTreeSet<ClassName> {
+		let mut found = BTreeSet::new();
+		let mut queue = VecDeque::from([*start]);
+		while let Some(class) = queue.pop_front() {
+			for next in next(&class) {
+				if next != *start && found.insert(next) {
+					queue.push_back(next);
+				}
+			}
+		}
+		found
+	}
+
+	/// Whether `class` is `of`, or extends or implements it. Every class and interface is a subtype of `java/lang/Object`.
+	pub fn is_subtype_of(&self, class: &ClassName, of: &ClassName) -> bool {
+		class == of || of.as_bytes() == OBJECT || self.supertypes(class).contains(of)
+	}
+
+	/// Whether a value of type `from` can be assigned to `to`, with the usual rules for arrays: they are covariant in their reference element types,
+	/// and assignable to `java/lang/Object`, `java/lang/Cloneable` and `java/io/Serializable`.
+	pub fn is_assignable(&self, from: &FieldDescriptor, to: &FieldDescriptor) -> bool {
+		if from.array_dimension == to.array_dimension {
+			return match (&from.base_type, &to.base_type) {
+				(BaseOrObjectType::Object(from), BaseOrObjectType::Object(to)) => self.is_subtype_of(from, to),
+				(from, to) => from == to,
+			};
+		}
+		match &to.base_type {
+			BaseOrObjectType::Object(class) if from.array_dimension > to.array_dimension => {
+				matches!(class.as_bytes(), OBJECT | b"java/lang/Cloneable" | b"java/io/Serializable")
+			},
+			_ => false,
+		}
+	}
+
+	/// Resolves a reference to a method, like an `invokevirtual` or `invokeinterface` instruction does (5.4.3.3 and 5.4.3.4): the method is looked up
+	/// in the class itself and its superclasses, for interfaces also in the public methods of `java/lang/Object`, and then in the superinterfaces,
+	/// preferring a maximally-specific default method. Returns the class declaring the method, with the method.
+	pub fn resolve_method(&self, class: &ClassName, name: MethodName, descriptor: &MethodDescriptor) -> Option<(ClassName, &MethodEntry)> {
+		let entry = self.get(class)?;
+		if entry.is_interface() {
+			if let Some(method) = entry.method(name, descriptor) {
+				return Some((*class, method));
+			}
+			let object = ClassName::from(OBJECT);
+			let object_method = self.get(&object)
+				.and_then(|object| object.method(name, descriptor))
+				.filter(|method| method.access_flags.is_public && !method.access_flags.is_static);
+			if let Some(method) = object_method {
+				return Some((object, method));
+			}
+		} else {
+			for class in core::iter::once(*class).chain(self.superclasses(class)) {
+				if let Some(method) = self.get(&class).and_then(|class| class.method(name, descriptor)) {
+					return Some((class, method));
+				}
+			}
+		}
+
+		let candidates = self.superinterface_methods(class, name, descriptor);
+		self.default_method(&candidates).or_else(|| candidates.first().copied())
+	}
+
+	/// Selects the method an `invokevirtual` or `invokeinterface` of the resolved `method` declared in `declaring` invokes on an instance of `receiver`
+	/// (5.4.6): the method of the receiver or its nearest superclass that overrides it, or else the one maximally-specific default method. Returns
+	/// `None` if that would throw an `AbstractMethodError` or an `IncompatibleClassChangeError`.
+	pub fn select_method<'a>(&'a self, receiver: &ClassName, declaring: &ClassName, method: &'a MethodEntry) -> Option<(ClassName, &'a MethodEntry)> {
+		if method.access_flags.is_private {
+			return Some((*declaring, method));
+		}
+		for class in core::iter::once(*receiver).chain(self.superclasses(receiver)) {
+			let selected = if class == *declaring {
+				Some(method)
+			} else {
+				self.get(&class)
+					.and_then(|entry| entry.method(method.name, &method.descriptor))
+					.filter(|candidate| self.overrides(&class, candidate, declaring, method))
+			};
+			if let Some(selected) = selected {
+				return (!selected.access_flags.is_abstract).then_some((class, selected));
+			}
+		}
+		self.default_method(&self.superinterface_methods(receiver, method.name, &method.descriptor))
+	}
+
+	/// Returns the instance methods with the name and descriptor the superinterfaces of `class` declare.
+	fn superinterface_methods(&self, class: &ClassName, name: MethodName, descriptor: &MethodDescriptor) -> Vec<(ClassName, &MethodEntry)> {
+		self.superinterfaces(class).into_iter()
+			.filter_map(|interface| Some((interface, self.get(&interface)?.method(name, descriptor)?)))
+			.filter(|(_, method)| !method.access_flags.is_private && !method.access_flags.is_static)
+			.collect()
+	}
+
+	/// Returns the one non-abstract method among the maximally-specific ones of `candidates`, those whose interface has no subinterface declaring the
+	/// method too.
+	fn default_method<'a>(&self, candidates: &[(ClassName, &'a MethodEntry)]) -> Option<(ClassName, &'a MethodEntry)> {
+		let mut non_abstract = candidates.iter()
+			.filter(|(interface, _)| !candidates.iter().any(|(other, _)| other != interface && self.is_subtype_of(other, interface)))
+			.filter(|(_, method)| !method.access_flags.is_abstract);
+		match (non_abstract.next(), non_abstract.next()) {
+			(Some(&selected), None) => Some(selected),
+			_ => None,
+		}
+	}
+
+	/// Resolves a reference to a field, like a `getfield` or `getstatic` instruction does (5.4.3.2): the field is looked up in the class itself, then
+	/// in its direct superinterfaces and theirs, then in its superclass. Returns the class declaring the field, with the field.
+	pub fn resolve_field(&self, class: &ClassName, name: FieldName, descriptor: &FieldDescriptor) -> Option<(ClassName, &FieldEntry)> {
+		self.resolve_field_in(class, name, descriptor, &mut BTreeSet::new())
+	}
+
+	fn resolve_field_in(&self, class: &ClassName, name: FieldName, descriptor: &FieldDescriptor, visited: &mut BTreeSet<ClassName>) -> Option<(ClassName, &FieldEntry)> {
+		if !visited.insert(*class) {
+			return None;
+		}
+		let entry = self.get(class)?;
+		if let Some(field) = entry.field(name, descriptor) {
+			return Some((*class, field));
+		}
+		for interface in &entry.interfaces {
+			if let Some(found) = self.resolve_field_in(interface, name, descriptor, visited) {
+				return Some(found);
+			}
+		}
+		self.resolve_field_in(entry.super_class.as_ref()?, name, descriptor, visited)
+	}
+
+	/// Returns all classes declaring a field with the name, with the field.
+	pub fn field_declarations(&self, name: FieldName) -> Vec<(ClassName, &FieldEntry)> {
+		self.classes.values()
+			.flat_map(|class| class.fields.iter().filter(move |field| field.name == name).map(move |field| (class.name, field)))
+			.collect()
+	}
+
+	/// Whether `method` declared in `class` overrides `overridden` declared in its supertype `declaring` (5.4.5). Package-private methods are only
+	/// overridden from the same package, or by overriding a method that overrides them from there.
+	fn overrides(&self, class: &ClassName, method: &MethodEntry, declaring: &ClassName, overridden: &MethodEntry) -> bool {
+		if method.name != overridden.name || method.descriptor != overridden.descriptor || method.is_initializer()
+			|| method.access_flags.is_private || method.access_flags.is_static
+			|| overridden.access_flags.is_private || overridden.access_flags.is_static {
+			return false;
+		}
+		let is_interface = self.get(declaring).is_some_and(ClassEntry::is_interface);
+		if is_interface || overridden.access_flags.is_public || overridden.access_flags.is_protected || package(class) == package(declaring) {
+			return true;
+		}
+		self.superclasses(class).iter()
+			.take_while(|&intermediate| intermediate != declaring)
+			.filter_map(|intermediate| Some((intermediate, self.get(intermediate)?.method(method.name, &method.descriptor)?)))
+			.any(|(intermediate, between)| {
+				self.overrides(intermediate, between, declaring, overridden) && self.overrides(class, method, intermediate, between)
+			})
+	}
+
+	/// Returns the supertypes declaring a method that the method of `class` overrides, nearest first along the superclass chain, then the interfaces.
+	pub fn overridden_methods(&self, class: &ClassName, name: MethodName, descriptor: &MethodDescriptor) -> Vec<ClassName> {
+		let Some(method) = self.get(class).and_then(|entry| entry.method(name, descriptor)) else {
+			return Vec::new();
+		};
+		self.superclasses(class).into_iter().chain(self.superinterfaces(class))
+			.filter(|declaring| {
+				self.get(declaring)
+					.and_then(|entry| entry.method(name, descriptor))
+					.is_some_and(|overridden| self.overrides(class, method, declaring, overridden))
+			})
+			.collect()
+	}
+
+	/// Returns the subtypes of `class` declaring a method that overrides its method.
+	pub fn overriding_methods(&self, class: &ClassName, name: MethodName, descriptor: &MethodDescriptor) -> BTreeSet<ClassName> {
+		let Some(method) = self.get(class).and_then(|entry| entry.method(name, descriptor)) else {
+			return BTreeSet::new();
+		};
+		let mut overriding = self.subtypes(class);
+		overriding.retain(|subtype| {
+			self.get(subtype)
+				.and_then(|entry| entry.method(name, descriptor))
+				.is_some_and(|overrider| self.overrides(subtype, overrider, class, method))
+		});
+		overriding
+	}
+}
+
+const MAGIC: &[u8; 4] = b"CHIX";
+const VERSION: u16 = 1;
+const NONE: u32 = u32::MAX;
+
+/// Builds the string table while writing, each string is written once and referenced by its index.
+#[derive(Default)]
+struct Writer {
+	strings: Vec<Vec<u8>>,
+	indices: BTreeMap<Vec<u8>, u32>,
+	body: Vec<u8>,
+}
+
+impl Writer {
+	fn u16(&mut self, value: u16) {
+		self.body.extend_from_slice(&value.to_be_bytes());
+	}
+
+	fn u32(&mut self, value: u32) {
+		self.body.extend_from_slice(&value.to_be_bytes());
+	}
+
+	fn string(&mut self, string: &[u8]) {
+		let index = match self.indices.get(string) {
+			Some(&index) => index,
+			None => {
+				let index = self.strings.len() as u32;
+				self.indices.insert(string.to_vec(), index);
+				self.strings.push(string.to_vec());
+				index
+			},
+		};
+		self.u32(index);
+	}
+}
+
+fn take<'a>(reader: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+	if reader.len() < length {
+		bail!("unexpected data end");
+	}
+	let (taken, rest) = reader.split_at(length);
+	*reader = rest;
+	Ok(taken)
+}
+
+impl ClassHierarchy {
+	/// Writes the index in a compact binary form, to be read back with [ClassHierarchy::from_bytes] instead of indexing the classes again.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let mut writer = Writer::default();
+		writer.u32(self.classes.len() as u32);
+		for class in self.classes.values() {
+			writer.u16(class.access_flags.to_flags());
+			writer.string(class.name.as_bytes());
+			match class.super_class {
+				Some(super_class) => writer.string(super_class.as_bytes()),
+				None => writer.u32(NONE),
+			}
+			writer.u16(class.interfaces.len() as u16);
+			for interface in &class.interfaces {
+				writer.string(interface.as_bytes());
+			}
+			writer.u16(class.fields.len() as u16);
+			for field in &class.fields {
+				writer.u16(field.access_flags.to_flags());
+				writer.string(field.name.as_bytes());
+				writer.string(&field.descriptor.to_bytes());
+			}
+			writer.u16(class.methods.len() as u16);
+			for method in &class.methods {
+				writer.u16(method.access_flags.to_flags());
+				writer.string(method.name.as_bytes());
+				writer.string(&method.descriptor.to_bytes());
+			}
+		}
+
+		let mut bytes = Vec::with_capacity(writer.body.len());
+		bytes.extend_from_slice(MAGIC);
+		bytes.extend_from_slice(&VERSION.to_be_bytes());
+		bytes.extend_from_slice(&(writer.strings.len() as u32).to_be_bytes());
+		for string in &writer.strings {
+			bytes.extend_from_slice(&(string.len() as u16).to_be_bytes());
+			bytes.extend_from_slice(string);
+		}
+		bytes.extend_from_slice(&writer.body);
+		bytes
+	}
+
+	/// Reads an index written by [ClassHierarchy::to_bytes].
+	pub fn from_bytes(bytes: &[u8]) -> Result<ClassHierarchy> {
+		let reader = &mut &bytes[..];
+		if take(reader, 4)? != MAGIC {
+			bail!("not a class hierarchy index");
+		}
+		let version = reader.read_u16()?;
+		if version != VERSION {
+			bail!("unsupported class hierarchy index version {version}, expected {VERSION}");
+		}
+		let strings = reader.read_vec(
+			|reader| reader.read_u32_as_usize(),
+			|reader| {
+				let length = reader.read_u16_as_usize()?;
+				take(reader, length)
+			},
+		)?;
+		let string = |reader: &mut &[u8]| -> Result<&[u8]> {
+			let index = reader.read_u32_as_usize()?;
+			strings.get(index).copied().with_context(|| format!("string index {index} out of bounds"))
+		};
+
+		let mut hierarchy = ClassHierarchy::new();
+		let count = reader.read_u32_as_usize()?;
+		for _ in 0..count {
+			let access_flags = ClassInfoAccess::parse(reader.read_u16()?)?;
+			let name = ClassName::from(string(reader)?);
+			let super_class = match reader.read_u32()? {
+				NONE => None,
+				index => Some(ClassName::from(*strings.get(index as usize).context("string index out of bounds")?)),
+			};
+			let interfaces = reader.read_vec(|reader| reader.read_u16_as_usize(), |reader| Ok(ClassName::from(string(reader)?)))?;
+			let fields = reader.read_vec(|reader| reader.read_u16_as_usize(), |reader| Ok(FieldEntry {
+				access_flags: FieldInfoAccess::parse(reader.read_u16()?)?,
+				name: FieldName::from(string(reader)?),
+				descriptor: FieldDescriptor::try_from(string(reader)?)?,
+			}))?;
+			let methods = reader.read_vec(|reader| reader.read_u16_as_usize(), |reader| Ok(MethodEntry {
+				access_flags: MethodInfoAccess::parse(reader.read_u16()?)?,
+				name: MethodName::from(string(reader)?),
+				descriptor: MethodDescriptor::try_from(string(reader)?)?,
+			}))?;
+			hierarchy.add(ClassEntry { access_flags, name, super_class, interfaces, fields, methods });
+		}
+		if !reader.is_empty() {
+			bail!("trailing data after class hierarchy index");
+		}
+		Ok(hierarchy)
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::descriptor::{FieldDescriptor, MethodDescriptor};
+	use crate::fixture;
+	use crate::hierarchy::{ClassEntry, ClassHierarchy};
+	use crate::name::{ClassName, FieldName, MethodName};
+
+	fn class(access_flags: u16, name: &[u8], super_class: &[u8], interfaces: &[&[u8]], fields: &[(u16, &[u8])], methods: &[(u16, &[u8])]) -> ClassEntry {
+		ClassEntry::from_class_file(&fixture::class(access_flags, name, super_class, interfaces,
+			fields.iter().map(|&(access_flags, name)| fixture::field(access_flags, name, b"I")).collect(),
+			methods.iter().map(|&(access_flags, name)| fixture::method(access_flags, name, b"()V")).collect(),
+		))
+	}
+
+	#[test]
+	fn hierarchy_queries() {
+		let object = b"java/lang/Object";
+		let mut hierarchy = ClassHierarchy::new();
+		hierarchy.add(class(0x0601, b"p/I", object, &[], &[], &[(0x0401, b"m")]));
+		hierarchy.add(class(0x0601, b"q/J", object, &[b"p/I"], &[], &[(0x0001, b"m")]));
+		hierarchy.add(class(0x0021, b"p/A", object, &[b"p/I"], &[(0x0000, b"f")], &[(0x0001, b"<init>"), (0x0001, b"m"), (0x0000, b"pkg")]));
+		hierarchy.add(class(0x0021, b"p/B", b"p/A", &[], &[], &[(0x0000, b"pkg")]));
+		hierarchy.add(class(0x0021, b"q/C", b"p/B", &[], &[(0x0008, b"f")], &[(0x0001, b"<init>"), (0x0001, b"m"), (0x0000, b"pkg")]));
+		hierarchy.add(class(0x0021, b"q/D", object, &[b"q/J"], &[], &[]));
+		assert!(!hierarchy.add(class(0x0021, b"q/D", b"p/A", &[], &[], &[])));
+
+		let name = |name: &[u8]| ClassName::from(name);
+		let names = |names: &[&[u8]]| names.iter().map(|&name| ClassName::from(name)).collect::<Vec<_>>();
+		let m = MethodName::from(b"m");
+		let pkg = MethodName::from(b"pkg");
+		let void = MethodDescriptor::try_from(&b"()V"[..]).unwrap();
+		let int = FieldDescriptor::try_from(&b"I"[..]).unwrap();
+
+		assert_eq!(hierarchy.superclasses(&name(b"q/C")), names(&[b"p/B", b"p/A", object]));
+		assert_eq!(hierarchy.superinterfaces(&name(b"q/D")).into_iter().collect::<Vec<_>>(), names(&[b"p/I", b"q/J"]));
+		assert_eq!(hierarchy.subclasses(&name(b"p/A")).into_iter().collect::<Vec<_>>(), names(&[b"p/B", b"q/C"]));
+		assert_eq!(hierarchy.implementors(&name(b"p/I")).into_iter().collect::<Vec<_>>(), names(&[b"p/A", b"p/B", b"q/C", b"q/D"]));
+		assert!(hierarchy.is_subtype_of(&name(b"q/C"), &name(b"p/I")));
+		assert!(!hierarchy.is_subtype_of(&name(b"q/D"), &name(b"p/A")));
+
+		// the default method of the more specific interface is picked
+		assert_eq!(hierarchy.resolve_method(&name(b"q/D"), m, &void).map(|(class, _)| class), Some(name(b"q/J")));
+		assert_eq!(hierarchy.resolve_method(&name(b"p/B"), m, &void).map(|(class, _)| class), Some(name(b"p/A")));
+		assert_eq!(hierarchy.resolve_method(&name(b"q/C"), pkg, &void).map(|(class, _)| class), Some(name(b"q/C")));
+
+		// q/C can't override the package-private methods of p/A and p/B from another package
+		assert_eq!(hierarchy.overriding_methods(&name(b"p/A"), pkg, &void).into_iter().collect::<Vec<_>>(), names(&[b"p/B"]));
+		assert_eq!(hierarchy.overridden_methods(&name(b"q/C"), pkg, &void), names(&[]));
+		assert_eq!(hierarchy.overridden_methods(&name(b"q/C"), m, &void), names(&[b"p/A", b"p/I"]));
+		assert_eq!(hierarchy.overriding_methods(&name(b"p/I"), m, &void).into_iter().collect::<Vec<_>>(), names(&[b"p/A", b"q/C", b"q/J"]));
+		assert!(hierarchy.overriding_methods(&name(b"p/A"), MethodName::from(b"<init>"), &void).is_empty());
+
+		let f = FieldName::from(b"f");
+		assert_eq!(hierarchy.resolve_field(&name(b"p/B"), f, &int).map(|(class, _)| class), Some(name(b"p/A")));
+		assert_eq!(hierarchy.resolve_field(&name(b"q/C"), f, &int).map(|(class, _)| class), Some(name(b"q/C")));
+		assert_eq!(hierarchy.field_declarations(f).into_iter().map(|(class, _)| class).collect::<Vec<_>>(), names(&[b"p/A", b"q/C"]));
+
+		let descriptor = |descriptor: &[u8]| FieldDescriptor::try_from(descriptor).unwrap();
+		assert!(hierarchy.is_assignable(&descriptor(b"[Lq/C;"), &descriptor(b"[Lp/I;")));
+		assert!(hierarchy.is_assignable(&descriptor(b"[[I"), &descriptor(b"[Ljava/lang/Cloneable;")));
+		assert!(!hierarchy.is_assignable(&descriptor(b"[I"), &descriptor(b"[J")));
+		assert!(!hierarchy.is_assignable(&descriptor(b"Lp/A;"), &descriptor(b"Lq/C;")));
+
+		let reloaded = ClassHierarchy::from_bytes(&hierarchy.to_bytes()).unwrap();
+		assert!(reloaded.classes().eq(hierarchy.classes()));
+		assert!(ClassHierarchy::from_bytes(&hierarchy.to_bytes()[..20]).is_err());
+	}
+
+	#[test]
+	fn test3_entry() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test3.class");
+		let class = crate::ClassFile::parse(&mut &bytes[..]).unwrap();
+		assert_eq!(ClassEntry::from_bytes(bytes).unwrap(), ClassEntry::from_class_file(&class));
+	}
+}
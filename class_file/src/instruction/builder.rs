@@ -0,0 +1,489 @@
+//! Writing code without computing offsets by hand.
+//!
+//! A [CodeBuilder] collects instructions, with jumps going to [Label]s that may be placed later on. When building the [CodeAttribute], the instructions
+//! are encoded with [assemble], and the labels of branches, exception handlers, line numbers and local variables are turned into offsets.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use crate::cp::{FieldRefInfo, InterfaceMethodRefInfo, InvokeDynamicInfo, MethodRefInfo};
+use crate::cp::attribute::{AttributeInfo, CodeAttribute, ExceptionTableEntry, LineNumberTableEntry, LocalVariableTableAttribute, LocalVariableTableEntry, StackMapTableAttribute};
+use crate::cp::builder::PoolBuilder;
+use crate::dataflow::frame::{Frame, FrameAnalysis, Interpreter, ValueKind};
+use crate::dataflow::solve_with_cfg;
+use crate::cfg::ControlFlowGraph;
+use crate::descriptor::{FieldDescriptor, MethodDescriptor};
+use crate::instruction::{BranchTarget, Instructions, LvIndex};
+use crate::instruction::encode::assemble;
+use crate::instruction::opcode::{ArrayType, Opcode};
+use crate::name::{ClassName, FieldName};
+
+/// A position in the code of a [CodeBuilder], created by [CodeBuilder::new_label] and placed by [CodeBuilder::place].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(usize);
+
+#[derive(Debug, Clone)]
+struct TryCatch {
+	start: Label,
+	end: Label,
+	handler: Label,
+	catch_type: Option<ClassName>,
+}
+
+#[derive(Debug, Clone)]
+struct LocalVariable {
+	start: Label,
+	end: Label,
+	name: FieldName,
+	descriptor: FieldDescriptor,
+	index: u16,
+}
+
+/// Builds the code of a method. The branch targets of the collected opcodes are the numbers of [Label]s, and only become offsets in [CodeBuilder::build].
+#[derive(Debug, Clone)]
+pub struct CodeBuilder {
+	descriptor: MethodDescriptor,
+	is_static: bool,
+	opcodes: Vec<Opcode>,
+	/// The index of the instruction each label was placed before.
+	labels: Vec<Option<usize>>,
+	try_catches: Vec<TryCatch>,
+	/// The instruction index at which each line starts.
+	line_numbers: Vec<(usize, u16)>,
+	local_variables: Vec<LocalVariable>,
+	/// The first misuse of the builder, returned by [CodeBuilder::build] so that building code can be chained.
+	error: Option<String>,
+}
+
+impl CodeBuilder {
+	/// Creates a builder for the code of a method with the given descriptor. The parameters are used for computing `max_locals` and `max_stack`.
+	pub fn new(descriptor: MethodDescriptor, is_static: bool) -> CodeBuilder {
+		CodeBuilder {
+			descriptor,
+			is_static,
+			opcodes: Vec::new(),
+			labels: Vec::new(),
+			try_catches: Vec::new(),
+			line_numbers: Vec::new(),
+			local_variables: Vec::new(),
+			error: None,
+		}
+	}
+
+	/// Creates a label that isn't placed yet.
+	pub fn new_label(&mut self) -> Label {
+		self.labels.push(None);
+		Label(self.labels.len() - 1)
+	}
+
+	/// Places `label` before the next instruction emitted. Placing a label twice is an error reported by [CodeBuilder::build].
+	pub fn place(&mut self, label: Label) -> &mut Self {
+		match self.labels.get_mut(label.0) {
+			Some(index @ None) => *index = Some(self.opcodes.len()),
+			Some(Some(_)) => self.fail(format!("label {} placed twice", label.0)),
+			None => self.fail(format!("label {} is not one of this builder", label.0)),
+		}
+		self
+	}
+
+	/// Creates a label and places it before the next instruction emitted.
+	pub fn here(&mut self) -> Label {
+		let label = self.new_label();
+		self.place(label);
+		label
+	}
+
+	/// Emits an opcode without branch targets. Jumps and switches have their own methods taking labels, emitting them here is an error reported by
+	/// [CodeBuilder::build].
+	pub fn emit(&mut self, opcode: Opcode) -> &mut Self {
+		if opcode.branch_targets().is_empty() {
+			self.opcodes.push(opcode);
+		} else {
+			self.fail(format!("use the methods taking labels for {opcode:?}"));
+		}
+		self
+	}
+
+	/// Remembers the first misuse of the builder.
+	fn fail(&mut self, error: String) {
+		self.error.get_or_insert(error);
+	}
+
+	/// Marks the next instruction emitted as the start of the line `line` in the source file.
+	pub fn line_number(&mut self, line: u16) -> &mut Self {
+		match self.line_numbers.last_mut() {
+			Some((index, previous)) if *index == self.opcodes.len() => *previous = line,
+			_ => self.line_numbers.push((self.opcodes.len(), line)),
+		}
+		self
+	}
+
+	/// Adds an entry to the local variable table, for the local `index` holding the variable `name` from `start` up to `end`.
+	pub fn local_variable(&mut self, name: FieldName, descriptor: FieldDescriptor, index: u16, start: Label, end: Label) -> &mut Self {
+		self.local_variables.push(LocalVariable { start, end, name, descriptor, index });
+		self
+	}
+
+	/// Adds an exception handler at `handler` for the instructions from `start` up to `end`, catching all exceptions if `catch_type` is `None`. The
+	/// handlers are tried in the order they are added.
+	pub fn try_catch(&mut self, start: Label, end: Label, handler: Label, catch_type: Option<ClassName>) -> &mut Self {
+		self.try_catches.push(TryCatch { start, end, handler, catch_type });
+		self
+	}
+
+	pub fn aload(&mut self, index: u16) -> &mut Self { self.emit(Opcode::ALoad(LvIndex(index as usize))) }
+	pub fn dload(&mut self, index: u16) -> &mut Self { self.emit(Opcode::DLoad(LvIndex(index as usize))) }
+	pub fn fload(&mut self, index: u16) -> &mut Self { self.emit(Opcode::FLoad(LvIndex(index as usize))) }
+	pub fn iload(&mut self, index: u16) -> &mut Self { self.emit(Opcode::ILoad(LvIndex(index as usize))) }
+	pub fn lload(&mut self, index: u16) -> &mut Self { self.emit(Opcode::LLoad(LvIndex(index as usize))) }
+	pub fn astore(&mut self, index: u16) -> &mut Self { self.emit(Opcode::AStore(LvIndex(index as usize))) }
+	pub fn dstore(&mut self, index: u16) -> &mut Self { self.emit(Opcode::DStore(LvIndex(index as usize))) }
+	pub fn fstore(&mut self, index: u16) -> &mut Self { self.emit(Opcode::FStore(LvIndex(index as usize))) }
+	pub fn istore(&mut self, index: u16) -> &mut Self { self.emit(Opcode::IStore(LvIndex(index as usize))) }
+	pub fn lstore(&mut self, index: u16) -> &mut Self { self.emit(Opcode::LStore(LvIndex(index as usize))) }
+	pub fn iinc(&mut self, index: u16, increment: i16) -> &mut Self {
+		self.emit(Opcode::IInc { lv_index: LvIndex(index as usize), const_: increment as i32 })
+	}
+
+	pub fn aconst_null(&mut self) -> &mut Self { self.emit(Opcode::AConstNull) }
+
+	/// Pushes an `int`, using the shortest instruction for it.
+	pub fn iconst(&mut self, value: i32) -> &mut Self { self.emit(int_constant(value)) }
+
+	/// Pushes a `long`, using the shortest instruction for it.
+	pub fn lconst(&mut self, value: i64) -> &mut Self { self.emit(long_constant(value)) }
+
+	/// Pushes a `float`, using the shortest instruction for it.
+	pub fn fconst(&mut self, value: f32) -> &mut Self { self.emit(float_constant(value)) }
+
+	/// Pushes a `double`, using the shortest instruction for it.
+	pub fn dconst(&mut self, value: f64) -> &mut Self { self.emit(double_constant(value)) }
+
+	/// Pushes a `String` constant, given in modified UTF-8.
+	pub fn ldc_string(&mut self, string: &[u8]) -> &mut Self { self.emit(Opcode::LdcReferenceString(string.to_vec())) }
+	pub fn ldc_class(&mut self, class: ClassName) -> &mut Self { self.emit(Opcode::LdcReferenceClass(class)) }
+
+	pub fn getfield(&mut self, field: FieldRefInfo) -> &mut Self { self.emit(Opcode::GetField(field)) }
+	pub fn getstatic(&mut self, field: FieldRefInfo) -> &mut Self { self.emit(Opcode::GetStatic(field)) }
+	pub fn putfield(&mut self, field: FieldRefInfo) -> &mut Self { self.emit(Opcode::PutField(field)) }
+	pub fn putstatic(&mut self, field: FieldRefInfo) -> &mut Self { self.emit(Opcode::PutStatic(field)) }
+
+	pub fn invokevirtual(&mut self, method: MethodRefInfo) -> &mut Self { self.emit(Opcode::InvokeVirtual(method)) }
+	pub fn invokespecial(&mut self, method: MethodRefInfo) -> &mut Self { self.emit(Opcode::InvokeSpecial(method)) }
+	pub fn invokestatic(&mut self, method: MethodRefInfo) -> &mut Self { self.emit(Opcode::InvokeStatic(method)) }
+	pub fn invokeinterface(&mut self, method: InterfaceMethodRefInfo) -> &mut Self {
+		// the count operand is the size of the arguments including the receiver
+		let count = 1 + method.descriptor.parameters.iter().map(|parameter| ValueKind::of(parameter).size()).sum::<usize>();
+		self.emit(Opcode::InvokeInterface { method_ref: method, count: count as u8, zero: 0 })
+	}
+	pub fn invokedynamic(&mut self, call_site: InvokeDynamicInfo) -> &mut Self {
+		self.emit(Opcode::InvokeDynamic { call_site, zero1: 0, zero2: 0 })
+	}
+
+	pub fn new_(&mut self, class: ClassName) -> &mut Self { self.emit(Opcode::New(class)) }
+	pub fn newarray(&mut self, a_type: ArrayType) -> &mut Self { self.emit(Opcode::NewArray { a_type }) }
+	pub fn anewarray(&mut self, class: ClassName) -> &mut Self { self.emit(Opcode::ANewArray(class)) }
+	pub fn multianewarray(&mut self, class: ClassName, dimensions: u8) -> &mut Self {
+		self.emit(Opcode::MultiANewArray(class, dimensions as usize))
+	}
+	pub fn checkcast(&mut self, class: ClassName) -> &mut Self { self.emit(Opcode::CheckCast(class)) }
+	pub fn instanceof(&mut self, class: ClassName) -> &mut Self { self.emit(Opcode::InstanceOf(class)) }
+
+	fn jump(&mut self, opcode: fn(BranchTarget) -> Opcode, label: Label) -> &mut Self {
+		self.opcodes.push(opcode(BranchTarget(label.0)));
+		self
+	}
+
+	pub fn goto(&mut self, label: Label) -> &mut Self { self.jump(Opcode::Goto, label) }
+	pub fn ifeq(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfEq, label) }
+	pub fn ifne(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfNe, label) }
+	pub fn iflt(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfLt, label) }
+	pub fn ifge(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfGe, label) }
+	pub fn ifgt(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfGt, label) }
+	pub fn ifle(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfLe, label) }
+	pub fn if_icmpeq(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfICmpEq, label) }
+	pub fn if_icmpne(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfICmpNe, label) }
+	pub fn if_icmplt(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfICmpLt, label) }
+	pub fn if_icmpge(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfICmpGe, label) }
+	pub fn if_icmpgt(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfICmpGt, label) }
+	pub fn if_icmple(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfICmpLe, label) }
+	pub fn if_acmpeq(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfACmpEq, label) }
+	pub fn if_acmpne(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfACmpNe, label) }
+	pub fn ifnull(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfNull, label) }
+	pub fn ifnonnull(&mut self, label: Label) -> &mut Self { self.jump(Opcode::IfNonNull, label) }
+
+	/// Emits a `tableswitch` jumping to `targets[i]` for the value `low + i`. Having no targets, or more than fit between `low` and `i32::MAX`, is an
+	/// error reported by [CodeBuilder::build].
+	pub fn tableswitch(&mut self, low: i32, default: Label, targets: &[Label]) -> &mut Self {
+		let high = i32::try_from(targets.len()).ok()
+			.filter(|&len| len > 0)
+			.and_then(|len| low.checked_add(len - 1));
+		match high {
+			Some(high) => self.opcodes.push(Opcode::TableSwitch {
+				default_target: BranchTarget(default.0),
+				low,
+				high,
+				targets: targets.iter().map(|label| BranchTarget(label.0)).collect(),
+			}),
+			None if targets.is_empty() => self.fail("tableswitch without targets".into()),
+			None => self.fail(format!("tableswitch from {low} with {} targets goes past the largest int", targets.len())),
+		}
+		self
+	}
+
+	/// Emits a `lookupswitch`, the pairs don't need to be sorted.
+	pub fn lookupswitch(&mut self, default: Label, pairs: &[(i32, Label)]) -> &mut Self {
+		self.opcodes.push(Opcode::LookupSwitch {
+			default_target: BranchTarget(default.0),
+			npairs: pairs.len(),
+			targets: pairs.iter().map(|(key, label)| (*key, BranchTarget(label.0))).collect(),
+		});
+		self
+	}
+
+	/// Encodes the code, adding the constants used to `pool`.
+	///
+	/// `max_locals` covers the parameters and all locals accessed, `max_stack` is computed by simulating the operand stack. No stack map frames are
+	/// computed.
+	pub fn build(self, pool: &mut PoolBuilder) -> Result<CodeAttribute> {
+		if let Some(error) = &self.error {
+			bail!("{error}");
+		}
+		let index_of = |label: &Label| self.labels[label.0].ok_or_else(|| anyhow!("label {} is not placed", label.0));
+		let assembled = assemble(&self.opcodes, |target| index_of(&Label(target.0)), pool)?;
+		let offset_of = |label: &Label| index_of(label).map(|index| assembled.offsets[index]);
+
+		let code = Instructions::parse(&assembled.code, pool.pool())?;
+
+		let exception_table = self.try_catches.iter()
+			.map(|try_catch| {
+				let entry = ExceptionTableEntry {
+					start_pc: offset_of(&try_catch.start)?,
+					end_pc: offset_of(&try_catch.end)?,
+					handler_pc: offset_of(&try_catch.handler)?,
+					catch_type: try_catch.catch_type,
+				};
+				if entry.start_pc >= entry.end_pc {
+					bail!("try block from {} to {} covers no instructions", entry.start_pc, entry.end_pc);
+				}
+				if let Some(catch_type) = &entry.catch_type {
+					pool.class(catch_type)?;
+				}
+				Ok(entry)
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		let line_number_table = self.line_numbers.iter()
+			.filter(|(index, _)| *index < self.opcodes.len())
+			.map(|&(index, line_number)| LineNumberTableEntry { start_pc: assembled.offsets[index], line_number })
+			.collect();
+
+		let mut attributes = Vec::new();
+		if !self.local_variables.is_empty() {
+			let local_variable_table = self.local_variables.iter()
+				.map(|local| Ok(LocalVariableTableEntry {
+					start_pc: offset_of(&local.start)?,
+					end_pc: offset_of(&local.end)?,
+					name: local.name,
+					descriptor: local.descriptor,
+					lv_index: local.index,
+				}))
+				.collect::<Result<Vec<_>>>()?;
+			attributes.push(AttributeInfo::LocalVariableTable(LocalVariableTableAttribute { local_variable_table }));
+		}
+
+		let max_locals = self.max_locals()?;
+		let max_stack = max_stack(&code, &exception_table, &self.descriptor, self.is_static, max_locals)?;
+
+		Ok(CodeAttribute {
+			max_stack,
+			max_locals: max_locals as u16,
+			code,
+			exception_table,
+			attributes,
+			line_number_table,
+			stack_map_table: StackMapTableAttribute { entries: Vec::new() },
+		})
+	}
+
+	fn max_locals(&self) -> Result<usize> {
+		let parameters = !self.is_static as usize
+			+ self.descriptor.parameters.iter().map(|parameter| ValueKind::of(parameter).size()).sum::<usize>();
+		let accessed = self.opcodes.iter()
+			.filter_map(|opcode| match opcode {
+				Opcode::LLoad(index) | Opcode::DLoad(index) | Opcode::LStore(index) | Opcode::DStore(index) => Some(index.0 + 2),
+				Opcode::ILoad(index) | Opcode::FLoad(index) | Opcode::ALoad(index) |
+				Opcode::IStore(index) | Opcode::FStore(index) | Opcode::AStore(index) |
+				Opcode::IInc { lv_index: index, .. } => Some(index.0 + 1),
+				_ => None,
+			})
+			.max()
+			.unwrap_or(0);
+		let max_locals = parameters.max(accessed);
+		if max_locals > u16::MAX as usize {
+			bail!("code uses {max_locals} locals, at most 65535 are possible");
+		}
+		Ok(max_locals)
+	}
+}
+
+/// The shortest instruction pushing the `int` `value`.
+pub(crate) fn int_constant(value: i32) -> Opcode {
+	match value {
+		-1 => Opcode::IConstM1,
+		0 => Opcode::IConst0,
+		1 => Opcode::IConst1,
+		2 => Opcode::IConst2,
+		3 => Opcode::IConst3,
+		4 => Opcode::IConst4,
+		5 => Opcode::IConst5,
+		value if i8::try_from(value).is_ok() => Opcode::BIPush(value as i8 as u8),
+		value if i16::try_from(value).is_ok() => Opcode::SIPush(value as i16),
+		value => Opcode::LdcInt(value),
+	}
+}
+
+/// The shortest instruction pushing the `long` `value`.
+pub(crate) fn long_constant(value: i64) -> Opcode {
+	match value {
+		0 => Opcode::LConst0,
+		1 => Opcode::LConst1,
+		value => Opcode::Ldc2WLong(value),
+	}
+}
+
+/// The shortest instruction pushing the `float` `value`.
+pub(crate) fn float_constant(value: f32) -> Opcode {
+	// comparing the bits keeps -0.0 from becoming fconst_0
+	match value.to_bits() {
+		bits if bits == 0.0f32.to_bits() => Opcode::FConst0,
+		bits if bits == 1.0f32.to_bits() => Opcode::FConst1,
+		bits if bits == 2.0f32.to_bits() => Opcode::FConst2,
+		bits => Opcode::LdcFloat(bits),
+	}
+}
+
+/// The shortest instruction pushing the `double` `value`.
+pub(crate) fn double_constant(value: f64) -> Opcode {
+	match value.to_bits() {
+		bits if bits == 0.0f64.to_bits() => Opcode::DConst0,
+		bits if bits == 1.0f64.to_bits() => Opcode::DConst1,
+		bits => Opcode::Ldc2WDouble(bits),
+	}
+}
+
+/// Tracks nothing but the size of values, for computing the depth of the operand stack.
+struct Sizes;
+
+impl Interpreter for Sizes {
+	type Value = usize;
+
+	fn unknown(&self, kind: Option<ValueKind>) -> usize { kind.map_or(1, ValueKind::size) }
+	fn size(&self, value: &usize) -> usize { *value }
+	fn parameter(&self, kind: ValueKind, _is_this: bool) -> usize { kind.size() }
+	fn caught_exception(&self, _entry: &ExceptionTableEntry) -> usize { 1 }
+	fn constant(&self, _opcode: &Opcode, kind: ValueKind) -> usize { kind.size() }
+	fn unary(&self, _opcode: &Opcode, _value: &usize, kind: ValueKind) -> usize { kind.size() }
+	fn binary(&self, _opcode: &Opcode, _value1: &usize, _value2: &usize, kind: ValueKind) -> usize { kind.size() }
+	fn nary(&self, _opcode: &Opcode, _values: &[usize], kind: ValueKind) -> usize { kind.size() }
+	fn merge(&self, value1: &usize, value2: &usize) -> usize { *value1.max(value2) }
+}
+
+pub(crate) fn max_stack(code: &Instructions, exception_table: &[ExceptionTableEntry], descriptor: &MethodDescriptor, is_static: bool, max_locals: usize) -> Result<u16> {
+	if code.is_empty() {
+		return Ok(0);
+	}
+	let cfg = ControlFlowGraph::from_instructions(code, exception_table)?;
+	let entry = Frame::entry_from_descriptor(&Sizes, descriptor, is_static, max_locals)?;
+	let frames = solve_with_cfg(&FrameAnalysis::with_entry(Sizes, entry), code, exception_table, &cfg)?;
+
+	let depth = |frame: &Option<Frame<usize>>| frame.as_ref().map_or(0, |frame| frame.stack.iter().sum::<usize>());
+	let max_stack = (0..code.len())
+		.map(|index| depth(frames.before(index)).max(depth(frames.after(index))))
+		.max()
+		.unwrap_or(0);
+	u16::try_from(max_stack).map_err(|_| anyhow!("operand stack of depth {max_stack} is too deep"))
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::cp::attribute::AttributeInfo;
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::{BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+	use crate::instruction::builder::CodeBuilder;
+	use crate::instruction::opcode::Opcode;
+	use crate::name::{ClassName, FieldName};
+
+	#[test]
+	fn loop_with_handler() {
+		let int = FieldDescriptor { array_dimension: 0, base_type: BaseOrObjectType::I };
+		let long = FieldDescriptor { array_dimension: 0, base_type: BaseOrObjectType::J };
+		let descriptor = MethodDescriptor { parameters: vec![int, long], return_type: Some(int) };
+		let exception = ClassName::from(&b"java/lang/ArithmeticException"[..]);
+
+		// static int f(int n, long l) { int sum = 0; try { for (...) sum += 100000 / n--; } catch (ArithmeticException e) { return -1; } return sum; }
+		let mut code = CodeBuilder::new(descriptor, true);
+		let (condition, end, handler) = (code.new_label(), code.new_label(), code.new_label());
+		code.line_number(1).iconst(0).istore(3);
+		let start = code.here();
+		code.goto(condition);
+		let body = code.here();
+		code.line_number(2).iload(3).iconst(100_000).iload(0).emit(Opcode::IDiv).emit(Opcode::IAdd).istore(3).iinc(0, -1);
+		code.place(condition).iload(0).ifgt(body);
+		code.place(end).line_number(3).iload(3).emit(Opcode::IReturn);
+		code.place(handler).emit(Opcode::Pop).iconst(-1).emit(Opcode::IReturn);
+		code.try_catch(start, end, handler, Some(exception));
+		code.local_variable(FieldName::from(&b"sum"[..]), int, 3, start, end);
+
+		let mut pool = PoolBuilder::new();
+		let code = code.build(&mut pool).unwrap();
+		assert_eq!(code.max_locals, 4);
+		assert_eq!(code.max_stack, 3);
+
+		let offsets: Vec<_> = code.code.iter().map(|instruction| instruction.offset()).collect();
+		assert_eq!(code.exception_table.len(), 1);
+		let entry = &code.exception_table[0];
+		assert_eq!((entry.start_pc, entry.end_pc, entry.handler_pc), (offsets[2], offsets[12], offsets[14]));
+		assert_eq!(entry.catch_type.as_ref(), Some(&exception));
+		assert_eq!(code.code.get(3).unwrap().opcode(), &Opcode::ILoad(crate::instruction::LvIndex(3)));
+
+		let lines: Vec<_> = code.line_number_table.iter().map(|entry| (entry.start_pc, entry.line_number)).collect();
+		assert_eq!(lines, vec![(0, 1), (offsets[3], 2), (offsets[12], 3)]);
+		assert!(matches!(&code.attributes[..], [AttributeInfo::LocalVariableTable(table)]
+			if table.local_variable_table[0].start_pc == offsets[2] && table.local_variable_table[0].end_pc == offsets[12]));
+	}
+
+	#[test]
+	fn unplaced_label() {
+		let mut code = CodeBuilder::new(MethodDescriptor { parameters: Vec::new(), return_type: None }, true);
+		let label = code.new_label();
+		code.goto(label);
+		assert!(code.build(&mut PoolBuilder::new()).is_err());
+	}
+
+	#[test]
+	fn misuse() {
+		let mut code = CodeBuilder::new(MethodDescriptor { parameters: Vec::new(), return_type: None }, true);
+		let label = code.here();
+		code.emit(Opcode::Nop).place(label).emit(Opcode::Return);
+		let error = code.build(&mut PoolBuilder::new()).unwrap_err();
+		assert_eq!(error.to_string(), "label 0 placed twice");
+
+		let mut code = CodeBuilder::new(MethodDescriptor { parameters: Vec::new(), return_type: None }, true);
+		code.emit(Opcode::Goto(crate::instruction::BranchTarget(0))).emit(Opcode::Return);
+		assert!(code.build(&mut PoolBuilder::new()).is_err());
+
+		for (low, targets) in [(0, 0), (i32::MAX, 2)] {
+			let mut code = CodeBuilder::new(MethodDescriptor { parameters: Vec::new(), return_type: None }, true);
+			let label = code.new_label();
+			code.iconst(0).tableswitch(low, label, &vec![label; targets]).place(label).emit(Opcode::Return);
+			assert!(code.build(&mut PoolBuilder::new()).is_err());
+		}
+		// a single target at the largest value is fine
+		let mut code = CodeBuilder::new(MethodDescriptor { parameters: Vec::new(), return_type: None }, true);
+		let label = code.new_label();
+		code.iconst(0).tableswitch(i32::MAX, label, &[label]).place(label).emit(Opcode::Return);
+		assert!(code.build(&mut PoolBuilder::new()).is_ok());
+	}
+}
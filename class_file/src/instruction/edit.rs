@@ -0,0 +1,153 @@
+//! Changing the instructions of a method while keeping everything referring to them in place.
+//!
+//! A [CodeEdit] collects replacements and insertions of opcodes, by the index of the instruction they apply to. [CodeEdit::apply] then encodes the new
+//! code and moves the exception table, the line numbers, the local variable tables and the stack map frames along: an offset of an instruction becomes
+//! the offset of the first opcode inserted before it or replacing it, or of the next instruction if it was removed.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use crate::cp::attribute::{AttributeInfo, CodeAttribute, StackMapFrame, VerificationTypeInfo};
+use crate::cp::builder::PoolBuilder;
+use crate::instruction::Instructions;
+use crate::instruction::encode::assemble;
+use crate::instruction::opcode::Opcode;
+
+#[derive(Debug, Clone, Default)]
+struct Edit {
+	before: Vec<Opcode>,
+	replacement: Option<Vec<Opcode>>,
+}
+
+/// Changes to the instructions of a [CodeAttribute]. The branch targets of the opcodes given are offsets of the original instructions, just like those
+/// of the instructions that are kept.
+#[derive(Debug, Clone, Default)]
+pub struct CodeEdit {
+	edits: BTreeMap<usize, Edit>,
+}
+
+impl CodeEdit {
+	pub fn new() -> CodeEdit {
+		CodeEdit::default()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.edits.is_empty()
+	}
+
+	/// Replaces the instruction with the index `index` by `opcodes`, replacing an earlier replacement.
+	pub fn replace(&mut self, index: usize, opcodes: Vec<Opcode>) -> &mut Self {
+		self.edits.entry(index).or_default().replacement = Some(opcodes);
+		self
+	}
+
+	/// Removes the instruction with the index `index`. Jumps to it go to the next instruction instead.
+	pub fn remove(&mut self, index: usize) -> &mut Self {
+		self.replace(index, Vec::new())
+	}
+
+	/// Inserts `opcodes` before the instruction with the index `index`, after the ones inserted there before. Jumps to the instruction execute them
+	/// first.
+	pub fn insert_before(&mut self, index: usize, opcodes: impl IntoIterator<Item=Opcode>) -> &mut Self {
+		self.edits.entry(index).or_default().before.extend(opcodes);
+		self
+	}
+
+	/// Applies the changes to `code`. `max_stack` and `max_locals` are left as they are, and stack map frames at removed instructions or at `new`
+	/// instructions that were replaced are likely wrong afterwards, see [compute_frames](crate::frames::compute_frames) for these. A frame moved onto an
+	/// instruction with a frame of its own is dropped, which breaks the frame after it unless that one is a [StackMapFrame::Full], as the others are
+	/// encoded relative to the frame before them.
+	pub fn apply(&self, code: &mut CodeAttribute) -> Result<()> {
+		let mut opcodes = Vec::new();
+		// the index in opcodes of the first opcode for each original instruction, followed by the end
+		let mut starts = Vec::with_capacity(code.code.len() + 1);
+		for (index, instruction) in code.code.iter().enumerate() {
+			starts.push(opcodes.len());
+			match self.edits.get(&index) {
+				Some(edit) => {
+					opcodes.extend(edit.before.iter().cloned());
+					match &edit.replacement {
+						Some(replacement) => opcodes.extend(replacement.iter().cloned()),
+						None => opcodes.push(instruction.opcode().clone()),
+					}
+				},
+				None => opcodes.push(instruction.opcode().clone()),
+			}
+		}
+		starts.push(opcodes.len());
+		if opcodes.is_empty() {
+			bail!("the edit removes all instructions");
+		}
+
+		let instructions = &code.code;
+		let start_of = |offset: usize| instructions.index_of_or_end(offset)
+			.map(|index| starts[index])
+			.ok_or_else(|| anyhow!("offset {offset} is not the start of an instruction"));
+		// a scratch pool, the instructions refer to constants by value
+		let mut pool = PoolBuilder::new();
+		let assembled = assemble(&opcodes, |target| start_of(target.0), &mut pool)?;
+		let at = |offset: usize| start_of(offset).map(|index| assembled.offsets[index]);
+		let end = *assembled.offsets.last().expect("offsets end with the length of the code");
+
+		for entry in &mut code.exception_table {
+			entry.start_pc = at(entry.start_pc)?;
+			entry.end_pc = at(entry.end_pc)?;
+			entry.handler_pc = at(entry.handler_pc)?;
+		}
+		code.exception_table.retain(|entry| entry.start_pc < entry.end_pc && entry.handler_pc < end);
+
+		for entry in &mut code.line_number_table {
+			entry.start_pc = at(entry.start_pc)?;
+		}
+		code.line_number_table.retain(|entry| entry.start_pc < end);
+
+		for attribute in &mut code.attributes {
+			match attribute {
+				AttributeInfo::LocalVariableTable(table) => {
+					for entry in &mut table.local_variable_table {
+						entry.start_pc = at(entry.start_pc)?;
+						entry.end_pc = at(entry.end_pc)?;
+					}
+					table.local_variable_table.retain(|entry| entry.start_pc < entry.end_pc);
+				},
+				AttributeInfo::LocalVariableTypeTable(table) => {
+					for entry in &mut table.local_variable_type_table {
+						entry.start_pc = at(entry.start_pc)?;
+						entry.end_pc = at(entry.end_pc)?;
+					}
+					table.local_variable_type_table.retain(|entry| entry.start_pc < entry.end_pc);
+				},
+				_ => {},
+			}
+		}
+
+		let mut frames = Vec::with_capacity(code.stack_map_table.entries.len());
+		for mut frame in code.stack_map_table.entries.drain(..) {
+			let (offset, types) = frame_parts(&mut frame);
+			*offset = at(*offset)?;
+			for verification_type in types {
+				if let VerificationTypeInfo::Uninitialized { bytecode_offset } = verification_type {
+					*bytecode_offset = at(*bytecode_offset)?;
+				}
+			}
+			// frames of removed instructions end up at the next one, where the last of them is kept
+			if frame.get_bytecode_offset() < end {
+				frames.retain(|other: &StackMapFrame| other.get_bytecode_offset() != frame.get_bytecode_offset());
+				frames.push(frame);
+			}
+		}
+		code.stack_map_table.entries = frames;
+
+		code.code = Instructions::parse(&assembled.code, pool.pool())?;
+		Ok(())
+	}
+}
+
+fn frame_parts(frame: &mut StackMapFrame) -> (&mut usize, Vec<&mut VerificationTypeInfo>) {
+	match frame {
+		StackMapFrame::Same { bytecode_offset } | StackMapFrame::Chop { bytecode_offset, .. } => (bytecode_offset, Vec::new()),
+		StackMapFrame::SameLocals1StackItem { bytecode_offset, stack } => (bytecode_offset, alloc::vec![stack]),
+		StackMapFrame::Append { bytecode_offset, locals } => (bytecode_offset, locals.iter_mut().collect()),
+		StackMapFrame::Full { bytecode_offset, locals, stack } => (bytecode_offset, locals.iter_mut().chain(stack.iter_mut()).collect()),
+	}
+}
@@ -0,0 +1,534 @@
+//! Encoding [Opcode]s into bytecode.
+//!
+//! The encoder always picks the shortest form of an instruction: `iload_0` over `iload 0` over `wide iload 0`, `ldc` over `ldc_w` depending on the
+//! constant pool index, and `goto` over `goto_w` as well as `jsr` over `jsr_w`. Conditional branches whose target is too far away for a 16 bit offset are relaxed into the inverted
+//! branch jumping over a `goto_w` to the original target.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use crate::cp::builder::PoolBuilder;
+use crate::instruction::{BranchTarget, Instructions};
+use crate::instruction::opcode::Opcode;
+
+/// Bytecode produced by [assemble].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembled {
+	pub code: Vec<u8>,
+	/// The offset of each instruction in `code`, followed by the length of `code`. This allows mapping exception tables and other attributes referring to
+	/// instructions to the new offsets.
+	pub offsets: Vec<usize>,
+}
+
+/// Encodes `opcodes`, adding the constants they refer to to `pool`. The branch targets of the opcodes are resolved by `target`, which returns the index
+/// of the target instruction in `opcodes`.
+///
+/// Branches are first encoded in their short form, and repeatedly relaxed as long as the layout leaves some target out of reach.
+pub fn assemble(opcodes: &[Opcode], target: impl Fn(&BranchTarget) -> Result<usize>, pool: &mut PoolBuilder) -> Result<Assembled> {
+	let targets = opcodes.iter()
+		.map(|opcode| opcode.branch_targets().into_iter().map(&target).collect::<Result<Vec<_>>>())
+		.collect::<Result<Vec<_>>>()?;
+	if let Some(&index) = targets.iter().flatten().find(|&&index| index >= opcodes.len()) {
+		bail!("branch target {index} is not an instruction");
+	}
+
+	let mut long = vec![false; opcodes.len()];
+	let mut scratch = Vec::new();
+	loop {
+		let mut offsets = Vec::with_capacity(opcodes.len() + 1);
+		let mut offset = 0;
+		for (opcode, &long) in opcodes.iter().zip(&long) {
+			offsets.push(offset);
+			scratch.clear();
+			// the length of an instruction doesn't depend on its targets, as long as the form of the branch is fixed
+			opcode.encode_with(offset, long, &|_| Ok(offset), pool, &mut scratch)?;
+			offset += scratch.len();
+		}
+		offsets.push(offset);
+
+		let mut changed = false;
+		for (index, targets) in targets.iter().enumerate() {
+			let out_of_reach = targets.iter().any(|&target| i16::try_from(offsets[target] as i64 - offsets[index] as i64).is_err());
+			if !long[index] && out_of_reach {
+				long[index] = true;
+				changed = true;
+			}
+		}
+		if changed {
+			continue;
+		}
+
+		if offset > u16::MAX as usize {
+			bail!("code of length {offset} is too long, it must be less than 65536");
+		}
+		let mut code = Vec::with_capacity(offset);
+		for (opcode, &long) in opcodes.iter().zip(&long) {
+			opcode.encode_with(code.len(), long, &|t| Ok(offsets[target(t)?]), pool, &mut code)?;
+		}
+		return Ok(Assembled { code, offsets });
+	}
+}
+
+impl Instructions {
+	/// Encodes the instructions again, moving the branch targets to the new offsets.
+	pub fn encode(&self, pool: &mut PoolBuilder) -> Result<Assembled> {
+		let opcodes: Vec<_> = self.iter().map(|instruction| instruction.opcode().clone()).collect();
+		assemble(&opcodes, |target| self.index_of(target.0).ok_or_else(|| anyhow!("branch target {} is not an instruction", target.0)), pool)
+	}
+}
+
+impl Opcode {
+	/// Encodes this opcode, to be placed at `offset`, with its branch targets taken as absolute offsets.
+	pub fn encode(&self, offset: usize, pool: &mut PoolBuilder, bytes: &mut Vec<u8>) -> Result<()> {
+		let long = self.branch_targets().iter().any(|target| i16::try_from(target.0 as i64 - offset as i64).is_err());
+		self.encode_with(offset, long, &|target| Ok(target.0), pool, bytes)
+	}
+
+	/// Encodes this opcode, using the long form of branches if `long` is set. The absolute offsets of branch targets are given by `target`.
+	fn encode_with(
+		&self, offset: usize, long: bool, target: &dyn Fn(&BranchTarget) -> Result<usize>, pool: &mut PoolBuilder, bytes: &mut Vec<u8>,
+	) -> Result<()> {
+		let relative = |branch_target: &BranchTarget| -> Result<i64> { Ok(target(branch_target)? as i64 - offset as i64) };
+		let u16 = |index: usize| (index as u16).to_be_bytes();
+
+		if let Some(code) = self.code_without_operands() {
+			bytes.push(code);
+			return Ok(());
+		}
+
+		match self {
+			Opcode::ILoad(index) => local(bytes, index.0, 0x1a, 0x15)?,
+			Opcode::LLoad(index) => local(bytes, index.0, 0x1e, 0x16)?,
+			Opcode::FLoad(index) => local(bytes, index.0, 0x22, 0x17)?,
+			Opcode::DLoad(index) => local(bytes, index.0, 0x26, 0x18)?,
+			Opcode::ALoad(index) => local(bytes, index.0, 0x2a, 0x19)?,
+			Opcode::IStore(index) => local(bytes, index.0, 0x3b, 0x36)?,
+			Opcode::LStore(index) => local(bytes, index.0, 0x3f, 0x37)?,
+			Opcode::FStore(index) => local(bytes, index.0, 0x43, 0x38)?,
+			Opcode::DStore(index) => local(bytes, index.0, 0x47, 0x39)?,
+			Opcode::AStore(index) => local(bytes, index.0, 0x4b, 0x3a)?,
+			Opcode::IInc { lv_index, const_ } => {
+				if let (Ok(index), Ok(const_)) = (u8::try_from(lv_index.0), i8::try_from(*const_)) {
+					bytes.extend_from_slice(&[0x84, index, const_ as u8]);
+				} else if let (Ok(index), Ok(const_)) = (u16::try_from(lv_index.0), i16::try_from(*const_)) {
+					bytes.extend_from_slice(&[0xc4, 0x84]);
+					bytes.extend_from_slice(&index.to_be_bytes());
+					bytes.extend_from_slice(&const_.to_be_bytes());
+				} else {
+					bail!("iinc of local {} by {const_} can't be encoded", lv_index.0);
+				}
+			},
+
+			Opcode::BIPush(value) => bytes.extend_from_slice(&[0x10, *value]),
+			Opcode::SIPush(value) => {
+				bytes.push(0x11);
+				bytes.extend_from_slice(&value.to_be_bytes());
+			},
+			Opcode::LdcInt(_) | Opcode::LdcFloat(_) | Opcode::LdcReferenceString(_) | Opcode::LdcReferenceClass(_) |
+			Opcode::LdcReferenceMethodType(_) | Opcode::LdcReferenceMethodHandle(_) => {
+				let index = match self {
+					Opcode::LdcInt(value) => pool.integer(*value)?,
+					Opcode::LdcFloat(bits) => pool.float(*bits)?,
+					Opcode::LdcReferenceString(string) => pool.string(string)?,
+					Opcode::LdcReferenceClass(class) => pool.class(class)?,
+					Opcode::LdcReferenceMethodType(descriptor) => pool.method_type(descriptor)?,
+					Opcode::LdcReferenceMethodHandle(method_handle) => pool.method_handle(method_handle)?,
+					_ => unreachable!(),
+				};
+				match u8::try_from(index) {
+					Ok(index) => bytes.extend_from_slice(&[0x12, index]),
+					Err(_) => {
+						bytes.push(0x13);
+						bytes.extend_from_slice(&u16(index));
+					},
+				}
+			},
+			Opcode::Ldc2WLong(value) => {
+				bytes.push(0x14);
+				bytes.extend_from_slice(&u16(pool.long(*value)?));
+			},
+			Opcode::Ldc2WDouble(bits) => {
+				bytes.push(0x14);
+				bytes.extend_from_slice(&u16(pool.double(*bits)?));
+			},
+
+			Opcode::ANewArray(class) | Opcode::CheckCast(class) | Opcode::InstanceOf(class) | Opcode::New(class) => {
+				bytes.push(match self {
+					Opcode::ANewArray(_) => 0xbd,
+					Opcode::CheckCast(_) => 0xc0,
+					Opcode::InstanceOf(_) => 0xc1,
+					_ => 0xbb,
+				});
+				bytes.extend_from_slice(&u16(pool.class(class)?));
+			},
+			Opcode::MultiANewArray(class, dimensions) => {
+				let Ok(dimensions) = u8::try_from(*dimensions) else {
+					bail!("multianewarray can create at most 255 dimensions, got {dimensions}");
+				};
+				bytes.push(0xc5);
+				bytes.extend_from_slice(&u16(pool.class(class)?));
+				bytes.push(dimensions);
+			},
+			Opcode::NewArray { a_type } => bytes.extend_from_slice(&[0xbc, a_type.code()]),
+
+			Opcode::GetField(field) | Opcode::GetStatic(field) | Opcode::PutField(field) | Opcode::PutStatic(field) => {
+				bytes.push(match self {
+					Opcode::GetField(_) => 0xb4,
+					Opcode::GetStatic(_) => 0xb2,
+					Opcode::PutField(_) => 0xb5,
+					_ => 0xb3,
+				});
+				bytes.extend_from_slice(&u16(pool.field_ref(field)?));
+			},
+			Opcode::InvokeSpecial(method) | Opcode::InvokeStatic(method) | Opcode::InvokeVirtual(method) => {
+				bytes.push(match self {
+					Opcode::InvokeSpecial(_) => 0xb7,
+					Opcode::InvokeStatic(_) => 0xb8,
+					_ => 0xb6,
+				});
+				bytes.extend_from_slice(&u16(pool.method_ref(method)?));
+			},
+			Opcode::InvokeInterface { method_ref, count, zero } => {
+				bytes.push(0xb9);
+				bytes.extend_from_slice(&u16(pool.interface_method_ref(method_ref)?));
+				bytes.extend_from_slice(&[*count, *zero]);
+			},
+			Opcode::InvokeDynamic { call_site, zero1, zero2 } => {
+				bytes.push(0xba);
+				bytes.extend_from_slice(&u16(pool.invoke_dynamic(call_site)?));
+				bytes.extend_from_slice(&[*zero1, *zero2]);
+			},
+
+			Opcode::Goto(branch_target) => {
+				let relative = relative(branch_target)?;
+				if long {
+					bytes.push(0xc8);
+					bytes.extend_from_slice(&i32::try_from(relative)?.to_be_bytes());
+				} else {
+					bytes.push(0xa7);
+					bytes.extend_from_slice(&short_offset(relative)?.to_be_bytes());
+				}
+			},
+			Opcode::Jsr(branch_target) => {
+				let relative = relative(branch_target)?;
+				if long {
+					bytes.push(0xc9);
+					bytes.extend_from_slice(&i32::try_from(relative)?.to_be_bytes());
+				} else {
+					bytes.push(0xa8);
+					bytes.extend_from_slice(&short_offset(relative)?.to_be_bytes());
+				}
+			},
+			Opcode::Ret(index) => {
+				if let Ok(index) = u8::try_from(index.0) {
+					bytes.extend_from_slice(&[0xa9, index]);
+				} else if let Ok(index) = u16::try_from(index.0) {
+					bytes.extend_from_slice(&[0xc4, 0xa9]);
+					bytes.extend_from_slice(&index.to_be_bytes());
+				} else {
+					bail!("ret of local variable index {} can't be encoded", index.0);
+				}
+			},
+			Opcode::IfACmpEq(branch_target) | Opcode::IfACmpNe(branch_target) |
+			Opcode::IfICmpEq(branch_target) | Opcode::IfICmpNe(branch_target) | Opcode::IfICmpLt(branch_target) |
+			Opcode::IfICmpGe(branch_target) | Opcode::IfICmpGt(branch_target) | Opcode::IfICmpLe(branch_target) |
+			Opcode::IfEq(branch_target) | Opcode::IfNe(branch_target) | Opcode::IfLt(branch_target) |
+			Opcode::IfGe(branch_target) | Opcode::IfGt(branch_target) | Opcode::IfLe(branch_target) |
+			Opcode::IfNull(branch_target) | Opcode::IfNonNull(branch_target) => {
+				let code = match self {
+					Opcode::IfEq(_) => 0x99,
+					Opcode::IfNe(_) => 0x9a,
+					Opcode::IfLt(_) => 0x9b,
+					Opcode::IfGe(_) => 0x9c,
+					Opcode::IfGt(_) => 0x9d,
+					Opcode::IfLe(_) => 0x9e,
+					Opcode::IfICmpEq(_) => 0x9f,
+					Opcode::IfICmpNe(_) => 0xa0,
+					Opcode::IfICmpLt(_) => 0xa1,
+					Opcode::IfICmpGe(_) => 0xa2,
+					Opcode::IfICmpGt(_) => 0xa3,
+					Opcode::IfICmpLe(_) => 0xa4,
+					Opcode::IfACmpEq(_) => 0xa5,
+					Opcode::IfACmpNe(_) => 0xa6,
+					Opcode::IfNull(_) => 0xc6,
+					_ => 0xc7,
+				};
+				let relative = relative(branch_target)?;
+				if long {
+					// the opcodes come in pairs of a condition and its negation, apart from ifnull and ifnonnull
+					let inverted = match code {
+						0xc6 => 0xc7,
+						0xc7 => 0xc6,
+						code if code % 2 == 1 => code + 1,
+						code => code - 1,
+					};
+					// jump over the goto_w, which is 5 bytes long, if the condition doesn't hold
+					bytes.extend_from_slice(&[inverted, 0, 8, 0xc8]);
+					bytes.extend_from_slice(&i32::try_from(relative - 3)?.to_be_bytes());
+				} else {
+					bytes.push(code);
+					bytes.extend_from_slice(&short_offset(relative)?.to_be_bytes());
+				}
+			},
+
+			Opcode::TableSwitch { default_target, low, high, targets } => {
+				if *high as i64 - *low as i64 + 1 != targets.len() as i64 {
+					bail!("tableswitch from {low} to {high} must have {} targets, got {}", *high as i64 - *low as i64 + 1, targets.len());
+				}
+				bytes.push(0xaa);
+				pad(bytes, offset);
+				bytes.extend_from_slice(&i32::try_from(relative(default_target)?)?.to_be_bytes());
+				bytes.extend_from_slice(&low.to_be_bytes());
+				bytes.extend_from_slice(&high.to_be_bytes());
+				for branch_target in targets {
+					bytes.extend_from_slice(&i32::try_from(relative(branch_target)?)?.to_be_bytes());
+				}
+			},
+			Opcode::LookupSwitch { default_target, targets, .. } => {
+				bytes.push(0xab);
+				pad(bytes, offset);
+				bytes.extend_from_slice(&i32::try_from(relative(default_target)?)?.to_be_bytes());
+				bytes.extend_from_slice(&(targets.len() as u32).to_be_bytes());
+				// the pairs must be sorted by their keys
+				let mut pairs: Vec<_> = targets.iter().collect();
+				pairs.sort_by_key(|(key, _)| *key);
+				for (key, branch_target) in pairs {
+					bytes.extend_from_slice(&key.to_be_bytes());
+					bytes.extend_from_slice(&i32::try_from(relative(branch_target)?)?.to_be_bytes());
+				}
+			},
+
+			opcode => bail!("opcode {opcode:?} has no encoding with operands"),
+		}
+		Ok(())
+	}
+
+	/// Returns the byte of an opcode that has no operands.
+	fn code_without_operands(&self) -> Option<u8> {
+		let code = match self {
+			Opcode::Nop => 0x00,
+			Opcode::AConstNull => 0x01,
+			Opcode::IConstM1 => 0x02,
+			Opcode::IConst0 => 0x03,
+			Opcode::IConst1 => 0x04,
+			Opcode::IConst2 => 0x05,
+			Opcode::IConst3 => 0x06,
+			Opcode::IConst4 => 0x07,
+			Opcode::IConst5 => 0x08,
+			Opcode::LConst0 => 0x09,
+			Opcode::LConst1 => 0x0a,
+			Opcode::FConst0 => 0x0b,
+			Opcode::FConst1 => 0x0c,
+			Opcode::FConst2 => 0x0d,
+			Opcode::DConst0 => 0x0e,
+			Opcode::DConst1 => 0x0f,
+			Opcode::IALoad => 0x2e,
+			Opcode::LALoad => 0x2f,
+			Opcode::FALoad => 0x30,
+			Opcode::DALoad => 0x31,
+			Opcode::AALoad => 0x32,
+			Opcode::BALoad => 0x33,
+			Opcode::CALoad => 0x34,
+			Opcode::SALoad => 0x35,
+			Opcode::IAStore => 0x4f,
+			Opcode::LAStore => 0x50,
+			Opcode::FAStore => 0x51,
+			Opcode::DAStore => 0x52,
+			Opcode::AAStore => 0x53,
+			Opcode::BAStore => 0x54,
+			Opcode::CAStore => 0x55,
+			Opcode::SAStore => 0x56,
+			Opcode::Pop => 0x57,
+			Opcode::Pop2 => 0x58,
+			Opcode::Dup => 0x59,
+			Opcode::DupX1 => 0x5a,
+			Opcode::DupX2 => 0x5b,
+			Opcode::Dup2 => 0x5c,
+			Opcode::Dup2X1 => 0x5d,
+			Opcode::Dup2X2 => 0x5e,
+			Opcode::Swap => 0x5f,
+			Opcode::IAdd => 0x60,
+			Opcode::LAdd => 0x61,
+			Opcode::FAdd => 0x62,
+			Opcode::DAdd => 0x63,
+			Opcode::ISub => 0x64,
+			Opcode::LSub => 0x65,
+			Opcode::FSub => 0x66,
+			Opcode::DSub => 0x67,
+			Opcode::IMul => 0x68,
+			Opcode::LMul => 0x69,
+			Opcode::FMul => 0x6a,
+			Opcode::DMul => 0x6b,
+			Opcode::IDiv => 0x6c,
+			Opcode::LDiv => 0x6d,
+			Opcode::FDiv => 0x6e,
+			Opcode::DDiv => 0x6f,
+			Opcode::IRem => 0x70,
+			Opcode::LRem => 0x71,
+			Opcode::FRem => 0x72,
+			Opcode::DRem => 0x73,
+			Opcode::INeg => 0x74,
+			Opcode::LNeg => 0x75,
+			Opcode::FNeg => 0x76,
+			Opcode::DNeg => 0x77,
+			Opcode::IShl => 0x78,
+			Opcode::LShl => 0x79,
+			Opcode::IShr => 0x7a,
+			Opcode::LShr => 0x7b,
+			Opcode::IUShr => 0x7c,
+			Opcode::LUShr => 0x7d,
+			Opcode::IAnd => 0x7e,
+			Opcode::LAnd => 0x7f,
+			Opcode::IOr => 0x80,
+			Opcode::LOr => 0x81,
+			Opcode::IXor => 0x82,
+			Opcode::LXor => 0x83,
+			Opcode::I2l => 0x85,
+			Opcode::I2f => 0x86,
+			Opcode::I2d => 0x87,
+			Opcode::L2i => 0x88,
+			Opcode::L2f => 0x89,
+			Opcode::L2d => 0x8a,
+			Opcode::F2i => 0x8b,
+			Opcode::F2l => 0x8c,
+			Opcode::F2d => 0x8d,
+			Opcode::D2i => 0x8e,
+			Opcode::D2l => 0x8f,
+			Opcode::D2f => 0x90,
+			Opcode::I2b => 0x91,
+			Opcode::I2c => 0x92,
+			Opcode::I2s => 0x93,
+			Opcode::LCmp => 0x94,
+			Opcode::FCmpL => 0x95,
+			Opcode::FCmpG => 0x96,
+			Opcode::DCmpL => 0x97,
+			Opcode::DCmpG => 0x98,
+			Opcode::IReturn => 0xac,
+			Opcode::LReturn => 0xad,
+			Opcode::FReturn => 0xae,
+			Opcode::DReturn => 0xaf,
+			Opcode::AReturn => 0xb0,
+			Opcode::Return => 0xb1,
+			Opcode::ArrayLength => 0xbe,
+			Opcode::AThrow => 0xbf,
+			Opcode::MonitorEnter => 0xc2,
+			Opcode::MonitorExit => 0xc3,
+			Opcode::Breakpoint => 0xca,
+			Opcode::ImpDep1 => 0xfe,
+			Opcode::ImpDep2 => 0xff,
+			_ => return None,
+		};
+		Some(code)
+	}
+}
+
+/// Encodes a load or store of the local `index`, using `short_code + index` for the first four locals.
+fn local(bytes: &mut Vec<u8>, index: usize, short_code: u8, code: u8) -> Result<()> {
+	if index <= 3 {
+		bytes.push(short_code + index as u8);
+	} else if let Ok(index) = u8::try_from(index) {
+		bytes.extend_from_slice(&[code, index]);
+	} else if let Ok(index) = u16::try_from(index) {
+		bytes.extend_from_slice(&[0xc4, code]);
+		bytes.extend_from_slice(&index.to_be_bytes());
+	} else {
+		bail!("local variable index {index} can't be encoded");
+	}
+	Ok(())
+}
+
+fn short_offset(relative: i64) -> Result<i16> {
+	i16::try_from(relative).map_err(|_| anyhow!("branch offset {relative} doesn't fit into 16 bits"))
+}
+
+/// Pads a switch instruction at `offset` such that its operands start at a multiple of four.
+fn pad(bytes: &mut Vec<u8>, offset: usize) {
+	let padding = (4 - (offset + 1) % 4) % 4;
+	bytes.resize(bytes.len() + padding, 0);
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::MethodDescriptor;
+	use crate::instruction::{BranchTarget, Instructions, LvIndex};
+	use crate::instruction::encode::assemble;
+	use crate::instruction::opcode::Opcode;
+	use crate::name::ClassName;
+
+	#[test]
+	fn round_trip() {
+		let opcodes = vec![
+			Opcode::ILoad(LvIndex(0)),
+			Opcode::ILoad(LvIndex(300)),
+			Opcode::IInc { lv_index: LvIndex(1), const_: -1 },
+			Opcode::LdcReferenceString(b"hello".to_vec()),
+			Opcode::Ldc2WLong(-2),
+			Opcode::New(ClassName::from(&b"java/lang/Object"[..])),
+			Opcode::TableSwitch { default_target: BranchTarget(0), low: 1, high: 2, targets: vec![BranchTarget(8), BranchTarget(9)] },
+			Opcode::LookupSwitch { default_target: BranchTarget(9), npairs: 2, targets: vec![(5, BranchTarget(8)), (-5, BranchTarget(0))] },
+			Opcode::Goto(BranchTarget(0)),
+			Opcode::Return,
+		];
+		let mut pool = PoolBuilder::new();
+		let assembled = assemble(&opcodes, |target| Ok(target.0), &mut pool).unwrap();
+		// the tableswitch at 16 is padded to 20, the lookupswitch at 40 to 44
+		assert_eq!(assembled.offsets, vec![0, 1, 5, 8, 10, 13, 16, 40, 68, 71, 72]);
+
+		let instructions = Instructions::parse(&assembled.code, &pool.build()).unwrap();
+		let parsed: Vec<_> = instructions.iter().map(|instruction| instruction.opcode().clone()).collect();
+		let offset = |index: usize| BranchTarget(assembled.offsets[index]);
+		assert_eq!(parsed[..6], opcodes[..6]);
+		assert_eq!(parsed[6], Opcode::TableSwitch { default_target: offset(0), low: 1, high: 2, targets: vec![offset(8), offset(9)] });
+		assert_eq!(parsed[7], Opcode::LookupSwitch { default_target: offset(9), npairs: 2, targets: vec![(-5, offset(0)), (5, offset(8))] });
+		assert_eq!(parsed[8], Opcode::Goto(offset(0)));
+	}
+
+	#[test]
+	fn subroutines() {
+		let opcodes = vec![Opcode::Jsr(BranchTarget(2)), Opcode::Return, Opcode::AStore(LvIndex(1)), Opcode::Ret(LvIndex(1)), Opcode::Ret(LvIndex(300))];
+		let mut pool = PoolBuilder::new();
+		let assembled = assemble(&opcodes, |target| Ok(target.0), &mut pool).unwrap();
+		assert_eq!(assembled.code, [0xa8, 0x00, 0x04, 0xb1, 0x4c, 0xa9, 0x01, 0xc4, 0xa9, 0x01, 0x2c]);
+
+		let instructions = Instructions::parse(&assembled.code, &pool.build()).unwrap();
+		let parsed: Vec<_> = instructions.iter().map(|instruction| instruction.opcode().clone()).collect();
+		assert_eq!(parsed[0], Opcode::Jsr(BranchTarget(4)));
+		assert_eq!(parsed[1..], opcodes[1..]);
+	}
+
+	#[test]
+	fn wide_forms() {
+		let mut pool = PoolBuilder::new();
+		for value in 0..300 {
+			pool.integer(value).unwrap();
+		}
+
+		let mut opcodes = vec![Opcode::LdcInt(5), Opcode::LdcInt(1000), Opcode::IfEq(BranchTarget(40_004)), Opcode::Goto(BranchTarget(40_004))];
+		opcodes.extend(core::iter::repeat_n(Opcode::Nop, 40_000));
+		opcodes.push(Opcode::Return);
+		let assembled = assemble(&opcodes, |target| Ok(target.0), &mut pool).unwrap();
+
+		assert_eq!(assembled.code[0..2], [0x12, 6]);
+		assert_eq!(assembled.code[2], 0x13);
+		// ifne over a goto_w, followed by the goto_w of the goto
+		assert_eq!(assembled.code[5..13], [0x9a, 0x00, 0x08, 0xc8, 0x00, 0x00, 0x9c, 0x4a]);
+		assert_eq!(assembled.code[13], 0xc8);
+		assert_eq!(assembled.offsets[40_004], 5 + 8 + 5 + 40_000);
+	}
+
+	#[test]
+	fn method_type_constants() {
+		let opcodes = vec![
+			Opcode::LdcReferenceMethodType(MethodDescriptor::try_from(&b"(ILjava/lang/String;)V"[..]).unwrap()),
+			Opcode::LdcReferenceClass(ClassName::from(&b"java/lang/String"[..])),
+			Opcode::Return,
+		];
+		let mut pool = PoolBuilder::new();
+		let assembled = assemble(&opcodes, |target| Ok(target.0), &mut pool).unwrap();
+		// ldc points at the MethodType entry, which points at the descriptor
+		let instructions = Instructions::parse(&assembled.code, &pool.build()).unwrap();
+		let parsed: Vec<_> = instructions.iter().map(|instruction| instruction.opcode().clone()).collect();
+		assert_eq!(parsed, opcodes);
+	}
+}
@@ -1,12 +1,16 @@
+use alloc::vec::Vec;
 use anyhow::{anyhow, Result};
 use crate::cp::Pool;
 use crate::instruction::opcode::Opcode;
 use crate::MyRead;
 
-/// Contains the pre-verifier opcodes.
+/// Inlining the subroutines of code before version 51.0.
 mod old;
 
 pub mod opcode;
+pub mod encode;
+pub mod builder;
+pub mod edit;
 
 /// Describes a local variable index. Is used in [Opcode::ALoad] and others.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -20,6 +24,8 @@ pub struct BranchTarget(pub usize);
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Instructions {
 	inner: Vec<Instruction>,
+	/// The length of the code in bytes.
+	code_length: usize,
 }
 
 impl Instructions {
@@ -34,7 +40,53 @@ impl Instructions {
 			instructions.push(instruction);
 		}
 
-		Ok(Instructions { inner: instructions })
+		Ok(Instructions { inner: instructions, code_length: bytes.len() })
+	}
+
+	pub fn iter(&self) -> core::slice::Iter<'_, Instruction> {
+		self.inner.iter()
+	}
+
+	pub fn len(&self) -> usize {
+		self.inner.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.inner.is_empty()
+	}
+
+	/// Returns the instruction with the index `index`, this is not the offset of the instruction.
+	pub fn get(&self, index: usize) -> Option<&Instruction> {
+		self.inner.get(index)
+	}
+
+	/// Returns the index of the instruction starting at `offset`, or `None` if no instruction starts there.
+	pub fn index_of(&self, offset: usize) -> Option<usize> {
+		self.inner.binary_search_by_key(&offset, |instruction| instruction.offset).ok()
+	}
+
+	/// Like [Instructions::index_of], but also maps the end of the code to [Instructions::len], as is needed for exclusive end offsets like
+	/// [ExceptionTableEntry::end_pc](crate::cp::attribute::ExceptionTableEntry).
+	pub fn index_of_or_end(&self, offset: usize) -> Option<usize> {
+		if offset == self.code_length {
+			Some(self.inner.len())
+		} else {
+			self.index_of(offset)
+		}
+	}
+
+	/// Returns the length of the code in bytes.
+	pub fn code_length(&self) -> usize {
+		self.code_length
+	}
+}
+
+impl<'a> IntoIterator for &'a Instructions {
+	type Item = &'a Instruction;
+	type IntoIter = core::slice::Iter<'a, Instruction>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.inner.iter()
 	}
 }
 
@@ -45,6 +97,15 @@ pub struct Instruction {
 }
 
 impl Instruction {
+	/// The offset in bytes of this instruction from the start of the code.
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	pub fn opcode(&self) -> &Opcode {
+		&self.opcode
+	}
+
 	fn parse<R: MyRead>(reader: &mut OpcodeReader<R>, pool: &Pool) -> Result<Instruction> {
 		Ok(Instruction {
 			offset: reader.pos,
@@ -53,13 +114,13 @@ impl Instruction {
 	}
 }
 
-struct OpcodeReader<R: std::io::Read> {
+struct OpcodeReader<R: crate::io::Read> {
 	reader: R,
 	pos: usize,
 	current_instruction_pos: usize,
 }
 
-impl<R: std::io::Read> OpcodeReader<R> {
+impl<R: crate::io::Read> OpcodeReader<R> {
 	fn new(reader: R) -> OpcodeReader<R> {
 		OpcodeReader {
 			reader,
@@ -69,8 +130,8 @@ impl<R: std::io::Read> OpcodeReader<R> {
 	}
 }
 
-impl<R: std::io::Read> std::io::Read for OpcodeReader<R> {
-	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+impl<R: crate::io::Read> crate::io::Read for OpcodeReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
 		let n = self.reader.read(buf)?;
 		self.pos += n;
 		Ok(n)
@@ -84,7 +145,7 @@ pub(crate) trait CodeReader: MyRead {
 	fn move_to_next_4_byte_boundary(&mut self) -> Result<()>;
 }
 
-impl<R: std::io::Read> CodeReader for OpcodeReader<R> {
+impl<R: crate::io::Read> CodeReader for OpcodeReader<R> {
 	fn next_instruction(&mut self) {
 		self.current_instruction_pos = self.pos
 	}
@@ -101,10 +162,14 @@ impl<R: std::io::Read> CodeReader for OpcodeReader<R> {
 	}
 
 	fn read_i32_branchoffset(&mut self) -> Result<BranchTarget> {
-		let offset = self.read_i32()?;
-		let target = offset + (self.current_instruction_pos as i32);
+		let offset: isize = self.read_i32()?.try_into()?;
+		let this_pos: isize = self.current_instruction_pos.try_into()?;
 
-		Ok(BranchTarget(target.try_into().unwrap()))//TODO: panic!
+		let target: usize = offset.checked_add(this_pos)
+			.ok_or_else(|| anyhow!("overflow in reading i32 branchoffset: instruction pos: {this_pos}, read offset: {offset}"))?
+			.try_into()?;
+
+		Ok(BranchTarget(target))
 	}
 
 	fn move_to_next_4_byte_boundary(&mut self) -> Result<()> {
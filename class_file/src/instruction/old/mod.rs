@@ -1,10 +1,293 @@
-use crate::instruction::LvIndex;
+//! The subroutines of class files before version 51.0, `jsr` and `ret` (JVMS 4.10.2.5).
+//!
+//! [CodeAttribute::inline_subroutines] replaces them by copies of their code: each `jsr` becomes an `aconst_null` standing in for the return address,
+//! followed by a `goto` to a copy of the subroutine made for this call, in which `ret` jumps back behind the `jsr`. The exception table, the line numbers
+//! and the local variable tables are copied along with the instructions they refer to. A subroutine is copied once for every copy of its callers, so
+//! nested subroutines can make the code grow a lot, which is why this is only done where it's asked for.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::collections::btree_map::Entry;
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use crate::cp::attribute::{AttributeInfo, CodeAttribute, ExceptionTableEntry, LineNumberTableEntry, LocalVariableTableEntry, LocalVariableTypeTableEntry};
+use crate::cp::builder::PoolBuilder;
+use crate::instruction::{BranchTarget, Instructions};
+use crate::instruction::encode::assemble;
 use crate::instruction::opcode::Opcode;
 
-#[allow(unused)]
-enum LegacyOpcode {
-	Modern(Opcode),
+impl CodeAttribute {
+	/// Returns whether the code calls subroutines with `jsr`, or returns from one with `ret`.
+	pub fn has_subroutines(&self) -> bool {
+		self.code.iter().any(|instruction| matches!(instruction.opcode(), Opcode::Jsr(_) | Opcode::Ret(_)))
+	}
+
+	/// Inlines the subroutines, see the [module](self) documentation, moving the entries of the tables referring to them to their copies. Code without
+	/// subroutines is left as it is. The stack map frames are dropped, as they don't match the new code.
+	pub fn inline_subroutines(&mut self) -> Result<()> {
+		if !self.has_subroutines() {
+			return Ok(());
+		}
+		let code = &self.code;
+
+		let ranges = self.exception_table.iter()
+			.map(|entry| Ok((index_of(code, entry.start_pc)?, index_of_or_end(code, entry.end_pc)?, index_of(code, entry.handler_pc)?)))
+			.collect::<Result<Vec<_>>>()?;
+
+		// the main code starting at 0, and the subroutines by the index of their first instruction
+		let mut subroutines = BTreeMap::new();
+		subroutines.insert(0, subroutine(code, 0, &ranges)?);
+		for instruction in code {
+			if let Opcode::Jsr(branch_target) = instruction.opcode() {
+				let entry = index_of(code, branch_target.0)?;
+				if let Entry::Vacant(vacant) = subroutines.entry(entry) {
+					vacant.insert(subroutine(code, entry, &ranges)?);
+				}
+			}
+		}
+
+		let mut inliner = Inliner { code, subroutines: &subroutines, opcodes: Vec::new(), labels: Vec::new(), instances: Vec::new() };
+		inliner.instance(0, None, None);
+		let mut next = 0;
+		while next < inliner.instances.len() {
+			inliner.emit(next)?;
+			next += 1;
+		}
+
+		// the tables refer to opcode indices until the code is assembled
+		let mut exception_table = Vec::new();
+		for (entry, &(start, end, handler)) in self.exception_table.iter().zip(&ranges) {
+			for (instance, from, to) in inliner.ranges(start, end) {
+				exception_table.push(ExceptionTableEntry {
+					start_pc: from,
+					end_pc: to,
+					handler_pc: inliner.placed(inliner.label(instance, handler)?)?,
+					catch_type: entry.catch_type,
+				});
+			}
+		}
+		let mut line_number_table = Vec::new();
+		for instance in &inliner.instances {
+			for &(index, from, _) in &instance.copies {
+				let offset = code.get(index).map_or(0, |instruction| instruction.offset());
+				line_number_table.extend(self.line_number_table.iter()
+					.filter(|entry| entry.start_pc == offset)
+					.map(|entry| LineNumberTableEntry { start_pc: from, line_number: entry.line_number }));
+			}
+		}
+		let mut attributes = self.attributes.clone();
+		for attribute in &mut attributes {
+			match attribute {
+				AttributeInfo::LocalVariableTable(table) => {
+					let mut entries = Vec::new();
+					for entry in &table.local_variable_table {
+						for (_, from, to) in inliner.ranges(index_of(code, entry.start_pc)?, index_of_or_end(code, entry.end_pc)?) {
+							entries.push(LocalVariableTableEntry { start_pc: from, end_pc: to, ..entry.clone() });
+						}
+					}
+					table.local_variable_table = entries;
+				},
+				AttributeInfo::LocalVariableTypeTable(table) => {
+					let mut entries = Vec::new();
+					for entry in &table.local_variable_type_table {
+						for (_, from, to) in inliner.ranges(index_of(code, entry.start_pc)?, index_of_or_end(code, entry.end_pc)?) {
+							entries.push(LocalVariableTypeTableEntry { start_pc: from, end_pc: to, ..entry.clone() });
+						}
+					}
+					table.local_variable_type_table = entries;
+				},
+				_ => {},
+			}
+		}
+
+		let mut pool = PoolBuilder::new();
+		let assembled = assemble(&inliner.opcodes, |target| inliner.placed(target.0), &mut pool)?;
+		let offset = |index: usize| assembled.offsets[index];
+		for entry in &mut exception_table {
+			entry.start_pc = offset(entry.start_pc);
+			entry.end_pc = offset(entry.end_pc);
+			entry.handler_pc = offset(entry.handler_pc);
+		}
+		for entry in &mut line_number_table {
+			entry.start_pc = offset(entry.start_pc);
+		}
+		for attribute in &mut attributes {
+			match attribute {
+				AttributeInfo::LocalVariableTable(table) => for entry in &mut table.local_variable_table {
+					entry.start_pc = offset(entry.start_pc);
+					entry.end_pc = offset(entry.end_pc);
+				},
+				AttributeInfo::LocalVariableTypeTable(table) => for entry in &mut table.local_variable_type_table {
+					entry.start_pc = offset(entry.start_pc);
+					entry.end_pc = offset(entry.end_pc);
+				},
+				_ => {},
+			}
+		}
+		self.code = Instructions::parse(&assembled.code, pool.pool())?;
+		self.exception_table = exception_table;
+		self.line_number_table = line_number_table;
+		self.attributes = attributes;
+		self.stack_map_table.entries.clear();
+		Ok(())
+	}
+}
+
+fn index_of(code: &Instructions, offset: usize) -> Result<usize> {
+	code.index_of(offset).ok_or_else(|| anyhow!("offset {offset} is not the start of an instruction"))
+}
+
+fn index_of_or_end(code: &Instructions, offset: usize) -> Result<usize> {
+	code.index_of_or_end(offset).ok_or_else(|| anyhow!("offset {offset} is not the start of an instruction"))
+}
+
+/// Returns the indices of the instructions reachable from `entry` without entering other subroutines, together with the handlers of the
+/// exception table entries covering them.
+fn subroutine(code: &Instructions, entry: usize, ranges: &[(usize, usize, usize)]) -> Result<BTreeSet<usize>> {
+	let mut members = BTreeSet::new();
+	let mut pending = vec![entry];
+	loop {
+		while let Some(index) = pending.pop() {
+			let Some(instruction) = code.get(index) else {
+				bail!("execution falls off the end of the code");
+			};
+			if !members.insert(index) {
+				continue;
+			}
+			match instruction.opcode() {
+				// the subroutine called isn't part of this one
+				Opcode::Jsr(_) => pending.push(index + 1),
+				opcode => {
+					for target in opcode.branch_targets() {
+						pending.push(index_of(code, target.0)?);
+					}
+					if opcode.falls_through() {
+						pending.push(index + 1);
+					}
+				},
+			}
+		}
+		pending.extend(ranges.iter()
+			.filter(|&&(start, end, handler)| !members.contains(&handler) && members.range(start..end).next().is_some())
+			.map(|&(_, _, handler)| handler));
+		if pending.is_empty() {
+			return Ok(members);
+		}
+	}
+}
+
+/// A copy of the main code or of a subroutine.
+struct Instance {
+	subroutine: usize,
+	/// The instance containing the `jsr` this one was made for.
+	parent: Option<usize>,
+	/// The label `ret` jumps to.
+	return_to: Option<usize>,
+	/// The label of the copy of each instruction of the subroutine.
+	labels: BTreeMap<usize, usize>,
+	/// The index of each instruction copied, with the range of opcodes it became.
+	copies: Vec<(usize, usize, usize)>,
+}
+
+struct Inliner<'a> {
+	code: &'a Instructions,
+	subroutines: &'a BTreeMap<usize, BTreeSet<usize>>,
+	/// The new code, with labels as branch targets.
+	opcodes: Vec<Opcode>,
+	/// The index in `opcodes` each label is placed at.
+	labels: Vec<Option<usize>>,
+	instances: Vec<Instance>,
+}
+
+impl Inliner<'_> {
+	fn instance(&mut self, subroutine: usize, parent: Option<usize>, return_to: Option<usize>) -> usize {
+		let mut labels = BTreeMap::new();
+		for &index in &self.subroutines[&subroutine] {
+			labels.insert(index, self.labels.len());
+			self.labels.push(None);
+		}
+		self.instances.push(Instance { subroutine, parent, return_to, labels, copies: Vec::new() });
+		self.instances.len() - 1
+	}
+
+	/// Returns the label of the copy of an instruction seen from `instance`. Jumps out of a subroutine go to the copy of the instance calling it.
+	fn label(&self, instance: usize, index: usize) -> Result<usize> {
+		let mut current = Some(instance);
+		while let Some(id) = current {
+			if let Some(&label) = self.instances[id].labels.get(&index) {
+				return Ok(label);
+			}
+			current = self.instances[id].parent;
+		}
+		bail!("jump to instruction {index} leaves the subroutine");
+	}
+
+	fn placed(&self, label: usize) -> Result<usize> {
+		self.labels.get(label).copied().flatten().ok_or_else(|| anyhow!("label {label} is not placed"))
+	}
+
+	fn emit(&mut self, id: usize) -> Result<()> {
+		let code = self.code;
+		let members: Vec<usize> = self.subroutines[&self.instances[id].subroutine].iter().copied().collect();
+		for (position, &index) in members.iter().enumerate() {
+			let label = self.instances[id].labels[&index];
+			self.labels[label] = Some(self.opcodes.len());
+			let start = self.opcodes.len();
+			let instruction = code.get(index).ok_or_else(|| anyhow!("no instruction {index}"))?;
+			let offset = instruction.offset();
+			match instruction.opcode() {
+				Opcode::Jsr(branch_target) => {
+					let entry = index_of(code, branch_target.0)?;
+					let mut caller = Some(id);
+					while let Some(instance) = caller {
+						if self.instances[instance].subroutine == entry {
+							bail!("recursive call of the subroutine at {} at offset {offset}", branch_target.0);
+						}
+						caller = self.instances[instance].parent;
+					}
+					let return_to = self.label(id, index + 1)?;
+					let callee = self.instance(entry, Some(id), Some(return_to));
+					self.opcodes.push(Opcode::AConstNull);
+					self.opcodes.push(Opcode::Goto(BranchTarget(self.instances[callee].labels[&entry])));
+				},
+				Opcode::Ret(_) => match self.instances[id].return_to {
+					Some(label) => self.opcodes.push(Opcode::Goto(BranchTarget(label))),
+					None => bail!("ret outside of a subroutine at offset {offset}"),
+				},
+				opcode => {
+					let mut copy = opcode.clone();
+					for target in copy.branch_targets_mut() {
+						*target = BranchTarget(self.label(id, index_of(code, target.0)?)?);
+					}
+					self.opcodes.push(copy);
+					// the next instruction may not be copied right after this one
+					if opcode.falls_through() && members.get(position + 1) != Some(&(index + 1)) {
+						let next = self.label(id, index + 1)?;
+						self.opcodes.push(Opcode::Goto(BranchTarget(next)));
+					}
+				},
+			}
+			self.instances[id].copies.push((index, start, self.opcodes.len()));
+		}
+		Ok(())
+	}
 
-	Jsr { branch_target: usize },
-	Ret(LvIndex),
-}
\ No newline at end of file
+	/// Returns the ranges of opcodes the instructions from `start` up to `end` were copied to, one for each run of copies in each instance.
+	fn ranges(&self, start: usize, end: usize) -> Vec<(usize, usize, usize)> {
+		let mut ranges = Vec::new();
+		for (id, instance) in self.instances.iter().enumerate() {
+			let mut run: Option<(usize, usize)> = None;
+			for &(index, from, to) in &instance.copies {
+				if (start..end).contains(&index) {
+					run = Some((run.map_or(from, |(run_start, _)| run_start), to));
+				} else if let Some((run_start, run_end)) = run.take() {
+					ranges.push((id, run_start, run_end));
+				}
+			}
+			if let Some((run_start, run_end)) = run {
+				ranges.push((id, run_start, run_end));
+			}
+		}
+		ranges
+	}
+}
@@ -1,23 +1,51 @@
+use alloc::vec;
+use alloc::vec::Vec;
 use anyhow::{bail, Result};
 use crate::cp::{FieldRefInfo, InterfaceMethodRefInfo, InvokeDynamicInfo, MethodHandleInfo, MethodRefInfo, Pool, PoolEntry};
 use crate::descriptor::MethodDescriptor;
 use crate::instruction::{BranchTarget, CodeReader, LvIndex};
 use crate::name::ClassName;
-use crate::verifier::VerificationType;
 
-// TODO: remove
-type LongInfo = i32;
-type FloatInfo = i32;
-type DoubleInfo = i32;
-type IntegerInfo = i32;
-type StringInfo = i32;
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum ArrayType {}
+/// The element type of an array created by [Opcode::NewArray].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArrayType {
+	Boolean,
+	Char,
+	Float,
+	Double,
+	Byte,
+	Short,
+	Int,
+	Long,
+}
 
 impl ArrayType {
-	fn parse(_: u8) -> Result<ArrayType> {
-		todo!()
+	fn parse(atype: u8) -> Result<ArrayType> {
+		match atype {
+			4 => Ok(ArrayType::Boolean),
+			5 => Ok(ArrayType::Char),
+			6 => Ok(ArrayType::Float),
+			7 => Ok(ArrayType::Double),
+			8 => Ok(ArrayType::Byte),
+			9 => Ok(ArrayType::Short),
+			10 => Ok(ArrayType::Int),
+			11 => Ok(ArrayType::Long),
+			atype => bail!("illegal newarray atype {atype}"),
+		}
+	}
+
+	/// The `atype` operand of the `newarray` instruction.
+	pub fn code(self) -> u8 {
+		match self {
+			ArrayType::Boolean => 4,
+			ArrayType::Char => 5,
+			ArrayType::Float => 6,
+			ArrayType::Double => 7,
+			ArrayType::Byte => 8,
+			ArrayType::Short => 9,
+			ArrayType::Int => 10,
+			ArrayType::Long => 11,
+		}
 	}
 }
 
@@ -121,7 +149,7 @@ pub enum Opcode {
 	///
 	/// # Run-time Exceptions
 	/// - If `count` is less than zero, throw a `java.lang.NegativeArraySizeException`.
-	ANewArray(VerificationType),
+	ANewArray(ClassName),
 	/// Return `reference` from method.
 	///
 	/// # Operand Stack
@@ -352,7 +380,7 @@ pub enum Opcode {
 	///   - If `T` is an array type `TC[]`, that is, an array of components of type `TC`, then one of the following must be true:
 	///     - `TC` and `SC` are the same primitive type.
 	///     - `TC` and `SC` are reference types, and type `SC` can be cast to `TC` by recursive application of these rules.
-	CheckCast(VerificationType),
+	CheckCast(ClassName),
 	D2f,
 	D2i,
 	D2l,
@@ -472,7 +500,7 @@ pub enum Opcode {
 	ImpDep1, ImpDep2,
 	IMul,
 	INeg,
-	InstanceOf(VerificationType),
+	InstanceOf(ClassName),
 	InvokeDynamic { call_site: InvokeDynamicInfo, zero1: u8, zero2: u8 },
 	InvokeInterface { method_ref: InterfaceMethodRefInfo, count: u8, zero: u8 },
 	InvokeSpecial(MethodRefInfo),
@@ -487,6 +515,9 @@ pub enum Opcode {
 	ISub,
 	IUShr,
 	IXor,
+	/// Jump to subroutine, pushing the offset of the next instruction as `returnAddress`. Only allowed before version 51.0, `jsr_w` is parsed into
+	/// this as well.
+	Jsr(BranchTarget),
 	L2d,
 	L2f,
 	L2i,
@@ -549,11 +580,11 @@ pub enum Opcode {
 	/// the constant pool (§4.4.4) must be taken from the float value set.
 	///
 	/// The `ldc_w` instruction is identical to the `ldc` instruction except for its wider run-time constant pool index.
-	LdcInt(IntegerInfo),
-	/// See [Opcode::LdcInt].
-	LdcFloat(FloatInfo),
-	/// See [Opcode::LdcInt].
-	LdcReferenceString(StringInfo),
+	LdcInt(i32),
+	/// See [Opcode::LdcInt]. The value is given by its bits, see [f32::from_bits].
+	LdcFloat(u32),
+	/// See [Opcode::LdcInt]. The string is given in modified UTF-8 (§4.4.7).
+	LdcReferenceString(Vec<u8>),
 	/// See [Opcode::LdcInt].
 	LdcReferenceClass(ClassName),
 	/// See [Opcode::LdcInt].
@@ -586,9 +617,9 @@ pub enum Opcode {
 	///
 	/// The `ldc2_w` instruction can only be used to push a value of type `double` taken from the double value set (§2.3.2) because a constant of type `double`
 	/// in the constant pool (§4.4.5) must be taken from the double value set.
-	Ldc2WDouble(DoubleInfo),
+	Ldc2WDouble(u64),
 	/// See [Opcode::Ldc2WDouble].
-	Ldc2WLong(LongInfo),
+	Ldc2WLong(i64),
 	LDiv,
 	LLoad(LvIndex),
 	LMul,
@@ -685,8 +716,8 @@ pub enum Opcode {
 	/// created and must be non-negative. The `count1` is the desired length in the first dimension, `count2` in the second, etc.
 	///
 	/// ...; todo: fill in
-	MultiANewArray(VerificationType, usize),
-	New(VerificationType),
+	MultiANewArray(ClassName, usize),
+	New(ClassName),
 	NewArray { a_type: ArrayType },
 	/// Do nothing.
 	///
@@ -700,6 +731,8 @@ pub enum Opcode {
 	Pop2,
 	PutField(FieldRefInfo),
 	PutStatic(FieldRefInfo),
+	/// Return from subroutine, to the `returnAddress` in the local variable. Only allowed before version 51.0.
+	Ret(LvIndex),
 	Return,
 	SALoad,
 	SAStore,
@@ -792,6 +825,223 @@ pub enum Opcode {
 }
 
 impl Opcode {
+	/// Returns the targets this instruction may branch to. The next instruction is not included, see [Opcode::falls_through] for that.
+	pub fn branch_targets(&self) -> Vec<&BranchTarget> {
+		match self {
+			Opcode::Goto(target) | Opcode::Jsr(target) |
+			Opcode::IfACmpEq(target) | Opcode::IfACmpNe(target) |
+			Opcode::IfICmpEq(target) | Opcode::IfICmpGe(target) | Opcode::IfICmpGt(target) |
+			Opcode::IfICmpLe(target) | Opcode::IfICmpLt(target) | Opcode::IfICmpNe(target) |
+			Opcode::IfEq(target) | Opcode::IfGe(target) | Opcode::IfGt(target) |
+			Opcode::IfLe(target) | Opcode::IfLt(target) | Opcode::IfNe(target) |
+			Opcode::IfNonNull(target) | Opcode::IfNull(target) => vec![target],
+			Opcode::LookupSwitch { default_target, targets, .. } => {
+				core::iter::once(default_target).chain(targets.iter().map(|(_, target)| target)).collect()
+			},
+			Opcode::TableSwitch { default_target, targets, .. } => {
+				core::iter::once(default_target).chain(targets).collect()
+			},
+			_ => Vec::new(),
+		}
+	}
+
+	/// Like [Opcode::branch_targets], for moving the targets.
+	pub fn branch_targets_mut(&mut self) -> Vec<&mut BranchTarget> {
+		match self {
+			Opcode::Goto(target) | Opcode::Jsr(target) |
+			Opcode::IfACmpEq(target) | Opcode::IfACmpNe(target) |
+			Opcode::IfICmpEq(target) | Opcode::IfICmpGe(target) | Opcode::IfICmpGt(target) |
+			Opcode::IfICmpLe(target) | Opcode::IfICmpLt(target) | Opcode::IfICmpNe(target) |
+			Opcode::IfEq(target) | Opcode::IfGe(target) | Opcode::IfGt(target) |
+			Opcode::IfLe(target) | Opcode::IfLt(target) | Opcode::IfNe(target) |
+			Opcode::IfNonNull(target) | Opcode::IfNull(target) => vec![target],
+			Opcode::LookupSwitch { default_target, targets, .. } => {
+				core::iter::once(default_target).chain(targets.iter_mut().map(|(_, target)| target)).collect()
+			},
+			Opcode::TableSwitch { default_target, targets, .. } => {
+				core::iter::once(default_target).chain(targets).collect()
+			},
+			_ => Vec::new(),
+		}
+	}
+
+	/// Returns the mnemonic of the instruction as in chapter 6, like `invokevirtual`. As the wide and short forms are parsed into the same opcode, these
+	/// get the name of the general form, like `iload` for `iload_0` and `ldc` for `ldc_w`.
+	pub fn mnemonic(&self) -> &'static str {
+		match self {
+			Opcode::AALoad => "aaload",
+			Opcode::AAStore => "aastore",
+			Opcode::AConstNull => "aconst_null",
+			Opcode::ALoad(..) => "aload",
+			Opcode::ANewArray(..) => "anewarray",
+			Opcode::AReturn => "areturn",
+			Opcode::ArrayLength => "arraylength",
+			Opcode::AStore(..) => "astore",
+			Opcode::AThrow => "athrow",
+			Opcode::BALoad => "baload",
+			Opcode::BAStore => "bastore",
+			Opcode::BIPush(..) => "bipush",
+			Opcode::Breakpoint => "breakpoint",
+			Opcode::CALoad => "caload",
+			Opcode::CAStore => "castore",
+			Opcode::CheckCast(..) => "checkcast",
+			Opcode::D2f => "d2f",
+			Opcode::D2i => "d2i",
+			Opcode::D2l => "d2l",
+			Opcode::DAdd => "dadd",
+			Opcode::DALoad => "daload",
+			Opcode::DAStore => "dastore",
+			Opcode::DCmpG => "dcmpg",
+			Opcode::DCmpL => "dcmpl",
+			Opcode::DConst0 => "dconst_0",
+			Opcode::DConst1 => "dconst_1",
+			Opcode::DDiv => "ddiv",
+			Opcode::DLoad(..) => "dload",
+			Opcode::DMul => "dmul",
+			Opcode::DNeg => "dneg",
+			Opcode::DRem => "drem",
+			Opcode::DReturn => "dreturn",
+			Opcode::DStore(..) => "dstore",
+			Opcode::DSub => "dsub",
+			Opcode::Dup => "dup",
+			Opcode::DupX1 => "dup_x1",
+			Opcode::DupX2 => "dup_x2",
+			Opcode::Dup2 => "dup2",
+			Opcode::Dup2X1 => "dup2_x1",
+			Opcode::Dup2X2 => "dup2_x2",
+			Opcode::F2d => "f2d",
+			Opcode::F2i => "f2i",
+			Opcode::F2l => "f2l",
+			Opcode::FAdd => "fadd",
+			Opcode::FALoad => "faload",
+			Opcode::FAStore => "fastore",
+			Opcode::FCmpG => "fcmpg",
+			Opcode::FCmpL => "fcmpl",
+			Opcode::FConst0 => "fconst_0",
+			Opcode::FConst1 => "fconst_1",
+			Opcode::FConst2 => "fconst_2",
+			Opcode::FDiv => "fdiv",
+			Opcode::FLoad(..) => "fload",
+			Opcode::FMul => "fmul",
+			Opcode::FNeg => "fneg",
+			Opcode::FRem => "frem",
+			Opcode::FReturn => "freturn",
+			Opcode::FStore(..) => "fstore",
+			Opcode::FSub => "fsub",
+			Opcode::GetField(..) => "getfield",
+			Opcode::GetStatic(..) => "getstatic",
+			Opcode::Goto(..) => "goto",
+			Opcode::I2b => "i2b",
+			Opcode::I2c => "i2c",
+			Opcode::I2d => "i2d",
+			Opcode::I2f => "i2f",
+			Opcode::I2l => "i2l",
+			Opcode::I2s => "i2s",
+			Opcode::IAdd => "iadd",
+			Opcode::IALoad => "iaload",
+			Opcode::IAnd => "iand",
+			Opcode::IAStore => "iastore",
+			Opcode::IConstM1 => "iconst_m1",
+			Opcode::IConst0 => "iconst_0",
+			Opcode::IConst1 => "iconst_1",
+			Opcode::IConst2 => "iconst_2",
+			Opcode::IConst3 => "iconst_3",
+			Opcode::IConst4 => "iconst_4",
+			Opcode::IConst5 => "iconst_5",
+			Opcode::IDiv => "idiv",
+			Opcode::IfACmpEq(..) => "if_acmpeq",
+			Opcode::IfACmpNe(..) => "if_acmpne",
+			Opcode::IfICmpEq(..) => "if_icmpeq",
+			Opcode::IfICmpGe(..) => "if_icmpge",
+			Opcode::IfICmpGt(..) => "if_icmpgt",
+			Opcode::IfICmpLe(..) => "if_icmple",
+			Opcode::IfICmpLt(..) => "if_icmplt",
+			Opcode::IfICmpNe(..) => "if_icmpne",
+			Opcode::IfEq(..) => "ifeq",
+			Opcode::IfGe(..) => "ifge",
+			Opcode::IfGt(..) => "ifgt",
+			Opcode::IfLe(..) => "ifle",
+			Opcode::IfLt(..) => "iflt",
+			Opcode::IfNe(..) => "ifne",
+			Opcode::IfNonNull(..) => "ifnonnull",
+			Opcode::IfNull(..) => "ifnull",
+			Opcode::IInc { .. } => "iinc",
+			Opcode::ILoad(..) => "iload",
+			Opcode::ImpDep1 => "impdep1",
+			Opcode::ImpDep2 => "impdep2",
+			Opcode::IMul => "imul",
+			Opcode::INeg => "ineg",
+			Opcode::InstanceOf(..) => "instanceof",
+			Opcode::InvokeDynamic { .. } => "invokedynamic",
+			Opcode::InvokeInterface { .. } => "invokeinterface",
+			Opcode::InvokeSpecial(..) => "invokespecial",
+			Opcode::InvokeStatic(..) => "invokestatic",
+			Opcode::InvokeVirtual(..) => "invokevirtual",
+			Opcode::IOr => "ior",
+			Opcode::IRem => "irem",
+			Opcode::IReturn => "ireturn",
+			Opcode::IShl => "ishl",
+			Opcode::IShr => "ishr",
+			Opcode::IStore(..) => "istore",
+			Opcode::ISub => "isub",
+			Opcode::IUShr => "iushr",
+			Opcode::IXor => "ixor",
+			Opcode::Jsr(..) => "jsr",
+			Opcode::L2d => "l2d",
+			Opcode::L2f => "l2f",
+			Opcode::L2i => "l2i",
+			Opcode::LAdd => "ladd",
+			Opcode::LALoad => "laload",
+			Opcode::LAnd => "land",
+			Opcode::LAStore => "lastore",
+			Opcode::LCmp => "lcmp",
+			Opcode::LConst0 => "lconst_0",
+			Opcode::LConst1 => "lconst_1",
+			Opcode::LdcInt(..) | Opcode::LdcFloat(..) | Opcode::LdcReferenceString(..) | Opcode::LdcReferenceClass(..) | Opcode::LdcReferenceMethodType(..) | Opcode::LdcReferenceMethodHandle(..) => "ldc",
+			Opcode::Ldc2WDouble(..) | Opcode::Ldc2WLong(..) => "ldc2_w",
+			Opcode::LDiv => "ldiv",
+			Opcode::LLoad(..) => "lload",
+			Opcode::LMul => "lmul",
+			Opcode::LNeg => "lneg",
+			Opcode::LookupSwitch { .. } => "lookupswitch",
+			Opcode::LOr => "lor",
+			Opcode::LRem => "lrem",
+			Opcode::LReturn => "lreturn",
+			Opcode::LShl => "lshl",
+			Opcode::LShr => "lshr",
+			Opcode::LStore(..) => "lstore",
+			Opcode::LSub => "lsub",
+			Opcode::LUShr => "lushr",
+			Opcode::LXor => "lxor",
+			Opcode::MonitorEnter => "monitorenter",
+			Opcode::MonitorExit => "monitorexit",
+			Opcode::MultiANewArray(..) => "multianewarray",
+			Opcode::New(..) => "new",
+			Opcode::NewArray { .. } => "newarray",
+			Opcode::Nop => "nop",
+			Opcode::Pop => "pop",
+			Opcode::Pop2 => "pop2",
+			Opcode::PutField(..) => "putfield",
+			Opcode::PutStatic(..) => "putstatic",
+			Opcode::Ret(..) => "ret",
+			Opcode::Return => "return",
+			Opcode::SALoad => "saload",
+			Opcode::SAStore => "sastore",
+			Opcode::SIPush(..) => "sipush",
+			Opcode::Swap => "swap",
+			Opcode::TableSwitch { .. } => "tableswitch",
+		}
+	}
+
+	/// Returns whether execution may continue with the next instruction after this one. This is `false` for unconditional jumps, switches, returns,
+	/// [Opcode::Ret] and [Opcode::AThrow]. [Opcode::Jsr] falls through like a call, as the subroutine returns to the instruction after it.
+	pub fn falls_through(&self) -> bool {
+		!matches!(self,
+			Opcode::Goto(_) | Opcode::Ret(_) | Opcode::LookupSwitch { .. } | Opcode::TableSwitch { .. } | Opcode::AThrow |
+			Opcode::Return | Opcode::AReturn | Opcode::DReturn | Opcode::FReturn | Opcode::IReturn | Opcode::LReturn
+		)
+	}
+
 	pub(crate) fn parse(reader: &mut impl CodeReader, pool: &Pool) -> Result<Opcode> {
 		match reader.read_u8()? {
 			0x32 => Ok(Opcode::AALoad),
@@ -917,7 +1167,7 @@ impl Opcode {
 			0xc6 => Ok(Opcode::IfNull(reader.read_i16_branchoffset()?)),
 			0x84 => Ok(Opcode::IInc {
 				lv_index: LvIndex(reader.read_u8_as_usize()?),
-				const_: reader.read_u8()? as i32,
+				const_: reader.read_i8()? as i32,
 			}),
 			0x15 => Ok(Opcode::ILoad(LvIndex(reader.read_u8_as_usize()?))),
 			0x1a => Ok(Opcode::ILoad(LvIndex(0))),
@@ -955,8 +1205,8 @@ impl Opcode {
 			0x64 => Ok(Opcode::ISub),
 			0x7c => Ok(Opcode::IUShr),
 			0x82 => Ok(Opcode::IXor),
-			0xa8 => bail!("jsr instruction is not legal in class files of version 52.0 or greater"),
-			0xc9 => bail!("jsr_w instruction is not legal in class files of version 52.0 or greater"),
+			0xa8 => Ok(Opcode::Jsr(reader.read_i16_branchoffset()?)),
+			0xc9 => Ok(Opcode::Jsr(reader.read_i32_branchoffset()?)),
 			0x8a => Ok(Opcode::L2d),
 			0x89 => Ok(Opcode::L2f),
 			0x88 => Ok(Opcode::L2i),
@@ -975,12 +1225,11 @@ impl Opcode {
 				};
 
 				match pool.get::<&PoolEntry>(cp_index)? {
-					// TODO: when we have proper int,float,long,double types, we can update this (see also ldc2_w)
-					PoolEntry::Integer(_)         => Ok(Opcode::LdcInt                  (pool.get(cp_index)?)),
-					PoolEntry::Float(_)           => Ok(Opcode::LdcFloat                (pool.get(cp_index)?)),
-					PoolEntry::String(_)          => Ok(Opcode::LdcReferenceString      (pool.get(cp_index)?)),
+					PoolEntry::Integer(value)     => Ok(Opcode::LdcInt                  (*value as i32)),
+					PoolEntry::Float(bits)        => Ok(Opcode::LdcFloat                (*bits)),
+					PoolEntry::String(index)      => Ok(Opcode::LdcReferenceString      (pool.get::<&Vec<u8>>(*index)?.clone())),
 					PoolEntry::ClassName(_)       => Ok(Opcode::LdcReferenceClass       (pool.get(cp_index)?)),
-					PoolEntry::MethodType(_)      => Ok(Opcode::LdcReferenceMethodType  (pool.get(cp_index)?)),
+					PoolEntry::MethodType(index)  => Ok(Opcode::LdcReferenceMethodType  (pool.get(*index)?)),
 					PoolEntry::MethodHandle(_, _) => Ok(Opcode::LdcReferenceMethodHandle(pool.get(cp_index)?)),
 					entry => bail!("ldc/ldc_w can only be used for int/float/String/Class/method type/method handle, got: {entry:?}"),
 				}
@@ -988,8 +1237,8 @@ impl Opcode {
 			0x14 => { // ldc2_w
 				let cp_index = reader.read_u16_as_usize()?;
 				match pool.get::<&PoolEntry>(cp_index)? {
-					PoolEntry::Long {..}   => Ok(Opcode::Ldc2WLong  (pool.get(cp_index)?)),
-					PoolEntry::Double {..} => Ok(Opcode::Ldc2WDouble(pool.get(cp_index)?)),
+					PoolEntry::Long { high, low }   => Ok(Opcode::Ldc2WLong  (((*high as u64) << 32 | *low as u64) as i64)),
+					PoolEntry::Double { high, low } => Ok(Opcode::Ldc2WDouble((*high as u64) << 32 | *low as u64)),
 					entry => bail!("ldc2_w can only be used for long/double, got: {entry:?}"),
 				}
 			},
@@ -1045,7 +1294,7 @@ impl Opcode {
 			0x58 => Ok(Opcode::Pop2),
 			0xb5 => Ok(Opcode::PutField(pool.get(reader.read_u16_as_usize()?)?)),
 			0xb3 => Ok(Opcode::PutStatic(pool.get(reader.read_u16_as_usize()?)?)),
-			0xa9 => bail!("ret instruction is not legal in class files of version 52.0 or greater"),
+			0xa9 => Ok(Opcode::Ret(LvIndex(reader.read_u8_as_usize()?))),
 			0xb1 => Ok(Opcode::Return),
 			0x35 => Ok(Opcode::SALoad),
 			0x56 => Ok(Opcode::SAStore),
@@ -1085,7 +1334,7 @@ impl Opcode {
 					0x36 => Ok(Opcode::IStore(LvIndex(reader.read_u16_as_usize()?))),
 					0x16 => Ok(Opcode::LLoad(LvIndex(reader.read_u16_as_usize()?))),
 					0x37 => Ok(Opcode::LStore(LvIndex(reader.read_u16_as_usize()?))),
-					0xa0 => bail!("wide ret instruction is not legal in class files of version 52.0 or greater"),
+					0xa9 => Ok(Opcode::Ret(LvIndex(reader.read_u16_as_usize()?))),
 					0x84 => Ok(Opcode::IInc {
 						lv_index: LvIndex(reader.read_u16_as_usize()?),
 						const_: reader.read_i16()? as i32,
@@ -0,0 +1,47 @@
+//! The reading the parser needs: `std::io::Read` with the `std` feature, and a minimal replacement for it without.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, Read, Result};
+
+#[cfg(not(feature = "std"))]
+pub use self::no_std::{Error, Read, Result};
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+	use core::fmt::{Display, Formatter};
+
+	/// Never returned by reading from a slice, it only exists to keep the signature of `std::io::Read`.
+	#[derive(Debug)]
+	pub struct Error;
+
+	impl Display for Error {
+		fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+			f.write_str("read error")
+		}
+	}
+
+	impl core::error::Error for Error {}
+
+	pub type Result<T> = core::result::Result<T, Error>;
+
+	/// The subset of `std::io::Read` used by the parser, see there.
+	pub trait Read {
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+	}
+
+	impl Read for &[u8] {
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+			let n = buf.len().min(self.len());
+			let (read, rest) = self.split_at(n);
+			buf[..n].copy_from_slice(read);
+			*self = rest;
+			Ok(n)
+		}
+	}
+
+	impl<R: Read + ?Sized> Read for &mut R {
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+			(**self).read(buf)
+		}
+	}
+}
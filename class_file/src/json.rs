@@ -0,0 +1,35 @@
+//! Just enough JSON for the exports of the analyses, which only write strings, numbers, arrays and objects.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+/// Appends `bytes` as a JSON string. Names in class files are modified UTF-8, which is decoded lossily.
+pub(crate) fn string(out: &mut String, bytes: &[u8]) {
+	out.push('"');
+	for char in String::from_utf8_lossy(bytes).chars() {
+		match char {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			char if (char as u32) < 0x20 => {
+				let _ = write!(out, "\\u{:04x}", char as u32);
+			},
+			char => out.push(char),
+		}
+	}
+	out.push('"');
+}
+
+/// Appends the items as a JSON array, writing each with `item`.
+pub(crate) fn array<T>(out: &mut String, items: impl IntoIterator<Item=T>, mut item: impl FnMut(&mut String, T)) {
+	out.push('[');
+	for (index, value) in items.into_iter().enumerate() {
+		if index > 0 {
+			out.push(',');
+		}
+		item(out, value);
+	}
+	out.push(']');
+}
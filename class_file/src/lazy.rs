@@ -0,0 +1,731 @@
+//! Lazy parsing of class files, borrowing from the bytes.
+//!
+//! [ClassFileRef::parse] only indexes the constant pool and finds where the members and attributes are, without decoding or copying anything. Names,
+//! descriptors, [constants](ClassFileRef::constant) and [annotations](ClassFileRef::annotations) are then read straight from the bytes, resolving
+//! constant pool indices as they are met. This makes looking at a few things of many classes, like the annotations of all classes on a class path,
+//! cheap.
+//!
+//! Only the conversions to the owned structures, like [ClassFileRef::to_class_file], [MemberRef::to_method_info] or [MemberRef::code], decode the
+//! whole constant pool, once per class.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::OnceCell;
+use anyhow::{anyhow, bail, Context, Result};
+use crate::{ClassFile, FieldInfo, MethodInfo};
+use crate::access::ClassInfoAccess;
+use crate::annotation::RetentionPolicy;
+use crate::cp::Pool;
+use crate::cp::attribute::{Annotation, AnnotationElementValue, AnnotationElementValuePair, AttributeInfo, CodeAttribute};
+
+const UTF8: u8 = 1;
+const INTEGER: u8 = 3;
+const FLOAT: u8 = 4;
+const LONG: u8 = 5;
+const DOUBLE: u8 = 6;
+const CLASS: u8 = 7;
+const STRING: u8 = 8;
+const FIELD_REF: u8 = 9;
+const METHOD_REF: u8 = 10;
+const INTERFACE_METHOD_REF: u8 = 11;
+const NAME_AND_TYPE: u8 = 12;
+const METHOD_HANDLE: u8 = 15;
+const METHOD_TYPE: u8 = 16;
+const DYNAMIC: u8 = 17;
+const INVOKE_DYNAMIC: u8 = 18;
+const MODULE: u8 = 19;
+const PACKAGE: u8 = 20;
+
+/// Reads big endian numbers from a byte slice, keeping track of the position.
+#[derive(Debug, Clone, Copy)]
+struct Cursor<'a> {
+	bytes: &'a [u8],
+	position: usize,
+}
+
+impl<'a> Cursor<'a> {
+	fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+		let slice = self.bytes.get(self.position..self.position + length)
+			.ok_or_else(|| anyhow!("unexpected end of class file at {}, expected {length} more bytes", self.position))?;
+		self.position += length;
+		Ok(slice)
+	}
+
+	fn u8(&mut self) -> Result<u8> {
+		Ok(self.take(1)?[0])
+	}
+
+	fn u16(&mut self) -> Result<u16> {
+		Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
+	}
+
+	fn u32(&mut self) -> Result<u32> {
+		Ok(u32::from_be_bytes(self.take(4)?.try_into()?))
+	}
+
+	fn u64(&mut self) -> Result<u64> {
+		Ok(u64::from_be_bytes(self.take(8)?.try_into()?))
+	}
+
+	/// Skips an `attributes_count` and the attributes following it, returning the range they take up.
+	fn attributes(&mut self) -> Result<Attributes<'a>> {
+		let count = self.u16()? as usize;
+		let start = self.position;
+		for _ in 0..count {
+			self.take(2)?;
+			let length = self.u32()? as usize;
+			self.take(length)?;
+		}
+		Ok(Attributes { count, bytes: &self.bytes[start..self.position] })
+	}
+}
+
+/// The undecoded attributes of a class, member or code attribute.
+#[derive(Debug, Clone, Copy)]
+struct Attributes<'a> {
+	count: usize,
+	bytes: &'a [u8],
+}
+
+/// A class file that is decoded as it is looked at. See the [module documentation](self).
+#[derive(Debug)]
+pub struct ClassFileRef<'a> {
+	bytes: &'a [u8],
+	/// The offset of each constant pool entry, `0` for the unusable indices.
+	pool_offsets: Vec<usize>,
+	/// The constant pool is only decoded when converting to the owned structures.
+	pool: OnceCell<Pool>,
+	minor_version: u16,
+	major_version: u16,
+	access_flags: u16,
+	this_class: u16,
+	super_class: u16,
+	interfaces: &'a [u8],
+	fields: Vec<&'a [u8]>,
+	methods: Vec<&'a [u8]>,
+	attributes: Attributes<'a>,
+}
+
+impl<'a> ClassFileRef<'a> {
+	/// Finds the parts of the class file, checking that they are all there but without decoding them.
+	pub fn parse(bytes: &'a [u8]) -> Result<ClassFileRef<'a>> {
+		let mut cursor = Cursor { bytes, position: 0 };
+		let magic = cursor.u32()?;
+		if magic != 0xCAFE_BABE {
+			bail!("magic didn't match up: {magic:x}")
+		}
+		let minor_version = cursor.u16()?;
+		let major_version = cursor.u16()?;
+		if major_version < 45 {
+			bail!("we only accept class files with version >= 45.0, this one has: {major_version}.{minor_version}")
+		}
+
+		let count = cursor.u16()? as usize;
+		let mut pool_offsets = Vec::with_capacity(count);
+		pool_offsets.push(0);
+		while pool_offsets.len() < count {
+			pool_offsets.push(cursor.position);
+			let length = match cursor.u8()? {
+				UTF8 => cursor.u16()? as usize,
+				INTEGER | FLOAT => 4,
+				LONG | DOUBLE => {
+					// Long and Double take up two entries each (4.4.5)
+					pool_offsets.push(0);
+					8
+				},
+				CLASS | STRING | METHOD_TYPE | MODULE | PACKAGE => 2,
+				FIELD_REF | METHOD_REF | INTERFACE_METHOD_REF | NAME_AND_TYPE | DYNAMIC | INVOKE_DYNAMIC => 4,
+				METHOD_HANDLE => 3,
+				tag => bail!("unknown constant pool tag {tag}"),
+			};
+			cursor.take(length).context("while parsing the constant pool")?;
+		}
+
+		let access_flags = cursor.u16()?;
+		let this_class = cursor.u16()?;
+		let super_class = cursor.u16()?;
+		let interfaces_count = cursor.u16()? as usize;
+		let interfaces = cursor.take(2 * interfaces_count)?;
+
+		let mut members = || -> Result<Vec<&'a [u8]>> {
+			let count = cursor.u16()? as usize;
+			(0..count)
+				.map(|_| {
+					let start = cursor.position;
+					cursor.take(6)?;
+					cursor.attributes()?;
+					Ok(&bytes[start..cursor.position])
+				})
+				.collect()
+		};
+		let fields = members()?;
+		let methods = members()?;
+		let attributes = cursor.attributes()?;
+		if cursor.position != bytes.len() {
+			bail!("expected end of class file")
+		}
+
+		Ok(ClassFileRef {
+			bytes, pool_offsets, pool: OnceCell::new(),
+			minor_version, major_version, access_flags, this_class, super_class, interfaces, fields, methods, attributes,
+		})
+	}
+
+	/// Returns a cursor at the tag of the constant pool entry at `index`.
+	fn offset(&self, index: u16) -> Result<Cursor<'a>> {
+		let offset = self.pool_offsets.get(index as usize).copied().filter(|&offset| offset != 0)
+			.ok_or_else(|| anyhow!("invalid constant pool index {index} for pool size {}", self.pool_offsets.len()))?;
+		Ok(Cursor { bytes: self.bytes, position: offset })
+	}
+
+	/// Returns a cursor after the tag of the constant pool entry at `index`, checking that it has the tag `tag`.
+	fn entry(&self, index: u16, tag: u8) -> Result<Cursor<'a>> {
+		let mut cursor = self.offset(index)?;
+		let actual = cursor.u8()?;
+		if actual != tag {
+			bail!("expected constant pool entry {index} to have tag {tag}, got {actual}");
+		}
+		Ok(cursor)
+	}
+
+	/// Reads the name and descriptor of the `CONSTANT_NameAndType_info` whose index is at the cursor.
+	fn name_and_type(&self, cursor: &mut Cursor<'a>) -> Result<(&'a [u8], &'a [u8])> {
+		let mut name_and_type = self.entry(cursor.u16()?, NAME_AND_TYPE)?;
+		Ok((self.utf8(name_and_type.u16()?)?, self.utf8(name_and_type.u16()?)?))
+	}
+
+	/// Returns the bytes of the `CONSTANT_Utf8_info` at `index`, in modified UTF-8.
+	pub fn utf8(&self, index: u16) -> Result<&'a [u8]> {
+		let mut cursor = self.entry(index, UTF8)?;
+		let length = cursor.u16()? as usize;
+		cursor.take(length)
+	}
+
+	/// Returns the name of the `CONSTANT_Class_info` at `index`.
+	pub fn class_name(&self, index: u16) -> Result<&'a [u8]> {
+		let name_index = self.entry(index, CLASS)?.u16()?;
+		self.utf8(name_index)
+	}
+
+	/// Returns the constant pool entry at `index`, resolving the entries it refers to except for the reference of a method handle.
+	pub fn constant(&self, index: u16) -> Result<ConstantRef<'a>> {
+		let mut cursor = self.offset(index)?;
+		Ok(match cursor.u8()? {
+			UTF8 => {
+				let length = cursor.u16()? as usize;
+				ConstantRef::Utf8(cursor.take(length)?)
+			},
+			INTEGER => ConstantRef::Integer(cursor.u32()? as i32),
+			FLOAT => ConstantRef::Float(cursor.u32()?),
+			LONG => ConstantRef::Long(cursor.u64()? as i64),
+			DOUBLE => ConstantRef::Double(cursor.u64()?),
+			CLASS => ConstantRef::Class(self.utf8(cursor.u16()?)?),
+			STRING => ConstantRef::String(self.utf8(cursor.u16()?)?),
+			tag @ (FIELD_REF | METHOD_REF | INTERFACE_METHOD_REF) => {
+				let class = self.class_name(cursor.u16()?)?;
+				let (name, descriptor) = self.name_and_type(&mut cursor)?;
+				let member = MemberConstantRef { class, name, descriptor };
+				match tag {
+					FIELD_REF => ConstantRef::FieldRef(member),
+					METHOD_REF => ConstantRef::MethodRef(member),
+					_ => ConstantRef::InterfaceMethodRef(member),
+				}
+			},
+			NAME_AND_TYPE => ConstantRef::NameAndType {
+				name: self.utf8(cursor.u16()?)?,
+				descriptor: self.utf8(cursor.u16()?)?,
+			},
+			METHOD_HANDLE => ConstantRef::MethodHandle {
+				reference_kind: cursor.u8()?,
+				reference_index: cursor.u16()?,
+			},
+			METHOD_TYPE => ConstantRef::MethodType(self.utf8(cursor.u16()?)?),
+			tag @ (DYNAMIC | INVOKE_DYNAMIC) => {
+				let bootstrap_method_attr_index = cursor.u16()?;
+				let (name, descriptor) = self.name_and_type(&mut cursor)?;
+				match tag {
+					DYNAMIC => ConstantRef::Dynamic { bootstrap_method_attr_index, name, descriptor },
+					_ => ConstantRef::InvokeDynamic { bootstrap_method_attr_index, name, descriptor },
+				}
+			},
+			MODULE => ConstantRef::Module(self.utf8(cursor.u16()?)?),
+			PACKAGE => ConstantRef::Package(self.utf8(cursor.u16()?)?),
+			// ClassFileRef::parse rejects other tags
+			tag => unreachable!("constant pool tag {tag}"),
+		})
+	}
+
+	/// Returns the decoded constant pool, decoding it the first time. Only used for the conversions to the owned structures.
+	fn pool(&self) -> Result<&Pool> {
+		if let Some(pool) = self.pool.get() {
+			return Ok(pool);
+		}
+		let pool = Pool::parse(&mut &self.bytes[8..])?;
+		Ok(self.pool.get_or_init(|| pool))
+	}
+
+	/// Returns the `constant_pool_count`, which is one larger than the largest valid index.
+	pub fn constant_pool_count(&self) -> usize {
+		self.pool_offsets.len()
+	}
+
+	/// Returns the tags of the constant pool entries in order, without decoding them.
+	pub fn constant_tags(&self) -> impl Iterator<Item=u8> + '_ {
+		self.pool_offsets.iter().filter(|&&offset| offset != 0).map(|&offset| self.bytes[offset])
+	}
+
+	pub fn minor_version(&self) -> u16 {
+		self.minor_version
+	}
+
+	pub fn major_version(&self) -> u16 {
+		self.major_version
+	}
+
+	pub fn access_flags(&self) -> Result<ClassInfoAccess> {
+		ClassInfoAccess::parse(self.access_flags)
+	}
+
+	pub fn this_class(&self) -> Result<&'a [u8]> {
+		self.class_name(self.this_class)
+	}
+
+	pub fn super_class(&self) -> Result<Option<&'a [u8]>> {
+		match self.super_class {
+			0 => Ok(None),
+			index => self.class_name(index).map(Some),
+		}
+	}
+
+	pub fn interfaces(&self) -> impl Iterator<Item=Result<&'a [u8]>> + '_ {
+		self.interfaces.chunks_exact(2)
+			.map(|index| self.class_name(u16::from_be_bytes([index[0], index[1]])))
+	}
+
+	pub fn fields(&self) -> impl ExactSizeIterator<Item=MemberRef<'_, 'a>> {
+		self.fields.iter().map(|&bytes| MemberRef { class: self, bytes })
+	}
+
+	pub fn methods(&self) -> impl ExactSizeIterator<Item=MemberRef<'_, 'a>> {
+		self.methods.iter().map(|&bytes| MemberRef { class: self, bytes })
+	}
+
+	pub fn attributes(&self) -> impl Iterator<Item=AttributeRef<'_, 'a>> {
+		attributes(self, self.attributes)
+	}
+
+	/// Returns the annotations of the class, the visible ones first, like [ClassFile::annotations].
+	pub fn annotations(&self) -> Result<Vec<(RetentionPolicy, AnnotationRef<'a>)>> {
+		annotations(self.attributes())
+	}
+
+	/// Reads an `annotation` structure (4.7.16).
+	fn annotation(&self, cursor: &mut Cursor<'a>) -> Result<AnnotationRef<'a>> {
+		let annotation_type = self.utf8(cursor.u16()?)?;
+		let count = cursor.u16()?;
+		let element_value_pairs = (0..count)
+			.map(|_| Ok((self.utf8(cursor.u16()?)?, self.element_value(cursor)?)))
+			.collect::<Result<_>>()?;
+		Ok(AnnotationRef { annotation_type, element_value_pairs })
+	}
+
+	/// Reads an `element_value` structure (4.7.16.1).
+	fn element_value(&self, cursor: &mut Cursor<'a>) -> Result<ElementValueRef<'a>> {
+		Ok(match cursor.u8()? {
+			tag @ (b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z') => match (tag, self.constant(cursor.u16()?)?) {
+				(b'B', ConstantRef::Integer(value)) => ElementValueRef::Byte(value as i8),
+				(b'C', ConstantRef::Integer(value)) => ElementValueRef::Char(value as u16),
+				(b'S', ConstantRef::Integer(value)) => ElementValueRef::Short(value as i16),
+				(b'Z', ConstantRef::Integer(value)) => ElementValueRef::Boolean(value != 0),
+				(b'I', ConstantRef::Integer(value)) => ElementValueRef::Int(value),
+				(b'D', ConstantRef::Double(bits)) => ElementValueRef::Double(bits),
+				(b'F', ConstantRef::Float(bits)) => ElementValueRef::Float(bits),
+				(b'J', ConstantRef::Long(value)) => ElementValueRef::Long(value),
+				(_, constant) => bail!("constant {constant:?} doesn't match the annotation element type {:?}", tag as char),
+			},
+			b's' => ElementValueRef::String(self.utf8(cursor.u16()?)?),
+			b'e' => ElementValueRef::Enum {
+				type_name: self.utf8(cursor.u16()?)?,
+				const_name: self.utf8(cursor.u16()?)?,
+			},
+			b'c' => ElementValueRef::Class(self.utf8(cursor.u16()?)?),
+			b'@' => ElementValueRef::Annotation(self.annotation(cursor)?),
+			b'[' => {
+				let count = cursor.u16()?;
+				ElementValueRef::Array((0..count).map(|_| self.element_value(cursor)).collect::<Result<_>>()?)
+			},
+			tag => bail!("unknown annotation element value tag: {tag}"),
+		})
+	}
+
+	/// Decodes the whole class file.
+	pub fn to_class_file(&self) -> Result<ClassFile> {
+		ClassFile::parse(&mut &self.bytes[..])
+	}
+}
+
+fn attributes<'r, 'a>(class: &'r ClassFileRef<'a>, attributes: Attributes<'a>) -> impl Iterator<Item=AttributeRef<'r, 'a>> {
+	let mut cursor = Cursor { bytes: attributes.bytes, position: 0 };
+	(0..attributes.count).map(move |_| {
+		// the lengths were all checked by ClassFileRef::parse
+		let start = cursor.position;
+		cursor.position += 2;
+		let length = cursor.u32().unwrap() as usize;
+		cursor.position += length;
+		AttributeRef { class, bytes: &attributes.bytes[start..cursor.position] }
+	})
+}
+
+/// Returns the annotations from all annotation attributes, the visible ones first.
+fn annotations<'r, 'a: 'r>(attributes: impl Iterator<Item=AttributeRef<'r, 'a>>) -> Result<Vec<(RetentionPolicy, AnnotationRef<'a>)>> {
+	let mut visible = Vec::new();
+	let mut invisible = Vec::new();
+	for attribute in attributes {
+		let (retention, annotations) = match attribute.name()? {
+			b"RuntimeVisibleAnnotations" => (RetentionPolicy::Runtime, &mut visible),
+			b"RuntimeInvisibleAnnotations" => (RetentionPolicy::Class, &mut invisible),
+			_ => continue,
+		};
+		annotations.extend(attribute.annotations()?.into_iter().map(|annotation| (retention, annotation)));
+	}
+	visible.append(&mut invisible);
+	Ok(visible)
+}
+
+/// A field or method of a [ClassFileRef].
+#[derive(Debug, Clone, Copy)]
+pub struct MemberRef<'r, 'a> {
+	class: &'r ClassFileRef<'a>,
+	/// The whole `field_info` or `method_info` structure.
+	bytes: &'a [u8],
+}
+
+impl<'r, 'a> MemberRef<'r, 'a> {
+	fn u16_at(&self, offset: usize) -> u16 {
+		u16::from_be_bytes([self.bytes[offset], self.bytes[offset + 1]])
+	}
+
+	/// Returns the raw access flags, as they are interpreted differently for fields and methods.
+	pub fn access_flags(&self) -> u16 {
+		self.u16_at(0)
+	}
+
+	pub fn name(&self) -> Result<&'a [u8]> {
+		self.class.utf8(self.u16_at(2))
+	}
+
+	pub fn descriptor(&self) -> Result<&'a [u8]> {
+		self.class.utf8(self.u16_at(4))
+	}
+
+	pub fn attributes(&self) -> impl Iterator<Item=AttributeRef<'r, 'a>> {
+		let count = self.u16_at(6) as usize;
+		attributes(self.class, Attributes { count, bytes: &self.bytes[8..] })
+	}
+
+	/// Returns the annotations of the member, the visible ones first.
+	pub fn annotations(&self) -> Result<Vec<(RetentionPolicy, AnnotationRef<'a>)>> {
+		annotations(self.attributes())
+	}
+
+	/// Decodes the `Code` attribute of the method, if it has one.
+	pub fn code(&self) -> Result<Option<CodeAttribute>> {
+		for attribute in self.attributes() {
+			if attribute.name()? == b"Code" {
+				return match attribute.to_attribute_info()? {
+					AttributeInfo::Code(code) => Ok(Some(code)),
+					_ => unreachable!(),
+				};
+			}
+		}
+		Ok(None)
+	}
+
+	/// Decodes the member as a field.
+	pub fn to_field_info(&self) -> Result<FieldInfo> {
+		FieldInfo::parse(&mut &self.bytes[..], self.class.pool()?)
+	}
+
+	/// Decodes the member as a method, including its code.
+	pub fn to_method_info(&self) -> Result<MethodInfo> {
+		MethodInfo::parse(&mut &self.bytes[..], self.class.pool()?)
+	}
+}
+
+/// An attribute of a [ClassFileRef] or one of its members.
+#[derive(Debug, Clone, Copy)]
+pub struct AttributeRef<'r, 'a> {
+	class: &'r ClassFileRef<'a>,
+	/// The whole `attribute_info` structure.
+	bytes: &'a [u8],
+}
+
+impl<'a> AttributeRef<'_, 'a> {
+	pub fn name(&self) -> Result<&'a [u8]> {
+		self.class.utf8(u16::from_be_bytes([self.bytes[0], self.bytes[1]]))
+	}
+
+	/// Returns the `info` of the attribute, without the name and length.
+	pub fn info(&self) -> &'a [u8] {
+		&self.bytes[6..]
+	}
+
+	/// Reads the annotations of a `RuntimeVisibleAnnotations` or `RuntimeInvisibleAnnotations` attribute.
+	pub fn annotations(&self) -> Result<Vec<AnnotationRef<'a>>> {
+		let name = self.name()?;
+		if name != b"RuntimeVisibleAnnotations" && name != b"RuntimeInvisibleAnnotations" {
+			bail!("expected an annotations attribute, got {}", String::from_utf8_lossy(name));
+		}
+		let mut cursor = Cursor { bytes: self.info(), position: 0 };
+		let count = cursor.u16()?;
+		let annotations = (0..count).map(|_| self.class.annotation(&mut cursor)).collect::<Result<_>>()?;
+		if cursor.position != cursor.bytes.len() {
+			bail!("expected end of attribute {}", String::from_utf8_lossy(name));
+		}
+		Ok(annotations)
+	}
+
+	/// Decodes the attribute.
+	pub fn to_attribute_info(&self) -> Result<AttributeInfo> {
+		AttributeInfo::parse(&mut &self.bytes[..], self.class.pool()?)
+	}
+}
+
+/// A constant pool entry of a [ClassFileRef], with the entries it refers to resolved. Floating point values are kept as their bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstantRef<'a> {
+	/// A string in modified UTF-8.
+	Utf8(&'a [u8]),
+	Integer(i32),
+	Float(u32),
+	Long(i64),
+	Double(u64),
+	Class(&'a [u8]),
+	String(&'a [u8]),
+	FieldRef(MemberConstantRef<'a>),
+	MethodRef(MemberConstantRef<'a>),
+	InterfaceMethodRef(MemberConstantRef<'a>),
+	NameAndType {
+		name: &'a [u8],
+		descriptor: &'a [u8],
+	},
+	MethodHandle {
+		reference_kind: u8,
+		/// The index of the field or method reference, see [ClassFileRef::constant].
+		reference_index: u16,
+	},
+	/// A method descriptor.
+	MethodType(&'a [u8]),
+	Dynamic {
+		bootstrap_method_attr_index: u16,
+		name: &'a [u8],
+		descriptor: &'a [u8],
+	},
+	InvokeDynamic {
+		bootstrap_method_attr_index: u16,
+		name: &'a [u8],
+		descriptor: &'a [u8],
+	},
+	Module(&'a [u8]),
+	Package(&'a [u8]),
+}
+
+/// The class, name and descriptor of a field or method reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemberConstantRef<'a> {
+	pub class: &'a [u8],
+	pub name: &'a [u8],
+	pub descriptor: &'a [u8],
+}
+
+/// An annotation read from a [ClassFileRef], borrowing its strings from the class file. See [Annotation] for the decoded one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotationRef<'a> {
+	/// The field descriptor of the annotation interface, like `Ljava/lang/Deprecated;`.
+	pub annotation_type: &'a [u8],
+	/// The names and values of the elements.
+	pub element_value_pairs: Vec<(&'a [u8], ElementValueRef<'a>)>,
+}
+
+impl<'a> AnnotationRef<'a> {
+	/// Returns whether the annotation has the type with the field descriptor `descriptor`, like `Ljava/lang/Deprecated;`.
+	pub fn is(&self, descriptor: &str) -> bool {
+		self.annotation_type == descriptor.as_bytes()
+	}
+
+	/// Returns the value of the element `name`, see [Annotation::get].
+	pub fn get(&self, name: &str) -> Option<&ElementValueRef<'a>> {
+		self.element_value_pairs.iter()
+			.find(|(element_name, _)| *element_name == name.as_bytes())
+			.map(|(_, value)| value)
+	}
+
+	pub fn to_annotation(&self) -> Annotation {
+		Annotation {
+			annotation_type: self.annotation_type.to_vec(),
+			element_value_pairs: self.element_value_pairs.iter()
+				.map(|(element_name, value)| AnnotationElementValuePair { element_name: element_name.to_vec(), value: value.to_element_value() })
+				.collect(),
+		}
+	}
+}
+
+/// The value of an annotation element, borrowing its strings from the class file. See [AnnotationElementValue] for the decoded one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElementValueRef<'a> {
+	Byte(i8),
+	Char(u16),
+	Double(u64),
+	Float(u32),
+	Int(i32),
+	Long(i64),
+	Short(i16),
+	Boolean(bool),
+	/// A string in modified UTF-8.
+	String(&'a [u8]),
+	Enum {
+		/// The field descriptor of the enum class.
+		type_name: &'a [u8],
+		const_name: &'a [u8],
+	},
+	/// A class literal, given as return descriptor.
+	Class(&'a [u8]),
+	Annotation(AnnotationRef<'a>),
+	Array(Vec<ElementValueRef<'a>>),
+}
+
+impl ElementValueRef<'_> {
+	pub fn to_element_value(&self) -> AnnotationElementValue {
+		match *self {
+			Self::Byte(value) => AnnotationElementValue::Byte(value),
+			Self::Char(value) => AnnotationElementValue::Char(value),
+			Self::Double(bits) => AnnotationElementValue::Double(bits),
+			Self::Float(bits) => AnnotationElementValue::Float(bits),
+			Self::Int(value) => AnnotationElementValue::Int(value),
+			Self::Long(value) => AnnotationElementValue::Long(value),
+			Self::Short(value) => AnnotationElementValue::Short(value),
+			Self::Boolean(value) => AnnotationElementValue::Boolean(value),
+			Self::String(value) => AnnotationElementValue::String(value.to_vec()),
+			Self::Enum { type_name, const_name } => AnnotationElementValue::Enum { type_name: type_name.to_vec(), const_name: const_name.to_vec() },
+			Self::Class(descriptor) => AnnotationElementValue::Class(descriptor.to_vec()),
+			Self::Annotation(ref annotation) => AnnotationElementValue::Annotation(annotation.to_annotation()),
+			Self::Array(ref values) => AnnotationElementValue::Array(values.iter().map(ElementValueRef::to_element_value).collect()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::ClassFile;
+	use crate::annotation::RetentionPolicy;
+	use crate::cp::{MethodRefInfo, PoolEntry};
+	use crate::cp::attribute::{Annotation, AnnotationElementValue, AnnotationElementValuePair, AttributeInfo, RuntimeInvisibleAnnotationsAttribute};
+	use crate::cp::attribute::RuntimeVisibleAnnotationsAttribute;
+	use crate::descriptor::MethodDescriptor;
+	use crate::fixture;
+	use crate::lazy::{ClassFileRef, ConstantRef, ElementValueRef};
+	use crate::name::{ClassName, MethodName};
+
+	#[test]
+	fn matches_eager_parsing() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test3.class");
+		let class = ClassFileRef::parse(bytes).unwrap();
+		let eager = ClassFile::parse(&mut &bytes[..]).unwrap();
+
+		assert_eq!(class.major_version(), eager.major_version);
+		assert_eq!(class.this_class().unwrap(), eager.this_class.as_bytes());
+		assert_eq!(class.super_class().unwrap(), eager.super_class.as_ref().map(|class| class.as_bytes()));
+		assert_eq!(class.interfaces().count(), eager.interfaces.len());
+		assert_eq!(class.fields().len(), eager.fields.len());
+		assert_eq!(class.methods().len(), eager.methods.len());
+		assert_eq!(class.attributes().count(), eager.attributes.len());
+
+		for (method, eager) in class.methods().zip(&eager.methods) {
+			assert_eq!(method.name().unwrap(), eager.name.as_bytes());
+			assert_eq!(method.code().unwrap(), eager.code);
+			assert_eq!(&method.to_method_info().unwrap(), eager);
+		}
+		assert_eq!(class.to_class_file().unwrap(), eager);
+	}
+
+	#[test]
+	fn constants_without_decoding_the_pool() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test3.class");
+		let class = ClassFileRef::parse(bytes).unwrap();
+		let eager = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap().1;
+
+		let mut method_refs = 0;
+		for index in 1..class.constant_pool_count() as u16 {
+			let Ok(constant) = class.constant(index) else {
+				// the second index of a Long or Double
+				assert_eq!(eager.get::<&PoolEntry>(index as usize).unwrap(), &PoolEntry::Unusable);
+				continue;
+			};
+			if let ConstantRef::MethodRef(method) = constant {
+				let eager: MethodRefInfo = eager.get(index as usize).unwrap();
+				assert_eq!(ClassName::from(method.class), eager.class);
+				assert_eq!(MethodName::from(method.name), eager.name);
+				assert_eq!(MethodDescriptor::try_from(method.descriptor).unwrap(), eager.descriptor);
+				method_refs += 1;
+			}
+		}
+		assert!(method_refs > 0);
+		assert!(class.constant(class.constant_pool_count() as u16).is_err());
+		assert!(class.pool.get().is_none());
+	}
+
+	#[test]
+	fn annotations_without_decoding_the_pool() {
+		// @Mapping(value = {"/a", "/b"}, timeout = 30L, method = Method.GET, body = String.class, retry = @Retry(3))
+		let retry = Annotation {
+			annotation_type: b"Lcom/example/Retry;".to_vec(),
+			element_value_pairs: vec![AnnotationElementValuePair { element_name: b"value".to_vec(), value: AnnotationElementValue::Int(3) }],
+		};
+		let pair = |name: &str, value| AnnotationElementValuePair { element_name: name.as_bytes().to_vec(), value };
+		let mapping = Annotation {
+			annotation_type: b"Lcom/example/Mapping;".to_vec(),
+			element_value_pairs: vec![
+				pair("value", AnnotationElementValue::Array(vec![
+					AnnotationElementValue::String(b"/a".to_vec()),
+					AnnotationElementValue::String(b"/b".to_vec()),
+				])),
+				pair("timeout", AnnotationElementValue::Long(30)),
+				pair("method", AnnotationElementValue::Enum { type_name: b"Lcom/example/Method;".to_vec(), const_name: b"GET".to_vec() }),
+				pair("body", AnnotationElementValue::Class(b"Ljava/lang/String;".to_vec())),
+				pair("retry", AnnotationElementValue::Annotation(retry)),
+				pair("ratio", AnnotationElementValue::Double(0.5f64.to_bits())),
+				pair("flag", AnnotationElementValue::Boolean(true)),
+			],
+		};
+		let generated = Annotation { annotation_type: b"Lcom/example/Generated;".to_vec(), element_value_pairs: Vec::new() };
+
+		let mut method = fixture::method(0x0401, b"handle", b"()V");
+		method.attributes.push(AttributeInfo::RuntimeVisibleAnnotations(RuntimeVisibleAnnotationsAttribute { annotations: vec![mapping.clone()] }));
+		let mut eager = fixture::class(0x0601, b"Handler", b"java/lang/Object", &[], Vec::new(), vec![method]);
+		eager.attributes.push(AttributeInfo::RuntimeInvisibleAnnotations(RuntimeInvisibleAnnotationsAttribute { annotations: vec![generated.clone()] }));
+		let bytes = eager.to_bytes(None).unwrap();
+		let class = ClassFileRef::parse(&bytes).unwrap();
+
+		let annotations = class.annotations().unwrap();
+		assert_eq!(annotations.len(), 1);
+		assert_eq!(annotations[0].0, RetentionPolicy::Class);
+		assert_eq!(annotations[0].1.to_annotation(), generated);
+
+		let method = class.methods().next().unwrap();
+		let annotations = method.annotations().unwrap();
+		assert_eq!(annotations.len(), 1);
+		let (retention, annotation) = &annotations[0];
+		assert_eq!(*retention, RetentionPolicy::Runtime);
+		assert!(annotation.is("Lcom/example/Mapping;"));
+		assert_eq!(annotation.get("timeout"), Some(&ElementValueRef::Long(30)));
+		assert_eq!(annotation.get("body"), Some(&ElementValueRef::Class(b"Ljava/lang/String;")));
+		assert_eq!(annotation.to_annotation(), mapping);
+		assert!(class.pool.get().is_none());
+	}
+
+	#[test]
+	fn truncated() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test3.class");
+		assert!(ClassFileRef::parse(&bytes[..bytes.len() - 1]).is_err());
+	}
+}
@@ -1,19 +1,52 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
 use anyhow::{bail, Result};
-use std::fmt::Debug;
-use std::io::Read;
+use core::fmt::Debug;
+use crate::io::Read;
 use itertools::{Either, Itertools};
 
 pub mod verifier;
 pub mod instruction;
+pub mod cfg;
+pub mod dataflow;
+pub mod smap;
+pub mod annotation;
+pub mod call_site;
+pub mod call_graph;
+pub mod lazy;
+pub mod signature;
+pub mod deps;
+pub mod hierarchy;
+pub mod shrink;
+pub mod compat;
+pub mod diff;
+pub mod strip;
+pub mod frames;
+pub mod retarget;
+pub mod optimize;
+pub mod decompile;
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod classpath;
 
 pub mod name;
+pub mod symbol;
 pub mod descriptor;
 pub mod access;
+pub mod format;
 
 pub mod cp;
+pub mod io;
+pub mod write;
+mod json;
+#[cfg(test)]
+mod fixture;
 
 use crate::access::{ClassInfoAccess, FieldInfoAccess, MethodInfoAccess};
-use crate::cp::attribute::{AttributeInfo, CodeAttribute, ConstantValueAttribute};
+use crate::cp::attribute::{AttributeInfo, CodeAttribute, ConstantValueAttribute, EnclosingMethodAttribute, InnerClassesAttributeClassesElement};
 use crate::cp::Pool;
 use crate::descriptor::{FieldDescriptor, MethodDescriptor};
 use crate::name::{ClassName, FieldName, MethodName};
@@ -47,13 +80,13 @@ pub trait MyRead: Read {
 		Ok(self.read_u32()? as usize)
 	}
 	fn read_i8(&mut self) -> Result<i8> {
-		todo!()
+		Ok(i8::from_be_bytes(self.read_n()?))
 	}
 	fn read_i16(&mut self) -> Result<i16> {
-		todo!()
+		Ok(i16::from_be_bytes(self.read_n()?))
 	}
 	fn read_i32(&mut self) -> Result<i32> {
-		todo!()
+		Ok(i32::from_be_bytes(self.read_n()?))
 	}
 	fn read_vec<T, S, E>(&mut self, get_size: S, get_element: E) -> Result<Vec<T>>
 	where
@@ -100,6 +133,15 @@ impl FieldInfo {
 			access_flags, name, descriptor, attributes, constant_value,
 		})
 	}
+
+	/// Returns the generic signature of the field, if its type uses type variables or parameterized types.
+	pub fn signature(&self) -> Option<&[u8]> {
+		signature(&self.attributes)
+	}
+
+	pub fn is_deprecated(&self) -> bool {
+		is_deprecated(&self.attributes)
+	}
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -129,16 +171,15 @@ impl MethodInfo {
 
 		let code = if access_flags.is_native | access_flags.is_abstract {
 			if code.len() != 0 {
-				// ERR: found a code attribute when none was expected as of spec
-				todo!("Found code, when code wasn't expected {code:?}");
+				bail!("found a code attribute on a native or abstract method")
 			}
 			None
 		} else {
 			if code.len() > 1 {
-				todo!("found multiple code attributes!");
+				bail!("found multiple code attributes")
 			}
 			if code.len() == 0 {
-				todo!("found no code attribute");
+				bail!("found no code attribute on a method that is neither native nor abstract")
 			}
 			Some(code.into_iter().next().unwrap())
 		};
@@ -151,6 +192,40 @@ impl MethodInfo {
 			code,
 		})
 	}
+
+	/// Returns the generic signature of the method, if its parameters, return type or exceptions use type variables or parameterized types.
+	pub fn signature(&self) -> Option<&[u8]> {
+		signature(&self.attributes)
+	}
+
+	pub fn is_deprecated(&self) -> bool {
+		is_deprecated(&self.attributes)
+	}
+
+	/// Returns the checked exceptions the method declares to throw, from its `Exceptions` attribute.
+	pub fn exceptions(&self) -> &[ClassName] {
+		find_attribute(&self.attributes, |attribute| match attribute {
+			AttributeInfo::Exceptions(attribute) => Some(&attribute.exception_table[..]),
+			_ => None,
+		}).unwrap_or(&[])
+	}
+}
+
+/// Returns the first attribute `f` returns something for. Attributes that may only appear once are checked to do so by
+/// [check_class](crate::format::check_class), the accessors using this don't report duplicates.
+fn find_attribute<'a, T>(attributes: &'a [AttributeInfo], f: impl Fn(&'a AttributeInfo) -> Option<T>) -> Option<T> {
+	attributes.iter().find_map(f)
+}
+
+fn signature(attributes: &[AttributeInfo]) -> Option<&[u8]> {
+	find_attribute(attributes, |attribute| match attribute {
+		AttributeInfo::Signature(attribute) => Some(&attribute.signature[..]),
+		_ => None,
+	})
+}
+
+fn is_deprecated(attributes: &[AttributeInfo]) -> bool {
+	attributes.iter().any(|attribute| matches!(attribute, AttributeInfo::Deprecated(_)))
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -168,6 +243,11 @@ pub struct ClassFile {
 
 impl ClassFile {
 	pub fn parse<R: Read>(reader: &mut R) -> Result<Self> {
+		Ok(Self::parse_with_pool(reader)?.0)
+	}
+
+	/// Parses a class file, also returning the constant pool it was read with. The [ClassFile] itself doesn't store constant pool indices.
+	pub fn parse_with_pool<R: Read>(reader: &mut R) -> Result<(Self, Pool)> {
 		let magic = reader.read_u32()?;
 		if magic != 0xCAFE_BABE {
 			bail!("magic didn't match up: {magic:x}")
@@ -176,8 +256,8 @@ impl ClassFile {
 		let minor_version = reader.read_u16()?;
 		let major_version = reader.read_u16()?;
 
-		if major_version <= 51 {
-			bail!("we only accept class files with version >= 52.0, this one has: {major_version}.{minor_version}")
+		if major_version < 45 {
+			bail!("we only accept class files with version >= 45.0, this one has: {major_version}.{minor_version}")
 		}
 
 		let pool = Pool::parse(reader)?;
@@ -209,19 +289,86 @@ impl ClassFile {
 			bail!("expected end of class file")
 		}
 
-		Ok(ClassFile { minor_version, major_version, access_flags, this_class, super_class, interfaces, fields, methods, attributes })
+		Ok((ClassFile { minor_version, major_version, access_flags, this_class, super_class, interfaces, fields, methods, attributes }, pool))
 	}
 
 	pub fn verify(&self) -> Result<()> {
 		Ok(())
 	}
+
+	/// Returns the name of the source file the class was compiled from, without any directories.
+	pub fn source_file(&self) -> Option<&[u8]> {
+		find_attribute(&self.attributes, |attribute| match attribute {
+			AttributeInfo::SourceFile(attribute) => Some(&attribute.sourcefile[..]),
+			_ => None,
+		})
+	}
+
+	/// Returns the generic signature of the class, if it has type parameters or extends or implements parameterized types.
+	pub fn signature(&self) -> Option<&[u8]> {
+		signature(&self.attributes)
+	}
+
+	pub fn is_deprecated(&self) -> bool {
+		is_deprecated(&self.attributes)
+	}
+
+	/// Returns the entries of the `InnerClasses` attribute. These are all the nested classes referenced by the class, not just its own members, and
+	/// the entry of the class itself if it's nested.
+	pub fn inner_classes(&self) -> &[InnerClassesAttributeClassesElement] {
+		find_attribute(&self.attributes, |attribute| match attribute {
+			AttributeInfo::InnerClasses(attribute) => Some(&attribute.classes[..]),
+			_ => None,
+		}).unwrap_or(&[])
+	}
+
+	/// Returns the `EnclosingMethod` attribute, which local and anonymous classes have.
+	pub fn enclosing_method(&self) -> Option<&EnclosingMethodAttribute> {
+		find_attribute(&self.attributes, |attribute| match attribute {
+			AttributeInfo::EnclosingMethod(attribute) => Some(attribute),
+			_ => None,
+		})
+	}
+
+	/// Returns the entry of the `InnerClasses` attribute describing this class, if it's a nested class.
+	pub fn inner_class_entry(&self) -> Option<&InnerClassesAttributeClassesElement> {
+		self.inner_classes().iter().find(|entry| entry.inner_class == self.this_class)
+	}
+
+	/// Returns the class this one is nested in: the declaring class of a member class, or the class containing the code of a local or anonymous class.
+	pub fn outer_class(&self) -> Option<&ClassName> {
+		let entry = self.inner_class_entry()?;
+		entry.outer_class.as_ref()
+			.or_else(|| self.enclosing_method().map(|enclosing_method| &enclosing_method.class))
+	}
+
+	/// Returns the name of the class as given in the source, like `Class.getSimpleName`: the name without the package for top level classes, and an
+	/// empty name for anonymous classes.
+	pub fn simple_name(&self) -> &[u8] {
+		match self.inner_class_entry() {
+			Some(entry) => entry.inner_name.as_deref().unwrap_or_default(),
+			None => {
+				let name = self.this_class.as_bytes();
+				name.rsplit(|&byte| byte == b'/').next().unwrap_or(name)
+			},
+		}
+	}
+
+	pub fn is_anonymous(&self) -> bool {
+		self.inner_class_entry().is_some_and(InnerClassesAttributeClassesElement::is_anonymous)
+	}
+
+	/// Returns whether the class is declared in a block, like a method body. Anonymous classes are not counted as local.
+	pub fn is_local(&self) -> bool {
+		self.inner_class_entry().is_some_and(InnerClassesAttributeClassesElement::is_local)
+	}
 }
 
 #[cfg(test)]
 mod testing {
-	use std::fs::File;
-	use std::io::BufReader;
-	use zip::ZipArchive;
+	use crate::access::ClassInfoAccess;
+	use crate::cp::attribute::{AttributeInfo, EnclosingMethodAttribute, InnerClassesAttribute, InnerClassesAttributeClassesElement};
+	use crate::name::ClassName;
 	use super::ClassFile;
 	#[test]
 	fn try_parse_classfile() {
@@ -237,8 +384,12 @@ mod testing {
 	}
 
 	#[test]
-	#[cfg(target_os = "linux")]
+	#[cfg(all(target_os = "linux", feature = "std"))]
 	fn try_parse_classfile_from_zip() {
+		use std::fs::File;
+		use std::io::BufReader;
+		use zip::ZipArchive;
+
 		let rt = File::open("/usr/lib/jvm/java-8-openjdk/jre/lib/rt.jar").unwrap();
 		let mut rt = ZipArchive::new(BufReader::new(rt)).unwrap();
 
@@ -247,4 +398,49 @@ mod testing {
 
 		println!("{classfile:#?}");
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn inner_classes() {
+		let entry = |inner: &[u8], outer: Option<&[u8]>, name: Option<&[u8]>| InnerClassesAttributeClassesElement {
+			inner_class: ClassName::from(inner),
+			outer_class: outer.map(ClassName::from),
+			inner_name: name.map(<[u8]>::to_vec),
+			inner_class_access_flags: 0,
+		};
+		let class = |this_class: &[u8], attributes| ClassFile {
+			minor_version: 0,
+			major_version: 52,
+			access_flags: ClassInfoAccess::parse(0x0020).unwrap(),
+			this_class: ClassName::from(this_class),
+			super_class: Some(ClassName::from(&b"java/lang/Object"[..])),
+			interfaces: Vec::new(),
+			fields: Vec::new(),
+			methods: Vec::new(),
+			attributes,
+		};
+		let classes = vec![
+			entry(b"a/Outer$Member", Some(b"a/Outer"), Some(b"Member")),
+			entry(b"a/Outer$1Local", None, Some(b"Local")),
+			entry(b"a/Outer$2", None, None),
+		];
+		let inner_classes = AttributeInfo::InnerClasses(InnerClassesAttribute { classes });
+		let enclosing_method = AttributeInfo::EnclosingMethod(EnclosingMethodAttribute { class: ClassName::from(&b"a/Outer"[..]), method: None });
+
+		let outer = class(b"a/Outer", vec![inner_classes.clone()]);
+		assert_eq!(outer.simple_name(), b"Outer");
+		assert_eq!(outer.outer_class(), None);
+		assert_eq!(outer.inner_classes().len(), 3);
+
+		let member = class(b"a/Outer$Member", vec![inner_classes.clone()]);
+		assert_eq!((member.simple_name(), member.is_local(), member.is_anonymous()), (&b"Member"[..], false, false));
+		assert_eq!(member.outer_class(), Some(&ClassName::from(&b"a/Outer"[..])));
+
+		let local = class(b"a/Outer$1Local", vec![inner_classes.clone(), enclosing_method.clone()]);
+		assert_eq!((local.simple_name(), local.is_local(), local.is_anonymous()), (&b"Local"[..], true, false));
+		assert_eq!(local.outer_class(), Some(&ClassName::from(&b"a/Outer"[..])));
+
+		let anonymous = class(b"a/Outer$2", vec![inner_classes, enclosing_method]);
+		assert_eq!((anonymous.simple_name(), anonymous.is_local(), anonymous.is_anonymous()), (&b""[..], false, true));
+		assert!(anonymous.enclosing_method().unwrap().method.is_none());
+	}
+}
@@ -1,60 +1,259 @@
+use alloc::string::String;
+use anyhow::{anyhow, bail, Result};
+use crate::descriptor::{BaseOrObjectType, FieldDescriptor};
+use crate::symbol::Symbol;
 
-
-
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Interned, so that copying, comparing and hashing is cheap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ClassName {
-	inner: Vec<u8>,
+	inner: Symbol,
 }
 
 impl ClassName {
 	pub fn new(_: &str) -> ClassName {
 		todo!()
 	}
+	pub fn as_bytes(&self) -> &'static [u8] {
+		self.inner.as_bytes()
+	}
+
+	pub fn symbol(&self) -> Symbol {
+		self.inner
+	}
+	/// Checks that this is either a binary class name, or an array class name (4.4.1).
+	pub fn check(&self) -> Result<()> {
+		if self.as_bytes().starts_with(b"[") {
+			check_array_class_name(self.as_bytes())
+		} else {
+			check_binary_class_name(self.as_bytes())
+		}
+	}
 }
 
 impl PartialEq<[u8]> for ClassName {
 	fn eq(&self, other: &[u8]) -> bool {
-		self.inner == other
+		self.as_bytes() == other
 	}
 }
 impl PartialEq<&[u8]> for ClassName {
 	fn eq(&self, other: &&[u8]) -> bool {
-		self.inner == *other
+		self.as_bytes() == *other
 	}
 }
 
 impl From<&[u8]> for ClassName {
 	fn from(value: &[u8]) -> Self {
-		Self { inner: value.to_vec() }
+		Self { inner: Symbol::intern(value) }
+	}
+}
+
+impl<const N: usize> From<&[u8; N]> for ClassName {
+	fn from(value: &[u8; N]) -> Self {
+		Self { inner: Symbol::intern(value) }
 	}
 }
 
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Interned, so that copying, comparing and hashing is cheap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct FieldName {
-	inner: Vec<u8>,
+	inner: Symbol,
+}
+
+impl FieldName {
+	pub fn as_bytes(&self) -> &'static [u8] {
+		self.inner.as_bytes()
+	}
+
+	pub fn symbol(&self) -> Symbol {
+		self.inner
+	}
+	/// Checks that this is a valid unqualified name (4.2.2).
+	pub fn check(&self) -> Result<()> {
+		check_unqualified_name(self.as_bytes())
+	}
 }
 
 impl From<&[u8]> for FieldName {
 	fn from(value: &[u8]) -> Self {
-		Self { inner: value.to_vec() }
+		Self { inner: Symbol::intern(value) }
 	}
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+impl<const N: usize> From<&[u8; N]> for FieldName {
+	fn from(value: &[u8; N]) -> Self {
+		Self { inner: Symbol::intern(value) }
+	}
+}
+
+/// Interned, so that copying, comparing and hashing is cheap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MethodName {
-	inner: Vec<u8>,
+	inner: Symbol,
+}
+
+impl MethodName {
+	pub fn as_bytes(&self) -> &'static [u8] {
+		self.inner.as_bytes()
+	}
+
+	pub fn symbol(&self) -> Symbol {
+		self.inner
+	}
+	/// Checks that this is a valid method name (4.2.2).
+	pub fn check(&self) -> Result<()> {
+		check_method_name(self.as_bytes())
+	}
+}
+
+impl PartialEq<[u8]> for MethodName {
+	fn eq(&self, other: &[u8]) -> bool {
+		self.as_bytes() == other
+	}
+}
+impl PartialEq<&[u8]> for MethodName {
+	fn eq(&self, other: &&[u8]) -> bool {
+		self.as_bytes() == *other
+	}
 }
 
 impl From<&[u8]> for MethodName {
 	fn from(value: &[u8]) -> Self {
-		Self { inner: value.to_vec() }
+		Self { inner: Symbol::intern(value) }
 	}
 }
 
 impl<const N: usize> From<&[u8; N]> for MethodName {
 	fn from(value: &[u8; N]) -> Self {
-		Self { inner: value.to_vec() }
+		Self { inner: Symbol::intern(value) }
+	}
+}
+
+// 4.2
+
+/// Checks that `name` is a valid unqualified name (4.2.2), as used for fields, local variables and formal parameters.
+///
+/// An unqualified name must contain at least one character, and must not contain any of `.`, `;`, `[` or `/`.
+pub fn check_unqualified_name(name: &[u8]) -> Result<()> {
+	if name.is_empty() {
+		bail!("unqualified name must not be empty")
+	}
+	if let Some(c) = name.iter().find(|c| matches!(c, b'.' | b';' | b'[' | b'/')) {
+		bail!("unqualified name must not contain '{}': '{}'", *c as char, String::from_utf8_lossy(name))
+	}
+	Ok(())
+}
+
+/// Checks that `name` is a valid method name (4.2.2).
+///
+/// Besides being an unqualified name, a method name must not contain `<` or `>`, unless it is one of the special names `<init>` and `<clinit>`.
+pub fn check_method_name(name: &[u8]) -> Result<()> {
+	if name == b"<init>" || name == b"<clinit>" {
+		return Ok(());
+	}
+	check_unqualified_name(name)?;
+	if let Some(c) = name.iter().find(|c| matches!(c, b'<' | b'>')) {
+		bail!("method name must not contain '{}': '{}'", *c as char, String::from_utf8_lossy(name))
+	}
+	Ok(())
+}
+
+/// Checks that `name` is a binary class or interface name in its internal form (4.2.1).
+///
+/// Such a name consists of unqualified names separated by `/`, like `java/lang/Thread`.
+pub fn check_binary_class_name(name: &[u8]) -> Result<()> {
+	for identifier in name.split(|c| *c == b'/') {
+		check_unqualified_name(identifier)
+			.map_err(|e| anyhow!("invalid binary name '{}': {e}", String::from_utf8_lossy(name)))?;
+	}
+	Ok(())
+}
+
+/// Checks that `name` is a valid array class name (4.4.1), that is a field descriptor with at least one array dimension, like `[[Ljava/lang/Thread;`.
+pub fn check_array_class_name(name: &[u8]) -> Result<()> {
+	let descriptor = FieldDescriptor::try_from(name)?;
+	if descriptor.array_dimension == 0 {
+		bail!("array class name must start with '[': '{}'", String::from_utf8_lossy(name))
+	}
+	if let BaseOrObjectType::Object(class_name) = &descriptor.base_type {
+		check_binary_class_name(class_name.as_bytes())?;
+	}
+	Ok(())
+}
+
+/// Checks that `name` is a valid module name (4.2.3).
+///
+/// A module name must not contain any character in the range `'\u0000'` to `'\u001F'`. The backslash is used as escape character, and may only be
+/// followed by another backslash, `:` or `@`. The characters `:` and `@` may only appear when escaped.
+pub fn check_module_name(name: &[u8]) -> Result<()> {
+	if name.is_empty() {
+		bail!("module name must not be empty")
+	}
+	let mut iter = name.iter();
+	while let Some(c) = iter.next() {
+		match c {
+			0x00..=0x1f => bail!("module name must not contain the control character {c:#x}: '{}'", String::from_utf8_lossy(name)),
+			b'\\' => match iter.next() {
+				Some(b'\\' | b':' | b'@') => {},
+				_ => bail!("backslash in module name must be followed by '\\', ':' or '@': '{}'", String::from_utf8_lossy(name)),
+			},
+			b':' | b'@' => bail!("module name must not contain an unescaped '{}': '{}'", *c as char, String::from_utf8_lossy(name)),
+			_ => {},
+		}
+	}
+	Ok(())
+}
+
+/// Checks that `name` is a valid package name (4.2.3), which is written in the internal form of binary names, like `java/lang`.
+pub fn check_package_name(name: &[u8]) -> Result<()> {
+	check_binary_class_name(name)
+}
+
+#[cfg(test)]
+mod testing {
+	use super::{check_array_class_name, check_binary_class_name, check_method_name, check_module_name, check_unqualified_name};
+
+	#[test]
+	fn unqualified_names() {
+		assert!(check_unqualified_name(b"this$0").is_ok());
+		assert!(check_unqualified_name(b"<lambda>").is_ok());
+		assert!(check_unqualified_name(b"").is_err());
+		assert!(check_unqualified_name(b"a.b").is_err());
+		assert!(check_unqualified_name(b"a;").is_err());
+		assert!(check_unqualified_name(b"[a").is_err());
+		assert!(check_unqualified_name(b"a/b").is_err());
+	}
+
+	#[test]
+	fn method_names() {
+		assert!(check_method_name(b"<init>").is_ok());
+		assert!(check_method_name(b"<clinit>").is_ok());
+		assert!(check_method_name(b"lambda$main$0").is_ok());
+		assert!(check_method_name(b"<lambda>").is_err());
+		assert!(check_method_name(b"<init>::/this_is_a_\"test").is_err());
+	}
+
+	#[test]
+	fn class_names() {
+		assert!(check_binary_class_name(b"java/lang/Object").is_ok());
+		assert!(check_binary_class_name(b"Foo$Bar").is_ok());
+		assert!(check_binary_class_name(b"java.lang.Object").is_err());
+		assert!(check_binary_class_name(b"java//Object").is_err());
+		assert!(check_binary_class_name(b"java/lang/").is_err());
+		assert!(check_binary_class_name(b"[I").is_err());
+
+		assert!(check_array_class_name(b"[I").is_ok());
+		assert!(check_array_class_name(b"[[Ljava/lang/Object;").is_ok());
+		assert!(check_array_class_name(b"Ljava/lang/Object;").is_err());
+		assert!(check_array_class_name(b"[Ljava.lang.Object;").is_err());
+	}
+
+	#[test]
+	fn module_names() {
+		assert!(check_module_name(b"java.base").is_ok());
+		assert!(check_module_name(b"foo\\@bar").is_ok());
+		assert!(check_module_name(b"foo@bar").is_err());
+		assert!(check_module_name(b"foo\\bar").is_err());
+		assert!(check_module_name(b"foo\x01").is_err());
 	}
 }
@@ -0,0 +1,622 @@
+//! A peephole optimizer for the code of methods.
+//!
+//! [optimize_method] looks for short sequences of instructions that can be replaced by fewer ones, and repeats this until there are none left:
+//! - constant folding: arithmetic and conversions of constants become the constant they compute, constants and locals that are popped right away
+//!   disappear, and branches on constants become a `goto` or nothing,
+//! - loads and stores: storing to a local that is loaded right after and not used anymore keeps the value on the operand stack instead, and loading
+//!   a local only to store it back is dropped,
+//! - jumps: a jump to the next instruction is removed, jumps to a `goto` go to its target right away, a `goto` to a return returns right away, and a
+//!   conditional branch over a `goto` is inverted to jump to the target of the `goto`,
+//! - code that can't be reached is removed.
+//!
+//! Sequences are only replaced where no jump, exception handler or stack map frame enters the code in between them. The changes are applied with a
+//! [CodeEdit], which moves the exception table, the line numbers, the local variable tables and the stack map frames along. The frames stay valid
+//! this way, so unlike when [computing them](crate::frames::compute_frames), no class hierarchy is needed, and the optimizer can run on classes whose
+//! class path isn't known, like before interpreting or compiling their methods. Methods with subroutines are left as they are.
+
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{anyhow, Context, Result};
+use crate::{ClassFile, MethodInfo};
+use crate::cfg::ControlFlowGraph;
+use crate::cp::Pool;
+use crate::cp::attribute::{CodeAttribute, StackMapFrame, VerificationTypeInfo};
+use crate::dataflow::frame::{Interpreter, ValueKind};
+use crate::dataflow::locals::Liveness;
+use crate::dataflow::solve;
+use crate::dataflow::values::{Constant, ConstantInterpreter};
+use crate::descriptor::method_string;
+use crate::frames::{compress_frames, entry_locals, expand_frames, frame_positions};
+use crate::instruction::BranchTarget;
+use crate::instruction::builder::{double_constant, float_constant, int_constant, long_constant, max_stack};
+use crate::instruction::edit::CodeEdit;
+use crate::instruction::opcode::Opcode;
+use crate::name::ClassName;
+
+/// The optimizations [optimize_method] does, all of them by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizeOptions {
+	/// Computing arithmetic, conversions and branches on constants, and removing constants and loads that are popped.
+	pub fold_constants: bool,
+	/// Removing a store followed by a load of the same local, if the local isn't used afterwards, and a load followed by a store to the same local.
+	pub loads_and_stores: bool,
+	/// Removing jumps to the next instruction, threading jumps to a `goto`, and inverting conditional branches over a `goto`.
+	pub jumps: bool,
+	/// Removing code that can't be reached.
+	pub unreachable_code: bool,
+}
+
+impl Default for OptimizeOptions {
+	fn default() -> Self {
+		OptimizeOptions {
+			fold_constants: true,
+			loads_and_stores: true,
+			jumps: true,
+			unreachable_code: true,
+		}
+	}
+}
+
+/// Optimizes the code of `method`, a method of `class`, with the optimizations of `options`, and computes its `max_stack` anew if it changed. Returns
+/// whether it changed.
+pub fn optimize_method(class: &ClassName, method: &mut MethodInfo, options: &OptimizeOptions) -> Result<bool> {
+	if method.code.as_ref().is_none_or(CodeAttribute::has_subroutines) {
+		return Ok(false);
+	}
+	let entry = entry_locals(class, method)?;
+	let is_static = method.access_flags.is_static;
+	let code = method.code.as_mut().expect("the method has code");
+	// only frames not relying on the one before them can be moved and removed
+	let frames = code.stack_map_table.entries.clone();
+	code.stack_map_table.entries = expand_frames(&entry, &frames)?;
+	let mut changed = false;
+	loop {
+		let edit = Peephole::new(code).run(options)?;
+		if edit.is_empty() {
+			break;
+		}
+		edit.apply(code)?;
+		changed = true;
+	}
+	if !changed {
+		code.stack_map_table.entries = frames;
+		return Ok(false);
+	}
+	// frames of jump targets that are gone aren't needed anymore
+	let positions = frame_positions(code);
+	let instructions = &code.code;
+	code.stack_map_table.entries.retain(|frame| instructions.index_of(frame.get_bytecode_offset()).is_some_and(|index| positions.contains(&index)));
+	code.stack_map_table.entries = compress_frames(&entry, &code.stack_map_table.entries);
+	code.max_stack = max_stack(&code.code, &code.exception_table, &method.descriptor, is_static, code.max_locals as usize)?;
+	Ok(true)
+}
+
+/// Optimizes all methods of `class` with [optimize_method], returning the number of methods that changed.
+pub fn optimize_class(class: &mut ClassFile, options: &OptimizeOptions) -> Result<usize> {
+	let mut changed = 0;
+	for method in &mut class.methods {
+		if optimize_method(&class.this_class, method, options)
+			.with_context(|| anyhow!("while optimizing the method {}", method_string(method.name, &method.descriptor)))? {
+			changed += 1;
+		}
+	}
+	Ok(changed)
+}
+
+/// Optimizes `class` like [optimize_class] and writes it, returning the bytes with the number of methods that changed. `pool` is the pool the class
+/// was parsed with, which is kept if attributes unknown to the parser remain.
+pub fn optimize(class: &ClassFile, pool: &Pool, options: &OptimizeOptions) -> Result<(Vec<u8>, usize)> {
+	let mut optimized = class.clone();
+	let methods = optimize_class(&mut optimized, options)?;
+	Ok((optimized.to_bytes(Some(pool))?, methods))
+}
+
+/// One round of looking for sequences to replace.
+struct Peephole<'a> {
+	code: &'a CodeAttribute,
+	/// Whether a jump, an exception handler or a stack map frame enters the code at each instruction, or an exception range starts or ends there.
+	entered: Vec<bool>,
+	/// Whether each instruction is part of a sequence replaced in this round already.
+	taken: Vec<bool>,
+	edit: CodeEdit,
+}
+
+impl<'a> Peephole<'a> {
+	fn new(code: &'a CodeAttribute) -> Peephole<'a> {
+		let mut entered = vec![false; code.code.len()];
+		let mut enter = |offset: usize| {
+			if let Some(index) = code.code.index_of(offset) {
+				entered[index] = true;
+			}
+		};
+		for instruction in &code.code {
+			for target in instruction.opcode().branch_targets() {
+				enter(target.0);
+			}
+		}
+		for entry in &code.exception_table {
+			enter(entry.start_pc);
+			enter(entry.end_pc);
+			enter(entry.handler_pc);
+		}
+		for frame in &code.stack_map_table.entries {
+			enter(frame.get_bytecode_offset());
+		}
+		Peephole { code, entered, taken: vec![false; code.code.len()], edit: CodeEdit::new() }
+	}
+
+	fn opcode(&self, index: usize) -> Option<&'a Opcode> {
+		self.code.code.get(index).map(|instruction| instruction.opcode())
+	}
+
+	/// The offset of the instruction with the index `index`, or the end of the code.
+	fn offset(&self, index: usize) -> usize {
+		self.code.code.get(index).map_or(self.code.code.code_length(), |instruction| instruction.offset())
+	}
+
+	/// Whether the `len` instructions starting at `index` can be replaced together.
+	fn is_free(&self, index: usize, len: usize) -> bool {
+		index + len <= self.code.code.len() && !self.taken[index..index + len].contains(&true) && !self.entered[index + 1..index + len].contains(&true)
+	}
+
+	/// Replaces the `len` instructions starting at `index` by `opcodes`.
+	fn replace(&mut self, index: usize, len: usize, opcodes: Vec<Opcode>) {
+		self.edit.replace(index, opcodes);
+		for index in index + 1..index + len {
+			self.edit.remove(index);
+		}
+		self.taken[index..index + len].fill(true);
+	}
+
+	fn run(mut self, options: &OptimizeOptions) -> Result<CodeEdit> {
+		if options.unreachable_code {
+			self.remove_unreachable_code()?;
+		}
+		let loads_and_stores = if options.loads_and_stores {
+			Some((solve(&Liveness, self.code)?, declared_locals(&self.code.stack_map_table.entries)))
+		} else {
+			None
+		};
+		for index in 0..self.code.code.len() {
+			if self.taken[index] {
+				continue;
+			}
+			let done = options.fold_constants && self.fold_constants(index)
+				|| loads_and_stores.as_ref().is_some_and(|(liveness, declared)| self.load_and_store(index, |local| {
+					!liveness.after(index + 1).contains(&local) && !declared.contains(&local)
+				}))
+				|| options.jumps && self.jump(index);
+			if !done && options.jumps {
+				self.thread_jumps(index);
+			}
+		}
+		Ok(self.edit)
+	}
+
+	fn remove_unreachable_code(&mut self) -> Result<()> {
+		let cfg = ControlFlowGraph::new(self.code)?;
+		let mut reachable = vec![false; cfg.blocks().len()];
+		for id in cfg.reverse_postorder() {
+			reachable[id.0] = true;
+		}
+		for (block, _) in cfg.blocks().iter().zip(reachable).filter(|(_, reachable)| !reachable) {
+			for index in block.instructions() {
+				self.edit.remove(index);
+				self.taken[index] = true;
+			}
+		}
+		Ok(())
+	}
+
+	fn fold_constants(&mut self, index: usize) -> bool {
+		let Some(first) = self.opcode(index).and_then(constant) else {
+			return self.is_free(index, 2) && self.popped_load(index);
+		};
+		let Some(second) = self.opcode(index + 1) else {
+			return false;
+		};
+		if !self.is_free(index, 2) {
+			return false;
+		}
+		if let Some(value) = constant(second) {
+			if self.is_free(index, 3) {
+				let third = self.opcode(index + 2).expect("is_free checks the length");
+				if let Some(opcode) = self.folded(index, 3, ConstantInterpreter.binary(third, &first, &value, ValueKind::Int)) {
+					self.replace(index, 3, vec![opcode]);
+					return true;
+				}
+				if let Some(taken) = branch_taken(third, &[first, value]) {
+					self.replace(index, 3, decided_branch(third, taken));
+					return true;
+				}
+			}
+			return false;
+		}
+		// conversions are followed as long as their result can't be pushed without growing the code, like `bipush 20; i2l; l2i`
+		let mut value = first;
+		let mut len = 1;
+		while let Some(opcode) = self.opcode(index + len).filter(|_| self.is_free(index, len + 1)) {
+			// iinc changes a local and not the value on the stack
+			if matches!(opcode, Opcode::IInc { .. }) {
+				break;
+			}
+			value = ConstantInterpreter.unary(opcode, &value, ValueKind::Int);
+			len += 1;
+			if push(value).is_none() {
+				break;
+			}
+			if let Some(opcode) = self.folded(index, len, value) {
+				self.replace(index, len, vec![opcode]);
+				return true;
+			}
+		}
+		if let Some(taken) = branch_taken(second, &[first]) {
+			self.replace(index, 2, decided_branch(second, taken));
+			return true;
+		}
+		if is_pop_of(second, ConstantInterpreter.size(&first)) {
+			self.replace(index, 2, Vec::new());
+			return true;
+		}
+		false
+	}
+
+	/// The instruction pushing `constant` that can replace the `len` instructions starting at `index`, if it is no longer than them and needs no entry
+	/// in the constant pool, so folding never grows the code or the pool.
+	fn folded(&self, index: usize, len: usize, constant: Constant) -> Option<Opcode> {
+		let opcode = push(constant)?;
+		let length = inline_constant_length(&opcode)?;
+		(length <= self.offset(index + len) - self.offset(index)).then_some(opcode)
+	}
+
+	/// Removes a load that is popped right away.
+	fn popped_load(&mut self, index: usize) -> bool {
+		let popped = match (self.opcode(index), self.opcode(index + 1)) {
+			(Some(load), Some(pop)) => local(load).is_some_and(|(is_store, kind, _)| !is_store && is_pop_of(pop, kind.size())),
+			_ => false,
+		};
+		if popped {
+			self.replace(index, 2, Vec::new());
+		}
+		popped
+	}
+
+	/// Removes a store and a load of the same local if `is_dead(local)` says it isn't used after them, or a load and a store of the same local.
+	fn load_and_store(&mut self, index: usize, is_dead: impl Fn(usize) -> bool) -> bool {
+		if !self.is_free(index, 2) {
+			return false;
+		}
+		let (Some(first), Some(second)) = (self.opcode(index).and_then(local), self.opcode(index + 1).and_then(local)) else {
+			return false;
+		};
+		let ((first_is_store, first_kind, first_local), (second_is_store, second_kind, second_local)) = (first, second);
+		if first_is_store == second_is_store || first_kind != second_kind || first_local != second_local {
+			return false;
+		}
+		if first_is_store && !is_dead(first_local) {
+			return false;
+		}
+		self.replace(index, 2, Vec::new());
+		true
+	}
+
+	fn jump(&mut self, index: usize) -> bool {
+		let opcode = self.opcode(index).expect("index is in bounds");
+		let next = self.offset(index + 1);
+		match opcode {
+			Opcode::Goto(target) if target.0 == next => {
+				self.replace(index, 1, Vec::new());
+				true
+			},
+			// a conditional branch to the next instruction only pops its operands
+			opcode if inverted(opcode, BranchTarget(0)).is_some() && opcode.branch_targets()[0].0 == next => {
+				self.replace(index, 1, vec![if operands(opcode) == 2 { Opcode::Pop2 } else { Opcode::Pop }]);
+				true
+			},
+			opcode if self.is_free(index, 2) => {
+				let Some(Opcode::Goto(goto_target)) = self.opcode(index + 1) else {
+					return false;
+				};
+				match inverted(opcode, goto_target.clone()) {
+					Some(inverted) if opcode.branch_targets()[0].0 == self.offset(index + 2) => {
+						self.replace(index, 2, vec![inverted]);
+						true
+					},
+					_ => false,
+				}
+			},
+			_ => false,
+		}
+	}
+
+	/// Makes the jumps of the instruction go to the final target of `goto`s they jump to, and a `goto` to a return return right away.
+	fn thread_jumps(&mut self, index: usize) {
+		let opcode = self.opcode(index).expect("index is in bounds");
+		if let Opcode::Goto(target) = opcode {
+			let target = self.final_target(target.0);
+			let returns = self.code.code.index_of(target)
+				.and_then(|index| self.opcode(index))
+				.filter(|opcode| is_return(opcode));
+			if let Some(opcode) = returns {
+				self.replace(index, 1, vec![opcode.clone()]);
+				return;
+			}
+		}
+		let mut threaded = opcode.clone();
+		let mut changed = false;
+		for target in threaded.branch_targets_mut() {
+			let final_target = self.final_target(target.0);
+			if final_target != target.0 {
+				target.0 = final_target;
+				changed = true;
+			}
+		}
+		if changed {
+			self.replace(index, 1, vec![threaded]);
+		}
+	}
+
+	/// Follows the `goto`s starting at `offset`, stopping at the first `goto` seen twice.
+	fn final_target(&self, mut offset: usize) -> usize {
+		let mut seen = BTreeSet::new();
+		while seen.insert(offset) {
+			match self.code.code.index_of(offset).and_then(|index| self.opcode(index)) {
+				Some(Opcode::Goto(target)) => offset = target.0,
+				_ => break,
+			}
+		}
+		offset
+	}
+}
+
+/// The value pushed by a constant instruction.
+fn constant(opcode: &Opcode) -> Option<Constant> {
+	match ConstantInterpreter.constant(opcode, ValueKind::Int) {
+		Constant::Top | Constant::Unknown(_) => None,
+		constant => Some(constant),
+	}
+}
+
+/// The shortest instruction pushing `constant`, if it is known.
+fn push(constant: Constant) -> Option<Opcode> {
+	match constant {
+		Constant::Int(value) => Some(int_constant(value)),
+		Constant::Long(value) => Some(long_constant(value)),
+		Constant::Float(bits) => Some(float_constant(f32::from_bits(bits))),
+		Constant::Double(bits) => Some(double_constant(f64::from_bits(bits))),
+		Constant::Null => Some(Opcode::AConstNull),
+		Constant::Top | Constant::Unknown(_) => None,
+	}
+}
+
+/// The length of an instruction pushing a constant without an entry in the constant pool, or `None` if `opcode` is none.
+fn inline_constant_length(opcode: &Opcode) -> Option<usize> {
+	match opcode {
+		Opcode::AConstNull | Opcode::IConstM1 | Opcode::IConst0 | Opcode::IConst1 | Opcode::IConst2 | Opcode::IConst3 | Opcode::IConst4 |
+		Opcode::IConst5 | Opcode::LConst0 | Opcode::LConst1 | Opcode::FConst0 | Opcode::FConst1 | Opcode::FConst2 | Opcode::DConst0 |
+		Opcode::DConst1 => Some(1),
+		Opcode::BIPush(_) => Some(2),
+		Opcode::SIPush(_) => Some(3),
+		_ => None,
+	}
+}
+
+/// Whether `opcode` pops a value that takes `size` slots.
+fn is_pop_of(opcode: &Opcode, size: usize) -> bool {
+	matches!((opcode, size), (Opcode::Pop, 1) | (Opcode::Pop2, 2))
+}
+
+fn is_return(opcode: &Opcode) -> bool {
+	matches!(opcode, Opcode::Return | Opcode::IReturn | Opcode::LReturn | Opcode::FReturn | Opcode::DReturn | Opcode::AReturn)
+}
+
+/// Whether a load or store accesses a local, the kind of the value and the index of the local.
+fn local(opcode: &Opcode) -> Option<(bool, ValueKind, usize)> {
+	match opcode {
+		Opcode::ILoad(index) => Some((false, ValueKind::Int, index.0)),
+		Opcode::LLoad(index) => Some((false, ValueKind::Long, index.0)),
+		Opcode::FLoad(index) => Some((false, ValueKind::Float, index.0)),
+		Opcode::DLoad(index) => Some((false, ValueKind::Double, index.0)),
+		Opcode::ALoad(index) => Some((false, ValueKind::Reference, index.0)),
+		Opcode::IStore(index) => Some((true, ValueKind::Int, index.0)),
+		Opcode::LStore(index) => Some((true, ValueKind::Long, index.0)),
+		Opcode::FStore(index) => Some((true, ValueKind::Float, index.0)),
+		Opcode::DStore(index) => Some((true, ValueKind::Double, index.0)),
+		Opcode::AStore(index) => Some((true, ValueKind::Reference, index.0)),
+		_ => None,
+	}
+}
+
+/// The number of operands of a conditional branch.
+fn operands(opcode: &Opcode) -> usize {
+	match opcode {
+		Opcode::IfACmpEq(_) | Opcode::IfACmpNe(_) | Opcode::IfICmpEq(_) | Opcode::IfICmpNe(_) |
+		Opcode::IfICmpLt(_) | Opcode::IfICmpGe(_) | Opcode::IfICmpGt(_) | Opcode::IfICmpLe(_) => 2,
+		_ => 1,
+	}
+}
+
+/// The conditional branch to `target` taken exactly when `opcode` isn't, or `None` if `opcode` isn't a conditional branch.
+fn inverted(opcode: &Opcode, target: BranchTarget) -> Option<Opcode> {
+	Some(match opcode {
+		Opcode::IfEq(_) => Opcode::IfNe(target),
+		Opcode::IfNe(_) => Opcode::IfEq(target),
+		Opcode::IfLt(_) => Opcode::IfGe(target),
+		Opcode::IfGe(_) => Opcode::IfLt(target),
+		Opcode::IfGt(_) => Opcode::IfLe(target),
+		Opcode::IfLe(_) => Opcode::IfGt(target),
+		Opcode::IfICmpEq(_) => Opcode::IfICmpNe(target),
+		Opcode::IfICmpNe(_) => Opcode::IfICmpEq(target),
+		Opcode::IfICmpLt(_) => Opcode::IfICmpGe(target),
+		Opcode::IfICmpGe(_) => Opcode::IfICmpLt(target),
+		Opcode::IfICmpGt(_) => Opcode::IfICmpLe(target),
+		Opcode::IfICmpLe(_) => Opcode::IfICmpGt(target),
+		Opcode::IfACmpEq(_) => Opcode::IfACmpNe(target),
+		Opcode::IfACmpNe(_) => Opcode::IfACmpEq(target),
+		Opcode::IfNull(_) => Opcode::IfNonNull(target),
+		Opcode::IfNonNull(_) => Opcode::IfNull(target),
+		_ => return None,
+	})
+}
+
+/// What the conditional branch `opcode` becomes once it is known whether it is `taken`.
+fn decided_branch(opcode: &Opcode, taken: bool) -> Vec<Opcode> {
+	if taken {
+		vec![Opcode::Goto(opcode.branch_targets()[0].clone())]
+	} else {
+		Vec::new()
+	}
+}
+
+/// Whether the conditional branch `opcode` is taken with the constant operands `values`, or `None` if that isn't known.
+fn branch_taken(opcode: &Opcode, values: &[Constant]) -> Option<bool> {
+	match (opcode, values) {
+		(Opcode::IfEq(_), [Constant::Int(a)]) => Some(*a == 0),
+		(Opcode::IfNe(_), [Constant::Int(a)]) => Some(*a != 0),
+		(Opcode::IfLt(_), [Constant::Int(a)]) => Some(*a < 0),
+		(Opcode::IfGe(_), [Constant::Int(a)]) => Some(*a >= 0),
+		(Opcode::IfGt(_), [Constant::Int(a)]) => Some(*a > 0),
+		(Opcode::IfLe(_), [Constant::Int(a)]) => Some(*a <= 0),
+		(Opcode::IfICmpEq(_), [Constant::Int(a), Constant::Int(b)]) => Some(a == b),
+		(Opcode::IfICmpNe(_), [Constant::Int(a), Constant::Int(b)]) => Some(a != b),
+		(Opcode::IfICmpLt(_), [Constant::Int(a), Constant::Int(b)]) => Some(a < b),
+		(Opcode::IfICmpGe(_), [Constant::Int(a), Constant::Int(b)]) => Some(a >= b),
+		(Opcode::IfICmpGt(_), [Constant::Int(a), Constant::Int(b)]) => Some(a > b),
+		(Opcode::IfICmpLe(_), [Constant::Int(a), Constant::Int(b)]) => Some(a <= b),
+		(Opcode::IfNull(_), [Constant::Null]) => Some(true),
+		(Opcode::IfNonNull(_), [Constant::Null]) => Some(false),
+		(Opcode::IfACmpEq(_), [Constant::Null, Constant::Null]) => Some(true),
+		(Opcode::IfACmpNe(_), [Constant::Null, Constant::Null]) => Some(false),
+		_ => None,
+	}
+}
+
+/// The locals some stack map frame gives a type other than `top`. Removing a store to any other local keeps the frames valid.
+fn declared_locals(frames: &[StackMapFrame]) -> BTreeSet<usize> {
+	let mut declared = BTreeSet::new();
+	for frame in frames {
+		let StackMapFrame::Full { locals, .. } = frame else {
+			unreachable!("frames are expanded");
+		};
+		let mut index = 0;
+		for local in locals {
+			if *local != VerificationTypeInfo::Top {
+				declared.insert(index);
+			}
+			index += if matches!(local, VerificationTypeInfo::Long | VerificationTypeInfo::Double) { 2 } else { 1 };
+		}
+	}
+	declared
+}
+
+#[cfg(test)]
+mod testing {
+	use alloc::vec::Vec;
+	use crate::{ClassFile, MethodInfo};
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::MethodDescriptor;
+	use crate::frames::compute_frames;
+	use crate::hierarchy::ClassHierarchy;
+	use crate::instruction::LvIndex;
+	use crate::instruction::builder::CodeBuilder;
+	use crate::instruction::opcode::Opcode;
+	use super::{optimize_method, OptimizeOptions};
+
+	/// Returns Test2 and a static method of it with the `descriptor` and the code built by `build`.
+	fn static_method(descriptor: &[u8], build: impl FnOnce(&mut CodeBuilder)) -> (ClassFile, MethodInfo) {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test2.class");
+		let class = ClassFile::parse(&mut &bytes[..]).unwrap();
+		let descriptor = MethodDescriptor::try_from(descriptor).unwrap();
+		let mut code = CodeBuilder::new(descriptor.clone(), true);
+		build(&mut code);
+		let mut method = class.methods.iter().find(|method| method.access_flags.is_static).unwrap().clone();
+		method.descriptor = descriptor;
+		method.code = Some(code.build(&mut PoolBuilder::new()).unwrap());
+		(class, method)
+	}
+
+	fn opcodes(method: &MethodInfo) -> Vec<Opcode> {
+		method.code.as_ref().unwrap().code.iter().map(|instruction| instruction.opcode().clone()).collect()
+	}
+
+	#[test]
+	fn constants() {
+		let (class, mut method) = static_method(b"()I", |code| {
+			let (zero, end) = (code.new_label(), code.new_label());
+			code.iconst(2).iconst(3).emit(Opcode::IAdd).iconst(4).emit(Opcode::IMul).emit(Opcode::I2l).emit(Opcode::L2i)
+				.iconst(1).ifeq(zero)
+				.goto(end)
+				.place(zero).emit(Opcode::Pop).iconst(0)
+				.place(end).emit(Opcode::IReturn);
+		});
+		assert!(optimize_method(&class.this_class, &mut method, &OptimizeOptions::default()).unwrap());
+		assert_eq!(opcodes(&method), [Opcode::BIPush(20), Opcode::IReturn]);
+		assert_eq!(method.code.unwrap().max_stack, 1);
+	}
+
+	#[test]
+	fn folding_never_grows_code() {
+		let fold = |descriptor: &[u8], build: fn(&mut CodeBuilder)| {
+			let (class, mut method) = static_method(descriptor, build);
+			let code_length = method.code.as_ref().unwrap().code.code_length();
+			optimize_method(&class.this_class, &mut method, &OptimizeOptions::default()).unwrap();
+			assert!(method.code.as_ref().unwrap().code.code_length() <= code_length);
+			opcodes(&method)
+		};
+		// ldc2_w and ldc would be longer or need a new entry in the constant pool
+		assert_eq!(fold(b"()D", |code| { code.iconst(3).emit(Opcode::I2d).emit(Opcode::DReturn); }), [Opcode::IConst3, Opcode::I2d, Opcode::DReturn]);
+		assert_eq!(fold(b"()J", |code| { code.iconst(-1).emit(Opcode::I2l).emit(Opcode::LReturn); }), [Opcode::IConstM1, Opcode::I2l, Opcode::LReturn]);
+		assert_eq!(fold(b"()I", |code| { code.iconst(100_000).iconst(1).emit(Opcode::IAdd).emit(Opcode::IReturn); }), [
+			Opcode::LdcInt(100_000), Opcode::IConst1, Opcode::IAdd, Opcode::IReturn,
+		]);
+		assert_eq!(fold(b"()J", |code| { code.iconst(1).emit(Opcode::I2l).emit(Opcode::LReturn); }), [Opcode::LConst1, Opcode::LReturn]);
+	}
+
+	#[test]
+	fn loads_and_stores() {
+		let (class, mut method) = static_method(b"(I)I", |code| {
+			code.iload(0).istore(1).iload(1).istore(2).iload(2).iload(2).istore(1).istore(2).iload(2).emit(Opcode::IReturn);
+		});
+		assert!(optimize_method(&class.this_class, &mut method, &OptimizeOptions::default()).unwrap());
+		// the first pair of local 2 stays as it is loaded twice, the store to local 1 stays as stores that aren't loaded aren't removed
+		assert_eq!(opcodes(&method), [
+			Opcode::ILoad(LvIndex(0)),
+			Opcode::IStore(LvIndex(2)),
+			Opcode::ILoad(LvIndex(2)),
+			Opcode::ILoad(LvIndex(2)),
+			Opcode::IStore(LvIndex(1)),
+			Opcode::IReturn,
+		]);
+
+		let (class, mut method) = static_method(b"(I)I", |code| {
+			code.iload(0).istore(1).iload(1).emit(Opcode::IReturn);
+		});
+		let options = OptimizeOptions { loads_and_stores: false, ..OptimizeOptions::default() };
+		assert!(!optimize_method(&class.this_class, &mut method, &options).unwrap());
+	}
+
+	#[test]
+	fn jumps_keep_frames() {
+		let (class, mut method) = static_method(b"(I)I", |code| {
+			let (zero, not_zero, end) = (code.new_label(), code.new_label(), code.new_label());
+			code.iload(0).ifeq(zero)
+				.goto(not_zero)
+				.place(zero).iconst(1).goto(end)
+				.place(not_zero).iconst(0).goto(end)
+				.place(end).emit(Opcode::IReturn);
+		});
+		let mut hierarchy = ClassHierarchy::new();
+		hierarchy.add_class_file(&class);
+		compute_frames(&hierarchy, &class.this_class, &mut method).unwrap();
+		assert!(optimize_method(&class.this_class, &mut method, &OptimizeOptions::default()).unwrap());
+		assert!(matches!(opcodes(&method)[..], [
+			Opcode::ILoad(_), Opcode::IfNe(_), Opcode::IConst1, Opcode::IReturn, Opcode::IConst0, Opcode::IReturn,
+		]));
+
+		// the frames moved along are the ones computed for the optimized code
+		let mut computed = method.clone();
+		compute_frames(&hierarchy, &class.this_class, &mut computed).unwrap();
+		assert_eq!(method, computed);
+	}
+}
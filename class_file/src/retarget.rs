@@ -0,0 +1,461 @@
+//! Changing the version of a class file, together with what the new version needs or doesn't support.
+//!
+//! Moving a class to version 50.0 or later computes the stack map frames of its methods with [compute_frames], so that it passes the type checking
+//! verifier. Subroutines, `jsr` and `ret`, are inlined first with [CodeAttribute::inline_subroutines], as frames can't describe them. Moving a class
+//! to an older version removes the frames and the attributes the older version doesn't know, desugars string concatenation with `invokedynamic` to a
+//! `StringBuilder`, and rejects what can't be expressed: lambdas, other `invokedynamic`s, loading method handles and method types, accessing private
+//! members of nestmates, records, and interface methods with code.
+//!
+//! Dynamic constants (`CONSTANT_Dynamic`) can't be parsed at all, so classes using them never get here.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Context, Result};
+use crate::ClassFile;
+use crate::call_site::CallSiteKind;
+use crate::cp::{MethodHandleInfo, MethodRefInfo, Pool, PoolEntry};
+use crate::cp::attribute::{AttributeInfo, BootstrapMethodArgument, CodeAttribute};
+use crate::dataflow::frame::ValueKind;
+use crate::descriptor::{method_string, BaseOrObjectType, FieldDescriptor, MethodDescriptor};
+use crate::frames::compute_frames;
+use crate::hierarchy::ClassHierarchy;
+use crate::instruction::LvIndex;
+use crate::instruction::builder::max_stack;
+use crate::instruction::edit::CodeEdit;
+use crate::instruction::opcode::Opcode;
+use crate::name::{ClassName, MethodName};
+use crate::write::WriteError;
+
+/// The first version with stack map frames.
+const STACK_MAP_VERSION: u16 = 50;
+/// The first version with `invokedynamic`, method handles and method types.
+const INVOKEDYNAMIC_VERSION: u16 = 51;
+/// The first version with nestmates, which may access each other's private members.
+const NESTMATES_VERSION: u16 = 55;
+
+/// Changes the version of `class` to `major_version`, see the [module](self) documentation for what this changes. `hierarchy` is used for computing
+/// stack map frames and for finding accesses to private members of other classes. It should hold the class itself and its class path, including the
+/// JDK classes it uses.
+pub fn retarget_class(class: &mut ClassFile, hierarchy: &ClassHierarchy, major_version: u16) -> Result<()> {
+	if major_version < 45 {
+		bail!("there are no class files with version {major_version}.0, the first one is 45.0");
+	}
+	if major_version < 53 && class.this_class.as_bytes() == b"module-info" {
+		bail!("modules need version 53.0");
+	}
+	if major_version < 60 && (class.super_class.is_some_and(|class| class.as_bytes() == b"java/lang/Record") || has_attribute(&class.attributes, b"Record")) {
+		bail!("records need version 60.0");
+	}
+	if class.access_flags.is_interface {
+		for method in &class.methods {
+			if major_version < 52 && method.code.is_some() && method.name.as_bytes() != b"<clinit>" {
+				bail!("the interface method {} has code, default and static interface methods need version 52.0", method_string(method.name, &method.descriptor));
+			}
+			if major_version < 53 && method.access_flags.is_private {
+				bail!("the interface method {} is private, private interface methods need version 53.0", method_string(method.name, &method.descriptor));
+			}
+		}
+	}
+
+	for method in &mut class.methods {
+		if let Some(code) = &mut method.code {
+			code.inline_subroutines()
+				.with_context(|| anyhow!("while inlining the subroutines of the method {}", method_string(method.name, &method.descriptor)))?;
+		}
+	}
+
+	let mut edits = Vec::with_capacity(class.methods.len());
+	for method in &class.methods {
+		let edit = match &method.code {
+			Some(code) => edit_code(class, hierarchy, code, major_version)
+				.with_context(|| anyhow!("while retargeting the method {}", method_string(method.name, &method.descriptor)))?,
+			None => None,
+		};
+		edits.push(edit);
+	}
+
+	let source_version = class.major_version;
+	let this_class = class.this_class;
+	for (method, edit) in class.methods.iter_mut().zip(edits) {
+		let changed = edit.is_some();
+		let is_static = method.access_flags.is_static;
+		let Some(code) = &mut method.code else {
+			continue;
+		};
+		if let Some((edit, max_locals)) = edit {
+			edit.apply(code)?;
+			code.max_locals = max_locals;
+		}
+		if major_version >= STACK_MAP_VERSION {
+			// frames of classes older than 50.0 aren't there, and the ones of methods with subroutines were dropped when inlining them
+			if changed || source_version < STACK_MAP_VERSION || code.stack_map_table.entries.is_empty() {
+				compute_frames(hierarchy, &this_class, method)
+					.with_context(|| anyhow!("while computing the frames of the method {}", method_string(method.name, &method.descriptor)))?;
+			}
+		} else {
+			code.stack_map_table.entries.clear();
+			if changed {
+				code.max_stack = max_stack(&code.code, &code.exception_table, &method.descriptor, is_static, code.max_locals as usize)?;
+			}
+		}
+	}
+
+	if major_version < INVOKEDYNAMIC_VERSION {
+		// all call sites are gone by now
+		class.attributes.retain(|attribute| !matches!(attribute, AttributeInfo::BootstrapMethods(_)));
+	}
+	if major_version < NESTMATES_VERSION {
+		class.attributes.retain(|attribute| !matches!(attribute.name(), b"NestHost" | b"NestMembers"));
+	}
+	if major_version < 61 {
+		// sealed classes aren't enforced before, which only allows more
+		class.attributes.retain(|attribute| attribute.name() != b"PermittedSubclasses");
+	}
+
+	class.major_version = major_version;
+	// before 45.3, the sizes in the Code attribute were shorter
+	class.minor_version = if major_version == 45 { 3 } else { 0 };
+	Ok(())
+}
+
+/// Changes the version of `class` like [retarget_class] and writes it. `pool` is the pool the class was parsed with, which is kept if attributes
+/// unknown to the parser remain.
+pub fn retarget(class: &ClassFile, pool: &Pool, hierarchy: &ClassHierarchy, major_version: u16) -> Result<Vec<u8>> {
+	let mut retargeted = class.clone();
+	retarget_class(&mut retargeted, hierarchy, major_version)?;
+	match retargeted.to_bytes(None) {
+		Ok(bytes) => Ok(bytes),
+		Err(WriteError::UnknownAttribute(_)) => {
+			if major_version < INVOKEDYNAMIC_VERSION {
+				let newer = pool.iter().find(|(_, entry)| matches!(entry, PoolEntry::MethodHandle(..) | PoolEntry::MethodType(_) | PoolEntry::InvokeDynamic { .. }));
+				if let Some((index, _)) = newer {
+					bail!("the original constant pool, kept for attributes unknown to the parser, has the entry {index} needing version 51.0");
+				}
+			}
+			Ok(retargeted.to_bytes(Some(pool))?)
+		},
+		Err(error) => Err(error.into()),
+	}
+}
+
+fn has_attribute(attributes: &[AttributeInfo], name: &[u8]) -> bool {
+	attributes.iter().any(|attribute| attribute.name() == name)
+}
+
+/// The version the bootstrap method of a call site needs, the version its class was added in.
+fn bootstrap_version(bootstrap_method: &MethodHandleInfo) -> u16 {
+	let class = match bootstrap_method {
+		MethodHandleInfo::InvokeStatic(method) => method.class,
+		_ => return INVOKEDYNAMIC_VERSION,
+	};
+	match class.as_bytes() {
+		b"java/lang/invoke/LambdaMetafactory" => 52,
+		b"java/lang/invoke/StringConcatFactory" => 53,
+		b"java/lang/runtime/ObjectMethods" => 60,
+		b"java/lang/runtime/SwitchBootstraps" => 65,
+		_ => INVOKEDYNAMIC_VERSION,
+	}
+}
+
+/// Returns the changes the code needs for `major_version` with the new `max_locals`, or `None` if it needs none. Fails if the code can't be expressed
+/// in that version.
+fn edit_code(class: &ClassFile, hierarchy: &ClassHierarchy, code: &CodeAttribute, major_version: u16) -> Result<Option<(CodeEdit, u16)>> {
+	let mut edit = CodeEdit::new();
+	let mut max_locals = code.max_locals as usize;
+	for (index, instruction) in code.code.iter().enumerate() {
+		let offset = instruction.offset();
+		match instruction.opcode() {
+			Opcode::InvokeDynamic { call_site, .. } => {
+				let call_site = class.call_site(call_site)?;
+				let needed = bootstrap_version(&call_site.bootstrap.bootstrap_method);
+				if major_version >= needed {
+					continue;
+				}
+				match call_site.kind {
+					CallSiteKind::StringConcat { recipe, constants } => {
+						// the arguments are kept in new locals, shared by all concatenations
+						let (opcodes, locals) = desugar_concat(&call_site.info.descriptor, recipe, constants, major_version, code.max_locals as usize)
+							.with_context(|| anyhow!("while desugaring the string concatenation at offset {offset}"))?;
+						edit.replace(index, opcodes);
+						max_locals = max_locals.max(code.max_locals as usize + locals);
+					},
+					CallSiteKind::Lambda { .. } => bail!("the lambda or method reference at offset {offset} needs version {needed}.0"),
+					CallSiteKind::Other => bail!("the invokedynamic at offset {offset} needs version {needed}.0"),
+				}
+			},
+			Opcode::LdcReferenceMethodHandle(_) | Opcode::LdcReferenceMethodType(_) if major_version < INVOKEDYNAMIC_VERSION => {
+				bail!("loading a method handle or method type at offset {offset} needs version 51.0");
+			},
+			Opcode::LdcReferenceClass(_) if major_version < 49 => bail!("loading a class constant at offset {offset} needs version 49.0"),
+			opcode if major_version < NESTMATES_VERSION => {
+				if let Some(opcode) = without_nestmates(class, hierarchy, opcode).with_context(|| anyhow!("at offset {offset}"))? {
+					edit.replace(index, vec![opcode]);
+				}
+			},
+			_ => {},
+		}
+	}
+	if edit.is_empty() {
+		return Ok(None);
+	}
+	let max_locals = u16::try_from(max_locals).map_err(|_| anyhow!("code uses {max_locals} locals, at most 65535 are possible"))?;
+	Ok(Some((edit, max_locals)))
+}
+
+/// Returns how `opcode` is written without nestmates, if it changes: calls to private methods of the class itself use `invokespecial`. Fails for
+/// accesses to private members of other classes, as far as `hierarchy` knows them.
+fn without_nestmates(class: &ClassFile, hierarchy: &ClassHierarchy, opcode: &Opcode) -> Result<Option<Opcode>> {
+	let is_own_private = |name: MethodName, descriptor: &MethodDescriptor| class.methods.iter()
+		.any(|method| method.access_flags.is_private && method.name == name && method.descriptor == *descriptor);
+	let check_method = |owner: &ClassName, name: MethodName, descriptor: &MethodDescriptor| {
+		if *owner != class.this_class && hierarchy.get(owner).and_then(|owner| owner.method(name, descriptor)).is_some_and(|method| method.access_flags.is_private) {
+			bail!("calling the private method {} of {} needs nestmates, version 55.0", method_string(name, descriptor), String::from_utf8_lossy(owner.as_bytes()));
+		}
+		Ok(())
+	};
+	match opcode {
+		Opcode::InvokeVirtual(method) if method.class == class.this_class && is_own_private(method.name, &method.descriptor) => {
+			Ok(Some(Opcode::InvokeSpecial(method.clone())))
+		},
+		Opcode::InvokeInterface { method_ref, .. } if method_ref.class == class.this_class && is_own_private(method_ref.name, &method_ref.descriptor) => {
+			// this would be an invokespecial of an interface method, which the parser doesn't support
+			bail!("calling the private interface method {} with invokeinterface needs version 55.0", method_string(method_ref.name, &method_ref.descriptor));
+		},
+		Opcode::InvokeVirtual(method) | Opcode::InvokeSpecial(method) | Opcode::InvokeStatic(method) => {
+			check_method(&method.class, method.name, &method.descriptor).map(|()| None)
+		},
+		Opcode::InvokeInterface { method_ref, .. } => check_method(&method_ref.class, method_ref.name, &method_ref.descriptor).map(|()| None),
+		Opcode::GetField(field) | Opcode::PutField(field) | Opcode::GetStatic(field) | Opcode::PutStatic(field) => {
+			let owner = &field.class;
+			if *owner != class.this_class && hierarchy.get(owner).and_then(|owner| owner.field(field.name, &field.descriptor)).is_some_and(|field| field.access_flags.is_private) {
+				bail!("accessing the private field {} of {} needs nestmates, version 55.0", String::from_utf8_lossy(field.name.as_bytes()), String::from_utf8_lossy(owner.as_bytes()));
+			}
+			Ok(None)
+		},
+		_ => Ok(None),
+	}
+}
+
+fn type_of(base_type: BaseOrObjectType) -> FieldDescriptor {
+	FieldDescriptor { array_dimension: 0, base_type }
+}
+
+fn object(class: &[u8]) -> FieldDescriptor {
+	type_of(BaseOrObjectType::Object(ClassName::from(class)))
+}
+
+/// Returns the code replacing a string concatenation call site, with the number of locals it needs after the ones of the method.
+///
+/// The arguments are stored in locals, and appended to a `StringBuilder` in the order of the recipe, with the same `append` overloads javac used before
+/// Java 9: references other than strings go through `append(Object)`, which converts them like the call site does. `StringBuffer` is used for versions
+/// before 49.0.
+fn desugar_concat(
+	descriptor: &MethodDescriptor, recipe: Option<&[u8]>, constants: &[BootstrapMethodArgument], major_version: u16, first_local: usize,
+) -> Result<(Vec<Opcode>, usize)> {
+	let builder = ClassName::from(if major_version < 49 { &b"java/lang/StringBuffer"[..] } else { &b"java/lang/StringBuilder"[..] });
+	let append = |parameter: FieldDescriptor| Opcode::InvokeVirtual(MethodRefInfo {
+		class: builder,
+		name: MethodName::from(b"append"),
+		descriptor: MethodDescriptor { parameters: vec![parameter], return_type: Some(type_of(BaseOrObjectType::Object(builder))) },
+	});
+	let append_string = |string: &[u8]| [Opcode::LdcReferenceString(string.to_vec()), append(object(b"java/lang/String"))];
+
+	let mut locals = Vec::with_capacity(descriptor.parameters.len());
+	let mut next_local = first_local;
+	for parameter in &descriptor.parameters {
+		locals.push(next_local);
+		next_local += ValueKind::of(parameter).size();
+	}
+
+	let mut opcodes = Vec::new();
+	for (parameter, &local) in descriptor.parameters.iter().zip(&locals).rev() {
+		let local = LvIndex(local);
+		opcodes.push(match ValueKind::of(parameter) {
+			ValueKind::Int => Opcode::IStore(local),
+			ValueKind::Long => Opcode::LStore(local),
+			ValueKind::Float => Opcode::FStore(local),
+			ValueKind::Double => Opcode::DStore(local),
+			ValueKind::Reference => Opcode::AStore(local),
+		});
+	}
+	opcodes.push(Opcode::New(builder));
+	opcodes.push(Opcode::Dup);
+	opcodes.push(Opcode::InvokeSpecial(MethodRefInfo {
+		class: builder,
+		name: MethodName::from(b"<init>"),
+		descriptor: MethodDescriptor { parameters: Vec::new(), return_type: None },
+	}));
+
+	let mut arguments = descriptor.parameters.iter().zip(&locals);
+	let mut append_argument = |opcodes: &mut Vec<Opcode>| -> Result<()> {
+		let (parameter, &local) = arguments.next().ok_or_else(|| anyhow!("the recipe has more arguments than the call site"))?;
+		let local = LvIndex(local);
+		let (load, appended) = match (parameter.array_dimension, &parameter.base_type) {
+			(0, BaseOrObjectType::B | BaseOrObjectType::S | BaseOrObjectType::I) => (Opcode::ILoad(local), type_of(BaseOrObjectType::I)),
+			(0, base_type @ (BaseOrObjectType::C | BaseOrObjectType::Z)) => (Opcode::ILoad(local), type_of(*base_type)),
+			(0, BaseOrObjectType::J) => (Opcode::LLoad(local), type_of(BaseOrObjectType::J)),
+			(0, BaseOrObjectType::F) => (Opcode::FLoad(local), type_of(BaseOrObjectType::F)),
+			(0, BaseOrObjectType::D) => (Opcode::DLoad(local), type_of(BaseOrObjectType::D)),
+			(0, BaseOrObjectType::Object(class)) if class.as_bytes() == b"java/lang/String" => (Opcode::ALoad(local), object(b"java/lang/String")),
+			_ => (Opcode::ALoad(local), object(b"java/lang/Object")),
+		};
+		opcodes.push(load);
+		opcodes.push(append(appended));
+		Ok(())
+	};
+
+	match recipe {
+		Some(recipe) => {
+			let mut constants = constants.iter();
+			let mut literal = Vec::new();
+			for &byte in recipe {
+				if byte != 1 && byte != 2 {
+					literal.push(byte);
+					continue;
+				}
+				if byte == 2 {
+					let constant = constants.next().ok_or_else(|| anyhow!("the recipe has more constants than the call site"))?;
+					match constant {
+						BootstrapMethodArgument::String(string) => literal.extend_from_slice(string),
+						BootstrapMethodArgument::Integer(value) => literal.extend_from_slice(format!("{value}").as_bytes()),
+						BootstrapMethodArgument::Long(value) => literal.extend_from_slice(format!("{value}").as_bytes()),
+						constant => bail!("the constant {constant:?} of the recipe can't be desugared"),
+					}
+					continue;
+				}
+				if !literal.is_empty() {
+					opcodes.extend(append_string(&literal));
+					literal.clear();
+				}
+				append_argument(&mut opcodes)?;
+			}
+			if !literal.is_empty() {
+				opcodes.extend(append_string(&literal));
+			}
+			if arguments.next().is_some() {
+				bail!("the call site has more arguments than the recipe");
+			}
+		},
+		None => {
+			for _ in &descriptor.parameters {
+				append_argument(&mut opcodes)?;
+			}
+		},
+	}
+
+	opcodes.push(Opcode::InvokeVirtual(MethodRefInfo {
+		class: builder,
+		name: MethodName::from(b"toString"),
+		descriptor: MethodDescriptor { parameters: Vec::new(), return_type: Some(object(b"java/lang/String")) },
+	}));
+	Ok((opcodes, next_local - first_local))
+}
+
+
+#[cfg(test)]
+mod testing {
+	use alloc::vec;
+	use alloc::vec::Vec;
+	use crate::ClassFile;
+	use crate::cp::{InvokeDynamicInfo, MethodHandleInfo, MethodRefInfo};
+	use crate::cp::attribute::{AttributeInfo, BootstrapMethodArgument, BootstrapMethodsAttribute, BootstrapMethodsAttributeEntry};
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::MethodDescriptor;
+	use crate::hierarchy::ClassHierarchy;
+	use crate::instruction::builder::CodeBuilder;
+	use crate::instruction::opcode::Opcode;
+	use crate::name::{ClassName, MethodName};
+	use super::{retarget, retarget_class};
+
+	/// Writes Test2 with the version `major_version` and the static method `added()V`, built by `build`.
+	fn with_method(major_version: u16, build: impl FnOnce(&mut CodeBuilder), bootstrap_methods: Vec<BootstrapMethodsAttributeEntry>) -> Vec<u8> {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test2.class");
+		let (mut class, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+		let mut pool = PoolBuilder::from_pool(&pool);
+		let descriptor = MethodDescriptor::try_from(&b"()V"[..]).unwrap();
+		let mut code = CodeBuilder::new(descriptor.clone(), true);
+		build(&mut code);
+		let mut method = class.methods.iter().find(|method| method.access_flags.is_static).unwrap().clone();
+		method.name = MethodName::from(b"added");
+		method.descriptor = descriptor;
+		method.code = Some(code.build(&mut pool).unwrap());
+		class.methods.push(method);
+		if !bootstrap_methods.is_empty() {
+			class.attributes.push(AttributeInfo::BootstrapMethods(BootstrapMethodsAttribute { bootstrap_methods }));
+		}
+		class.major_version = major_version;
+		class.write(&mut pool).unwrap()
+	}
+
+	fn added(class: &ClassFile) -> Vec<Opcode> {
+		let method = class.methods.iter().find(|method| method.name.as_bytes() == b"added").unwrap();
+		method.code.as_ref().unwrap().code.iter().map(|instruction| instruction.opcode().clone()).collect()
+	}
+
+	#[test]
+	fn subroutines() {
+		let mut bytes = with_method(49, |code| {
+			code.emit(Opcode::SIPush(0x1234)).emit(Opcode::Return).astore(1).emit(Opcode::Return).emit(Opcode::Return);
+		}, Vec::new());
+		// jsr to the astore_1, which stores the return address for the ret in place of the two returns
+		let position = bytes.windows(7).position(|window| window == [0x11, 0x12, 0x34, 0xb1, 0x4c, 0xb1, 0xb1]).unwrap();
+		bytes[position..position + 7].copy_from_slice(&[0xa8, 0x00, 0x04, 0xb1, 0x4c, 0xa9, 0x01]);
+		let (class, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+		// parsing keeps the code as it is
+		assert!(matches!(added(&class)[..], [Opcode::Jsr(_), Opcode::Return, Opcode::AStore(_), Opcode::Ret(_)]));
+
+		let mut hierarchy = ClassHierarchy::new();
+		hierarchy.add_class_file(&class);
+		let bytes = retarget(&class, &pool, &hierarchy, 52).unwrap();
+		let class = ClassFile::parse(&mut &bytes[..]).unwrap();
+		assert_eq!(class.major_version, 52);
+		// the subroutine is inlined at the jsr, with null as return address
+		assert!(matches!(added(&class)[..], [Opcode::AConstNull, Opcode::Goto(_), Opcode::Return, Opcode::AStore(_), Opcode::Goto(_)]));
+		let method = class.methods.iter().find(|method| method.name.as_bytes() == b"added").unwrap();
+		assert!(!method.code.as_ref().unwrap().stack_map_table.entries.is_empty());
+	}
+
+	#[test]
+	fn downgrade() {
+		let string_concat_factory = ClassName::from(b"java/lang/invoke/StringConcatFactory");
+		let bootstrap = BootstrapMethodsAttributeEntry {
+			bootstrap_method: MethodHandleInfo::InvokeStatic(MethodRefInfo {
+				class: string_concat_factory,
+				name: MethodName::from(b"makeConcatWithConstants"),
+				descriptor: MethodDescriptor::try_from(&b"(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;\
+					Ljava/lang/String;[Ljava/lang/Object;)Ljava/lang/invoke/CallSite;"[..]).unwrap(),
+			}),
+			bootstrap_arguments: vec![BootstrapMethodArgument::String(b"n=\x01 \x02 s=\x01".to_vec()), BootstrapMethodArgument::String(b"c".to_vec())],
+		};
+		let bytes = with_method(53, |code| {
+			code.iconst(5).ldc_string(b"s").invokedynamic(InvokeDynamicInfo {
+				bootstrap_method_attribute_index: 0,
+				name: MethodName::from(b"makeConcatWithConstants"),
+				descriptor: MethodDescriptor::try_from(&b"(ILjava/lang/String;)Ljava/lang/String;"[..]).unwrap(),
+			}).emit(Opcode::Pop).emit(Opcode::Return);
+		}, vec![bootstrap]);
+		let (class, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+		let mut hierarchy = ClassHierarchy::new();
+		hierarchy.add_class_file(&class);
+
+		let bytes = retarget(&class, &pool, &hierarchy, 52).unwrap();
+		let retargeted = ClassFile::parse(&mut &bytes[..]).unwrap();
+		let opcodes = added(&retargeted);
+		assert!(!opcodes.iter().any(|opcode| matches!(opcode, Opcode::InvokeDynamic { .. })));
+		let constants: Vec<&[u8]> = opcodes.iter()
+			.filter_map(|opcode| match opcode {
+				Opcode::LdcReferenceString(string) => Some(&string[..]),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(constants, [&b"s"[..], b"n=", b" c s="]);
+		let appends = opcodes.iter().filter(|opcode| matches!(opcode, Opcode::InvokeVirtual(method) if method.name.as_bytes() == b"append")).count();
+		assert_eq!(appends, 4);
+
+		let mut record = class.clone();
+		record.super_class = Some(ClassName::from(b"java/lang/Record"));
+		assert!(retarget_class(&mut record, &hierarchy, 59).is_err());
+	}
+}
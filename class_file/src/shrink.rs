@@ -0,0 +1,657 @@
+//! Finding the classes, methods and fields that can't be used from some roots, and removing them, like the shrinking step of ProGuard.
+//!
+//! The roots are the main methods, the classes and members with some annotations, and the ones matched by [KeepRule]s, which is how the uses through
+//! reflection are declared. The methods used are the ones reachable from the roots in a [CallGraph], together with the methods the JVM or the library
+//! calls without a call in the code given: overrides of library methods, like `toString` or `Runnable.run`, of the classes instantiated, the methods of
+//! serialization and `values` and `valueOf` of enums.
+//!
+//! A class is used if it's referenced by the code of a method used, named in the descriptor of a member used, annotates a class or member used, or is a
+//! supertype of a class used. The fields used are the ones accessed by the methods used, and the serialized fields of the classes instantiated.
+//! Classes not given, like the ones of the JDK or the libraries on the class path, are never removed.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+use anyhow::{anyhow, bail, Context, Result};
+use crate::{ClassFile, FieldInfo, MethodInfo};
+use crate::call_graph::{Analysis, CallGraph, CallGraphBuilder, MethodId};
+use crate::cp::MethodHandleInfo;
+use crate::cp::attribute::{AttributeInfo, BootstrapMethodArgument};
+use crate::deps::visit_descriptor_classes;
+use crate::descriptor::{FieldDescriptor, MethodDescriptor};
+use crate::hierarchy::ClassHierarchy;
+use crate::instruction::opcode::Opcode;
+use crate::name::{ClassName, FieldName};
+
+const OBJECT: &[u8] = b"java/lang/Object";
+const SERIALIZABLE: &[u8] = b"java/io/Serializable";
+
+/// The methods of `java/lang/Object` a class may override, called by the library on any object.
+const OBJECT_METHODS: &[(&[u8], &[u8])] = &[
+	(b"equals", b"(Ljava/lang/Object;)Z"),
+	(b"hashCode", b"()I"),
+	(b"toString", b"()Ljava/lang/String;"),
+	(b"clone", b"()Ljava/lang/Object;"),
+	(b"finalize", b"()V"),
+];
+
+/// The methods `ObjectOutputStream` and `ObjectInputStream` call on serializable classes, these may be private.
+const SERIALIZATION_METHODS: &[(&[u8], &[u8])] = &[
+	(b"writeObject", b"(Ljava/io/ObjectOutputStream;)V"),
+	(b"readObject", b"(Ljava/io/ObjectInputStream;)V"),
+	(b"readObjectNoData", b"()V"),
+	(b"writeReplace", b"()Ljava/lang/Object;"),
+	(b"readResolve", b"()Ljava/lang/Object;"),
+];
+
+/// A field, given by the class declaring it, its name and its descriptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FieldId {
+	pub class: ClassName,
+	pub name: FieldName,
+	pub descriptor: FieldDescriptor,
+}
+
+impl FieldId {
+	pub fn new(class: ClassName, name: FieldName, descriptor: FieldDescriptor) -> FieldId {
+		FieldId { class, name, descriptor }
+	}
+}
+
+/// Formats the field like `java/lang/System.out:Ljava/io/PrintStream;`.
+impl Display for FieldId {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}.{}:{}",
+			String::from_utf8_lossy(self.class.as_bytes()),
+			String::from_utf8_lossy(self.name.as_bytes()),
+			String::from_utf8_lossy(&self.descriptor.to_bytes()))
+	}
+}
+
+/// Keeps the classes or members matching it, for the ones used through reflection. A rule is written on one line, like:
+///
+/// ```txt
+/// com/example/Plugin                       the class, created through reflection with its constructors
+/// com/example/plugins/*                    all classes of a package, `**` also matches the classes of subpackages
+/// com/example/Plugin.configure             all methods and fields named `configure`
+/// com/example/Plugin.configure(I)V         a method with a descriptor
+/// com/example/Plugin.level:I               a field with a descriptor
+/// com/example/Plugin.*                     all members
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeepRule {
+	/// The pattern for the names of the classes, where `*` matches any part of a name within a package and `**` any part of a name.
+	pub class: Vec<u8>,
+	/// The pattern for the names of the members, where `*` matches any part of a name. `None` keeps the class itself with its constructors.
+	pub member: Option<Vec<u8>>,
+	/// The descriptor of the members, `None` to match the members with any descriptor.
+	pub descriptor: Option<Vec<u8>>,
+}
+
+impl KeepRule {
+	pub fn parse(rule: &str) -> Result<KeepRule> {
+		let rule = rule.trim();
+		if rule.is_empty() {
+			bail!("empty keep rule");
+		}
+		let Some((class, member)) = rule.split_once('.') else {
+			return Ok(KeepRule { class: rule.as_bytes().to_vec(), member: None, descriptor: None });
+		};
+		let (member, descriptor) = if let Some(start) = member.find('(') {
+			let descriptor = &member.as_bytes()[start..];
+			MethodDescriptor::try_from(descriptor).with_context(|| anyhow!("in keep rule {rule:?}"))?;
+			(&member[..start], Some(descriptor.to_vec()))
+		} else if let Some((member, descriptor)) = member.split_once(':') {
+			FieldDescriptor::try_from(descriptor.as_bytes()).with_context(|| anyhow!("in keep rule {rule:?}"))?;
+			(member, Some(descriptor.as_bytes().to_vec()))
+		} else {
+			(member, None)
+		};
+		if class.is_empty() || member.is_empty() {
+			bail!("keep rule {rule:?} is missing the class or the member");
+		}
+		Ok(KeepRule { class: class.as_bytes().to_vec(), member: Some(member.as_bytes().to_vec()), descriptor })
+	}
+
+	/// Parses a file of keep rules, one per line. Empty lines and lines starting with `#` are skipped.
+	pub fn parse_all(rules: &str) -> Result<Vec<KeepRule>> {
+		rules.lines()
+			.enumerate()
+			.filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+			.map(|(index, line)| KeepRule::parse(line).with_context(|| anyhow!("on line {}", index + 1)))
+			.collect()
+	}
+
+	/// Whether the rule keeps the class itself.
+	pub fn keeps_class(&self, class: &ClassName) -> bool {
+		self.member.is_none() && matches(&self.class, class.as_bytes())
+	}
+
+	/// Whether the rule keeps the member with the name and descriptor in `class`.
+	pub fn keeps_member(&self, class: &ClassName, name: &[u8], descriptor: &[u8]) -> bool {
+		let Some(member) = &self.member else {
+			return false;
+		};
+		matches(&self.class, class.as_bytes()) && matches(member, name) && self.descriptor.as_ref().is_none_or(|expected| expected == descriptor)
+	}
+}
+
+/// Matches a name against a pattern with `*` matching any part of a name within a package, and `**` any part.
+fn matches(pattern: &[u8], name: &[u8]) -> bool {
+	match pattern {
+		[] => name.is_empty(),
+		[b'*', b'*', rest @ ..] => (0..=name.len()).any(|start| matches(rest, &name[start..])),
+		[b'*', rest @ ..] => (0..=name.len())
+			.take_while(|&start| start == 0 || name[start - 1] != b'/')
+			.any(|start| matches(rest, &name[start..])),
+		[first, rest @ ..] => name.first() == Some(first) && matches(rest, &name[1..]),
+	}
+}
+
+/// What the analysis found to be used and unused. Only the classes given to the [Shrinker] are in here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Usage {
+	/// The call graph from the roots, including the methods the JVM calls implicitly.
+	pub graph: CallGraph,
+	pub classes: BTreeSet<ClassName>,
+	pub methods: BTreeSet<MethodId>,
+	pub fields: BTreeSet<FieldId>,
+	pub unused_classes: BTreeSet<ClassName>,
+	/// The methods of the classes used that aren't used, the members of unused classes are left out.
+	pub unused_methods: BTreeSet<MethodId>,
+	/// The fields of the classes used that aren't used.
+	pub unused_fields: BTreeSet<FieldId>,
+}
+
+impl Usage {
+	/// Returns the class without its unused methods and fields, and without the entries of unused classes in its `InnerClasses` attribute. Returns
+	/// `None` if the class itself is unused.
+	pub fn shrink(&self, class: &ClassFile) -> Option<ClassFile> {
+		if self.unused_classes.contains(&class.this_class) {
+			return None;
+		}
+		let mut class = class.clone();
+		let name = class.this_class;
+		class.methods.retain(|method| !self.unused_methods.contains(&MethodId::new(name, method.name, method.descriptor.clone())));
+		class.fields.retain(|field| !self.unused_fields.contains(&FieldId::new(name, field.name, field.descriptor)));
+		for attribute in &mut class.attributes {
+			if let AttributeInfo::InnerClasses(inner_classes) = attribute {
+				inner_classes.classes.retain(|entry| !self.unused_classes.contains(&entry.inner_class));
+			}
+		}
+		Some(class)
+	}
+}
+
+/// Collects the classes to shrink and the roots, see [Shrinker::analyze].
+#[derive(Debug, Clone)]
+pub struct Shrinker<'a> {
+	hierarchy: &'a ClassHierarchy,
+	analysis: Analysis,
+	classes: BTreeMap<ClassName, &'a ClassFile>,
+	main_methods: bool,
+	annotations: BTreeSet<Vec<u8>>,
+	rules: Vec<KeepRule>,
+}
+
+impl<'a> Shrinker<'a> {
+	/// Creates a shrinker resolving references with `hierarchy`, which should contain the classes added with [Shrinker::add_class] and the libraries
+	/// they use. Methods overriding the ones of classes not in the hierarchy are kept, apart from the methods of `java/lang/Object`.
+	pub fn new(hierarchy: &'a ClassHierarchy, analysis: Analysis) -> Shrinker<'a> {
+		Shrinker {
+			hierarchy,
+			analysis,
+			classes: BTreeMap::new(),
+			main_methods: false,
+			annotations: BTreeSet::new(),
+			rules: Vec::new(),
+		}
+	}
+
+	/// Adds a class that may be shrunk. Like on a class path, the first class added with a name wins.
+	pub fn add_class(&mut self, class: &'a ClassFile) -> &mut Self {
+		self.classes.entry(class.this_class).or_insert(class);
+		self
+	}
+
+	/// Keeps the `public static void main(String[])` methods.
+	pub fn main_methods(&mut self) -> &mut Self {
+		self.main_methods = true;
+		self
+	}
+
+	/// Keeps the classes, methods and fields annotated with the annotation interface with the field descriptor `descriptor`, like
+	/// `Lorg/junit/jupiter/api/Test;`. An annotated class is kept like with a [KeepRule] for the class.
+	pub fn annotation(&mut self, descriptor: &[u8]) -> &mut Self {
+		self.annotations.insert(descriptor.to_vec());
+		self
+	}
+
+	pub fn keep(&mut self, rule: KeepRule) -> &mut Self {
+		self.rules.push(rule);
+		self
+	}
+
+	fn is_annotated(&self, attributes: &[AttributeInfo]) -> bool {
+		!self.annotations.is_empty() && attributes.iter()
+			.filter_map(|attribute| match attribute {
+				AttributeInfo::RuntimeVisibleAnnotations(attribute) => Some(&attribute.annotations),
+				AttributeInfo::RuntimeInvisibleAnnotations(attribute) => Some(&attribute.annotations),
+				_ => None,
+			})
+			.flatten()
+			.any(|annotation| self.annotations.contains(&annotation.annotation_type))
+	}
+
+	/// Whether the JVM or the library may call `method` on an instance of `class`, without a call in the code given.
+	fn is_called_implicitly(&self, class: &ClassFile, method: &MethodInfo) -> bool {
+		let flags = &method.access_flags;
+		let key = (method.name.as_bytes(), &method.descriptor.to_bytes()[..]);
+		if flags.is_static {
+			// Enum.valueOf calls values() through reflection
+			return class.access_flags.is_enum && (key.0 == b"values" || key.0 == b"valueOf");
+		}
+		if method.name.as_bytes() == b"<init>" || method.name.as_bytes() == b"<clinit>" {
+			return false;
+		}
+		if SERIALIZATION_METHODS.contains(&key) && self.hierarchy.is_subtype_of(&class.this_class, &ClassName::from(SERIALIZABLE)) {
+			return true;
+		}
+		if flags.is_private {
+			return false;
+		}
+		self.hierarchy.supertypes(&class.this_class).iter()
+			.filter(|supertype| !self.classes.contains_key(supertype))
+			.any(|supertype| match self.hierarchy.get(supertype) {
+				Some(entry) => entry.method(method.name, &method.descriptor)
+					.is_some_and(|method| !method.access_flags.is_private && !method.access_flags.is_static),
+				None if supertype.as_bytes() == OBJECT => OBJECT_METHODS.contains(&key),
+				// nothing is known of the methods of a class outside the hierarchy
+				None => true,
+			})
+	}
+
+	/// Whether the serialization of an instance of a class writes `field`.
+	fn is_serialized(&self, class: &ClassFile, field: &FieldInfo) -> bool {
+		let flags = &field.access_flags;
+		let is_serialized = match field.name.as_bytes() {
+			b"serialVersionUID" | b"serialPersistentFields" => flags.is_static,
+			_ => !flags.is_static && !flags.is_transient,
+		};
+		is_serialized && self.hierarchy.is_subtype_of(&class.this_class, &ClassName::from(SERIALIZABLE))
+	}
+
+	/// Builds the call graph from the roots and collects the classes and members used.
+	pub fn analyze(&self) -> Usage {
+		let mut builder = CallGraphBuilder::new(self.hierarchy, self.analysis);
+		for class in self.classes.values() {
+			builder.add_class(class);
+		}
+		if self.main_methods {
+			builder.main_methods();
+		}
+
+		let mut kept_classes = BTreeSet::new();
+		let mut used = Used { shrinker: self, classes: Vec::new(), methods: BTreeSet::new(), fields: BTreeSet::new() };
+		for class in self.classes.values() {
+			let name = class.this_class;
+			if self.rules.iter().any(|rule| rule.keeps_class(&name)) || self.is_annotated(&class.attributes) {
+				kept_classes.insert(name);
+				builder.instantiated(name);
+				for method in class.methods.iter().filter(|method| method.name.as_bytes() == b"<init>") {
+					builder.entry_point(MethodId::new(name, method.name, method.descriptor.clone()));
+				}
+			}
+			for method in &class.methods {
+				let descriptor = method.descriptor.to_bytes();
+				if self.is_annotated(&method.attributes) || self.rules.iter().any(|rule| rule.keeps_member(&name, method.name.as_bytes(), &descriptor)) {
+					if !method.access_flags.is_static {
+						builder.instantiated(name);
+					}
+					builder.entry_point(MethodId::new(name, method.name, method.descriptor.clone()));
+				}
+			}
+			for field in &class.fields {
+				let descriptor = field.descriptor.to_bytes();
+				if self.is_annotated(&field.attributes) || self.rules.iter().any(|rule| rule.keeps_member(&name, field.name.as_bytes(), &descriptor)) {
+					used.field(FieldId::new(name, field.name, field.descriptor));
+				}
+			}
+		}
+
+		// the implicitly called methods of the classes instantiated may instantiate more classes
+		let graph = loop {
+			let graph = builder.build();
+			let implicit: Vec<MethodId> = graph.instantiated.iter()
+				.chain(&kept_classes)
+				.filter_map(|class| self.classes.get(class))
+				.flat_map(|class| class.methods.iter()
+					.filter(|method| self.is_called_implicitly(class, method))
+					.map(|method| MethodId::new(class.this_class, method.name, method.descriptor.clone())))
+				.filter(|method| !graph.methods.contains(method))
+				.collect();
+			if implicit.is_empty() {
+				break graph;
+			}
+			for method in implicit {
+				builder.entry_point(method);
+			}
+		};
+
+		for method in &graph.methods {
+			let Some(class) = self.classes.get(&method.class) else {
+				continue;
+			};
+			if let Some(info) = class.methods.iter().find(|info| info.name == method.name && info.descriptor == method.descriptor) {
+				used.method(method.clone());
+				used.scan(class, info);
+			}
+		}
+		for name in graph.instantiated.iter().chain(&kept_classes) {
+			used.classes.push(*name);
+			// an instance also has the fields of the superclasses
+			for class in core::iter::once(*name).chain(self.hierarchy.superclasses(name)).filter_map(|class| self.classes.get(&class)) {
+				for field in class.fields.iter().filter(|field| self.is_serialized(class, field)) {
+					used.field(FieldId::new(class.this_class, field.name, field.descriptor));
+				}
+			}
+		}
+		let classes = used.close();
+
+		let mut usage = Usage {
+			graph,
+			classes,
+			methods: used.methods,
+			fields: used.fields,
+			unused_classes: BTreeSet::new(),
+			unused_methods: BTreeSet::new(),
+			unused_fields: BTreeSet::new(),
+		};
+		for class in self.classes.values() {
+			let name = class.this_class;
+			if !usage.classes.contains(&name) {
+				usage.unused_classes.insert(name);
+				continue;
+			}
+			for method in &class.methods {
+				let method = MethodId::new(name, method.name, method.descriptor.clone());
+				if !usage.methods.contains(&method) {
+					usage.unused_methods.insert(method);
+				}
+			}
+			for field in &class.fields {
+				let field = FieldId::new(name, field.name, field.descriptor);
+				if !usage.fields.contains(&field) {
+					usage.unused_fields.insert(field);
+				}
+			}
+		}
+		usage
+	}
+}
+
+/// The classes and members found to be used so far. Classes are collected with duplicates and closed over their supertypes in the end.
+struct Used<'b, 'a> {
+	shrinker: &'b Shrinker<'a>,
+	classes: Vec<ClassName>,
+	methods: BTreeSet<MethodId>,
+	fields: BTreeSet<FieldId>,
+}
+
+impl Used<'_, '_> {
+	fn class(&mut self, class: ClassName) {
+		if class.as_bytes().first() == Some(&b'[') {
+			visit_descriptor_classes(class.as_bytes(), &mut |class| self.classes.push(class));
+		} else {
+			self.classes.push(class);
+		}
+	}
+
+	fn descriptor(&mut self, descriptor: &[u8]) {
+		visit_descriptor_classes(descriptor, &mut |class| self.classes.push(class));
+	}
+
+	fn method(&mut self, method: MethodId) {
+		if self.shrinker.classes.contains_key(&method.class) {
+			self.classes.push(method.class);
+			self.descriptor(&method.descriptor.to_bytes());
+			self.methods.insert(method);
+		}
+	}
+
+	fn field(&mut self, field: FieldId) {
+		if self.shrinker.classes.contains_key(&field.class) {
+			self.classes.push(field.class);
+			self.descriptor(&field.descriptor.to_bytes());
+			self.fields.insert(field);
+		}
+	}
+
+	/// Marks the method or field a reference resolves to as used, which keeps abstract methods and the members inherited by the class referenced.
+	fn method_reference(&mut self, class: ClassName, name: crate::name::MethodName, descriptor: &MethodDescriptor) {
+		self.class(class);
+		self.descriptor(&descriptor.to_bytes());
+		if let Some((class, method)) = self.shrinker.hierarchy.resolve_method(&class, name, descriptor) {
+			self.method(MethodId::new(class, method.name, method.descriptor.clone()));
+		}
+	}
+
+	fn field_reference(&mut self, class: ClassName, name: FieldName, descriptor: &FieldDescriptor) {
+		self.class(class);
+		self.descriptor(&descriptor.to_bytes());
+		if let Some((class, field)) = self.shrinker.hierarchy.resolve_field(&class, name, descriptor) {
+			self.field(FieldId::new(class, field.name, field.descriptor));
+		}
+	}
+
+	fn method_handle(&mut self, handle: &MethodHandleInfo) {
+		match handle {
+			MethodHandleInfo::GetField(field) | MethodHandleInfo::GetStatic(field) |
+			MethodHandleInfo::PutField(field) | MethodHandleInfo::PutStatic(field) => self.field_reference(field.class, field.name, &field.descriptor),
+			MethodHandleInfo::InvokeVirtual(method) | MethodHandleInfo::InvokeStatic(method) |
+			MethodHandleInfo::InvokeSpecial(method) | MethodHandleInfo::NewInvokeSpecial(method) => {
+				self.method_reference(method.class, method.name, &method.descriptor);
+			},
+			MethodHandleInfo::InvokeInterface(method) | MethodHandleInfo::InvokeStaticInterface(method) |
+			MethodHandleInfo::InvokeSpecialInterface(method) => self.method_reference(method.class, method.name, &method.descriptor),
+		}
+	}
+
+	fn annotations(&mut self, attributes: &[AttributeInfo]) {
+		for attribute in attributes {
+			let annotations = match attribute {
+				AttributeInfo::RuntimeVisibleAnnotations(attribute) => &attribute.annotations,
+				AttributeInfo::RuntimeVisibleParameterAnnotations(attribute) => {
+					for parameter in &attribute.parameter_annotations {
+						for annotation in &parameter.annotations {
+							self.descriptor(&annotation.annotation_type);
+						}
+					}
+					continue;
+				},
+				_ => continue,
+			};
+			for annotation in annotations {
+				self.descriptor(&annotation.annotation_type);
+			}
+		}
+	}
+
+	/// Marks everything the code of a method used refers to as used.
+	fn scan(&mut self, class: &ClassFile, method: &MethodInfo) {
+		self.annotations(&method.attributes);
+		for &exception in method.exceptions() {
+			self.class(exception);
+		}
+		let Some(code) = &method.code else {
+			return;
+		};
+		for instruction in &code.code {
+			match instruction.opcode() {
+				Opcode::GetField(field) | Opcode::GetStatic(field) | Opcode::PutField(field) | Opcode::PutStatic(field) => {
+					self.field_reference(field.class, field.name, &field.descriptor);
+				},
+				Opcode::InvokeStatic(method) | Opcode::InvokeSpecial(method) | Opcode::InvokeVirtual(method) => {
+					self.method_reference(method.class, method.name, &method.descriptor);
+				},
+				Opcode::InvokeInterface { method_ref, .. } => self.method_reference(method_ref.class, method_ref.name, &method_ref.descriptor),
+				Opcode::InvokeDynamic { call_site, .. } => {
+					self.descriptor(&call_site.descriptor.to_bytes());
+					if let Ok(call_site) = class.call_site(call_site) {
+						self.method_handle(&call_site.bootstrap.bootstrap_method);
+						for argument in &call_site.bootstrap.bootstrap_arguments {
+							match argument {
+								BootstrapMethodArgument::Class(class) => self.class(*class),
+								BootstrapMethodArgument::MethodHandle(handle) => self.method_handle(handle),
+								BootstrapMethodArgument::MethodType(descriptor) => self.descriptor(&descriptor.to_bytes()),
+								_ => {},
+							}
+						}
+					}
+				},
+				Opcode::New(class) | Opcode::ANewArray(class) | Opcode::CheckCast(class) | Opcode::InstanceOf(class) |
+				Opcode::MultiANewArray(class, _) | Opcode::LdcReferenceClass(class) => self.class(*class),
+				Opcode::LdcReferenceMethodType(descriptor) => self.descriptor(&descriptor.to_bytes()),
+				Opcode::LdcReferenceMethodHandle(handle) => self.method_handle(handle),
+				_ => {},
+			}
+		}
+		for entry in &code.exception_table {
+			if let Some(catch_type) = entry.catch_type {
+				self.class(catch_type);
+			}
+		}
+		// the verifier may load the classes in the stack map frames to check assignments
+		for frame in &code.stack_map_table.entries {
+			use crate::cp::attribute::{StackMapFrame, VerificationTypeInfo};
+			let types: &[VerificationTypeInfo] = match frame {
+				StackMapFrame::SameLocals1StackItem { stack, .. } => core::slice::from_ref(stack),
+				StackMapFrame::Append { locals, .. } => locals,
+				StackMapFrame::Full { locals, stack, .. } => {
+					for verification_type in stack {
+						if let VerificationTypeInfo::Object(class) = verification_type {
+							self.class(*class);
+						}
+					}
+					locals
+				},
+				_ => &[],
+			};
+			for verification_type in types {
+				if let VerificationTypeInfo::Object(class) = verification_type {
+					self.class(*class);
+				}
+			}
+		}
+	}
+
+	/// Closes the classes used over their supertypes and annotations, keeping all elements of the annotation interfaces used. Returns the classes
+	/// used out of the ones given to the shrinker.
+	fn close(&mut self) -> BTreeSet<ClassName> {
+		let mut classes = BTreeSet::new();
+		while let Some(name) = self.classes.pop() {
+			let Some(class) = self.shrinker.classes.get(&name).copied() else {
+				continue;
+			};
+			if !classes.insert(name) {
+				continue;
+			}
+			self.classes.extend(class.super_class.iter().chain(&class.interfaces));
+			self.annotations(&class.attributes);
+			for field in &class.fields {
+				if self.fields.contains(&FieldId::new(name, field.name, field.descriptor)) {
+					self.annotations(&field.attributes);
+				}
+			}
+			if class.access_flags.is_annotation {
+				for method in &class.methods {
+					self.method(MethodId::new(name, method.name, method.descriptor.clone()));
+				}
+			}
+		}
+		classes
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::ClassFile;
+	use crate::call_graph::{Analysis, MethodId};
+	use crate::cp::{FieldRefInfo, MethodRefInfo};
+	use crate::cp::attribute::{Annotation, AttributeInfo, RuntimeVisibleAnnotationsAttribute};
+	use crate::descriptor::{FieldDescriptor, MethodDescriptor};
+	use crate::fixture::{class, field, method_with_code};
+	use crate::hierarchy::ClassHierarchy;
+	use crate::instruction::builder::CodeBuilder;
+	use crate::instruction::opcode::Opcode;
+	use crate::name::{ClassName, FieldName, MethodName};
+	use crate::shrink::{FieldId, KeepRule, Shrinker};
+
+	fn init(builder: &mut CodeBuilder) {
+		builder.aload(0)
+			.invokespecial(MethodRefInfo { class: ClassName::from(b"java/lang/Object"), name: MethodName::from(b"<init>"), descriptor: MethodDescriptor::try_from(&b"()V"[..]).unwrap() })
+			.emit(Opcode::Return);
+	}
+
+	#[test]
+	fn unused_members() {
+		let reference = |class: &[u8], name: &[u8], descriptor: &[u8]| MethodRefInfo {
+			class: ClassName::from(class), name: MethodName::from(name), descriptor: MethodDescriptor::try_from(descriptor).unwrap(),
+		};
+		let used = FieldRefInfo { class: ClassName::from(b"A"), name: FieldName::from(b"used"), descriptor: FieldDescriptor::try_from(&b"I"[..]).unwrap() };
+		let object = b"java/lang/Object";
+		let classes = [
+			class(0x0021, b"Main", object, &[], Vec::new(), vec![
+				method_with_code(0x0009, b"main", b"([Ljava/lang/String;)V", |code| {
+					code.new_(ClassName::from(b"A")).emit(Opcode::Dup).invokespecial(reference(b"A", b"<init>", b"()V"))
+						.getfield(used.clone()).emit(Opcode::Pop).emit(Opcode::Return);
+				}),
+			]),
+			class(0x0021, b"A", object, &[], vec![field(0x0000, b"used", b"I"), field(0x0000, b"unused", b"I")], vec![
+				method_with_code(0x0001, b"<init>", b"()V", init),
+				method_with_code(0x0001, b"toString", b"()Ljava/lang/String;", |code| { code.aconst_null().emit(Opcode::AReturn); }),
+				method_with_code(0x0001, b"unused", b"()V", |code| { code.emit(Opcode::Return); }),
+			]),
+			class(0x0021, b"B", object, &[], Vec::new(), vec![method_with_code(0x0001, b"<init>", b"()V", init)]),
+			class(0x0021, b"plugins/Plugin", object, &[], Vec::new(), vec![method_with_code(0x0001, b"<init>", b"()V", init)]),
+			ClassFile {
+				attributes: vec![AttributeInfo::RuntimeVisibleAnnotations(RuntimeVisibleAnnotationsAttribute {
+					annotations: vec![Annotation { annotation_type: b"Lorg/junit/Test;".to_vec(), element_value_pairs: Vec::new() }],
+				})],
+				..class(0x0021, b"ATest", object, &[], Vec::new(), vec![method_with_code(0x0001, b"<init>", b"()V", init)])
+			},
+		];
+		let mut hierarchy = ClassHierarchy::new();
+		for class in &classes {
+			hierarchy.add_class_file(class);
+		}
+		let mut shrinker = Shrinker::new(&hierarchy, Analysis::RapidType);
+		for class in &classes {
+			shrinker.add_class(class);
+		}
+		let usage = shrinker.main_methods()
+			.annotation(b"Lorg/junit/Test;")
+			.keep(KeepRule::parse("plugins/*").unwrap())
+			.analyze();
+
+		let names = |classes: &std::collections::BTreeSet<ClassName>| classes.iter().map(|class| class.as_bytes()).collect::<Vec<_>>();
+		assert_eq!(names(&usage.classes), [&b"A"[..], b"ATest", b"Main", b"plugins/Plugin"]);
+		assert_eq!(names(&usage.unused_classes), [b"B"]);
+		// toString may be called by the library on the instance of A
+		assert_eq!(usage.unused_methods.iter().map(MethodId::to_string).collect::<Vec<_>>(), ["A.unused()V"]);
+		assert_eq!(usage.unused_fields.iter().map(FieldId::to_string).collect::<Vec<_>>(), ["A.unused:I"]);
+
+		let a = usage.shrink(&classes[1]).unwrap();
+		assert_eq!(a.methods.iter().map(|method| method.name.as_bytes()).collect::<Vec<_>>(), [&b"<init>"[..], b"toString"]);
+		assert_eq!(a.fields.len(), 1);
+		assert!(usage.shrink(&classes[2]).is_none());
+
+		assert_eq!(KeepRule::parse("a/B.run(I)V").unwrap(), KeepRule { class: b"a/B".to_vec(), member: Some(b"run".to_vec()), descriptor: Some(b"(I)V".to_vec()) });
+		assert!(KeepRule::parse("a/B.level:Q").is_err());
+		let rules = KeepRule::parse_all("# plugins\na/**\n\na/B.*\n").unwrap();
+		assert!(rules[0].keeps_class(&ClassName::from(b"a/b/C")));
+		assert!(!KeepRule::parse("a/*").unwrap().keeps_class(&ClassName::from(b"a/b/C")));
+		assert!(rules[1].keeps_member(&ClassName::from(b"a/B"), b"anything", b"()V"));
+	}
+}
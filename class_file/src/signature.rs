@@ -0,0 +1,361 @@
+//! Generic signatures, as found in the `Signature` attribute (4.7.9.1).
+//!
+//! Signatures describe the types of classes, methods and fields as declared in the source, including type parameters and type arguments, which
+//! descriptors erase.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use alloc::string::String;
+use anyhow::{anyhow, bail, Result};
+use crate::name::ClassName;
+
+/// ```text
+/// ReferenceTypeSignature | BaseType
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeSignature {
+	/// One of `B`, `C`, `D`, `F`, `I`, `J`, `S` and `Z`.
+	Base(u8),
+	Class(ClassTypeSignature),
+	TypeVariable(Vec<u8>),
+	Array(Box<TypeSignature>),
+}
+
+/// A class type, like `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassTypeSignature {
+	/// The binary name of the class, with `$` separating the nested classes, like `java/util/Map$Entry`.
+	pub name: ClassName,
+	pub type_arguments: Vec<TypeArgument>,
+	/// The enclosing class with its type arguments, if this is a nested class written as `Outer<...>.Inner`.
+	pub outer: Option<Box<ClassTypeSignature>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeArgument {
+	/// `*`, the unbounded wildcard `?`.
+	Any,
+	/// `+`, `? extends T`.
+	Extends(TypeSignature),
+	/// `-`, `? super T`.
+	Super(TypeSignature),
+	Exact(TypeSignature),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeParameter {
+	pub name: Vec<u8>,
+	/// The class bound, which is missing for type parameters only bounded by interfaces.
+	pub class_bound: Option<TypeSignature>,
+	pub interface_bounds: Vec<TypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassSignature {
+	pub type_parameters: Vec<TypeParameter>,
+	pub super_class: ClassTypeSignature,
+	pub interfaces: Vec<ClassTypeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodSignature {
+	pub type_parameters: Vec<TypeParameter>,
+	pub parameters: Vec<TypeSignature>,
+	/// `None` for `void`.
+	pub return_type: Option<TypeSignature>,
+	pub throws: Vec<TypeSignature>,
+}
+
+struct Parser<'a> {
+	bytes: &'a [u8],
+	pos: usize,
+}
+
+impl Parser<'_> {
+	fn peek(&self) -> Option<u8> {
+		self.bytes.get(self.pos).copied()
+	}
+
+	fn next(&mut self) -> Result<u8> {
+		let byte = self.peek().ok_or_else(|| anyhow!("unexpected end of signature {:?}", String::from_utf8_lossy(self.bytes)))?;
+		self.pos += 1;
+		Ok(byte)
+	}
+
+	fn expect(&mut self, expected: u8) -> Result<()> {
+		let byte = self.next()?;
+		if byte != expected {
+			bail!("expected {:?} at {} in signature {:?}, got {:?}", expected as char, self.pos - 1, String::from_utf8_lossy(self.bytes), byte as char);
+		}
+		Ok(())
+	}
+
+	fn finish(&self) -> Result<()> {
+		if self.pos != self.bytes.len() {
+			bail!("unexpected content at {} in signature {:?}", self.pos, String::from_utf8_lossy(self.bytes));
+		}
+		Ok(())
+	}
+
+	/// Reads an identifier, which ends at any of `.;[/<>:`.
+	fn identifier(&mut self) -> Result<&[u8]> {
+		let start = self.pos;
+		while let Some(byte) = self.peek() {
+			if b".;[/<>:".contains(&byte) {
+				break;
+			}
+			self.pos += 1;
+		}
+		if start == self.pos {
+			bail!("expected identifier at {start} in signature {:?}", String::from_utf8_lossy(self.bytes));
+		}
+		Ok(&self.bytes[start..self.pos])
+	}
+
+	fn type_parameters(&mut self) -> Result<Vec<TypeParameter>> {
+		let mut type_parameters = Vec::new();
+		if self.peek() != Some(b'<') {
+			return Ok(type_parameters);
+		}
+		self.pos += 1;
+		while self.peek() != Some(b'>') {
+			let name = self.identifier()?.to_vec();
+			self.expect(b':')?;
+			let class_bound = match self.peek() {
+				Some(b'L' | b'T' | b'[') => Some(self.reference_type()?),
+				_ => None,
+			};
+			let mut interface_bounds = Vec::new();
+			while self.peek() == Some(b':') {
+				self.pos += 1;
+				interface_bounds.push(self.reference_type()?);
+			}
+			type_parameters.push(TypeParameter { name, class_bound, interface_bounds });
+		}
+		self.pos += 1;
+		if type_parameters.is_empty() {
+			bail!("empty type parameters in signature {:?}", String::from_utf8_lossy(self.bytes));
+		}
+		Ok(type_parameters)
+	}
+
+	fn reference_type(&mut self) -> Result<TypeSignature> {
+		match self.peek() {
+			Some(b'L') => Ok(TypeSignature::Class(self.class_type()?)),
+			Some(b'T') => {
+				self.pos += 1;
+				let name = self.identifier()?.to_vec();
+				self.expect(b';')?;
+				Ok(TypeSignature::TypeVariable(name))
+			},
+			Some(b'[') => {
+				self.pos += 1;
+				Ok(TypeSignature::Array(Box::new(self.java_type()?)))
+			},
+			_ => bail!("expected reference type at {} in signature {:?}", self.pos, String::from_utf8_lossy(self.bytes)),
+		}
+	}
+
+	fn java_type(&mut self) -> Result<TypeSignature> {
+		match self.peek() {
+			Some(base @ (b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z')) => {
+				self.pos += 1;
+				Ok(TypeSignature::Base(base))
+			},
+			_ => self.reference_type(),
+		}
+	}
+
+	fn class_type(&mut self) -> Result<ClassTypeSignature> {
+		self.expect(b'L')?;
+		// the package specifier and the first simple class name
+		let start = self.pos;
+		loop {
+			self.identifier()?;
+			if self.peek() != Some(b'/') {
+				break;
+			}
+			self.pos += 1;
+		}
+		let mut name = self.bytes[start..self.pos].to_vec();
+		let mut class = ClassTypeSignature { name: ClassName::from(&name[..]), type_arguments: self.type_arguments()?, outer: None };
+		while self.peek() == Some(b'.') {
+			self.pos += 1;
+			name.push(b'$');
+			name.extend_from_slice(self.identifier()?);
+			class = ClassTypeSignature {
+				name: ClassName::from(&name[..]),
+				type_arguments: self.type_arguments()?,
+				outer: Some(Box::new(class)),
+			};
+		}
+		self.expect(b';')?;
+		Ok(class)
+	}
+
+	fn type_arguments(&mut self) -> Result<Vec<TypeArgument>> {
+		let mut type_arguments = Vec::new();
+		if self.peek() != Some(b'<') {
+			return Ok(type_arguments);
+		}
+		self.pos += 1;
+		while self.peek() != Some(b'>') {
+			type_arguments.push(match self.peek() {
+				Some(b'*') => {
+					self.pos += 1;
+					TypeArgument::Any
+				},
+				Some(b'+') => {
+					self.pos += 1;
+					TypeArgument::Extends(self.reference_type()?)
+				},
+				Some(b'-') => {
+					self.pos += 1;
+					TypeArgument::Super(self.reference_type()?)
+				},
+				_ => TypeArgument::Exact(self.reference_type()?),
+			});
+		}
+		self.pos += 1;
+		if type_arguments.is_empty() {
+			bail!("empty type arguments in signature {:?}", String::from_utf8_lossy(self.bytes));
+		}
+		Ok(type_arguments)
+	}
+}
+
+impl TypeSignature {
+	/// Parses a field signature, which is a reference type.
+	pub fn parse(bytes: &[u8]) -> Result<TypeSignature> {
+		let mut parser = Parser { bytes, pos: 0 };
+		let signature = parser.reference_type()?;
+		parser.finish()?;
+		Ok(signature)
+	}
+
+	/// Calls `f` with every class named in this type, including the ones in type arguments and enclosing classes.
+	pub fn visit_classes(&self, f: &mut impl FnMut(ClassName)) {
+		match self {
+			TypeSignature::Base(_) | TypeSignature::TypeVariable(_) => {},
+			TypeSignature::Class(class) => class.visit_classes(f),
+			TypeSignature::Array(component) => component.visit_classes(f),
+		}
+	}
+}
+
+impl ClassTypeSignature {
+	pub fn visit_classes(&self, f: &mut impl FnMut(ClassName)) {
+		f(self.name);
+		for argument in &self.type_arguments {
+			match argument {
+				TypeArgument::Any => {},
+				TypeArgument::Extends(signature) | TypeArgument::Super(signature) | TypeArgument::Exact(signature) => signature.visit_classes(f),
+			}
+		}
+		if let Some(outer) = &self.outer {
+			outer.visit_classes(f);
+		}
+	}
+}
+
+impl TypeParameter {
+	fn visit_classes(&self, f: &mut impl FnMut(ClassName)) {
+		for bound in self.class_bound.iter().chain(&self.interface_bounds) {
+			bound.visit_classes(f);
+		}
+	}
+}
+
+impl ClassSignature {
+	pub fn parse(bytes: &[u8]) -> Result<ClassSignature> {
+		let mut parser = Parser { bytes, pos: 0 };
+		let type_parameters = parser.type_parameters()?;
+		let super_class = parser.class_type()?;
+		let mut interfaces = Vec::new();
+		while parser.peek().is_some() {
+			interfaces.push(parser.class_type()?);
+		}
+		Ok(ClassSignature { type_parameters, super_class, interfaces })
+	}
+
+	pub fn visit_classes(&self, f: &mut impl FnMut(ClassName)) {
+		for type_parameter in &self.type_parameters {
+			type_parameter.visit_classes(f);
+		}
+		self.super_class.visit_classes(f);
+		for interface in &self.interfaces {
+			interface.visit_classes(f);
+		}
+	}
+}
+
+impl MethodSignature {
+	pub fn parse(bytes: &[u8]) -> Result<MethodSignature> {
+		let mut parser = Parser { bytes, pos: 0 };
+		let type_parameters = parser.type_parameters()?;
+		parser.expect(b'(')?;
+		let mut parameters = Vec::new();
+		while parser.peek() != Some(b')') {
+			parameters.push(parser.java_type()?);
+		}
+		parser.pos += 1;
+		let return_type = if parser.peek() == Some(b'V') {
+			parser.pos += 1;
+			None
+		} else {
+			Some(parser.java_type()?)
+		};
+		let mut throws = Vec::new();
+		while parser.peek() == Some(b'^') {
+			parser.pos += 1;
+			throws.push(parser.reference_type()?);
+		}
+		parser.finish()?;
+		Ok(MethodSignature { type_parameters, parameters, return_type, throws })
+	}
+
+	pub fn visit_classes(&self, f: &mut impl FnMut(ClassName)) {
+		for type_parameter in &self.type_parameters {
+			type_parameter.visit_classes(f);
+		}
+		for signature in self.parameters.iter().chain(&self.return_type).chain(&self.throws) {
+			signature.visit_classes(f);
+		}
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::name::ClassName;
+	use crate::signature::{ClassSignature, MethodSignature, TypeArgument, TypeSignature};
+
+	#[test]
+	fn generic_signatures() {
+		// class Cache<K extends Comparable<? super K>, V> extends AbstractMap<K, List<V>> implements Map<K, List<V>>
+		let class = ClassSignature::parse(
+			b"<K::Ljava/lang/Comparable<-TK;>;V:Ljava/lang/Object;>Ljava/util/AbstractMap<TK;Ljava/util/List<TV;>;>;Ljava/util/Map<TK;Ljava/util/List<TV;>;>;"
+		).unwrap();
+		assert_eq!(class.type_parameters.len(), 2);
+		assert_eq!(class.type_parameters[0].class_bound, None);
+		assert_eq!(class.interfaces.len(), 1);
+		let mut classes = Vec::new();
+		class.visit_classes(&mut |class| classes.push(class));
+		let names: Vec<_> = classes.iter().map(ClassName::as_bytes).collect();
+		assert_eq!(names, [
+			&b"java/lang/Comparable"[..], b"java/lang/Object", b"java/util/AbstractMap", b"java/util/List", b"java/util/Map", b"java/util/List",
+		]);
+
+		// <T> void forEach(Map<String, T>.Entry<T>[] entries, int count) throws E
+		let method = MethodSignature::parse(b"<T:Ljava/lang/Object;>([Ljava/util/Map<Ljava/lang/String;TT;>.Entry<TT;>;I)V^TE;").unwrap();
+		assert_eq!(method.return_type, None);
+		assert_eq!(method.throws, [TypeSignature::TypeVariable(b"E".to_vec())]);
+		let TypeSignature::Array(entry) = &method.parameters[0] else { panic!() };
+		let TypeSignature::Class(entry) = &**entry else { panic!() };
+		assert_eq!(entry.name, ClassName::from(b"java/util/Map$Entry"));
+		assert_eq!(entry.type_arguments, [TypeArgument::Exact(TypeSignature::TypeVariable(b"T".to_vec()))]);
+		assert_eq!(entry.outer.as_ref().unwrap().type_arguments.len(), 2);
+
+		assert!(TypeSignature::parse(b"Ljava/util/List<>;").is_err());
+		assert!(MethodSignature::parse(b"(I").is_err());
+	}
+}
@@ -0,0 +1,280 @@
+//! Source maps of JSR-45, found in the `SourceDebugExtension` attribute.
+//!
+//! Code compiled from languages other than Java, like JSPs or Kotlin with inline functions, has line numbers in its `LineNumberTable` that don't belong
+//! to the file of the `SourceFile` attribute. A source map (SMAP) maps these "output" lines back to lines in the actual input files, for one or more
+//! strata, like `Kotlin` and `KotlinDebug`.
+//!
+//! ```text
+//! SMAP
+//! Foo.kt
+//! Kotlin
+//! *S Kotlin
+//! *F
+//! + 1 Foo.kt
+//! com/example/Foo.kt
+//! + 2 Bar.kt
+//! com/example/Bar.kt
+//! *L
+//! 1#1,20:1
+//! 5#2,3:21
+//! *E
+//! ```
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Context, Result};
+use crate::cp::attribute::CodeAttribute;
+
+/// The stratum of the lines in the `LineNumberTable`, it's used when no stratum of that name is defined.
+pub const JAVA_STRATUM: &str = "Java";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Smap {
+	/// The name of the file the lines of the `LineNumberTable` belong to, usually the same as in the `SourceFile` attribute.
+	pub output_file: String,
+	/// The stratum to use if none is asked for.
+	pub default_stratum: String,
+	pub strata: Vec<Stratum>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stratum {
+	pub name: String,
+	pub files: Vec<SourceFile>,
+	/// The line sections in the order they appear, the first one matching an output line is used.
+	pub lines: Vec<LineInfo>,
+}
+
+/// An entry of a file section (`*F`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+	pub id: u32,
+	pub name: String,
+	/// The path of the file relative to the source path, only present if the entry starts with `+`.
+	pub path: Option<String>,
+}
+
+/// An entry of a line section (`*L`), `InputStartLine # LineFileID , RepeatCount : OutputStartLine , OutputLineIncrement`.
+///
+/// The input line `input_start_line + i` maps to `output_line_increment` output lines starting at `output_start_line + i * output_line_increment`, for
+/// all `i` below `repeat_count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineInfo {
+	pub input_start_line: u32,
+	pub file_id: u32,
+	pub repeat_count: u32,
+	pub output_start_line: u32,
+	pub output_line_increment: u32,
+}
+
+impl LineInfo {
+	/// Returns the input line the output line `line` maps to.
+	pub fn input_line(&self, line: u32) -> Option<u32> {
+		// an increment of zero maps several input lines to the same output line, the first one is taken then
+		let increment = self.output_line_increment.max(1);
+		let i = line.checked_sub(self.output_start_line)? / increment;
+		if i < self.repeat_count {
+			Some(self.input_start_line + i)
+		} else {
+			None
+		}
+	}
+}
+
+/// A line in an input file, as returned by [Smap::resolve].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation<'a> {
+	pub file_name: &'a str,
+	pub path: Option<&'a str>,
+	pub line: u32,
+}
+
+impl Stratum {
+	pub fn file(&self, id: u32) -> Option<&SourceFile> {
+		self.files.iter().find(|file| file.id == id)
+	}
+
+	/// Maps the output line `line` back to the input file and line it came from.
+	pub fn resolve(&self, line: u32) -> Option<SourceLocation<'_>> {
+		self.lines.iter()
+			.find_map(|info| Some((info, info.input_line(line)?)))
+			.and_then(|(info, line)| {
+				let file = self.file(info.file_id)?;
+				Some(SourceLocation { file_name: &file.name, path: file.path.as_deref(), line })
+			})
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+	None,
+	File,
+	Line,
+	Ignored,
+}
+
+impl Smap {
+	/// Parses a resolved SMAP, as found in the `SourceDebugExtension` attribute. Embedded SMAPs (`*O` and `*C` sections) are only present before the
+	/// compiler resolves them and are rejected, vendor sections (`*V`) and unknown sections are skipped.
+	pub fn parse(bytes: &[u8]) -> Result<Smap> {
+		let text = core::str::from_utf8(bytes).context("SMAP is not valid UTF-8")?;
+		// lines may end in CR, LF or CR LF
+		let mut lines = text.split('\n')
+			.map(|line| line.strip_suffix('\r').unwrap_or(line))
+			.flat_map(|line| line.split('\r'))
+			.enumerate()
+			.map(|(number, line)| (number + 1, line));
+
+		let mut header = |what: &str| lines.next().map(|(_, line)| line).ok_or_else(|| anyhow!("SMAP ended before the {what}"));
+		if header("header")? != "SMAP" {
+			bail!("SMAP doesn't start with the line \"SMAP\"");
+		}
+		let output_file = header("output file name")?.to_owned();
+		let default_stratum = header("default stratum")?.trim().to_owned();
+
+		let mut strata: Vec<Stratum> = Vec::new();
+		let mut section = Section::None;
+		let mut file_id = 0;
+		let mut ended = false;
+		while let Some((number, line)) = lines.next() {
+			let fail = |message: &str| anyhow!("SMAP line {number}: {message}: {line:?}");
+			if ended {
+				if !line.trim().is_empty() {
+					return Err(fail("content after the end section"));
+				}
+				continue;
+			}
+			if let Some(header) = line.strip_prefix('*') {
+				let mut parts = header.split_whitespace();
+				section = match parts.next() {
+					Some("S") => {
+						let name = parts.next().ok_or_else(|| fail("stratum without a name"))?;
+						strata.push(Stratum { name: name.to_owned(), files: Vec::new(), lines: Vec::new() });
+						file_id = 0;
+						Section::None
+					},
+					Some("F") => Section::File,
+					Some("L") => Section::Line,
+					Some("E") => {
+						ended = true;
+						Section::None
+					},
+					Some("O") | Some("C") => return Err(fail("embedded SMAPs are not supported")),
+					// vendor and future sections are ignored up to the next section
+					_ => Section::Ignored,
+				};
+				if matches!(section, Section::File | Section::Line) && strata.is_empty() {
+					return Err(fail("section outside of a stratum"));
+				}
+				continue;
+			}
+			if line.trim().is_empty() || matches!(section, Section::None | Section::Ignored) {
+				continue;
+			}
+
+			let stratum = strata.last_mut().ok_or_else(|| fail("content outside of a stratum"))?;
+			if section == Section::File {
+				let (entry, has_path) = match line.strip_prefix('+') {
+					Some(entry) => (entry, true),
+					None => (line, false),
+				};
+				let (id, name) = entry.trim_start().split_once(' ').ok_or_else(|| fail("file entry without a name"))?;
+				let id = id.parse().map_err(|_| fail("invalid file id"))?;
+				let path = if has_path {
+					let (_, path) = lines.next().ok_or_else(|| fail("file entry without a path"))?;
+					Some(path.to_owned())
+				} else {
+					None
+				};
+				stratum.files.push(SourceFile { id, name: name.trim().to_owned(), path });
+			} else {
+				let info = parse_line_info(line, file_id).ok_or_else(|| fail("invalid line entry"))?;
+				file_id = info.file_id;
+				stratum.lines.push(info);
+			}
+		}
+		if !ended {
+			bail!("SMAP has no end section");
+		}
+		Ok(Smap { output_file, default_stratum, strata })
+	}
+
+	pub fn stratum(&self, name: &str) -> Option<&Stratum> {
+		self.strata.iter().find(|stratum| stratum.name == name)
+	}
+
+	/// Maps the line `line` of the `LineNumberTable` to a line of an input file, in the stratum `stratum` or the default one if `None`.
+	///
+	/// The `Java` stratum maps each line to itself in the output file, unless the SMAP defines it.
+	pub fn resolve(&self, stratum: Option<&str>, line: u32) -> Option<SourceLocation<'_>> {
+		let name = stratum.unwrap_or(&self.default_stratum);
+		match self.stratum(name) {
+			Some(stratum) => stratum.resolve(line),
+			None if name == JAVA_STRATUM => Some(SourceLocation { file_name: &self.output_file, path: None, line }),
+			None => None,
+		}
+	}
+
+	/// Resolves the source location of the instruction at `offset` in `code`, see [Smap::resolve].
+	pub fn resolve_offset(&self, stratum: Option<&str>, code: &CodeAttribute, offset: usize) -> Option<SourceLocation<'_>> {
+		self.resolve(stratum, code.line_for_offset(offset)? as u32)
+	}
+}
+
+/// Parses `InputStartLine [# LineFileID] [, RepeatCount] : OutputStartLine [, OutputLineIncrement]`, the file id defaults to the one of the previous
+/// entry.
+fn parse_line_info(line: &str, file_id: u32) -> Option<LineInfo> {
+	let number = |string: &str| string.trim().parse::<u32>().ok();
+	let (input, output) = line.split_once(':')?;
+	let (input, repeat_count) = match input.split_once(',') {
+		Some((input, repeat_count)) => (input, number(repeat_count)?),
+		None => (input, 1),
+	};
+	let (input_start_line, file_id) = match input.split_once('#') {
+		Some((input, file_id)) => (number(input)?, number(file_id)?),
+		None => (number(input)?, file_id),
+	};
+	let (output_start_line, output_line_increment) = match output.split_once(',') {
+		Some((output, increment)) => (number(output)?, number(increment)?),
+		None => (number(output)?, 1),
+	};
+	Some(LineInfo { input_start_line, file_id, repeat_count, output_start_line, output_line_increment })
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::smap::{LineInfo, Smap, SourceLocation};
+
+	#[test]
+	fn kotlin_inline_function() {
+		let smap = concat!(
+			"SMAP\nMain.kt\nKotlin\n",
+			"*S Kotlin\n*F\n+ 1 Main.kt\ncom/example/Main.kt\n+ 2 Util.kt\ncom/example/Util.kt\n*L\n1#1,12:1\n4#2,2:13\n7,3:15,2\n",
+			"*S KotlinDebug\n*F\n+ 1 Main.kt\ncom/example/Main.kt\n*L\n5#1:13,2\n",
+			"*E\n",
+		);
+		let smap = Smap::parse(smap.as_bytes()).unwrap();
+		assert_eq!(smap.output_file, "Main.kt");
+		assert_eq!(smap.default_stratum, "Kotlin");
+		let kotlin = smap.stratum("Kotlin").unwrap();
+		assert_eq!(kotlin.files[1].path.as_deref(), Some("com/example/Util.kt"));
+		assert_eq!(kotlin.lines[2], LineInfo { input_start_line: 7, file_id: 2, repeat_count: 3, output_start_line: 15, output_line_increment: 2 });
+
+		let location = |file_name, path, line| Some(SourceLocation { file_name, path: Some(path), line });
+		assert_eq!(smap.resolve(None, 12), location("Main.kt", "com/example/Main.kt", 12));
+		assert_eq!(smap.resolve(None, 14), location("Util.kt", "com/example/Util.kt", 5));
+		assert_eq!(smap.resolve(None, 18), location("Util.kt", "com/example/Util.kt", 8));
+		assert_eq!(smap.resolve(None, 21), None);
+		assert_eq!(smap.resolve(Some("KotlinDebug"), 14), location("Main.kt", "com/example/Main.kt", 5));
+		assert_eq!(smap.resolve(Some("Java"), 14), Some(SourceLocation { file_name: "Main.kt", path: None, line: 14 }));
+		assert_eq!(smap.resolve(Some("JSP"), 14), None);
+	}
+
+	#[test]
+	fn malformed() {
+		assert!(Smap::parse(b"SMAP\nA.kt\nKotlin\n*S Kotlin\n*L\n1#1:1\n").is_err());
+		assert!(Smap::parse(b"SMAP\nA.kt\nKotlin\n*S Kotlin\n*L\n1#x:1\n*E\n").is_err());
+		assert!(Smap::parse(b"SMAP\nA.kt\nKotlin\n*O Kotlin\n*E\n").is_err());
+	}
+}
@@ -0,0 +1,418 @@
+//! Statistics over many class files, for tracking how the code of a project grows between releases, and catching generated code that grows too much.
+//!
+//! [Statistics] counts instructions by opcode and constant pool entries by kind, and keeps the size and the cyclomatic complexity of each method. The
+//! limits of the class file format are what generated code runs into first: the code of a method must be shorter than 65536 bytes (4.7.3), and a class
+//! can have at most 65534 constant pool entries (4.1).
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{Context, Result};
+use core::cmp::Reverse;
+use core::fmt::Write;
+use crate::json;
+use crate::access::MethodInfoAccess;
+use crate::cfg::{BlockId, ControlFlowGraph, EdgeKind};
+use crate::cp::attribute::CodeAttribute;
+use crate::instruction::opcode::Opcode;
+use crate::lazy::ClassFileRef;
+
+/// The largest `code_length` of a method.
+pub const MAX_CODE_LENGTH: usize = 65535;
+/// The largest `constant_pool_count` of a class.
+pub const MAX_CONSTANT_POOL_COUNT: usize = 65535;
+
+/// The size and complexity of a method with code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodStatistics {
+	pub class: String,
+	pub name: String,
+	pub descriptor: String,
+	/// The length of the code in bytes.
+	pub code_length: usize,
+	pub instructions: usize,
+	/// See [cyclomatic_complexity].
+	pub complexity: usize,
+}
+
+/// How often the features that are costly at run time, or that tools have to handle specially, are used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Features {
+	/// The number of `invokedynamic` instructions, which javac emits for lambdas, string concatenation, records and pattern matching.
+	pub invokedynamic: usize,
+	pub synchronized_methods: usize,
+	/// The number of `monitorenter` instructions, one for each `synchronized` block.
+	pub synchronized_blocks: usize,
+	pub native_methods: usize,
+}
+
+/// Statistics collected over the classes added. See the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct Statistics {
+	pub classes: usize,
+	/// The number of methods, with or without code.
+	pub method_count: usize,
+	/// The methods with code, in the order they were added.
+	pub methods: Vec<MethodStatistics>,
+	/// The name of each class with its `constant_pool_count`.
+	pub pools: Vec<(String, usize)>,
+	/// The number of constant pool entries by the name of their kind, like `Utf8` or `Methodref`.
+	pub constants: BTreeMap<&'static str, usize>,
+	/// The number of instructions by the mnemonic of their opcode.
+	pub opcodes: BTreeMap<&'static str, usize>,
+	pub features: Features,
+}
+
+impl Statistics {
+	pub fn new() -> Statistics {
+		Statistics::default()
+	}
+
+	/// Adds the class, decoding the code of its methods. Nothing is added if the class can't be decoded.
+	pub fn add_class(&mut self, class: &ClassFileRef) -> Result<()> {
+		let class_name = String::from_utf8_lossy(class.this_class()?).into_owned();
+		let mut methods = Vec::new();
+		let mut opcodes: BTreeMap<&'static str, usize> = BTreeMap::new();
+		let mut features = Features::default();
+		for method in class.methods() {
+			let name = String::from_utf8_lossy(method.name()?).into_owned();
+			let descriptor = String::from_utf8_lossy(method.descriptor()?).into_owned();
+			let access_flags = MethodInfoAccess::parse(method.access_flags())?;
+			features.synchronized_methods += access_flags.is_synchronised as usize;
+			features.native_methods += access_flags.is_native as usize;
+			let Some(code) = method.code().with_context(|| format!("while decoding the code of {name}{descriptor}"))? else {
+				continue;
+			};
+			for instruction in code.code.iter() {
+				match instruction.opcode() {
+					Opcode::InvokeDynamic { .. } => features.invokedynamic += 1,
+					Opcode::MonitorEnter => features.synchronized_blocks += 1,
+					_ => {},
+				}
+				*opcodes.entry(instruction.opcode().mnemonic()).or_default() += 1;
+			}
+			let complexity = cyclomatic_complexity(&code).with_context(|| format!("while building the control flow graph of {name}{descriptor}"))?;
+			methods.push(MethodStatistics {
+				class: class_name.clone(),
+				name,
+				descriptor,
+				code_length: code.code.code_length(),
+				instructions: code.code.len(),
+				complexity,
+			});
+		}
+
+		self.classes += 1;
+		self.method_count += class.methods().len();
+		self.methods.append(&mut methods);
+		for tag in class.constant_tags() {
+			*self.constants.entry(constant_kind(tag)).or_default() += 1;
+		}
+		self.pools.push((class_name, class.constant_pool_count()));
+		for (mnemonic, count) in opcodes {
+			*self.opcodes.entry(mnemonic).or_default() += count;
+		}
+		self.features.invokedynamic += features.invokedynamic;
+		self.features.synchronized_methods += features.synchronized_methods;
+		self.features.synchronized_blocks += features.synchronized_blocks;
+		self.features.native_methods += features.native_methods;
+		Ok(())
+	}
+
+	/// Returns the number of methods by the size of their code, in buckets by the next power of two: `(16, n)` are the `n` methods shorter than 16
+	/// bytes, and every later bucket `(2 * size, n)` counts the methods with at least `size` bytes. Only buckets with methods are returned.
+	pub fn size_distribution(&self) -> Vec<(usize, usize)> {
+		let mut buckets: BTreeMap<usize, usize> = BTreeMap::new();
+		for method in &self.methods {
+			*buckets.entry((method.code_length + 1).next_power_of_two().max(16)).or_default() += 1;
+		}
+		buckets.into_iter().collect()
+	}
+
+	/// Returns the code length at or below which `percent` percent of the methods are, `0` if there are no methods.
+	pub fn code_length_percentile(&self, percent: usize) -> usize {
+		let mut lengths: Vec<usize> = self.methods.iter().map(|method| method.code_length).collect();
+		lengths.sort_unstable();
+		match lengths.len() {
+			0 => 0,
+			len => lengths[(len - 1) * percent.min(100) / 100],
+		}
+	}
+
+	/// Returns the methods with code at least `percent` percent as long as it may be, the longest first.
+	pub fn near_code_limit(&self, percent: usize) -> Vec<&MethodStatistics> {
+		let mut methods: Vec<&MethodStatistics> = self.methods.iter().filter(|method| method.code_length * 100 >= MAX_CODE_LENGTH * percent).collect();
+		methods.sort_by_key(|method| Reverse(method.code_length));
+		methods
+	}
+
+	/// Returns the classes with a constant pool at least `percent` percent as large as it may be, with their `constant_pool_count`, the largest first.
+	pub fn near_pool_limit(&self, percent: usize) -> Vec<(&str, usize)> {
+		let mut pools: Vec<(&str, usize)> = self.pools.iter()
+			.filter(|(_, count)| count * 100 >= MAX_CONSTANT_POOL_COUNT * percent)
+			.map(|(class, count)| (class.as_str(), *count))
+			.collect();
+		pools.sort_by_key(|(_, count)| Reverse(*count));
+		pools
+	}
+
+	/// Returns the `count` methods with the highest cyclomatic complexity, the most complex first.
+	pub fn most_complex(&self, count: usize) -> Vec<&MethodStatistics> {
+		let mut methods: Vec<&MethodStatistics> = self.methods.iter().collect();
+		methods.sort_by_key(|method| Reverse(method.complexity));
+		methods.truncate(count);
+		methods
+	}
+
+	/// Formats the statistics for reading, listing the `top` most complex methods, and the methods and classes at least `percent` percent of the way
+	/// to the limits.
+	pub fn to_text(&self, top: usize, percent: usize) -> String {
+		let mut out = String::new();
+		let code_length: usize = self.methods.iter().map(|method| method.code_length).sum();
+		let _ = writeln!(out, "classes: {}, methods: {} ({} with code), code: {code_length} bytes", self.classes, self.method_count, self.methods.len());
+
+		out.push_str("\nmethod sizes:\n");
+		for (size, count) in self.size_distribution() {
+			let _ = writeln!(out, "  < {size:<6} {count:>8}");
+		}
+		let _ = writeln!(out, "  median {} bytes, 90% below {} bytes, 99% below {} bytes, largest {} bytes",
+			self.code_length_percentile(50), self.code_length_percentile(90), self.code_length_percentile(99), self.code_length_percentile(100));
+
+		let constants: usize = self.constants.values().sum();
+		let _ = writeln!(out, "\nconstant pool entries: {constants}");
+		for (kind, count) in by_count(&self.constants) {
+			let _ = writeln!(out, "  {kind:<20} {count:>8}");
+		}
+		if let Some((class, count)) = self.pools.iter().max_by_key(|(_, count)| *count) {
+			let _ = writeln!(out, "  largest pool: {count} entries in {class}");
+		}
+
+		let instructions: usize = self.opcodes.values().sum();
+		let _ = writeln!(out, "\ninstructions: {instructions}");
+		for (mnemonic, count) in by_count(&self.opcodes) {
+			let _ = writeln!(out, "  {mnemonic:<20} {count:>8}");
+		}
+
+		out.push_str("\nfeatures:\n");
+		let _ = writeln!(out, "  {:<20} {:>8}", "invokedynamic", self.features.invokedynamic);
+		let _ = writeln!(out, "  {:<20} {:>8}", "synchronized methods", self.features.synchronized_methods);
+		let _ = writeln!(out, "  {:<20} {:>8}", "synchronized blocks", self.features.synchronized_blocks);
+		let _ = writeln!(out, "  {:<20} {:>8}", "native methods", self.features.native_methods);
+
+		if top > 0 && !self.methods.is_empty() {
+			out.push_str("\nmost complex methods:\n");
+			for method in self.most_complex(top) {
+				let _ = writeln!(out, "  {:>6}  {}.{}{}", method.complexity, method.class, method.name, method.descriptor);
+			}
+		}
+
+		let _ = writeln!(out, "\nmethods with at least {percent}% of the maximum code length of {MAX_CODE_LENGTH} bytes:");
+		let methods = self.near_code_limit(percent);
+		if methods.is_empty() {
+			out.push_str("  none\n");
+		}
+		for method in methods {
+			let _ = writeln!(out, "  {:>6}  {}.{}{}", method.code_length, method.class, method.name, method.descriptor);
+		}
+		let _ = writeln!(out, "\nclasses with at least {percent}% of the maximum constant pool count of {MAX_CONSTANT_POOL_COUNT}:");
+		let pools = self.near_pool_limit(percent);
+		if pools.is_empty() {
+			out.push_str("  none\n");
+		}
+		for (class, count) in pools {
+			let _ = writeln!(out, "  {count:>6}  {class}");
+		}
+		out
+	}
+
+	/// Formats the statistics as JSON, with all methods, and the methods and classes at least `percent` percent of the way to the limits:
+	///
+	/// ```json
+	/// {"classes": 1, "method_count": 2, "sizes": [{"below": 16, "methods": 1}, ...], "constants": {"Utf8": 20, ...}, "opcodes": {"aload": 3, ...},
+	///  "features": {"invokedynamic": 0, "synchronized_methods": 0, "synchronized_blocks": 0, "native_methods": 0},
+	///  "methods": [{"class": "...", "name": "...", "descriptor": "...", "code_length": 5, "instructions": 3, "complexity": 1}, ...],
+	///  "near_code_limit": [<methods>], "near_pool_limit": [{"class": "...", "constant_pool_count": 65000}]}
+	/// ```
+	pub fn to_json(&self, percent: usize) -> String {
+		let mut out = String::new();
+		let _ = write!(out, "{{\"classes\":{},\"method_count\":{},\"sizes\":", self.classes, self.method_count);
+		json::array(&mut out, self.size_distribution(), |out, (size, count)| {
+			let _ = write!(out, "{{\"below\":{size},\"methods\":{count}}}");
+		});
+		out.push_str(",\"constants\":");
+		json_counts(&mut out, &self.constants);
+		out.push_str(",\"opcodes\":");
+		json_counts(&mut out, &self.opcodes);
+		let _ = write!(out, ",\"features\":{{\"invokedynamic\":{},\"synchronized_methods\":{},\"synchronized_blocks\":{},\"native_methods\":{}}}",
+			self.features.invokedynamic, self.features.synchronized_methods, self.features.synchronized_blocks, self.features.native_methods);
+		out.push_str(",\"methods\":");
+		json::array(&mut out, &self.methods, json_method);
+		out.push_str(",\"near_code_limit\":");
+		json::array(&mut out, self.near_code_limit(percent), json_method);
+		out.push_str(",\"near_pool_limit\":");
+		json::array(&mut out, self.near_pool_limit(percent), |out, (class, count)| {
+			out.push_str("{\"class\":");
+			json::string(out, class.as_bytes());
+			let _ = write!(out, ",\"constant_pool_count\":{count}}}");
+		});
+		out.push('}');
+		out
+	}
+}
+
+/// Returns the cyclomatic complexity of the code, that is one more than the number of decisions in it. It's counted on the control flow graph, as
+/// the number of blocks each reachable block can continue with beyond the first, and one for each handler catching an exception type. Handlers
+/// catching any exception aren't counted, as they are the `finally` blocks and the release of monitors javac adds, which don't decide anything. For
+/// the same reason, a `jsr` counts as going on with the subroutine only.
+pub fn cyclomatic_complexity(code: &CodeAttribute) -> Result<usize> {
+	let graph = ControlFlowGraph::new(code)?;
+	let mut complexity = 1;
+	let mut handlers: Vec<BlockId> = Vec::new();
+	for id in graph.reverse_postorder() {
+		let block = graph.block(id);
+		let calls_subroutine = code.code.get(block.end.wrapping_sub(1)).is_some_and(|last| matches!(last.opcode(), Opcode::Jsr(_)));
+		let mut targets: Vec<BlockId> = Vec::new();
+		for edge in &block.successors {
+			match edge.kind {
+				EdgeKind::Exception(entry) => {
+					if code.exception_table[entry].catch_type.is_some() && !handlers.contains(&edge.to) {
+						handlers.push(edge.to);
+					}
+				},
+				EdgeKind::FallThrough if calls_subroutine => {},
+				EdgeKind::FallThrough | EdgeKind::Branch => {
+					if !targets.contains(&edge.to) {
+						targets.push(edge.to);
+					}
+				},
+			}
+		}
+		complexity += targets.len().saturating_sub(1);
+	}
+	Ok(complexity + handlers.len())
+}
+
+/// Returns the name of the kind of constant pool entry with the `tag`, as in table 4.4-B without the `CONSTANT_` prefix.
+fn constant_kind(tag: u8) -> &'static str {
+	match tag {
+		1 => "Utf8",
+		3 => "Integer",
+		4 => "Float",
+		5 => "Long",
+		6 => "Double",
+		7 => "Class",
+		8 => "String",
+		9 => "Fieldref",
+		10 => "Methodref",
+		11 => "InterfaceMethodref",
+		12 => "NameAndType",
+		15 => "MethodHandle",
+		16 => "MethodType",
+		17 => "Dynamic",
+		18 => "InvokeDynamic",
+		19 => "Module",
+		20 => "Package",
+		// ClassFileRef::parse rejects other tags
+		_ => "unknown",
+	}
+}
+
+/// Returns the counts, the largest first.
+fn by_count(counts: &BTreeMap<&'static str, usize>) -> Vec<(&'static str, usize)> {
+	let mut counts: Vec<(&'static str, usize)> = counts.iter().map(|(&name, &count)| (name, count)).collect();
+	counts.sort_by_key(|(_, count)| Reverse(*count));
+	counts
+}
+
+fn json_counts(out: &mut String, counts: &BTreeMap<&'static str, usize>) {
+	out.push('{');
+	for (index, (name, count)) in counts.iter().enumerate() {
+		if index > 0 {
+			out.push(',');
+		}
+		json::string(out, name.as_bytes());
+		let _ = write!(out, ":{count}");
+	}
+	out.push('}');
+}
+
+fn json_method(out: &mut String, method: &MethodStatistics) {
+	out.push_str("{\"class\":");
+	json::string(out, method.class.as_bytes());
+	out.push_str(",\"name\":");
+	json::string(out, method.name.as_bytes());
+	out.push_str(",\"descriptor\":");
+	json::string(out, method.descriptor.as_bytes());
+	let _ = write!(out, ",\"code_length\":{},\"instructions\":{},\"complexity\":{}}}", method.code_length, method.instructions, method.complexity);
+}
+
+#[cfg(test)]
+mod testing {
+	use alloc::collections::BTreeMap;
+	use crate::ClassFile;
+	use crate::cp::builder::PoolBuilder;
+	use crate::descriptor::MethodDescriptor;
+	use crate::instruction::builder::CodeBuilder;
+	use crate::instruction::opcode::Opcode;
+	use crate::lazy::ClassFileRef;
+	use crate::name::MethodName;
+	use super::Statistics;
+
+	#[test]
+	fn test2() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test2.class");
+		let class = ClassFileRef::parse(bytes).unwrap();
+		let mut statistics = Statistics::new();
+		statistics.add_class(&class).unwrap();
+
+		assert_eq!(statistics.classes, 1);
+		assert_eq!(statistics.method_count, 4);
+		let count = |counts: &BTreeMap<&str, usize>, name| counts.get(name).copied().unwrap_or(0);
+		// Long and Double entries take up two indices
+		let entries: usize = statistics.constants.values().sum();
+		assert_eq!(1 + entries + count(&statistics.constants, "Long") + count(&statistics.constants, "Double"), class.constant_pool_count());
+		let main = statistics.methods.iter().find(|method| method.name == "main").unwrap();
+		assert_eq!(main.descriptor, "([Ljava/lang/String;)V");
+		// the loop is the only decision
+		assert_eq!(main.complexity, 2);
+		assert_eq!(statistics.methods.iter().map(|method| method.instructions).sum::<usize>(), statistics.opcodes.values().sum::<usize>());
+		assert_eq!(count(&statistics.opcodes, "invokedynamic"), statistics.features.invokedynamic);
+		assert_eq!(statistics.features.synchronized_blocks, 0);
+
+		assert_eq!(statistics.size_distribution().iter().map(|(_, count)| count).sum::<usize>(), statistics.methods.len());
+		assert!(statistics.near_code_limit(90).is_empty());
+		assert_eq!(statistics.near_code_limit(0).len(), statistics.methods.len());
+		assert_eq!(statistics.near_pool_limit(0), [("Test2", class.constant_pool_count())]);
+		assert!(statistics.to_json(90).starts_with("{\"classes\":1,\"method_count\":4,\"sizes\":[{\"below\":16,"));
+	}
+
+	#[test]
+	fn subroutines() {
+		// `static void added() { try { return; } finally {} }` the way javac compiled it before 1.4.2: the jsr calls the finally block, whose astore
+		// keeps the return address for the ret
+		let bytes = include_bytes!("../../../java_example_classfiles/Test2.class");
+		let (mut class, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+		let mut pool = PoolBuilder::from_pool(&pool);
+		let descriptor = MethodDescriptor::try_from(&b"()V"[..]).unwrap();
+		let mut code = CodeBuilder::new(descriptor.clone(), true);
+		code.emit(Opcode::SIPush(0x1234)).emit(Opcode::Return).astore(1).emit(Opcode::Return).emit(Opcode::Return);
+		let mut method = class.methods.iter().find(|method| method.access_flags.is_static).unwrap().clone();
+		method.name = MethodName::from(b"added");
+		method.descriptor = descriptor;
+		method.code = Some(code.build(&mut pool).unwrap());
+		class.methods.push(method);
+		class.major_version = 48;
+		let mut bytes = class.write(&mut pool).unwrap();
+		let position = bytes.windows(7).position(|window| window == [0x11, 0x12, 0x34, 0xb1, 0x4c, 0xb1, 0xb1]).unwrap();
+		bytes[position..position + 7].copy_from_slice(&[0xa8, 0x00, 0x04, 0xb1, 0x4c, 0xa9, 0x01]);
+
+		let mut statistics = Statistics::new();
+		statistics.add_class(&ClassFileRef::parse(&bytes).unwrap()).unwrap();
+		// counted as in the class file, not with the subroutine inlined
+		let added = statistics.methods.iter().find(|method| method.name == "added").unwrap();
+		assert_eq!((added.code_length, added.instructions, added.complexity), (7, 4, 1));
+		assert_eq!((statistics.opcodes.get("jsr"), statistics.opcodes.get("ret")), (Some(&1), Some(&1)));
+		assert_eq!(statistics.opcodes.get("aconst_null"), None);
+	}
+}
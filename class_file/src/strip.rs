@@ -0,0 +1,213 @@
+//! Removing debug information and other attributes a class doesn't need to run, and writing it with a constant pool of only the entries it uses.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Context, Result};
+use crate::{ClassFile, MyRead};
+use crate::cp::Pool;
+use crate::cp::attribute::AttributeInfo;
+use crate::cp::builder::PoolBuilder;
+
+/// The attributes [strip] removes. By default these are all debug information and the invisible annotations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StripOptions {
+	/// `LineNumberTable`, without which stack traces show no line numbers.
+	pub line_numbers: bool,
+	/// `LocalVariableTable`.
+	pub local_variables: bool,
+	/// `LocalVariableTypeTable`, the generic signatures of the local variables.
+	pub local_variable_types: bool,
+	/// `SourceFile`, without which stack traces show `Unknown Source`.
+	pub source_file: bool,
+	/// `SourceDebugExtension`, like the [SMAP](crate::smap) of classes compiled from JSPs or Kotlin inline functions.
+	pub source_debug_extension: bool,
+	/// `RuntimeInvisibleAnnotations`, `RuntimeInvisibleParameterAnnotations` and `RuntimeInvisibleTypeAnnotations`, which only tools reading class
+	/// files see.
+	pub invisible_annotations: bool,
+	/// Further attributes to remove, by name, like `Deprecated` or `MethodParameters`.
+	pub attributes: Vec<Vec<u8>>,
+}
+
+impl Default for StripOptions {
+	fn default() -> Self {
+		StripOptions {
+			line_numbers: true,
+			local_variables: true,
+			local_variable_types: true,
+			source_file: true,
+			source_debug_extension: true,
+			invisible_annotations: true,
+			attributes: Vec::new(),
+		}
+	}
+}
+
+impl StripOptions {
+	/// Whether attributes named `name` are removed.
+	pub fn strips(&self, name: &[u8]) -> bool {
+		match name {
+			b"LineNumberTable" => self.line_numbers,
+			b"LocalVariableTable" => self.local_variables,
+			b"LocalVariableTypeTable" => self.local_variable_types,
+			b"SourceFile" => self.source_file,
+			b"SourceDebugExtension" => self.source_debug_extension,
+			b"RuntimeInvisibleAnnotations" | b"RuntimeInvisibleParameterAnnotations" | b"RuntimeInvisibleTypeAnnotations" => self.invisible_annotations,
+			name => self.attributes.iter().any(|attribute| attribute == name),
+		}
+	}
+}
+
+/// Removes the attributes selected by `options` from the class, its fields, its methods and their code.
+pub fn strip_attributes(class: &mut ClassFile, options: &StripOptions) {
+	let keep = |attribute: &AttributeInfo| !options.strips(attribute.name());
+	class.attributes.retain(keep);
+	for field in &mut class.fields {
+		field.attributes.retain(keep);
+	}
+	for method in &mut class.methods {
+		method.attributes.retain(keep);
+		if let Some(code) = &mut method.code {
+			code.attributes.retain(keep);
+			if options.line_numbers {
+				code.line_number_table.clear();
+			}
+		}
+	}
+}
+
+/// Removes the attributes selected by `options` and writes the class with a new constant pool holding only the entries it still uses.
+///
+/// `pool` is the pool the class was parsed with, as attributes unknown to the parser are kept as bytes that may refer to it. Those added in Java 11
+/// and later whose layout is simple, `NestHost`, `NestMembers`, `PermittedSubclasses` and `Record`, are moved to the new pool. If others remain, all
+/// entries of `pool` are kept so that their indices stay valid, and only the attributes are removed.
+pub fn strip(class: &ClassFile, pool: &Pool, options: &StripOptions) -> Result<Vec<u8>> {
+	let mut stripped = class.clone();
+	strip_attributes(&mut stripped, options);
+
+	let mut compact = stripped.clone();
+	let mut builder = PoolBuilder::new();
+	let mut movable = true;
+	for attribute in attributes_mut(&mut compact) {
+		if let AttributeInfo::Unknown { name, info } = attribute {
+			let moved = move_attribute(name, info, pool, &mut builder, options)
+				.with_context(|| anyhow!("while moving the attribute {:?} to the new constant pool", String::from_utf8_lossy(name)))?;
+			match moved {
+				Some(moved) => *info = moved,
+				None => movable = false,
+			}
+		}
+	}
+	if movable {
+		compact.write(&mut builder)
+	} else {
+		stripped.write(&mut PoolBuilder::from_pool(pool))
+	}
+}
+
+fn attributes_mut(class: &mut ClassFile) -> impl Iterator<Item=&mut AttributeInfo> {
+	class.attributes.iter_mut()
+		.chain(class.fields.iter_mut().flat_map(|field| &mut field.attributes))
+		.chain(class.methods.iter_mut().flat_map(|method| method.attributes.iter_mut().chain(method.code.iter_mut().flat_map(|code| &mut code.attributes))))
+}
+
+/// Returns the bytes of an attribute unknown to the parser with its constant pool indices moved from `pool` to `builder`, or `None` if its layout
+/// isn't known. Attributes of record components selected by `options` are removed.
+fn move_attribute(name: &[u8], mut info: &[u8], pool: &Pool, builder: &mut PoolBuilder, options: &StripOptions) -> Result<Option<Vec<u8>>> {
+	let reader = &mut info;
+	let mut moved = Vec::new();
+	match name {
+		b"NestHost" | b"Signature" => move_index(reader, &mut moved, pool, builder)?,
+		b"NestMembers" | b"PermittedSubclasses" => {
+			let count = reader.read_u16()?;
+			moved.extend_from_slice(&count.to_be_bytes());
+			for _ in 0..count {
+				move_index(reader, &mut moved, pool, builder)?;
+			}
+		},
+		b"Record" => {
+			let count = reader.read_u16()?;
+			moved.extend_from_slice(&count.to_be_bytes());
+			for _ in 0..count {
+				// name and descriptor
+				move_index(reader, &mut moved, pool, builder)?;
+				move_index(reader, &mut moved, pool, builder)?;
+
+				let attributes_count = reader.read_u16()?;
+				let mut kept = 0u16;
+				let mut attributes = Vec::new();
+				for _ in 0..attributes_count {
+					let name: &Vec<u8> = pool.get(reader.read_u16_as_usize()?)?;
+					let length = reader.read_u32_as_usize()?;
+					if reader.len() < length {
+						bail!("record component attribute of length {length} is longer than the rest of the attribute");
+					}
+					let (info, rest) = reader.split_at(length);
+					*reader = rest;
+					if options.strips(name) {
+						continue;
+					}
+					let Some(info) = move_attribute(name, info, pool, builder, options)? else {
+						return Ok(None);
+					};
+					attributes.extend_from_slice(&(builder.utf8(name)? as u16).to_be_bytes());
+					attributes.extend_from_slice(&(info.len() as u32).to_be_bytes());
+					attributes.extend_from_slice(&info);
+					kept += 1;
+				}
+				moved.extend_from_slice(&kept.to_be_bytes());
+				moved.extend_from_slice(&attributes);
+			}
+		},
+		_ => return Ok(None),
+	}
+	if !reader.is_empty() {
+		bail!("attribute has {} bytes after its contents", reader.len());
+	}
+	Ok(Some(moved))
+}
+
+fn move_index(reader: &mut &[u8], moved: &mut Vec<u8>, pool: &Pool, builder: &mut PoolBuilder) -> Result<()> {
+	let index = builder.copy(pool, reader.read_u16_as_usize()?)?;
+	moved.extend_from_slice(&(index as u16).to_be_bytes());
+	Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+	use alloc::vec::Vec;
+	use crate::ClassFile;
+	use crate::cp::attribute::AttributeInfo;
+	use crate::cp::builder::PoolBuilder;
+	use crate::name::ClassName;
+	use super::{strip, StripOptions};
+
+	#[test]
+	fn debug_info() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test3.class");
+		let (mut class, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+		// an attribute unknown to the parser, referring to a class only it uses
+		let mut original = PoolBuilder::from_pool(&pool);
+		let host = original.class(&ClassName::from(&b"Outer"[..])).unwrap();
+		class.attributes.push(AttributeInfo::Unknown { name: b"NestHost".to_vec(), info: (host as u16).to_be_bytes().to_vec() });
+		let bytes = class.write(&mut original).unwrap();
+		let (class, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+
+		let stripped = strip(&class, &pool, &StripOptions::default()).unwrap();
+		assert!(stripped.len() < bytes.len());
+		let (stripped, stripped_pool) = ClassFile::parse_with_pool(&mut &stripped[..]).unwrap();
+		assert!(stripped.attributes.iter().all(|attribute| !matches!(attribute, AttributeInfo::SourceFile(_))));
+		assert!(stripped.methods.iter().filter_map(|method| method.code.as_ref()).all(|code| code.line_number_table.is_empty()));
+		let Some(AttributeInfo::Unknown { info, .. }) = stripped.attributes.iter().find(|attribute| attribute.name() == b"NestHost") else {
+			panic!("NestHost was removed");
+		};
+		let host: ClassName = stripped_pool.get(u16::from_be_bytes([info[0], info[1]]) as usize).unwrap();
+		assert_eq!(host.as_bytes(), b"Outer");
+		assert_eq!(stripped.methods, class.methods.iter().cloned().map(|mut method| {
+			if let Some(code) = &mut method.code {
+				code.line_number_table.clear();
+				code.attributes.clear();
+			}
+			method
+		}).collect::<Vec<_>>());
+	}
+}
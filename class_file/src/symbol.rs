@@ -0,0 +1,173 @@
+//! Interning of names.
+//!
+//! The same class, field and method names show up in many class files, and are compared and hashed all the time when looking things up. A [Symbol] is
+//! a handle to bytes stored once in a global table, so that copying, comparing and hashing it doesn't look at the bytes at all.
+//!
+//! Interned bytes are never freed, which is fine for names, as the set of names in a program is bounded by its class path.
+
+use core::cmp::Ordering;
+use core::fmt::{Debug, Formatter};
+use core::hash::{Hash, Hasher};
+
+/// The table with `std`: a hashed set, which is only locked for writing to add names, as most names looked up are interned already.
+#[cfg(feature = "std")]
+mod table {
+	use alloc::boxed::Box;
+	use std::collections::HashSet;
+	use std::sync::{OnceLock, RwLock};
+
+	static SYMBOLS: OnceLock<RwLock<HashSet<&'static [u8]>>> = OnceLock::new();
+
+	fn symbols() -> &'static RwLock<HashSet<&'static [u8]>> {
+		SYMBOLS.get_or_init(Default::default)
+	}
+
+	/// Returns the interned bytes equal to `bytes`, interning them if there are none.
+	pub fn intern(bytes: &[u8]) -> &'static [u8] {
+		if let Some(&interned) = symbols().read().unwrap().get(bytes) {
+			return interned;
+		}
+		let mut symbols = symbols().write().unwrap();
+		// another thread may have interned them in between
+		match symbols.get(bytes) {
+			Some(&interned) => interned,
+			None => {
+				let interned: &'static [u8] = Box::leak(bytes.into());
+				symbols.insert(interned);
+				interned
+			},
+		}
+	}
+
+	pub fn len() -> usize {
+		symbols().read().unwrap().len()
+	}
+}
+
+/// The table without `std`: an ordered set behind a spin lock, as there are neither OS locks nor a source of random hash keys.
+#[cfg(not(feature = "std"))]
+mod table {
+	use alloc::boxed::Box;
+	use alloc::collections::BTreeSet;
+	use core::cell::UnsafeCell;
+	use core::sync::atomic::{AtomicBool, Ordering};
+
+	struct Lock<T> {
+		locked: AtomicBool,
+		value: UnsafeCell<T>,
+	}
+
+	// SAFETY: the value is only accessed while holding the lock
+	unsafe impl<T: Send> Sync for Lock<T> {}
+
+	impl<T> Lock<T> {
+		const fn new(value: T) -> Lock<T> {
+			Lock { locked: AtomicBool::new(false), value: UnsafeCell::new(value) }
+		}
+
+		fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+			while self.locked.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+				core::hint::spin_loop();
+			}
+			// SAFETY: the lock is held until the end of this function
+			let result = f(unsafe { &mut *self.value.get() });
+			self.locked.store(false, Ordering::Release);
+			result
+		}
+	}
+
+	static SYMBOLS: Lock<BTreeSet<&'static [u8]>> = Lock::new(BTreeSet::new());
+
+	/// Returns the interned bytes equal to `bytes`, interning them if there are none.
+	pub fn intern(bytes: &[u8]) -> &'static [u8] {
+		SYMBOLS.with(|symbols| match symbols.get(bytes) {
+			Some(&interned) => interned,
+			None => {
+				let interned: &'static [u8] = Box::leak(bytes.into());
+				symbols.insert(interned);
+				interned
+			},
+		})
+	}
+
+	pub fn len() -> usize {
+		SYMBOLS.with(|symbols| symbols.len())
+	}
+}
+
+/// Interned bytes. Two symbols are equal if and only if their bytes are, this is checked by comparing their address only.
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static [u8]);
+
+impl Symbol {
+	/// Returns the symbol for `bytes`, adding them to the table if they aren't already.
+	pub fn intern(bytes: &[u8]) -> Symbol {
+		Symbol(table::intern(bytes))
+	}
+
+	pub fn as_bytes(self) -> &'static [u8] {
+		self.0
+	}
+
+	/// Returns the number of symbols interned so far.
+	pub fn count() -> usize {
+		table::len()
+	}
+}
+
+impl PartialEq for Symbol {
+	fn eq(&self, other: &Self) -> bool {
+		core::ptr::eq(self.0, other.0)
+	}
+}
+
+impl Eq for Symbol {}
+
+/// Orders by the bytes, not by address, so that the order is the same in every run.
+impl PartialOrd for Symbol {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Symbol {
+	fn cmp(&self, other: &Self) -> Ordering {
+		if self == other {
+			Ordering::Equal
+		} else {
+			self.0.cmp(other.0)
+		}
+	}
+}
+
+impl Hash for Symbol {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		core::ptr::hash(self.0, state)
+	}
+}
+
+impl Debug for Symbol {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		Debug::fmt(&alloc::string::String::from_utf8_lossy(self.0), f)
+	}
+}
+
+#[cfg(test)]
+mod testing {
+	use crate::symbol::Symbol;
+
+	#[test]
+	fn interning() {
+		let object = Symbol::intern(b"java/lang/Object");
+		let string = Symbol::intern(b"java/lang/String");
+		assert_ne!(object, string);
+		assert_eq!(object, Symbol::intern(b"java/lang/Object".as_ref()));
+		assert!(std::ptr::eq(object.as_bytes(), Symbol::intern(b"java/lang/Object").as_bytes()));
+		assert_eq!(Symbol::intern(b""), Symbol::intern(&[]));
+		assert_eq!(format!("{string:?}"), "\"java/lang/String\"");
+
+		let threads: Vec<_> = (0..4).map(|_| std::thread::spawn(|| Symbol::intern(b"from/a/Thread"))).collect();
+		let symbols: Vec<_> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+		assert!(symbols.iter().all(|&symbol| symbol == symbols[0]));
+	}
+}
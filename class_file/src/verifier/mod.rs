@@ -1,5 +1,8 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
 use itertools::Itertools;
-use crate::descriptor::MethodDescriptor;
+use crate::descriptor::{FieldDescriptor, MethodDescriptor};
 use crate::instruction::LvIndex;
 use crate::instruction::opcode::Opcode;
 use crate::MethodInfoAccess as MethodAccessFlags;
@@ -19,9 +22,9 @@ impl FailAsBool for bool {
 	}
 }
 
-type Bool = std::result::Result<(), ()>;
+type Bool = core::result::Result<(), ()>;
 
-type Result<T> = std::result::Result<T, ()>;
+type Result<T> = core::result::Result<T, ()>;
 
 fn fail<T>(_message: & str) -> Result<T> {
 	Err(())
@@ -297,6 +300,17 @@ fn parse_field_descriptor<Descriptor>(_descriptor: &Descriptor) -> Result<Verifi
 	todo!()
 }
 
+// The class operand of anewarray, checkcast, instanceof, multianewarray and new is either class(Name, L), with L the defining loader of the current
+// class, or arrayOf(Type).
+fn class_operand(name: &ClassName) -> Result<VerificationType> {
+	if name.as_bytes().starts_with(b"[") {
+		let descriptor = FieldDescriptor::try_from(name.as_bytes()).or_else(|_| fail("invalid array class name"))?;
+		parse_field_descriptor(&descriptor)
+	} else {
+		Ok(VerificationType::Class(*name, Loader))
+	}
+}
+
 // parseMethodDescriptor(Descriptor, ArgTypeList, ReturnType)
 //     Converts a method descriptor, Descriptor, into a list of verification types, ArgTypeList, corresponding to the method argument types, and a verification
 //     type, ReturnType, corresponding to the return type.
@@ -387,7 +401,7 @@ impl Environment {
 	fn this_class(&self) -> Result<(ClassName, &Loader)> {
 		let l = self.class.defining_loader()?;
 		let class_name = self.class.class_name()?;
-		Ok((class_name.clone(), l))
+		Ok((*class_name, l))
 	}
 
 	// thisMethodReturnType(Environment, ReturnType) :-
@@ -689,7 +703,7 @@ impl VerificationType {
 }
 
 fn superclass_chain(class_name: &ClassName, l: &Loader) -> Result<Vec<(ClassName, Loader)>> {
-	let mut class_name = class_name.clone();
+	let mut class_name = *class_name;
 	let mut l = l.clone();
 
 	let mut chain = Vec::new();
@@ -715,7 +729,7 @@ fn superclass_chain(class_name: &ClassName, l: &Loader) -> Result<Vec<(ClassName
 			let super_class_name = class.super_class_name()?;
 			let ls = class.defining_loader()?;
 
-			chain.push((super_class_name.clone(), ls.clone()));
+			chain.push((super_class_name, ls.clone()));
 
 			class_name = super_class_name;
 			l = ls.clone();
@@ -1275,7 +1289,7 @@ fn handler_exception_class(handler: &Handler, loader: &Loader) -> Result<Verific
 		// handlerExceptionClass(handler(_, _, _, Name),
 		//                       class(Name, L), L) :-
 		//     Name \= 0.
-		Ok(VerificationType::Class(name.clone(), loader.clone()))
+		Ok(VerificationType::Class(*name, loader.clone()))
 	} else {
 		// handlerExceptionClass(handler(_, _, _, 0),
 		//                       class('java/lang/Throwable', BL), _) :-
@@ -1558,7 +1572,7 @@ fn instance_method_initial_this_type(class: &Class, method: &Method) -> Result<V
 		//     MethodName \= '<init>',
 		//     classDefiningLoader(Class, L),
 		//     classClassName(Class, ClassName).
-		Ok(VerificationType::Class(class_name.clone(), l.clone()))
+		Ok(VerificationType::Class(*class_name, l.clone()))
 	}
 }
 
@@ -1873,7 +1887,7 @@ fn passes_protected_check<Name, Descriptor>(environment: &Environment, member_cl
 			let d = |_| {
 				let target = stack_frame.operand_stack.head()?;
 				is_protected(&referenced_class, member_name, member_descriptor)?;
-				VerificationType::is_assignable(target, &VerificationType::Class(current_class_name.clone(), current_loader.clone()))
+				VerificationType::is_assignable(target, &VerificationType::Class(*current_class_name, current_loader.clone()))
 			};
 
 			c().or_else(d)
@@ -2128,6 +2142,7 @@ fn instruction_is_type_safe(instruction: Opcode, environment: &Environment, offs
 		//                         StackFrame, NextStackFrame),
 		//     exceptionStackFrame(StackFrame, ExceptionStackFrame).
 		ANewArray(cp) => {
+			let cp = class_operand(&cp)?;
 			match cp {
 				Class(_, _) | ArrayOf(_) => Ok(()),
 				_ => fail("")
@@ -2265,6 +2280,7 @@ fn instruction_is_type_safe(instruction: Opcode, environment: &Environment, offs
 		//                         StackFrame, NextStackFrame),
 		//     exceptionStackFrame(StackFrame, ExceptionStackFrame).
 		CheckCast(cp) => {
+			let cp = class_operand(&cp)?;
 			match cp {
 				Class(_, _) | ArrayOf(_) => Ok(()),
 				_ => fail(""),
@@ -3171,6 +3187,7 @@ fn instruction_is_type_safe(instruction: Opcode, environment: &Environment, offs
 		//                         StackFrame,NextStackFrame),
 		//     exceptionStackFrame(StackFrame, ExceptionStackFrame).
 		InstanceOf(cp) => {
+			let cp = class_operand(&cp)?;
 			match cp {
 				Class(_, _) | ArrayOf(_) => Ok(()),
 				_ => fail("")
@@ -3343,7 +3360,7 @@ fn instruction_is_type_safe(instruction: Opcode, environment: &Environment, offs
 				vec.push(last);
 				vec
 			}
-			let stack_arg_list = as_end(Class(method.class.clone(), current_loader), reverse(operand_arg_list));
+			let stack_arg_list = as_end(Class(method.class, current_loader), reverse(operand_arg_list));
 			let next_stack_frame = stack_frame.clone().valid_type_transition(
 				environment,
 				stack_arg_list,
@@ -3675,6 +3692,7 @@ fn instruction_is_type_safe(instruction: Opcode, environment: &Environment, offs
 		//                         StackFrame, NextStackFrame),
 		//     exceptionStackFrame(StackFrame, ExceptionStackFrame).
 		MultiANewArray(cp, dim) => {
+			let cp = class_operand(&cp)?;
 			(matches!(cp, ArrayOf(_))).fail("")?;
 			let dimension = cp.class_dimension();
 			(dimension >= dim && dim > 0).fail("")?;
@@ -3706,6 +3724,7 @@ fn instruction_is_type_safe(instruction: Opcode, environment: &Environment, offs
 		//                         NextStackFrame),
 		//     exceptionStackFrame(StackFrame, ExceptionStackFrame).
 		New(cp) => {
+			let cp = class_operand(&cp)?;
 			(matches!(cp, Class(_, _))).fail("")?;
 			let new_item = UninitializedOffset(offset);
 			stack_frame.operand_stack.not_member(&new_item)?;
@@ -3936,5 +3955,8 @@ fn instruction_is_type_safe(instruction: Opcode, environment: &Environment, offs
 			}
 			Ok((FrameT::AfterGoto, exception_stack_frame))
 		},
+
+		// there are no rules for jsr, jsr_w and ret, so code with subroutines never passes type checking (4.10.1)
+		Jsr(_) | Ret(_) => fail("subroutines are not allowed in code verified by type checking"),
 	}
 }
\ No newline at end of file
@@ -0,0 +1,559 @@
+//! Writing a [ClassFile] back into the class file format.
+//!
+//! The constant pool is built while writing, see [PoolBuilder]. Since a [ClassFile] doesn't store constant pool indices, the `Unknown` attributes are
+//! the only part that may refer to the pool it was parsed with; write such classes with [PoolBuilder::from_pool], or pass the pool to
+//! [ClassFile::to_bytes], to keep these indices valid.
+//!
+//! The code of methods is encoded again by [Instructions::encode], which may move instructions. All offsets into the code, in the exception table,
+//! the line numbers, the local variable tables and the stack map frames, are moved along with them. Offsets in `Unknown` attributes of the code, like
+//! `RuntimeVisibleTypeAnnotations`, are kept as they are.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Context, Result};
+use core::fmt::{Display, Formatter};
+use crate::{ClassFile, FieldInfo, MethodInfo};
+use crate::cp::Pool;
+use crate::cp::builder::PoolBuilder;
+use crate::cp::attribute::{Annotation, AnnotationElementValue, AttributeInfo, BootstrapMethodArgument, CodeAttribute, ConstantValueAttribute, LineNumberTableEntry, StackMapFrame, VerificationTypeInfo};
+use crate::instruction::Instructions;
+
+impl ClassFile {
+	/// Writes the class file, adding all constants it refers to to `pool`. The pool is written as it is after adding these, so it must not contain
+	/// entries the class file doesn't need, unless they're wanted.
+	pub fn write(&self, pool: &mut PoolBuilder) -> Result<Vec<u8>> {
+		let mut body = Vec::new();
+		u16(&mut body, self.access_flags.to_flags());
+		u16(&mut body, pool.class(&self.this_class)?);
+		u16(&mut body, match &self.super_class {
+			Some(super_class) => pool.class(super_class)?,
+			None => 0,
+		});
+		count(&mut body, self.interfaces.len())?;
+		for interface in &self.interfaces {
+			u16(&mut body, pool.class(interface)?);
+		}
+		count(&mut body, self.fields.len())?;
+		for field in &self.fields {
+			field.write(&mut body, pool)
+				.with_context(|| anyhow!("while writing the field {:?}", field.name))?;
+		}
+		count(&mut body, self.methods.len())?;
+		for method in &self.methods {
+			method.write(&mut body, pool)
+				.with_context(|| anyhow!("while writing the method {:?}{:?}", method.name, method.descriptor))?;
+		}
+		write_attributes(&mut body, pool, &self.attributes, &Ok)?;
+
+		let mut bytes = Vec::with_capacity(body.len() + 10);
+		bytes.extend_from_slice(&0xCAFE_BABE_u32.to_be_bytes());
+		u16(&mut bytes, self.minor_version as usize);
+		u16(&mut bytes, self.major_version as usize);
+		pool.write(&mut bytes);
+		bytes.extend_from_slice(&body);
+		Ok(bytes)
+	}
+
+	/// Writes the class file with a new constant pool, which only has the entries the class file needs.
+	///
+	/// `Unknown` attributes may refer to the pool the class was parsed with. If the class has any, it's written with all entries of `original` kept at
+	/// their indices instead, see [PoolBuilder::from_pool], and without `original` this fails with [WriteError::UnknownAttribute].
+	pub fn to_bytes(&self, original: Option<&Pool>) -> Result<Vec<u8>, WriteError> {
+		let unknown = self.attributes.iter()
+			.chain(self.fields.iter().flat_map(|field| &field.attributes))
+			.chain(self.methods.iter().flat_map(|method| method.attributes.iter().chain(method.code.iter().flat_map(|code| &code.attributes))))
+			.find_map(|attribute| match attribute {
+				AttributeInfo::Unknown { name, .. } => Some(name),
+				_ => None,
+			});
+		let mut pool = match (unknown, original) {
+			(None, _) => PoolBuilder::new(),
+			(Some(_), Some(original)) => PoolBuilder::from_pool(original),
+			(Some(name), None) => return Err(WriteError::UnknownAttribute(name.clone())),
+		};
+		self.write(&mut pool).map_err(WriteError::Other)
+	}
+}
+
+/// Why [ClassFile::to_bytes] failed.
+#[derive(Debug)]
+pub enum WriteError {
+	/// The class has the `Unknown` attribute with this name, which may refer to the pool the class was parsed with, and that pool wasn't given.
+	UnknownAttribute(Vec<u8>),
+	/// Anything else, like code that is too long or a constant pool that is too large.
+	Other(anyhow::Error),
+}
+
+impl Display for WriteError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+		match self {
+			WriteError::UnknownAttribute(name) => write!(f,
+				"can't write the attribute {:?} with a new constant pool, it may refer to the pool the class was read with", String::from_utf8_lossy(name),
+			),
+			WriteError::Other(error) => write!(f, "{error:#}"),
+		}
+	}
+}
+
+impl core::error::Error for WriteError {}
+
+impl FieldInfo {
+	fn write(&self, bytes: &mut Vec<u8>, pool: &mut PoolBuilder) -> Result<()> {
+		u16(bytes, self.access_flags.to_flags());
+		u16(bytes, pool.utf8(self.name.as_bytes())?);
+		u16(bytes, pool.utf8(&self.descriptor.to_bytes())?);
+
+		count(bytes, self.attributes.len() + self.constant_value.is_some() as usize)?;
+		if let Some(constant_value) = &self.constant_value {
+			attribute(bytes, pool, b"ConstantValue", |bytes, pool| {
+				u16(bytes, match constant_value {
+					ConstantValueAttribute::Long(value) => pool.long(*value)?,
+					ConstantValueAttribute::Float(bits) => pool.float(*bits)?,
+					ConstantValueAttribute::Double(bits) => pool.double(*bits)?,
+					ConstantValueAttribute::Integer(value) => pool.integer(*value)?,
+					ConstantValueAttribute::String(string) => pool.string(string)?,
+				});
+				Ok(())
+			})?;
+		}
+		for attribute in &self.attributes {
+			attribute.write(bytes, pool, &Ok)?;
+		}
+		Ok(())
+	}
+}
+
+impl MethodInfo {
+	fn write(&self, bytes: &mut Vec<u8>, pool: &mut PoolBuilder) -> Result<()> {
+		u16(bytes, self.access_flags.to_flags());
+		u16(bytes, pool.utf8(self.name.as_bytes())?);
+		u16(bytes, pool.utf8(&self.descriptor.to_bytes())?);
+
+		count(bytes, self.attributes.len() + self.code.is_some() as usize)?;
+		if let Some(code) = &self.code {
+			code.write(bytes, pool)?;
+		}
+		for attribute in &self.attributes {
+			attribute.write(bytes, pool, &Ok)?;
+		}
+		Ok(())
+	}
+}
+
+impl CodeAttribute {
+	fn write(&self, bytes: &mut Vec<u8>, pool: &mut PoolBuilder) -> Result<()> {
+		let assembled = self.code.encode(pool)?;
+		let at = |offset: usize| moved(&self.code, &assembled.offsets, offset);
+
+		attribute(bytes, pool, b"Code", |bytes, pool| {
+			u16(bytes, self.max_stack as usize);
+			u16(bytes, self.max_locals as usize);
+			bytes.extend_from_slice(&(assembled.code.len() as u32).to_be_bytes());
+			bytes.extend_from_slice(&assembled.code);
+
+			count(bytes, self.exception_table.len())?;
+			for entry in &self.exception_table {
+				u16(bytes, at(entry.start_pc)?);
+				u16(bytes, at(entry.end_pc)?);
+				u16(bytes, at(entry.handler_pc)?);
+				u16(bytes, match &entry.catch_type {
+					Some(catch_type) => pool.class(catch_type)?,
+					None => 0,
+				});
+			}
+
+			let has_line_numbers = !self.line_number_table.is_empty();
+			let has_stack_map = !self.stack_map_table.entries.is_empty();
+			count(bytes, self.attributes.len() + has_line_numbers as usize + has_stack_map as usize)?;
+			if has_line_numbers {
+				write_line_numbers(bytes, pool, &self.line_number_table, &at)?;
+			}
+			if has_stack_map {
+				write_stack_map(bytes, pool, &self.stack_map_table.entries, &at)?;
+			}
+			for attribute in &self.attributes {
+				attribute.write(bytes, pool, &at)?;
+			}
+			Ok(())
+		})
+	}
+}
+
+/// Returns the offset the instruction at `offset` in `code` has after encoding it again, given the `offsets` of the encoded instructions.
+fn moved(code: &Instructions, offsets: &[usize], offset: usize) -> Result<usize> {
+	code.index_of_or_end(offset)
+		.map(|index| offsets[index])
+		.ok_or_else(|| anyhow!("offset {offset} is not the start of an instruction"))
+}
+
+impl AttributeInfo {
+	/// Writes the attribute, with the offsets into the code it belongs to mapped by `at`.
+	pub(crate) fn write(&self, bytes: &mut Vec<u8>, pool: &mut PoolBuilder, at: &dyn Fn(usize) -> Result<usize>) -> Result<()> {
+		match self {
+			AttributeInfo::ConstantValue(_) => bail!("the ConstantValue attribute is only written for fields"),
+			AttributeInfo::Code(code) => code.write(bytes, pool),
+			AttributeInfo::StackMapTable(stack_map_table) => write_stack_map(bytes, pool, &stack_map_table.entries, at),
+			AttributeInfo::Exceptions(exceptions) => attribute(bytes, pool, b"Exceptions", |bytes, pool| {
+				count(bytes, exceptions.exception_table.len())?;
+				for exception in &exceptions.exception_table {
+					u16(bytes, pool.class(exception)?);
+				}
+				Ok(())
+			}),
+			AttributeInfo::InnerClasses(inner_classes) => attribute(bytes, pool, b"InnerClasses", |bytes, pool| {
+				count(bytes, inner_classes.classes.len())?;
+				for entry in &inner_classes.classes {
+					u16(bytes, pool.class(&entry.inner_class)?);
+					u16(bytes, match &entry.outer_class {
+						Some(outer_class) => pool.class(outer_class)?,
+						None => 0,
+					});
+					u16(bytes, match &entry.inner_name {
+						Some(inner_name) => pool.utf8(inner_name)?,
+						None => 0,
+					});
+					u16(bytes, entry.inner_class_access_flags as usize);
+				}
+				Ok(())
+			}),
+			AttributeInfo::EnclosingMethod(enclosing_method) => attribute(bytes, pool, b"EnclosingMethod", |bytes, pool| {
+				u16(bytes, pool.class(&enclosing_method.class)?);
+				u16(bytes, match &enclosing_method.method {
+					Some((name, descriptor)) => pool.name_and_type(name.as_bytes(), &descriptor.to_bytes())?,
+					None => 0,
+				});
+				Ok(())
+			}),
+			AttributeInfo::Synthetic(_) => attribute(bytes, pool, b"Synthetic", |_, _| Ok(())),
+			AttributeInfo::Signature(signature) => attribute(bytes, pool, b"Signature", |bytes, pool| {
+				u16(bytes, pool.utf8(&signature.signature)?);
+				Ok(())
+			}),
+			AttributeInfo::SourceFile(source_file) => attribute(bytes, pool, b"SourceFile", |bytes, pool| {
+				u16(bytes, pool.utf8(&source_file.sourcefile)?);
+				Ok(())
+			}),
+			AttributeInfo::SourceDebugExtension(extension) => attribute(bytes, pool, b"SourceDebugExtension", |bytes, _| {
+				bytes.extend_from_slice(&extension.debug_extension);
+				Ok(())
+			}),
+			AttributeInfo::LineNumberTable(line_numbers) => write_line_numbers(bytes, pool, &line_numbers.line_number_table, at),
+			AttributeInfo::LocalVariableTable(locals) => attribute(bytes, pool, b"LocalVariableTable", |bytes, pool| {
+				count(bytes, locals.local_variable_table.len())?;
+				for entry in &locals.local_variable_table {
+					let start_pc = at(entry.start_pc)?;
+					u16(bytes, start_pc);
+					u16(bytes, at(entry.end_pc)? - start_pc);
+					u16(bytes, pool.utf8(entry.name.as_bytes())?);
+					u16(bytes, pool.utf8(&entry.descriptor.to_bytes())?);
+					u16(bytes, entry.lv_index as usize);
+				}
+				Ok(())
+			}),
+			AttributeInfo::LocalVariableTypeTable(locals) => attribute(bytes, pool, b"LocalVariableTypeTable", |bytes, pool| {
+				count(bytes, locals.local_variable_type_table.len())?;
+				for entry in &locals.local_variable_type_table {
+					let start_pc = at(entry.start_pc)?;
+					u16(bytes, start_pc);
+					u16(bytes, at(entry.end_pc)? - start_pc);
+					u16(bytes, pool.utf8(entry.name.as_bytes())?);
+					u16(bytes, pool.utf8(&entry.signature)?);
+					u16(bytes, entry.lv_index as usize);
+				}
+				Ok(())
+			}),
+			AttributeInfo::Deprecated(_) => attribute(bytes, pool, b"Deprecated", |_, _| Ok(())),
+			AttributeInfo::RuntimeVisibleAnnotations(annotations) => attribute(bytes, pool, b"RuntimeVisibleAnnotations", |bytes, pool| {
+				write_annotations(bytes, pool, &annotations.annotations)
+			}),
+			AttributeInfo::RuntimeInvisibleAnnotations(annotations) => attribute(bytes, pool, b"RuntimeInvisibleAnnotations", |bytes, pool| {
+				write_annotations(bytes, pool, &annotations.annotations)
+			}),
+			AttributeInfo::RuntimeVisibleParameterAnnotations(annotations) => attribute(bytes, pool, b"RuntimeVisibleParameterAnnotations", |bytes, pool| {
+				short_count(bytes, annotations.parameter_annotations.len())?;
+				for parameter in &annotations.parameter_annotations {
+					write_annotations(bytes, pool, &parameter.annotations)?;
+				}
+				Ok(())
+			}),
+			AttributeInfo::RuntimeInvisibleParameterAnnotations(annotations) => attribute(bytes, pool, b"RuntimeInvisibleParameterAnnotations", |bytes, pool| {
+				short_count(bytes, annotations.parameter_annotations.len())?;
+				for parameter in &annotations.parameter_annotations {
+					write_annotations(bytes, pool, &parameter.annotations)?;
+				}
+				Ok(())
+			}),
+			AttributeInfo::AnnotationDefault(default) => attribute(bytes, pool, b"AnnotationDefault", |bytes, pool| {
+				write_element_value(bytes, pool, &default.default_value)
+			}),
+			AttributeInfo::BootstrapMethods(bootstrap_methods) => attribute(bytes, pool, b"BootstrapMethods", |bytes, pool| {
+				count(bytes, bootstrap_methods.bootstrap_methods.len())?;
+				for entry in &bootstrap_methods.bootstrap_methods {
+					u16(bytes, pool.method_handle(&entry.bootstrap_method)?);
+					count(bytes, entry.bootstrap_arguments.len())?;
+					for argument in &entry.bootstrap_arguments {
+						u16(bytes, match argument {
+							BootstrapMethodArgument::String(string) => pool.string(string)?,
+							BootstrapMethodArgument::Class(class) => pool.class(class)?,
+							BootstrapMethodArgument::Integer(value) => pool.integer(*value)?,
+							BootstrapMethodArgument::Long(value) => pool.long(*value)?,
+							BootstrapMethodArgument::Float(bits) => pool.float(*bits)?,
+							BootstrapMethodArgument::Double(bits) => pool.double(*bits)?,
+							BootstrapMethodArgument::MethodHandle(method_handle) => pool.method_handle(method_handle)?,
+							BootstrapMethodArgument::MethodType(descriptor) => pool.method_type(descriptor)?,
+						});
+					}
+				}
+				Ok(())
+			}),
+			AttributeInfo::MethodParameters(parameters) => attribute(bytes, pool, b"MethodParameters", |bytes, pool| {
+				short_count(bytes, parameters.parameters.len())?;
+				for parameter in &parameters.parameters {
+					u16(bytes, match &parameter.name {
+						Some(name) => pool.utf8(name)?,
+						None => 0,
+					});
+					u16(bytes, parameter.access_flags.to_flags());
+				}
+				Ok(())
+			}),
+			AttributeInfo::Unknown { name, info } => attribute(bytes, pool, name, |bytes, _| {
+				bytes.extend_from_slice(info);
+				Ok(())
+			}),
+		}
+	}
+}
+
+fn write_attributes(bytes: &mut Vec<u8>, pool: &mut PoolBuilder, attributes: &[AttributeInfo], at: &dyn Fn(usize) -> Result<usize>) -> Result<()> {
+	count(bytes, attributes.len())?;
+	for attribute in attributes {
+		attribute.write(bytes, pool, at)?;
+	}
+	Ok(())
+}
+
+fn write_line_numbers(bytes: &mut Vec<u8>, pool: &mut PoolBuilder, entries: &[LineNumberTableEntry], at: &dyn Fn(usize) -> Result<usize>) -> Result<()> {
+	attribute(bytes, pool, b"LineNumberTable", |bytes, _| {
+		count(bytes, entries.len())?;
+		for entry in entries {
+			u16(bytes, at(entry.start_pc)?);
+			u16(bytes, entry.line_number as usize);
+		}
+		Ok(())
+	})
+}
+
+// 4.7.4
+fn write_stack_map(bytes: &mut Vec<u8>, pool: &mut PoolBuilder, frames: &[StackMapFrame], at: &dyn Fn(usize) -> Result<usize>) -> Result<()> {
+	attribute(bytes, pool, b"StackMapTable", |bytes, pool| {
+		count(bytes, frames.len())?;
+		let mut last_offset = None;
+		for frame in frames {
+			let offset = at(frame.get_bytecode_offset())?;
+			let offset_delta = match last_offset {
+				None => offset,
+				Some(last_offset) if offset > last_offset => offset - last_offset - 1,
+				Some(last_offset) => bail!("stack map frame at {offset} doesn't come after the one at {last_offset}"),
+			};
+			last_offset = Some(offset);
+
+			match frame {
+				StackMapFrame::Same { .. } if offset_delta <= 63 => bytes.push(offset_delta as u8),
+				StackMapFrame::Same { .. } => {
+					bytes.push(251);
+					u16(bytes, offset_delta);
+				},
+				StackMapFrame::SameLocals1StackItem { stack, .. } => {
+					if offset_delta <= 63 {
+						bytes.push(64 + offset_delta as u8);
+					} else {
+						bytes.push(247);
+						u16(bytes, offset_delta);
+					}
+					write_verification_type(bytes, pool, stack, at)?;
+				},
+				StackMapFrame::Chop { k, .. } => {
+					bytes.push(251 - k);
+					u16(bytes, offset_delta);
+				},
+				StackMapFrame::Append { locals, .. } => {
+					if !(1..=3).contains(&locals.len()) {
+						bail!("an append frame must add one to three locals, got {}", locals.len());
+					}
+					bytes.push(251 + locals.len() as u8);
+					u16(bytes, offset_delta);
+					for local in locals {
+						write_verification_type(bytes, pool, local, at)?;
+					}
+				},
+				StackMapFrame::Full { locals, stack, .. } => {
+					bytes.push(255);
+					u16(bytes, offset_delta);
+					count(bytes, locals.len())?;
+					for local in locals {
+						write_verification_type(bytes, pool, local, at)?;
+					}
+					count(bytes, stack.len())?;
+					for stack in stack {
+						write_verification_type(bytes, pool, stack, at)?;
+					}
+				},
+			}
+		}
+		Ok(())
+	})
+}
+
+fn write_verification_type(bytes: &mut Vec<u8>, pool: &mut PoolBuilder, verification_type: &VerificationTypeInfo, at: &dyn Fn(usize) -> Result<usize>) -> Result<()> {
+	match verification_type {
+		VerificationTypeInfo::Top => bytes.push(0),
+		VerificationTypeInfo::Integer => bytes.push(1),
+		VerificationTypeInfo::Float => bytes.push(2),
+		VerificationTypeInfo::Double => bytes.push(3),
+		VerificationTypeInfo::Long => bytes.push(4),
+		VerificationTypeInfo::Null => bytes.push(5),
+		VerificationTypeInfo::UninitializedThis => bytes.push(6),
+		VerificationTypeInfo::Object(class) => {
+			bytes.push(7);
+			u16(bytes, pool.class(class)?);
+		},
+		VerificationTypeInfo::Uninitialized { bytecode_offset } => {
+			bytes.push(8);
+			u16(bytes, at(*bytecode_offset)?);
+		},
+	}
+	Ok(())
+}
+
+// 4.7.16
+fn write_annotations(bytes: &mut Vec<u8>, pool: &mut PoolBuilder, annotations: &[Annotation]) -> Result<()> {
+	count(bytes, annotations.len())?;
+	for annotation in annotations {
+		write_annotation(bytes, pool, annotation)?;
+	}
+	Ok(())
+}
+
+fn write_annotation(bytes: &mut Vec<u8>, pool: &mut PoolBuilder, annotation: &Annotation) -> Result<()> {
+	u16(bytes, pool.utf8(&annotation.annotation_type)?);
+	count(bytes, annotation.element_value_pairs.len())?;
+	for pair in &annotation.element_value_pairs {
+		u16(bytes, pool.utf8(&pair.element_name)?);
+		write_element_value(bytes, pool, &pair.value)?;
+	}
+	Ok(())
+}
+
+// 4.7.16.1
+fn write_element_value(bytes: &mut Vec<u8>, pool: &mut PoolBuilder, value: &AnnotationElementValue) -> Result<()> {
+	let (tag, index) = match value {
+		AnnotationElementValue::Byte(value) => (b'B', pool.integer(*value as i32)?),
+		AnnotationElementValue::Char(value) => (b'C', pool.integer(*value as i32)?),
+		AnnotationElementValue::Double(bits) => (b'D', pool.double(*bits)?),
+		AnnotationElementValue::Float(bits) => (b'F', pool.float(*bits)?),
+		AnnotationElementValue::Int(value) => (b'I', pool.integer(*value)?),
+		AnnotationElementValue::Long(value) => (b'J', pool.long(*value)?),
+		AnnotationElementValue::Short(value) => (b'S', pool.integer(*value as i32)?),
+		AnnotationElementValue::Boolean(value) => (b'Z', pool.integer(*value as i32)?),
+		AnnotationElementValue::String(string) => (b's', pool.utf8(string)?),
+		AnnotationElementValue::Class(descriptor) => (b'c', pool.utf8(descriptor)?),
+		AnnotationElementValue::Enum { type_name, const_name } => {
+			bytes.push(b'e');
+			u16(bytes, pool.utf8(type_name)?);
+			u16(bytes, pool.utf8(const_name)?);
+			return Ok(());
+		},
+		AnnotationElementValue::Annotation(annotation) => {
+			bytes.push(b'@');
+			return write_annotation(bytes, pool, annotation);
+		},
+		AnnotationElementValue::Array(values) => {
+			bytes.push(b'[');
+			count(bytes, values.len())?;
+			for value in values {
+				write_element_value(bytes, pool, value)?;
+			}
+			return Ok(());
+		},
+	};
+	bytes.push(tag);
+	u16(bytes, index);
+	Ok(())
+}
+
+/// Writes an attribute named `name`, with the `attribute_length` computed from what `body` writes.
+fn attribute(
+	bytes: &mut Vec<u8>, pool: &mut PoolBuilder, name: &[u8], body: impl FnOnce(&mut Vec<u8>, &mut PoolBuilder) -> Result<()>,
+) -> Result<()> {
+	u16(bytes, pool.utf8(name)?);
+	let start = bytes.len();
+	bytes.extend_from_slice(&[0; 4]);
+	body(bytes, pool)?;
+	let Ok(length) = u32::try_from(bytes.len() - start - 4) else {
+		bail!("attribute {:?} is too long", String::from_utf8_lossy(name));
+	};
+	bytes[start..start + 4].copy_from_slice(&length.to_be_bytes());
+	Ok(())
+}
+
+fn u16(bytes: &mut Vec<u8>, value: impl Into<usize>) {
+	bytes.extend_from_slice(&(value.into() as u16).to_be_bytes());
+}
+
+/// Writes the length of a table that has a `u16` length.
+fn count(bytes: &mut Vec<u8>, length: usize) -> Result<()> {
+	if length > u16::MAX as usize {
+		bail!("table of length {length} is too long, it must be less than 65536");
+	}
+	u16(bytes, length);
+	Ok(())
+}
+
+/// Writes the length of a table that has a `u8` length.
+fn short_count(bytes: &mut Vec<u8>, length: usize) -> Result<()> {
+	let Ok(length) = u8::try_from(length) else {
+		bail!("table of length {length} is too long, it must be less than 256");
+	};
+	bytes.push(length);
+	Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+	use alloc::vec;
+	use crate::ClassFile;
+	use crate::cp::attribute::AttributeInfo;
+	use crate::cp::builder::PoolBuilder;
+	use crate::write::WriteError;
+
+	#[test]
+	fn round_trip() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test3.class");
+		let (class_file, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+
+		let written = class_file.write(&mut PoolBuilder::from_pool(&pool)).unwrap();
+		assert_eq!(ClassFile::parse(&mut &written[..]).unwrap(), class_file);
+
+		// a new pool may order the constants differently, but writing the class again gives the same bytes
+		let written = class_file.to_bytes(None).unwrap();
+		let again = ClassFile::parse(&mut &written[..]).unwrap();
+		assert_eq!(again.to_bytes(None).unwrap(), written);
+		assert_eq!(again.methods.len(), class_file.methods.len());
+	}
+
+	#[test]
+	fn unknown_attributes() {
+		let bytes = include_bytes!("../../../java_example_classfiles/Test3.class");
+		let (mut class_file, pool) = ClassFile::parse_with_pool(&mut &bytes[..]).unwrap();
+		class_file.attributes.push(AttributeInfo::Unknown { name: b"Custom".to_vec(), info: vec![0, 1] });
+
+		assert!(matches!(class_file.to_bytes(None), Err(WriteError::UnknownAttribute(name)) if name == b"Custom"));
+		// the original pool is kept, so that the indices in the attribute stay valid, only the name of the attribute is added
+		let written = class_file.to_bytes(Some(&pool)).unwrap();
+		let (again, again_pool) = ClassFile::parse_with_pool(&mut &written[..]).unwrap();
+		assert_eq!(again, class_file);
+		assert_eq!(again_pool.len(), pool.len() + 1);
+		assert!(pool.iter().zip(again_pool.iter()).all(|(entry, again)| entry == again));
+	}
+}
@@ -0,0 +1,118 @@
+//! The parsing of arguments, the warnings and the exit codes the command line tools share.
+
+use std::error::Error;
+use std::process::ExitCode;
+use std::str::FromStr;
+use anyhow::{bail, Context, Result};
+use class_file::classpath::ClassPathEntry;
+use class_file::hierarchy::ClassHierarchy;
+
+/// The arguments of a tool, without the name of the program.
+pub struct Args<I> {
+	args: I,
+}
+
+impl<I: Iterator<Item=String>> Args<I> {
+	pub fn new(args: I) -> Args<I> {
+		Args { args }
+	}
+
+	/// Takes the value of `option`, `what` names it in the error if it is missing.
+	pub fn value(&mut self, option: &str, what: &str) -> Result<String> {
+		self.args.next().with_context(|| format!("missing {what} after {option}"))
+	}
+
+	/// Takes the value of `option` and parses it.
+	pub fn parse<T: FromStr>(&mut self, option: &str, what: &str) -> Result<T> where T::Err: Error + Send + Sync + 'static {
+		let value = self.value(option, what)?;
+		value.parse().with_context(|| format!("invalid {what} {value:?}"))
+	}
+
+	/// Takes the value of a class path option, see [is_class_path].
+	pub fn class_path(&mut self, option: &str) -> Result<Vec<ClassPathEntry>> {
+		Ok(ClassPathEntry::split(&self.value(option, "class path")?))
+	}
+}
+
+impl<I: Iterator<Item=String>> Iterator for Args<I> {
+	type Item = String;
+
+	fn next(&mut self) -> Option<String> {
+		self.args.next()
+	}
+}
+
+/// Whether the option gives a class path, separated by ':'. Like `java`, `-cp`, `--class-path` and `-classpath` all do.
+pub fn is_class_path(option: &str) -> bool {
+	matches!(option, "-cp" | "--class-path" | "-classpath")
+}
+
+/// Checks that an argument no option of the tool took is a path, not an unknown option.
+pub fn path(arg: &str) -> Result<&str> {
+	if arg.starts_with('-') {
+		bail!("unknown option {arg}");
+	}
+	Ok(arg)
+}
+
+/// Warns that the class file `file_name` of `entry` is left out.
+pub fn skipping(entry: &ClassPathEntry, file_name: &str, error: &anyhow::Error) {
+	eprintln!("warning: skipping {file_name} in {}: {error:#}", entry.path().display());
+}
+
+/// Adds the classes of the class path to the hierarchy, warning about the ones that can't be parsed.
+pub fn add_class_path(hierarchy: &mut ClassHierarchy, class_path: &[ClassPathEntry]) -> Result<()> {
+	for entry in class_path {
+		for (file_name, error) in hierarchy.add_class_path_entry(entry)? {
+			skipping(entry, &file_name, &error);
+		}
+	}
+	Ok(())
+}
+
+/// What running a tool results in.
+pub trait Outcome {
+	fn exit_code(self) -> ExitCode;
+}
+
+impl Outcome for () {
+	fn exit_code(self) -> ExitCode {
+		ExitCode::SUCCESS
+	}
+}
+
+/// `false` if the tool found what it looks for, like differences or incompatible changes.
+impl Outcome for bool {
+	fn exit_code(self) -> ExitCode {
+		if self { ExitCode::SUCCESS } else { ExitCode::from(1) }
+	}
+}
+
+/// Parses the arguments of the program and runs the tool with the options. `parse` returns `None` if the usage was asked for, which is printed
+/// together with errors in the arguments.
+///
+/// Exits with 1 if `run` returns `false`, and with 2 on errors.
+pub fn main<O, T: Outcome>(
+	usage: &str,
+	parse: impl FnOnce(Args<std::iter::Skip<std::env::Args>>) -> Result<Option<O>>,
+	run: impl FnOnce(&O) -> Result<T>,
+) -> ExitCode {
+	let options = match parse(Args::new(std::env::args().skip(1))) {
+		Ok(Some(options)) => options,
+		Ok(None) => {
+			println!("{usage}");
+			return ExitCode::SUCCESS;
+		},
+		Err(error) => {
+			eprintln!("error: {error:#}\n\n{usage}");
+			return ExitCode::from(2);
+		},
+	};
+	match run(&options) {
+		Ok(outcome) => outcome.exit_code(),
+		Err(error) => {
+			eprintln!("error: {error:#}");
+			ExitCode::from(2)
+		},
+	}
+}
@@ -0,0 +1,69 @@
+//! Writing a jar with the changed classes of the inputs of a tool, together with their resources.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write as _};
+use anyhow::{Context, Result};
+use zip::{ZipArchive, ZipWriter};
+use zip::write::FileOptions;
+use class_file::classpath::ClassPathEntry;
+
+/// The class files to write for an input, by their name in the input, like `foo/Bar.class`. Classes with `None` are left out.
+pub type Classes<'a> = Vec<(&'a str, Option<Cow<'a, [u8]>>)>;
+
+/// Whether the file is part of the signature of a jar, which no longer matches once classes are changed.
+fn is_signature_file(name: &str) -> bool {
+	name.starts_with("META-INF/") && [".SF", ".RSA", ".DSA", ".EC"].iter().any(|extension| name.ends_with(extension))
+}
+
+/// Writes the jar `path` with the files of `inputs`, the class files replaced by the ones in `classes`, which has an entry for each input.
+///
+/// The files of a jar are written in its order, the ones it has that aren't in `classes` are copied as they are. Directories and class files have
+/// their classes written first, followed by the resources of directories. Like on a class path, the first file with a name wins. The signature files
+/// of jars are left out.
+pub fn write_jar(path: &str, inputs: &[ClassPathEntry], classes: &[Classes]) -> Result<()> {
+	let file = File::create(path).with_context(|| format!("while creating {path}"))?;
+	let mut jar = ZipWriter::new(BufWriter::new(file));
+	let mut written = HashSet::new();
+	let write = |jar: &mut ZipWriter<_>, name: &str, bytes: &[u8]| -> Result<()> {
+		jar.start_file(name, FileOptions::default())?;
+		jar.write_all(bytes)?;
+		Ok(())
+	};
+	for (entry, classes) in inputs.iter().zip(classes) {
+		match entry {
+			ClassPathEntry::Jar(jar_path, _) => {
+				let classes: HashMap<&str, &Option<Cow<[u8]>>> = classes.iter().map(|(name, bytes)| (*name, bytes)).collect();
+				let file = File::open(jar_path).with_context(|| format!("while opening {}", jar_path.display()))?;
+				let mut archive = ZipArchive::new(BufReader::new(file)).with_context(|| format!("while reading {} as jar", jar_path.display()))?;
+				for index in 0..archive.len() {
+					let file = archive.by_index(index)?;
+					let name = file.name().to_owned();
+					if is_signature_file(&name) || !written.insert(name.clone()) {
+						continue;
+					}
+					match classes.get(name.as_str()) {
+						Some(Some(bytes)) => write(&mut jar, &name, bytes)?,
+						Some(None) => {},
+						None => jar.raw_copy_file(file)?,
+					}
+				}
+			},
+			ClassPathEntry::Directory(_) | ClassPathEntry::Class(_) => {
+				for (name, bytes) in classes {
+					if let (true, Some(bytes)) = (written.insert(name.to_string()), bytes) {
+						write(&mut jar, name, bytes)?;
+					}
+				}
+				for (name, bytes) in entry.resources()? {
+					if !is_signature_file(&name) && written.insert(name.clone()) {
+						write(&mut jar, &name, &bytes)?;
+					}
+				}
+			},
+		}
+	}
+	jar.finish()?.flush()?;
+	Ok(())
+}
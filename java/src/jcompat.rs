@@ -0,0 +1,95 @@
+use std::process::ExitCode;
+use anyhow::{bail, Context, Result};
+use class_file::ClassFile;
+use class_file::classpath::ClassPathEntry;
+use class_file::compat::{compare, Api, Incompatibility};
+use class_file::hierarchy::ClassEntry;
+use java::cli::{self, Args};
+
+const USAGE: &str = "\
+Usage: jcompat [options] <old jars> <new jars>
+
+Lists the changes to the API of a library that break the classes using it, as defined by chapter 13 of the JLS. The versions are given as jars,
+directories or classes, separated by ':'.
+
+Options:
+  -cp, --class-path <path>  Class path of the libraries the library uses, to find inherited members, separated by ':'
+  -b, --binary              Only list binary-incompatible changes
+  -s, --source              Also fail on source-incompatible changes
+  -h, --help                Show this help";
+
+#[derive(Debug, Default)]
+struct Options {
+	old: Vec<ClassPathEntry>,
+	new: Vec<ClassPathEntry>,
+	class_path: Vec<ClassPathEntry>,
+	binary: bool,
+	source: bool,
+}
+
+impl Options {
+	fn parse(mut args: Args<impl Iterator<Item=String>>) -> Result<Option<Options>> {
+		let mut options = Options::default();
+		let mut versions = Vec::new();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				option if cli::is_class_path(option) => options.class_path.extend(args.class_path(option)?),
+				"-b" | "--binary" => options.binary = true,
+				"-s" | "--source" => options.source = true,
+				"-h" | "--help" => return Ok(None),
+				path => versions.push(ClassPathEntry::split(cli::path(path)?)),
+			}
+		}
+		let Ok([old, new]) = <[_; 2]>::try_from(versions) else {
+			bail!("expected the old and the new version");
+		};
+		options.old = old;
+		options.new = new;
+		Ok(Some(options))
+	}
+}
+
+fn read_api(entries: &[ClassPathEntry], class_path: &[ClassPathEntry]) -> Result<Api> {
+	let mut api = Api::new();
+	for entry in entries {
+		for (file_name, bytes) in entry.class_files()? {
+			match ClassFile::parse(&mut &bytes[..]) {
+				Ok(class) => {
+					api.add_class(&class);
+				},
+				Err(error) => cli::skipping(entry, &file_name, &error),
+			}
+		}
+	}
+	for entry in class_path {
+		for (file_name, bytes) in entry.class_files()? {
+			match ClassEntry::from_bytes(&bytes) {
+				Ok(class) => {
+					api.add_library(class);
+				},
+				Err(error) => cli::skipping(entry, &file_name, &error),
+			}
+		}
+	}
+	Ok(api)
+}
+
+fn run(options: &Options) -> Result<bool> {
+	let old = read_api(&options.old, &options.class_path).context("while reading the old version")?;
+	let new = read_api(&options.new, &options.class_path).context("while reading the new version")?;
+	let mut ok = true;
+	for change in compare(&old, &new) {
+		let incompatibility = change.kind.incompatibility();
+		if options.binary && incompatibility != Incompatibility::Binary {
+			continue;
+		}
+		println!("{change}");
+		ok &= incompatibility == Incompatibility::Source && !options.source;
+	}
+	Ok(ok)
+}
+
+/// Exits with 1 if binary-incompatible changes are found, or source-incompatible ones with `--source`, and with 2 on errors.
+fn main() -> ExitCode {
+	cli::main(USAGE, Options::parse, run)
+}
@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use anyhow::{bail, Context, Result};
+use class_file::ClassFile;
+use class_file::classpath::ClassPathEntry;
+use class_file::decompile::Decompiler;
+use java::cli::{self, Args};
+
+const USAGE: &str = "\
+Usage: jdecompile [options] <jar|directory|class>...
+
+Reconstructs Java source from the given classes, writing member classes inside the class they're declared in. The output is meant for reading:
+code that doesn't map to Java statements shows up with labels and gotos, and methods that can't be decompiled are written as a listing.
+
+Options:
+  -c, --class <name>        Only decompile this class and its member classes, like java.util.ArrayList (can be repeated)
+  -d, --output-dir <dir>    Write a .java file per class into this directory, instead of printing the sources
+  -h, --help                Show this help";
+
+#[derive(Debug, Default)]
+struct Options {
+	inputs: Vec<ClassPathEntry>,
+	/// Internal names of the classes to decompile, all if empty.
+	classes: Vec<String>,
+	output_dir: Option<PathBuf>,
+}
+
+impl Options {
+	fn parse(mut args: Args<impl Iterator<Item=String>>) -> Result<Option<Options>> {
+		let mut options = Options::default();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"-c" | "--class" => options.classes.push(args.value(&arg, "class name")?.replace('.', "/")),
+				"-d" | "--output-dir" => options.output_dir = Some(args.value(&arg, "directory")?.into()),
+				"-h" | "--help" => return Ok(None),
+				path => options.inputs.push(ClassPathEntry::new(cli::path(path)?)),
+			}
+		}
+		if options.inputs.is_empty() {
+			bail!("no classes given");
+		}
+		Ok(Some(options))
+	}
+
+	/// Whether the class, or the class it is nested in, was asked for.
+	fn is_selected(&self, class: &ClassFile) -> bool {
+		let name = String::from_utf8_lossy(class.this_class.as_bytes());
+		self.classes.is_empty() || self.classes.iter().any(|selected| {
+			name.strip_prefix(selected.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('$'))
+		})
+	}
+}
+
+fn run(options: &Options) -> Result<()> {
+	let mut classes = Vec::new();
+	for entry in &options.inputs {
+		for (file_name, bytes) in entry.class_files()? {
+			match ClassFile::parse(&mut &bytes[..]) {
+				Ok(class) => classes.push(class),
+				Err(error) => cli::skipping(entry, &file_name, &error),
+			}
+		}
+	}
+	classes.retain(|class| options.is_selected(class));
+	if classes.is_empty() {
+		bail!("no classes to decompile");
+	}
+
+	let mut decompiler = Decompiler::new();
+	for class in &classes {
+		decompiler.add_class(class);
+	}
+	let mut first = true;
+	for class in classes.iter().filter(|class| !decompiler.is_nested(class)) {
+		let source = decompiler.decompile(class);
+		match &options.output_dir {
+			Some(output_dir) => {
+				let path = output_dir.join(format!("{}.java", String::from_utf8_lossy(class.this_class.as_bytes())));
+				if let Some(parent) = path.parent() {
+					fs::create_dir_all(parent).with_context(|| format!("while creating {}", parent.display()))?;
+				}
+				fs::write(&path, source).with_context(|| format!("while writing {}", path.display()))?;
+			},
+			None => {
+				if !first {
+					println!();
+				}
+				print!("{source}");
+			},
+		}
+		first = false;
+	}
+	Ok(())
+}
+
+fn main() -> ExitCode {
+	cli::main(USAGE, Options::parse, run)
+}
@@ -0,0 +1,151 @@
+use std::process::ExitCode;
+use anyhow::{bail, Result};
+use class_file::ClassFile;
+use class_file::classpath::ClassPathEntry;
+use class_file::deps::{package, DependencyAnalysis};
+use class_file::lazy::ClassFileRef;
+use class_file::name::ClassName;
+use java::cli::{self, Args};
+
+const USAGE: &str = "\
+Usage: jdeps [options] <jar|directory|class>...
+
+Lists the classes the given classes depend on.
+
+Options:
+  -cp, --class-path <path>  Class path to look up dependencies in, separated by ':'
+  -p, --package             Show dependencies between packages instead of classes
+  -s, --summary             Show dependencies between jars and directories instead of classes
+  -v, --verbose             Show how each class is referenced
+  --cycles                  Report cycles between packages
+  --missing                 Report the classes that can't be found in the analyzed classes or the class path
+  --include-jdk             Don't assume the classes of the JDK to be present
+  -h, --help                Show this help";
+
+/// The packages of the JDK, which are assumed to be present unless `--include-jdk` is given.
+const JDK_PACKAGES: &[&[u8]] = &[b"java/", b"javax/", b"jdk/", b"sun/", b"com/sun/"];
+
+#[derive(Debug, Default)]
+struct Options {
+	inputs: Vec<ClassPathEntry>,
+	class_path: Vec<ClassPathEntry>,
+	package: bool,
+	summary: bool,
+	verbose: bool,
+	cycles: bool,
+	missing: bool,
+	include_jdk: bool,
+}
+
+impl Options {
+	fn parse(mut args: Args<impl Iterator<Item=String>>) -> Result<Option<Options>> {
+		let mut options = Options::default();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				option if cli::is_class_path(option) => options.class_path.extend(args.class_path(option)?),
+				"-p" | "--package" => options.package = true,
+				"-s" | "--summary" => options.summary = true,
+				"-v" | "--verbose" => options.verbose = true,
+				"--cycles" => options.cycles = true,
+				"--missing" => options.missing = true,
+				"--include-jdk" => options.include_jdk = true,
+				"-h" | "--help" => return Ok(None),
+				path => options.inputs.push(ClassPathEntry::new(cli::path(path)?)),
+			}
+		}
+		if options.inputs.is_empty() {
+			bail!("no classes given");
+		}
+		Ok(Some(options))
+	}
+}
+
+fn name(bytes: &[u8]) -> String {
+	if bytes.is_empty() {
+		"<unnamed package>".to_owned()
+	} else {
+		String::from_utf8_lossy(bytes).into_owned()
+	}
+}
+
+fn analyze(options: &Options) -> Result<DependencyAnalysis> {
+	let mut analysis = DependencyAnalysis::new();
+	for input in &options.inputs {
+		let origin = input.path().display().to_string();
+		for (file_name, bytes) in input.class_files()? {
+			match ClassFile::parse_with_pool(&mut &bytes[..]) {
+				Ok((class, pool)) => analysis.add(&origin, &class, &pool),
+				Err(error) => cli::skipping(input, &file_name, &error),
+			}
+		}
+	}
+	for entry in &options.class_path {
+		let origin = entry.path().display().to_string();
+		for (file_name, bytes) in entry.class_files()? {
+			match ClassFileRef::parse(&bytes).and_then(|class| class.this_class()) {
+				Ok(class) => analysis.add_available(&origin, ClassName::from(class)),
+				Err(error) => cli::skipping(entry, &file_name, &error),
+			}
+		}
+	}
+	Ok(analysis)
+}
+
+fn run(options: &Options) -> Result<bool> {
+	let analysis = analyze(options)?;
+	let provided = |class: &ClassName| !options.include_jdk && JDK_PACKAGES.iter().any(|package| class.as_bytes().starts_with(package));
+
+	if options.summary {
+		for (origin, targets) in analysis.origin_dependencies() {
+			for target in targets {
+				println!("{origin} -> {target}");
+			}
+		}
+	} else if options.package {
+		for (from, targets) in analysis.package_dependencies() {
+			for to in targets {
+				println!("{} -> {}", name(from), name(to));
+			}
+		}
+	} else {
+		for (origin, dependencies) in analysis.classes() {
+			println!("{} ({origin})", name(dependencies.class.as_bytes()));
+			for (class, kinds) in &dependencies.dependencies {
+				let location = match analysis.origin_of(class) {
+					Some(origin) => origin,
+					None if provided(class) => "JDK",
+					None => "not found",
+				};
+				if options.verbose {
+					println!("   -> {} ({location}) {kinds:?}", name(class.as_bytes()));
+				} else {
+					println!("   -> {} ({location})", name(class.as_bytes()));
+				}
+			}
+		}
+	}
+
+	let mut ok = true;
+	if options.cycles {
+		let cycles = analysis.package_cycles();
+		for cycle in &cycles {
+			let packages: Vec<_> = cycle.iter().map(|package| name(package)).collect();
+			println!("cycle: {}", packages.join(", "));
+		}
+		ok &= cycles.is_empty();
+	}
+	if options.missing {
+		let missing = analysis.missing(provided);
+		for (class, referrers) in &missing {
+			let referrers: Vec<_> = referrers.iter().map(|referrer| name(referrer.as_bytes())).collect();
+			println!("missing: {} (package {}), referenced by {}", name(class.as_bytes()), name(package(class)), referrers.join(", "));
+		}
+		ok &= missing.is_empty();
+	}
+	Ok(ok)
+}
+
+/// Exits with 1 if cycles or missing classes are found when asked for, and with 2 on errors.
+fn main() -> ExitCode {
+	cli::main(USAGE, Options::parse, run)
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+use anyhow::{bail, Context, Result};
+use class_file::ClassFile;
+use class_file::classpath::ClassPathEntry;
+use class_file::diff::ClassDiff;
+use java::cli::{self, Args};
+
+const USAGE: &str = "\
+Usage: jdiff [options] <old jar|directory|class> <new jar|directory|class>
+
+Shows what changed between two versions of class files: the classes added and removed, and for the others the members added and removed, changed
+flags and attributes, and the changed instructions, with labels instead of offsets.
+
+Options:
+  -U, --context <lines>  Unchanged lines to show around changed instructions, 3 by default
+  --json                 Write the differences as JSON, with all instructions of changed methods
+  -h, --help             Show this help";
+
+#[derive(Debug)]
+struct Options {
+	old: ClassPathEntry,
+	new: ClassPathEntry,
+	context: usize,
+	json: bool,
+}
+
+impl Options {
+	fn parse(mut args: Args<impl Iterator<Item=String>>) -> Result<Option<Options>> {
+		let mut versions = Vec::new();
+		let mut context = 3;
+		let mut json = false;
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"-U" | "--context" => context = args.parse(&arg, "number of lines")?,
+				"--json" => json = true,
+				"-h" | "--help" => return Ok(None),
+				path => versions.push(ClassPathEntry::new(cli::path(path)?)),
+			}
+		}
+		let Ok([old, new]) = <[_; 2]>::try_from(versions) else {
+			bail!("expected the old and the new version");
+		};
+		Ok(Some(Options { old, new, context, json }))
+	}
+}
+
+fn read(entry: &ClassPathEntry) -> Result<BTreeMap<String, Vec<u8>>> {
+	let mut class_files = BTreeMap::new();
+	for (file_name, bytes) in entry.class_files()? {
+		// two single class files are compared even if their names differ
+		let file_name = match entry {
+			ClassPathEntry::Class(_) => String::new(),
+			_ => file_name,
+		};
+		class_files.entry(file_name).or_insert(bytes);
+	}
+	Ok(class_files)
+}
+
+fn parse(file_name: &str, bytes: &[u8]) -> Result<ClassFile> {
+	ClassFile::parse(&mut &bytes[..]).with_context(|| format!("while parsing {file_name}"))
+}
+
+fn run(options: &Options) -> Result<bool> {
+	let old = read(&options.old)?;
+	let new = read(&options.new)?;
+	let added: Vec<&str> = new.keys().filter(|file_name| !old.contains_key(*file_name)).map(String::as_str).collect();
+	let removed: Vec<&str> = old.keys().filter(|file_name| !new.contains_key(*file_name)).map(String::as_str).collect();
+	let mut diffs = Vec::new();
+	for (file_name, old_bytes) in &old {
+		let Some(new_bytes) = new.get(file_name) else {
+			continue;
+		};
+		if old_bytes != new_bytes {
+			let diff = ClassDiff::new(&parse(file_name, old_bytes)?, &parse(file_name, new_bytes)?);
+			if !diff.is_empty() {
+				diffs.push(diff);
+			}
+		}
+	}
+
+	if options.json {
+		println!("{}", ClassDiff::to_json_document(&added, &removed, &diffs));
+	} else {
+		for file_name in &added {
+			println!("+ {file_name}");
+		}
+		for file_name in &removed {
+			println!("- {file_name}");
+		}
+		for diff in &diffs {
+			print!("{}", diff.to_text(options.context));
+		}
+	}
+	Ok(added.is_empty() && removed.is_empty() && diffs.is_empty())
+}
+
+/// Exits with 1 if the versions differ, and with 2 on errors, like `diff`.
+fn main() -> ExitCode {
+	cli::main(USAGE, Options::parse, run)
+}
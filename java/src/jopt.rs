@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+use std::process::ExitCode;
+use anyhow::{bail, Result};
+use class_file::ClassFile;
+use class_file::classpath::ClassPathEntry;
+use class_file::optimize::{optimize, OptimizeOptions};
+use java::cli::{self, Args};
+use java::jar::{write_jar, Classes};
+
+const USAGE: &str = "\
+Usage: jopt [options] <jar|directory|class>...
+
+Runs a peephole optimizer over the code of the given classes: it folds constants, removes redundant loads and stores, jumps to the next instruction
+and unreachable code, and threads jumps. Prints how much smaller the code gets, and optionally writes a jar with the optimized classes.
+
+Options:
+  --no-fold                 Don't fold constants
+  --no-loads-stores         Don't remove loads and stores
+  --no-jumps                Don't remove or thread jumps
+  --no-unreachable          Don't remove unreachable code
+  -o, --output <jar>        Write the optimized classes and the resources of the inputs to a jar
+  -h, --help                Show this help";
+
+#[derive(Debug, Default)]
+struct Options {
+	inputs: Vec<ClassPathEntry>,
+	optimize: OptimizeOptions,
+	output: Option<String>,
+}
+
+impl Options {
+	fn parse(mut args: Args<impl Iterator<Item=String>>) -> Result<Option<Options>> {
+		let mut options = Options::default();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--no-fold" => options.optimize.fold_constants = false,
+				"--no-loads-stores" => options.optimize.loads_and_stores = false,
+				"--no-jumps" => options.optimize.jumps = false,
+				"--no-unreachable" => options.optimize.unreachable_code = false,
+				"-o" | "--output" => options.output = Some(args.value(&arg, "jar")?),
+				"-h" | "--help" => return Ok(None),
+				path => options.inputs.push(ClassPathEntry::new(cli::path(path)?)),
+			}
+		}
+		if options.inputs.is_empty() {
+			bail!("no classes given");
+		}
+		Ok(Some(options))
+	}
+}
+
+/// A class file of an input, with the bytes of the optimized class if it changed.
+struct Input {
+	file_name: String,
+	bytes: Vec<u8>,
+	optimized: Option<Vec<u8>>,
+}
+
+impl Input {
+	fn bytes(&self) -> &[u8] {
+		self.optimized.as_deref().unwrap_or(&self.bytes)
+	}
+}
+
+#[derive(Debug, Default)]
+struct Totals {
+	classes: usize,
+	methods: usize,
+	optimized_methods: usize,
+	code_length: usize,
+	optimized_code_length: usize,
+}
+
+fn code_length(class: &ClassFile) -> usize {
+	class.methods.iter().filter_map(|method| method.code.as_ref()).map(|code| code.code.code_length()).sum()
+}
+
+/// Returns the bytes of the optimized class, or `None` if it didn't change. Classes that can't be parsed or optimized are kept as they are.
+fn optimize_input(file_name: &str, origin: &str, bytes: &[u8], options: &OptimizeOptions, totals: &mut Totals) -> Option<Vec<u8>> {
+	let (class, pool) = match ClassFile::parse_with_pool(&mut &bytes[..]) {
+		Ok(class) => class,
+		Err(error) => {
+			eprintln!("warning: keeping {file_name} in {origin} as it is: {error:#}");
+			return None;
+		},
+	};
+	let result = optimize(&class, &pool, options).and_then(|(bytes, methods)| {
+		if methods == 0 {
+			return Ok((methods, code_length(&class), None));
+		}
+		let optimized = ClassFile::parse(&mut &bytes[..])?;
+		Ok((methods, code_length(&optimized), Some(bytes)))
+	});
+	let (methods, optimized_code_length, optimized_bytes) = match result {
+		Ok(result) => result,
+		Err(error) => {
+			eprintln!("warning: keeping {file_name} in {origin} as it is: {error:#}");
+			return None;
+		},
+	};
+	totals.classes += 1;
+	totals.methods += class.methods.iter().filter(|method| method.code.is_some()).count();
+	totals.optimized_methods += methods;
+	totals.code_length += code_length(&class);
+	totals.optimized_code_length += optimized_code_length;
+	optimized_bytes
+}
+
+fn percent(part: usize, whole: usize) -> Cow<'static, str> {
+	if whole == 0 {
+		Cow::Borrowed("0%")
+	} else {
+		Cow::Owned(format!("{:.1}%", part as f64 * 100.0 / whole as f64))
+	}
+}
+
+/// Describes how much `after` is smaller or larger than `before`, as the optimizations may also make code longer.
+fn change(before: usize, after: usize) -> String {
+	if after <= before {
+		format!("{} smaller", percent(before - after, before))
+	} else {
+		format!("{} larger", percent(after - before, before))
+	}
+}
+
+fn run(options: &Options) -> Result<()> {
+	let mut totals = Totals::default();
+	let mut inputs = Vec::with_capacity(options.inputs.len());
+	for entry in &options.inputs {
+		let origin = entry.path().display().to_string();
+		let mut classes = Vec::new();
+		for (file_name, bytes) in entry.class_files()? {
+			let optimized = optimize_input(&file_name, &origin, &bytes, &options.optimize, &mut totals);
+			classes.push(Input { file_name, bytes, optimized });
+		}
+		inputs.push(classes);
+	}
+
+	let Totals { classes, methods, optimized_methods, code_length, optimized_code_length } = totals;
+	println!("{optimized_methods} of {methods} methods in {classes} classes optimized");
+	println!("code: {code_length} -> {optimized_code_length} bytes ({})", change(code_length, optimized_code_length));
+
+	if let Some(output) = &options.output {
+		let classes: Vec<Classes> = inputs.iter()
+			.map(|classes| classes.iter().map(|input| (input.file_name.as_str(), Some(Cow::Borrowed(input.bytes())))).collect())
+			.collect();
+		write_jar(output, &options.inputs, &classes)?;
+	}
+	Ok(())
+}
+
+fn main() -> ExitCode {
+	cli::main(USAGE, Options::parse, run)
+}
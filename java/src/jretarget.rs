@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+use std::process::ExitCode;
+use anyhow::{bail, Context, Result};
+use class_file::ClassFile;
+use class_file::classpath::ClassPathEntry;
+use class_file::cp::Pool;
+use class_file::hierarchy::ClassHierarchy;
+use class_file::retarget::retarget;
+use java::cli::{self, Args};
+use java::jar::{write_jar, Classes};
+
+const USAGE: &str = "\
+Usage: jretarget [options] --release <release> <jar|directory|class>...
+
+Changes the class file version of the given classes. Moving to Java 6 or later adds the stack map frames the verifier needs, moving to an older
+release desugars string concatenation and fails on features it doesn't have, like lambdas, nestmates and records. Subroutines of old classes are
+inlined in any case.
+
+Options:
+  -r, --release <release>   The Java release to target, like 8 or 1.4
+  --class-version <major>   The major class file version to target instead, like 52
+  -cp, --class-path <path>  Class path of the libraries used, including the JDK classes, separated by ':'. Stack map frames need the superclasses
+                            of the classes used
+  -o, --output <jar>        Write the retargeted classes and the resources of the inputs to a jar
+  -h, --help                Show this help";
+
+#[derive(Debug, Default)]
+struct Options {
+	inputs: Vec<ClassPathEntry>,
+	class_path: Vec<ClassPathEntry>,
+	major_version: u16,
+	output: Option<String>,
+}
+
+/// The class file version of a release, `1.2` to `1.8` are releases 2 to 8.
+fn major_version(release: &str) -> Result<u16> {
+	let number = match release.strip_prefix("1.") {
+		Some(number) => number.parse().ok().filter(|number| (1..=8).contains(number)),
+		None => release.parse().ok().filter(|number| *number >= 5),
+	};
+	number.map(|number: u16| 44 + number).with_context(|| format!("invalid release {release:?}"))
+}
+
+impl Options {
+	fn parse(mut args: Args<impl Iterator<Item=String>>) -> Result<Option<Options>> {
+		let mut options = Options::default();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"-r" | "--release" => options.major_version = major_version(&args.value(&arg, "release")?)?,
+				"--class-version" => options.major_version = args.parse(&arg, "class file version")?,
+				option if cli::is_class_path(option) => options.class_path.extend(args.class_path(option)?),
+				"-o" | "--output" => options.output = Some(args.value(&arg, "jar")?),
+				"-h" | "--help" => return Ok(None),
+				path => options.inputs.push(ClassPathEntry::new(cli::path(path)?)),
+			}
+		}
+		if options.major_version == 0 {
+			bail!("no release given");
+		}
+		if options.inputs.is_empty() {
+			bail!("no classes given");
+		}
+		Ok(Some(options))
+	}
+}
+
+/// A class file of an input, with the error if it couldn't be parsed.
+struct Input {
+	file_name: String,
+	class: Result<(ClassFile, Pool)>,
+}
+
+/// Returns whether all classes could be retargeted.
+fn run(options: &Options) -> Result<bool> {
+	let mut inputs = Vec::new();
+	for entry in &options.inputs {
+		let classes: Vec<Input> = entry.class_files()?.into_iter()
+			.map(|(file_name, bytes)| Input { file_name, class: ClassFile::parse_with_pool(&mut &bytes[..]) })
+			.collect();
+		inputs.push(classes);
+	}
+
+	let mut hierarchy = ClassHierarchy::new();
+	for input in inputs.iter().flatten() {
+		if let Ok((class, _)) = &input.class {
+			hierarchy.add_class_file(class);
+		}
+	}
+	cli::add_class_path(&mut hierarchy, &options.class_path)?;
+
+	let mut failed = 0;
+	let mut retargeted = Vec::with_capacity(inputs.len());
+	for (entry, classes) in options.inputs.iter().zip(inputs) {
+		let mut bytes = Vec::new();
+		for Input { file_name, class } in classes {
+			match class.and_then(|(class, pool)| retarget(&class, &pool, &hierarchy, options.major_version)) {
+				Ok(retargeted) => bytes.push((file_name, retargeted)),
+				Err(error) => {
+					eprintln!("error: can't retarget {file_name} in {}: {error:#}", entry.path().display());
+					failed += 1;
+				},
+			}
+		}
+		retargeted.push(bytes);
+	}
+	let count: usize = retargeted.iter().map(Vec::len).sum();
+	eprintln!("{count} of {} classes retargeted to version {}.0", count + failed, options.major_version);
+
+	if failed > 0 {
+		return Ok(false);
+	}
+	if let Some(output) = &options.output {
+		let classes: Vec<Classes> = retargeted.iter()
+			.map(|classes| classes.iter().map(|(file_name, bytes)| (file_name.as_str(), Some(Cow::Borrowed(&bytes[..])))).collect())
+			.collect();
+		write_jar(output, &options.inputs, &classes)?;
+	}
+	Ok(true)
+}
+
+/// Exits with 1 if some classes can't be retargeted, in which case nothing is written, and with 2 on errors.
+fn main() -> ExitCode {
+	cli::main(USAGE, Options::parse, run)
+}
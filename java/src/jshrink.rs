@@ -0,0 +1,165 @@
+use std::borrow::Cow;
+use std::process::ExitCode;
+use anyhow::{bail, Context, Result};
+use class_file::ClassFile;
+use class_file::call_graph::Analysis;
+use class_file::classpath::ClassPathEntry;
+use class_file::cp::Pool;
+use class_file::hierarchy::ClassHierarchy;
+use class_file::shrink::{KeepRule, Shrinker, Usage};
+use java::cli::{self, Args};
+use java::jar::{write_jar, Classes};
+
+const USAGE: &str = "\
+Usage: jshrink [options] <jar|directory|class>...
+
+Lists the classes, methods and fields of the given classes that can't be used from the roots, and optionally writes a jar without them.
+
+Options:
+  -cp, --class-path <path>    Class path of the libraries used, which are never removed, separated by ':'
+  --no-main                   Don't keep the main methods
+  --annotation <descriptor>   Keep the classes and members with an annotation, like Lorg/junit/jupiter/api/Test;
+  --keep <rule>               Keep the classes or members matching a rule, like com/example/Plugin or com/example/Plugin.run()V
+  --keep-file <file>          Keep the classes or members matching the rules in a file, one per line
+  --cha                       Build the call graph with class hierarchy analysis instead of rapid type analysis
+  -o, --output <jar>          Write the classes and resources of the inputs without the unused code to a jar
+  -h, --help                  Show this help";
+
+#[derive(Debug, Default)]
+struct Options {
+	inputs: Vec<ClassPathEntry>,
+	class_path: Vec<ClassPathEntry>,
+	no_main: bool,
+	annotations: Vec<String>,
+	rules: Vec<KeepRule>,
+	cha: bool,
+	output: Option<String>,
+}
+
+impl Options {
+	fn parse(mut args: Args<impl Iterator<Item=String>>) -> Result<Option<Options>> {
+		let mut options = Options::default();
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				option if cli::is_class_path(option) => options.class_path.extend(args.class_path(option)?),
+				"--no-main" => options.no_main = true,
+				"--annotation" => options.annotations.push(args.value(&arg, "descriptor")?),
+				"--keep" => options.rules.push(KeepRule::parse(&args.value(&arg, "rule")?)?),
+				"--keep-file" => {
+					let path = args.value(&arg, "file")?;
+					let rules = std::fs::read_to_string(&path).with_context(|| format!("while reading {path}"))?;
+					options.rules.extend(KeepRule::parse_all(&rules).with_context(|| format!("in {path}"))?);
+				},
+				"--cha" => options.cha = true,
+				"-o" | "--output" => options.output = Some(args.value(&arg, "jar")?),
+				"-h" | "--help" => return Ok(None),
+				path => options.inputs.push(ClassPathEntry::new(cli::path(path)?)),
+			}
+		}
+		if options.inputs.is_empty() {
+			bail!("no classes given");
+		}
+		Ok(Some(options))
+	}
+}
+
+/// A class file of an input, `class` is `None` if it couldn't be parsed.
+struct Input {
+	file_name: String,
+	bytes: Vec<u8>,
+	class: Option<(ClassFile, Pool)>,
+}
+
+fn read_inputs(options: &Options) -> Result<Vec<Vec<Input>>> {
+	let mut inputs = Vec::new();
+	for entry in &options.inputs {
+		let origin = entry.path().display().to_string();
+		let mut classes = Vec::new();
+		for (file_name, bytes) in entry.class_files()? {
+			let class = match ClassFile::parse_with_pool(&mut &bytes[..]) {
+				Ok(class) => Some(class),
+				Err(error) => {
+					eprintln!("warning: can't analyze {file_name} in {origin}: {error:#}");
+					None
+				},
+			};
+			classes.push(Input { file_name, bytes, class });
+		}
+		inputs.push(classes);
+	}
+	Ok(inputs)
+}
+
+/// Returns the bytes of the class without its unused members, or `None` if the whole class is unused. Classes that don't change are copied as they are.
+fn shrink<'a>(input: &'a Input, usage: &Usage) -> Result<Option<Cow<'a, [u8]>>> {
+	let Some((class, pool)) = &input.class else {
+		return Ok(Some(Cow::Borrowed(&input.bytes)));
+	};
+	let Some(shrunk) = usage.shrink(class) else {
+		return Ok(None);
+	};
+	if shrunk == *class {
+		return Ok(Some(Cow::Borrowed(&input.bytes)));
+	}
+	Ok(Some(Cow::Owned(shrunk.to_bytes(Some(pool))?)))
+}
+
+fn run(options: &Options) -> Result<()> {
+	let inputs = read_inputs(options)?;
+	let classes: Vec<&ClassFile> = inputs.iter().flatten().filter_map(|input| input.class.as_ref().map(|(class, _)| class)).collect();
+
+	let mut hierarchy = ClassHierarchy::new();
+	for class in &classes {
+		hierarchy.add_class_file(class);
+	}
+	cli::add_class_path(&mut hierarchy, &options.class_path)?;
+
+	let analysis = if options.cha { Analysis::ClassHierarchy } else { Analysis::RapidType };
+	let mut shrinker = Shrinker::new(&hierarchy, analysis);
+	for class in classes {
+		shrinker.add_class(class);
+	}
+	if !options.no_main {
+		shrinker.main_methods();
+	}
+	for annotation in &options.annotations {
+		shrinker.annotation(annotation.as_bytes());
+	}
+	for rule in &options.rules {
+		shrinker.keep(rule.clone());
+	}
+	let usage = shrinker.analyze();
+
+	for class in &usage.unused_classes {
+		println!("unused class {}", String::from_utf8_lossy(class.as_bytes()));
+	}
+	for method in &usage.unused_methods {
+		println!("unused method {method}");
+	}
+	for field in &usage.unused_fields {
+		println!("unused field {field}");
+	}
+	eprintln!("{} of {} classes, {} methods and {} fields unused",
+		usage.unused_classes.len(), usage.unused_classes.len() + usage.classes.len(), usage.unused_methods.len(), usage.unused_fields.len());
+
+	if let Some(output) = &options.output {
+		// whatever only the classes that couldn't be parsed use would be removed
+		let unparsed = inputs.iter().flatten().filter(|input| input.class.is_none()).count();
+		if unparsed > 0 {
+			bail!("{unparsed} classes couldn't be parsed, not writing {output} as the code only they use would be removed");
+		}
+		let mut classes: Vec<Classes> = Vec::with_capacity(inputs.len());
+		for input in &inputs {
+			classes.push(input.iter()
+				.map(|input| Ok((input.file_name.as_str(), shrink(input, &usage).with_context(|| format!("while shrinking {}", input.file_name))?)))
+				.collect::<Result<_>>()?);
+		}
+		write_jar(output, &options.inputs, &classes)?;
+	}
+	Ok(())
+}
+
+/// Exits with 2 on errors.
+fn main() -> ExitCode {
+	cli::main(USAGE, Options::parse, run)
+}
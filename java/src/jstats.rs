@@ -0,0 +1,77 @@
+use std::process::ExitCode;
+use anyhow::{bail, Result};
+use class_file::classpath::ClassPathEntry;
+use class_file::lazy::ClassFileRef;
+use class_file::stats::Statistics;
+use java::cli::{self, Args};
+
+const USAGE: &str = "\
+Usage: jstats [options] <jar|directory|class>...
+
+Reports statistics over the given classes: instructions by opcode, the distribution of method sizes, constant pool entries by kind, the most
+complex methods by cyclomatic complexity, and the use of invokedynamic, synchronized and native methods. Methods and classes close to the limits
+of the class file format, 65535 bytes of code per method and 65535 constant pool entries per class, are listed separately.
+
+Options:
+  -n, --top <count>         Number of most complex methods to list, 10 by default
+  -t, --threshold <percent> How close to a limit a method or class is listed, 90 by default
+  --json                    Write the statistics as JSON, with the size and complexity of every method
+  -h, --help                Show this help";
+
+#[derive(Debug)]
+struct Options {
+	inputs: Vec<ClassPathEntry>,
+	top: usize,
+	threshold: usize,
+	json: bool,
+}
+
+impl Options {
+	fn parse(mut args: Args<impl Iterator<Item=String>>) -> Result<Option<Options>> {
+		let mut options = Options { inputs: Vec::new(), top: 10, threshold: 90, json: false };
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"-n" | "--top" => options.top = args.parse(&arg, "count")?,
+				"-t" | "--threshold" => {
+					options.threshold = args.parse(&arg, "percentage")?;
+					if options.threshold > 100 {
+						bail!("the threshold must be at most 100%");
+					}
+				},
+				"--json" => options.json = true,
+				"-h" | "--help" => return Ok(None),
+				path => options.inputs.push(ClassPathEntry::new(cli::path(path)?)),
+			}
+		}
+		if options.inputs.is_empty() {
+			bail!("no classes given");
+		}
+		Ok(Some(options))
+	}
+}
+
+fn run(options: &Options) -> Result<bool> {
+	let mut statistics = Statistics::new();
+	for entry in &options.inputs {
+		for (file_name, bytes) in entry.class_files()? {
+			let added = ClassFileRef::parse(&bytes).and_then(|class| statistics.add_class(&class));
+			if let Err(error) = added {
+				cli::skipping(entry, &file_name, &error);
+			}
+		}
+	}
+	if statistics.classes == 0 {
+		bail!("no classes to report on");
+	}
+	if options.json {
+		println!("{}", statistics.to_json(options.threshold));
+	} else {
+		print!("{}", statistics.to_text(options.top, options.threshold));
+	}
+	Ok(statistics.near_code_limit(options.threshold).is_empty() && statistics.near_pool_limit(options.threshold).is_empty())
+}
+
+/// Exits with 1 if a method or a class is close to a limit, and with 2 on errors.
+fn main() -> ExitCode {
+	cli::main(USAGE, Options::parse, run)
+}
@@ -0,0 +1,4 @@
+//! What the command line tools of this crate share.
+
+pub mod cli;
+pub mod jar;
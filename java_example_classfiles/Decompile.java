@@ -0,0 +1,59 @@
+import java.util.ArrayList;
+import java.util.List;
+
+public class Decompile<T extends Comparable<T>> {
+	private final List<T> items = new ArrayList<>();
+
+	public T max() {
+		T best = null;
+		for (T item : items) {
+			if (best == null || item.compareTo(best) > 0) {
+				best = item;
+			}
+		}
+		return best;
+	}
+
+	public static int parse(String text) {
+		try {
+			return Integer.parseInt(text);
+		} catch (NumberFormatException e) {
+			return -1;
+		} finally {
+			System.out.println(text);
+		}
+	}
+
+	public static String name(int day) {
+		String name;
+		switch (day) {
+			case 0:
+				name = "sunday";
+				break;
+			case 6:
+				name = "saturday";
+				break;
+			default:
+				name = "weekday";
+		}
+		return name;
+	}
+
+	public static int count(int[][] rows, int wanted) {
+		int count = 0;
+		outer:
+		for (int[] row : rows) {
+			for (int cell : row) {
+				if (cell < 0) {
+					continue outer;
+				}
+				if (cell == wanted) {
+					break outer;
+				}
+				count++;
+			}
+			count *= 2;
+		}
+		return count;
+	}
+}
@@ -1,11 +1,13 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use std::collections::HashMap;
 use std::fs::File;
 use std::path::Path;
 use std::rc::Rc;
 use crate::class_instance::{Class, Field};
 use class_file::{ClassFile, FieldInfo};
+use class_file::classpath::{ClassPathEntry, OpenJar};
 use class_file::descriptor::BaseOrObjectType;
+use class_file::hierarchy::ClassHierarchy;
 use class_file::name::ClassName;
 use crate::errors::ClassLoadError;
 
@@ -18,8 +20,8 @@ use crate::errors::ClassLoadError;
 #[derive(Debug)]
 pub enum ClassesSource {
 	/// A `.zip` or `.jar` file containing `.class` files, given by a name like `/foo/bar/Baz.class` for the class `foo/bar/Baz`. Will be searched for a class
-	/// matching in file name, then the name in the class file is checked.
-	Jar(Box<Path>),
+	/// matching in file name, then the name in the class file is checked. The jar is opened on the first search and then kept open.
+	Jar(Box<Path>, OpenJar),
 	/// A directory containing (possibly multiple) subdirectories containing `.class` files, given by a name like `foo/bar/Baz.class` for the class
 	/// `foo/bar/Baz`. Will be searched for a class matching the file name and package, then the name in the class file is checked.
 	Directory(Box<Path>),
@@ -39,26 +41,39 @@ pub enum ClassesSource {
 impl ClassesSource {
 	/// Attempts to locate and load a class. Returns `Ok(None)` if no class with the name can be found.
 	fn load(&self, name: &ClassName) -> Result<Option<ClassFile>> {
-		if name == match self {
-			ClassesSource::Class { name, .. } => name,
-			ClassesSource::Bytes { name, .. } => name,
-			_ => todo!(),
-		} {
-			let class_file = match self {
-				ClassesSource::Class { path, .. } => {
-					let mut file = File::open(path)?;
-					ClassFile::parse(&mut file)?
-				}
-				ClassesSource::Bytes { bytes, .. } => {
-					ClassFile::parse(&mut &bytes[..])?
-				}
-				_ => todo!(),
-			};
+		let bytes = match self {
+			ClassesSource::Jar(path, jar) => ClassPathEntry::Jar(path.to_path_buf(), jar.clone()).find(name)?,
+			ClassesSource::Directory(path) => ClassPathEntry::Directory(path.to_path_buf()).find(name)?,
+			ClassesSource::Class { name: class_name, path } if class_name == name => {
+				let mut file = File::open(path)?;
+				return Ok(Some(ClassFile::parse(&mut file)?));
+			},
+			ClassesSource::Bytes { name: class_name, bytes } if class_name == name => {
+				return Ok(Some(ClassFile::parse(&mut &bytes[..])?));
+			},
+			_ => None,
+		};
+		let Some(bytes) = bytes else {
+			return Ok(None);
+		};
 
-			Ok(Some(class_file))
-		} else {
-			Ok(None)
+		let class_file = ClassFile::parse(&mut &bytes[..])?;
+		if class_file.this_class != *name {
+			bail!("class file for {name:?} in {self:?} contains the class {:?}", class_file.this_class);
 		}
+		Ok(Some(class_file))
+	}
+
+	/// Reads all class files of the source, with their name: the name in the jar or directory, the path of a single class file, or the name of the
+	/// class with `.class` appended for embedded bytes.
+	fn class_files(&self) -> Result<Vec<(String, Vec<u8>)>> {
+		let entry = match self {
+			ClassesSource::Jar(path, jar) => ClassPathEntry::Jar(path.to_path_buf(), jar.clone()),
+			ClassesSource::Directory(path) => ClassPathEntry::Directory(path.to_path_buf()),
+			ClassesSource::Class { path, .. } => return Ok(vec![(path.display().to_string(), std::fs::read(path)?)]),
+			ClassesSource::Bytes { name, bytes } => return Ok(vec![(format!("{}.class", String::from_utf8_lossy(name.as_bytes())), bytes.clone())]),
+		};
+		entry.class_files()
 	}
 }
 
@@ -76,6 +91,21 @@ impl ClassLoader {
 		}
 	}
 
+	/// Indexes the hierarchy of all classes the sources provide, loaded or not. Earlier sources shadow later ones, like when loading. Class files that
+	/// can't be read are skipped, and returned with their name and the reason, like [ClassHierarchy::add_class_path_entry] does.
+	pub fn class_hierarchy(&self) -> Result<(ClassHierarchy, Vec<(String, anyhow::Error)>)> {
+		let mut hierarchy = ClassHierarchy::new();
+		let mut skipped = Vec::new();
+		for source in &self.sources {
+			for (file_name, bytes) in source.class_files()? {
+				if let Err(error) = hierarchy.add_bytes(&bytes) {
+					skipped.push((file_name, error));
+				}
+			}
+		}
+		Ok((hierarchy, skipped))
+	}
+
 	// call only if you tried getting and didn't find any
 	fn load(&mut self, class_name: &ClassName, currently_loading: &mut Vec<ClassName>) -> Result<Class, ClassLoadError> {
 		let class_file = self.sources.iter()
@@ -89,7 +119,7 @@ impl ClassLoader {
 					},
 				}
 			})
-			.ok_or_else(|| ClassLoadError::NoClassDefFoundError(class_name.clone()))?;
+			.ok_or_else(|| ClassLoadError::NoClassDefFoundError(*class_name))?;
 
 		let super_class_size = if let Some(super_class) = &class_file.super_class {
 			let super_class = self.get_checked(super_class, currently_loading)?;
@@ -117,8 +147,8 @@ impl ClassLoader {
 				non_static_field_offset += size;
 				Ok::<_, ClassLoadError>((
 					(
-						field.name.clone(),
-						field.descriptor.clone()
+						field.name,
+						field.descriptor
 					),
 					f
 				))
@@ -138,8 +168,8 @@ impl ClassLoader {
 				static_field_offset += size;
 				(
 					(
-						field.name.clone(),
-						field.descriptor.clone()
+						field.name,
+						field.descriptor
 					),
 					f
 				)
@@ -180,10 +210,10 @@ impl ClassLoader {
 				return Err(ClassLoadError::ClassCircularityError());
 			}
 
-			currently_loading.push(class_name.clone());
+			currently_loading.push(*class_name);
 
 			let class = self.load(&class_name, currently_loading)?;
-			self.classes.insert(class_name.clone(), Rc::new(class));
+			self.classes.insert(*class_name, Rc::new(class));
 
 			// TODO: don't let this panic, throw some useful exception!
 			assert_eq!(&currently_loading.pop().unwrap(), class_name);